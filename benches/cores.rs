@@ -0,0 +1,81 @@
+// Runs SETs from as many tasks as there are cores at once, once against the backend they all
+// share and once in thread-per-core mode, each command sent to the thread owning its key.
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use simple_redis::{
+    cmd::{Command, CommandExecutor},
+    cores::Cores,
+    Backend, BulkString, RespArray, RespFrame,
+};
+use std::{sync::Arc, thread};
+use tokio::{runtime::Runtime, task::JoinSet};
+
+const COMMANDS_PER_TASK: usize = 1024;
+
+fn set(key: Bytes) -> RespFrame {
+    RespArray::new([
+        BulkString::from("set").into(),
+        BulkString::new(key).into(),
+        BulkString::from("value").into(),
+    ])
+    .into()
+}
+
+fn bench_cores(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let tasks = thread::available_parallelism().map_or(1, usize::from);
+    let frames: Arc<Vec<Vec<RespFrame>>> = Arc::new(
+        (0..tasks)
+            .map(|t| {
+                (0..COMMANDS_PER_TASK)
+                    .map(|i| set(Bytes::from(format!("key:{}:{}", t, i))))
+                    .collect()
+            })
+            .collect(),
+    );
+    let mut group = c.benchmark_group("cores");
+    group.throughput(Throughput::Elements((tasks * COMMANDS_PER_TASK) as u64));
+
+    let backend = Backend::new();
+    group.bench_function(BenchmarkId::new("set", "shared"), |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let mut set = JoinSet::new();
+                for t in 0..tasks {
+                    let (backend, frames) = (backend.clone(), frames.clone());
+                    set.spawn(async move {
+                        for frame in &frames[t] {
+                            Command::try_from(frame.clone()).unwrap().execute(&backend);
+                        }
+                    });
+                }
+                while set.join_next().await.is_some() {}
+            })
+        })
+    });
+
+    let cores = Cores::start(tasks, 1).unwrap();
+    group.bench_function(BenchmarkId::new("set", "thread-per-core"), |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let mut set = JoinSet::new();
+                for t in 0..tasks {
+                    let (cores, frames) = (cores.clone(), frames.clone());
+                    set.spawn(async move {
+                        for frame in &frames[t] {
+                            let cmd = Command::try_from(frame.clone()).unwrap();
+                            let core = cores.route(&cmd, &cmd.keys(frame)).unwrap().unwrap();
+                            cores.execute(core, 0, cmd).await;
+                        }
+                    });
+                }
+                while set.join_next().await.is_some() {}
+            })
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_cores);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+// Decodes a SET command carrying values of growing sizes, as the connection handler does with
+// each command read from a client, and batches of small pipelined commands read all at once.
+
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use simple_redis::{BulkString, RespArray, RespDecoder, RespEncoder, RespFrame};
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for size in [1024, 64 * 1024, 1024 * 1024, 16 * 1024 * 1024] {
+        let command = RespArray::new([
+            BulkString::from("set").into(),
+            BulkString::from("key").into(),
+            RespFrame::from(BulkString::new(vec![b'x'; size])),
+        ])
+        .encode();
+        group.throughput(Throughput::Bytes(command.len() as u64));
+        group.bench_with_input(BenchmarkId::new("set", size), &command, |b, command| {
+            b.iter_batched(
+                || BytesMut::from(&command[..]),
+                |mut buf| RespFrame::decode(&mut buf).unwrap(),
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_decode_pipeline(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_pipeline");
+    let command = RespArray::new([
+        BulkString::from("set").into(),
+        BulkString::from("key").into(),
+        BulkString::from("value").into(),
+    ])
+    .encode();
+    for commands in [16, 256, 4096] {
+        let batch = command.repeat(commands);
+        group.throughput(Throughput::Elements(commands as u64));
+        group.bench_with_input(BenchmarkId::new("set", commands), &batch, |b, batch| {
+            b.iter_batched(
+                || BytesMut::from(&batch[..]),
+                |mut buf| {
+                    while !buf.is_empty() {
+                        RespFrame::decode(&mut buf).unwrap();
+                    }
+                },
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode, bench_decode_pipeline);
+criterion_main!(benches);
@@ -0,0 +1,48 @@
+// Writes keys from as many threads as there are cores at once, into keyspaces striped over a few
+// shards, over as many as dashmap picks by default and over many more, to compare how much the
+// threads wait on the shard locks.
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use simple_redis::{Backend, BulkString};
+use std::thread;
+
+const KEYS_PER_THREAD: usize = 1024;
+
+fn bench_keyspace(c: &mut Criterion) {
+    let threads = thread::available_parallelism().map_or(1, usize::from);
+    let keys: Vec<Vec<Bytes>> = (0..threads)
+        .map(|t| {
+            (0..KEYS_PER_THREAD)
+                .map(|i| Bytes::from(format!("key:{}:{}", t, i)))
+                .collect()
+        })
+        .collect();
+    let mut group = c.benchmark_group("keyspace");
+    group.throughput(Throughput::Elements((threads * KEYS_PER_THREAD) as u64));
+    // zero stands for the default count
+    for shards in [2, 0, 1024] {
+        let backend = Backend::with_shards(1, shards);
+        let id = match shards {
+            0 => "default".to_string(),
+            shards => shards.to_string(),
+        };
+        group.bench_with_input(BenchmarkId::new("set", id), &backend, |b, backend| {
+            b.iter(|| {
+                thread::scope(|scope| {
+                    for keys in &keys {
+                        scope.spawn(move || {
+                            for key in keys {
+                                backend.set(key.clone(), BulkString::from("value").into());
+                            }
+                        });
+                    }
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_keyspace);
+criterion_main!(benches);
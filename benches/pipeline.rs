@@ -0,0 +1,68 @@
+// Runs a batch of commands against a server, once waiting for each reply before sending the next
+// command and once pipelining them, the replies then written out together.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use simple_redis::{network, Backend};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    runtime::Runtime,
+};
+
+const SET: &[u8] = b"*3\r\n$3\r\nset\r\n$3\r\nkey\r\n$5\r\nvalue\r\n";
+
+fn server(rt: &Runtime) -> TcpStream {
+    rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(network::stream_handler(stream, Backend::new()));
+            }
+        });
+        let client = TcpStream::connect(addr).await.unwrap();
+        client.set_nodelay(true).unwrap();
+        client
+    })
+}
+
+fn bench_pipeline(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut client = server(&rt);
+    let mut group = c.benchmark_group("set");
+    for commands in [1, 16, 256] {
+        group.throughput(Throughput::Elements(commands as u64));
+        let mut reply = vec![0; b"+OK\r\n".len() * commands];
+        group.bench_with_input(
+            BenchmarkId::new("sequential", commands),
+            &commands,
+            |b, &n| {
+                b.iter(|| {
+                    rt.block_on(async {
+                        for reply in reply.chunks_mut(b"+OK\r\n".len()).take(n) {
+                            client.write_all(SET).await.unwrap();
+                            client.read_exact(reply).await.unwrap();
+                        }
+                    })
+                })
+            },
+        );
+        let batch = SET.repeat(commands);
+        group.bench_with_input(
+            BenchmarkId::new("pipelined", commands),
+            &commands,
+            |b, _| {
+                b.iter(|| {
+                    rt.block_on(async {
+                        client.write_all(&batch).await.unwrap();
+                        client.read_exact(&mut reply).await.unwrap();
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pipeline);
+criterion_main!(benches);
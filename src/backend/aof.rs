@@ -0,0 +1,819 @@
+// Append-only file: write commands are logged as RESP arrays while they are executed and
+// replayed at startup. How much a crash may lose depends on the fsync policy. Rewrites compact
+// the file into the current dataset, written as a snapshot preamble or as commands, followed
+// by the commands logged while the rewrite ran.
+
+use super::{
+    clock::unix_millis,
+    db::{select_command, Value},
+    persistence::{corrupt, is_snapshot, next_frame, write_snapshot_to, Entry, PersistenceError},
+    Backend,
+};
+use crate::{BulkString, RespArray, RespEncoder, RespFrame};
+use bytes::BytesMut;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, RwLock,
+    },
+    thread,
+    time::Instant,
+};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AppendFsync {
+    /// fsync after every write command, before replying
+    Always,
+    /// fsync once per second from a background thread
+    #[default]
+    EverySec,
+    /// leave flushing to the operating system
+    No,
+}
+
+impl AppendFsync {
+    pub fn parse(policy: &str) -> Option<Self> {
+        match policy.to_ascii_lowercase().as_str() {
+            "always" => Some(AppendFsync::Always),
+            "everysec" => Some(AppendFsync::EverySec),
+            "no" => Some(AppendFsync::No),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AppendFsync::Always => "always",
+            AppendFsync::EverySec => "everysec",
+            AppendFsync::No => "no",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct Aof {
+    filename: RwLock<String>,
+    fsync: RwLock<AppendFsync>,
+    use_rdb_preamble: AtomicBool,
+    load_truncated: AtomicBool,
+    // open while the AOF is enabled
+    file: Mutex<Option<AofFile>>,
+    rewrite_in_progress: AtomicBool,
+    last_rewrite_failed: AtomicBool,
+    // unix time in milliseconds the current or last rewrite started at
+    rewrite_started: AtomicU64,
+    // seconds the last rewrite took, -1 before the first one
+    last_rewrite_secs: AtomicI64,
+    // shared with the thread running the once-per-second fsync
+    fsync_in_progress: Arc<AtomicBool>,
+    // fsyncs postponed because the previous one had not completed yet
+    delayed_fsync: AtomicU64,
+    last_write_failed: AtomicBool,
+}
+
+impl Default for Aof {
+    fn default() -> Self {
+        Self {
+            filename: RwLock::new("appendonly.aof".to_string()),
+            fsync: RwLock::default(),
+            use_rdb_preamble: AtomicBool::new(true),
+            load_truncated: AtomicBool::new(true),
+            file: Mutex::default(),
+            rewrite_in_progress: AtomicBool::default(),
+            last_rewrite_failed: AtomicBool::default(),
+            rewrite_started: AtomicU64::default(),
+            last_rewrite_secs: AtomicI64::new(-1),
+            fsync_in_progress: Arc::default(),
+            delayed_fsync: AtomicU64::default(),
+            last_write_failed: AtomicBool::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct AofFile {
+    file: File,
+    // commands not written yet, because writing them failed; retried before anything else
+    buf: Vec<u8>,
+    // commands logged since a background rewrite copied the dataset
+    rewrite_buf: Option<Vec<u8>>,
+    // the database the logged commands run against, unknown until the first SELECT
+    selected: Option<usize>,
+    size: u64,
+    // whether anything was written since the last fsync
+    unsynced: bool,
+}
+
+impl AofFile {
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            file,
+            buf: vec![],
+            rewrite_buf: None,
+            selected: None,
+            size,
+            unsynced: false,
+        })
+    }
+
+    fn write_buf(&mut self) -> io::Result<()> {
+        while !self.buf.is_empty() {
+            let written = self.file.write(&self.buf)?;
+            if written == 0 {
+                return Err(ErrorKind::WriteZero.into());
+            }
+            self.buf.drain(..written);
+            self.size += written as u64;
+            self.unsynced = true;
+        }
+        Ok(())
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_data()?;
+        self.unsynced = false;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AofContents {
+    /// keys loaded from the snapshot the file starts with, if it does
+    pub preamble_keys: Option<usize>,
+    pub commands: Vec<RespFrame>,
+}
+
+/// The outcome of checking an AOF with [`Backend::check_aof`].
+#[derive(Debug)]
+pub struct AofCheck {
+    pub size: usize,
+    /// whether the file starts with a snapshot
+    pub preamble: bool,
+    pub commands: usize,
+    /// bytes up to the end of the last valid command
+    pub valid_len: usize,
+    /// what stopped the check before the end of the file
+    pub error: Option<PersistenceError>,
+    /// whether nothing but the end of the file is damaged, so truncating it loses no command
+    pub tail_only: bool,
+}
+
+// The commands of an AOF, up to the first damaged one.
+struct AofScan {
+    preamble: Option<Vec<Entry>>,
+    commands: Vec<RespFrame>,
+    valid_len: usize,
+    error: Option<PersistenceError>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AofStats {
+    pub enabled: bool,
+    pub rewrite_in_progress: bool,
+    pub last_rewrite_ok: bool,
+    pub last_rewrite_time_sec: Option<u64>,
+    pub current_rewrite_time_sec: Option<u64>,
+    pub last_write_ok: bool,
+    pub current_size: u64,
+    pub buffer_length: usize,
+    pub fsync_in_progress: bool,
+    pub delayed_fsync: u64,
+}
+
+impl Backend {
+    pub fn appendonly(&self) -> bool {
+        self.aof_file().is_some()
+    }
+
+    /// Turns the AOF on or off at runtime. Turning it on rewrites the file from the current
+    /// dataset, as the existing file may be stale.
+    pub fn set_appendonly(&self, enabled: bool) -> Result<(), PersistenceError> {
+        match (enabled, self.appendonly()) {
+            (true, false) => self.enable_aof(),
+            (false, true) => {
+                if let Some(mut aof) = self.aof_file().take() {
+                    aof.write_buf()?;
+                    aof.sync()?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn appendfilename(&self) -> String {
+        self.aof
+            .filename
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn set_appendfilename(&self, name: String) {
+        *self.aof.filename.write().unwrap_or_else(|e| e.into_inner()) = name;
+    }
+
+    pub fn appendfsync(&self) -> AppendFsync {
+        *self.aof.fsync.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_appendfsync(&self, policy: AppendFsync) {
+        *self.aof.fsync.write().unwrap_or_else(|e| e.into_inner()) = policy;
+    }
+
+    /// Whether rewrites start the AOF with a snapshot of the dataset rather than commands.
+    pub fn aof_use_rdb_preamble(&self) -> bool {
+        self.aof.use_rdb_preamble.load(Ordering::Relaxed)
+    }
+
+    pub fn set_aof_use_rdb_preamble(&self, enabled: bool) {
+        self.aof.use_rdb_preamble.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether an AOF whose last command is truncated or corrupt is loaded without it, rather
+    /// than refused.
+    pub fn aof_load_truncated(&self) -> bool {
+        self.aof.load_truncated.load(Ordering::Relaxed)
+    }
+
+    pub fn set_aof_load_truncated(&self, enabled: bool) {
+        self.aof.load_truncated.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn aof_rewrite_in_progress(&self) -> bool {
+        self.aof.rewrite_in_progress.load(Ordering::Acquire)
+    }
+
+    pub fn aof_path(&self) -> PathBuf {
+        self.dir().join(self.appendfilename())
+    }
+
+    /// Opens the AOF for appending, creating it if needed, without rewriting it. Used at
+    /// startup once the file has been replayed.
+    pub fn open_aof(&self) -> Result<(), PersistenceError> {
+        *self.aof_file() = Some(AofFile::open(&self.aof_path())?);
+        Ok(())
+    }
+
+    /// Reads the AOF, loading its snapshot preamble into the keyspace if it starts with one,
+    /// and returns the commands to replay. Returns `None` when there is no such file.
+    pub fn read_aof(&self) -> Result<Option<AofContents>, PersistenceError> {
+        let data = match fs::read(self.aof_path()) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let scan = self.scan_aof(&data)?;
+        if let Some(error) = scan.error {
+            let offset = scan.valid_len;
+            let tail_only = !command_follows(&data, offset);
+            if !self.aof_load_truncated() || !tail_only {
+                if tail_only {
+                    warn!(
+                        "Only the end of the append only file is damaged: set \
+                         aof-load-truncated to yes, or run with --check-aof --fix {}",
+                        self.aof_path().display()
+                    );
+                }
+                return Err(PersistenceError::BadAof {
+                    offset,
+                    source: Box::new(error),
+                });
+            }
+            warn!(
+                "{}: discarding the last {} bytes of the append only file, from offset {}: \"{}\"",
+                error,
+                data.len() - offset,
+                offset,
+                data[offset..].escape_ascii()
+            );
+            truncate_file(&self.aof_path(), offset)?;
+        }
+        Ok(Some(AofContents {
+            preamble_keys: scan.preamble.map(|entries| self.restore_entries(entries)),
+            commands: scan.commands,
+        }))
+    }
+
+    /// Checks the AOF at `path` without loading it, and with `fix` truncates it to its last
+    /// valid command when only its end is damaged.
+    pub fn check_aof(&self, path: &Path, fix: bool) -> Result<AofCheck, PersistenceError> {
+        let data = fs::read(path)?;
+        let scan = self.scan_aof(&data)?;
+        let tail_only = scan.error.is_some() && !command_follows(&data, scan.valid_len);
+        if fix && tail_only {
+            truncate_file(path, scan.valid_len)?;
+        }
+        Ok(AofCheck {
+            size: data.len(),
+            preamble: scan.preamble.is_some(),
+            commands: scan.commands.len(),
+            valid_len: scan.valid_len,
+            error: scan.error,
+            tail_only,
+        })
+    }
+
+    // Parses the AOF up to its first damaged command. A damaged preamble is an error, as its
+    // checksum makes it all or nothing.
+    fn scan_aof(&self, data: &[u8]) -> Result<AofScan, PersistenceError> {
+        let (preamble, offset) = if is_snapshot(data) {
+            let (entries, len) = self.read_snapshot(data)?;
+            (Some(entries), len)
+        } else {
+            (None, 0)
+        };
+        let mut buf = BytesMut::from(&data[offset..]);
+        let mut scan = AofScan {
+            preamble,
+            commands: vec![],
+            valid_len: offset,
+            error: None,
+        };
+        while !buf.is_empty() {
+            match next_frame(&mut buf) {
+                Ok(frame @ RespFrame::Array(_)) => {
+                    scan.commands.push(frame);
+                    scan.valid_len = data.len() - buf.len();
+                }
+                Ok(_) => {
+                    scan.error = Some(corrupt("expected a command"));
+                    break;
+                }
+                Err(e) => {
+                    scan.error = Some(e);
+                    break;
+                }
+            }
+        }
+        Ok(scan)
+    }
+
+    /// Logs a write command that was just executed against the database of this handle. With
+    /// the `always` policy it is on disk once this returns.
+    pub fn feed_aof(&self, command: &RespFrame) {
+        let mut file = self.aof_file();
+        let Some(aof) = file.as_mut() else {
+            return;
+        };
+        let mut encoded = BytesMut::new();
+        if aof.selected != Some(self.db_index()) {
+            aof.selected = Some(self.db_index());
+            select_command(self.db_index()).encode_to(&mut encoded);
+        }
+        command.encode_to(&mut encoded);
+        if let Some(rewrite_buf) = aof.rewrite_buf.as_mut() {
+            rewrite_buf.extend_from_slice(&encoded);
+        }
+        aof.buf.extend_from_slice(&encoded);
+        let mut result = aof.write_buf();
+        if result.is_ok() && self.appendfsync() == AppendFsync::Always {
+            let started = Instant::now();
+            result = aof.sync();
+            self.record_latency("aof-fsync-always", started.elapsed());
+        }
+        self.aof_written(result);
+    }
+
+    /// Writes out what is left in the buffer and fsyncs the AOF, whatever the policy, for the
+    /// server to exit with every command on disk.
+    pub fn sync_aof(&self) -> Result<(), PersistenceError> {
+        if let Some(aof) = self.aof_file().as_mut() {
+            aof.write_buf()?;
+            aof.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Retries failed writes and, with the `everysec` policy, fsyncs the AOF off the calling
+    /// thread. Meant to be called once per second.
+    pub fn flush_aof(&self) {
+        let mut file = self.aof_file();
+        let Some(aof) = file.as_mut() else {
+            return;
+        };
+        if !aof.buf.is_empty() {
+            let result = aof.write_buf();
+            self.aof_written(result);
+        }
+        if self.appendfsync() != AppendFsync::EverySec || !aof.unsynced {
+            return;
+        }
+        let in_progress = self.aof.fsync_in_progress.clone();
+        if in_progress.swap(true, Ordering::AcqRel) {
+            self.aof.delayed_fsync.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let backend = self.clone();
+        let synced = aof.file.try_clone().and_then(|file| {
+            thread::Builder::new()
+                .name("aof-fsync".to_string())
+                .spawn(move || {
+                    let started = Instant::now();
+                    if let Err(e) = file.sync_data() {
+                        warn!("Can't fsync the append only file: {}", e);
+                    }
+                    backend.record_latency("aof-background-fsync", started.elapsed());
+                    in_progress.store(false, Ordering::Release);
+                })
+        });
+        match synced {
+            Ok(_) => aof.unsynced = false,
+            Err(e) => {
+                warn!("Can't start the append only file fsync: {}", e);
+                self.aof.fsync_in_progress.store(false, Ordering::Release);
+            }
+        }
+    }
+
+    pub fn aof_stats(&self) -> AofStats {
+        let file = self.aof_file();
+        let in_progress = self.aof_rewrite_in_progress();
+        let started = self.aof.rewrite_started.load(Ordering::Relaxed);
+        AofStats {
+            enabled: file.is_some(),
+            rewrite_in_progress: in_progress,
+            last_rewrite_ok: !self.aof.last_rewrite_failed.load(Ordering::Relaxed),
+            last_rewrite_time_sec: self
+                .aof
+                .last_rewrite_secs
+                .load(Ordering::Relaxed)
+                .try_into()
+                .ok(),
+            current_rewrite_time_sec: in_progress
+                .then(|| unix_millis().saturating_sub(started) / 1000),
+            last_write_ok: !self.aof.last_write_failed.load(Ordering::Relaxed),
+            current_size: file.as_ref().map(|aof| aof.size).unwrap_or(0),
+            buffer_length: file.as_ref().map(|aof| aof.buf.len()).unwrap_or(0),
+            fsync_in_progress: self.aof.fsync_in_progress.load(Ordering::Acquire),
+            delayed_fsync: self.aof.delayed_fsync.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Rewrites the AOF from a copy of the dataset on a background thread. Commands logged
+    /// meanwhile are appended to the new file before it replaces the old one.
+    pub fn bgrewriteaof(&self) -> Result<(), PersistenceError> {
+        self.start_rewrite()?;
+        let entries = {
+            let mut file = self.aof_file();
+            if let Some(aof) = file.as_mut() {
+                aof.rewrite_buf = Some(vec![]);
+                // the rewritten file may end in any database
+                aof.selected = None;
+            }
+            self.fork_capture()
+        };
+        let backend = self.clone();
+        let spawned = thread::Builder::new()
+            .name("aof-rewrite".to_string())
+            .spawn(move || {
+                let result = backend.write_aof_base(entries).and_then(|file| {
+                    let mut aof = backend.aof_file();
+                    let reopen = aof.is_some();
+                    backend.install_aof(&mut aof, file, reopen)
+                });
+                match &result {
+                    Ok(()) => info!("Background AOF rewrite finished successfully"),
+                    Err(e) => warn!("Background AOF rewrite failed: {}", e),
+                }
+                backend.finish_rewrite(&mut backend.aof_file(), &result);
+            });
+        if let Err(e) = spawned {
+            self.finish_rewrite(&mut self.aof_file(), &Err(io::Error::other(e.to_string())));
+            return Err(PersistenceError::Io(e));
+        }
+        Ok(())
+    }
+
+    // Writes the dataset to a new file which then replaces the AOF. Writes wait for the
+    // rewrite, so none is lost between the copy of the dataset and the switch.
+    fn enable_aof(&self) -> Result<(), PersistenceError> {
+        self.start_rewrite()?;
+        let mut aof = self.aof_file();
+        let result = self
+            .write_aof_base(self.capture())
+            .and_then(|file| self.install_aof(&mut aof, file, true));
+        self.finish_rewrite(&mut aof, &result);
+        Ok(result?)
+    }
+
+    fn start_rewrite(&self) -> Result<(), PersistenceError> {
+        if self.aof.rewrite_in_progress.swap(true, Ordering::AcqRel) {
+            return Err(PersistenceError::RewriteInProgress);
+        }
+        self.aof
+            .rewrite_started
+            .store(unix_millis(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn finish_rewrite(&self, aof: &mut Option<AofFile>, result: &io::Result<()>) {
+        if let Some(aof) = aof.as_mut() {
+            aof.rewrite_buf = None;
+        }
+        if result.is_err() {
+            let _ = fs::remove_file(self.aof_temp_path());
+        }
+        self.aof
+            .last_rewrite_failed
+            .store(result.is_err(), Ordering::Relaxed);
+        let started = self.aof.rewrite_started.load(Ordering::Relaxed);
+        let secs = unix_millis().saturating_sub(started) / 1000;
+        self.aof
+            .last_rewrite_secs
+            .store(secs as i64, Ordering::Relaxed);
+        self.aof.rewrite_in_progress.store(false, Ordering::Release);
+    }
+
+    // Writes the dataset to the temporary file of the rewrite, as a snapshot or as commands.
+    fn write_aof_base(&self, entries: Vec<Entry>) -> io::Result<File> {
+        let mut writer = BufWriter::new(File::create(self.aof_temp_path())?);
+        if self.aof_use_rdb_preamble() {
+            writer = write_snapshot_to(writer, entries, self.dump_format())?;
+        } else {
+            let mut db = 0;
+            for entry in entries {
+                if entry.db != db {
+                    db = entry.db;
+                    writer.write_all(&select_command(db).encode())?;
+                }
+                for command in entry.into_commands() {
+                    writer.write_all(&command.encode())?;
+                }
+            }
+        }
+        writer.into_inner().map_err(|e| e.into_error())
+    }
+
+    // Appends the commands logged during the rewrite to its file, then swaps it in for the
+    // AOF. Holding the lock on the AOF keeps new commands out until then.
+    fn install_aof(
+        &self,
+        aof: &mut Option<AofFile>,
+        mut file: File,
+        reopen: bool,
+    ) -> io::Result<()> {
+        if let Some(rewrite_buf) = aof.as_mut().and_then(|aof| aof.rewrite_buf.take()) {
+            file.write_all(&rewrite_buf)?;
+        }
+        file.sync_all()?;
+        let path = self.aof_path();
+        fs::rename(self.aof_temp_path(), &path)?;
+        if reopen {
+            *aof = Some(AofFile::open(&path)?);
+        }
+        Ok(())
+    }
+
+    fn aof_temp_path(&self) -> PathBuf {
+        self.aof_path()
+            .with_file_name(format!("temp-rewriteaof-{}.aof", process::id()))
+    }
+
+    fn aof_written(&self, result: io::Result<()>) {
+        if let Err(e) = &result {
+            warn!("Error writing to the append only file: {}", e);
+        }
+        self.aof
+            .last_write_failed
+            .store(result.is_err(), Ordering::Relaxed);
+    }
+
+    fn aof_file(&self) -> MutexGuard<'_, Option<AofFile>> {
+        self.aof.file.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Whether a complete command starts after the damaged one at `offset`, which means the
+// damage is not confined to the end of the file.
+fn command_follows(data: &[u8], offset: usize) -> bool {
+    (offset + 1..data.len().saturating_sub(2)).any(|start| {
+        if &data[start..start + 3] != b"\r\n*" {
+            return false;
+        }
+        let mut buf = BytesMut::from(&data[start + 2..]);
+        matches!(next_frame(&mut buf), Ok(RespFrame::Array(_)))
+    })
+}
+
+fn truncate_file(path: &Path, len: usize) -> io::Result<()> {
+    let file = OpenOptions::new().write(true).open(path)?;
+    file.set_len(len as u64)?;
+    file.sync_all()
+}
+
+impl Entry {
+    // Commands recreating the key when replayed.
+    fn into_commands(self) -> Vec<RespFrame> {
+        let key = BulkString::from(self.key.clone());
+        let command = |name: &'static str, args: Vec<RespFrame>| -> RespFrame {
+            let mut frames = vec![BulkString::from(name).into(), key.clone().into()];
+            frames.extend(args);
+            RespArray::new(frames).into()
+        };
+        let mut commands = match self.value {
+            Value::String(value) => vec![command("SET", vec![value.to_frame()])],
+            Value::Hash(hash) => {
+                let args = hash
+                    .iter()
+                    .flat_map(|(field, value)| {
+                        [
+                            BulkString::from(field.clone()).into(),
+                            BulkString::new(value.clone()).into(),
+                        ]
+                    })
+                    .collect();
+                vec![command("HSET", args)]
+            }
+            Value::Set(set) => {
+                let args = set.iter().map(|m| BulkString::new(m).into()).collect();
+                vec![command("SADD", args)]
+            }
+            Value::List(list) => {
+                let args = list
+                    .iter()
+                    .map(|v| BulkString::new(v.clone()).into())
+                    .collect();
+                vec![command("RPUSH", args)]
+            }
+            Value::ZSet(zset) => {
+                let args = zset
+                    .range_by_rank(0, -1, false)
+                    .into_iter()
+                    .flat_map(|(member, score)| {
+                        [
+                            BulkString::from(score.to_string()).into(),
+                            BulkString::from(member).into(),
+                        ]
+                    })
+                    .collect();
+                vec![command("ZADD", args)]
+            }
+            Value::Stream(stream) => stream
+                .iter()
+                .map(|(id, fields)| {
+                    let mut args = vec![BulkString::from(id.to_string()).into()];
+                    for (field, value) in fields {
+                        args.push(BulkString::from(field.clone()).into());
+                        args.push(BulkString::new(value.clone()).into());
+                    }
+                    command("XADD", args)
+                })
+                .collect(),
+        };
+        if let Some(at) = self.expire_at {
+            let at = BulkString::from(at.to_string()).into();
+            commands.push(command("PEXPIREAT", vec![at]));
+        }
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::ListDirection;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_load_damaged_aof() {
+        let backend = Backend::new();
+        let dir = std::env::temp_dir().join(format!("simple-redis-aof-tail-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        backend.set_dir(dir.clone());
+        let valid = b"*3\r\n$3\r\nSET\r\n$1\r\na\r\n$1\r\n1\r\n*2\r\n$3\r\nDEL\r\n$1\r\nb\r\n";
+        let truncated = [&valid[..], b"*3\r\n$3\r\nSET\r\n$1"].concat();
+
+        fs::write(backend.aof_path(), &truncated).unwrap();
+        let check = backend.check_aof(&backend.aof_path(), false).unwrap();
+        assert_eq!((check.commands, check.valid_len), (2, valid.len()));
+        assert!(matches!(check.error, Some(PersistenceError::Truncated)));
+        assert!(check.tail_only);
+
+        backend.set_aof_load_truncated(false);
+        assert!(matches!(
+            backend.read_aof(),
+            Err(PersistenceError::BadAof { offset, .. }) if offset == valid.len()
+        ));
+        backend.set_aof_load_truncated(true);
+        assert_eq!(backend.read_aof().unwrap().unwrap().commands.len(), 2);
+        assert_eq!(fs::read(backend.aof_path()).unwrap(), valid);
+
+        // a command following the damage can't be dropped
+        let damaged = [&valid[..], b"+OK\r\n", &valid[..]].concat();
+        fs::write(backend.aof_path(), &damaged).unwrap();
+        assert!(backend.read_aof().is_err());
+        let check = backend.check_aof(&backend.aof_path(), true).unwrap();
+        assert!(check.error.is_some() && !check.tail_only);
+        assert_eq!(fs::read(backend.aof_path()).unwrap(), damaged);
+
+        fs::write(backend.aof_path(), &truncated).unwrap();
+        backend.check_aof(&backend.aof_path(), true).unwrap();
+        let check = backend.check_aof(&backend.aof_path(), false).unwrap();
+        assert!(check.error.is_none());
+        assert_eq!(check.size, valid.len());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_aof_rewrite_and_feed() {
+        let backend = Backend::new();
+        let dir = std::env::temp_dir().join(format!("simple-redis-aof-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        backend.set_dir(dir.clone());
+        backend.set_aof_use_rdb_preamble(false);
+        assert_eq!(backend.read_aof().unwrap(), None);
+
+        backend.set("key".into(), BulkString::from("value").into());
+        backend.expire(b"key", 10_000);
+        backend
+            .push(
+                "list".into(),
+                vec![BulkString::from("a").into()],
+                ListDirection::Right,
+            )
+            .unwrap();
+        backend.set_appendonly(true).unwrap();
+        let contents = backend.read_aof().unwrap().unwrap();
+        assert_eq!(contents.preamble_keys, None);
+        assert_eq!(contents.commands.len(), 3);
+        assert!(backend.aof_stats().enabled);
+
+        let command: RespFrame = RespArray::new([
+            BulkString::from("DEL").into(),
+            BulkString::from("key").into(),
+        ])
+        .into();
+        backend.set_appendfsync(AppendFsync::Always);
+        backend.feed_aof(&command);
+        let stats = backend.aof_stats();
+        assert_eq!(
+            stats.current_size,
+            fs::metadata(backend.aof_path()).unwrap().len()
+        );
+        assert_eq!(stats.buffer_length, 0);
+        assert!(stats.last_write_ok);
+        let contents = backend.read_aof().unwrap().unwrap();
+        // the first command logged is preceded by the database it runs against
+        assert_eq!(contents.commands.len(), 5);
+        assert_eq!(contents.commands[3], select_command(0));
+        assert_eq!(contents.commands.last(), Some(&command));
+
+        backend.set_appendfsync(AppendFsync::EverySec);
+        backend.feed_aof(&command);
+        backend.flush_aof();
+        backend.set_appendonly(false).unwrap();
+        backend.feed_aof(&command);
+        assert_eq!(backend.read_aof().unwrap().unwrap().commands.len(), 6);
+        assert!(!backend.aof_stats().enabled);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_bgrewriteaof_with_preamble() {
+        let backend = Backend::new();
+        let dir = std::env::temp_dir().join(format!("simple-redis-aof-preamble-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        backend.set_dir(dir.clone());
+        backend.set("key".into(), BulkString::from("value").into());
+        backend.set_appendonly(true).unwrap();
+        assert!(is_snapshot(&fs::read(backend.aof_path()).unwrap()));
+
+        let set = |key: &'static str| -> RespFrame {
+            backend.set(key.into(), BulkString::from("value").into());
+            let command = RespArray::new([
+                BulkString::from("SET").into(),
+                BulkString::from(key).into(),
+                BulkString::from("value").into(),
+            ]);
+            backend.feed_aof(&command.clone().into());
+            command.into()
+        };
+        set("before");
+        backend.bgrewriteaof().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while backend.aof_rewrite_in_progress() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(backend.aof_stats().last_rewrite_ok);
+        let after = set("after");
+
+        let loaded = Backend::new();
+        loaded.set_dir(dir.clone());
+        let contents = loaded.read_aof().unwrap().unwrap();
+        assert_eq!(contents.preamble_keys, Some(2));
+        assert_eq!(contents.commands, vec![select_command(0), after]);
+        assert_eq!(
+            loaded.get(b"before").unwrap(),
+            Some(BulkString::from("value").into())
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
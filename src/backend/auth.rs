@@ -0,0 +1,101 @@
+// Password authentication. With requirepass set, a connection runs no command until it gives
+// the password with AUTH or HELLO. The default user is the only one, as in Redis without ACLs.
+
+use super::Backend;
+use sha1_smol::Sha1;
+use std::sync::RwLock;
+
+#[derive(Debug, Default)]
+pub(super) struct Auth {
+    requirepass: RwLock<Option<String>>,
+    // given to the master by a replica
+    masterauth: RwLock<Option<String>>,
+}
+
+impl Backend {
+    /// The password the connections authenticate with, if they must.
+    pub fn requirepass(&self) -> Option<String> {
+        self.auth
+            .requirepass
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn set_requirepass(&self, password: Option<String>) {
+        *self
+            .auth
+            .requirepass
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = password;
+    }
+
+    /// Whether new connections must authenticate before running commands.
+    pub fn requires_auth(&self) -> bool {
+        self.auth
+            .requirepass
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    /// Whether `password` authenticates `username`. Without requirepass the default user takes
+    /// any password.
+    pub fn check_password(&self, username: &str, password: &str) -> bool {
+        if username != "default" {
+            return false;
+        }
+        match self.requirepass() {
+            Some(required) => secure_eq(&required, password),
+            None => true,
+        }
+    }
+
+    /// The password a replica authenticates to its master with, if any.
+    pub fn masterauth(&self) -> Option<String> {
+        self.auth
+            .masterauth
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn set_masterauth(&self, password: Option<String>) {
+        *self
+            .auth
+            .masterauth
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = password;
+    }
+}
+
+// Compares digests of the passwords, whose bytes are all looked at whatever they are, so the
+// time taken tells nothing of the password.
+fn secure_eq(a: &str, b: &str) -> bool {
+    let a = Sha1::from(a).digest().bytes();
+    let b = Sha1::from(b).digest().bytes();
+    a.iter()
+        .zip(b.iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_password() {
+        let backend = Backend::new();
+        assert!(!backend.requires_auth());
+        assert!(backend.check_password("default", "anything"));
+        assert!(!backend.check_password("admin", "anything"));
+
+        backend.set_requirepass(Some("secret".to_string()));
+        assert!(backend.requires_auth());
+        assert!(backend.check_password("default", "secret"));
+        assert!(!backend.check_password("default", "secreT"));
+        assert!(!backend.check_password("default", ""));
+        assert!(!backend.check_password("admin", "secret"));
+    }
+}
@@ -0,0 +1,254 @@
+// Bit-level helpers over string values. Bit 0 is the most significant bit of the first byte.
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BitRangeUnit {
+    #[default]
+    Byte,
+    Bit,
+}
+
+// An integer field of BITFIELD: `i1`..`i64` or `u1`..`u63`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitFieldType {
+    pub signed: bool,
+    pub bits: u8,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BitFieldOverflow {
+    #[default]
+    Wrap,
+    Sat,
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitFieldOp {
+    Get {
+        ty: BitFieldType,
+        offset: usize,
+    },
+    Set {
+        ty: BitFieldType,
+        offset: usize,
+        value: i64,
+        overflow: BitFieldOverflow,
+    },
+    IncrBy {
+        ty: BitFieldType,
+        offset: usize,
+        increment: i64,
+        overflow: BitFieldOverflow,
+    },
+}
+
+impl BitFieldType {
+    fn bounds(&self) -> (i128, i128) {
+        if self.signed {
+            let half = 1_i128 << (self.bits - 1);
+            (-half, half - 1)
+        } else {
+            (0, (1_i128 << self.bits) - 1)
+        }
+    }
+
+    // Fits `value` into the field, or returns `None` when it overflows with FAIL.
+    fn fit(&self, value: i128, overflow: BitFieldOverflow) -> Option<i64> {
+        let (min, max) = self.bounds();
+        if (min..=max).contains(&value) {
+            return Some(value as i64);
+        }
+        match overflow {
+            BitFieldOverflow::Wrap => Some(((value - min).rem_euclid(max - min + 1) + min) as i64),
+            BitFieldOverflow::Sat => Some(value.clamp(min, max) as i64),
+            BitFieldOverflow::Fail => None,
+        }
+    }
+}
+
+pub fn get_bit(bytes: &[u8], offset: usize) -> bool {
+    bytes
+        .get(offset / 8)
+        .is_some_and(|byte| byte & (0x80 >> (offset % 8)) != 0)
+}
+
+/// Sets or clears the bit at `offset`, zero-extending `bytes` as needed, and returns the old bit.
+pub fn set_bit(bytes: &mut Vec<u8>, offset: usize, on: bool) -> bool {
+    let index = offset / 8;
+    if bytes.len() <= index {
+        bytes.resize(index + 1, 0);
+    }
+    let mask = 0x80 >> (offset % 8);
+    let old = bytes[index] & mask != 0;
+    if on {
+        bytes[index] |= mask;
+    } else {
+        bytes[index] &= !mask;
+    }
+    old
+}
+
+pub fn read_field(bytes: &[u8], ty: BitFieldType, offset: usize) -> i64 {
+    let raw = (0..ty.bits as usize).fold(0_u64, |acc, i| {
+        (acc << 1) | get_bit(bytes, offset + i) as u64
+    });
+    if ty.signed {
+        let shift = 64 - ty.bits as u32;
+        ((raw << shift) as i64) >> shift
+    } else {
+        raw as i64
+    }
+}
+
+fn write_field(bytes: &mut Vec<u8>, ty: BitFieldType, offset: usize, value: i64) {
+    let last = (offset + ty.bits as usize - 1) / 8;
+    if bytes.len() <= last {
+        bytes.resize(last + 1, 0);
+    }
+    for i in 0..ty.bits as usize {
+        let on = (value >> (ty.bits as usize - 1 - i)) & 1 == 1;
+        set_bit(bytes, offset + i, on);
+    }
+}
+
+/// Runs one BITFIELD operation: GET returns the field, SET the old value and INCRBY the new one.
+pub fn apply_bitfield(bytes: &mut Vec<u8>, op: BitFieldOp) -> Option<i64> {
+    match op {
+        BitFieldOp::Get { ty, offset } => Some(read_field(bytes, ty, offset)),
+        BitFieldOp::Set {
+            ty,
+            offset,
+            value,
+            overflow,
+        } => {
+            let value = ty.fit(value as i128, overflow)?;
+            let old = read_field(bytes, ty, offset);
+            write_field(bytes, ty, offset, value);
+            Some(old)
+        }
+        BitFieldOp::IncrBy {
+            ty,
+            offset,
+            increment,
+            overflow,
+        } => {
+            let old = read_field(bytes, ty, offset);
+            let value = ty.fit(old as i128 + increment as i128, overflow)?;
+            write_field(bytes, ty, offset, value);
+            Some(value)
+        }
+    }
+}
+
+pub fn count_bits(bytes: &[u8]) -> usize {
+    let chunks = bytes.chunks_exact(8);
+    let rest = chunks.remainder();
+    let words = chunks
+        .map(|chunk| u64::from_ne_bytes(chunk.try_into().expect("chunk of 8 bytes")).count_ones())
+        .sum::<u32>();
+    let tail = rest.iter().map(|byte| byte.count_ones()).sum::<u32>();
+    (words + tail) as usize
+}
+
+/// Counts the set bits between the bit offsets `start` and `end` inclusive.
+pub fn count_bits_in_range(bytes: &[u8], start: usize, end: usize) -> usize {
+    let (first, last) = (start / 8, end / 8);
+    // masks keep the bits of the boundary bytes that fall inside the range
+    let head = 0xff_u8 >> (start % 8);
+    let tail = 0xff_u8 << (7 - end % 8);
+    if first == last {
+        return (bytes[first] & head & tail).count_ones() as usize;
+    }
+    (bytes[first] & head).count_ones() as usize
+        + count_bits(&bytes[first + 1..last])
+        + (bytes[last] & tail).count_ones() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_and_set_bit() {
+        let mut bytes = vec![];
+        assert!(!set_bit(&mut bytes, 7, true));
+        assert_eq!(bytes, vec![0b0000_0001]);
+        assert!(!set_bit(&mut bytes, 17, true));
+        assert_eq!(bytes, vec![0b0000_0001, 0, 0b0100_0000]);
+        assert!(set_bit(&mut bytes, 7, false));
+        assert!(get_bit(&bytes, 17));
+        assert!(!get_bit(&bytes, 7));
+        assert!(!get_bit(&bytes, 1000));
+    }
+
+    #[test]
+    fn test_bitfield_ops() {
+        let u8 = BitFieldType {
+            signed: false,
+            bits: 8,
+        };
+        let i5 = BitFieldType {
+            signed: true,
+            bits: 5,
+        };
+        let mut bytes = vec![];
+        let set = |ty, offset, value, overflow| BitFieldOp::Set {
+            ty,
+            offset,
+            value,
+            overflow,
+        };
+        let incr = |ty, offset, increment, overflow| BitFieldOp::IncrBy {
+            ty,
+            offset,
+            increment,
+            overflow,
+        };
+        assert_eq!(
+            apply_bitfield(&mut bytes, set(u8, 4, 255, BitFieldOverflow::Wrap)),
+            Some(0)
+        );
+        assert_eq!(bytes, vec![0x0f, 0xf0]);
+        assert_eq!(
+            apply_bitfield(&mut bytes, incr(u8, 4, 10, BitFieldOverflow::Wrap)),
+            Some(9)
+        );
+        assert_eq!(
+            apply_bitfield(&mut bytes, incr(u8, 4, 300, BitFieldOverflow::Sat)),
+            Some(255)
+        );
+        assert_eq!(
+            apply_bitfield(&mut bytes, incr(u8, 4, 1, BitFieldOverflow::Fail)),
+            None
+        );
+        assert_eq!(
+            apply_bitfield(&mut bytes, set(i5, 16, -17, BitFieldOverflow::Wrap)),
+            Some(0)
+        );
+        assert_eq!(read_field(&bytes, i5, 16), 15);
+        assert_eq!(
+            apply_bitfield(&mut bytes, incr(i5, 16, -40, BitFieldOverflow::Sat)),
+            Some(-16)
+        );
+        assert_eq!(
+            apply_bitfield(
+                &mut bytes,
+                BitFieldOp::Get {
+                    ty: u8,
+                    offset: 100
+                }
+            ),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_count_bits() {
+        let bytes = b"foobar_foobar".to_vec();
+        assert_eq!(count_bits(&bytes), 58);
+        assert_eq!(count_bits(&bytes[1..2]), 6);
+        assert_eq!(count_bits_in_range(&bytes, 5, 30), 17);
+        assert_eq!(count_bits_in_range(&bytes, 1, 1), 1);
+        assert_eq!(count_bits_in_range(&bytes, 0, bytes.len() * 8 - 1), 58);
+    }
+}
@@ -0,0 +1,573 @@
+// The client registry. Every connection has an entry from the creation of its `Subscriber`
+// until it is dropped, which the connection keeps up to date as it runs commands and which the
+// CLIENT commands report. CLIENT PAUSE holds the commands of the connections for a while, and
+// the connections idle longer than the timeout are closed, as are those whose output piles up
+// beyond the limit of their class. The connections are also counted by source address, so an
+// address opening too many, or too many at once, is refused more.
+
+use super::Backend;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{watch, Notify},
+    time,
+};
+
+#[derive(Debug)]
+pub(super) struct Clients {
+    registry: Mutex<HashMap<u64, ClientInfo>>,
+    pause: watch::Sender<Option<Pause>>,
+    // in seconds, 0 keeping the idle connections open
+    timeout: AtomicU64,
+    // by class, in the order of `ClientClass::ALL`
+    output_limits: Mutex<[OutputBufferLimit; 3]>,
+    addresses: Mutex<HashMap<IpAddr, AddressConnections>>,
+    // the connections an address may have open, 0 for no limit
+    max_per_address: AtomicUsize,
+    // the connections an address may open each second, 0 for no limit
+    rate_per_address: AtomicUsize,
+}
+
+impl Default for Clients {
+    fn default() -> Self {
+        Self {
+            registry: Mutex::default(),
+            pause: watch::channel(None).0,
+            timeout: AtomicU64::new(0),
+            // as in Redis
+            output_limits: Mutex::new([
+                OutputBufferLimit::default(),
+                OutputBufferLimit {
+                    hard: 256 << 20,
+                    soft: 64 << 20,
+                    soft_seconds: 60,
+                },
+                OutputBufferLimit {
+                    hard: 32 << 20,
+                    soft: 8 << 20,
+                    soft_seconds: 60,
+                },
+            ]),
+            addresses: Mutex::default(),
+            max_per_address: AtomicUsize::new(0),
+            rate_per_address: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Clients {
+    fn lock(&self) -> MutexGuard<'_, HashMap<u64, ClientInfo>> {
+        self.registry.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn output_limits(&self) -> MutexGuard<'_, [OutputBufferLimit; 3]> {
+        self.output_limits.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn addresses(&self) -> MutexGuard<'_, HashMap<IpAddr, AddressConnections>> {
+        self.addresses.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Past this many addresses, those with no connection open nor opened this second are forgotten.
+const ADDRESSES_KEPT: usize = 1024;
+
+// The connections of a source address.
+#[derive(Debug)]
+struct AddressConnections {
+    open: usize,
+    // when the second the connections opened are counted for started, and how many were
+    since: Instant,
+    opened: usize,
+}
+
+/// A connection the per-address limits admitted, which counts among those of its address until
+/// dropped.
+#[derive(Debug)]
+pub struct AdmittedConnection {
+    backend: Backend,
+    ip: IpAddr,
+}
+
+impl Drop for AdmittedConnection {
+    fn drop(&mut self) {
+        if let Some(connections) = self.backend.clients.addresses().get_mut(&self.ip) {
+            connections.open -= 1;
+        }
+    }
+}
+
+/// The classes of connections, which have output buffer limits of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientClass {
+    Normal,
+    Replica,
+    PubSub,
+}
+
+impl ClientClass {
+    pub const ALL: [ClientClass; 3] = [
+        ClientClass::Normal,
+        ClientClass::Replica,
+        ClientClass::PubSub,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClientClass::Normal => "normal",
+            ClientClass::Replica => "replica",
+            ClientClass::PubSub => "pubsub",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" => Some(ClientClass::Normal),
+            "replica" | "slave" => Some(ClientClass::Replica),
+            "pubsub" => Some(ClientClass::PubSub),
+            _ => None,
+        }
+    }
+}
+
+/// How many bytes may wait to be written out to a connection: never more than `hard`, and
+/// more than `soft` for less than `soft_seconds`. A limit of 0 is no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputBufferLimit {
+    pub hard: usize,
+    pub soft: usize,
+    pub soft_seconds: u64,
+}
+
+/// The bytes queued for a connection that it didn't write out yet. Once they overcome the limit
+/// of its class the buffer overflows, and the connection is closed.
+#[derive(Debug)]
+pub struct OutputBuffer {
+    pending: AtomicUsize,
+    state: Mutex<OutputState>,
+    overflowed: AtomicBool,
+    overflow: Notify,
+}
+
+#[derive(Debug)]
+struct OutputState {
+    class: ClientClass,
+    // since when the soft limit is exceeded, without a break
+    over_soft: Option<Instant>,
+}
+
+impl OutputBuffer {
+    pub fn new(class: ClientClass) -> Self {
+        OutputBuffer {
+            pending: AtomicUsize::new(0),
+            state: Mutex::new(OutputState {
+                class,
+                over_soft: None,
+            }),
+            overflowed: AtomicBool::new(false),
+            overflow: Notify::new(),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, OutputState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Relaxed)
+    }
+
+    /// Counts `bytes` of the output as written out.
+    pub fn written(&self, bytes: usize) {
+        self.pending.fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    pub fn set_class(&self, class: ClientClass) {
+        self.state().class = class;
+    }
+
+    pub fn overflowed(&self) -> bool {
+        self.overflowed.load(Ordering::Relaxed)
+    }
+}
+
+// The commands held by CLIENT PAUSE, and until when.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Pause {
+    until: Instant,
+    writes_only: bool,
+}
+
+/// What the server knows of a connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub id: u64,
+    /// the address of the peer, unknown for the connections made in process
+    pub addr: Option<SocketAddr>,
+    pub name: Option<String>,
+    pub created: Instant,
+    /// when the connection last ran a command
+    pub last_active: Instant,
+    /// the lower-cased name of the last command
+    pub cmd: Option<String>,
+    pub db: usize,
+    /// the number of channels, patterns and shard channels subscribed to
+    pub sub: usize,
+    pub psub: usize,
+    pub ssub: usize,
+    /// the number of commands queued since MULTI, if in a transaction
+    pub multi: Option<usize>,
+    /// the RESP version
+    pub protocol: u8,
+    /// whether the connection is a replica the replication stream is sent to
+    pub replica: bool,
+}
+
+impl ClientInfo {
+    fn new(id: u64) -> Self {
+        let now = Instant::now();
+        ClientInfo {
+            id,
+            addr: None,
+            name: None,
+            created: now,
+            last_active: now,
+            cmd: None,
+            db: 0,
+            sub: 0,
+            psub: 0,
+            ssub: 0,
+            multi: None,
+            protocol: 2,
+            replica: false,
+        }
+    }
+}
+
+impl Backend {
+    pub(super) fn register_client(&self, id: u64) {
+        self.clients.lock().insert(id, ClientInfo::new(id));
+    }
+
+    pub(super) fn unregister_client(&self, id: u64) {
+        self.clients.lock().remove(&id);
+    }
+
+    /// What the server knows of the connection `id`, if still connected.
+    pub fn client_info(&self, id: u64) -> Option<ClientInfo> {
+        self.clients.lock().get(&id).cloned()
+    }
+
+    /// What the server knows of every connection, by id.
+    pub fn client_infos(&self) -> Vec<ClientInfo> {
+        let mut infos: Vec<ClientInfo> = self.clients.lock().values().cloned().collect();
+        infos.sort_by_key(|info| info.id);
+        infos
+    }
+
+    /// Updates the entry of the connection `id`, if still connected.
+    pub fn update_client(&self, id: u64, update: impl FnOnce(&mut ClientInfo)) {
+        if let Some(info) = self.clients.lock().get_mut(&id) {
+            update(info);
+        }
+    }
+
+    /// Holds the commands of the connections until `until` or CLIENT UNPAUSE, only those that
+    /// may write when `writes_only`. A pause in effect lasts until the later of the deadlines
+    /// and holds the commands of both.
+    pub fn pause_clients(&self, until: Instant, writes_only: bool) {
+        self.clients.pause.send_modify(|pause| {
+            let merged = match *pause {
+                Some(current) if current.until > Instant::now() => Pause {
+                    until: until.max(current.until),
+                    writes_only: writes_only && current.writes_only,
+                },
+                _ => Pause { until, writes_only },
+            };
+            *pause = Some(merged);
+        });
+    }
+
+    pub fn unpause_clients(&self) {
+        self.clients.pause.send_replace(None);
+    }
+
+    /// Resolves once CLIENT PAUSE no longer holds the commands, those that may write when
+    /// `write` is set.
+    pub async fn clients_unpaused(&self, write: bool) {
+        let mut pause = self.clients.pause.subscribe();
+        loop {
+            let Some(current) = *pause.borrow_and_update() else {
+                return;
+            };
+            if (current.writes_only && !write) || current.until <= Instant::now() {
+                return;
+            }
+            tokio::select! {
+                _ = time::sleep_until(current.until.into()) => return,
+                changed = pause.changed() => {
+                    if changed.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// How long a connection may stay idle before it is closed, if it may not forever.
+    pub fn client_timeout(&self) -> Option<Duration> {
+        match self.clients.timeout.load(Ordering::Relaxed) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    pub fn set_client_timeout(&self, secs: u64) {
+        self.clients.timeout.store(secs, Ordering::Relaxed);
+    }
+
+    pub fn output_buffer_limit(&self, class: ClientClass) -> OutputBufferLimit {
+        self.clients.output_limits()[class as usize]
+    }
+
+    pub fn set_output_buffer_limit(&self, class: ClientClass, limit: OutputBufferLimit) {
+        self.clients.output_limits()[class as usize] = limit;
+    }
+
+    /// Counts `bytes` more waiting to be written out to a connection, returning whether its
+    /// output buffer is still within the limit.
+    pub fn queue_output(&self, buffer: &OutputBuffer, bytes: usize) -> bool {
+        buffer.pending.fetch_add(bytes, Ordering::Relaxed);
+        self.check_output(buffer)
+    }
+
+    /// Whether the output buffer of a connection is within the limit of its class: below the
+    /// hard limit, and not above the soft one for longer than allowed. It overflows otherwise.
+    pub fn check_output(&self, buffer: &OutputBuffer) -> bool {
+        if buffer.overflowed() {
+            return false;
+        }
+        let pending = buffer.pending();
+        let mut state = buffer.state();
+        let limit = self.output_buffer_limit(state.class);
+        let now = Instant::now();
+        if limit.soft == 0 || pending <= limit.soft {
+            state.over_soft = None;
+        }
+        let over_soft = limit.soft > 0 && pending > limit.soft && {
+            let since = *state.over_soft.get_or_insert(now);
+            now.duration_since(since) >= Duration::from_secs(limit.soft_seconds)
+        };
+        if !over_soft && (limit.hard == 0 || pending <= limit.hard) {
+            return true;
+        }
+        buffer.overflowed.store(true, Ordering::Relaxed);
+        buffer.overflow.notify_waiters();
+        false
+    }
+
+    /// Resolves once the output buffer of a connection overflows, checking every second
+    /// whether it stayed above the soft limit for too long.
+    pub async fn output_overflow(&self, buffer: &OutputBuffer) {
+        loop {
+            let overflow = buffer.overflow.notified();
+            if !self.check_output(buffer) {
+                return;
+            }
+            tokio::select! {
+                _ = overflow => {}
+                _ = time::sleep(Duration::from_secs(1)) => {}
+            }
+        }
+    }
+
+    /// The connections an address may have open, if limited.
+    pub fn max_connections_per_address(&self) -> usize {
+        self.clients.max_per_address.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_connections_per_address(&self, max: usize) {
+        self.clients.max_per_address.store(max, Ordering::Relaxed);
+    }
+
+    /// The connections an address may open each second, if limited.
+    pub fn connection_rate_per_address(&self) -> usize {
+        self.clients.rate_per_address.load(Ordering::Relaxed)
+    }
+
+    pub fn set_connection_rate_per_address(&self, rate: usize) {
+        self.clients.rate_per_address.store(rate, Ordering::Relaxed);
+    }
+
+    /// Admits a connection from `ip` unless the address has as many connections open as it
+    /// may, or opened as many this second, returning the error to refuse it with then.
+    pub fn admit_connection(&self, ip: IpAddr) -> Result<AdmittedConnection, &'static str> {
+        let now = Instant::now();
+        let mut addresses = self.clients.addresses();
+        if addresses.len() >= ADDRESSES_KEPT {
+            addresses.retain(|_, connections| {
+                connections.open > 0
+                    || now.duration_since(connections.since) < Duration::from_secs(1)
+            });
+        }
+        let connections = addresses.entry(ip).or_insert(AddressConnections {
+            open: 0,
+            since: now,
+            opened: 0,
+        });
+        if now.duration_since(connections.since) >= Duration::from_secs(1) {
+            connections.since = now;
+            connections.opened = 0;
+        }
+        let max = self.max_connections_per_address();
+        if max > 0 && connections.open >= max {
+            return Err("ERR max number of connections from your address reached");
+        }
+        let rate = self.connection_rate_per_address();
+        if rate > 0 && connections.opened >= rate {
+            return Err("ERR too many new connections from your address, try again later");
+        }
+        connections.open += 1;
+        connections.opened += 1;
+        Ok(AdmittedConnection {
+            backend: self.clone(),
+            ip,
+        })
+    }
+
+    /// Records that the connection `id` runs the command `name`.
+    pub fn client_command(&self, id: u64, name: &str) {
+        self.update_client(id, |info| {
+            info.last_active = Instant::now();
+            info.cmd = Some(name.to_string());
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_client_registry() {
+        let backend = Backend::new();
+        let client = backend.subscriber();
+        let other = backend.subscriber();
+        assert_ne!(client.id(), other.id());
+
+        let info = backend.client_info(client.id()).unwrap();
+        assert_eq!((info.id, info.cmd, info.name), (client.id(), None, None));
+        client.set_name(Some("app".to_string()));
+        backend.client_command(client.id(), "get");
+        let info = backend.client_info(client.id()).unwrap();
+        assert_eq!(info.name.as_deref(), Some("app"));
+        assert_eq!(info.cmd.as_deref(), Some("get"));
+        assert!(info.last_active >= info.created);
+
+        let ids: Vec<u64> = backend.client_infos().iter().map(|info| info.id).collect();
+        assert_eq!(ids, [client.id(), other.id()]);
+
+        let id = client.id();
+        drop(client);
+        assert_eq!(backend.client_info(id), None);
+        assert!(backend.client_info(other.id()).is_some());
+    }
+
+    #[test]
+    fn test_admit_connection() {
+        let backend = Backend::new();
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+        backend.set_max_connections_per_address(2);
+        let first = backend.admit_connection(ip).unwrap();
+        let _second = backend.admit_connection(ip).unwrap();
+        assert!(backend.admit_connection(ip).is_err());
+        assert!(backend.admit_connection(other).is_ok());
+        // room again once one is closed
+        drop(first);
+        let _third = backend.admit_connection(ip).unwrap();
+
+        backend.set_max_connections_per_address(0);
+        backend.set_connection_rate_per_address(3);
+        // the connections opened this second count, whether or not they are still open
+        assert!(backend.admit_connection(ip).is_err());
+        assert!(backend.admit_connection(other).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_output_buffer_limit() {
+        let backend = Backend::new();
+        let mut subscriber = backend.subscriber();
+        subscriber.subscribe("news");
+        subscriber.record_state(0, None);
+        backend.set_output_buffer_limit(
+            ClientClass::PubSub,
+            OutputBufferLimit {
+                hard: 200,
+                soft: 100,
+                soft_seconds: 1,
+            },
+        );
+        let message = BulkString::from("x".repeat(20));
+        assert_eq!(backend.publish("news", &message), 1);
+        assert_eq!(subscriber.output().pending(), 54);
+        // above the soft limit, for less than the soft seconds
+        assert_eq!(backend.publish("news", &message), 1);
+        assert!(backend.check_output(subscriber.output()));
+        subscriber.message().await.unwrap();
+        assert_eq!(subscriber.output().pending(), 54);
+        assert!(backend.check_output(subscriber.output()));
+
+        // over the hard limit the messages are dropped and the connection is to be closed
+        for _ in 0..3 {
+            backend.publish("news", &message);
+        }
+        assert!(subscriber.output().overflowed());
+        assert_eq!(backend.publish("news", &message), 0);
+        time::timeout(
+            time::Duration::from_millis(50),
+            backend.output_overflow(subscriber.output()),
+        )
+        .await
+        .unwrap();
+
+        // the normal connections have no limit by default
+        let client = backend.subscriber();
+        assert!(backend.queue_output(client.output(), 1 << 30));
+    }
+
+    #[tokio::test]
+    async fn test_pause_clients() {
+        let backend = Backend::new();
+        let held = |write| {
+            let backend = backend.clone();
+            tokio::spawn(async move { backend.clients_unpaused(write).await })
+        };
+        let short = time::Duration::from_millis(50);
+        backend.pause_clients(Instant::now() + time::Duration::from_secs(60), true);
+        // reads go on while writes are held
+        time::timeout(short, backend.clients_unpaused(false))
+            .await
+            .unwrap();
+        let write = held(true);
+        time::sleep(short).await;
+        assert!(!write.is_finished());
+        backend.unpause_clients();
+        time::timeout(short, write).await.unwrap().unwrap();
+
+        // the stricter pause holds everything until it ends
+        backend.pause_clients(Instant::now() + short, false);
+        backend.pause_clients(Instant::now() + time::Duration::from_millis(10), true);
+        let started = Instant::now();
+        backend.clients_unpaused(false).await;
+        assert!(started.elapsed() >= time::Duration::from_millis(40));
+    }
+}
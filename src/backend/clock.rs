@@ -0,0 +1,121 @@
+// The wall clock of the server. TIME and the expiration of keys read it through the backend, so
+// the tests can set the time instead of waiting for it to pass.
+
+use super::{db, Backend, BackendInner, DEFAULT_DATABASES};
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Where the server reads the current time from.
+pub trait Clock: Debug + Send + Sync {
+    /// The time elapsed since the unix epoch.
+    fn now(&self) -> Duration;
+}
+
+/// The time of the system, which the server runs on.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+}
+
+/// A clock standing still until it is set or advanced.
+#[derive(Debug, Default)]
+pub struct MockClock {
+    // microseconds since the unix epoch
+    micros: AtomicU64,
+}
+
+impl MockClock {
+    pub fn new(now: Duration) -> Self {
+        let clock = MockClock::default();
+        clock.set(now);
+        clock
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.micros.store(now.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.micros
+            .fetch_add(by.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        Duration::from_micros(self.micros.load(Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct ServerClock(Arc<dyn Clock>);
+
+impl Default for ServerClock {
+    fn default() -> Self {
+        Self(Arc::new(SystemClock))
+    }
+}
+
+impl Backend {
+    /// A backend with the default databases, reading the time from `clock`.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        let inner = BackendInner {
+            dbs: db::Databases::new(DEFAULT_DATABASES, 0),
+            clock: ServerClock(clock),
+            ..Default::default()
+        };
+        Self {
+            inner: Arc::new(inner),
+            db: 0,
+        }
+    }
+
+    /// The current unix time, as TIME reports it.
+    pub fn now(&self) -> Duration {
+        self.clock.0.now()
+    }
+
+    /// The current unix time in milliseconds, which the key timeouts compare to.
+    pub fn now_millis(&self) -> u64 {
+        self.now().as_millis() as u64
+    }
+}
+
+// The unix time of the system in milliseconds, for the bookkeeping that doesn't go by the
+// server clock.
+pub(super) fn unix_millis() -> u64 {
+    SystemClock.now().as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = Arc::new(MockClock::new(Duration::from_secs(1_000)));
+        let backend = Backend::with_clock(clock.clone());
+        assert_eq!(backend.now_millis(), 1_000_000);
+
+        backend.set("key".into(), BulkString::from("value").into());
+        assert!(backend.expire(b"key", 1_500));
+        clock.advance(Duration::from_millis(1_000));
+        assert_eq!(backend.ttl(b"key"), Some(Some(500)));
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(backend.get(b"key").unwrap(), None);
+        assert_eq!(backend.ttl(b"key"), None);
+    }
+}
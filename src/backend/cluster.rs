@@ -0,0 +1,426 @@
+// Cluster mode. The keyspace is split into 16384 hash slots, each served by one node; a node
+// only serves the keys of its own slots and redirects clients to the owner of the others. The
+// topology is kept in the cluster config file, in the format of the Redis `nodes.conf`.
+
+use super::{crc16::crc16, replication::new_replid, Backend};
+use std::{
+    fs, io,
+    path::PathBuf,
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+};
+use tracing::{info, warn};
+
+pub const CLUSTER_SLOTS: u16 = 16384;
+
+// the cluster bus port of a node is its client port plus this offset
+const BUS_PORT_OFFSET: u16 = 10000;
+
+/// A node of the cluster. The host of this node may be empty, as it only knows its own port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterNode {
+    pub id: String,
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Debug)]
+pub(super) struct Cluster {
+    enabled: AtomicBool,
+    config_file: RwLock<String>,
+    state: RwLock<ClusterState>,
+}
+
+#[derive(Debug)]
+struct ClusterState {
+    // this node comes first
+    nodes: Vec<ClusterNode>,
+    // index in `nodes` of the owner of each slot
+    slots: Vec<Option<usize>>,
+}
+
+impl Default for Cluster {
+    fn default() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            config_file: RwLock::new("nodes.conf".to_string()),
+            state: RwLock::new(ClusterState {
+                nodes: vec![ClusterNode {
+                    id: new_replid(),
+                    host: String::new(),
+                    port: 0,
+                }],
+                slots: vec![None; CLUSTER_SLOTS as usize],
+            }),
+        }
+    }
+}
+
+/// The hash slot of `key`. When the key holds a non-empty `{...}` hash tag, only the tag is
+/// hashed, so related keys can be kept in the same slot.
+pub fn key_slot(key: &[u8]) -> u16 {
+    let tag = key.iter().position(|&b| b == b'{').and_then(|start| {
+        let rest = &key[start + 1..];
+        rest.iter()
+            .position(|&b| b == b'}')
+            .filter(|&end| end > 0)
+            .map(|end| &rest[..end])
+    });
+    crc16(tag.unwrap_or(key)) % CLUSTER_SLOTS
+}
+
+impl Backend {
+    pub fn cluster_enabled(&self) -> bool {
+        self.cluster.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Turns cluster mode on, loading the topology from the cluster config file, or creating
+    /// the file with this node alone when there is none.
+    pub fn enable_cluster(&self) -> io::Result<()> {
+        let path = self.cluster_config_path();
+        match fs::read_to_string(&path) {
+            Ok(config) => {
+                let mut state = parse_config(&config)?;
+                // an address announced at startup wins over the saved one
+                let announced = self.cluster_announce_ip();
+                if !announced.is_empty() {
+                    state.nodes[0].host = announced;
+                }
+                info!(
+                    "Loaded {} cluster nodes from {}",
+                    state.nodes.len(),
+                    path.display()
+                );
+                *self.cluster_state_mut() = state;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => self.save_cluster_config()?,
+            Err(e) => return Err(e),
+        }
+        self.cluster.enabled.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn cluster_config_file(&self) -> String {
+        self.cluster
+            .config_file
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn set_cluster_config_file(&self, name: String) {
+        *self
+            .cluster
+            .config_file
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = name;
+    }
+
+    pub fn myid(&self) -> String {
+        self.cluster_state().nodes[0].id.clone()
+    }
+
+    /// The node serving `slot`, if any.
+    pub fn slot_owner(&self, slot: u16) -> Option<ClusterNode> {
+        let state = self.cluster_state();
+        state.slots[slot as usize].map(|owner| self.node(&state, owner))
+    }
+
+    /// Checks this node serves `keys`, which must all hash to the same slot, returning the
+    /// error to reply otherwise.
+    pub fn check_keys_slot(&self, keys: &[&[u8]]) -> Result<(), String> {
+        let Some((first, rest)) = keys.split_first() else {
+            return Ok(());
+        };
+        let slot = key_slot(first);
+        if rest.iter().any(|key| key_slot(key) != slot) {
+            return Err("CROSSSLOT Keys in request don't hash to the same slot".to_string());
+        }
+        let state = self.cluster_state();
+        match state.slots[slot as usize] {
+            Some(0) => Ok(()),
+            Some(owner) => {
+                let node = &state.nodes[owner];
+                Err(format!("MOVED {} {}:{}", slot, node.host, node.port))
+            }
+            None => Err("CLUSTERDOWN Hash slot not served".to_string()),
+        }
+    }
+
+    /// Assigns `slots` to this node. None of them may be served already.
+    pub fn add_slots(&self, slots: &[u16]) -> Result<(), String> {
+        self.update_slots(slots, |slot, owner| match owner {
+            Some(_) => Err(format!("Slot {} is already busy", slot)),
+            None => Ok(Some(0)),
+        })
+    }
+
+    /// Leaves `slots` unassigned, whichever node served them.
+    pub fn del_slots(&self, slots: &[u16]) -> Result<(), String> {
+        self.update_slots(slots, |slot, owner| match owner {
+            Some(_) => Ok(None),
+            None => Err(format!("Slot {} is already unassigned", slot)),
+        })
+    }
+
+    // Applies `update` to the owner of every slot of `slots`, all or none of them, then saves
+    // the topology.
+    fn update_slots(
+        &self,
+        slots: &[u16],
+        update: impl Fn(u16, Option<usize>) -> Result<Option<usize>, String>,
+    ) -> Result<(), String> {
+        {
+            let mut state = self.cluster_state_mut();
+            let mut owners = Vec::with_capacity(slots.len());
+            for (i, &slot) in slots.iter().enumerate() {
+                if slots[..i].contains(&slot) {
+                    return Err(format!("Slot {} specified multiple times", slot));
+                }
+                owners.push(update(slot, state.slots[slot as usize])?);
+            }
+            for (&slot, owner) in slots.iter().zip(owners) {
+                state.slots[slot as usize] = owner;
+            }
+        }
+        if let Err(e) = self.save_cluster_config() {
+            warn!("Can't save the cluster config: {}", e);
+        }
+        Ok(())
+    }
+
+    /// The nodes of the cluster, this one first, with the ranges of slots they serve.
+    pub fn cluster_nodes(&self) -> Vec<(ClusterNode, Vec<(u16, u16)>)> {
+        let state = self.cluster_state();
+        (0..state.nodes.len())
+            .map(|i| (self.node(&state, i), slot_ranges(&state.slots, i)))
+            .collect()
+    }
+
+    /// One line per node describing the topology, as in the cluster config file and the
+    /// CLUSTER NODES reply.
+    pub fn cluster_nodes_config(&self) -> String {
+        let mut config = String::new();
+        for (i, (node, slots)) in self.cluster_nodes().into_iter().enumerate() {
+            let flags = if i == 0 { "myself,master" } else { "master" };
+            config += &format!(
+                "{} {}:{}@{} {} - 0 0 0 connected",
+                node.id,
+                node.host,
+                node.port,
+                node.port.saturating_add(BUS_PORT_OFFSET),
+                flags
+            );
+            for (start, end) in slots {
+                config += &if start == end {
+                    format!(" {}", start)
+                } else {
+                    format!(" {}-{}", start, end)
+                };
+            }
+            config.push('\n');
+        }
+        config
+    }
+
+    /// The address other nodes and clients reach this node at, empty when unknown.
+    pub fn cluster_announce_ip(&self) -> String {
+        self.cluster_state().nodes[0].host.clone()
+    }
+
+    pub fn set_cluster_announce_ip(&self, ip: String) {
+        self.cluster_state_mut().nodes[0].host = ip;
+        if self.cluster_enabled() {
+            if let Err(e) = self.save_cluster_config() {
+                warn!("Can't save the cluster config: {}", e);
+            }
+        }
+    }
+
+    fn save_cluster_config(&self) -> io::Result<()> {
+        let config = self.cluster_nodes_config() + "vars currentEpoch 0 lastVoteEpoch 0\n";
+        let path = self.cluster_config_path();
+        let temp = path.with_file_name(format!("temp-{}.conf", process::id()));
+        fs::write(&temp, config)?;
+        fs::rename(&temp, path)
+    }
+
+    fn cluster_config_path(&self) -> PathBuf {
+        self.dir().join(self.cluster_config_file())
+    }
+
+    // The node at `index`, with the listening port as the address of this node.
+    fn node(&self, state: &ClusterState, index: usize) -> ClusterNode {
+        let mut node = state.nodes[index].clone();
+        if index == 0 {
+            node.port = self.listening_port();
+        }
+        node
+    }
+
+    fn cluster_state(&self) -> RwLockReadGuard<'_, ClusterState> {
+        self.cluster.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn cluster_state_mut(&self) -> RwLockWriteGuard<'_, ClusterState> {
+        self.cluster
+            .state
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// The ranges of consecutive slots served by the node at `owner`.
+fn slot_ranges(slots: &[Option<usize>], owner: usize) -> Vec<(u16, u16)> {
+    let mut ranges: Vec<(u16, u16)> = vec![];
+    for slot in (0..CLUSTER_SLOTS).filter(|&slot| slots[slot as usize] == Some(owner)) {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == slot => *end = slot,
+            _ => ranges.push((slot, slot)),
+        }
+    }
+    ranges
+}
+
+// Reads the lines `<id> <ip>:<port>@<bus port> <flags> <master> <ping sent> <pong received>
+// <epoch> <link state> <slot or range>...` of a config file. The node flagged `myself` is this
+// one.
+fn parse_config(config: &str) -> io::Result<ClusterState> {
+    let bad = |line: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Bad cluster config line: {}", line),
+        )
+    };
+    let mut nodes = vec![];
+    let mut owned = vec![];
+    let mut myself = None;
+    for line in config.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() || fields[0] == "vars" {
+            continue;
+        }
+        if fields.len() < 8 {
+            return Err(bad(line));
+        }
+        let addr = fields[1].split(['@', ',']).next().unwrap_or_default();
+        let (host, port) = addr.rsplit_once(':').ok_or_else(|| bad(line))?;
+        let port = port.parse().map_err(|_| bad(line))?;
+        if fields[2].split(',').any(|flag| flag == "myself") {
+            myself = Some(nodes.len());
+        }
+        for slots in &fields[8..] {
+            // slots being migrated are listed in brackets
+            if slots.starts_with('[') {
+                continue;
+            }
+            let (start, end) = slots.split_once('-').unwrap_or((slots, slots));
+            let (start, end) = match (start.parse::<u16>(), end.parse::<u16>()) {
+                (Ok(start), Ok(end)) if start <= end && end < CLUSTER_SLOTS => (start, end),
+                _ => return Err(bad(line)),
+            };
+            owned.push((nodes.len(), start, end));
+        }
+        nodes.push(ClusterNode {
+            id: fields[0].to_string(),
+            host: host.to_string(),
+            port,
+        });
+    }
+    let myself = myself.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "No node flagged myself in the cluster config",
+        )
+    })?;
+    // keep this node first
+    nodes.swap(0, myself);
+    let mut slots = vec![None; CLUSTER_SLOTS as usize];
+    for (owner, start, end) in owned {
+        let owner = match owner {
+            0 => myself,
+            owner if owner == myself => 0,
+            owner => owner,
+        };
+        slots[start as usize..=end as usize].fill(Some(owner));
+    }
+    Ok(ClusterState { nodes, slots })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_key_slot() {
+        assert_eq!(key_slot(b"foo"), 12182);
+        assert_eq!(key_slot(b"{user1000}.following"), key_slot(b"user1000"));
+        // an empty tag doesn't count, and only the first tag does
+        assert_ne!(key_slot(b"foo{}{bar}"), key_slot(b"bar"));
+        assert_eq!(key_slot(b"foo{{bar}}zap"), key_slot(b"{bar"));
+        assert_eq!(key_slot(b"foo{bar}{zap}"), key_slot(b"bar"));
+    }
+
+    #[test]
+    fn test_slot_ownership() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("simple-redis-cluster-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        let config = "\
+            b0f0 127.0.0.1:7001@17001 master - 0 0 1 connected 0-8000\n\
+            a1e1 127.0.0.1:7000@17000 myself,master - 0 0 0 connected 8001-16383\n\
+            vars currentEpoch 1 lastVoteEpoch 0\n";
+        fs::write(dir.join("nodes.conf"), config)?;
+        let backend = Backend::new();
+        backend.set_dir(dir.clone());
+        backend.set_listening_port(7000);
+        backend.enable_cluster()?;
+        assert_eq!(backend.myid(), "a1e1");
+
+        // foo hashes to 12182
+        assert_eq!(backend.check_keys_slot(&[b"foo".as_slice()]), Ok(()));
+        let keys = [b"{foo}.a".as_slice(), b"{foo}.b", b"foo"];
+        assert_eq!(backend.check_keys_slot(&keys), Ok(()));
+        assert_eq!(
+            backend.check_keys_slot(&[b"foo".as_slice(), b"bar"]),
+            Err("CROSSSLOT Keys in request don't hash to the same slot".to_string())
+        );
+        let slot = key_slot(b"bar");
+        assert_eq!(slot, 5061);
+        assert_eq!(
+            backend.check_keys_slot(&[b"bar".as_slice()]),
+            Err("MOVED 5061 127.0.0.1:7001".to_string())
+        );
+        assert_eq!(
+            backend.slot_owner(slot).map(|node| node.id),
+            Some("b0f0".to_string())
+        );
+
+        assert!(backend.add_slots(&[0]).is_err());
+        assert_eq!(backend.del_slots(&[5061, 5062]), Ok(()));
+        assert_eq!(
+            backend.check_keys_slot(&[b"bar".as_slice()]),
+            Err("CLUSTERDOWN Hash slot not served".to_string())
+        );
+        assert!(backend.add_slots(&[5061, 5061]).is_err());
+        assert_eq!(backend.add_slots(&[5061]), Ok(()));
+        assert_eq!(backend.check_keys_slot(&[b"bar".as_slice()]), Ok(()));
+
+        // the changes are saved
+        let saved = fs::read_to_string(dir.join("nodes.conf"))?;
+        assert!(saved.starts_with(
+            "a1e1 127.0.0.1:7000@17000 myself,master - 0 0 0 connected 5061 8001-16383\n"
+        ));
+        assert!(saved
+            .contains("\nb0f0 127.0.0.1:7001@17001 master - 0 0 0 connected 0-5060 5063-8000\n"));
+        let restarted = Backend::new();
+        restarted.set_dir(dir.clone());
+        restarted.enable_cluster()?;
+        assert_eq!(restarted.check_keys_slot(&[b"bar".as_slice()]), Ok(()));
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
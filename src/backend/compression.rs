@@ -0,0 +1,78 @@
+// Opt-in LZ4 compression of long string values, trading some CPU on every read and write for
+// memory. Nothing is compressed until a threshold is configured.
+
+use super::Backend;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug, Default)]
+pub(super) struct Compression {
+    // shortest string that gets compressed; zero disables compression
+    threshold: AtomicUsize,
+}
+
+/// LZ4 block holding a string, prefixed with its uncompressed length.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Compressed(Box<[u8]>);
+
+impl Compressed {
+    pub(super) fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub(super) fn decompress(&self) -> Vec<u8> {
+        lz4_flex::decompress_size_prepended(&self.0).expect("value was compressed by lz4_flex")
+    }
+}
+
+impl Backend {
+    pub fn compression_threshold(&self) -> usize {
+        self.compression.threshold.load(Ordering::Relaxed)
+    }
+
+    pub fn set_compression_threshold(&self, bytes: usize) {
+        self.compression.threshold.store(bytes, Ordering::Relaxed);
+    }
+
+    // Compresses long strings, keeping those that don't shrink as they are.
+    pub(super) fn compress(&self, bytes: &[u8]) -> Option<Compressed> {
+        let threshold = self.compression_threshold();
+        if threshold == 0 || bytes.len() < threshold {
+            return None;
+        }
+        let compressed = lz4_flex::compress_prepend_size(bytes);
+        (compressed.len() < bytes.len()).then(|| Compressed(compressed.into_boxed_slice()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespFrame};
+
+    #[test]
+    fn test_compress_long_strings() {
+        let backend = Backend::new();
+        let value: RespFrame = BulkString::new(vec![b'a'; 1000]).into();
+        backend.set("key".into(), value.clone());
+        assert_eq!(backend.object_encoding(b"key"), Some("raw"));
+        let raw = backend.memory_usage(b"key", 0).unwrap();
+
+        backend.set_compression_threshold(100);
+        backend.set("key".into(), value.clone());
+        assert_eq!(backend.object_encoding(b"key"), Some("lz4"));
+        assert!(backend.memory_usage(b"key", 0).unwrap() < raw / 2);
+        assert_eq!(backend.get(b"key").unwrap(), Some(value));
+        assert_eq!(backend.bitcount(b"key", None).unwrap(), 3000);
+
+        // short and incompressible strings are stored as they are
+        backend.set("short".into(), BulkString::new(vec![b'a'; 99]).into());
+        assert_eq!(backend.object_encoding(b"short"), Some("raw"));
+        let noise = (0..1000).map(|_| rand::random::<u8>()).collect::<Vec<_>>();
+        backend.set("noise".into(), BulkString::new(noise).into());
+        assert_eq!(backend.object_encoding(b"noise"), Some("raw"));
+
+        backend.setbit("key".into(), 0, true).unwrap();
+        assert_eq!(backend.object_encoding(b"key"), Some("raw"));
+        assert_eq!(backend.bitcount(b"key", None).unwrap(), 3001);
+    }
+}
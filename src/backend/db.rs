@@ -0,0 +1,475 @@
+// Logical databases. Each one holds a keyspace of its own, numbered from zero. A backend is a
+// handle on the server running commands against one of them, the one its connection selected.
+// SWAPDB exchanges the keyspaces behind two numbers, leaving the keyspaces where they are.
+//
+// A key holds a single value of one type. Commands reading or updating a value of a given type
+// find out through the typed accessors of `Db` when the key holds another one, and fail with
+// `WrongType` as Redis does, rather than seeing the key as missing or replacing its value.
+//
+// Each map of a keyspace is striped over a number of shards, each behind a lock of its own, so
+// commands on keys of different shards don't wait on each other. The count is set at startup,
+// more shards making it less likely that the cores writing at once go for the same lock. Each
+// shard keeps counters of its accesses and of the times a command found its lock taken.
+
+use super::{memory::KeyStats, string::StringValue, Backend, Hash, QuickList, Set, Stream, ZSet};
+use crate::{BulkString, RespArray, RespFrame};
+use bytes::Bytes;
+use dashmap::{
+    mapref::one::{MappedRef, MappedRefMut},
+    try_result::TryResult,
+    DashMap,
+};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use thiserror::Error;
+
+// as in the default redis.conf
+pub const DEFAULT_DATABASES: usize = 16;
+
+/// What a shard of the keyspace went through, as INFO reports it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShardStats {
+    pub keys: usize,
+    /// the commands that read or wrote a key of the shard
+    pub accesses: u64,
+    pub writes: u64,
+    /// the lookups that found the lock of the shard taken and had to wait for it
+    pub contended: u64,
+}
+
+// The counters of a shard, each shard's on a cache line of its own so the cores counting in
+// different shards don't contend on it.
+#[derive(Debug, Default)]
+#[repr(align(64))]
+struct ShardCounters {
+    accesses: AtomicU64,
+    writes: AtomicU64,
+    contended: AtomicU64,
+}
+
+// The shard count of a map when none is set, as dashmap picks it.
+fn default_shards() -> usize {
+    (std::thread::available_parallelism().map_or(1, usize::from) * 4).next_power_of_two()
+}
+
+/// The error of a command used against a key holding a value of another type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+pub struct WrongType;
+
+/// The value of a key, of any of the types a key can hold. Values are kept in representations
+/// of their own, strings and the elements of collections as plain bytes, rather than as the
+/// frames they are written and read with.
+#[derive(Debug, Clone)]
+pub(super) enum Value {
+    String(StringValue),
+    Hash(Hash),
+    Set(Set),
+    List(QuickList),
+    ZSet(ZSet),
+    Stream(Stream),
+}
+
+impl Value {
+    /// The name of the type as TYPE reports it.
+    pub(super) fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Hash(_) => "hash",
+            Value::Set(_) => "set",
+            Value::List(_) => "list",
+            Value::ZSet(_) => "zset",
+            Value::Stream(_) => "stream",
+        }
+    }
+
+    /// The number of elements dropped along with the value.
+    pub(super) fn len(&self) -> usize {
+        match self {
+            Value::String(_) => 1,
+            Value::Hash(hash) => hash.len(),
+            Value::Set(set) => set.len(),
+            Value::List(list) => list.len(),
+            Value::ZSet(zset) => zset.len(),
+            Value::Stream(stream) => stream.len(),
+        }
+    }
+
+    // Collections go away with their last element, except streams, which keep their last id.
+    fn is_empty(&self) -> bool {
+        match self {
+            Value::String(_) | Value::Stream(_) => false,
+            Value::Hash(hash) => hash.is_empty(),
+            Value::Set(set) => set.is_empty(),
+            Value::List(list) => list.is_empty(),
+            Value::ZSet(zset) => zset.is_empty(),
+        }
+    }
+}
+
+/// A type of value stored in the keyspace as a variant of `Value`.
+pub(super) trait ValueType: Sized {
+    fn into_value(self) -> Value;
+    fn of(value: &Value) -> Option<&Self>;
+    fn of_mut(value: &mut Value) -> Option<&mut Self>;
+}
+
+macro_rules! value_type {
+    ($ty:ty, $variant:ident) => {
+        impl ValueType for $ty {
+            fn into_value(self) -> Value {
+                Value::$variant(self)
+            }
+
+            fn of(value: &Value) -> Option<&Self> {
+                match value {
+                    Value::$variant(value) => Some(value),
+                    _ => None,
+                }
+            }
+
+            fn of_mut(value: &mut Value) -> Option<&mut Self> {
+                match value {
+                    Value::$variant(value) => Some(value),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+value_type!(StringValue, String);
+value_type!(Hash, Hash);
+value_type!(Set, Set);
+value_type!(QuickList, List);
+value_type!(ZSet, ZSet);
+value_type!(Stream, Stream);
+
+pub(super) type ValueRef<'a, T> = MappedRef<'a, Bytes, Value, T>;
+pub(super) type ValueRefMut<'a, T> = MappedRefMut<'a, Bytes, Value, T>;
+
+#[derive(Debug)]
+pub(super) struct Db {
+    // the value of every key, whatever its type
+    pub(super) keyspace: DashMap<Bytes, Value>,
+    // absolute expiry times in unix milliseconds of keys that have a timeout
+    pub(super) expires: DashMap<Bytes, u64>,
+    // per-key size and access bookkeeping used for maxmemory eviction
+    pub(super) keys: DashMap<Bytes, KeyStats>,
+    // those of each shard of the keyspace
+    counters: Box<[ShardCounters]>,
+}
+
+impl Default for Db {
+    fn default() -> Self {
+        Db::with_shards(default_shards())
+    }
+}
+
+impl Db {
+    // A keyspace whose maps have `shards` shards, a power of two.
+    fn with_shards(shards: usize) -> Self {
+        Db {
+            keyspace: DashMap::with_shard_amount(shards),
+            expires: DashMap::with_shard_amount(shards),
+            keys: DashMap::with_shard_amount(shards),
+            counters: (0..shards).map(|_| ShardCounters::default()).collect(),
+        }
+    }
+
+    /// The value at `key`, or `None` when the key is missing.
+    pub(super) fn get<T: ValueType>(
+        &self,
+        key: &[u8],
+    ) -> Result<Option<ValueRef<'_, T>>, WrongType> {
+        let value = match self.keyspace.try_get(key) {
+            TryResult::Present(value) => Some(value),
+            TryResult::Absent => None,
+            TryResult::Locked => {
+                self.contended(key);
+                self.keyspace.get(key)
+            }
+        };
+        match value {
+            Some(value) => value.try_map(T::of).map(Some).map_err(|_| WrongType),
+            None => Ok(None),
+        }
+    }
+
+    pub(super) fn get_mut<T: ValueType>(
+        &self,
+        key: &[u8],
+    ) -> Result<Option<ValueRefMut<'_, T>>, WrongType> {
+        let value = match self.keyspace.try_get_mut(key) {
+            TryResult::Present(value) => Some(value),
+            TryResult::Absent => None,
+            TryResult::Locked => {
+                self.contended(key);
+                self.keyspace.get_mut(key)
+            }
+        };
+        match value {
+            Some(value) => value.try_map(T::of_mut).map(Some).map_err(|_| WrongType),
+            None => Ok(None),
+        }
+    }
+
+    /// The value at `key`, which `init` creates when the key is missing.
+    pub(super) fn get_or_insert_with<T: ValueType>(
+        &self,
+        key: Bytes,
+        init: impl FnOnce() -> T,
+    ) -> Result<ValueRefMut<'_, T>, WrongType> {
+        let entry = match self.keyspace.try_entry(key.clone()) {
+            Some(entry) => entry,
+            None => {
+                self.contended(&key);
+                self.keyspace.entry(key)
+            }
+        };
+        entry
+            .or_insert_with(|| init().into_value())
+            .try_map(T::of_mut)
+            .map_err(|_| WrongType)
+    }
+
+    pub(super) fn get_or_default<T: ValueType + Default>(
+        &self,
+        key: Bytes,
+    ) -> Result<ValueRefMut<'_, T>, WrongType> {
+        self.get_or_insert_with(key, T::default)
+    }
+
+    /// Deletes `key` when it holds a collection whose last element was just removed.
+    pub(super) fn remove_if_empty(&self, key: &[u8]) {
+        self.keyspace.remove_if(key, |_, value| value.is_empty());
+    }
+
+    pub(super) fn accessed(&self, key: &[u8]) {
+        self.shard_counters(key)
+            .accesses
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn wrote(&self, key: &[u8]) {
+        self.shard_counters(key)
+            .writes
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn contended(&self, key: &[u8]) {
+        self.shard_counters(key)
+            .contended
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn shard_counters(&self, key: &[u8]) -> &ShardCounters {
+        &self.counters[self.keyspace.determine_map(key)]
+    }
+
+    fn shard_stats(&self) -> Vec<ShardStats> {
+        let shards = self.keyspace.shards().iter().zip(self.counters.iter());
+        shards
+            .map(|(shard, counters)| ShardStats {
+                keys: shard.read().len(),
+                accesses: counters.accesses.load(Ordering::Relaxed),
+                writes: counters.writes.load(Ordering::Relaxed),
+                contended: counters.contended.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Databases {
+    keyspaces: Box<[Db]>,
+    // the keyspace each database number refers to
+    numbers: Box<[AtomicUsize]>,
+}
+
+impl Databases {
+    // `databases` keyspaces of `shards` shards each, the default count when zero.
+    pub(super) fn new(databases: usize, shards: usize) -> Self {
+        let shards = match shards {
+            0 => default_shards(),
+            shards => shards.max(2).next_power_of_two(),
+        };
+        Databases {
+            keyspaces: (0..databases).map(|_| Db::with_shards(shards)).collect(),
+            numbers: (0..databases).map(AtomicUsize::new).collect(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.keyspaces.len()
+    }
+
+    fn get(&self, index: usize) -> &Db {
+        &self.keyspaces[self.numbers[index].load(Ordering::Acquire)]
+    }
+}
+
+impl Backend {
+    /// The number of databases, fixed when the backend is created.
+    pub fn databases(&self) -> usize {
+        self.dbs.len()
+    }
+
+    /// The number of shards each keyspace is striped over, fixed when the backend is created.
+    pub fn keyspace_shards(&self) -> usize {
+        self.db().counters.len()
+    }
+
+    /// The keys and the accesses of each shard of the selected database.
+    pub fn shard_stats(&self) -> Vec<ShardStats> {
+        self.db().shard_stats()
+    }
+
+    /// The index of the database the commands run against.
+    pub fn db_index(&self) -> usize {
+        self.db
+    }
+
+    /// A handle running commands against the database `index` instead, or `None` when there
+    /// is no such database.
+    pub fn select(&self, index: usize) -> Option<Backend> {
+        (index < self.databases()).then(|| Backend {
+            inner: self.inner.clone(),
+            db: index,
+        })
+    }
+
+    /// Exchanges the contents of the databases `index1` and `index2`, so the connections that
+    /// selected one of them see the other. Returns false when there is no such database.
+    ///
+    /// The caller holds the transaction lock, as a command running meanwhile could find both
+    /// numbers on the same keyspace.
+    pub fn swapdb(&self, index1: usize, index2: usize) -> bool {
+        let databases = self.databases();
+        if index1 >= databases || index2 >= databases {
+            return false;
+        }
+        if index1 != index2 {
+            let numbers = &self.dbs.numbers;
+            let keyspace1 = numbers[index1].load(Ordering::Acquire);
+            let keyspace2 = numbers[index2].swap(keyspace1, Ordering::AcqRel);
+            numbers[index1].store(keyspace2, Ordering::Release);
+            self.touch_watched_swapped(index1, index2);
+            self.mark_dirty();
+            // the clients blocked in one database may find their keys in the other
+            self.list_notify.notify_waiters();
+        }
+        true
+    }
+
+    /// The type of the value at `key` as TYPE reports it, without counting this lookup as an
+    /// access.
+    pub fn key_type(&self, key: &[u8]) -> Option<&'static str> {
+        if self.expire_if_needed(key) {
+            return None;
+        }
+        self.db().keyspace.get(key).map(|value| value.type_name())
+    }
+
+    pub(super) fn db(&self) -> &Db {
+        self.dbs.get(self.db)
+    }
+
+    // Handles on every database, in order.
+    pub(super) fn all_dbs(&self) -> impl Iterator<Item = Backend> + '_ {
+        (0..self.databases()).filter_map(|index| self.select(index))
+    }
+}
+
+// The command switching the AOF or the replication stream to the database `index`, so the
+// commands after it are replayed there.
+pub(super) fn select_command(index: usize) -> RespFrame {
+    RespArray::new([
+        BulkString::from("SELECT").into(),
+        BulkString::from(index.to_string()).into(),
+    ])
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_select() {
+        let backend = Backend::with_databases(2);
+        assert_eq!(backend.databases(), 2);
+        assert_eq!(backend.db_index(), 0);
+        assert!(backend.select(2).is_none());
+
+        let other = backend.select(1).unwrap();
+        assert_eq!(other.db_index(), 1);
+        other.set("key".into(), BulkString::from("value").into());
+        assert_eq!(backend.get(b"key").unwrap(), None);
+        assert_eq!(
+            other.get(b"key").unwrap(),
+            Some(BulkString::from("value").into())
+        );
+        // the memory accounting covers every database
+        assert_eq!(backend.memory_stats().keys, 1);
+        assert!(backend.used_memory() > 0);
+    }
+
+    #[test]
+    fn test_swapdb() {
+        let backend = Backend::with_databases(3);
+        let other = backend.select(2).unwrap();
+        backend.set("key".into(), BulkString::from("0").into());
+        other.set("key".into(), BulkString::from("2").into());
+        other.set("other".into(), BulkString::from("2").into());
+        backend.watch(1, b"key");
+        other.watch(2, b"missing");
+
+        assert!(backend.swapdb(0, 2));
+        assert_eq!(
+            backend.get(b"key").unwrap(),
+            Some(BulkString::from("2").into())
+        );
+        assert_eq!(
+            backend.get(b"other").unwrap(),
+            Some(BulkString::from("2").into())
+        );
+        assert_eq!(
+            other.get(b"key").unwrap(),
+            Some(BulkString::from("0").into())
+        );
+        assert_eq!(other.get(b"other").unwrap(), None);
+        // only the watched keys in one of the databases changed
+        assert!(backend.unwatch(1));
+        assert!(!backend.unwatch(2));
+
+        assert!(backend.swapdb(2, 2));
+        assert!(!backend.swapdb(0, 3));
+        assert_eq!(
+            backend.get(b"key").unwrap(),
+            Some(BulkString::from("2").into())
+        );
+    }
+
+    #[test]
+    fn test_keyspace_shards() {
+        assert_eq!(Backend::with_shards(1, 5).keyspace_shards(), 8);
+        assert_eq!(Backend::with_shards(1, 1).keyspace_shards(), 2);
+        assert!(Backend::new().keyspace_shards().is_power_of_two());
+
+        let backend = Backend::with_shards(2, 4);
+        for key in ["a", "b", "c", "d", "e", "f"] {
+            backend.set(key.into(), BulkString::from("value").into());
+            backend.get(key.as_bytes()).unwrap();
+        }
+        let stats = backend.shard_stats();
+        assert_eq!(stats.len(), 4);
+        assert_eq!(stats.iter().map(|shard| shard.keys).sum::<usize>(), 6);
+        assert_eq!(stats.iter().map(|shard| shard.writes).sum::<u64>(), 6);
+        assert_eq!(stats.iter().map(|shard| shard.accesses).sum::<u64>(), 12);
+        assert_eq!(stats.iter().map(|shard| shard.contended).sum::<u64>(), 0);
+        // the other database keeps counters of its own
+        let other = backend.select(1).unwrap();
+        assert!(other.shard_stats().iter().all(|shard| shard.accesses == 0));
+    }
+}
@@ -0,0 +1,403 @@
+// A storage engine keeping the keys in a sled database on disk, with those used last cached in
+// an in-memory backend in front of it, so the dataset can outgrow the memory. A cached key is
+// written to the disk along with the write that changed it, or, written back, only when it
+// leaves the cache or the engine is flushed. The values are stored as they are in RDB files,
+// after the unix time in milliseconds they expire at, zero for none.
+
+use super::{
+    persistence::{Entry, PersistenceError},
+    rdb::encode_value,
+    ListDirection, Storage, WrongType,
+};
+use crate::{Backend, RespFrame};
+use bytes::Bytes;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io,
+    path::Path,
+    sync::{Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+// How long opening a database waits for sled to release the lock on it.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// When the writes to the cached keys reach the disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritePolicy {
+    /// Along with each write.
+    WriteThrough,
+    /// When the key leaves the cache, or on [`DiskStorage::flush`].
+    WriteBack,
+}
+
+/// A [`Storage`] engine keeping the keys on disk and caching up to `capacity` of them. It is for
+/// embedders running commands through `Command::execute_on`; the server doesn't use it.
+pub struct DiskStorage {
+    tree: sled::Db,
+    cache: Backend,
+    capacity: usize,
+    policy: WritePolicy,
+    // taken for the whole of each operation, so that a key is never loaded twice nor evicted
+    // while in use
+    resident: Mutex<Resident>,
+}
+
+// The keys the cache holds the state of, whether they exist or were deleted. They leave it
+// oldest loaded first, but those used since they were last up for eviction go round once more.
+#[derive(Debug, Default)]
+struct Resident {
+    order: VecDeque<Bytes>,
+    // whether each key was used since it was last up for eviction
+    keys: HashMap<Bytes, bool>,
+    // changed since they were last written to the disk
+    dirty: HashSet<Bytes>,
+}
+
+impl DiskStorage {
+    /// Opens the database in the directory at `path`, creating it when there is none. A database
+    /// just closed in this process may still be locked by sled's threads for a moment, which
+    /// opening it again waits for, up to a second.
+    pub fn open(
+        path: impl AsRef<Path>,
+        capacity: usize,
+        policy: WritePolicy,
+    ) -> Result<Self, PersistenceError> {
+        let deadline = Instant::now() + LOCK_TIMEOUT;
+        let tree = loop {
+            match sled::open(path.as_ref()) {
+                Err(sled::Error::Io(e)) if is_locked(&e) && Instant::now() < deadline => {
+                    thread::sleep(Duration::from_millis(10))
+                }
+                res => break res.map_err(io::Error::from)?,
+            }
+        };
+        Ok(DiskStorage {
+            tree,
+            cache: Backend::new(),
+            capacity: capacity.max(1),
+            policy,
+            resident: Mutex::default(),
+        })
+    }
+
+    /// Writes the keys changed in the cache to the disk and waits until they are durable.
+    pub fn flush(&self) -> Result<(), PersistenceError> {
+        let mut resident = self.resident();
+        for key in std::mem::take(&mut resident.dirty) {
+            self.write_key(&key)?;
+        }
+        self.tree.flush().map_err(io::Error::from)?;
+        Ok(())
+    }
+
+    /// How many keys the cache holds the state of.
+    pub fn cached(&self) -> usize {
+        self.resident().keys.len()
+    }
+
+    fn resident(&self) -> MutexGuard<'_, Resident> {
+        self.resident.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Runs `op` against the cache once `key` is in it, `write` telling whether `op` may change
+    // the key.
+    fn with_key<T>(&self, key: &[u8], write: bool, op: impl FnOnce(&Backend) -> T) -> T {
+        let mut resident = self.resident();
+        self.load(&mut resident, key);
+        let res = op(&self.cache);
+        if write {
+            match self.policy {
+                WritePolicy::WriteThrough => {
+                    if let Err(e) = self.write_key(key) {
+                        warn!("Failed to write a key to the disk: {}", e);
+                        resident.dirty.insert(Bytes::copy_from_slice(key));
+                    }
+                }
+                WritePolicy::WriteBack => {
+                    resident.dirty.insert(Bytes::copy_from_slice(key));
+                }
+            }
+        }
+        self.evict(&mut resident);
+        res
+    }
+
+    // Brings `key` into the cache, from the disk unless the cache holds it already.
+    fn load(&self, resident: &mut Resident, key: &[u8]) {
+        if let Some(used) = resident.keys.get_mut(key) {
+            *used = true;
+            return;
+        }
+        let key = Bytes::copy_from_slice(key);
+        match self.read_key(&key) {
+            Ok(Some(entry)) => {
+                self.cache.restore_entries(vec![entry]);
+            }
+            Ok(None) => {}
+            Err(e) => warn!(
+                "Failed to read the key {} from the disk: {}",
+                String::from_utf8_lossy(&key),
+                e
+            ),
+        }
+        resident.keys.insert(key.clone(), false);
+        resident.order.push_back(key);
+    }
+
+    // Drops keys off the cache until it holds no more than its capacity, writing them to the
+    // disk first when they changed.
+    fn evict(&self, resident: &mut Resident) {
+        while resident.keys.len() > self.capacity {
+            let Some(key) = resident.order.pop_front() else {
+                break;
+            };
+            if let Some(used @ true) = resident.keys.get_mut(&key) {
+                *used = false;
+                resident.order.push_back(key);
+                continue;
+            }
+            if resident.dirty.contains(&key) {
+                if let Err(e) = self.write_key(&key) {
+                    // kept until it can be written
+                    warn!("Failed to write a key to the disk: {}", e);
+                    resident.order.push_back(key);
+                    break;
+                }
+                resident.dirty.remove(&key);
+            }
+            resident.keys.remove(&key);
+            self.cache.remove_key(&key);
+        }
+    }
+
+    fn read_key(&self, key: &Bytes) -> Result<Option<Entry>, PersistenceError> {
+        let Some(record) = self.tree.get(key).map_err(io::Error::from)? else {
+            return Ok(None);
+        };
+        let Some((header, data)) = record.split_first_chunk::<9>() else {
+            return Err(PersistenceError::Corrupt("truncated record".to_string()));
+        };
+        let expire_at = u64::from_be_bytes(header[..8].try_into().expect("eight bytes"));
+        Ok(Some(Entry {
+            db: 0,
+            key: key.clone(),
+            expire_at: (expire_at > 0).then_some(expire_at),
+            value: self.cache.decode_value(header[8], data)?,
+        }))
+    }
+
+    // Writes the value the cache holds for `key` to the disk, or deletes it there when the
+    // cache holds none.
+    fn write_key(&self, key: &[u8]) -> Result<(), PersistenceError> {
+        let db = self.cache.db();
+        let encoded = db.keyspace.get(key).and_then(|value| encode_value(&value));
+        match encoded {
+            Some((ty, data)) => {
+                let expire_at = db.expires.get(key).map(|at| *at).unwrap_or(0);
+                let mut record = Vec::with_capacity(9 + data.len());
+                record.extend_from_slice(&expire_at.to_be_bytes());
+                record.push(ty);
+                record.extend_from_slice(&data);
+                self.tree.insert(key, record).map_err(io::Error::from)?;
+            }
+            None => {
+                self.tree.remove(key).map_err(io::Error::from)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// sled reports the lock held on the database only through the message of the error.
+fn is_locked(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::WouldBlock || e.to_string().starts_with("could not acquire lock")
+}
+
+impl Drop for DiskStorage {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            warn!("Failed to flush the disk storage: {}", e);
+        }
+    }
+}
+
+impl Storage for DiskStorage {
+    fn get(&self, key: &[u8]) -> Result<Option<RespFrame>, WrongType> {
+        self.with_key(key, false, |cache| cache.get(key))
+    }
+
+    fn set(&self, key: Bytes, value: RespFrame) {
+        self.with_key(&key.clone(), true, |cache| cache.set(key, value))
+    }
+
+    fn del(&self, key: &[u8]) -> bool {
+        self.with_key(key, true, |cache| cache.del(key))
+    }
+
+    fn hget(&self, key: &[u8], field: &[u8]) -> Result<Option<RespFrame>, WrongType> {
+        self.with_key(key, false, |cache| cache.hget(key, field))
+    }
+
+    fn hset(&self, key: Bytes, field: Bytes, value: RespFrame) -> Result<bool, WrongType> {
+        self.with_key(&key.clone(), true, |cache| cache.hset(key, field, value))
+    }
+
+    fn hgetall(&self, key: &[u8]) -> Result<Option<Vec<(Bytes, RespFrame)>>, WrongType> {
+        self.with_key(key, false, |cache| cache.hgetall(key))
+    }
+
+    fn hdel(&self, key: &[u8], field: &[u8]) -> Result<bool, WrongType> {
+        self.with_key(key, true, |cache| cache.hdel(key, field))
+    }
+
+    fn sadd(&self, key: Bytes, member: RespFrame) -> Result<bool, WrongType> {
+        self.with_key(&key.clone(), true, |cache| cache.sadd(key, member))
+    }
+
+    fn srem(&self, key: &[u8], member: &RespFrame) -> Result<bool, WrongType> {
+        self.with_key(key, true, |cache| cache.srem(key, member))
+    }
+
+    fn sismember(&self, key: &[u8], member: &RespFrame) -> Result<bool, WrongType> {
+        self.with_key(key, false, |cache| cache.sismember(key, member))
+    }
+
+    fn smembers(&self, key: &[u8]) -> Result<Option<Vec<RespFrame>>, WrongType> {
+        self.with_key(key, false, |cache| cache.smembers(key))
+    }
+
+    fn push(
+        &self,
+        key: Bytes,
+        values: Vec<RespFrame>,
+        direction: ListDirection,
+    ) -> Result<usize, WrongType> {
+        self.with_key(&key.clone(), true, |cache| {
+            cache.push(key, values, direction)
+        })
+    }
+
+    fn pop(
+        &self,
+        key: &[u8],
+        count: usize,
+        direction: ListDirection,
+    ) -> Result<Option<Vec<RespFrame>>, WrongType> {
+        self.with_key(key, true, |cache| cache.pop(key, count, direction))
+    }
+
+    fn llen(&self, key: &[u8]) -> Result<usize, WrongType> {
+        self.with_key(key, false, |cache| cache.llen(key))
+    }
+
+    fn lrange(&self, key: &[u8], start: i64, stop: i64) -> Result<Vec<RespFrame>, WrongType> {
+        self.with_key(key, false, |cache| cache.lrange(key, start, stop))
+    }
+
+    fn expire(&self, key: &[u8], millis: i64) -> bool {
+        self.with_key(key, true, |cache| cache.expire(key, millis))
+    }
+
+    fn ttl(&self, key: &[u8]) -> Option<Option<u64>> {
+        self.with_key(key, false, |cache| cache.ttl(key))
+    }
+
+    fn persist(&self, key: &[u8]) -> bool {
+        self.with_key(key, true, |cache| cache.persist(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("simple-redis-disk-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn fill(storage: &DiskStorage) {
+        for i in 0..10 {
+            storage.set(
+                format!("key:{}", i).into(),
+                BulkString::from("value").into(),
+            );
+        }
+        storage
+            .push(
+                "list".into(),
+                vec![BulkString::from("a").into()],
+                ListDirection::Right,
+            )
+            .unwrap();
+        storage
+            .hset("hash".into(), "field".into(), BulkString::from("1").into())
+            .unwrap();
+        assert!(storage.expire(b"key:0", 100_000));
+        assert!(storage.del(b"key:1"));
+    }
+
+    fn check(storage: &DiskStorage) {
+        assert_eq!(
+            storage.get(b"key:9").unwrap(),
+            Some(BulkString::from("value").into())
+        );
+        assert_eq!(storage.get(b"key:1").unwrap(), None);
+        assert!(matches!(storage.ttl(b"key:0"), Some(Some(ttl)) if ttl > 90_000));
+        assert_eq!(storage.ttl(b"key:2"), Some(None));
+        assert_eq!(
+            storage.lrange(b"list", 0, -1).unwrap(),
+            vec![BulkString::from("a").into()]
+        );
+        assert_eq!(
+            storage.hget(b"hash", b"field").unwrap(),
+            Some(BulkString::from("1").into())
+        );
+        assert_eq!(storage.hget(b"list", b"field"), Err(WrongType));
+    }
+
+    #[test]
+    fn test_disk_storage_outgrows_cache() {
+        for policy in [WritePolicy::WriteThrough, WritePolicy::WriteBack] {
+            let dir = temp_dir(&format!("{:?}", policy));
+            let storage = DiskStorage::open(&dir, 4, policy).unwrap();
+            fill(&storage);
+            assert_eq!(storage.cached(), 4);
+            check(&storage);
+            drop(storage);
+
+            // reopened with nothing cached
+            let storage = DiskStorage::open(&dir, 4, policy).unwrap();
+            assert_eq!(storage.cached(), 0);
+            check(&storage);
+            drop(storage);
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[test]
+    fn test_write_back_defers_writes() {
+        let dir = temp_dir("defer");
+        let storage = DiskStorage::open(&dir, 16, WritePolicy::WriteBack).unwrap();
+        storage.set("key".into(), BulkString::from("value").into());
+        assert!(storage.tree.get(b"key").unwrap().is_none());
+        storage.flush().unwrap();
+        assert!(storage.tree.get(b"key").unwrap().is_some());
+
+        assert!(storage.del(b"key"));
+        // deleted in the cache, the disk still holding it, without it coming back
+        assert!(storage.tree.get(b"key").unwrap().is_some());
+        assert_eq!(storage.get(b"key").unwrap(), None);
+        storage.flush().unwrap();
+        assert!(storage.tree.get(b"key").unwrap().is_none());
+        drop(storage);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,173 @@
+use super::{db::Value, hash::ListPackLimits, Backend};
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+
+// defaults of the Redis hash-max-listpack-* settings
+const HASH_MAX_LISTPACK_ENTRIES: usize = 128;
+const HASH_MAX_LISTPACK_VALUE: usize = 64;
+const SET_MAX_INTSET_ENTRIES: usize = 512;
+// default of list-max-listpack-size, chunks of up to 8kb
+const LIST_MAX_LISTPACK_SIZE: i64 = -2;
+
+/// Size limits under which values are kept in their compact encodings.
+#[derive(Debug)]
+pub(super) struct Encoding {
+    hash_max_listpack_entries: AtomicUsize,
+    hash_max_listpack_value: AtomicUsize,
+    set_max_intset_entries: AtomicUsize,
+    list_max_listpack_size: AtomicI64,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Self {
+            hash_max_listpack_entries: AtomicUsize::new(HASH_MAX_LISTPACK_ENTRIES),
+            hash_max_listpack_value: AtomicUsize::new(HASH_MAX_LISTPACK_VALUE),
+            set_max_intset_entries: AtomicUsize::new(SET_MAX_INTSET_ENTRIES),
+            list_max_listpack_size: AtomicI64::new(LIST_MAX_LISTPACK_SIZE),
+        }
+    }
+}
+
+impl Backend {
+    pub fn hash_max_listpack_entries(&self) -> usize {
+        self.encoding
+            .hash_max_listpack_entries
+            .load(Ordering::Relaxed)
+    }
+
+    pub fn set_hash_max_listpack_entries(&self, entries: usize) {
+        self.encoding
+            .hash_max_listpack_entries
+            .store(entries, Ordering::Relaxed);
+    }
+
+    pub fn hash_max_listpack_value(&self) -> usize {
+        self.encoding
+            .hash_max_listpack_value
+            .load(Ordering::Relaxed)
+    }
+
+    pub fn set_hash_max_listpack_value(&self, bytes: usize) {
+        self.encoding
+            .hash_max_listpack_value
+            .store(bytes, Ordering::Relaxed);
+    }
+
+    pub fn set_max_intset_entries(&self) -> usize {
+        self.encoding.set_max_intset_entries.load(Ordering::Relaxed)
+    }
+
+    pub fn set_set_max_intset_entries(&self, entries: usize) {
+        self.encoding
+            .set_max_intset_entries
+            .store(entries, Ordering::Relaxed);
+    }
+
+    /// Fill factor of list chunks: a positive value caps their entries, -1 to -5 their size
+    /// from 4kb to 64kb.
+    pub fn list_max_listpack_size(&self) -> i64 {
+        self.encoding.list_max_listpack_size.load(Ordering::Relaxed)
+    }
+
+    pub fn set_list_max_listpack_size(&self, fill: i64) {
+        self.encoding
+            .list_max_listpack_size
+            .store(fill, Ordering::Relaxed);
+    }
+
+    /// Returns the internal encoding of the value at `key` as reported by OBJECT ENCODING.
+    pub fn object_encoding(&self, key: &[u8]) -> Option<&'static str> {
+        if self.expire_if_needed(key) {
+            return None;
+        }
+        Some(match &*self.db().keyspace.get(key)? {
+            Value::String(value) => value.encoding(),
+            Value::Hash(hash) => hash.encoding(),
+            Value::Set(set) => set.encoding(),
+            Value::List(list) => list.encoding(),
+            Value::ZSet(_) => "skiplist",
+            Value::Stream(_) => "stream",
+        })
+    }
+
+    pub(super) fn hash_limits(&self) -> ListPackLimits {
+        ListPackLimits {
+            max_entries: self.hash_max_listpack_entries(),
+            max_value: self.hash_max_listpack_value(),
+        }
+    }
+}
+
+/// Parses `bytes` as an integer only when formatting it back gives the same bytes, so that
+/// storing the number instead of the string loses nothing.
+pub(super) fn parse_integer(bytes: &[u8]) -> Option<i64> {
+    let n = std::str::from_utf8(bytes).ok()?.parse::<i64>().ok()?;
+    (n.to_string().as_bytes() == bytes).then_some(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::ListDirection, BulkString};
+
+    #[test]
+    fn test_object_encoding() {
+        let backend = Backend::new();
+        assert_eq!(backend.object_encoding(b"key"), None);
+        backend.set("key".into(), BulkString::from("12345").into());
+        assert_eq!(backend.object_encoding(b"key"), Some("int"));
+        backend.set("key".into(), BulkString::from("value").into());
+        assert_eq!(backend.object_encoding(b"key"), Some("embstr"));
+        backend.set("key".into(), BulkString::new(vec![b'a'; 45]).into());
+        assert_eq!(backend.object_encoding(b"key"), Some("raw"));
+
+        backend.set_hash_max_listpack_entries(2);
+        for i in 0..2 {
+            backend
+                .hset(
+                    "hash".into(),
+                    i.to_string().into(),
+                    BulkString::from("v").into(),
+                )
+                .unwrap();
+        }
+        assert_eq!(backend.object_encoding(b"hash"), Some("listpack"));
+        backend
+            .hset("hash".into(), "2".into(), BulkString::from("v").into())
+            .unwrap();
+        assert_eq!(backend.object_encoding(b"hash"), Some("hashtable"));
+
+        backend.set_hash_max_listpack_value(4);
+        backend
+            .hset("long".into(), "field".into(), BulkString::from("v").into())
+            .unwrap();
+        assert_eq!(backend.object_encoding(b"long"), Some("hashtable"));
+
+        backend.set("key".into(), BulkString::from("+1").into());
+        assert_eq!(backend.object_encoding(b"key"), Some("embstr"));
+
+        backend.set_list_max_listpack_size(2);
+        let values = vec![BulkString::from("a").into(), BulkString::from("b").into()];
+        backend
+            .push("list".into(), values, ListDirection::Right)
+            .unwrap();
+        assert_eq!(backend.object_encoding(b"list"), Some("listpack"));
+        backend
+            .push(
+                "list".into(),
+                vec![BulkString::from("c").into()],
+                ListDirection::Left,
+            )
+            .unwrap();
+        assert_eq!(backend.object_encoding(b"list"), Some("quicklist"));
+
+        backend
+            .sadd("set".into(), BulkString::from("1").into())
+            .unwrap();
+        assert_eq!(backend.object_encoding(b"set"), Some("intset"));
+        backend
+            .sadd("set".into(), BulkString::from("member").into())
+            .unwrap();
+        assert_eq!(backend.object_encoding(b"set"), Some("hashtable"));
+    }
+}
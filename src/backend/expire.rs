@@ -0,0 +1,160 @@
+// Key timeouts. A key is deleted once its timeout passed, when a command looks it up or when
+// the active expiry cycle, run ten times per second, samples it among the keys with a timeout.
+
+use super::Backend;
+use bytes::Bytes;
+use rand::seq::IteratorRandom;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+// keys with a timeout checked at once in a database
+const ACTIVE_EXPIRE_SAMPLES: usize = 20;
+// the time a cycle may take, a quarter of the interval between two
+const ACTIVE_EXPIRE_BUDGET: Duration = Duration::from_millis(25);
+
+#[derive(Debug)]
+pub(super) struct ActiveExpire {
+    // turned off by DEBUG SET-ACTIVE-EXPIRE, leaving the keys to expire when looked up
+    enabled: AtomicBool,
+}
+
+impl Default for ActiveExpire {
+    fn default() -> Self {
+        Self {
+            enabled: AtomicBool::new(true),
+        }
+    }
+}
+
+impl Backend {
+    /// Sets `key` to expire `millis` milliseconds from now, deleting it right away when the
+    /// timeout is not positive. Returns `false` when the key does not exist.
+    pub fn expire(&self, key: &[u8], millis: i64) -> bool {
+        self.expire_at(key, self.now_millis().saturating_add_signed(millis))
+    }
+
+    /// Sets `key` to expire at the unix time `at`, in milliseconds, deleting it right away
+    /// when that time has passed. Returns `false` when the key does not exist.
+    pub fn expire_at(&self, key: &[u8], at: u64) -> bool {
+        self.touch(key);
+        if !self.exists(key) {
+            return false;
+        }
+        if at <= self.now_millis() {
+            self.remove_key(key);
+        } else {
+            self.db().expires.insert(Bytes::copy_from_slice(key), at);
+        }
+        true
+    }
+
+    /// Returns `None` for a missing key, otherwise the milliseconds left before it expires, if
+    /// it has a timeout at all.
+    pub fn ttl(&self, key: &[u8]) -> Option<Option<u64>> {
+        self.touch(key);
+        if !self.exists(key) {
+            return None;
+        }
+        let at = self.db().expires.get(key).map(|at| *at);
+        Some(at.map(|at| at.saturating_sub(self.now_millis())))
+    }
+
+    /// Removes the timeout of `key`, reporting whether it had one.
+    pub fn persist(&self, key: &[u8]) -> bool {
+        self.touch(key);
+        self.db().expires.remove(key).is_some()
+    }
+
+    pub fn active_expire_enabled(&self) -> bool {
+        self.active_expire.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_active_expire(&self, enabled: bool) {
+        self.active_expire.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Deletes the expired keys among samples of the keys with a timeout, sampling a database
+    /// again while more than a quarter of its sample had expired, within a time budget.
+    /// Returns how many keys were deleted.
+    pub fn active_expire_cycle(&self) -> usize {
+        if !self.active_expire_enabled() {
+            return 0;
+        }
+        let started = Instant::now();
+        let mut expired = 0;
+        for db in self.all_dbs() {
+            loop {
+                let keys = db
+                    .db()
+                    .expires
+                    .iter()
+                    .choose_multiple(&mut rand::thread_rng(), ACTIVE_EXPIRE_SAMPLES)
+                    .into_iter()
+                    .map(|entry| entry.key().clone())
+                    .collect::<Vec<_>>();
+                let sampled = keys.len();
+                let deleted = keys.iter().filter(|key| db.expire_if_needed(key)).count();
+                expired += deleted;
+                if deleted * 4 <= sampled || started.elapsed() >= ACTIVE_EXPIRE_BUDGET {
+                    break;
+                }
+            }
+        }
+        self.record_latency("expire-cycle", started.elapsed());
+        expired
+    }
+
+    // Lazily deletes `key` once its timeout has passed, reporting whether it did so.
+    pub(super) fn expire_if_needed(&self, key: &[u8]) -> bool {
+        let expired = self
+            .db()
+            .expires
+            .get(key)
+            .is_some_and(|at| *at <= self.now_millis());
+        if expired {
+            self.remove_key(key);
+        }
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_expire_and_persist() {
+        let backend = Backend::new();
+        assert!(!backend.expire(b"key", 10_000));
+        assert_eq!(backend.ttl(b"key"), None);
+
+        backend.set("key".into(), BulkString::from("value").into());
+        assert_eq!(backend.ttl(b"key"), Some(None));
+        assert!(backend.expire(b"key", 10_000));
+        let ttl = backend.ttl(b"key").flatten().unwrap();
+        assert!(ttl > 9_000 && ttl <= 10_000);
+        assert!(backend.persist(b"key"));
+        assert!(!backend.persist(b"key"));
+        assert_eq!(backend.ttl(b"key"), Some(None));
+
+        // overwriting a key clears its timeout
+        backend.expire(b"key", 10_000);
+        backend.set("key".into(), BulkString::from("other").into());
+        assert_eq!(backend.ttl(b"key"), Some(None));
+
+        backend
+            .db()
+            .expires
+            .insert("key".into(), backend.now_millis() - 1);
+        assert_eq!(backend.get(b"key").unwrap(), None);
+        assert_eq!(backend.used_memory(), 0);
+        assert!(backend.db().expires.is_empty());
+
+        backend.set("key".into(), BulkString::from("value").into());
+        assert!(backend.expire(b"key", 0));
+        assert_eq!(backend.get(b"key").unwrap(), None);
+    }
+}
@@ -0,0 +1,201 @@
+// Functions. Libraries of functions written in Rust are registered by the embedding program,
+// then loaded with FUNCTION LOAD to be called with FCALL. Like scripts, they run with no other
+// command in between, and may run commands themselves.
+
+use super::Backend;
+use crate::{BulkString, RespFrame};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+type Callback = Arc<dyn Fn(&FunctionCall) -> RespFrame + Send + Sync>;
+
+/// A library of functions, registered with [`Backend::register_library`].
+#[derive(Clone)]
+pub struct FunctionLibrary {
+    name: String,
+    functions: Vec<(String, Callback)>,
+}
+
+/// What a function is called with: the keys and arguments given to FCALL, and a way to run
+/// commands as a script does.
+pub struct FunctionCall<'a> {
+    pub keys: &'a [BulkString],
+    pub args: &'a [BulkString],
+    runner: &'a dyn Fn(Vec<Vec<u8>>) -> RespFrame,
+}
+
+#[derive(Default)]
+pub(super) struct Functions {
+    registered: Mutex<HashMap<String, FunctionLibrary>>,
+    // by library name, sorted for FUNCTION LIST
+    loaded: Mutex<BTreeMap<String, FunctionLibrary>>,
+}
+
+impl FunctionLibrary {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            functions: vec![],
+        }
+    }
+
+    /// Adds a function to the library.
+    pub fn function<F>(mut self, name: impl Into<String>, callback: F) -> Self
+    where
+        F: Fn(&FunctionCall) -> RespFrame + Send + Sync + 'static,
+    {
+        self.functions.push((name.into(), Arc::new(callback)));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn function_names(&self) -> impl Iterator<Item = &str> {
+        self.functions.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+impl fmt::Debug for FunctionLibrary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionLibrary")
+            .field("name", &self.name)
+            .field("functions", &self.function_names().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl<'a> FunctionCall<'a> {
+    pub fn new(
+        keys: &'a [BulkString],
+        args: &'a [BulkString],
+        runner: &'a dyn Fn(Vec<Vec<u8>>) -> RespFrame,
+    ) -> Self {
+        Self { keys, args, runner }
+    }
+
+    /// Runs a command, given with its arguments, replying with its reply or error.
+    pub fn call(&self, args: &[&[u8]]) -> RespFrame {
+        (self.runner)(args.iter().map(|arg| arg.to_vec()).collect())
+    }
+}
+
+impl fmt::Debug for Functions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Functions")
+            .field("loaded", &self.loaded())
+            .finish()
+    }
+}
+
+impl Functions {
+    fn registered(&self) -> MutexGuard<'_, HashMap<String, FunctionLibrary>> {
+        self.registered.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn loaded(&self) -> MutexGuard<'_, BTreeMap<String, FunctionLibrary>> {
+        self.loaded.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Backend {
+    /// Makes a library available to FUNCTION LOAD, replacing the one registered with the same
+    /// name. A library already loaded keeps its former functions until loaded again.
+    pub fn register_library(&self, library: FunctionLibrary) {
+        self.functions
+            .registered()
+            .insert(library.name.clone(), library);
+    }
+
+    /// Loads the registered library `name`, making its functions callable.
+    pub fn load_library(&self, name: &str, replace: bool) -> Result<(), String> {
+        let Some(library) = self.functions.registered().get(name).cloned() else {
+            return Err(format!("ERR Library '{}' is not registered", name));
+        };
+        let mut loaded = self.functions.loaded();
+        if loaded.contains_key(name) && !replace {
+            return Err(format!("ERR Library '{}' already exists", name));
+        }
+        for function in library.function_names() {
+            let taken = loaded
+                .values()
+                .filter(|other| other.name != name)
+                .any(|other| other.function_names().any(|f| f == function));
+            if taken {
+                return Err(format!("ERR Function {} already exists", function));
+            }
+        }
+        loaded.insert(name.to_string(), library);
+        Ok(())
+    }
+
+    pub fn delete_library(&self, name: &str) -> Result<(), String> {
+        match self.functions.loaded().remove(name) {
+            Some(_) => Ok(()),
+            None => Err("ERR Library not found".to_string()),
+        }
+    }
+
+    /// The loaded libraries, by name.
+    pub fn loaded_libraries(&self) -> Vec<FunctionLibrary> {
+        self.functions.loaded().values().cloned().collect()
+    }
+
+    /// Calls the loaded function `name`, or returns `None` if there is none.
+    pub fn call_function(&self, name: &str, call: &FunctionCall) -> Option<RespFrame> {
+        let callback = self
+            .functions
+            .loaded()
+            .values()
+            .flat_map(|library| &library.functions)
+            .find(|(function, _)| function == name)
+            .map(|(_, callback)| callback.clone())?;
+        // not holding the lock, so the function may run FUNCTION commands
+        Some(callback(call))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(name: &str, function: &str) -> FunctionLibrary {
+        FunctionLibrary::new(name).function(function, |call| {
+            RespFrame::Integer((call.keys.len() + call.args.len()) as i64)
+        })
+    }
+
+    #[test]
+    fn test_load_library() {
+        let backend = Backend::new();
+        assert!(backend.load_library("lib", false).is_err());
+
+        backend.register_library(library("lib", "count"));
+        backend.register_library(library("other", "count"));
+        assert_eq!(backend.load_library("lib", false), Ok(()));
+        assert!(backend.load_library("lib", false).is_err());
+        assert_eq!(backend.load_library("lib", true), Ok(()));
+        assert_eq!(
+            backend.load_library("other", false),
+            Err("ERR Function count already exists".to_string())
+        );
+
+        let runner = |_: Vec<Vec<u8>>| RespFrame::Integer(0);
+        let keys = [BulkString::from("key")];
+        let call = FunctionCall::new(&keys, &[], &runner);
+        assert_eq!(
+            backend.call_function("count", &call),
+            Some(RespFrame::Integer(1))
+        );
+        assert_eq!(backend.call_function("missing", &call), None);
+
+        assert_eq!(backend.delete_library("lib"), Ok(()));
+        assert!(backend.delete_library("lib").is_err());
+        assert_eq!(backend.call_function("count", &call), None);
+        assert!(backend.loaded_libraries().is_empty());
+    }
+}
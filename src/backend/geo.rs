@@ -0,0 +1,160 @@
+// Geo positions are stored in sorted sets as 52-bit interleaved geohashes, the same encoding
+// Redis uses, so members close to each other get close scores.
+
+const GEO_STEP: u32 = 26;
+pub const LONGITUDE_MIN: f64 = -180.0;
+pub const LONGITUDE_MAX: f64 = 180.0;
+pub const LATITUDE_MIN: f64 = -85.051_128_78;
+pub const LATITUDE_MAX: f64 = 85.051_128_78;
+const EARTH_RADIUS_IN_METERS: f64 = 6_372_797.560_856;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum GeoUnit {
+    #[default]
+    Meters,
+    Kilometers,
+    Miles,
+    Feet,
+}
+
+impl GeoUnit {
+    pub fn parse(unit: &str) -> Option<Self> {
+        match unit.to_ascii_lowercase().as_str() {
+            "m" => Some(GeoUnit::Meters),
+            "km" => Some(GeoUnit::Kilometers),
+            "mi" => Some(GeoUnit::Miles),
+            "ft" => Some(GeoUnit::Feet),
+            _ => None,
+        }
+    }
+
+    pub fn to_meters(self) -> f64 {
+        match self {
+            GeoUnit::Meters => 1.0,
+            GeoUnit::Kilometers => 1000.0,
+            GeoUnit::Miles => 1609.34,
+            GeoUnit::Feet => 0.3048,
+        }
+    }
+}
+
+// The area covered by GEOSEARCH, with dimensions in meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    Radius(f64),
+    Box { width: f64, height: f64 },
+}
+
+impl GeoShape {
+    /// Returns the distance from `center` to `point` when the point lies inside the shape.
+    pub fn distance_within(&self, center: (f64, f64), point: (f64, f64)) -> Option<f64> {
+        let distance = geo_distance(center, point);
+        match *self {
+            GeoShape::Radius(radius) => (distance <= radius).then_some(distance),
+            GeoShape::Box { width, height } => {
+                // measure along the meridian of the center and the parallel of the point
+                let lat_distance = geo_distance((center.0, center.1), (center.0, point.1));
+                let lon_distance = geo_distance((center.0, point.1), point);
+                (lat_distance <= height / 2.0 && lon_distance <= width / 2.0).then_some(distance)
+            }
+        }
+    }
+}
+
+pub fn is_valid_position(longitude: f64, latitude: f64) -> bool {
+    (LONGITUDE_MIN..=LONGITUDE_MAX).contains(&longitude)
+        && (LATITUDE_MIN..=LATITUDE_MAX).contains(&latitude)
+}
+
+pub fn geohash_encode(longitude: f64, latitude: f64) -> u64 {
+    let scale = (1_u64 << GEO_STEP) as f64;
+    let lat = ((latitude - LATITUDE_MIN) / (LATITUDE_MAX - LATITUDE_MIN) * scale) as u64;
+    let lon = ((longitude - LONGITUDE_MIN) / (LONGITUDE_MAX - LONGITUDE_MIN) * scale) as u64;
+    // clamp the upper edge of the range into the last cell
+    let max = (1 << GEO_STEP) - 1;
+    interleave(lat.min(max), lon.min(max))
+}
+
+/// Returns the `(longitude, latitude)` at the center of the geohash cell.
+pub fn geohash_decode(hash: u64) -> (f64, f64) {
+    let (lat, lon) = deinterleave(hash);
+    let scale = (1_u64 << GEO_STEP) as f64;
+    let lat_step = (LATITUDE_MAX - LATITUDE_MIN) / scale;
+    let lon_step = (LONGITUDE_MAX - LONGITUDE_MIN) / scale;
+    let latitude = LATITUDE_MIN + (lat as f64 + 0.5) * lat_step;
+    let longitude = LONGITUDE_MIN + (lon as f64 + 0.5) * lon_step;
+    (
+        longitude.clamp(LONGITUDE_MIN, LONGITUDE_MAX),
+        latitude.clamp(LATITUDE_MIN, LATITUDE_MAX),
+    )
+}
+
+/// Great-circle distance in meters between two `(longitude, latitude)` points.
+pub fn geo_distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lon1, lat1) = (from.0.to_radians(), from.1.to_radians());
+    let (lon2, lat2) = (to.0.to_radians(), to.1.to_radians());
+    let u = ((lat2 - lat1) / 2.0).sin();
+    let v = ((lon2 - lon1) / 2.0).sin();
+    2.0 * EARTH_RADIUS_IN_METERS * (u * u + lat1.cos() * lat2.cos() * v * v).sqrt().asin()
+}
+
+// Latitude bits take the even positions and longitude bits the odd ones.
+fn interleave(lat: u64, lon: u64) -> u64 {
+    (0..GEO_STEP).fold(0, |hash, i| {
+        hash | ((lat >> i) & 1) << (2 * i) | ((lon >> i) & 1) << (2 * i + 1)
+    })
+}
+
+fn deinterleave(hash: u64) -> (u64, u64) {
+    (0..GEO_STEP).fold((0, 0), |(lat, lon), i| {
+        (
+            lat | ((hash >> (2 * i)) & 1) << i,
+            lon | ((hash >> (2 * i + 1)) & 1) << i,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geohash_round_trip() {
+        let hash = geohash_encode(13.361389, 38.115556);
+        // the score Redis stores for Palermo in the GEOADD docs example
+        assert_eq!(hash, 3479099956230698);
+        let (longitude, latitude) = geohash_decode(hash);
+        assert!((longitude - 13.361389).abs() < 1e-5);
+        assert!((latitude - 38.115556).abs() < 1e-5);
+
+        let (longitude, latitude) = geohash_decode(geohash_encode(180.0, LATITUDE_MAX));
+        assert!(is_valid_position(longitude, latitude));
+    }
+
+    #[test]
+    fn test_geo_distance() {
+        // distances are measured between the stored cell centers, as GEODIST does
+        let palermo = geohash_decode(geohash_encode(13.361389, 38.115556));
+        let catania = geohash_decode(geohash_encode(15.087269, 37.502669));
+        let distance = geo_distance(palermo, catania);
+        assert!((distance - 166274.1516).abs() < 0.01, "{}", distance);
+        assert!(GeoShape::Radius(200_000.0)
+            .distance_within(palermo, catania)
+            .is_some());
+        assert!(GeoShape::Radius(100_000.0)
+            .distance_within(palermo, catania)
+            .is_none());
+        let square = GeoShape::Box {
+            width: 400_000.0,
+            height: 400_000.0,
+        };
+        assert!(square.distance_within(palermo, catania).is_some());
+        let flat = GeoShape::Box {
+            width: 400_000.0,
+            height: 10_000.0,
+        };
+        assert!(flat.distance_within(palermo, catania).is_none());
+        assert_eq!(GeoUnit::parse("KM"), Some(GeoUnit::Kilometers));
+        assert_eq!(GeoUnit::parse("yards"), None);
+    }
+}
@@ -0,0 +1,69 @@
+/// Redis-style glob matching supporting `*`, `?`, `[...]` classes and `\` escapes.
+pub fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => {
+            let rest = rest
+                .iter()
+                .position(|&c| c != b'*')
+                .map_or(&[][..], |i| &rest[i..]);
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        Some((b'?', rest)) => !text.is_empty() && glob_match(rest, &text[1..]),
+        Some((b'[', rest)) => {
+            let Some((&c, text_rest)) = text.split_first() else {
+                return false;
+            };
+            let (negate, mut class) = match rest.first() {
+                Some(b'^') => (true, &rest[1..]),
+                _ => (false, rest),
+            };
+            let mut matched = false;
+            loop {
+                match class {
+                    [] => break,
+                    [b']', tail @ ..] => {
+                        class = tail;
+                        break;
+                    }
+                    [b'\\', escaped, tail @ ..] => {
+                        matched |= *escaped == c;
+                        class = tail;
+                    }
+                    [lo, b'-', hi, tail @ ..] if *hi != b']' => {
+                        let (lo, hi) = if lo <= hi { (lo, hi) } else { (hi, lo) };
+                        matched |= (*lo..=*hi).contains(&c);
+                        class = tail;
+                    }
+                    [x, tail @ ..] => {
+                        matched |= *x == c;
+                        class = tail;
+                    }
+                }
+            }
+            matched != negate && glob_match(class, text_rest)
+        }
+        Some((b'\\', [escaped, rest @ ..])) => {
+            text.first() == Some(escaped) && glob_match(rest, &text[1..])
+        }
+        Some((c, rest)) => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*", b""));
+        assert!(glob_match(b"h?llo", b"hello"));
+        assert!(glob_match(b"h*llo", b"heeeello"));
+        assert!(glob_match(b"h[ae]llo", b"hallo"));
+        assert!(!glob_match(b"h[^e]llo", b"hello"));
+        assert!(glob_match(b"h[a-b]llo", b"hbllo"));
+        assert!(glob_match(b"h\\*llo", b"h*llo"));
+        assert!(!glob_match(b"h\\*llo", b"hello"));
+        assert!(!glob_match(b"user:*", b"session:1"));
+    }
+}
@@ -0,0 +1,151 @@
+// Small hashes are kept as a flat vector of field/value pairs, which costs far less memory than
+// a hash table for a handful of entries. A hash is converted for good once it grows past the
+// configured limits, as Redis does with its listpack encoding.
+
+use bytes::Bytes;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Hash {
+    ListPack(Vec<(Bytes, Bytes)>),
+    HashTable(HashMap<Bytes, Bytes>),
+}
+
+/// Largest hash, in entries and in bytes per field or value, kept in the listpack encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListPackLimits {
+    pub max_entries: usize,
+    pub max_value: usize,
+}
+
+impl Default for Hash {
+    fn default() -> Self {
+        Hash::ListPack(vec![])
+    }
+}
+
+impl Hash {
+    pub fn len(&self) -> usize {
+        match self {
+            Hash::ListPack(entries) => entries.len(),
+            Hash::HashTable(table) => table.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        match self {
+            Hash::ListPack(entries) => entries.capacity(),
+            Hash::HashTable(table) => table.capacity(),
+        }
+    }
+
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Hash::ListPack(_) => "listpack",
+            Hash::HashTable(_) => "hashtable",
+        }
+    }
+
+    pub fn get(&self, field: &[u8]) -> Option<&Bytes> {
+        match self {
+            Hash::ListPack(entries) => entries.iter().find(|(f, _)| f == field).map(|(_, v)| v),
+            Hash::HashTable(table) => table.get(field),
+        }
+    }
+
+    /// Sets `field`, returning `true` when it was not in the hash before.
+    pub fn insert(&mut self, field: Bytes, value: Bytes, limits: ListPackLimits) -> bool {
+        if let Hash::ListPack(entries) = self {
+            if let Some(entry) = entries.iter_mut().find(|(f, _)| *f == field) {
+                entry.1 = value;
+                self.convert_if_needed(None, limits);
+                return false;
+            }
+            entries.push((field, value));
+            let last = entries.last().map(|(f, v)| (f.len(), v.len()));
+            self.convert_if_needed(last, limits);
+            return true;
+        }
+        match self {
+            Hash::HashTable(table) => table.insert(field, value).is_none(),
+            Hash::ListPack(_) => unreachable!("listpack handled above"),
+        }
+    }
+
+    pub fn remove(&mut self, field: &[u8]) -> bool {
+        match self {
+            Hash::ListPack(entries) => match entries.iter().position(|(f, _)| f == field) {
+                Some(index) => {
+                    entries.remove(index);
+                    true
+                }
+                None => false,
+            },
+            Hash::HashTable(table) => table.remove(field).is_some(),
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (&Bytes, &Bytes)> + '_> {
+        match self {
+            Hash::ListPack(entries) => Box::new(entries.iter().map(|(f, v)| (f, v))),
+            Hash::HashTable(table) => Box::new(table.iter()),
+        }
+    }
+
+    // Switches to the hash table encoding when the hash holds too many entries or the entry
+    // just written is too long.
+    fn convert_if_needed(&mut self, written: Option<(usize, usize)>, limits: ListPackLimits) {
+        let Hash::ListPack(entries) = self else {
+            return;
+        };
+        let too_long = match written {
+            Some((field, value)) => field > limits.max_value || value > limits.max_value,
+            None => entries
+                .iter()
+                .any(|(f, v)| f.len() > limits.max_value || v.len() > limits.max_value),
+        };
+        if too_long || entries.len() > limits.max_entries {
+            *self = Hash::HashTable(std::mem::take(entries).into_iter().collect());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: ListPackLimits = ListPackLimits {
+        max_entries: 4,
+        max_value: 8,
+    };
+
+    #[test]
+    fn test_hash_listpack_conversion() {
+        let mut hash = Hash::default();
+        for i in 0..4 {
+            assert!(hash.insert(i.to_string().into(), Bytes::from("v"), LIMITS));
+        }
+        assert!(!hash.insert("0".into(), Bytes::from("w"), LIMITS));
+        assert_eq!(hash.encoding(), "listpack");
+        assert_eq!(hash.get(b"0"), Some(&Bytes::from("w")));
+        assert!(hash.remove(b"3"));
+        assert!(!hash.remove(b"3"));
+        assert_eq!(hash.len(), 3);
+
+        // a long value converts the hash even when updating an existing field
+        hash.insert("0".into(), Bytes::from("too long value"), LIMITS);
+        assert_eq!(hash.encoding(), "hashtable");
+        assert_eq!(hash.get(b"1"), Some(&Bytes::from("v")));
+
+        let mut hash = Hash::default();
+        for i in 0..5 {
+            hash.insert(i.to_string().into(), Bytes::from("v"), LIMITS);
+        }
+        assert_eq!(hash.encoding(), "hashtable");
+        assert_eq!(hash.iter().count(), 5);
+    }
+}
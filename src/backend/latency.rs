@@ -0,0 +1,186 @@
+// Latency monitoring. The sources of latency time what they do and, once latency monitoring is
+// on, record the spikes at or above latency-monitor-threshold under the name of the event, as
+// LATENCY reports them. An event keeps one sample per second, the highest, for the last
+// LATENCY_SAMPLES seconds it had spikes in.
+
+use super::Backend;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::Duration,
+};
+
+// as in Redis
+const LATENCY_SAMPLES: usize = 160;
+
+#[derive(Debug, Default)]
+pub(super) struct Latency {
+    // in milliseconds, 0 turning the monitoring off
+    threshold: AtomicUsize,
+    events: Mutex<HashMap<String, LatencyEvent>>,
+}
+
+#[derive(Debug, Default)]
+struct LatencyEvent {
+    samples: VecDeque<LatencySample>,
+    // the highest latency recorded, older samples included
+    max: u64,
+}
+
+/// A latency spike of an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencySample {
+    /// unix time in seconds
+    pub time: u64,
+    /// in milliseconds
+    pub latency: u64,
+}
+
+/// The state of an event, as LATENCY LATEST reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyLatest {
+    pub event: String,
+    pub latest: LatencySample,
+    pub max: u64,
+}
+
+impl Latency {
+    fn events(&self) -> MutexGuard<'_, HashMap<String, LatencyEvent>> {
+        self.events.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Backend {
+    pub fn latency_monitor_threshold(&self) -> usize {
+        self.latency.threshold.load(Ordering::Relaxed)
+    }
+
+    pub fn set_latency_monitor_threshold(&self, millis: usize) {
+        self.latency.threshold.store(millis, Ordering::Relaxed);
+    }
+
+    /// Records that `event` took `elapsed`, if latency monitoring is on and that is at or
+    /// above the threshold.
+    pub fn record_latency(&self, event: &str, elapsed: Duration) {
+        let threshold = self.latency_monitor_threshold();
+        let latency = elapsed.as_millis() as u64;
+        if threshold == 0 || latency < threshold as u64 {
+            return;
+        }
+        let time = self.now().as_secs();
+        let mut events = self.latency.events();
+        let event = events.entry(event.to_string()).or_default();
+        event.max = event.max.max(latency);
+        match event.samples.back_mut() {
+            Some(last) if last.time == time => last.latency = last.latency.max(latency),
+            _ => {
+                if event.samples.len() == LATENCY_SAMPLES {
+                    event.samples.pop_front();
+                }
+                event.samples.push_back(LatencySample { time, latency });
+            }
+        }
+    }
+
+    /// The latest spike and the highest latency of every event with spikes, by name.
+    pub fn latency_latest(&self) -> Vec<LatencyLatest> {
+        let mut latest: Vec<LatencyLatest> = self
+            .latency
+            .events()
+            .iter()
+            .filter_map(|(name, event)| {
+                Some(LatencyLatest {
+                    event: name.clone(),
+                    latest: *event.samples.back()?,
+                    max: event.max,
+                })
+            })
+            .collect();
+        latest.sort_by(|a, b| a.event.cmp(&b.event));
+        latest
+    }
+
+    /// The spikes of `event`, oldest first.
+    pub fn latency_history(&self, event: &str) -> Vec<LatencySample> {
+        self.latency
+            .events()
+            .get(event)
+            .map(|event| event.samples.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Forgets the spikes of `events`, or of every event when empty, returning the number of
+    /// events that had some.
+    pub fn latency_reset(&self, events: &[String]) -> usize {
+        let mut recorded = self.latency.events();
+        if events.is_empty() {
+            let count = recorded.len();
+            recorded.clear();
+            return count;
+        }
+        events
+            .iter()
+            .filter(|event| recorded.remove(event.as_str()).is_some())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockClock;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_record_latency() {
+        let clock = Arc::new(MockClock::new(Duration::from_secs(1_000)));
+        let backend = Backend::with_clock(clock.clone());
+        let ms = Duration::from_millis;
+        // off by default
+        backend.record_latency("command", ms(500));
+        assert!(backend.latency_latest().is_empty());
+
+        backend.set_latency_monitor_threshold(100);
+        backend.record_latency("command", ms(99));
+        backend.record_latency("command", ms(150));
+        backend.record_latency("command", ms(120));
+        clock.advance(Duration::from_secs(1));
+        backend.record_latency("command", ms(110));
+        backend.record_latency("fork", ms(300));
+        let sample = |time, latency| LatencySample { time, latency };
+        assert_eq!(
+            backend.latency_history("command"),
+            [sample(1_000, 150), sample(1_001, 110)]
+        );
+        assert_eq!(
+            backend.latency_latest(),
+            [
+                LatencyLatest {
+                    event: "command".to_string(),
+                    latest: sample(1_001, 110),
+                    max: 150,
+                },
+                LatencyLatest {
+                    event: "fork".to_string(),
+                    latest: sample(1_001, 300),
+                    max: 300,
+                },
+            ]
+        );
+
+        for _ in 0..LATENCY_SAMPLES {
+            clock.advance(Duration::from_secs(1));
+            backend.record_latency("command", ms(100));
+        }
+        let history = backend.latency_history("command");
+        assert_eq!(history.len(), LATENCY_SAMPLES);
+        assert_eq!(history[0], sample(1_002, 100));
+
+        assert_eq!(backend.latency_reset(&["fork".into(), "missing".into()]), 1);
+        assert_eq!(backend.latency_reset(&[]), 1);
+        assert!(backend.latency_history("command").is_empty());
+    }
+}
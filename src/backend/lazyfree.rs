@@ -0,0 +1,182 @@
+use super::Backend;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Sender},
+        Arc, OnceLock,
+    },
+    thread,
+};
+
+// containers with more elements than this are dropped in the background, as in Redis
+const LAZYFREE_THRESHOLD: usize = 64;
+
+type Garbage = Box<dyn Send>;
+
+#[derive(Debug)]
+pub(super) struct LazyFree {
+    threshold: AtomicUsize,
+    // values handed to the background thread that it has not dropped yet
+    pending: Arc<AtomicUsize>,
+    // started on first use so backends that never free large values don't own a thread
+    sender: OnceLock<Sender<Garbage>>,
+}
+
+impl Default for LazyFree {
+    fn default() -> Self {
+        Self {
+            threshold: AtomicUsize::new(LAZYFREE_THRESHOLD),
+            pending: Arc::default(),
+            sender: OnceLock::new(),
+        }
+    }
+}
+
+impl LazyFree {
+    fn sender(&self) -> &Sender<Garbage> {
+        self.sender.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel::<Garbage>();
+            let pending = self.pending.clone();
+            thread::Builder::new()
+                .name("lazyfree".to_string())
+                .spawn(move || {
+                    for garbage in receiver {
+                        drop(garbage);
+                        pending.fetch_sub(1, Ordering::Relaxed);
+                    }
+                })
+                .expect("failed to spawn the lazyfree thread");
+            sender
+        })
+    }
+}
+
+impl Backend {
+    /// Element count above which deleted containers are dropped in the background; zero
+    /// disables lazy freeing.
+    pub fn lazyfree_threshold(&self) -> usize {
+        self.lazyfree.threshold.load(Ordering::Relaxed)
+    }
+
+    pub fn set_lazyfree_threshold(&self, elements: usize) {
+        self.lazyfree.threshold.store(elements, Ordering::Relaxed);
+    }
+
+    /// Number of deleted values still waiting to be dropped in the background.
+    pub fn lazyfree_pending(&self) -> usize {
+        self.lazyfree.pending.load(Ordering::Relaxed)
+    }
+
+    /// Deletes every key of the database, dropping all values in the background when `lazy`
+    /// is set.
+    pub fn flushdb(&self, lazy: bool) {
+        for key in self.all_keys() {
+            self.remove_key_with(&key, lazy);
+        }
+    }
+
+    /// Deletes every key of every database.
+    pub fn flushall(&self, lazy: bool) {
+        for db in self.all_dbs() {
+            db.flushdb(lazy);
+        }
+    }
+
+    /// Deletes `key` whatever the type of its value, reporting whether it existed.
+    pub(super) fn remove_key(&self, key: &[u8]) -> bool {
+        self.remove_key_with(key, false)
+    }
+
+    fn remove_key_with(&self, key: &[u8], lazy: bool) -> bool {
+        let removed = self.db().keyspace.remove(key);
+        let existed = removed.is_some();
+        if let Some((_, value)) = removed {
+            self.free(value.len(), value, lazy);
+        }
+        self.written(key);
+        existed
+    }
+
+    /// Drops a value that was taken out of the keyspace, handing it to the background thread
+    /// when it has enough elements for the deallocation to stall the caller.
+    pub(super) fn free<T: Send + 'static>(&self, elements: usize, value: T, lazy: bool) {
+        let threshold = self.lazyfree_threshold();
+        if !lazy && (threshold == 0 || elements <= threshold) {
+            return;
+        }
+        self.lazyfree.pending.fetch_add(1, Ordering::Relaxed);
+        if self.lazyfree.sender().send(Box::new(value)).is_err() {
+            self.lazyfree.pending.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+    use std::time::{Duration, Instant};
+
+    fn wait_for_lazyfree(backend: &Backend) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while backend.lazyfree_pending() > 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(backend.lazyfree_pending(), 0);
+    }
+
+    #[test]
+    fn test_lazyfree_large_values() {
+        let backend = Backend::new();
+        backend.set_lazyfree_threshold(10);
+        for i in 0..100 {
+            backend
+                .hset(
+                    "big".into(),
+                    i.to_string().into(),
+                    BulkString::from("value").into(),
+                )
+                .unwrap();
+        }
+        backend
+            .hset(
+                "small".into(),
+                "field".into(),
+                BulkString::from("value").into(),
+            )
+            .unwrap();
+        assert!(backend.remove_key(b"small"));
+        assert!(backend.lazyfree.sender.get().is_none());
+
+        assert!(backend.remove_key(b"big"));
+        assert!(!backend.remove_key(b"big"));
+        assert_eq!(backend.hget(b"big", b"0").unwrap(), None);
+        assert_eq!(backend.used_memory(), 0);
+        wait_for_lazyfree(&backend);
+    }
+
+    #[test]
+    fn test_flushdb() {
+        let backend = Backend::new();
+        for i in 0..10 {
+            backend.set(
+                format!("key:{}", i).into(),
+                BulkString::from("value").into(),
+            );
+            backend
+                .sadd(
+                    format!("set:{}", i).into(),
+                    BulkString::from("member").into(),
+                )
+                .unwrap();
+        }
+        backend.flushdb(false);
+        assert_eq!(backend.used_memory(), 0);
+        assert!(backend.lazyfree.sender.get().is_none());
+
+        backend.set("key".into(), BulkString::from("value").into());
+        backend.flushdb(true);
+        assert_eq!(backend.get(b"key").unwrap(), None);
+        wait_for_lazyfree(&backend);
+    }
+}
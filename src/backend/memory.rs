@@ -0,0 +1,740 @@
+use super::{
+    clock::unix_millis, db::Value, string::StringValue, Backend, Hash, QuickList, Set, Stream, ZSet,
+};
+use bytes::Bytes;
+use rand::seq::IteratorRandom;
+use std::{
+    mem::size_of,
+    ops::Bound,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        RwLock,
+    },
+};
+
+// Rough per-allocation overheads on a 64-bit target; the figures only need to be in the right
+// ballpark for eviction decisions.
+const KEY_OVERHEAD: usize = 56;
+const ENTRY_OVERHEAD: usize = 32;
+// elements sampled per collection when a write refreshes the size of a key
+const TRACKING_SAMPLES: usize = 5;
+// default number of keys sampled per eviction round
+const MAXMEMORY_SAMPLES: usize = 5;
+// frequency given to new keys so they are not evicted before they had a chance to be used
+const LFU_INIT_VAL: u8 = 5;
+const LFU_LOG_FACTOR: usize = 10;
+// minutes a key has to sit idle for its frequency counter to drop by one
+const LFU_DECAY_TIME: usize = 1;
+// names of the value types in the order of the variants of `Value`, as reported by MEMORY STATS
+const VALUE_TYPES: [&str; 6] = ["strings", "hashes", "sets", "lists", "zsets", "streams"];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    #[default]
+    NoEviction,
+    AllKeysLru,
+    AllKeysLfu,
+    AllKeysRandom,
+    VolatileLru,
+    VolatileLfu,
+    VolatileTtl,
+    VolatileRandom,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct KeyStats {
+    // bookkeeping bytes for the key itself
+    overhead: usize,
+    // bytes held by the value of the key, in the slot of its type
+    sizes: [usize; VALUE_TYPES.len()],
+    last_access: u64,
+    // logarithmic access counter, see `lfu_increment`
+    frequency: u8,
+}
+
+#[derive(Debug)]
+pub(super) struct Memory {
+    used: AtomicUsize,
+    peak: AtomicUsize,
+    overhead: AtomicUsize,
+    datasets: [AtomicUsize; VALUE_TYPES.len()],
+    maxmemory: AtomicUsize,
+    policy: RwLock<EvictionPolicy>,
+    samples: AtomicUsize,
+    lfu_log_factor: AtomicUsize,
+    lfu_decay_time: AtomicUsize,
+}
+
+/// A snapshot of the memory accounting, as reported by MEMORY STATS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub used: usize,
+    pub peak: usize,
+    pub maxmemory: usize,
+    /// Bytes spent on keys rather than on the values they hold.
+    pub overhead: usize,
+    pub keys: usize,
+    /// Bytes held by the values of each type, keyed by type name.
+    pub datasets: Vec<(&'static str, usize)>,
+}
+
+impl KeyStats {
+    fn size(&self) -> usize {
+        self.overhead + self.sizes.iter().sum::<usize>()
+    }
+}
+
+impl Default for KeyStats {
+    fn default() -> Self {
+        Self {
+            overhead: 0,
+            sizes: [0; VALUE_TYPES.len()],
+            last_access: unix_millis(),
+            frequency: LFU_INIT_VAL,
+        }
+    }
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self {
+            used: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            overhead: AtomicUsize::new(0),
+            datasets: Default::default(),
+            maxmemory: AtomicUsize::new(0),
+            policy: RwLock::default(),
+            samples: AtomicUsize::new(MAXMEMORY_SAMPLES),
+            lfu_log_factor: AtomicUsize::new(LFU_LOG_FACTOR),
+            lfu_decay_time: AtomicUsize::new(LFU_DECAY_TIME),
+        }
+    }
+}
+
+impl EvictionPolicy {
+    pub fn parse(policy: &str) -> Option<Self> {
+        match policy.to_ascii_lowercase().as_str() {
+            "noeviction" => Some(EvictionPolicy::NoEviction),
+            "allkeys-lru" => Some(EvictionPolicy::AllKeysLru),
+            "allkeys-lfu" => Some(EvictionPolicy::AllKeysLfu),
+            "allkeys-random" => Some(EvictionPolicy::AllKeysRandom),
+            "volatile-lru" => Some(EvictionPolicy::VolatileLru),
+            "volatile-lfu" => Some(EvictionPolicy::VolatileLfu),
+            "volatile-ttl" => Some(EvictionPolicy::VolatileTtl),
+            "volatile-random" => Some(EvictionPolicy::VolatileRandom),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EvictionPolicy::NoEviction => "noeviction",
+            EvictionPolicy::AllKeysLru => "allkeys-lru",
+            EvictionPolicy::AllKeysLfu => "allkeys-lfu",
+            EvictionPolicy::AllKeysRandom => "allkeys-random",
+            EvictionPolicy::VolatileLru => "volatile-lru",
+            EvictionPolicy::VolatileLfu => "volatile-lfu",
+            EvictionPolicy::VolatileTtl => "volatile-ttl",
+            EvictionPolicy::VolatileRandom => "volatile-random",
+        }
+    }
+
+    pub fn is_lfu(&self) -> bool {
+        matches!(
+            self,
+            EvictionPolicy::AllKeysLfu | EvictionPolicy::VolatileLfu
+        )
+    }
+
+    // Volatile policies only evict keys that have a timeout set.
+    fn is_volatile(&self) -> bool {
+        matches!(
+            self,
+            EvictionPolicy::VolatileLru
+                | EvictionPolicy::VolatileLfu
+                | EvictionPolicy::VolatileTtl
+                | EvictionPolicy::VolatileRandom
+        )
+    }
+
+    fn is_random(&self) -> bool {
+        matches!(
+            self,
+            EvictionPolicy::AllKeysRandom | EvictionPolicy::VolatileRandom
+        )
+    }
+}
+
+impl Backend {
+    /// Approximate number of bytes held by all keys.
+    pub fn used_memory(&self) -> usize {
+        self.memory.used.load(Ordering::Relaxed)
+    }
+
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            used: self.used_memory(),
+            peak: self.memory.peak.load(Ordering::Relaxed),
+            maxmemory: self.maxmemory(),
+            overhead: self.memory.overhead.load(Ordering::Relaxed),
+            keys: self.all_dbs().map(|db| db.db().keys.len()).sum(),
+            datasets: VALUE_TYPES
+                .iter()
+                .zip(&self.memory.datasets)
+                .map(|(name, bytes)| (*name, bytes.load(Ordering::Relaxed)))
+                .collect(),
+        }
+    }
+
+    pub fn maxmemory(&self) -> usize {
+        self.memory.maxmemory.load(Ordering::Relaxed)
+    }
+
+    pub fn set_maxmemory(&self, bytes: usize) {
+        self.memory.maxmemory.store(bytes, Ordering::Relaxed);
+    }
+
+    pub fn eviction_policy(&self) -> EvictionPolicy {
+        *self
+            .memory
+            .policy
+            .read()
+            .expect("eviction policy lock poisoned")
+    }
+
+    pub fn set_eviction_policy(&self, policy: EvictionPolicy) {
+        *self
+            .memory
+            .policy
+            .write()
+            .expect("eviction policy lock poisoned") = policy;
+    }
+
+    /// Number of keys looked at to pick each eviction victim.
+    pub fn maxmemory_samples(&self) -> usize {
+        self.memory.samples.load(Ordering::Relaxed)
+    }
+
+    pub fn set_maxmemory_samples(&self, samples: usize) {
+        self.memory.samples.store(samples.max(1), Ordering::Relaxed);
+    }
+
+    pub fn lfu_log_factor(&self) -> usize {
+        self.memory.lfu_log_factor.load(Ordering::Relaxed)
+    }
+
+    pub fn set_lfu_log_factor(&self, factor: usize) {
+        self.memory.lfu_log_factor.store(factor, Ordering::Relaxed);
+    }
+
+    pub fn lfu_decay_time(&self) -> usize {
+        self.memory.lfu_decay_time.load(Ordering::Relaxed)
+    }
+
+    pub fn set_lfu_decay_time(&self, minutes: usize) {
+        self.memory.lfu_decay_time.store(minutes, Ordering::Relaxed);
+    }
+
+    /// Returns the access frequency counter of `key` without counting this lookup as an access.
+    pub fn object_freq(&self, key: &[u8]) -> Option<u8> {
+        if self.expire_if_needed(key) {
+            return None;
+        }
+        let stats = *self.db().keys.get(key)?;
+        Some(self.lfu_decay(&stats))
+    }
+
+    /// Returns the seconds since `key` was last accessed, without counting this lookup as one.
+    pub fn object_idletime(&self, key: &[u8]) -> Option<u64> {
+        if self.expire_if_needed(key) {
+            return None;
+        }
+        let stats = *self.db().keys.get(key)?;
+        Some(unix_millis().saturating_sub(stats.last_access) / 1000)
+    }
+
+    /// Estimates the bytes held by `key` without counting this lookup as an access.
+    pub fn memory_usage(&self, key: &[u8], samples: usize) -> Option<usize> {
+        if self.expire_if_needed(key) {
+            return None;
+        }
+        match self.key_size(key, samples) {
+            0 => None,
+            size => Some(size),
+        }
+    }
+
+    /// Evicts keys until memory use is under `maxmemory`, returning `false` when that is not
+    /// possible and the pending write has to be refused.
+    pub fn evict_to_fit(&self) -> bool {
+        let maxmemory = self.maxmemory();
+        if maxmemory == 0 {
+            return true;
+        }
+        while self.used_memory() > maxmemory {
+            let policy = self.eviction_policy();
+            if policy == EvictionPolicy::NoEviction {
+                return false;
+            }
+            match self.sample_victim(policy) {
+                Some((db, key)) => {
+                    db.remove_key(&key);
+                }
+                None => return false,
+            }
+        }
+        true
+    }
+
+    // Approximates LRU, LFU and TTL ordering like Redis: the worst of a few random keys of
+    // each database goes first. Random policies simply take a single sample. Returns the
+    // database holding the victim along with it.
+    fn sample_victim(&self, policy: EvictionPolicy) -> Option<(Backend, Bytes)> {
+        let mut rng = rand::thread_rng();
+        let samples = if policy.is_random() {
+            1
+        } else {
+            self.maxmemory_samples()
+        };
+        let mut candidates = vec![];
+        for db in self.all_dbs() {
+            let keys = if policy.is_volatile() {
+                db.db()
+                    .expires
+                    .iter()
+                    .choose_multiple(&mut rng, samples)
+                    .into_iter()
+                    .map(|entry| entry.key().clone())
+                    .collect::<Vec<_>>()
+            } else {
+                db.db()
+                    .keys
+                    .iter()
+                    .choose_multiple(&mut rng, samples)
+                    .into_iter()
+                    .map(|entry| entry.key().clone())
+                    .collect::<Vec<_>>()
+            };
+            for key in keys {
+                let Some(stats) = db.db().keys.get(&key).map(|stats| *stats) else {
+                    continue;
+                };
+                let at = db.db().expires.get(&key).map(|at| *at);
+                candidates.push((db.clone(), key, stats, at));
+            }
+        }
+        if policy.is_random() {
+            // one sample per database, of which any will do
+            return candidates
+                .into_iter()
+                .choose(&mut rng)
+                .map(|(db, key, _, _)| (db, key));
+        }
+        candidates
+            .into_iter()
+            .min_by_key(|(_, _, stats, at)| match policy {
+                EvictionPolicy::AllKeysLfu | EvictionPolicy::VolatileLfu => {
+                    (self.lfu_decay(stats) as u64, stats.last_access)
+                }
+                EvictionPolicy::VolatileTtl => (at.unwrap_or(u64::MAX), stats.last_access),
+                _ => (0, stats.last_access),
+            })
+            .map(|(db, key, _, _)| (db, key))
+    }
+
+    pub(super) fn all_keys(&self) -> Vec<Bytes> {
+        self.db()
+            .keys
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// Records an access to `key` for LRU and LFU bookkeeping, deleting it first if it has
+    /// expired. Must not be called while holding a guard into the keyspace.
+    pub(super) fn touch(&self, key: &[u8]) {
+        if self.expire_if_needed(key) {
+            return;
+        }
+        self.db().accessed(key);
+        if let Some(mut stats) = self.db().keys.get_mut(key) {
+            stats.frequency = self.lfu_increment(self.lfu_decay(&stats));
+            stats.last_access = unix_millis();
+        }
+    }
+
+    pub(super) fn exists(&self, key: &[u8]) -> bool {
+        self.db().keys.contains_key(key)
+    }
+
+    /// Refreshes the accounted size of `key` after a write. Must not be called while holding a
+    /// guard into the keyspace.
+    pub(super) fn written(&self, key: &[u8]) {
+        self.db().wrote(key);
+        self.mark_dirty();
+        self.invalidate(key);
+        self.touch_watched(key);
+        let Some(sizes) = self.type_sizes(key, TRACKING_SAMPLES) else {
+            if let Some((_, stats)) = self.db().keys.remove(key) {
+                let removed = KeyStats {
+                    overhead: 0,
+                    sizes: [0; VALUE_TYPES.len()],
+                    ..stats
+                };
+                self.account(&stats, &removed);
+            }
+            self.db().expires.remove(key);
+            return;
+        };
+        let mut stats = self
+            .db()
+            .keys
+            .entry(Bytes::copy_from_slice(key))
+            .or_default();
+        let updated = KeyStats {
+            overhead: KEY_OVERHEAD + key.len(),
+            sizes,
+            last_access: unix_millis(),
+            ..*stats
+        };
+        self.account(&stats, &updated);
+        *stats = updated;
+    }
+
+    // Moves the global counters from the old to the new size of a key.
+    fn account(&self, before: &KeyStats, after: &KeyStats) {
+        adjust(&self.memory.used, before.size(), after.size());
+        adjust(&self.memory.overhead, before.overhead, after.overhead);
+        for (i, dataset) in self.memory.datasets.iter().enumerate() {
+            adjust(dataset, before.sizes[i], after.sizes[i]);
+        }
+        self.memory
+            .peak
+            .fetch_max(self.used_memory(), Ordering::Relaxed);
+    }
+
+    /// Estimates the bytes held by `key`, looking at up to `samples` elements of a collection
+    /// (all of them when `samples` is zero).
+    pub(super) fn key_size(&self, key: &[u8], samples: usize) -> usize {
+        self.type_sizes(key, samples)
+            .map(|sizes| KEY_OVERHEAD + key.len() + sizes.iter().sum::<usize>())
+            .unwrap_or(0)
+    }
+
+    // Returns the size of the value stored under `key` in the slot of its type, or `None` if
+    // there is none.
+    fn type_sizes(&self, key: &[u8], samples: usize) -> Option<[usize; VALUE_TYPES.len()]> {
+        let value = self.db().keyspace.get(key)?;
+        let (dataset, size) = match &*value {
+            Value::String(v) => (0, v.memory_size(samples)),
+            Value::Hash(v) => (1, v.memory_size(samples)),
+            Value::Set(v) => (2, v.memory_size(samples)),
+            Value::List(v) => (3, v.memory_size(samples)),
+            Value::ZSet(v) => (4, v.memory_size(samples)),
+            Value::Stream(v) => (5, v.memory_size(samples)),
+        };
+        let mut sizes = [0; VALUE_TYPES.len()];
+        sizes[dataset] = size;
+        Some(sizes)
+    }
+
+    // Bumps the counter with a probability that shrinks as it grows, so the eight bits cover
+    // anything from a handful to millions of accesses.
+    fn lfu_increment(&self, frequency: u8) -> u8 {
+        if frequency == u8::MAX {
+            return frequency;
+        }
+        let base = frequency.saturating_sub(LFU_INIT_VAL) as f64;
+        let probability = 1.0 / (base * self.lfu_log_factor() as f64 + 1.0);
+        if rand::random::<f64>() < probability {
+            frequency + 1
+        } else {
+            frequency
+        }
+    }
+
+    // Takes one off the counter for every `lfu-decay-time` minutes the key sat idle.
+    fn lfu_decay(&self, stats: &KeyStats) -> u8 {
+        let decay_time = self.lfu_decay_time() as u64;
+        if decay_time == 0 {
+            return stats.frequency;
+        }
+        let periods = unix_millis().saturating_sub(stats.last_access) / 60_000 / decay_time;
+        stats
+            .frequency
+            .saturating_sub(periods.min(u8::MAX as u64) as u8)
+    }
+}
+
+/// Estimates the heap footprint of a stored value, extrapolating collections from their first
+/// `samples` elements (all of them when `samples` is zero).
+trait MemorySize {
+    fn memory_size(&self, samples: usize) -> usize;
+}
+
+impl MemorySize for Bytes {
+    fn memory_size(&self, _samples: usize) -> usize {
+        size_of::<Bytes>() + self.len()
+    }
+}
+
+impl MemorySize for StringValue {
+    fn memory_size(&self, _samples: usize) -> usize {
+        // integers live in the value itself
+        size_of::<Self>()
+            + match self {
+                StringValue::Bytes(bytes) => bytes.len(),
+                StringValue::Int(_) => 0,
+                #[cfg(feature = "compression")]
+                StringValue::Compressed(compressed) => compressed.len(),
+            }
+    }
+}
+
+impl MemorySize for Hash {
+    fn memory_size(&self, samples: usize) -> usize {
+        // both encodings store their pairs inline, the hash table with a control byte per slot
+        // and spare slots to keep its load factor down
+        let slot = size_of::<(String, Bytes)>()
+            + match self {
+                Hash::ListPack(_) => 0,
+                Hash::HashTable(_) => 1,
+            };
+        let sizes = self.iter().map(|(field, value)| field.len() + value.len());
+        size_of::<Self>() + self.capacity() * slot + sampled_size(self.len(), sizes, samples)
+    }
+}
+
+impl MemorySize for Set {
+    fn memory_size(&self, samples: usize) -> usize {
+        match self {
+            Set::IntSet(members) => size_of::<Self>() + members.capacity() * size_of::<i64>(),
+            Set::HashTable(members) => {
+                let sizes = members.iter().map(Bytes::len);
+                size_of::<Self>()
+                    + self.capacity() * (size_of::<Bytes>() + 1)
+                    + sampled_size(self.len(), sizes, samples)
+            }
+        }
+    }
+}
+
+impl MemorySize for QuickList {
+    fn memory_size(&self, samples: usize) -> usize {
+        // chunks store their elements inline, so only their payload is counted per element
+        let sizes = self.iter().map(Bytes::len);
+        size_of::<Self>()
+            + self.chunk_count() * ENTRY_OVERHEAD
+            + self.capacity() * size_of::<Bytes>()
+            + sampled_size(self.len(), sizes, samples)
+    }
+}
+
+impl MemorySize for ZSet {
+    fn memory_size(&self, samples: usize) -> usize {
+        // every member is kept both in the score map and in the ordered index
+        let sizes = self
+            .iter_by_score(Bound::Unbounded, Bound::Unbounded)
+            .map(|(member, _)| 2 * (member.len() + size_of::<f64>() + ENTRY_OVERHEAD));
+        size_of::<Self>() + sampled_size(self.len(), sizes, samples)
+    }
+}
+
+impl MemorySize for Stream {
+    fn memory_size(&self, samples: usize) -> usize {
+        let sizes = self.iter().map(|(_, fields)| {
+            let fields = fields
+                .iter()
+                .map(|(field, value)| field.len() + value.memory_size(samples))
+                .sum::<usize>();
+            fields + ENTRY_OVERHEAD
+        });
+        size_of::<Self>() + sampled_size(self.len(), sizes, samples)
+    }
+}
+
+fn adjust(counter: &AtomicUsize, before: usize, after: usize) {
+    if after >= before {
+        counter.fetch_add(after - before, Ordering::Relaxed);
+    } else {
+        counter.fetch_sub(before - after, Ordering::Relaxed);
+    }
+}
+
+// Extrapolates the size of a collection from the sizes of its first `samples` elements.
+fn sampled_size(len: usize, sizes: impl Iterator<Item = usize>, samples: usize) -> usize {
+    let samples = if samples == 0 { len } else { samples };
+    let (count, total) = sizes
+        .take(samples)
+        .fold((0, 0), |(count, total), size| (count + 1, total + size));
+    (total * len).checked_div(count).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::ListDirection, BulkString};
+
+    #[test]
+    fn test_memory_accounting() {
+        let backend = Backend::new();
+        backend.set("key".into(), BulkString::from("value").into());
+        let used = backend.used_memory();
+        assert!(used > "key".len() + "value".len());
+        backend.set("key".into(), BulkString::new(vec![0; 1000]).into());
+        assert!(backend.used_memory() >= used + 995);
+        backend.del(b"key");
+        assert_eq!(backend.used_memory(), 0);
+    }
+
+    #[test]
+    fn test_memory_stats() {
+        let backend = Backend::new();
+        backend.set("string".into(), BulkString::new(vec![0; 1000]).into());
+        backend
+            .hset(
+                "hash".into(),
+                "field".into(),
+                BulkString::from("value").into(),
+            )
+            .unwrap();
+        let stats = backend.memory_stats();
+        assert_eq!(stats.keys, 2);
+        assert_eq!(
+            stats.overhead,
+            2 * KEY_OVERHEAD + "string".len() + "hash".len()
+        );
+        let datasets = stats.datasets.iter().map(|(_, bytes)| bytes).sum::<usize>();
+        assert_eq!(stats.used, stats.overhead + datasets);
+        assert!(stats.datasets[0].1 > 1000);
+        assert!(stats.datasets[1].1 > 0);
+        assert_eq!(stats.datasets[2..].iter().map(|(_, b)| b).sum::<usize>(), 0);
+
+        backend.del(b"string");
+        let stats = backend.memory_stats();
+        assert_eq!(stats.datasets[0].1, 0);
+        assert!(stats.peak > stats.used + 1000);
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let backend = Backend::new();
+        assert_eq!(backend.memory_usage(b"list", 0), None);
+        let values = (0..100)
+            .map(|i| BulkString::new(vec![0; if i < 5 { 10 } else { 1000 }]).into())
+            .collect();
+        backend
+            .push("list".into(), values, ListDirection::Right)
+            .unwrap();
+        let exact = backend.memory_usage(b"list", 0).unwrap();
+        assert!(exact > 95 * 1000);
+        // sampling only the small head elements underestimates the list
+        assert!(backend.memory_usage(b"list", 5).unwrap() < exact / 10);
+
+        backend
+            .hset(
+                "hash".into(),
+                "field".into(),
+                BulkString::from("value").into(),
+            )
+            .unwrap();
+        let hash = backend.memory_usage(b"hash", 5).unwrap();
+        assert!(hash > size_of::<Hash>() + "fieldvalue".len());
+    }
+
+    #[test]
+    fn test_evict_to_fit() {
+        let backend = Backend::new();
+        for i in 0..10 {
+            backend.set(
+                format!("key:{}", i).into(),
+                BulkString::new(vec![0; 100]).into(),
+            );
+        }
+        backend.set_maxmemory(backend.used_memory() / 2);
+        assert!(!backend.evict_to_fit());
+        assert_eq!(backend.db().keys.len(), 10);
+
+        backend.set_eviction_policy(EvictionPolicy::AllKeysLru);
+        assert!(backend.evict_to_fit());
+        assert!(backend.used_memory() <= backend.maxmemory());
+        assert!(backend.db().keys.len() < 10);
+        assert_eq!(
+            backend.db().keys.len(),
+            (0..10)
+                .filter(|i| backend
+                    .get(format!("key:{}", i).as_bytes())
+                    .unwrap()
+                    .is_some())
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_evict_lfu() {
+        let backend = Backend::new();
+        for i in 0..4 {
+            backend.set(
+                format!("key:{}", i).into(),
+                BulkString::new(vec![0; 100]).into(),
+            );
+        }
+        // every access counts with a zero log factor, keeping the test deterministic
+        backend.set_lfu_log_factor(0);
+        for _ in 0..10 {
+            backend.get(b"key:0").unwrap();
+        }
+        assert!(backend.object_freq(b"key:0").unwrap() > LFU_INIT_VAL);
+
+        // only keys with a timeout are candidates for volatile policies
+        backend.set_eviction_policy(EvictionPolicy::VolatileLfu);
+        backend.set_maxmemory(backend.used_memory() - 1);
+        assert!(!backend.evict_to_fit());
+        backend.expire(b"key:0", 10_000);
+        assert!(backend.evict_to_fit());
+        assert_eq!(backend.get(b"key:0").unwrap(), None);
+
+        // with every key sampled, the least frequently used one goes first
+        backend.set_eviction_policy(EvictionPolicy::AllKeysLfu);
+        for _ in 0..10 {
+            backend.get(b"key:1").unwrap();
+            backend.get(b"key:2").unwrap();
+        }
+        backend.set_maxmemory(backend.used_memory() - 1);
+        assert!(backend.evict_to_fit());
+        assert_eq!(backend.get(b"key:3").unwrap(), None);
+        assert!(backend.get(b"key:1").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_evict_volatile_ttl_and_random() {
+        let backend = Backend::new();
+        for i in 0..4 {
+            backend.set(
+                format!("key:{}", i).into(),
+                BulkString::new(vec![0; 100]).into(),
+            );
+        }
+        backend.expire(b"key:1", 20_000);
+        backend.expire(b"key:2", 10_000);
+        backend.set_maxmemory_samples(10);
+        backend.set_eviction_policy(EvictionPolicy::VolatileTtl);
+        backend.set_maxmemory(backend.used_memory() - 1);
+        assert!(backend.evict_to_fit());
+        assert_eq!(backend.get(b"key:2").unwrap(), None);
+        assert!(backend.get(b"key:1").unwrap().is_some());
+
+        backend.set_eviction_policy(EvictionPolicy::VolatileRandom);
+        backend.set_maxmemory(backend.used_memory() - 1);
+        assert!(backend.evict_to_fit());
+        assert_eq!(backend.get(b"key:1").unwrap(), None);
+        // no volatile keys are left to evict
+        backend.set_maxmemory(backend.used_memory() - 1);
+        assert!(!backend.evict_to_fit());
+
+        backend.set_eviction_policy(EvictionPolicy::AllKeysRandom);
+        backend.set_maxmemory(1);
+        assert!(backend.evict_to_fit());
+        assert_eq!(backend.used_memory(), 0);
+    }
+}
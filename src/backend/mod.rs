@@ -1,81 +1,846 @@
-use crate::RespFrame;
-use dashmap::{DashMap, DashSet};
+mod aof;
+mod auth;
+mod bitmap;
+mod client;
+mod clock;
+mod cluster;
+#[cfg(feature = "compression")]
+mod compression;
+mod crc16;
+mod crc64;
+mod db;
+#[cfg(feature = "disk")]
+mod disk;
+mod encoding;
+mod expire;
+mod function;
+pub mod geo;
+mod glob;
+mod hash;
+mod hyperloglog;
+mod latency;
+mod lazyfree;
+mod memory;
+mod persistence;
+mod pubsub;
+mod quicklist;
+mod rdb;
+mod replication;
+mod scripting;
+mod set;
+mod shutdown;
+mod storage;
+mod stream;
+mod string;
+mod tracking;
+mod transaction;
+mod zset;
+
+use self::{
+    db::Value,
+    string::{frame_bytes, StringValue},
+};
+use crate::{BulkString, RespFrame};
+use bytes::Bytes;
 use derive_more::Deref;
-use std::sync::Arc;
+use std::{ops::Bound, sync::Arc};
+use tokio::sync::{futures::Notified, Notify};
+
+pub use self::aof::AppendFsync;
+pub use self::bitmap::{BitFieldOp, BitFieldOverflow, BitFieldType, BitRangeUnit};
+pub use self::client::{ClientClass, ClientInfo, OutputBufferLimit};
+pub use self::clock::{Clock, MockClock, SystemClock};
+pub use self::cluster::{key_slot, CLUSTER_SLOTS};
+pub use self::db::{ShardStats, WrongType, DEFAULT_DATABASES};
+#[cfg(feature = "disk")]
+pub use self::disk::{DiskStorage, WritePolicy};
+pub use self::function::{FunctionCall, FunctionLibrary};
+pub use self::geo::{GeoShape, GeoUnit};
+pub use self::glob::glob_match;
+pub use self::hash::Hash;
+pub use self::hyperloglog::HyperLogLog;
+pub use self::latency::LatencyLatest;
+pub use self::memory::{EvictionPolicy, MemoryStats};
+pub use self::persistence::{PersistenceError, SaveRule, SnapshotFormat};
+pub use self::pubsub::Subscriber;
+pub use self::quicklist::QuickList;
+pub use self::replication::{FailoverState, FullSync, MasterAddr, ReplicationTls, SyncSnapshot};
+pub use self::set::Set;
+pub use self::storage::Storage;
+pub use self::stream::{Stream, StreamFields, StreamId, StreamIdSpec, StreamTrim, TrimStrategy};
+pub use self::string::IncrError;
+pub use self::tracking::TrackingOptions;
+pub use self::zset::{Aggregate, ZAddFlags, ZAddOutcome, ZSet, ZSetOperation};
 
-#[derive(Debug, Clone, Deref, Default)]
-pub struct Backend(Arc<BackendInner>);
+#[derive(Debug, Clone, Deref)]
+pub struct Backend {
+    #[deref]
+    inner: Arc<BackendInner>,
+    // the database the commands run against, selected by the connection holding the handle
+    db: usize,
+}
 
 #[derive(Debug, Default)]
 pub struct BackendInner {
-    map: DashMap<String, RespFrame>,
-    hmap: DashMap<String, DashMap<String, RespFrame>>,
-    set: DashMap<String, DashSet<RespFrame>>,
+    // the keyspaces of the numbered databases
+    dbs: db::Databases,
+    // wakes up clients blocked on list commands whenever elements are pushed
+    list_notify: Notify,
+    // memory accounting and eviction settings, across the databases
+    memory: memory::Memory,
+    // drops large deleted values off the command path
+    lazyfree: lazyfree::LazyFree,
+    // thresholds for the compact encodings of small values
+    encoding: encoding::Encoding,
+    #[cfg(feature = "compression")]
+    compression: compression::Compression,
+    // dump file location and background save state
+    persistence: persistence::Persistence,
+    // append-only log of the write commands
+    aof: aof::Aof,
+    // role, replication stream and connected replicas
+    replication: replication::Replication,
+    // hash slots served by this node in cluster mode
+    cluster: cluster::Cluster,
+    // the password connections authenticate with
+    auth: auth::Auth,
+    // what the server knows of each connection
+    clients: client::Clients,
+    // channels and patterns the connections are subscribed to
+    pubsub: pubsub::PubSub,
+    // keys cached by the clients that turned tracking on
+    tracking: tracking::Tracking,
+    // lock isolating transactions, and the keys watched by the connections
+    transactions: transaction::Transactions,
+    // bodies of the Lua scripts by SHA1
+    scripts: scripting::Scripts,
+    // libraries of native functions, registered and loaded
+    functions: function::Functions,
+    // the time TIME reports and the key timeouts go by
+    clock: clock::ServerClock,
+    // whether the expired keys are deleted before being looked up
+    active_expire: expire::ActiveExpire,
+    // the latency spikes of the event sources
+    latency: latency::Latency,
+    // whether increments may go past 64 bits for RESP3 clients
+    bignum_incr: string::BignumIncr,
+    // signaled by SHUTDOWN for the server to exit
+    shutdown: shutdown::Shutdown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListDirection {
+    Left,
+    Right,
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Backend {
     pub fn new() -> Self {
-        Self::default()
+        Self::with_databases(DEFAULT_DATABASES)
+    }
+
+    /// A backend with `databases` empty databases, at least one.
+    pub fn with_databases(databases: usize) -> Self {
+        Self::with_shards(databases, 0)
+    }
+
+    /// A backend with `databases` empty databases whose keyspaces are striped over `shards`
+    /// shards, rounded up to a power of two, or as many as dashmap picks when zero.
+    pub fn with_shards(databases: usize, shards: usize) -> Self {
+        let inner = BackendInner {
+            dbs: db::Databases::new(databases.max(1), shards),
+            ..Default::default()
+        };
+        Self {
+            inner: Arc::new(inner),
+            db: 0,
+        }
+    }
+
+    pub fn get(&self, key: &[u8]) -> Result<Option<RespFrame>, WrongType> {
+        self.touch(key);
+        Ok(self.db().get::<StringValue>(key)?.map(|v| v.to_frame()))
+    }
+
+    /// Sets `key` to the string `value`, replacing whatever value it held.
+    pub fn set(&self, key: Bytes, value: RespFrame) {
+        self.touch(&key);
+        self.db().expires.remove(&key);
+        let value = self.string_value(frame_bytes(value));
+        if let Some(old) = self.db().keyspace.insert(key.clone(), Value::String(value)) {
+            self.free(old.len(), old, false);
+        }
+        self.written(&key);
+    }
+
+    pub fn del(&self, key: &[u8]) -> bool {
+        self.touch(key);
+        self.remove_key(key)
+    }
+
+    pub fn getbit(&self, key: &[u8], offset: usize) -> Result<bool, WrongType> {
+        self.touch(key);
+        Ok(self
+            .db()
+            .get::<StringValue>(key)?
+            .map(|v| bitmap::get_bit(&v.bytes(), offset))
+            .unwrap_or(false))
+    }
+
+    pub fn setbit(&self, key: Bytes, offset: usize, on: bool) -> Result<bool, WrongType> {
+        self.touch(&key);
+        let old = {
+            let mut value = self
+                .db()
+                .get_or_insert_with(key.clone(), || StringValue::Bytes(Bytes::new()))?;
+            value.update_bytes(|bytes| bitmap::set_bit(bytes, offset, on))
+        };
+        self.written(&key);
+        Ok(old)
+    }
+
+    pub fn bitfield(&self, key: Bytes, ops: &[BitFieldOp]) -> Result<Vec<Option<i64>>, WrongType> {
+        self.touch(&key);
+        // read-only calls must not create the key
+        if ops.iter().all(|op| matches!(op, BitFieldOp::Get { .. })) {
+            let value = self.db().get::<StringValue>(&key)?;
+            let bytes = value.map(|v| v.bytes()).unwrap_or_default();
+            return Ok(ops
+                .iter()
+                .map(|op| match *op {
+                    BitFieldOp::Get { ty, offset } => Some(bitmap::read_field(&bytes, ty, offset)),
+                    _ => unreachable!("only GET operations reach here"),
+                })
+                .collect());
+        }
+        let results = {
+            let mut value = self
+                .db()
+                .get_or_insert_with(key.clone(), || StringValue::Bytes(Bytes::new()))?;
+            value.update_bytes(|bytes| {
+                ops.iter()
+                    .map(|op| bitmap::apply_bitfield(bytes, *op))
+                    .collect()
+            })
+        };
+        self.written(&key);
+        Ok(results)
+    }
+
+    pub fn bitcount(
+        &self,
+        key: &[u8],
+        range: Option<(i64, i64, BitRangeUnit)>,
+    ) -> Result<usize, WrongType> {
+        self.touch(key);
+        let Some(value) = self.db().get::<StringValue>(key)? else {
+            return Ok(0);
+        };
+        let bytes = value.bytes();
+        Ok(match range {
+            None => bitmap::count_bits(&bytes),
+            Some((start, end, BitRangeUnit::Byte)) => {
+                zset::normalize_range(start, end, bytes.len())
+                    .map(|(start, end)| bitmap::count_bits(&bytes[start..=end]))
+                    .unwrap_or(0)
+            }
+            Some((start, end, BitRangeUnit::Bit)) => {
+                zset::normalize_range(start, end, bytes.len() * 8)
+                    .map(|(start, end)| bitmap::count_bits_in_range(&bytes, start, end))
+                    .unwrap_or(0)
+            }
+        })
+    }
+
+    /// Adds elements to a HyperLogLog, returning `None` when the key holds some other string.
+    pub fn pfadd(&self, key: Bytes, elements: &[Bytes]) -> Result<Option<bool>, WrongType> {
+        self.touch(&key);
+        let mut created = false;
+        let changed = {
+            let mut value = self.db().get_or_insert_with(key.clone(), || {
+                created = true;
+                StringValue::Bytes(HyperLogLog::default().to_bytes().into())
+            })?;
+            let Some(mut hll) = parse_hyperloglog(&value) else {
+                return Ok(None);
+            };
+            let mut changed = false;
+            for element in elements {
+                changed |= hll.add(element);
+            }
+            if changed {
+                *value = StringValue::Bytes(hll.to_bytes().into());
+            }
+            changed
+        };
+        self.written(&key);
+        Ok(Some(created || changed))
+    }
+
+    pub fn pfcount(&self, keys: &[Bytes]) -> Result<Option<u64>, WrongType> {
+        let mut merged = HyperLogLog::default();
+        for key in keys {
+            self.touch(key);
+            if let Some(value) = self.db().get::<StringValue>(key)? {
+                let Some(hll) = parse_hyperloglog(&value) else {
+                    return Ok(None);
+                };
+                merged.merge(&hll);
+            }
+        }
+        Ok(Some(merged.count()))
+    }
+
+    pub fn pfmerge(&self, destination: Bytes, sources: &[Bytes]) -> Result<Option<()>, WrongType> {
+        self.touch(&destination);
+        // read the sources first so no two shard locks are held at the same time
+        let mut merged = HyperLogLog::default();
+        for key in sources {
+            self.touch(key);
+            if let Some(value) = self.db().get::<StringValue>(key)? {
+                let Some(hll) = parse_hyperloglog(&value) else {
+                    return Ok(None);
+                };
+                merged.merge(&hll);
+            }
+        }
+        {
+            let mut value = self.db().get_or_insert_with(destination.clone(), || {
+                StringValue::Bytes(HyperLogLog::default().to_bytes().into())
+            })?;
+            let Some(hll) = parse_hyperloglog(&value) else {
+                return Ok(None);
+            };
+            merged.merge(&hll);
+            *value = StringValue::Bytes(merged.to_bytes().into());
+        }
+        self.written(&destination);
+        Ok(Some(()))
+    }
+
+    pub fn hget(&self, key: &[u8], field: &[u8]) -> Result<Option<RespFrame>, WrongType> {
+        self.touch(key);
+        Ok(self
+            .db()
+            .get::<Hash>(key)?
+            .and_then(|v| v.get(field).map(bulk_string)))
+    }
+
+    /// Sets `field` in the hash at `key`, returning `true` when the field is new.
+    pub fn hset(&self, key: Bytes, field: Bytes, value: RespFrame) -> Result<bool, WrongType> {
+        self.touch(&key);
+        let limits = self.hash_limits();
+        let added = self.db().get_or_default::<Hash>(key.clone())?.insert(
+            field,
+            frame_bytes(value),
+            limits,
+        );
+        self.written(&key);
+        Ok(added)
+    }
+
+    pub fn hgetall(&self, key: &[u8]) -> Result<Option<Vec<(Bytes, RespFrame)>>, WrongType> {
+        self.touch(key);
+        Ok(self.db().get::<Hash>(key)?.map(|v| {
+            v.iter()
+                .map(|(field, value)| (field.clone(), bulk_string(value)))
+                .collect()
+        }))
+    }
+
+    pub fn hdel(&self, key: &[u8], field: &[u8]) -> Result<bool, WrongType> {
+        self.touch(key);
+        let removed = self
+            .db()
+            .get_mut::<Hash>(key)?
+            .map(|mut v| v.remove(field))
+            .unwrap_or(false);
+        self.db().remove_if_empty(key);
+        self.written(key);
+        Ok(removed)
+    }
+
+    pub fn sadd(&self, key: Bytes, member: RespFrame) -> Result<bool, WrongType> {
+        self.touch(&key);
+        let max_intset_entries = self.set_max_intset_entries();
+        let added = self
+            .db()
+            .get_or_default::<Set>(key.clone())?
+            .insert(frame_bytes(member), max_intset_entries);
+        self.written(&key);
+        Ok(added)
+    }
+
+    pub fn srem(&self, key: &[u8], member: &RespFrame) -> Result<bool, WrongType> {
+        self.touch(key);
+        let removed = self
+            .db()
+            .get_mut::<Set>(key)?
+            .map(|mut v| v.remove(&frame_bytes(member.clone())))
+            .unwrap_or(false);
+        self.db().remove_if_empty(key);
+        self.written(key);
+        Ok(removed)
+    }
+
+    pub fn sismember(&self, key: &[u8], member: &RespFrame) -> Result<bool, WrongType> {
+        self.touch(key);
+        Ok(self
+            .db()
+            .get::<Set>(key)?
+            .map(|v| v.contains(&frame_bytes(member.clone())))
+            .unwrap_or(false))
+    }
+
+    pub fn smembers(&self, key: &[u8]) -> Result<Option<Vec<RespFrame>>, WrongType> {
+        self.touch(key);
+        Ok(self
+            .db()
+            .get::<Set>(key)?
+            .map(|v| v.iter().map(|member| bulk_string(&member)).collect()))
     }
 
-    pub fn get(&self, key: &str) -> Option<RespFrame> {
-        self.map.get(key).map(|v| v.value().clone())
+    pub fn push(
+        &self,
+        key: Bytes,
+        values: Vec<RespFrame>,
+        direction: ListDirection,
+    ) -> Result<usize, WrongType> {
+        self.touch(&key);
+        let fill = self.list_max_listpack_size();
+        let len = {
+            let mut list = self.db().get_or_default::<QuickList>(key.clone())?;
+            for value in values.into_iter().map(frame_bytes) {
+                match direction {
+                    ListDirection::Left => list.push_front(value, fill),
+                    ListDirection::Right => list.push_back(value, fill),
+                }
+            }
+            list.len()
+        };
+        self.written(&key);
+        self.list_notify.notify_waiters();
+        Ok(len)
     }
 
-    pub fn set(&self, key: String, value: RespFrame) {
-        self.map.insert(key, value);
+    pub fn pop(
+        &self,
+        key: &[u8],
+        count: usize,
+        direction: ListDirection,
+    ) -> Result<Option<Vec<RespFrame>>, WrongType> {
+        self.touch(key);
+        let values = {
+            let Some(mut list) = self.db().get_mut::<QuickList>(key)? else {
+                return Ok(None);
+            };
+            let count = count.min(list.len());
+            (0..count)
+                .filter_map(|_| match direction {
+                    ListDirection::Left => list.pop_front(),
+                    ListDirection::Right => list.pop_back(),
+                })
+                .map(|value| bulk_string(&value))
+                .collect::<Vec<_>>()
+        };
+        self.db().remove_if_empty(key);
+        self.written(key);
+        Ok((!values.is_empty()).then_some(values))
     }
 
-    pub fn del(&self, key: &str) -> bool {
-        self.map.remove(key).is_some()
+    pub fn lmove(
+        &self,
+        source: &[u8],
+        destination: Bytes,
+        from: ListDirection,
+        to: ListDirection,
+    ) -> Result<Option<RespFrame>, WrongType> {
+        // nothing is popped when it couldn't be pushed
+        self.db().get::<QuickList>(&destination)?;
+        let Some(value) = self
+            .pop(source, 1, from)?
+            .and_then(|mut values| values.pop())
+        else {
+            return Ok(None);
+        };
+        self.push(destination, vec![value.clone()], to)?;
+        Ok(Some(value))
     }
 
-    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
-        self.hmap
-            .get(key)
-            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+    pub fn llen(&self, key: &[u8]) -> Result<usize, WrongType> {
+        self.touch(key);
+        Ok(self
+            .db()
+            .get::<QuickList>(key)?
+            .map(|v| v.len())
+            .unwrap_or(0))
     }
 
-    pub fn hset(&self, key: String, field: String, value: RespFrame) {
-        let hmap = self.hmap.entry(key).or_default();
-        hmap.insert(field, value);
+    /// Returns the element at `index`, counting from the tail when it is negative.
+    pub fn lindex(&self, key: &[u8], index: i64) -> Result<Option<RespFrame>, WrongType> {
+        self.touch(key);
+        let Some(list) = self.db().get::<QuickList>(key)? else {
+            return Ok(None);
+        };
+        let index = if index < 0 {
+            match list.len().checked_sub(index.unsigned_abs() as usize) {
+                Some(index) => index,
+                None => return Ok(None),
+            }
+        } else {
+            index as usize
+        };
+        Ok(list.get(index).map(bulk_string))
     }
 
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.clone())
+    /// Inserts `value` before or after the first occurrence of `pivot`, returning the new
+    /// length of the list, which is zero when the list does not exist, or `None` when the
+    /// pivot is not found.
+    pub fn linsert(
+        &self,
+        key: &[u8],
+        pivot: &RespFrame,
+        value: RespFrame,
+        after: bool,
+    ) -> Result<Option<usize>, WrongType> {
+        self.touch(key);
+        let fill = self.list_max_listpack_size();
+        let len = {
+            let Some(mut list) = self.db().get_mut::<QuickList>(key)? else {
+                return Ok(Some(0));
+            };
+            let pivot = frame_bytes(pivot.clone());
+            let Some(index) = list.iter().position(|v| *v == pivot) else {
+                return Ok(None);
+            };
+            list.insert(index + after as usize, frame_bytes(value), fill);
+            list.len()
+        };
+        self.written(key);
+        Ok(Some(len))
     }
 
-    pub fn hdel(&self, key: &str, field: &str) -> bool {
-        self.hmap
-            .get(key)
-            .map(|v| v.remove(field).is_some())
-            .unwrap_or(false)
+    pub fn lrange(&self, key: &[u8], start: i64, stop: i64) -> Result<Vec<RespFrame>, WrongType> {
+        self.touch(key);
+        let Some(list) = self.db().get::<QuickList>(key)? else {
+            return Ok(vec![]);
+        };
+        Ok(match zset::normalize_range(start, stop, list.len()) {
+            Some((start, stop)) => list
+                .iter_from(start)
+                .take(stop - start + 1)
+                .map(bulk_string)
+                .collect(),
+            None => vec![],
+        })
     }
 
-    pub fn sadd(&self, key: String, member: RespFrame) -> bool {
-        let set = self.set.entry(key).or_default();
-        set.insert(member)
+    pub fn zadd(
+        &self,
+        key: Bytes,
+        members: Vec<(Bytes, f64)>,
+        flags: ZAddFlags,
+    ) -> Result<Vec<ZAddOutcome>, WrongType> {
+        self.touch(&key);
+        let outcomes = {
+            let mut zset = self.db().get_or_default::<ZSet>(key.clone())?;
+            members
+                .into_iter()
+                .map(|(member, score)| zset.add(member, score, flags))
+                .collect()
+        };
+        self.db().remove_if_empty(&key);
+        self.written(&key);
+        Ok(outcomes)
     }
 
-    pub fn srem(&self, key: &str, member: &RespFrame) -> bool {
-        self.set
-            .get(key)
-            .map(|v| v.remove(member).is_some())
-            .unwrap_or(false)
+    pub fn zscore(&self, key: &[u8], member: &[u8]) -> Result<Option<f64>, WrongType> {
+        self.touch(key);
+        Ok(self.db().get::<ZSet>(key)?.and_then(|v| v.score(member)))
     }
 
-    pub fn sismember(&self, key: &str, member: &RespFrame) -> bool {
-        self.set
-            .get(key)
-            .map(|v| v.contains(member))
-            .unwrap_or(false)
+    pub fn zrange(
+        &self,
+        key: &[u8],
+        start: i64,
+        stop: i64,
+        rev: bool,
+    ) -> Result<Vec<(Bytes, f64)>, WrongType> {
+        self.touch(key);
+        Ok(self
+            .db()
+            .get::<ZSet>(key)?
+            .map(|v| v.range_by_rank(start, stop, rev))
+            .unwrap_or_default())
     }
 
-    pub fn smembers(&self, key: &str) -> Option<Vec<RespFrame>> {
-        self.set
-            .get(key)
-            .map(|v| v.iter().map(|v| v.clone()).collect())
+    pub fn zrange_by_score(
+        &self,
+        key: &[u8],
+        min: Bound<f64>,
+        max: Bound<f64>,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Result<Vec<(Bytes, f64)>, WrongType> {
+        self.touch(key);
+        let Some(zset) = self.db().get::<ZSet>(key)? else {
+            return Ok(vec![]);
+        };
+        Ok(zset
+            .iter_by_score(min, max)
+            .skip(offset)
+            .take(count.unwrap_or(usize::MAX))
+            .map(|(member, score)| (member.clone(), score))
+            .collect())
     }
+
+    pub fn zrange_by_lex(
+        &self,
+        key: &[u8],
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Result<Vec<Bytes>, WrongType> {
+        self.touch(key);
+        let Some(zset) = self.db().get::<ZSet>(key)? else {
+            return Ok(vec![]);
+        };
+        Ok(zset
+            .iter_by_lex(min, max)
+            .skip(offset)
+            .take(count.unwrap_or(usize::MAX))
+            .map(|(member, _)| member.clone())
+            .collect())
+    }
+
+    pub fn zcount(&self, key: &[u8], min: Bound<f64>, max: Bound<f64>) -> Result<usize, WrongType> {
+        self.touch(key);
+        Ok(self
+            .db()
+            .get::<ZSet>(key)?
+            .map(|v| v.iter_by_score(min, max).count())
+            .unwrap_or(0))
+    }
+
+    pub fn zlexcount(
+        &self,
+        key: &[u8],
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+    ) -> Result<usize, WrongType> {
+        self.touch(key);
+        Ok(self
+            .db()
+            .get::<ZSet>(key)?
+            .map(|v| v.iter_by_lex(min, max).count())
+            .unwrap_or(0))
+    }
+
+    pub fn zpop(
+        &self,
+        key: &[u8],
+        count: usize,
+        max: bool,
+    ) -> Result<Option<Vec<(Bytes, f64)>>, WrongType> {
+        self.touch(key);
+        let members = match self.db().get_mut::<ZSet>(key)? {
+            Some(mut zset) => zset.pop(count, max),
+            None => return Ok(None),
+        };
+        self.db().remove_if_empty(key);
+        self.written(key);
+        Ok((!members.is_empty()).then_some(members))
+    }
+
+    pub fn zrandmember(&self, key: &[u8], count: i64) -> Result<Vec<(Bytes, f64)>, WrongType> {
+        self.touch(key);
+        Ok(self
+            .db()
+            .get::<ZSet>(key)?
+            .map(|v| v.random_members(count))
+            .unwrap_or_default())
+    }
+
+    pub fn zscan(
+        &self,
+        key: &[u8],
+        cursor: usize,
+        count: usize,
+    ) -> Result<(usize, Vec<(Bytes, f64)>), WrongType> {
+        self.touch(key);
+        Ok(self
+            .db()
+            .get::<ZSet>(key)?
+            .map(|v| v.scan(cursor, count))
+            .unwrap_or_default())
+    }
+
+    pub fn zremrange_by_rank(&self, key: &[u8], start: i64, stop: i64) -> Result<usize, WrongType> {
+        self.zremove_with(key, |zset| zset.remove_range_by_rank(start, stop))
+    }
+
+    pub fn zremrange_by_score(
+        &self,
+        key: &[u8],
+        min: Bound<f64>,
+        max: Bound<f64>,
+    ) -> Result<usize, WrongType> {
+        self.zremove_with(key, |zset| zset.remove_range_by_score(min, max))
+    }
+
+    pub fn zremrange_by_lex(
+        &self,
+        key: &[u8],
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+    ) -> Result<usize, WrongType> {
+        self.zremove_with(key, |zset| zset.remove_range_by_lex(min, max))
+    }
+
+    // Runs a removal on the sorted set and drops the key once it becomes empty.
+    fn zremove_with(
+        &self,
+        key: &[u8],
+        remove: impl FnOnce(&mut ZSet) -> usize,
+    ) -> Result<usize, WrongType> {
+        self.touch(key);
+        let removed = match self.db().get_mut::<ZSet>(key)? {
+            Some(mut zset) => remove(&mut zset),
+            None => return Ok(0),
+        };
+        self.db().remove_if_empty(key);
+        self.written(key);
+        Ok(removed)
+    }
+
+    pub fn zcombine(
+        &self,
+        operation: ZSetOperation,
+        keys: &[Bytes],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> Result<ZSet, WrongType> {
+        // snapshot each input so no two shard locks are held at the same time
+        let sets = keys
+            .iter()
+            .map(|key| {
+                self.touch(key);
+                Ok(self.db().get::<ZSet>(key)?.map(|v| v.clone()))
+            })
+            .collect::<Result<Vec<_>, WrongType>>()?;
+        Ok(ZSet::combine(operation, &sets, weights, aggregate))
+    }
+
+    /// Replaces `destination` with `zset` in one step, whatever it held before, deleting it
+    /// when `zset` is empty.
+    pub fn zstore(&self, destination: Bytes, zset: ZSet) -> usize {
+        self.touch(&destination);
+        self.db().expires.remove(&destination);
+        let len = zset.len();
+        let old = if zset.is_empty() {
+            self.db().keyspace.remove(&destination).map(|(_, old)| old)
+        } else {
+            self.db()
+                .keyspace
+                .insert(destination.clone(), Value::ZSet(zset))
+        };
+        if let Some(old) = old {
+            self.free(old.len(), old, false);
+        }
+        self.written(&destination);
+        len
+    }
+
+    pub fn zcard(&self, key: &[u8]) -> Result<usize, WrongType> {
+        self.touch(key);
+        Ok(self.db().get::<ZSet>(key)?.map(|v| v.len()).unwrap_or(0))
+    }
+
+    pub fn xadd(
+        &self,
+        key: Bytes,
+        id: StreamIdSpec,
+        fields: StreamFields,
+        trim: Option<StreamTrim>,
+    ) -> Result<Option<StreamId>, WrongType> {
+        self.touch(&key);
+        let id = {
+            let mut stream = self.db().get_or_default::<Stream>(key.clone())?;
+            let Some(id) = stream.add(id, fields) else {
+                return Ok(None);
+            };
+            if let Some(trim) = trim {
+                stream.trim(trim);
+            }
+            id
+        };
+        self.written(&key);
+        Ok(Some(id))
+    }
+
+    pub fn xtrim(&self, key: &[u8], trim: StreamTrim) -> Result<usize, WrongType> {
+        self.touch(key);
+        let removed = self
+            .db()
+            .get_mut::<Stream>(key)?
+            .map(|mut v| v.trim(trim))
+            .unwrap_or(0);
+        self.written(key);
+        Ok(removed)
+    }
+
+    pub fn xdel(&self, key: &[u8], ids: &[StreamId]) -> Result<usize, WrongType> {
+        self.touch(key);
+        let removed = self
+            .db()
+            .get_mut::<Stream>(key)?
+            .map(|mut v| v.delete(ids))
+            .unwrap_or(0);
+        self.written(key);
+        Ok(removed)
+    }
+
+    pub fn xlen(&self, key: &[u8]) -> Result<usize, WrongType> {
+        self.touch(key);
+        Ok(self.db().get::<Stream>(key)?.map(|v| v.len()).unwrap_or(0))
+    }
+
+    pub fn xrange(
+        &self,
+        key: &[u8],
+        start: StreamId,
+        end: StreamId,
+        rev: bool,
+        count: Option<usize>,
+    ) -> Result<Vec<(StreamId, StreamFields)>, WrongType> {
+        self.touch(key);
+        Ok(self
+            .db()
+            .get::<Stream>(key)?
+            .map(|v| v.range(start, end, rev, count))
+            .unwrap_or_default())
+    }
+
+    /// Returns a future that resolves the next time elements are pushed to any list.
+    pub fn list_pushed(&self) -> Notified<'_> {
+        self.list_notify.notified()
+    }
+}
+
+fn parse_hyperloglog(value: &StringValue) -> Option<HyperLogLog> {
+    HyperLogLog::from_bytes(&value.bytes())
+}
+
+// Elements are read back as bulk strings, whatever frame they were written as.
+fn bulk_string(bytes: &Bytes) -> RespFrame {
+    BulkString::new(bytes.clone()).into()
 }
 
 #[cfg(test)]
@@ -85,13 +850,52 @@ mod tests {
     #[test]
     fn test_backend() {
         let backend = Backend::new();
-        backend.hset(
-            "key".into(),
-            "field".into(),
-            RespFrame::SimpleString("value".into()),
+        backend
+            .hset(
+                "key".into(),
+                "field".into(),
+                RespFrame::SimpleString("value".into()),
+            )
+            .unwrap();
+        assert_eq!(backend.hdel(b"key", b"field"), Ok(true));
+        assert_eq!(backend.hdel(b"key", b"field"), Ok(false));
+        assert_eq!(backend.hdel(b"ke", b"field"), Ok(false));
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let backend = Backend::new();
+        let member: RespFrame = BulkString::from("member").into();
+        backend.set("string".into(), BulkString::from("value").into());
+        backend.sadd("set".into(), member.clone()).unwrap();
+
+        assert_eq!(
+            backend.sadd("string".into(), member.clone()),
+            Err(WrongType)
+        );
+        assert_eq!(backend.hget(b"string", b"field"), Err(WrongType));
+        assert_eq!(backend.llen(b"set"), Err(WrongType));
+        assert_eq!(backend.get(b"set"), Err(WrongType));
+        assert_eq!(
+            backend.lmove(
+                b"missing",
+                "set".into(),
+                ListDirection::Left,
+                ListDirection::Left
+            ),
+            Err(WrongType)
         );
-        assert!(backend.hdel("key", "field"));
-        assert!(!backend.hdel("key", "field"));
-        assert!(!backend.hdel("ke", "field"));
+        // the values were left as they were
+        assert_eq!(backend.key_type(b"string"), Some("string"));
+        assert_eq!(backend.key_type(b"set"), Some("set"));
+        assert_eq!(backend.smembers(b"set"), Ok(Some(vec![member.clone()])));
+
+        // SET replaces a value of any type
+        backend.set("set".into(), BulkString::from("value").into());
+        assert_eq!(backend.key_type(b"set"), Some("string"));
+        assert_eq!(backend.sismember(b"set", &member), Err(WrongType));
+        assert!(backend.del(b"set"));
+        assert_eq!(backend.key_type(b"set"), None);
+        assert_eq!(backend.sismember(b"set", &member), Ok(false));
     }
 }
@@ -0,0 +1,1137 @@
+// Point-in-time snapshots of the whole dataset. A native snapshot is a sequence of RESP
+// frames: a header naming the format and its version, one array per key holding its type,
+// name, expiry time and value, and an end marker carrying the CRC-64 of everything before it.
+// The keys of the first database come first, those of the others after a SELECT array
+// naming their database.
+// Snapshots may also be written in the RDB format of redis-server, and both are recognized
+// when loading.
+
+use super::{
+    clock::unix_millis,
+    crc64::crc64,
+    db::Value,
+    rdb::{is_rdb, serialized_len, write_rdb},
+    string::frame_bytes,
+    Backend, Hash, QuickList, Set, Stream, StreamId, StreamIdSpec,
+};
+use crate::{BulkString, RespArray, RespDecoder, RespEncoder, RespError, RespFrame};
+use bytes::{Bytes, BytesMut};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, ErrorKind, Write},
+    ops::Bound,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    thread,
+    time::Instant,
+};
+use thiserror::Error;
+use tracing::{info, warn};
+
+const SNAPSHOT_MAGIC: &str = "SREDIS";
+// version 1 predates the SELECT arrays, everything in it belongs to the first database
+const SNAPSHOT_VERSION: i64 = 2;
+const SNAPSHOT_EOF: &str = "EOF";
+const SNAPSHOT_SELECT: &str = "SELECT";
+
+// the rules of the default redis.conf
+const DEFAULT_SAVE_RULES: [SaveRule; 3] = [
+    SaveRule::new(3600, 1),
+    SaveRule::new(300, 100),
+    SaveRule::new(60, 10000),
+];
+// a failed background save is not retried by the save rules any sooner than this
+const BGSAVE_RETRY_DELAY: u64 = 5_000;
+
+#[derive(Debug, Error)]
+pub enum PersistenceError {
+    #[error("Background save already in progress")]
+    SaveInProgress,
+    #[error("Background append only file rewriting already in progress")]
+    RewriteInProgress,
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("Wrong signature trying to load the snapshot")]
+    BadSignature,
+    #[error("Can't handle snapshot format version {0}")]
+    UnsupportedVersion(i64),
+    #[error("Snapshot checksum mismatch: expected {expected:016x}, got {actual:016x}")]
+    ChecksumMismatch { expected: u64, actual: u64 },
+    #[error("Unexpected end of file, the file is truncated")]
+    Truncated,
+    #[error("Corrupt snapshot: {0}")]
+    Corrupt(String),
+    #[error("Bad append only file at offset {offset}: {source}")]
+    BadAof {
+        offset: usize,
+        source: Box<PersistenceError>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    #[default]
+    Native,
+    /// the format of redis-server, for exchanging dump files with it
+    Rdb,
+}
+
+impl SnapshotFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format.to_ascii_lowercase().as_str() {
+            "native" => Some(SnapshotFormat::Native),
+            "rdb" => Some(SnapshotFormat::Rdb),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SnapshotFormat::Native => "native",
+            SnapshotFormat::Rdb => "rdb",
+        }
+    }
+}
+
+/// Snapshot the dataset once at least `changes` writes happened and `seconds` passed since
+/// the last snapshot, like a `save <seconds> <changes>` line of redis.conf.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaveRule {
+    pub seconds: u64,
+    pub changes: u64,
+}
+
+impl SaveRule {
+    pub const fn new(seconds: u64, changes: u64) -> Self {
+        Self { seconds, changes }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaveStats {
+    pub bgsave_in_progress: bool,
+    pub changes_since_last_save: u64,
+    /// unix time of the last successful save, in seconds
+    pub last_save_time: u64,
+    pub last_bgsave_ok: bool,
+    pub last_bgsave_time_sec: Option<u64>,
+    pub current_bgsave_time_sec: Option<u64>,
+}
+
+#[derive(Debug)]
+pub(super) struct Persistence {
+    dir: RwLock<PathBuf>,
+    dbfilename: RwLock<String>,
+    // the file the server was started with, which CONFIG REWRITE updates
+    config_file: RwLock<Option<PathBuf>>,
+    format: RwLock<SnapshotFormat>,
+    save_rules: RwLock<Vec<SaveRule>>,
+    // shared with the background thread, which updates it once the snapshot is written
+    state: Arc<SaveState>,
+}
+
+impl Default for Persistence {
+    fn default() -> Self {
+        Self {
+            dir: RwLock::new(PathBuf::from(".")),
+            dbfilename: RwLock::new("dump.rdb".to_string()),
+            config_file: RwLock::default(),
+            format: RwLock::default(),
+            save_rules: RwLock::new(DEFAULT_SAVE_RULES.to_vec()),
+            state: Arc::new(SaveState {
+                last_save: AtomicU64::new(unix_millis()),
+                last_bgsave_secs: AtomicI64::new(-1),
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SaveState {
+    bgsave_in_progress: AtomicBool,
+    // writes since the last successful snapshot
+    dirty: AtomicU64,
+    // unix times in milliseconds
+    last_save: AtomicU64,
+    last_bgsave_try: AtomicU64,
+    last_bgsave_failed: AtomicBool,
+    // seconds the last background save took, -1 before the first one
+    last_bgsave_secs: AtomicI64,
+}
+
+impl SaveState {
+    // Records the outcome of a snapshot taken when `dirty` writes were pending; writes made
+    // while it was being written stay pending.
+    fn finish(&self, dirty: u64, result: &io::Result<()>) {
+        if result.is_ok() {
+            self.dirty.fetch_sub(dirty, Ordering::Relaxed);
+            self.last_save.store(unix_millis(), Ordering::Relaxed);
+        }
+        self.last_bgsave_failed
+            .store(result.is_err(), Ordering::Relaxed);
+    }
+}
+
+// A key copied out of the keyspace, so it can be written out off the command path.
+#[derive(Debug)]
+pub(super) struct Entry {
+    pub(super) db: usize,
+    pub(super) key: Bytes,
+    pub(super) expire_at: Option<u64>,
+    pub(super) value: Value,
+}
+
+impl Backend {
+    /// Directory the dump file is written to.
+    pub fn dir(&self) -> PathBuf {
+        self.persistence
+            .dir
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn set_dir(&self, dir: PathBuf) {
+        *self
+            .persistence
+            .dir
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = dir;
+    }
+
+    /// The configuration file the server was started with, if any.
+    pub fn config_file(&self) -> Option<PathBuf> {
+        self.persistence
+            .config_file
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn set_config_file(&self, path: PathBuf) {
+        *self
+            .persistence
+            .config_file
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = Some(path);
+    }
+
+    pub fn dbfilename(&self) -> String {
+        self.persistence
+            .dbfilename
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn set_dbfilename(&self, name: String) {
+        *self
+            .persistence
+            .dbfilename
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = name;
+    }
+
+    /// Format new snapshots are written in, in the dump file and the AOF preamble alike.
+    pub fn dump_format(&self) -> SnapshotFormat {
+        *self
+            .persistence
+            .format
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_dump_format(&self, format: SnapshotFormat) {
+        *self
+            .persistence
+            .format
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = format;
+    }
+
+    pub fn save_rules(&self) -> Vec<SaveRule> {
+        self.persistence
+            .save_rules
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Replaces the save rules; with none, snapshots are only taken on request.
+    pub fn set_save_rules(&self, rules: Vec<SaveRule>) {
+        *self
+            .persistence
+            .save_rules
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = rules;
+    }
+
+    /// Number of writes since the last successful snapshot.
+    pub fn dirty(&self) -> u64 {
+        self.persistence.state.dirty.load(Ordering::Relaxed)
+    }
+
+    pub fn bgsave_in_progress(&self) -> bool {
+        self.persistence
+            .state
+            .bgsave_in_progress
+            .load(Ordering::Acquire)
+    }
+
+    /// Unix time of the last successful save, in seconds. Starts at the time the server did.
+    pub fn lastsave(&self) -> u64 {
+        self.persistence.state.last_save.load(Ordering::Relaxed) / 1000
+    }
+
+    pub fn save_stats(&self) -> SaveStats {
+        let state = &self.persistence.state;
+        let in_progress = self.bgsave_in_progress();
+        let started = state.last_bgsave_try.load(Ordering::Relaxed);
+        SaveStats {
+            bgsave_in_progress: in_progress,
+            changes_since_last_save: self.dirty(),
+            last_save_time: self.lastsave(),
+            last_bgsave_ok: !state.last_bgsave_failed.load(Ordering::Relaxed),
+            last_bgsave_time_sec: state
+                .last_bgsave_secs
+                .load(Ordering::Relaxed)
+                .try_into()
+                .ok(),
+            current_bgsave_time_sec: in_progress
+                .then(|| unix_millis().saturating_sub(started) / 1000),
+        }
+    }
+
+    /// Writes a snapshot of the dataset to the dump file, returning once it is on disk.
+    pub fn save(&self) -> Result<(), PersistenceError> {
+        if self.bgsave_in_progress() {
+            return Err(PersistenceError::SaveInProgress);
+        }
+        let dirty = self.dirty();
+        let result = write_snapshot(&self.dump_path(), self.capture(), self.dump_format());
+        self.persistence.state.finish(dirty, &result);
+        Ok(result?)
+    }
+
+    /// Copies the dataset and writes it to the dump file on a background thread, so commands
+    /// keep being served while the file is written.
+    pub fn bgsave(&self) -> Result<(), PersistenceError> {
+        let state = self.persistence.state.clone();
+        if state.bgsave_in_progress.swap(true, Ordering::AcqRel) {
+            return Err(PersistenceError::SaveInProgress);
+        }
+        let started = unix_millis();
+        state.last_bgsave_try.store(started, Ordering::Relaxed);
+        let dirty = self.dirty();
+        let entries = self.fork_capture();
+        let (path, format) = (self.dump_path(), self.dump_format());
+        let spawned = thread::Builder::new()
+            .name("bgsave".to_string())
+            .spawn(move || {
+                let result = write_snapshot(&path, entries, format);
+                match &result {
+                    Ok(()) => info!("Background saving terminated with success"),
+                    Err(e) => warn!("Background saving error: {}", e),
+                }
+                state.finish(dirty, &result);
+                let secs = unix_millis().saturating_sub(started) / 1000;
+                state.last_bgsave_secs.store(secs as i64, Ordering::Relaxed);
+                state.bgsave_in_progress.store(false, Ordering::Release);
+            });
+        if let Err(e) = spawned {
+            self.persistence
+                .state
+                .bgsave_in_progress
+                .store(false, Ordering::Release);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Starts a background save when one of the save rules matches, returning whether it did.
+    /// Meant to be called periodically.
+    pub fn run_save_rules(&self) -> bool {
+        let state = &self.persistence.state;
+        let now = unix_millis();
+        let retry_at = state.last_bgsave_try.load(Ordering::Relaxed) + BGSAVE_RETRY_DELAY;
+        if self.bgsave_in_progress()
+            || (state.last_bgsave_failed.load(Ordering::Relaxed) && now < retry_at)
+        {
+            return false;
+        }
+        let dirty = self.dirty();
+        let elapsed = now.saturating_sub(state.last_save.load(Ordering::Relaxed)) / 1000;
+        let Some(rule) = self
+            .save_rules()
+            .into_iter()
+            .find(|rule| dirty >= rule.changes && elapsed >= rule.seconds)
+        else {
+            return false;
+        };
+        info!(
+            "{} changes in {} seconds. Saving...",
+            rule.changes, rule.seconds
+        );
+        match self.bgsave() {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Can't start the background save: {}", e);
+                false
+            }
+        }
+    }
+
+    // Counts a write towards the save rules.
+    pub(super) fn mark_dirty(&self) {
+        self.persistence.state.dirty.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Loads the dump file into the keyspace, skipping keys that expired in the meantime.
+    /// Returns the number of keys loaded, or `None` when there is no dump file. The whole file
+    /// is validated before any key is loaded.
+    pub fn load(&self) -> Result<Option<usize>, PersistenceError> {
+        let data = match fs::read(self.dump_path()) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let (entries, len) = self.read_snapshot(&data)?;
+        if len != data.len() {
+            return Err(corrupt("trailing data after the end marker"));
+        }
+        let loaded = self.restore_entries(entries);
+        // the dataset now matches the dump file
+        self.persistence.state.dirty.store(0, Ordering::Relaxed);
+        Ok(Some(loaded))
+    }
+
+    pub fn dump_path(&self) -> PathBuf {
+        self.dir().join(self.dbfilename())
+    }
+
+    // Copies every live key of every database along with its expiry time, database by
+    // database. Each value is copied atomically, while writes landing during the copy may or
+    // may not make it in, as they would had they been sent a moment earlier or later.
+    pub(super) fn capture(&self) -> Vec<Entry> {
+        let now = self.now_millis();
+        let mut entries = vec![];
+        for db in self.all_dbs() {
+            for key in db.all_keys() {
+                let expire_at = db.db().expires.get(&key).map(|at| *at);
+                if expire_at.is_some_and(|at| at <= now) {
+                    continue;
+                }
+                entries.extend(db.key_entry(&key, expire_at));
+            }
+        }
+        entries
+    }
+
+    // Copies the dataset for a background thread to write it, the part Redis forks for, which
+    // is timed as the fork latency event.
+    pub(super) fn fork_capture(&self) -> Vec<Entry> {
+        let started = Instant::now();
+        let entries = self.capture();
+        self.record_latency("fork", started.elapsed());
+        entries
+    }
+
+    // Copies the value at `key` in the database, if there is one.
+    fn key_entry(&self, key: &[u8], expire_at: Option<u64>) -> Option<Entry> {
+        let value = self.db().keyspace.get(key)?.clone();
+        Some(Entry {
+            db: self.db_index(),
+            key: Bytes::copy_from_slice(key),
+            expire_at,
+            value,
+        })
+    }
+
+    /// The bytes the value at `key` takes in a snapshot in the dump format, or in the native
+    /// one for the values the RDB format can't hold, as DEBUG OBJECT reports it.
+    pub fn serialized_length(&self, key: &[u8]) -> Option<usize> {
+        if self.expire_if_needed(key) {
+            return None;
+        }
+        let entry = self.key_entry(key, None)?;
+        let rdb_len = match self.dump_format() {
+            SnapshotFormat::Rdb => serialized_len(&entry.value),
+            SnapshotFormat::Native => None,
+        };
+        Some(rdb_len.unwrap_or_else(|| entry.value.into_frame().encode().len()))
+    }
+
+    // Checks that the database `index` a snapshot refers to exists here.
+    pub(super) fn snapshot_db(&self, index: u64) -> Result<usize, PersistenceError> {
+        match usize::try_from(index) {
+            Ok(index) if index < self.databases() => Ok(index),
+            _ => Err(corrupt(format!(
+                "database {} is out of range, the server has {} databases",
+                index,
+                self.databases()
+            ))),
+        }
+    }
+}
+
+impl Backend {
+    // Parses the snapshot at the start of `data`, returning its entries and its length.
+    pub(super) fn read_snapshot(
+        &self,
+        data: &[u8],
+    ) -> Result<(Vec<Entry>, usize), PersistenceError> {
+        if is_rdb(data) {
+            return self.read_rdb(data);
+        }
+        let mut buf = BytesMut::from(data);
+        let header = array(next_frame(&mut buf)?).map_err(|_| PersistenceError::BadSignature)?;
+        match header.as_slice() {
+            [RespFrame::BulkString(magic), RespFrame::Integer(version)]
+                if magic.as_slice() == SNAPSHOT_MAGIC.as_bytes() =>
+            {
+                if !(1..=SNAPSHOT_VERSION).contains(version) {
+                    return Err(PersistenceError::UnsupportedVersion(*version));
+                }
+            }
+            _ => return Err(PersistenceError::BadSignature),
+        }
+        let mut entries = vec![];
+        let mut db = 0;
+        loop {
+            let offset = data.len() - buf.len();
+            let items = array(next_frame(&mut buf)?)?;
+            if items.first() == Some(&BulkString::from(SNAPSHOT_SELECT).into()) {
+                db = match items.get(1) {
+                    Some(RespFrame::Integer(index)) if *index >= 0 => {
+                        self.snapshot_db(*index as u64)?
+                    }
+                    _ => return Err(corrupt("invalid database index")),
+                };
+                continue;
+            }
+            if items.first() != Some(&BulkString::from(SNAPSHOT_EOF).into()) {
+                entries.push(self.parse_entry(db, items)?);
+                continue;
+            }
+            let Some(RespFrame::Integer(expected)) = items.get(1) else {
+                return Err(corrupt("the end marker has no checksum"));
+            };
+            let (expected, actual) = (*expected as u64, crc64(0, &data[..offset]));
+            if expected != actual {
+                return Err(PersistenceError::ChecksumMismatch { expected, actual });
+            }
+            return Ok((entries, data.len() - buf.len()));
+        }
+    }
+
+    // Loads the entries whose keys have not expired since into their databases, returning how
+    // many were loaded.
+    pub(super) fn restore_entries(&self, entries: Vec<Entry>) -> usize {
+        let now = self.now_millis();
+        let mut loaded = 0;
+        for entry in entries {
+            if entry.expire_at.is_some_and(|at| at <= now) {
+                continue;
+            }
+            // checked when parsing the snapshot
+            let Some(db) = self.select(entry.db) else {
+                continue;
+            };
+            db.restore(entry);
+            loaded += 1;
+        }
+        loaded
+    }
+
+    fn parse_entry(&self, db: usize, items: Vec<RespFrame>) -> Result<Entry, PersistenceError> {
+        let [ty, key, expire_at, value]: [RespFrame; 4] = items
+            .try_into()
+            .map_err(|_| corrupt("an entry must have four fields"))?;
+        let key = bulk_bytes(key)?;
+        let name = String::from_utf8_lossy(&key);
+        let expire_at = match expire_at {
+            RespFrame::Integer(-1) => None,
+            RespFrame::Integer(at) if at >= 0 => Some(at as u64),
+            _ => return Err(corrupt(format!("invalid expiry time for key '{}'", name))),
+        };
+        let value = match bulk_string(ty)?.as_str() {
+            "string" => Value::String(self.string_value(frame_bytes(value))),
+            "hash" => {
+                let limits = self.hash_limits();
+                let mut hash = Hash::default();
+                for (field, value) in pairs(value)? {
+                    hash.insert(frame_bytes(field), frame_bytes(value), limits);
+                }
+                Value::Hash(hash)
+            }
+            "set" => {
+                let max_intset_entries = self.set_max_intset_entries();
+                let mut set = Set::default();
+                for member in array(value)? {
+                    set.insert(frame_bytes(member), max_intset_entries);
+                }
+                Value::Set(set)
+            }
+            "list" => {
+                let fill = self.list_max_listpack_size();
+                let mut list = QuickList::default();
+                for value in array(value)? {
+                    list.push_back(frame_bytes(value), fill);
+                }
+                Value::List(list)
+            }
+            "zset" => Value::ZSet(
+                pairs(value)?
+                    .into_iter()
+                    .map(|(member, score)| {
+                        let score = bulk_string(score)?
+                            .parse::<f64>()
+                            .map_err(|_| corrupt(format!("invalid score in zset '{}'", name)))?;
+                        Ok((frame_bytes(member), score))
+                    })
+                    .collect::<Result<_, PersistenceError>>()?,
+            ),
+            "stream" => Value::Stream(parse_stream(value)?),
+            ty => return Err(corrupt(format!("unknown type '{}' for key '{}'", ty, name))),
+        };
+        Ok(Entry {
+            db,
+            key,
+            expire_at,
+            value,
+        })
+    }
+
+    // Stores the entry in this database.
+    fn restore(&self, entry: Entry) {
+        let Entry {
+            key,
+            expire_at,
+            value,
+            ..
+        } = entry;
+        self.db().keyspace.insert(key.clone(), value);
+        if let Some(at) = expire_at {
+            self.db().expires.insert(key.clone(), at);
+        }
+        self.written(&key);
+    }
+}
+
+impl Entry {
+    fn into_frame(self) -> RespFrame {
+        RespArray::new([
+            BulkString::from(self.value.type_name()).into(),
+            BulkString::from(self.key).into(),
+            RespFrame::Integer(self.expire_at.map_or(-1, |at| at as i64)),
+            self.value.into_frame(),
+        ])
+        .into()
+    }
+}
+
+impl Value {
+    fn into_frame(self) -> RespFrame {
+        let frames: Vec<RespFrame> = match self {
+            Value::String(value) => return value.to_frame(),
+            Value::Hash(hash) => hash
+                .iter()
+                .flat_map(|(field, value)| {
+                    [
+                        BulkString::from(field.clone()).into(),
+                        BulkString::new(value.clone()).into(),
+                    ]
+                })
+                .collect(),
+            Value::Set(set) => set.iter().map(|m| BulkString::new(m).into()).collect(),
+            Value::List(list) => list
+                .iter()
+                .map(|v| BulkString::new(v.clone()).into())
+                .collect(),
+            // scores are written in their shortest form that parses back to the same f64
+            Value::ZSet(zset) => zset
+                .iter_by_score(Bound::Unbounded, Bound::Unbounded)
+                .flat_map(|(member, score)| {
+                    [
+                        BulkString::new(member.clone()).into(),
+                        BulkString::from(score.to_string()).into(),
+                    ]
+                })
+                .collect(),
+            Value::Stream(stream) => {
+                let entries = stream
+                    .iter()
+                    .map(|(id, fields)| {
+                        let fields = fields
+                            .iter()
+                            .flat_map(|(field, value)| {
+                                [
+                                    BulkString::from(field.clone()).into(),
+                                    BulkString::new(value.clone()).into(),
+                                ]
+                            })
+                            .collect::<Vec<RespFrame>>();
+                        RespArray::new([
+                            BulkString::from(id.to_string()).into(),
+                            RespArray::new(fields).into(),
+                        ])
+                        .into()
+                    })
+                    .collect::<Vec<RespFrame>>();
+                vec![
+                    BulkString::from(stream.last_id().to_string()).into(),
+                    RespArray::new(entries).into(),
+                ]
+            }
+        };
+        RespArray::new(frames).into()
+    }
+}
+
+// Keeps a running checksum of everything written through it.
+pub(super) struct ChecksumWriter<W> {
+    pub(super) inner: W,
+    pub(super) crc: u64,
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.crc = crc64(self.crc, &buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Writes to a temporary file first and renames it over the dump file, so a crash midway never
+// leaves a truncated snapshot behind.
+fn write_snapshot(path: &Path, entries: Vec<Entry>, format: SnapshotFormat) -> io::Result<()> {
+    let temp = path.with_file_name(format!("temp-{}.rdb", process::id()));
+    let file = write_snapshot_to(BufWriter::new(File::create(&temp)?), entries, format)?;
+    file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    fs::rename(&temp, path)
+}
+
+pub(super) fn write_snapshot_to<W: Write>(
+    writer: W,
+    entries: Vec<Entry>,
+    format: SnapshotFormat,
+) -> io::Result<W> {
+    if format == SnapshotFormat::Rdb {
+        return write_rdb(writer, entries);
+    }
+    let mut writer = ChecksumWriter {
+        inner: writer,
+        crc: 0,
+    };
+    let header = RespArray::new([
+        BulkString::from(SNAPSHOT_MAGIC).into(),
+        RespFrame::Integer(SNAPSHOT_VERSION),
+    ]);
+    writer.write_all(&header.encode())?;
+    let mut db = 0;
+    for entry in entries {
+        if entry.db != db {
+            db = entry.db;
+            let select = RespArray::new([
+                BulkString::from(SNAPSHOT_SELECT).into(),
+                RespFrame::Integer(db as i64),
+            ]);
+            writer.write_all(&select.encode())?;
+        }
+        writer.write_all(&entry.into_frame().encode())?;
+    }
+    let eof = RespArray::new([
+        BulkString::from(SNAPSHOT_EOF).into(),
+        RespFrame::Integer(writer.crc as i64),
+    ]);
+    let mut writer = writer.inner;
+    writer.write_all(&eof.encode())?;
+    Ok(writer)
+}
+
+// Whether `data` starts with a snapshot header, whatever its format and version.
+pub(super) fn is_snapshot(data: &[u8]) -> bool {
+    if is_rdb(data) {
+        return true;
+    }
+    // far longer than any header
+    let mut buf = BytesMut::from(&data[..data.len().min(64)]);
+    match RespFrame::decode(&mut buf) {
+        Ok(RespFrame::Array(header)) => {
+            header.first() == Some(&BulkString::from(SNAPSHOT_MAGIC).into())
+        }
+        _ => false,
+    }
+}
+
+pub(super) fn next_frame(buf: &mut BytesMut) -> Result<RespFrame, PersistenceError> {
+    if buf.is_empty() {
+        return Err(PersistenceError::Truncated);
+    }
+    RespFrame::decode(buf).map_err(|e| match e {
+        RespError::FrameNotComplete => PersistenceError::Truncated,
+        e => corrupt(e.to_string()),
+    })
+}
+
+fn array(frame: RespFrame) -> Result<Vec<RespFrame>, PersistenceError> {
+    match frame {
+        RespFrame::Array(array) => Ok(array.0),
+        _ => Err(corrupt("expected an array")),
+    }
+}
+
+fn pairs(frame: RespFrame) -> Result<Vec<(RespFrame, RespFrame)>, PersistenceError> {
+    let items = array(frame)?;
+    if items.len() % 2 != 0 {
+        return Err(corrupt("expected an even number of items"));
+    }
+    let mut items = items.into_iter();
+    Ok(std::iter::from_fn(|| Some((items.next()?, items.next()?))).collect())
+}
+
+fn bulk_string(frame: RespFrame) -> Result<String, PersistenceError> {
+    match frame {
+        RespFrame::BulkString(s) => {
+            String::from_utf8(s.0.into()).map_err(|e| corrupt(e.to_string()))
+        }
+        _ => Err(corrupt("expected a bulk string")),
+    }
+}
+
+fn bulk_bytes(frame: RespFrame) -> Result<Bytes, PersistenceError> {
+    match frame {
+        RespFrame::BulkString(s) => Ok(s.0),
+        _ => Err(corrupt("expected a bulk string")),
+    }
+}
+
+fn parse_stream(frame: RespFrame) -> Result<Stream, PersistenceError> {
+    let [last_id, entries]: [RespFrame; 2] = array(frame)?
+        .try_into()
+        .map_err(|_| corrupt("a stream must have a last ID and entries"))?;
+    let mut stream = Stream::default();
+    for entry in array(entries)? {
+        let [id, fields]: [RespFrame; 2] = array(entry)?
+            .try_into()
+            .map_err(|_| corrupt("a stream entry must have an ID and fields"))?;
+        let fields = pairs(fields)?
+            .into_iter()
+            .map(|(field, value)| Ok((bulk_string(field)?, frame_bytes(value))))
+            .collect::<Result<_, PersistenceError>>()?;
+        stream
+            .add(StreamIdSpec::Explicit(parse_stream_id(id)?), fields)
+            .ok_or_else(|| corrupt("stream IDs are out of order"))?;
+    }
+    if !stream.set_last_id(parse_stream_id(last_id)?) {
+        return Err(corrupt("the last ID of a stream is below its entries"));
+    }
+    Ok(stream)
+}
+
+fn parse_stream_id(frame: RespFrame) -> Result<StreamId, PersistenceError> {
+    let id = bulk_string(frame)?;
+    id.split_once('-')
+        .and_then(|(ms, seq)| Some(StreamId::new(ms.parse().ok()?, seq.parse().ok()?)))
+        .ok_or_else(|| corrupt(format!("invalid stream ID '{}'", id)))
+}
+
+pub(super) fn corrupt(reason: impl Into<String>) -> PersistenceError {
+    PersistenceError::Corrupt(reason.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{string::StringValue, ListDirection, StreamTrim, TrimStrategy, ZAddFlags};
+    use std::time::{Duration, Instant};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("simple-redis-{}-{}", name, process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read_frames(path: &Path) -> Vec<RespFrame> {
+        let mut buf = BytesMut::from(&fs::read(path).unwrap()[..]);
+        let mut frames = vec![];
+        while !buf.is_empty() {
+            frames.push(RespFrame::decode(&mut buf).unwrap());
+        }
+        frames
+    }
+
+    #[test]
+    fn test_save_snapshot() {
+        let backend = Backend::new();
+        let dir = temp_dir("save");
+        backend.set_dir(dir.clone());
+        backend.set("key".into(), BulkString::from("1").into());
+        backend.expire(b"key", 10_000);
+        backend
+            .push(
+                "list".into(),
+                vec![BulkString::from("a").into()],
+                ListDirection::Right,
+            )
+            .unwrap();
+        backend.save().unwrap();
+
+        let frames = read_frames(&dir.join("dump.rdb"));
+        assert_eq!(frames.len(), 4);
+        assert_eq!(
+            frames[0],
+            RespArray::new([BulkString::from("SREDIS").into(), RespFrame::Integer(2)]).into()
+        );
+        let string = frames
+            .iter()
+            .find_map(|frame| match frame {
+                RespFrame::Array(entry) if entry[0] == BulkString::from("string").into() => {
+                    Some(entry)
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(string[1], BulkString::from("key").into());
+        assert!(matches!(string[2], RespFrame::Integer(at) if at > 0));
+        assert_eq!(string[3], BulkString::from("1").into());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_bgsave_snapshot() {
+        let backend = Backend::new();
+        let dir = temp_dir("bgsave");
+        backend.set_dir(dir.clone());
+        backend.set_dbfilename("background.rdb".into());
+        backend
+            .hset(
+                "hash".into(),
+                "field".into(),
+                BulkString::from("value").into(),
+            )
+            .unwrap();
+        assert_eq!(backend.save_stats().last_bgsave_time_sec, None);
+        backend.bgsave().unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while backend.bgsave_in_progress() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(!backend.bgsave_in_progress());
+        let stats = backend.save_stats();
+        assert!(stats.last_bgsave_ok);
+        assert_eq!(stats.last_bgsave_time_sec, Some(0));
+        assert_eq!(stats.changes_since_last_save, 0);
+        let frames = read_frames(&dir.join("background.rdb"));
+        assert_eq!(frames.len(), 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_snapshot() {
+        let backend = Backend::new();
+        let dir = temp_dir("load");
+        backend.set_dir(dir.clone());
+        assert_eq!(backend.load().unwrap(), None);
+
+        backend.set("string".into(), BulkString::from("value").into());
+        backend.expire(b"string", 10_000);
+        backend
+            .hset(
+                "hash".into(),
+                "field".into(),
+                BulkString::from("value").into(),
+            )
+            .unwrap();
+        backend
+            .sadd("set".into(), BulkString::from("1").into())
+            .unwrap();
+        backend
+            .push(
+                "list".into(),
+                vec![BulkString::from("a").into(), BulkString::from("b").into()],
+                ListDirection::Right,
+            )
+            .unwrap();
+        backend
+            .zadd(
+                "zset".into(),
+                vec![("member".into(), 0.1)],
+                ZAddFlags::default(),
+            )
+            .unwrap();
+        let fields = vec![("field".to_string(), "value".into())];
+        for _ in 0..2 {
+            backend
+                .xadd("stream".into(), StreamIdSpec::Auto, fields.clone(), None)
+                .unwrap();
+        }
+        let trim = StreamTrim {
+            strategy: TrimStrategy::MaxLen(1),
+            approximate: false,
+            limit: None,
+        };
+        backend.xtrim(b"stream", trim).unwrap();
+        backend.save().unwrap();
+
+        let loaded = Backend::new();
+        loaded.set_dir(dir.clone());
+        assert_eq!(loaded.load().unwrap(), Some(6));
+        assert_eq!(
+            loaded.get(b"string").unwrap(),
+            Some(BulkString::from("value").into())
+        );
+        assert!(loaded.ttl(b"string").flatten().is_some());
+        assert_eq!(
+            loaded.hget(b"hash", b"field").unwrap(),
+            Some(BulkString::from("value").into())
+        );
+        assert!(loaded
+            .sismember(b"set", &BulkString::from("1").into())
+            .unwrap());
+        assert_eq!(
+            loaded.lrange(b"list", 0, -1).unwrap(),
+            backend.lrange(b"list", 0, -1).unwrap()
+        );
+        assert_eq!(loaded.zscore(b"zset", b"member").unwrap(), Some(0.1));
+        assert_eq!(loaded.xlen(b"stream").unwrap(), 1);
+        assert_eq!(loaded.used_memory(), backend.used_memory());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_databases() {
+        let backend = Backend::new();
+        let dir = temp_dir("databases");
+        backend.set_dir(dir.clone());
+        backend.set("zero".into(), BulkString::from("0").into());
+        let other = backend.select(3).unwrap();
+        other.set("three".into(), BulkString::from("3").into());
+        other
+            .sadd("set".into(), BulkString::from("3").into())
+            .unwrap();
+
+        for format in [SnapshotFormat::Native, SnapshotFormat::Rdb] {
+            backend.set_dump_format(format);
+            backend.save().unwrap();
+            let loaded = Backend::new();
+            loaded.set_dir(dir.clone());
+            assert_eq!(loaded.load().unwrap(), Some(3));
+            assert_eq!(
+                loaded.get(b"zero").unwrap(),
+                Some(BulkString::from("0").into())
+            );
+            assert_eq!(loaded.get(b"three").unwrap(), None);
+            let three = loaded.select(3).unwrap();
+            assert_eq!(
+                three.get(b"three").unwrap(),
+                Some(BulkString::from("3").into())
+            );
+            assert!(three
+                .sismember(b"set", &BulkString::from("3").into())
+                .unwrap());
+
+            // a server with fewer databases can't hold them
+            let loaded = Backend::with_databases(2);
+            loaded.set_dir(dir.clone());
+            assert!(matches!(loaded.load(), Err(PersistenceError::Corrupt(_))));
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_skips_expired_keys() {
+        let backend = Backend::new();
+        let dir = temp_dir("load-expired");
+        backend.set_dir(dir.clone());
+        let entry = |key: &[u8], expire_at| Entry {
+            db: 0,
+            key: Bytes::copy_from_slice(key),
+            expire_at,
+            value: Value::String(StringValue::Bytes("value".into())),
+        };
+        let entries = vec![entry(b"expired", Some(1)), entry(b"live", None)];
+        write_snapshot(&backend.dump_path(), entries, SnapshotFormat::Native).unwrap();
+        assert_eq!(backend.load().unwrap(), Some(1));
+        assert_eq!(backend.get(b"expired").unwrap(), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_rejects_bad_snapshots() {
+        let backend = Backend::new();
+        let dir = temp_dir("load-bad");
+        backend.set_dir(dir.clone());
+        backend.set("key".into(), BulkString::from("value").into());
+        backend.save().unwrap();
+        let path = backend.dump_path();
+        let data = fs::read(&path).unwrap();
+
+        let load = |bytes: &[u8]| {
+            fs::write(&path, bytes).unwrap();
+            let backend = Backend::new();
+            backend.set_dir(dir.clone());
+            backend.load()
+        };
+        let mut flipped = data.clone();
+        let value = data.windows(5).position(|w| w == b"value").unwrap();
+        flipped[value] ^= 1;
+        assert!(matches!(
+            load(&flipped),
+            Err(PersistenceError::ChecksumMismatch { .. })
+        ));
+        assert!(matches!(
+            load(&data[..data.len() - 5]),
+            Err(PersistenceError::Truncated)
+        ));
+        assert!(matches!(
+            load(b"*1\r\n$5\r\nOTHER\r\n"),
+            Err(PersistenceError::BadSignature)
+        ));
+        assert!(matches!(
+            load(b"*2\r\n$6\r\nSREDIS\r\n:99\r\n"),
+            Err(PersistenceError::UnsupportedVersion(99))
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_rules() {
+        let backend = Backend::new();
+        let dir = temp_dir("save-rules");
+        backend.set_dir(dir.clone());
+        backend.set_save_rules(vec![SaveRule::new(3600, 1), SaveRule::new(0, 2)]);
+        backend.set("a".into(), BulkString::from("1").into());
+        assert_eq!(backend.dirty(), 1);
+        assert!(!backend.run_save_rules());
+
+        backend.set("b".into(), BulkString::from("2").into());
+        assert!(backend.run_save_rules());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while backend.bgsave_in_progress() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(backend.dirty(), 0);
+        assert!(dir.join("dump.rdb").is_file());
+        assert!(!backend.run_save_rules());
+
+        backend.set("c".into(), BulkString::from("3").into());
+        backend.save().unwrap();
+        assert_eq!(backend.dirty(), 0);
+        backend.set_save_rules(vec![]);
+        backend.set("d".into(), BulkString::from("4").into());
+        backend.set("e".into(), BulkString::from("5").into());
+        assert!(!backend.run_save_rules());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,550 @@
+// Publish/subscribe. Each connection owns a `Subscriber`, whose outbox is registered under the
+// channels and patterns it subscribed to; publishing pushes the message to the outboxes of the
+// matching entries and the connection writes it out as soon as it gets it. Shard channels are
+// kept apart, as in cluster mode they are served by the node owning their slot like keys. The
+// messages a connection didn't write out yet count toward its output buffer limit.
+
+use super::{
+    client::{ClientClass, OutputBuffer},
+    glob::glob_match,
+    Backend,
+};
+use crate::{BulkString, RespArray, RespEncoder, RespFrame};
+use std::{
+    collections::{BTreeSet, HashMap},
+    mem,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+#[derive(Debug, Default)]
+pub(super) struct PubSub {
+    next_id: AtomicU64,
+    // the outbox of every connection, to push it messages of its own such as invalidations
+    clients: Mutex<HashMap<u64, Outbox>>,
+    channels: Registry,
+    patterns: Registry,
+    shard_channels: Registry,
+}
+
+// The sending end of the messages to a connection, with their size in bytes. A message
+// published to several connections is shared by their outboxes rather than copied into each.
+#[derive(Debug, Clone)]
+struct Outbox {
+    sender: UnboundedSender<(Arc<RespFrame>, usize)>,
+    output: Arc<OutputBuffer>,
+}
+
+impl Outbox {
+    // queues `frame`, of `size` bytes, unless the connection is gone or its output buffer
+    // overflows, returning whether it did
+    fn send(&self, backend: &Backend, frame: Arc<RespFrame>, size: usize) -> bool {
+        if self.sender.is_closed() || !backend.queue_output(&self.output, size) {
+            return false;
+        }
+        self.sender.send((frame, size)).is_ok()
+    }
+}
+
+// the outboxes of the subscribers of each channel or pattern, by subscriber id
+#[derive(Debug, Default)]
+struct Registry(Mutex<HashMap<String, HashMap<u64, Outbox>>>);
+
+impl Registry {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, HashMap<u64, Outbox>>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn add(&self, name: &str, id: u64, outbox: &Outbox) {
+        self.lock()
+            .entry(name.to_string())
+            .or_default()
+            .insert(id, outbox.clone());
+    }
+
+    // sends `frame` to the subscribers of `name`, returning how many received it
+    fn send(&self, backend: &Backend, name: &str, frame: impl Fn() -> RespFrame) -> usize {
+        self.lock().get(name).map_or(0, |subscribers| {
+            let frame = Arc::new(frame());
+            let size = frame.encode().len();
+            subscribers
+                .values()
+                .filter(|outbox| outbox.send(backend, frame.clone(), size))
+                .count()
+        })
+    }
+
+    fn names(&self, pattern: Option<&str>) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .lock()
+            .keys()
+            .filter(|name| {
+                pattern.is_none_or(|pattern| glob_match(pattern.as_bytes(), name.as_bytes()))
+            })
+            .cloned()
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn subscribers(&self, name: &str) -> usize {
+        self.lock()
+            .get(name)
+            .map_or(0, |subscribers| subscribers.len())
+    }
+
+    fn remove(&self, name: &str, id: u64) {
+        let mut registry = self.lock();
+        if let Some(subscribers) = registry.get_mut(name) {
+            subscribers.remove(&id);
+            if subscribers.is_empty() {
+                registry.remove(name);
+            }
+        }
+    }
+}
+
+/// The channels and patterns a connection is subscribed to, and the messages published to
+/// them. Dropping it unsubscribes the connection from all of them.
+#[derive(Debug)]
+pub struct Subscriber {
+    id: u64,
+    outbox: Outbox,
+    receiver: UnboundedReceiver<(Arc<RespFrame>, usize)>,
+    channels: BTreeSet<String>,
+    patterns: BTreeSet<String>,
+    shard_channels: BTreeSet<String>,
+    // the RESP version negotiated with HELLO
+    protocol: u8,
+    // whether the connection may run commands when requirepass is set
+    authenticated: bool,
+    backend: Backend,
+}
+
+impl Subscriber {
+    /// The id of the connection, unique for the lifetime of the server.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The RESP version the connection speaks, 2 until HELLO switches it to 3.
+    pub fn protocol(&self) -> u8 {
+        self.protocol
+    }
+
+    pub fn set_protocol(&mut self, protocol: u8) {
+        self.protocol = protocol;
+        self.backend
+            .update_client(self.id, |info| info.protocol = protocol);
+    }
+
+    /// The name the connection gave itself, if any.
+    pub fn name(&self) -> Option<String> {
+        self.backend.client_info(self.id).and_then(|info| info.name)
+    }
+
+    pub fn set_name(&self, name: Option<String>) {
+        self.backend.update_client(self.id, |info| info.name = name);
+    }
+
+    /// Whether the connection gave the password, or connected while none was required.
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated
+    }
+
+    pub fn set_authenticated(&mut self, authenticated: bool) {
+        self.authenticated = authenticated;
+    }
+
+    /// Subscribes to `channel`, returning the number of subscriptions of the connection.
+    pub fn subscribe(&mut self, channel: &str) -> usize {
+        if self.channels.insert(channel.to_string()) {
+            self.backend
+                .pubsub
+                .channels
+                .add(channel, self.id, &self.outbox);
+        }
+        self.count()
+    }
+
+    /// Unsubscribes from `channel`, returning the number of subscriptions left.
+    pub fn unsubscribe(&mut self, channel: &str) -> usize {
+        if self.channels.remove(channel) {
+            self.backend.pubsub.channels.remove(channel, self.id);
+        }
+        self.count()
+    }
+
+    /// Subscribes to the channels matching the glob-style `pattern`, returning the number of
+    /// subscriptions of the connection.
+    pub fn psubscribe(&mut self, pattern: &str) -> usize {
+        if self.patterns.insert(pattern.to_string()) {
+            self.backend
+                .pubsub
+                .patterns
+                .add(pattern, self.id, &self.outbox);
+        }
+        self.count()
+    }
+
+    /// Unsubscribes from `pattern`, returning the number of subscriptions left.
+    pub fn punsubscribe(&mut self, pattern: &str) -> usize {
+        if self.patterns.remove(pattern) {
+            self.backend.pubsub.patterns.remove(pattern, self.id);
+        }
+        self.count()
+    }
+
+    /// Subscribes to the shard channel `channel`, returning the number of shard channels the
+    /// connection is subscribed to.
+    pub fn ssubscribe(&mut self, channel: &str) -> usize {
+        if self.shard_channels.insert(channel.to_string()) {
+            self.backend
+                .pubsub
+                .shard_channels
+                .add(channel, self.id, &self.outbox);
+        }
+        self.shard_channels.len()
+    }
+
+    /// Unsubscribes from the shard channel `channel`, returning the number of shard channels
+    /// left.
+    pub fn sunsubscribe(&mut self, channel: &str) -> usize {
+        if self.shard_channels.remove(channel) {
+            self.backend.pubsub.shard_channels.remove(channel, self.id);
+        }
+        self.shard_channels.len()
+    }
+
+    pub fn channels(&self) -> Vec<String> {
+        self.channels.iter().cloned().collect()
+    }
+
+    pub fn patterns(&self) -> Vec<String> {
+        self.patterns.iter().cloned().collect()
+    }
+
+    pub fn shard_channels(&self) -> Vec<String> {
+        self.shard_channels.iter().cloned().collect()
+    }
+
+    /// The number of channels and patterns the connection is subscribed to.
+    pub fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    /// Whether the connection is subscribed to anything, which puts it in subscribe mode.
+    pub fn is_subscribed(&self) -> bool {
+        self.count() + self.shard_count() > 0
+    }
+
+    /// The number of shard channels the connection is subscribed to.
+    pub fn shard_count(&self) -> usize {
+        self.shard_channels.len()
+    }
+
+    /// Records the state of the connection in the client registry after a command, with the
+    /// database it selected and the commands it queued since MULTI, and the class of its
+    /// output buffer limit.
+    pub fn record_state(&self, db: usize, multi: Option<usize>) {
+        self.backend.update_client(self.id, |info| {
+            info.db = db;
+            info.sub = self.channels.len();
+            info.psub = self.patterns.len();
+            info.ssub = self.shard_channels.len();
+            info.multi = multi;
+        });
+        self.outbox.output.set_class(match self.is_subscribed() {
+            true => ClientClass::PubSub,
+            false => ClientClass::Normal,
+        });
+    }
+
+    /// Brings the connection back to the state it connected in, as RESET does: unsubscribed,
+    /// with no pending message, no tracking nor watched key, unnamed, speaking RESP2 and only
+    /// authenticated if no password is required.
+    pub fn reset(&mut self) {
+        self.backend.disable_tracking(self.id);
+        self.backend.unwatch(self.id);
+        let pubsub = &self.backend.pubsub;
+        for channel in mem::take(&mut self.channels) {
+            pubsub.channels.remove(&channel, self.id);
+        }
+        for pattern in mem::take(&mut self.patterns) {
+            pubsub.patterns.remove(&pattern, self.id);
+        }
+        for channel in mem::take(&mut self.shard_channels) {
+            pubsub.shard_channels.remove(&channel, self.id);
+        }
+        while let Ok((_, size)) = self.receiver.try_recv() {
+            self.outbox.output.written(size);
+        }
+        self.set_name(None);
+        self.set_protocol(2);
+        self.authenticated = !self.backend.requires_auth();
+    }
+
+    /// Waits for the next message published to the subscriptions of the connection. It no
+    /// longer counts toward the output buffer, unless written out through it.
+    pub async fn message(&mut self) -> Option<Arc<RespFrame>> {
+        let (frame, size) = self.receiver.recv().await?;
+        self.outbox.output.written(size);
+        Some(frame)
+    }
+
+    /// The bytes queued for the connection, messages and replies it writes out.
+    pub fn output(&self) -> &OutputBuffer {
+        &self.outbox.output
+    }
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        self.backend.disable_tracking(self.id);
+        self.backend.unwatch(self.id);
+        self.backend.unregister_client(self.id);
+        let pubsub = &self.backend.pubsub;
+        pubsub.clients().remove(&self.id);
+        for channel in &self.channels {
+            pubsub.channels.remove(channel, self.id);
+        }
+        for pattern in &self.patterns {
+            pubsub.patterns.remove(pattern, self.id);
+        }
+        for channel in &self.shard_channels {
+            pubsub.shard_channels.remove(channel, self.id);
+        }
+    }
+}
+
+impl PubSub {
+    fn clients(&self) -> MutexGuard<'_, HashMap<u64, Outbox>> {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn message(kind: &'static str, args: Vec<BulkString>) -> RespFrame {
+    let mut frames = vec![BulkString::from(kind).into()];
+    frames.extend(args.into_iter().map(RespFrame::from));
+    RespArray::new(frames).into()
+}
+
+impl Backend {
+    /// A new subscriber for a connection, with no subscription yet.
+    pub fn subscriber(&self) -> Subscriber {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let outbox = Outbox {
+            sender,
+            output: Arc::new(OutputBuffer::new(ClientClass::Normal)),
+        };
+        let id = self.pubsub.next_id.fetch_add(1, Ordering::Relaxed);
+        self.pubsub.clients().insert(id, outbox.clone());
+        self.register_client(id);
+        Subscriber {
+            id,
+            outbox,
+            receiver,
+            channels: BTreeSet::new(),
+            patterns: BTreeSet::new(),
+            shard_channels: BTreeSet::new(),
+            protocol: 2,
+            authenticated: !self.requires_auth(),
+            backend: self.clone(),
+        }
+    }
+
+    pub(super) fn client_connected(&self, id: u64) -> bool {
+        self.pubsub.clients().contains_key(&id)
+    }
+
+    pub(super) fn client_subscribed(&self, id: u64, channel: &str) -> bool {
+        self.pubsub
+            .channels
+            .lock()
+            .get(channel)
+            .is_some_and(|subscribers| subscribers.contains_key(&id))
+    }
+
+    // Queues `frame` to be written out to the connection `id`, if still connected.
+    pub(super) fn send_to_client(&self, id: u64, frame: RespFrame) {
+        let outbox = self.pubsub.clients().get(&id).cloned();
+        if let Some(outbox) = outbox {
+            let size = frame.encode().len();
+            outbox.send(self, Arc::new(frame), size);
+        }
+    }
+
+    /// Sends `message` to the subscribers of `channel` and of the patterns matching it,
+    /// returning the number of subscribers that received it.
+    pub fn publish(&self, channel: &str, message: &BulkString) -> usize {
+        let mut received = self.pubsub.channels.send(self, channel, || {
+            self::message(
+                "message",
+                vec![BulkString::from(channel.to_string()), message.clone()],
+            )
+        });
+        for (pattern, subscribers) in self.pubsub.patterns.lock().iter() {
+            if !glob_match(pattern.as_bytes(), channel.as_bytes()) {
+                continue;
+            }
+            let frame = Arc::new(self::message(
+                "pmessage",
+                vec![
+                    BulkString::from(pattern.clone()),
+                    BulkString::from(channel.to_string()),
+                    message.clone(),
+                ],
+            ));
+            let size = frame.encode().len();
+            received += subscribers
+                .values()
+                .filter(|outbox| outbox.send(self, frame.clone(), size))
+                .count();
+        }
+        received
+    }
+
+    /// Sends `message` to the subscribers of the shard channel `channel`, returning the number
+    /// of subscribers that received it.
+    pub fn spublish(&self, channel: &str, message: &BulkString) -> usize {
+        self.pubsub.shard_channels.send(self, channel, || {
+            self::message(
+                "smessage",
+                vec![BulkString::from(channel.to_string()), message.clone()],
+            )
+        })
+    }
+
+    /// The channels with at least one subscriber, only those matching `pattern` if given.
+    pub fn pubsub_channels(&self, pattern: Option<&str>) -> Vec<String> {
+        self.pubsub.channels.names(pattern)
+    }
+
+    /// The number of subscribers of `channel`, pattern subscriptions left aside.
+    pub fn pubsub_numsub(&self, channel: &str) -> usize {
+        self.pubsub.channels.subscribers(channel)
+    }
+
+    /// The shard channels with at least one subscriber, only those matching `pattern` if given.
+    pub fn pubsub_shard_channels(&self, pattern: Option<&str>) -> Vec<String> {
+        self.pubsub.shard_channels.names(pattern)
+    }
+
+    /// The number of subscribers of the shard channel `channel`.
+    pub fn pubsub_shard_numsub(&self, channel: &str) -> usize {
+        self.pubsub.shard_channels.subscribers(channel)
+    }
+
+    /// The number of distinct patterns subscribed to.
+    pub fn pubsub_numpat(&self) -> usize {
+        self.pubsub.patterns.lock().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish() {
+        let backend = Backend::new();
+        let mut subscriber = backend.subscriber();
+        let mut psubscriber = backend.subscriber();
+        assert_eq!(subscriber.subscribe("news"), 1);
+        assert_eq!(subscriber.subscribe("news"), 1);
+        assert_eq!(subscriber.subscribe("sport"), 2);
+        assert_eq!(psubscriber.psubscribe("n*"), 1);
+
+        let message = BulkString::from("hello");
+        assert_eq!(backend.publish("news", &message), 2);
+        assert_eq!(backend.publish("weather", &message), 0);
+        assert_eq!(
+            subscriber.message().await.map(Arc::unwrap_or_clone),
+            Some(self::message(
+                "message",
+                vec!["news".into(), "hello".into()]
+            ))
+        );
+        assert_eq!(
+            psubscriber.message().await.map(Arc::unwrap_or_clone),
+            Some(self::message(
+                "pmessage",
+                vec!["n*".into(), "news".into(), "hello".into()]
+            ))
+        );
+
+        assert_eq!(subscriber.unsubscribe("news"), 1);
+        assert_eq!(backend.publish("news", &message), 1);
+    }
+
+    #[tokio::test]
+    async fn test_publish_shares_message() {
+        let backend = Backend::new();
+        let mut subscribers = [backend.subscriber(), backend.subscriber()];
+        for subscriber in &mut subscribers {
+            subscriber.subscribe("news");
+        }
+        assert_eq!(backend.publish("news", &BulkString::from("hello")), 2);
+        let [first, second] = &mut subscribers;
+        let (first, second) = (first.message().await, second.message().await);
+        assert!(Arc::ptr_eq(&first.unwrap(), &second.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_spublish() {
+        let backend = Backend::new();
+        let mut subscriber = backend.subscriber();
+        assert_eq!(subscriber.subscribe("news"), 1);
+        assert_eq!(subscriber.ssubscribe("news"), 1);
+        assert_eq!(subscriber.ssubscribe("sport"), 2);
+        assert_eq!(subscriber.count(), 1);
+
+        let message = BulkString::from("hello");
+        assert_eq!(backend.spublish("news", &message), 1);
+        assert_eq!(
+            subscriber.message().await.map(Arc::unwrap_or_clone),
+            Some(self::message(
+                "smessage",
+                vec!["news".into(), "hello".into()]
+            ))
+        );
+        assert_eq!(backend.pubsub_shard_channels(Some("s*")), vec!["sport"]);
+        assert_eq!(backend.pubsub_shard_numsub("news"), 1);
+        assert_eq!(backend.pubsub_channels(None), vec!["news"]);
+
+        assert_eq!(subscriber.sunsubscribe("news"), 1);
+        assert_eq!(backend.spublish("news", &message), 0);
+        drop(subscriber);
+        assert!(backend.pubsub_shard_channels(None).is_empty());
+    }
+
+    #[test]
+    fn test_pubsub_registry() {
+        let backend = Backend::new();
+        let mut first = backend.subscriber();
+        let mut second = backend.subscriber();
+        first.subscribe("news");
+        first.subscribe("sport");
+        second.subscribe("news");
+        first.psubscribe("n*");
+        second.psubscribe("n*");
+        second.psubscribe("s*");
+
+        assert_eq!(backend.pubsub_channels(None), vec!["news", "sport"]);
+        assert_eq!(backend.pubsub_channels(Some("n*")), vec!["news"]);
+        assert_eq!(backend.pubsub_numsub("news"), 2);
+        assert_eq!(backend.pubsub_numsub("weather"), 0);
+        assert_eq!(backend.pubsub_numpat(), 2);
+
+        drop(first);
+        assert_eq!(backend.pubsub_channels(None), vec!["news"]);
+        assert_eq!(backend.pubsub_numsub("news"), 1);
+        assert_eq!(backend.pubsub_numpat(), 2);
+        drop(second);
+        assert!(backend.pubsub_channels(None).is_empty());
+        assert_eq!(backend.pubsub_numpat(), 0);
+    }
+}
@@ -0,0 +1,256 @@
+// Lists are stored as a sequence of small chunks, as in Redis' quicklist, so pushing or popping
+// at either end never moves more than one chunk and inserting or removing in the middle only
+// shifts the elements of the chunk being touched.
+
+use bytes::Bytes;
+use std::collections::VecDeque;
+
+// chunk size in bytes for the negative fill factors, from -1 (4kb) to -5 (64kb)
+const FILL_BYTES: [usize; 5] = [4096, 8192, 16384, 32768, 65536];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuickList {
+    chunks: VecDeque<Chunk>,
+    len: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Chunk {
+    entries: VecDeque<Bytes>,
+    // payload bytes of the entries, checked against negative fill factors
+    bytes: usize,
+}
+
+impl Chunk {
+    // A positive `fill` caps the number of entries per chunk and a negative one its size in
+    // bytes, like Redis' list-max-listpack-size. A chunk always takes at least one entry.
+    fn has_room(&self, value: &Bytes, fill: i64) -> bool {
+        if self.entries.is_empty() {
+            return true;
+        }
+        if fill > 0 {
+            return self.entries.len() < fill as usize;
+        }
+        let limit = FILL_BYTES[(fill.unsigned_abs() as usize).clamp(1, FILL_BYTES.len()) - 1];
+        self.bytes + value.len() <= limit
+    }
+
+    fn insert(&mut self, index: usize, value: Bytes) {
+        self.bytes += value.len();
+        self.entries.insert(index, value);
+    }
+
+    fn remove(&mut self, index: usize) -> Option<Bytes> {
+        let value = self.entries.remove(index)?;
+        self.bytes -= value.len();
+        Some(value)
+    }
+
+    fn split_off(&mut self, at: usize) -> Chunk {
+        let entries = self.entries.split_off(at);
+        let bytes = entries.iter().map(Bytes::len).sum::<usize>();
+        self.bytes -= bytes;
+        Chunk { entries, bytes }
+    }
+}
+
+impl QuickList {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Total number of entry slots allocated across all chunks.
+    pub fn capacity(&self) -> usize {
+        self.chunks
+            .iter()
+            .map(|chunk| chunk.entries.capacity())
+            .sum()
+    }
+
+    /// A list that still fits in a single chunk is reported as a listpack, as Redis does.
+    pub fn encoding(&self) -> &'static str {
+        if self.chunks.len() > 1 {
+            "quicklist"
+        } else {
+            "listpack"
+        }
+    }
+
+    pub fn push_front(&mut self, value: Bytes, fill: i64) {
+        if !self
+            .chunks
+            .front()
+            .is_some_and(|c| c.has_room(&value, fill))
+        {
+            self.chunks.push_front(Chunk::default());
+        }
+        if let Some(chunk) = self.chunks.front_mut() {
+            chunk.insert(0, value);
+            self.len += 1;
+        }
+    }
+
+    pub fn push_back(&mut self, value: Bytes, fill: i64) {
+        if !self.chunks.back().is_some_and(|c| c.has_room(&value, fill)) {
+            self.chunks.push_back(Chunk::default());
+        }
+        if let Some(chunk) = self.chunks.back_mut() {
+            chunk.insert(chunk.entries.len(), value);
+            self.len += 1;
+        }
+    }
+
+    pub fn pop_front(&mut self) -> Option<Bytes> {
+        self.remove(0)
+    }
+
+    pub fn pop_back(&mut self) -> Option<Bytes> {
+        self.remove(self.len.checked_sub(1)?)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Bytes> {
+        let (chunk, offset) = self.locate(index)?;
+        self.chunks[chunk].entries.get(offset)
+    }
+
+    /// Inserts `value` so that it ends up at `index`, splitting the chunk it lands in when that
+    /// one is full. Indexes past the end append to the list.
+    pub fn insert(&mut self, index: usize, value: Bytes, fill: i64) {
+        if index == 0 {
+            return self.push_front(value, fill);
+        }
+        // insert after the previous element so a full chunk boundary never leaves a gap
+        let Some((mut chunk, mut offset)) = self.locate(index - 1) else {
+            return self.push_back(value, fill);
+        };
+        offset += 1;
+        if !self.chunks[chunk].has_room(&value, fill) {
+            let half = self.chunks[chunk].entries.len() / 2;
+            let tail = self.chunks[chunk].split_off(half);
+            self.chunks.insert(chunk + 1, tail);
+            if offset > half {
+                chunk += 1;
+                offset -= half;
+            }
+        }
+        self.chunks[chunk].insert(offset, value);
+        self.len += 1;
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Bytes> {
+        let (chunk, offset) = self.locate(index)?;
+        let value = self.chunks[chunk].remove(offset)?;
+        if self.chunks[chunk].entries.is_empty() {
+            self.chunks.remove(chunk);
+        }
+        self.len -= 1;
+        Some(value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Bytes> {
+        self.iter_from(0)
+    }
+
+    /// Iterates from `index` on, skipping whole chunks to get there.
+    pub fn iter_from(&self, index: usize) -> impl Iterator<Item = &Bytes> {
+        let (first, offset) = self.locate(index).unwrap_or((self.chunks.len(), 0));
+        self.chunks
+            .range(first..)
+            .enumerate()
+            .flat_map(move |(i, chunk)| chunk.entries.range(if i == 0 { offset } else { 0 }..))
+    }
+
+    // Returns the chunk holding `index` and the offset of the element within it.
+    fn locate(&self, mut index: usize) -> Option<(usize, usize)> {
+        if index >= self.len {
+            return None;
+        }
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            if index < chunk.entries.len() {
+                return Some((i, index));
+            }
+            index -= chunk.entries.len();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(n: usize) -> Bytes {
+        n.to_string().into()
+    }
+
+    fn values(list: &QuickList) -> Vec<Bytes> {
+        list.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_quicklist_push_pop() {
+        let mut list = QuickList::default();
+        for n in 0..10 {
+            list.push_back(value(n), 4);
+        }
+        list.push_front(value(100), 4);
+        assert_eq!(list.len(), 11);
+        assert_eq!(list.chunk_count(), 4);
+        assert_eq!(list.encoding(), "quicklist");
+        assert_eq!(list.get(1), Some(&value(0)));
+        assert_eq!(list.iter_from(5).cloned().collect::<Vec<_>>(), {
+            (4..10).map(value).collect::<Vec<_>>()
+        });
+
+        assert_eq!(list.pop_front(), Some(value(100)));
+        assert_eq!(list.pop_back(), Some(value(9)));
+        assert_eq!(values(&list), (0..9).map(value).collect::<Vec<_>>());
+        while list.pop_front().is_some() {}
+        assert!(list.is_empty());
+        assert_eq!(list.chunk_count(), 0);
+        assert_eq!(list.iter_from(3).count(), 0);
+    }
+
+    #[test]
+    fn test_quicklist_insert_remove() {
+        let mut list = QuickList::default();
+        for n in 0..8 {
+            list.push_back(value(n), 4);
+        }
+        // inserting into a full chunk splits it
+        list.insert(2, value(100), 4);
+        assert_eq!(list.chunk_count(), 3);
+        list.insert(100, value(200), 4);
+        let expected = [0, 1, 100, 2, 3, 4, 5, 6, 7, 200].map(value);
+        assert_eq!(values(&list), expected);
+
+        assert_eq!(list.remove(2), Some(value(100)));
+        assert_eq!(list.remove(100), None);
+        assert_eq!(list.len(), 9);
+        assert_eq!(list.get(8), Some(&value(200)));
+    }
+
+    #[test]
+    fn test_quicklist_fill_bytes() {
+        let mut list = QuickList::default();
+        for _ in 0..3 {
+            list.push_back(Bytes::from(vec![0; 3000]), -1);
+        }
+        assert_eq!(list.chunk_count(), 3);
+
+        let mut list = QuickList::default();
+        for _ in 0..3 {
+            list.push_back(Bytes::from(vec![0; 3000]), -2);
+        }
+        assert_eq!(list.chunk_count(), 2);
+        assert_eq!(list.encoding(), "quicklist");
+    }
+}
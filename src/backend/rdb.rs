@@ -0,0 +1,798 @@
+// The dump file format of redis-server, so datasets can be moved between it and this server.
+// Strings, lists, sets, sorted sets and hashes are read in all the encodings Redis writes them
+// in, and written in the plain ones every version can read. Streams, modules and functions are
+// not supported.
+
+use super::{
+    clock::unix_millis,
+    crc64::crc64,
+    db::Value,
+    persistence::{ChecksumWriter, Entry, PersistenceError},
+    Backend, Hash, QuickList, Set, ZSet,
+};
+use std::io::{self, Write};
+use tracing::warn;
+
+const RDB_MAGIC: &[u8] = b"REDIS";
+// written files use the version of Redis 5 and 6, which later versions still load
+const RDB_VERSION: u32 = 9;
+const RDB_MAX_VERSION: u32 = 12;
+
+const OPCODE_FUNCTION2: u8 = 0xf5;
+const OPCODE_FUNCTION: u8 = 0xf6;
+const OPCODE_MODULE_AUX: u8 = 0xf7;
+const OPCODE_IDLE: u8 = 0xf8;
+const OPCODE_FREQ: u8 = 0xf9;
+const OPCODE_AUX: u8 = 0xfa;
+const OPCODE_RESIZEDB: u8 = 0xfb;
+const OPCODE_EXPIRETIME_MS: u8 = 0xfc;
+const OPCODE_EXPIRETIME: u8 = 0xfd;
+const OPCODE_SELECTDB: u8 = 0xfe;
+const OPCODE_EOF: u8 = 0xff;
+
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+const TYPE_SET: u8 = 2;
+const TYPE_ZSET: u8 = 3;
+const TYPE_HASH: u8 = 4;
+const TYPE_ZSET_2: u8 = 5;
+const TYPE_LIST_ZIPLIST: u8 = 10;
+const TYPE_SET_INTSET: u8 = 11;
+const TYPE_ZSET_ZIPLIST: u8 = 12;
+const TYPE_HASH_ZIPLIST: u8 = 13;
+const TYPE_LIST_QUICKLIST: u8 = 14;
+const TYPE_HASH_LISTPACK: u8 = 16;
+const TYPE_ZSET_LISTPACK: u8 = 17;
+const TYPE_LIST_QUICKLIST_2: u8 = 18;
+const TYPE_SET_LISTPACK: u8 = 20;
+
+// special string encodings, flagged by the two high bits of the length
+const ENC_INT8: u8 = 0;
+const ENC_INT16: u8 = 1;
+const ENC_INT32: u8 = 2;
+const ENC_LZF: u8 = 3;
+
+// an element of a list, set, hash or sorted set
+type Item = Vec<u8>;
+
+// node kinds of a quicklist 2
+const QUICKLIST_NODE_PLAIN: usize = 1;
+const QUICKLIST_NODE_PACKED: usize = 2;
+
+pub(super) fn is_rdb(data: &[u8]) -> bool {
+    data.starts_with(RDB_MAGIC)
+}
+
+pub(super) fn write_rdb<W: Write>(writer: W, entries: Vec<Entry>) -> io::Result<W> {
+    let mut writer = ChecksumWriter {
+        inner: writer,
+        crc: 0,
+    };
+    let entries = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let Entry {
+                db,
+                key,
+                expire_at,
+                value,
+            } = entry;
+            match encode_value(&value) {
+                Some(value) => Some((db, key, expire_at, value)),
+                None => {
+                    warn!(
+                        "Key '{}' can't be stored in the RDB format, skipping it",
+                        String::from_utf8_lossy(&key)
+                    );
+                    None
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut header = format!("REDIS{:04}", RDB_VERSION).into_bytes();
+    for (name, value) in [
+        ("redis-bits", "64".to_string()),
+        ("ctime", (unix_millis() / 1000).to_string()),
+    ] {
+        header.push(OPCODE_AUX);
+        write_string(&mut header, name.as_bytes());
+        write_string(&mut header, value.as_bytes());
+    }
+    writer.write_all(&header)?;
+
+    // the entries come database by database, each one announced with its size
+    let mut db = None;
+    for (i, (entry_db, key, expire_at, (ty, payload))) in entries.iter().enumerate() {
+        let mut buf = vec![];
+        if db != Some(*entry_db) {
+            db = Some(*entry_db);
+            let keys = &entries[i..];
+            let len = keys.iter().take_while(|(db, ..)| db == entry_db).count();
+            let expires = keys[..len]
+                .iter()
+                .filter(|(_, _, at, _)| at.is_some())
+                .count();
+            buf.push(OPCODE_SELECTDB);
+            write_len(&mut buf, *entry_db);
+            buf.push(OPCODE_RESIZEDB);
+            write_len(&mut buf, len);
+            write_len(&mut buf, expires);
+        }
+        if let Some(at) = expire_at {
+            buf.push(OPCODE_EXPIRETIME_MS);
+            buf.extend_from_slice(&at.to_le_bytes());
+        }
+        buf.push(*ty);
+        write_string(&mut buf, key);
+        buf.extend_from_slice(payload);
+        writer.write_all(&buf)?;
+    }
+    writer.write_all(&[OPCODE_EOF])?;
+    let crc = writer.crc;
+    let mut writer = writer.inner;
+    writer.write_all(&crc.to_le_bytes())?;
+    Ok(writer)
+}
+
+// The length of the serialized value, or `None` for values the format can't hold.
+pub(super) fn serialized_len(value: &Value) -> Option<usize> {
+    encode_value(value).map(|(_, buf)| buf.len())
+}
+
+// Returns the type and the serialized value, or `None` for values the format can't hold.
+pub(super) fn encode_value(value: &Value) -> Option<(u8, Vec<u8>)> {
+    let mut buf = vec![];
+    let ty = match value {
+        Value::String(value) => {
+            write_string(&mut buf, &value.bytes());
+            TYPE_STRING
+        }
+        Value::List(list) => {
+            write_len(&mut buf, list.len());
+            for value in list.iter() {
+                write_string(&mut buf, value);
+            }
+            TYPE_LIST
+        }
+        Value::Set(set) => {
+            write_len(&mut buf, set.len());
+            for member in set.iter() {
+                write_string(&mut buf, &member);
+            }
+            TYPE_SET
+        }
+        Value::ZSet(zset) => {
+            let members = zset.range_by_rank(0, -1, false);
+            write_len(&mut buf, members.len());
+            for (member, score) in members {
+                write_string(&mut buf, &member);
+                buf.extend_from_slice(&score.to_le_bytes());
+            }
+            TYPE_ZSET_2
+        }
+        Value::Hash(hash) => {
+            write_len(&mut buf, hash.len());
+            for (field, value) in hash.iter() {
+                write_string(&mut buf, field);
+                write_string(&mut buf, value);
+            }
+            TYPE_HASH
+        }
+        Value::Stream(_) => return None,
+    };
+    Some((ty, buf))
+}
+
+fn write_len(buf: &mut Vec<u8>, len: usize) {
+    if len < 1 << 6 {
+        buf.push(len as u8);
+    } else if len < 1 << 14 {
+        buf.extend_from_slice(&[0x40 | (len >> 8) as u8, len as u8]);
+    } else if let Ok(len) = u32::try_from(len) {
+        buf.push(0x80);
+        buf.extend_from_slice(&len.to_be_bytes());
+    } else {
+        buf.push(0x81);
+        buf.extend_from_slice(&(len as u64).to_be_bytes());
+    }
+}
+
+fn write_string(buf: &mut Vec<u8>, bytes: &[u8]) {
+    write_len(buf, bytes.len());
+    buf.extend_from_slice(bytes);
+}
+
+impl Backend {
+    // Parses the RDB file at the start of `data`, returning its entries and its length.
+    pub(super) fn read_rdb(&self, data: &[u8]) -> Result<(Vec<Entry>, usize), PersistenceError> {
+        let mut reader = Reader::new(data);
+        if reader.take(RDB_MAGIC.len())? != RDB_MAGIC {
+            return Err(PersistenceError::BadSignature);
+        }
+        let version = std::str::from_utf8(reader.take(4)?)
+            .ok()
+            .and_then(|version| version.parse::<u32>().ok())
+            .ok_or(PersistenceError::BadSignature)?;
+        if !(1..=RDB_MAX_VERSION).contains(&version) {
+            return Err(PersistenceError::UnsupportedVersion(version as i64));
+        }
+        let mut entries = vec![];
+        let mut db = 0;
+        let mut expire_at = None;
+        loop {
+            match reader.u8()? {
+                OPCODE_EOF => break,
+                OPCODE_SELECTDB => {
+                    db = self.snapshot_db(reader.length()? as u64)?;
+                }
+                OPCODE_RESIZEDB => {
+                    reader.length()?;
+                    reader.length()?;
+                }
+                OPCODE_AUX => {
+                    reader.string()?;
+                    reader.string()?;
+                }
+                OPCODE_EXPIRETIME_MS => {
+                    expire_at = Some(u64::from_le_bytes(reader.array()?));
+                }
+                OPCODE_EXPIRETIME => {
+                    expire_at = Some(u32::from_le_bytes(reader.array()?) as u64 * 1000);
+                }
+                OPCODE_IDLE => {
+                    reader.length()?;
+                }
+                OPCODE_FREQ => {
+                    reader.u8()?;
+                }
+                OPCODE_MODULE_AUX | OPCODE_FUNCTION | OPCODE_FUNCTION2 => {
+                    return Err(corrupt("modules and functions are not supported"));
+                }
+                ty => {
+                    let key = reader.string()?.into();
+                    let value = self.read_value(&mut reader, ty)?;
+                    entries.push(Entry {
+                        db,
+                        key,
+                        expire_at: expire_at.take(),
+                        value,
+                    });
+                }
+            }
+        }
+        // files written with checksums disabled end with zeros
+        let actual = crc64(0, &data[..reader.pos]);
+        if version >= 5 {
+            let expected = u64::from_le_bytes(reader.array()?);
+            if expected != 0 && expected != actual {
+                return Err(PersistenceError::ChecksumMismatch { expected, actual });
+            }
+        }
+        Ok((entries, reader.pos))
+    }
+
+    // Reads back a value of type `ty` serialized as `encode_value` writes it.
+    #[cfg(feature = "disk")]
+    pub(super) fn decode_value(&self, ty: u8, data: &[u8]) -> Result<Value, PersistenceError> {
+        self.read_value(&mut Reader::new(data), ty)
+    }
+
+    fn read_value(&self, reader: &mut Reader, ty: u8) -> Result<Value, PersistenceError> {
+        let value = match ty {
+            TYPE_STRING => Value::String(self.string_value(reader.string()?.into())),
+            TYPE_LIST => {
+                let len = reader.length()?;
+                let items = (0..len)
+                    .map(|_| reader.string())
+                    .collect::<Result<_, _>>()?;
+                self.list_value(items)
+            }
+            TYPE_LIST_ZIPLIST => self.list_value(ziplist(&reader.string()?)?),
+            TYPE_LIST_QUICKLIST => {
+                let mut items = vec![];
+                for _ in 0..reader.length()? {
+                    items.extend(ziplist(&reader.string()?)?);
+                }
+                self.list_value(items)
+            }
+            TYPE_LIST_QUICKLIST_2 => {
+                let mut items = vec![];
+                for _ in 0..reader.length()? {
+                    match reader.length()? {
+                        QUICKLIST_NODE_PLAIN => items.push(reader.string()?),
+                        QUICKLIST_NODE_PACKED => items.extend(listpack(&reader.string()?)?),
+                        node => return Err(corrupt(format!("unknown quicklist node {}", node))),
+                    }
+                }
+                self.list_value(items)
+            }
+            TYPE_SET => {
+                let len = reader.length()?;
+                let members = (0..len)
+                    .map(|_| reader.string())
+                    .collect::<Result<_, _>>()?;
+                self.set_value(members)
+            }
+            TYPE_SET_INTSET => self.set_value(intset(&reader.string()?)?),
+            TYPE_SET_LISTPACK => self.set_value(listpack(&reader.string()?)?),
+            TYPE_ZSET | TYPE_ZSET_2 => {
+                let mut members = vec![];
+                for _ in 0..reader.length()? {
+                    let member = reader.string()?;
+                    let score = if ty == TYPE_ZSET_2 {
+                        f64::from_le_bytes(reader.array()?)
+                    } else {
+                        reader.old_double()?
+                    };
+                    members.push((member.into(), score));
+                }
+                Value::ZSet(members.into_iter().collect())
+            }
+            TYPE_ZSET_ZIPLIST | TYPE_ZSET_LISTPACK => {
+                let blob = reader.string()?;
+                let items = if ty == TYPE_ZSET_ZIPLIST {
+                    ziplist(&blob)?
+                } else {
+                    listpack(&blob)?
+                };
+                Value::ZSet(
+                    pairs(items)?
+                        .into_iter()
+                        .map(|(member, score)| Ok((member.into(), parse_score(&score)?)))
+                        .collect::<Result<ZSet, PersistenceError>>()?,
+                )
+            }
+            TYPE_HASH => {
+                let mut items = vec![];
+                for _ in 0..reader.length()? * 2 {
+                    items.push(reader.string()?);
+                }
+                self.hash_value(items)?
+            }
+            TYPE_HASH_ZIPLIST => self.hash_value(ziplist(&reader.string()?)?)?,
+            TYPE_HASH_LISTPACK => self.hash_value(listpack(&reader.string()?)?)?,
+            ty => return Err(corrupt(format!("unsupported value type {}", ty))),
+        };
+        Ok(value)
+    }
+
+    fn list_value(&self, items: Vec<Vec<u8>>) -> Value {
+        let fill = self.list_max_listpack_size();
+        let mut list = QuickList::default();
+        for item in items {
+            list.push_back(item.into(), fill);
+        }
+        Value::List(list)
+    }
+
+    fn set_value(&self, members: Vec<Vec<u8>>) -> Value {
+        let max_intset_entries = self.set_max_intset_entries();
+        let mut set = Set::default();
+        for member in members {
+            set.insert(member.into(), max_intset_entries);
+        }
+        Value::Set(set)
+    }
+
+    fn hash_value(&self, items: Vec<Vec<u8>>) -> Result<Value, PersistenceError> {
+        let limits = self.hash_limits();
+        let mut hash = Hash::default();
+        for (field, value) in pairs(items)? {
+            hash.insert(field.into(), value.into(), limits);
+        }
+        Ok(Value::Hash(hash))
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos == self.data.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], PersistenceError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or(PersistenceError::Truncated)?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], PersistenceError> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn u8(&mut self) -> Result<u8, PersistenceError> {
+        Ok(self.take(1)?[0])
+    }
+
+    // A length, or the kind of a specially encoded string.
+    fn raw_length(&mut self) -> Result<(u64, bool), PersistenceError> {
+        let first = self.u8()?;
+        let len = match first >> 6 {
+            0 => (first & 0x3f) as u64,
+            1 => ((first & 0x3f) as u64) << 8 | self.u8()? as u64,
+            2 if first == 0x80 => u32::from_be_bytes(self.array()?) as u64,
+            2 if first == 0x81 => u64::from_be_bytes(self.array()?),
+            2 => return Err(corrupt(format!("unknown length encoding {:#x}", first))),
+            _ => return Ok(((first & 0x3f) as u64, true)),
+        };
+        Ok((len, false))
+    }
+
+    fn length(&mut self) -> Result<usize, PersistenceError> {
+        match self.raw_length()? {
+            (len, false) => usize::try_from(len).map_err(|_| corrupt("length out of range")),
+            _ => Err(corrupt("expected a length")),
+        }
+    }
+
+    fn string(&mut self) -> Result<Vec<u8>, PersistenceError> {
+        let (len, encoded) = self.raw_length()?;
+        if !encoded {
+            let len = usize::try_from(len).map_err(|_| corrupt("length out of range"))?;
+            return Ok(self.take(len)?.to_vec());
+        }
+        let n = match len as u8 {
+            ENC_INT8 => self.u8()? as i8 as i64,
+            ENC_INT16 => i16::from_le_bytes(self.array()?) as i64,
+            ENC_INT32 => i32::from_le_bytes(self.array()?) as i64,
+            ENC_LZF => {
+                let compressed_len = self.length()?;
+                let len = self.length()?;
+                return lzf_decompress(self.take(compressed_len)?, len);
+            }
+            encoding => return Err(corrupt(format!("unknown string encoding {}", encoding))),
+        };
+        Ok(n.to_string().into_bytes())
+    }
+
+    // Score of the original sorted set type, stored as a string.
+    fn old_double(&mut self) -> Result<f64, PersistenceError> {
+        match self.u8()? {
+            253 => Ok(f64::NAN),
+            254 => Ok(f64::INFINITY),
+            255 => Ok(f64::NEG_INFINITY),
+            len => parse_score(self.take(len as usize)?),
+        }
+    }
+}
+
+fn ziplist(blob: &[u8]) -> Result<Vec<Vec<u8>>, PersistenceError> {
+    let mut reader = Reader::new(blob);
+    // total bytes, offset of the tail and number of entries
+    reader.take(10)?;
+    let mut items = vec![];
+    loop {
+        let prevlen = reader.u8()?;
+        if prevlen == 0xff {
+            return Ok(items);
+        }
+        if prevlen == 0xfe {
+            reader.take(4)?;
+        }
+        let encoding = reader.u8()?;
+        let item = match encoding {
+            0x00..=0x3f => reader.take(encoding as usize)?.to_vec(),
+            0x40..=0x7f => {
+                let len = ((encoding & 0x3f) as usize) << 8 | reader.u8()? as usize;
+                reader.take(len)?.to_vec()
+            }
+            0x80 => {
+                let len = u32::from_be_bytes(reader.array()?) as usize;
+                reader.take(len)?.to_vec()
+            }
+            0xc0 => integer(i16::from_le_bytes(reader.array()?) as i64),
+            0xd0 => integer(i32::from_le_bytes(reader.array()?) as i64),
+            0xe0 => integer(i64::from_le_bytes(reader.array()?)),
+            0xf0 => integer(i24(reader.array()?)),
+            0xfe => integer(reader.u8()? as i8 as i64),
+            0xf1..=0xfd => integer((encoding & 0x0f) as i64 - 1),
+            _ => return Err(corrupt(format!("unknown ziplist encoding {:#x}", encoding))),
+        };
+        items.push(item);
+    }
+}
+
+fn listpack(blob: &[u8]) -> Result<Vec<Vec<u8>>, PersistenceError> {
+    let mut reader = Reader::new(blob);
+    // total bytes and number of elements
+    reader.take(6)?;
+    let mut items = vec![];
+    loop {
+        let encoding = reader.u8()?;
+        let (item, len) = match encoding {
+            0xff => return Ok(items),
+            0x00..=0x7f => (integer(encoding as i64), 1),
+            0x80..=0xbf => {
+                let len = (encoding & 0x3f) as usize;
+                (reader.take(len)?.to_vec(), 1 + len)
+            }
+            0xc0..=0xdf => {
+                let n = ((encoding & 0x1f) as i64) << 8 | reader.u8()? as i64;
+                // 13 bit two's complement
+                let n = if n >= 1 << 12 { n - (1 << 13) } else { n };
+                (integer(n), 2)
+            }
+            0xe0..=0xef => {
+                let len = ((encoding & 0x0f) as usize) << 8 | reader.u8()? as usize;
+                (reader.take(len)?.to_vec(), 2 + len)
+            }
+            0xf0 => {
+                let len = u32::from_le_bytes(reader.array()?) as usize;
+                (reader.take(len)?.to_vec(), 5 + len)
+            }
+            0xf1 => (integer(i16::from_le_bytes(reader.array()?) as i64), 3),
+            0xf2 => (integer(i24(reader.array()?)), 4),
+            0xf3 => (integer(i32::from_le_bytes(reader.array()?) as i64), 5),
+            0xf4 => (integer(i64::from_le_bytes(reader.array()?)), 9),
+            _ => {
+                return Err(corrupt(format!(
+                    "unknown listpack encoding {:#x}",
+                    encoding
+                )))
+            }
+        };
+        // every element ends with its own length, for traversal from the tail
+        let backlen = match len {
+            0..=127 => 1,
+            128..=16382 => 2,
+            16383..=2097150 => 3,
+            2097151..=268435454 => 4,
+            _ => 5,
+        };
+        reader.take(backlen)?;
+        items.push(item);
+    }
+}
+
+fn intset(blob: &[u8]) -> Result<Vec<Vec<u8>>, PersistenceError> {
+    let mut reader = Reader::new(blob);
+    let width = u32::from_le_bytes(reader.array()?);
+    let len = u32::from_le_bytes(reader.array()?);
+    let mut members = vec![];
+    for _ in 0..len {
+        let n = match width {
+            2 => i16::from_le_bytes(reader.array()?) as i64,
+            4 => i32::from_le_bytes(reader.array()?) as i64,
+            8 => i64::from_le_bytes(reader.array()?),
+            _ => return Err(corrupt(format!("invalid intset encoding {}", width))),
+        };
+        members.push(integer(n));
+    }
+    if !reader.is_empty() {
+        return Err(corrupt("trailing data in an intset"));
+    }
+    Ok(members)
+}
+
+// Expands LZF-compressed data, as used by Redis for long strings.
+fn lzf_decompress(input: &[u8], len: usize) -> Result<Vec<u8>, PersistenceError> {
+    let mut out: Vec<u8> = Vec::with_capacity(len);
+    let mut reader = Reader::new(input);
+    while !reader.is_empty() {
+        let ctrl = reader.u8()? as usize;
+        if ctrl < 1 << 5 {
+            out.extend_from_slice(reader.take(ctrl + 1)?);
+            continue;
+        }
+        let mut run = ctrl >> 5;
+        if run == 7 {
+            run += reader.u8()? as usize;
+        }
+        let back = ((ctrl & 0x1f) << 8 | reader.u8()? as usize) + 1;
+        let start = out
+            .len()
+            .checked_sub(back)
+            .ok_or_else(|| corrupt("invalid LZF back reference"))?;
+        // the run may overlap the bytes it produces, so copy one byte at a time
+        for i in start..start + run + 2 {
+            out.push(out[i]);
+        }
+    }
+    if out.len() != len {
+        return Err(corrupt("LZF data does not match its length"));
+    }
+    Ok(out)
+}
+
+fn i24(bytes: [u8; 3]) -> i64 {
+    (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as i64
+}
+
+fn integer(n: i64) -> Vec<u8> {
+    n.to_string().into_bytes()
+}
+
+fn pairs(items: Vec<Item>) -> Result<Vec<(Item, Item)>, PersistenceError> {
+    if !items.len().is_multiple_of(2) {
+        return Err(corrupt("expected an even number of items"));
+    }
+    let mut items = items.into_iter();
+    Ok(std::iter::from_fn(|| Some((items.next()?, items.next()?))).collect())
+}
+
+fn parse_score(bytes: &[u8]) -> Result<f64, PersistenceError> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|score| score.parse().ok())
+        .ok_or_else(|| corrupt("invalid sorted set score"))
+}
+
+fn corrupt(reason: impl Into<String>) -> PersistenceError {
+    PersistenceError::Corrupt(reason.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::{ListDirection, StreamIdSpec, ZAddFlags},
+        BulkString,
+    };
+
+    #[test]
+    fn test_rdb_round_trip() {
+        let backend = Backend::new();
+        backend.set("string".into(), BulkString::from("value").into());
+        backend.set("number".into(), BulkString::from("42").into());
+        backend.expire(b"string", 10_000);
+        backend
+            .hset(
+                "hash".into(),
+                "field".into(),
+                BulkString::from("value").into(),
+            )
+            .unwrap();
+        backend
+            .sadd("set".into(), BulkString::from("1").into())
+            .unwrap();
+        backend
+            .sadd("set".into(), BulkString::from("a").into())
+            .unwrap();
+        backend
+            .push(
+                "list".into(),
+                vec![BulkString::from("a").into(), BulkString::from("b").into()],
+                ListDirection::Right,
+            )
+            .unwrap();
+        backend
+            .zadd(
+                "zset".into(),
+                vec![("member".into(), 0.1)],
+                ZAddFlags::default(),
+            )
+            .unwrap();
+        let fields = vec![("field".to_string(), "value".into())];
+        backend
+            .xadd("stream".into(), StreamIdSpec::Auto, fields, None)
+            .unwrap();
+
+        let data = write_rdb(vec![], backend.capture()).unwrap();
+        assert!(data.starts_with(b"REDIS0009"));
+        let loaded = Backend::new();
+        let (entries, len) = loaded.read_rdb(&data).unwrap();
+        assert_eq!(len, data.len());
+        // streams are left out
+        assert_eq!(loaded.restore_entries(entries), 6);
+        assert_eq!(
+            loaded.get(b"number").unwrap(),
+            Some(BulkString::from("42").into())
+        );
+        assert!(loaded.ttl(b"string").flatten().is_some());
+        assert_eq!(
+            loaded.hgetall(b"hash").unwrap(),
+            backend.hgetall(b"hash").unwrap()
+        );
+        assert_eq!(loaded.smembers(b"set").unwrap().map(|m| m.len()), Some(2));
+        assert_eq!(
+            loaded.lrange(b"list", 0, -1).unwrap(),
+            backend.lrange(b"list", 0, -1).unwrap()
+        );
+        assert_eq!(loaded.zscore(b"zset", b"member").unwrap(), Some(0.1));
+
+        let mut corrupted = data.clone();
+        corrupted[20] ^= 1;
+        assert!(matches!(
+            loaded.read_rdb(&corrupted),
+            Err(PersistenceError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_read_compact_encodings() {
+        let mut data = b"REDIS0011".to_vec();
+        let mut entry = |ty: u8, key: &[u8], payload: &[u8]| {
+            data.push(ty);
+            write_string(&mut data, key);
+            data.extend_from_slice(payload);
+        };
+        // listpack of "field", "value", 7 and -300
+        let mut listpack = vec![0, 0, 0, 0, 4, 0];
+        listpack.extend_from_slice(&[0x85, b'f', b'i', b'e', b'l', b'd', 6]);
+        listpack.extend_from_slice(&[0x85, b'v', b'a', b'l', b'u', b'e', 6]);
+        listpack.extend_from_slice(&[7, 1]);
+        listpack.extend_from_slice(&[0xde, 0xd4, 2, 0xff]);
+        let mut payload = vec![];
+        write_string(&mut payload, &listpack);
+        entry(TYPE_HASH_LISTPACK, b"hash", &payload);
+
+        // quicklist with a plain node and a listpack node
+        let mut payload = vec![2, QUICKLIST_NODE_PLAIN as u8];
+        write_string(&mut payload, b"plain");
+        payload.push(QUICKLIST_NODE_PACKED as u8);
+        write_string(&mut payload, &listpack);
+        entry(TYPE_LIST_QUICKLIST_2, b"list", &payload);
+
+        // intset of 16 bit integers
+        let mut payload = vec![];
+        write_string(&mut payload, &[2, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0xff, 0xff]);
+        entry(TYPE_SET_INTSET, b"set", &payload);
+
+        // ziplist of "a", 1.5, 12 and 2
+        let mut ziplist = vec![0; 10];
+        ziplist.extend_from_slice(&[0, 1, b'a']);
+        ziplist.extend_from_slice(&[3, 3, b'1', b'.', b'5']);
+        ziplist.extend_from_slice(&[5, 0xfe, 12]);
+        ziplist.extend_from_slice(&[3, 0xf3, 0xff]);
+        let mut payload = vec![];
+        write_string(&mut payload, &ziplist);
+        entry(TYPE_ZSET_ZIPLIST, b"zset", &payload);
+
+        // LZF compressed "a" repeated ten times, and an integer encoded string
+        entry(
+            TYPE_STRING,
+            b"lzf",
+            &[0xc0 | ENC_LZF, 5, 10, 0, b'a', 0xe0, 0, 0],
+        );
+        entry(TYPE_STRING, b"int", &[0xc0 | ENC_INT16, 0x39, 0x30]);
+        data.push(OPCODE_EOF);
+        data.extend_from_slice(&[0; 8]);
+
+        let backend = Backend::new();
+        let (entries, _) = backend.read_rdb(&data).unwrap();
+        assert_eq!(backend.restore_entries(entries), 6);
+        assert_eq!(
+            backend.hget(b"hash", b"7").unwrap(),
+            Some(BulkString::from("-300").into())
+        );
+        let list = backend.lrange(b"list", 0, -1).unwrap();
+        assert_eq!(list.len(), 5);
+        assert_eq!(list[4], BulkString::from("-300").into());
+        assert!(backend
+            .sismember(b"set", &BulkString::from("-1").into())
+            .unwrap());
+        assert_eq!(backend.zscore(b"zset", b"a").unwrap(), Some(1.5));
+        assert_eq!(backend.zscore(b"zset", b"12").unwrap(), Some(2.0));
+        assert_eq!(
+            backend.get(b"lzf").unwrap(),
+            Some(BulkString::new(vec![b'a'; 10]).into())
+        );
+        assert_eq!(
+            backend.get(b"int").unwrap(),
+            Some(BulkString::from("12345").into())
+        );
+
+        assert!(matches!(
+            backend.read_rdb(b"REDIS0099"),
+            Err(PersistenceError::UnsupportedVersion(99))
+        ));
+        assert!(matches!(
+            backend.read_rdb(&data[..data.len() - 12]),
+            Err(PersistenceError::Truncated)
+        ));
+    }
+}
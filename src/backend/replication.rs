@@ -0,0 +1,760 @@
+// Replication state. A master streams the write commands it executes to its replicas, once it
+// has sent them a snapshot of the dataset; a replica loads that snapshot and applies the
+// stream. The networking side lives in `crate::replication`. A replica whose stream piles up
+// beyond the replica output buffer limit is dropped, and has to synchronize again.
+
+use super::{
+    client::{ClientClass, OutputBuffer},
+    db::select_command,
+    persistence::{corrupt, write_snapshot_to, Entry, PersistenceError},
+    Backend,
+};
+use crate::{BulkString, RespArray, RespEncoder, RespFrame};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    mem,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, AtomicU16, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, RwLock,
+    },
+    thread,
+    time::Instant,
+};
+use tokio::sync::{futures::Notified, mpsc, watch, Notify};
+use tracing::{info, warn};
+
+/// Address of the master a replica connects to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MasterAddr {
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Debug)]
+pub(super) struct Replication {
+    // watched by the task keeping the link with the master up; `None` on a master
+    master: watch::Sender<Option<MasterAddr>>,
+    // id of the history of the dataset, which replicas take from their master
+    replid: RwLock<String>,
+    // the previous id of a promoted replica, valid up to `second_offset`
+    replid2: RwLock<String>,
+    second_offset: AtomicI64,
+    // bytes of replication stream produced so far, or applied on a replica
+    offset: AtomicU64,
+    replicas: Mutex<Vec<Replica>>,
+    // the database the commands streamed to the replicas run against, `usize::MAX` until the
+    // stream selects one; only changed holding the lock on the replicas
+    selected: AtomicUsize,
+    next_replica_id: AtomicU64,
+    // whether a replica is synchronized with its master and applying its stream
+    link_up: AtomicBool,
+    // advertised to the master, so it can list this replica
+    listening_port: AtomicU16,
+    read_only: AtomicBool,
+    diskless_sync: AtomicBool,
+    tls: RwLock<ReplicationTls>,
+    // wakes up the clients waiting for acknowledgements
+    ack_notify: Notify,
+    failover: Mutex<FailoverState>,
+    // holds write commands while a failover waits for its target to catch up
+    writes_paused: watch::Sender<bool>,
+    // held by a write from when it runs until it is propagated
+    order: Mutex<()>,
+}
+
+impl Default for Replication {
+    fn default() -> Self {
+        Self {
+            master: watch::channel(None).0,
+            replid: RwLock::new(new_replid()),
+            replid2: RwLock::new("0".repeat(40)),
+            second_offset: AtomicI64::new(-1),
+            offset: AtomicU64::default(),
+            replicas: Mutex::default(),
+            selected: AtomicUsize::new(usize::MAX),
+            next_replica_id: AtomicU64::default(),
+            link_up: AtomicBool::default(),
+            listening_port: AtomicU16::new(6379),
+            read_only: AtomicBool::new(true),
+            diskless_sync: AtomicBool::new(true),
+            tls: RwLock::default(),
+            ack_notify: Notify::new(),
+            failover: Mutex::default(),
+            writes_paused: watch::channel(false).0,
+            order: Mutex::default(),
+        }
+    }
+}
+
+// A replica connected to this server.
+#[derive(Debug)]
+struct Replica {
+    id: u64,
+    ip: String,
+    port: u16,
+    // the replication stream, written to the replica's socket by its connection
+    sender: mpsc::UnboundedSender<Vec<u8>>,
+    // the bytes of the stream the connection didn't write yet
+    output: Arc<OutputBuffer>,
+    // whether it received its snapshot and is applying the stream
+    online: bool,
+    // offset of the stream the replica last acknowledged, and when
+    ack_offset: u64,
+    last_ack: Instant,
+}
+
+/// TLS settings of the link with the master, apart from those of client connections. The
+/// certificate and key, if set, authenticate this replica to its master.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplicationTls {
+    pub enabled: bool,
+    pub ca_cert_file: Option<PathBuf>,
+    pub cert_file: Option<PathBuf>,
+    pub key_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicaInfo {
+    pub ip: String,
+    pub port: u16,
+    pub online: bool,
+    pub ack_offset: u64,
+    /// seconds since the replica last acknowledged the stream
+    pub lag: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FailoverState {
+    #[default]
+    NoFailover,
+    /// waiting for the target replica to catch up, with writes paused
+    WaitingForSync,
+    /// connecting to the target replica, which takes over as the master
+    InProgress(MasterAddr),
+}
+
+impl FailoverState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailoverState::NoFailover => "no-failover",
+            FailoverState::WaitingForSync => "waiting-for-sync",
+            FailoverState::InProgress(_) => "failover-in-progress",
+        }
+    }
+}
+
+/// What a replica needs to synchronize: the dataset at `offset`, and the stream after it.
+#[derive(Debug)]
+pub struct FullSync {
+    pub id: u64,
+    pub replid: String,
+    pub offset: u64,
+    pub snapshot: SyncSnapshot,
+    pub stream: mpsc::UnboundedReceiver<Vec<u8>>,
+    /// the bytes of the stream not written out yet, which the connection counts down
+    pub output: Arc<OutputBuffer>,
+}
+
+#[derive(Debug)]
+pub enum SyncSnapshot {
+    /// written to disk before it is sent, so its length is known
+    File(Vec<u8>),
+    /// written while it is sent, in chunks; the channel closes once it is complete
+    Diskless(mpsc::Receiver<io::Result<Vec<u8>>>),
+}
+
+// size of the chunks a diskless sync sends the snapshot in
+const CHUNK_SIZE: usize = 64 * 1024;
+
+impl Backend {
+    /// The master this server replicates, if it is a replica.
+    pub fn master(&self) -> Option<MasterAddr> {
+        self.replication.master.borrow().clone()
+    }
+
+    /// Makes this server a replica of `master`, or a master again with `None`. The link is
+    /// set up by [`crate::replication::replicate`].
+    pub fn set_master(&self, master: Option<MasterAddr>) {
+        self.replication.link_up.store(false, Ordering::Relaxed);
+        let promoted = master.is_none();
+        if self.replication.master.send_replace(master).is_some() && promoted {
+            self.shift_replid();
+        }
+    }
+
+    // Starts a new history as a replica becomes a master, keeping the previous id so its
+    // replicas can tell the dataset is the same up to the current offset.
+    fn shift_replid(&self) {
+        let mut replid = self
+            .replication
+            .replid
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        let previous = mem::replace(&mut *replid, new_replid());
+        *self
+            .replication
+            .replid2
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = previous;
+        self.replication
+            .second_offset
+            .store(self.repl_offset() as i64 + 1, Ordering::Relaxed);
+        info!("Promoted to master, new replication id {}", replid);
+    }
+
+    /// Starts a new history with no previous one, as DEBUG CHANGE-REPL-ID does, so the
+    /// replicas can't continue from their offset and resynchronize in full.
+    pub fn change_replid(&self) {
+        let replid = new_replid();
+        info!("Changed the replication id to {}", replid);
+        *self
+            .replication
+            .replid
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = replid;
+        *self
+            .replication
+            .replid2
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = "0".repeat(40);
+        self.replication.second_offset.store(-1, Ordering::Relaxed);
+    }
+
+    /// The replication id before the last promotion, and the offset up to which it applies.
+    pub fn replid2(&self) -> (String, Option<u64>) {
+        let replid2 = self
+            .replication
+            .replid2
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let offset = self.replication.second_offset.load(Ordering::Relaxed);
+        (replid2, offset.try_into().ok())
+    }
+
+    pub fn failover_state(&self) -> FailoverState {
+        self.failover().clone()
+    }
+
+    /// Starts a failover: writes are held until it hands over or is aborted.
+    pub fn start_failover(&self) -> Result<(), String> {
+        let mut failover = self.failover();
+        if *failover != FailoverState::NoFailover {
+            return Err("FAILOVER already in progress.".to_string());
+        }
+        *failover = FailoverState::WaitingForSync;
+        self.replication.writes_paused.send_replace(true);
+        Ok(())
+    }
+
+    /// Hands over to `target` once it caught up: this server becomes its replica, and asks it
+    /// to take over as the master when connecting.
+    pub fn hand_over(&self, target: MasterAddr) {
+        *self.failover() = FailoverState::InProgress(target.clone());
+        self.set_master(Some(target));
+        self.replication.writes_paused.send_replace(false);
+    }
+
+    /// Ends the failover. Unless it completed, this server stays or becomes the master again.
+    /// Returns whether a failover was in progress.
+    pub fn end_failover(&self, completed: bool) -> bool {
+        let state = mem::take(&mut *self.failover());
+        if state == FailoverState::NoFailover {
+            return false;
+        }
+        if !completed && matches!(state, FailoverState::InProgress(_)) {
+            self.set_master(None);
+        }
+        self.replication.writes_paused.send_replace(false);
+        // wakes up the client waiting for the target to catch up
+        self.replication.ack_notify.notify_waiters();
+        true
+    }
+
+    /// Resolves once write commands are no longer held.
+    pub async fn writes_unpaused(&self) {
+        let mut paused = self.replication.writes_paused.subscribe();
+        let _ = paused.wait_for(|paused| !paused).await;
+    }
+
+    pub fn watch_master(&self) -> watch::Receiver<Option<MasterAddr>> {
+        self.replication.master.subscribe()
+    }
+
+    pub fn replid(&self) -> String {
+        self.replication
+            .replid
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn repl_offset(&self) -> u64 {
+        self.replication.offset.load(Ordering::Acquire)
+    }
+
+    pub fn master_link_up(&self) -> bool {
+        self.replication.link_up.load(Ordering::Relaxed)
+    }
+
+    pub fn listening_port(&self) -> u16 {
+        self.replication.listening_port.load(Ordering::Relaxed)
+    }
+
+    pub fn set_listening_port(&self, port: u16) {
+        self.replication
+            .listening_port
+            .store(port, Ordering::Relaxed);
+    }
+
+    /// Whether a replica refuses write commands from its clients. The master link is not
+    /// affected.
+    pub fn replica_read_only(&self) -> bool {
+        self.replication.read_only.load(Ordering::Relaxed)
+    }
+
+    pub fn set_replica_read_only(&self, enabled: bool) {
+        self.replication.read_only.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether write commands from clients are refused, as this is a read-only replica.
+    pub fn rejects_writes(&self) -> bool {
+        self.replica_read_only() && self.master().is_some()
+    }
+
+    pub fn has_replicas(&self) -> bool {
+        !self.replicas().is_empty()
+    }
+
+    pub fn replica_infos(&self) -> Vec<ReplicaInfo> {
+        self.replicas()
+            .iter()
+            .map(|replica| ReplicaInfo {
+                ip: replica.ip.clone(),
+                port: replica.port,
+                online: replica.online,
+                ack_offset: replica.ack_offset,
+                lag: replica.last_ack.elapsed().as_secs(),
+            })
+            .collect()
+    }
+
+    /// Whether executed write commands have to be kept for the AOF or the replicas.
+    pub fn propagating(&self) -> bool {
+        self.appendonly() || self.has_replicas()
+    }
+
+    /// Logs a write command to the AOF and streams it to the replicas. The replicas of a
+    /// replica get the stream of its master instead, so writes made on a writable replica
+    /// stay local to it.
+    pub fn propagate(&self, command: &RespFrame) {
+        if self.has_replicas() && self.master().is_none() {
+            let mut replicas = self.replicas();
+            let db = self.db_index();
+            if self.replication.selected.swap(db, Ordering::Relaxed) != db {
+                self.stream_to(&mut replicas, &select_command(db));
+            }
+            self.stream_to(&mut replicas, command);
+        }
+        self.feed_aof(command);
+    }
+
+    /// Held by a propagated write from when it runs until it is propagated, so the writes
+    /// running at once are logged and streamed in the order they ran. Transactions and scripts,
+    /// which run alone, don't need it.
+    pub fn propagation_order(&self) -> MutexGuard<'_, ()> {
+        self.replication
+            .order
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Appends a frame to the replication stream. A replica calls it with what its master
+    /// sends, to keep its offset in step.
+    pub fn feed_replicas(&self, frame: &RespFrame) {
+        let mut replicas = self.replicas();
+        // the master switches databases in the stream on its own
+        self.replication
+            .selected
+            .store(usize::MAX, Ordering::Relaxed);
+        self.stream_to(&mut replicas, frame);
+    }
+
+    fn stream_to(&self, replicas: &mut Vec<Replica>, frame: &RespFrame) {
+        let bytes = frame.encode();
+        self.replication
+            .offset
+            .fetch_add(bytes.len() as u64, Ordering::AcqRel);
+        replicas.retain(|replica| {
+            if !self.queue_output(&replica.output, bytes.len()) {
+                warn!(
+                    "Dropping replica {}:{} for overcoming its output buffer limit",
+                    replica.ip, replica.port
+                );
+                return false;
+            }
+            replica.sender.send(bytes.clone()).is_ok()
+        });
+    }
+
+    /// Registers a replica connecting from `ip` and snapshots the dataset for it. Writes
+    /// executed from then on are queued in the returned stream.
+    pub fn full_sync(&self, ip: String, port: u16) -> Result<FullSync, PersistenceError> {
+        let id = self
+            .replication
+            .next_replica_id
+            .fetch_add(1, Ordering::Relaxed);
+        let (sender, stream) = mpsc::unbounded_channel();
+        let output = Arc::new(OutputBuffer::new(ClientClass::Replica));
+        let (offset, entries) = {
+            let mut replicas = self.replicas();
+            replicas.push(Replica {
+                id,
+                ip,
+                port,
+                sender,
+                output: output.clone(),
+                online: false,
+                ack_offset: 0,
+                last_ack: Instant::now(),
+            });
+            // so the next command streamed tells the replica its database
+            self.replication
+                .selected
+                .store(usize::MAX, Ordering::Relaxed);
+            (self.repl_offset(), self.fork_capture())
+        };
+        let snapshot = if self.repl_diskless_sync() {
+            self.stream_sync_snapshot(entries)
+        } else {
+            let path = self
+                .dir()
+                .join(format!("temp-repl-{}-{}.rdb", process::id(), id));
+            let snapshot = self.write_sync_snapshot(&path, entries);
+            let _ = fs::remove_file(&path);
+            snapshot.map(SyncSnapshot::File)
+        };
+        Ok(FullSync {
+            id,
+            replid: self.replid(),
+            offset,
+            snapshot: snapshot.inspect_err(|_| self.remove_replica(id))?,
+            stream,
+            output,
+        })
+    }
+
+    /// Whether full syncs send the snapshot as it is written instead of writing it to disk
+    /// first.
+    pub fn repl_diskless_sync(&self) -> bool {
+        self.replication.diskless_sync.load(Ordering::Relaxed)
+    }
+
+    pub fn set_repl_diskless_sync(&self, enabled: bool) {
+        self.replication
+            .diskless_sync
+            .store(enabled, Ordering::Relaxed);
+    }
+
+    /// Read when connecting to the master, so changes apply from the next connection.
+    pub fn replication_tls(&self) -> ReplicationTls {
+        self.replication
+            .tls
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn set_replication_tls(&self, tls: ReplicationTls) {
+        *self
+            .replication
+            .tls
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = tls;
+    }
+
+    pub fn replica_online(&self, id: u64) {
+        if let Some(replica) = self.replicas().iter_mut().find(|r| r.id == id) {
+            replica.online = true;
+        }
+    }
+
+    /// Records that a replica applied the stream up to `offset`.
+    pub fn replica_ack(&self, id: u64, offset: u64) {
+        if let Some(replica) = self.replicas().iter_mut().find(|r| r.id == id) {
+            replica.ack_offset = offset;
+            replica.last_ack = Instant::now();
+        }
+        self.replication.ack_notify.notify_waiters();
+    }
+
+    /// Number of replicas that acknowledged the stream up to `offset` at least.
+    pub fn acked_replicas(&self, offset: u64) -> usize {
+        self.replicas()
+            .iter()
+            .filter(|replica| replica.online && replica.ack_offset >= offset)
+            .count()
+    }
+
+    /// Whether `addr` is the address of a connected replica.
+    pub fn is_replica(&self, addr: &MasterAddr) -> bool {
+        self.replicas()
+            .iter()
+            .any(|replica| replica.ip == addr.host && replica.port == addr.port)
+    }
+
+    /// The address of an online replica that acknowledged the stream up to `offset`, `target`
+    /// if given.
+    pub fn caught_up_replica(
+        &self,
+        offset: u64,
+        target: Option<&MasterAddr>,
+    ) -> Option<MasterAddr> {
+        self.replicas()
+            .iter()
+            .filter(|replica| replica.online && replica.ack_offset >= offset)
+            .map(|replica| MasterAddr {
+                host: replica.ip.clone(),
+                port: replica.port,
+            })
+            .find(|addr| target.is_none_or(|target| target == addr))
+    }
+
+    /// Asks the replicas to acknowledge the stream right away.
+    pub fn request_acks(&self) {
+        if self.has_replicas() {
+            let getack = ["REPLCONF", "GETACK", "*"].map(|arg| BulkString::from(arg).into());
+            self.feed_replicas(&RespArray::new(getack).into());
+        }
+    }
+
+    /// Returns a future that resolves the next time a replica acknowledges the stream.
+    pub fn replica_acked(&self) -> Notified<'_> {
+        self.replication.ack_notify.notified()
+    }
+
+    pub fn remove_replica(&self, id: u64) {
+        self.replicas().retain(|replica| replica.id != id);
+    }
+
+    /// Replaces the dataset with the snapshot received from the master, taking on its
+    /// replication id and offset. Returns the number of keys loaded.
+    pub fn load_master_snapshot(
+        &self,
+        replid: String,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<usize, PersistenceError> {
+        let (entries, len) = self.read_snapshot(data)?;
+        if len != data.len() {
+            return Err(corrupt("trailing data after the end marker"));
+        }
+        // the replicas of this server followed the history being replaced, they have to
+        // synchronize again
+        let dropped = mem::take(&mut *self.replicas());
+        if !dropped.is_empty() {
+            info!(
+                "Disconnecting {} replicas to have them synchronize again",
+                dropped.len()
+            );
+        }
+        self.flushall(false);
+        let loaded = self.restore_entries(entries);
+        *self
+            .replication
+            .replid
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = replid;
+        self.replication.offset.store(offset, Ordering::Release);
+        self.replication.link_up.store(true, Ordering::Relaxed);
+        info!(
+            "MASTER <-> REPLICA sync: loaded {} keys at offset {}",
+            loaded, offset
+        );
+        Ok(loaded)
+    }
+
+    pub fn set_master_link_down(&self) {
+        self.replication.link_up.store(false, Ordering::Relaxed);
+    }
+
+    // Writes the snapshot for a full sync to `path` and reads it back.
+    fn write_sync_snapshot(&self, path: &Path, entries: Vec<Entry>) -> io::Result<Vec<u8>> {
+        let writer = BufWriter::new(File::create(path)?);
+        let mut writer = write_snapshot_to(writer, entries, self.dump_format())?;
+        writer.flush()?;
+        drop(writer);
+        fs::read(path)
+    }
+
+    // Writes the snapshot for a full sync on a background thread, handing it over in chunks.
+    // The channel is bounded, so the thread doesn't get ahead of the replica.
+    fn stream_sync_snapshot(&self, entries: Vec<Entry>) -> io::Result<SyncSnapshot> {
+        let (sender, receiver) = mpsc::channel(4);
+        let format = self.dump_format();
+        thread::Builder::new()
+            .name("repl-diskless".to_string())
+            .spawn(move || {
+                let writer = ChunkWriter {
+                    sender: sender.clone(),
+                    buf: vec![],
+                };
+                let result = write_snapshot_to(writer, entries, format)
+                    .and_then(|mut writer| writer.flush());
+                if let Err(e) = result {
+                    warn!("Diskless sync of the snapshot failed: {}", e);
+                    let _ = sender.blocking_send(Err(e));
+                }
+            })?;
+        Ok(SyncSnapshot::Diskless(receiver))
+    }
+
+    fn failover(&self) -> MutexGuard<'_, FailoverState> {
+        self.replication
+            .failover
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn replicas(&self) -> MutexGuard<'_, Vec<Replica>> {
+        self.replication
+            .replicas
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Passes what is written on in chunks of `CHUNK_SIZE` bytes.
+struct ChunkWriter {
+    sender: mpsc::Sender<io::Result<Vec<u8>>>,
+    buf: Vec<u8>,
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        self.sender
+            .blocking_send(Ok(mem::take(&mut self.buf)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the replica went away"))
+    }
+}
+
+// 40 hex characters, like the replication ids of Redis.
+pub(super) fn new_replid() -> String {
+    (0..20)
+        .map(|_| format!("{:02x}", rand::random::<u8>()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_sync_and_stream() {
+        let master = Backend::new();
+        let dir = std::env::temp_dir().join(format!("simple-redis-repl-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        master.set_dir(dir.clone());
+        master.set("key".into(), BulkString::from("value").into());
+        assert_eq!(master.replid().len(), 40);
+
+        master.set_repl_diskless_sync(false);
+        let mut sync = master.full_sync("127.0.0.1".to_string(), 6380).unwrap();
+        assert!(master.propagating());
+        assert_eq!(
+            master.replica_infos(),
+            [ReplicaInfo {
+                ip: "127.0.0.1".to_string(),
+                port: 6380,
+                online: false,
+                ack_offset: 0,
+                lag: 0,
+            }]
+        );
+        let command: RespFrame = crate::RespArray::new([
+            BulkString::from("DEL").into(),
+            BulkString::from("key").into(),
+        ])
+        .into();
+        master.propagate(&command);
+        // the stream starts by selecting the database
+        let select = sync.stream.try_recv().unwrap();
+        assert_eq!(select, select_command(0).encode());
+        let streamed = sync.stream.try_recv().unwrap();
+        assert_eq!(streamed, command.encode());
+        assert_eq!(
+            master.repl_offset(),
+            sync.offset + (select.len() + streamed.len()) as u64
+        );
+
+        let replica = Backend::new();
+        assert!(!replica.rejects_writes());
+        replica.set_master(Some(MasterAddr {
+            host: "localhost".to_string(),
+            port: 6379,
+        }));
+        assert!(replica.rejects_writes());
+        replica.set_replica_read_only(false);
+        assert!(!replica.rejects_writes());
+        replica.set("stale".into(), BulkString::from("value").into());
+        let SyncSnapshot::File(snapshot) = &sync.snapshot else {
+            panic!("expected a snapshot written to disk");
+        };
+        let loaded = replica
+            .load_master_snapshot(sync.replid.clone(), sync.offset, snapshot)
+            .unwrap();
+        assert_eq!(loaded, 1);
+        assert_eq!(replica.get(b"stale").unwrap(), None);
+        assert_eq!(replica.replid(), master.replid());
+        assert!(replica.master_link_up());
+
+        master.replica_online(sync.id);
+        assert_eq!(master.acked_replicas(master.repl_offset()), 0);
+        master.replica_ack(sync.id, master.repl_offset());
+        assert_eq!(master.acked_replicas(master.repl_offset()), 1);
+        master.request_acks();
+        assert_eq!(master.acked_replicas(master.repl_offset()), 0);
+
+        master.remove_replica(sync.id);
+        assert!(!master.has_replicas());
+        assert!(fs::read_dir(&dir).unwrap().next().is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_diskless_full_sync() {
+        let master = Backend::new();
+        for i in 0..10_000 {
+            master.set(format!("key{}", i).into(), BulkString::from("value").into());
+        }
+        let sync = master.full_sync("127.0.0.1".to_string(), 6380).unwrap();
+        let SyncSnapshot::Diskless(mut chunks) = sync.snapshot else {
+            panic!("expected a diskless snapshot");
+        };
+        let mut snapshot = vec![];
+        while let Some(chunk) = chunks.blocking_recv() {
+            let chunk = chunk.unwrap();
+            assert!(chunk.len() <= CHUNK_SIZE * 2);
+            snapshot.extend(chunk);
+        }
+        let replica = Backend::new();
+        let loaded = replica
+            .load_master_snapshot(sync.replid, sync.offset, &snapshot)
+            .unwrap();
+        assert_eq!(loaded, 10_000);
+    }
+}
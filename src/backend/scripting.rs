@@ -0,0 +1,182 @@
+// Lua scripting. The scripts run by EVAL or loaded by SCRIPT LOAD are cached by the SHA1 of
+// their body, so EVALSHA can run them again without sending them. A script running for longer
+// than busy-reply-threshold makes the other commands fail with BUSY instead of waiting for it,
+// and may then be killed unless it already wrote.
+
+use super::Backend;
+use std::{
+    collections::HashMap,
+    future,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::watch,
+    time::{self, Instant},
+};
+
+#[derive(Debug)]
+pub(super) struct Scripts {
+    bodies: Mutex<HashMap<String, Arc<str>>>,
+    // when the running script started, if one is running
+    running: watch::Sender<Option<Instant>>,
+    // whether the running script wrote, which makes it unkillable
+    wrote: AtomicBool,
+    killed: AtomicBool,
+    // in milliseconds
+    busy_reply_threshold: AtomicUsize,
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        Self {
+            bodies: Mutex::default(),
+            running: watch::channel(None).0,
+            wrote: AtomicBool::new(false),
+            killed: AtomicBool::new(false),
+            busy_reply_threshold: AtomicUsize::new(5000),
+        }
+    }
+}
+
+impl Scripts {
+    fn bodies(&self) -> MutexGuard<'_, HashMap<String, Arc<str>>> {
+        self.bodies.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// The lower-case hex SHA1 of a script body, which EVALSHA refers to it by.
+fn script_sha(body: &str) -> String {
+    sha1_smol::Sha1::from(body).hexdigest()
+}
+
+impl Backend {
+    /// Caches a script body, returning its SHA1.
+    pub fn cache_script(&self, body: &str) -> String {
+        let sha = script_sha(body);
+        self.scripts
+            .bodies()
+            .entry(sha.clone())
+            .or_insert_with(|| body.into());
+        sha
+    }
+
+    /// The body of the cached script with the SHA1 `sha`, in any case.
+    pub fn cached_script(&self, sha: &str) -> Option<Arc<str>> {
+        self.scripts
+            .bodies()
+            .get(&sha.to_ascii_lowercase())
+            .cloned()
+    }
+
+    pub fn busy_reply_threshold(&self) -> usize {
+        self.scripts.busy_reply_threshold.load(Ordering::Relaxed)
+    }
+
+    pub fn set_busy_reply_threshold(&self, millis: usize) {
+        self.scripts
+            .busy_reply_threshold
+            .store(millis, Ordering::Relaxed);
+    }
+
+    /// Marks a script as running. Scripts run one at a time, holding the transaction lock.
+    pub fn start_script(&self) {
+        self.scripts.wrote.store(false, Ordering::Relaxed);
+        self.scripts.killed.store(false, Ordering::Relaxed);
+        self.scripts.running.send_replace(Some(Instant::now()));
+    }
+
+    /// Marks the running script as done, returning whether it was killed.
+    pub fn end_script(&self) -> bool {
+        self.scripts.running.send_replace(None);
+        self.scripts.killed.swap(false, Ordering::Relaxed)
+    }
+
+    /// Records that the running script wrote to the dataset, which can't be undone by killing
+    /// it anymore.
+    pub fn script_wrote(&self) {
+        self.scripts.wrote.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the running script was asked to stop, which it checks as it runs.
+    pub fn script_killed(&self) -> bool {
+        self.scripts.killed.load(Ordering::Relaxed)
+    }
+
+    /// Asks the running script to stop, unless it already wrote.
+    pub fn kill_script(&self) -> Result<(), String> {
+        if self.scripts.running.borrow().is_none() {
+            return Err("NOTBUSY No scripts in execution right now.".to_string());
+        }
+        if self.scripts.wrote.load(Ordering::Relaxed) {
+            return Err(
+                "UNKILLABLE Sorry the script already executed write commands against \
+                the dataset. You can either wait the script termination or use the SHUTDOWN \
+                NOSAVE command."
+                    .to_string(),
+            );
+        }
+        self.scripts.killed.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Resolves once a script has been running for longer than busy-reply-threshold.
+    pub async fn script_busy(&self) {
+        let mut running = self.scripts.running.subscribe();
+        loop {
+            let started = *running.borrow_and_update();
+            let Some(started) = started else {
+                if running.changed().await.is_err() {
+                    future::pending::<()>().await;
+                }
+                continue;
+            };
+            let threshold = Duration::from_millis(self.busy_reply_threshold() as u64);
+            tokio::select! {
+                _ = time::sleep_until(started + threshold) => return,
+                _ = running.changed() => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_cache() {
+        let backend = Backend::new();
+        assert_eq!(
+            script_sha("return 1"),
+            "e0e1f9fabfc9d4800c877a703b823ac0578ff8db"
+        );
+        assert_eq!(backend.cached_script(&script_sha("return 1")), None);
+        let sha = backend.cache_script("return 1");
+        assert_eq!(backend.cached_script(&sha).as_deref(), Some("return 1"));
+        assert_eq!(
+            backend.cached_script(&sha.to_ascii_uppercase()).as_deref(),
+            Some("return 1")
+        );
+    }
+
+    #[test]
+    fn test_kill_script() {
+        let backend = Backend::new();
+        assert!(backend.kill_script().unwrap_err().starts_with("NOTBUSY"));
+
+        backend.start_script();
+        assert!(backend.kill_script().is_ok());
+        assert!(backend.script_killed());
+        assert!(backend.end_script());
+
+        backend.start_script();
+        assert!(!backend.script_killed());
+        backend.script_wrote();
+        assert!(backend.kill_script().unwrap_err().starts_with("UNKILLABLE"));
+        assert!(!backend.end_script());
+    }
+}
@@ -0,0 +1,142 @@
+// Sets whose members are all integers are kept as a sorted vector of i64, a fraction of the
+// size of a hash set of strings. Like Redis' intset, a set is upgraded for good to the hash
+// table encoding once it gets a non-integer member or grows past the configured limit.
+
+use super::encoding::parse_integer;
+use bytes::Bytes;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Set {
+    IntSet(Vec<i64>),
+    HashTable(HashSet<Bytes>),
+}
+
+impl Default for Set {
+    fn default() -> Self {
+        Set::IntSet(vec![])
+    }
+}
+
+impl Set {
+    pub fn len(&self) -> usize {
+        match self {
+            Set::IntSet(members) => members.len(),
+            Set::HashTable(members) => members.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        match self {
+            Set::IntSet(members) => members.capacity(),
+            Set::HashTable(members) => members.capacity(),
+        }
+    }
+
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Set::IntSet(_) => "intset",
+            Set::HashTable(_) => "hashtable",
+        }
+    }
+
+    pub fn contains(&self, member: &[u8]) -> bool {
+        match self {
+            Set::IntSet(members) => {
+                parse_integer(member).is_some_and(|n| members.binary_search(&n).is_ok())
+            }
+            Set::HashTable(members) => members.contains(member),
+        }
+    }
+
+    /// Adds `member`, returning `true` when it was not in the set before. An intset holding
+    /// more than `max_intset_entries` members is converted to a hash table.
+    pub fn insert(&mut self, member: Bytes, max_intset_entries: usize) -> bool {
+        if let Set::IntSet(members) = self {
+            if let Some(n) = parse_integer(&member) {
+                let Err(index) = members.binary_search(&n) else {
+                    return false;
+                };
+                members.insert(index, n);
+                if members.len() > max_intset_entries {
+                    self.upgrade();
+                }
+                return true;
+            }
+            self.upgrade();
+        }
+        match self {
+            Set::HashTable(members) => members.insert(member),
+            Set::IntSet(_) => unreachable!("intset upgraded above"),
+        }
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match self {
+            Set::IntSet(members) => {
+                match parse_integer(member).map(|n| members.binary_search(&n)) {
+                    Some(Ok(index)) => {
+                        members.remove(index);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            Set::HashTable(members) => members.remove(member),
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = Bytes> + '_> {
+        match self {
+            Set::IntSet(members) => Box::new(members.iter().map(|n| integer_member(*n))),
+            Set::HashTable(members) => Box::new(members.iter().cloned()),
+        }
+    }
+
+    fn upgrade(&mut self) {
+        if let Set::IntSet(members) = self {
+            let members = members.iter().map(|n| integer_member(*n)).collect();
+            *self = Set::HashTable(members);
+        }
+    }
+}
+
+fn integer_member(n: i64) -> Bytes {
+    n.to_string().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_intset_upgrade() {
+        let mut set = Set::default();
+        for n in [3, 1, 2] {
+            assert!(set.insert(integer_member(n), 3));
+        }
+        assert!(!set.insert(integer_member(2), 3));
+        assert_eq!(set, Set::IntSet(vec![1, 2, 3]));
+        assert!(set.contains(b"1"));
+        // non-canonical integers are distinct members
+        assert!(!set.contains(b"01"));
+        assert!(set.remove(b"3"));
+        assert!(!set.remove(b"3"));
+
+        assert!(set.insert(Bytes::from("a"), 3));
+        assert_eq!(set.encoding(), "hashtable");
+        assert!(set.contains(b"1"));
+        assert_eq!(set.iter().count(), 3);
+
+        let mut set = Set::default();
+        for n in 0..4 {
+            set.insert(integer_member(n), 3);
+        }
+        assert_eq!(set.encoding(), "hashtable");
+        assert_eq!(set.len(), 4);
+    }
+}
@@ -0,0 +1,121 @@
+// Shutting the server down. SHUTDOWN makes the dataset durable, then signals the accept loops,
+// which stop taking connections so the process exits. On SIGTERM or SIGINT the server also
+// waits for its replicas to acknowledge the last writes.
+
+use super::{persistence::PersistenceError, Backend};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::{sync::watch, time};
+
+#[derive(Debug)]
+pub(super) struct Shutdown(watch::Sender<bool>);
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self(watch::channel(false).0)
+    }
+}
+
+impl Backend {
+    /// Saves a snapshot when `save` is set, or when there are save rules if it isn't, and
+    /// flushes the AOF, then signals the server to stop. Nothing is signaled when that fails.
+    pub fn shutdown(&self, save: Option<bool>) -> Result<(), PersistenceError> {
+        if save.unwrap_or_else(|| !self.save_rules().is_empty()) {
+            // the background save can't be stopped, it is done before long
+            while self.bgsave_in_progress() {
+                thread::sleep(Duration::from_millis(10));
+            }
+            self.save()?;
+        }
+        self.sync_aof()?;
+        self.shutdown.0.send_replace(true);
+        Ok(())
+    }
+
+    pub fn shutting_down(&self) -> bool {
+        *self.shutdown.0.borrow()
+    }
+
+    /// Resolves once SHUTDOWN succeeded.
+    pub async fn shutdown_requested(&self) {
+        let mut shutdown = self.shutdown.0.subscribe();
+        // the sender lives as long as the backend
+        let _ = shutdown.wait_for(|requested| *requested).await;
+    }
+
+    /// Waits for the online replicas to acknowledge the writes made so far, for `timeout` at
+    /// most. Returns whether they all did.
+    pub async fn wait_for_replicas(&self, timeout: Duration) -> bool {
+        let offset = self.repl_offset();
+        let deadline = Instant::now() + timeout;
+        self.request_acks();
+        loop {
+            // register interest before counting so an acknowledgement in between isn't missed
+            let notified = self.replica_acked();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let online = self
+                .replica_infos()
+                .iter()
+                .filter(|replica| replica.online)
+                .count();
+            if self.acked_replicas(offset) >= online {
+                return true;
+            }
+            if time::timeout_at(deadline.into(), notified).await.is_err() {
+                return false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+    use std::{fs, process};
+    use tokio::time;
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let backend = Backend::new();
+        let dir = std::env::temp_dir().join(format!("simple-redis-shutdown-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        backend.set_dir(dir.clone());
+        backend.set_appendonly(true).unwrap();
+        let requested = tokio::spawn({
+            let backend = backend.clone();
+            async move { backend.shutdown_requested().await }
+        });
+
+        backend.set_save_rules(vec![]);
+        backend.set("key".into(), BulkString::from("value").into());
+        backend.shutdown(None).unwrap();
+        // no save rules, no snapshot
+        assert!(!backend.dump_path().exists());
+        assert!(backend.shutting_down());
+        time::timeout(Duration::from_millis(50), requested)
+            .await
+            .unwrap()
+            .unwrap();
+
+        backend.shutdown(Some(true)).unwrap();
+        assert!(backend.dump_path().exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_replicas_without_replicas() {
+        let backend = Backend::new();
+        backend.set("key".into(), BulkString::from("value").into());
+        let waited = time::timeout(
+            Duration::from_millis(50),
+            backend.wait_for_replicas(Duration::from_secs(10)),
+        )
+        .await;
+        assert_eq!(waited, Ok(true));
+    }
+}
@@ -0,0 +1,263 @@
+// The keyspace behind the string, hash, set, list and expire commands, as a trait engines other
+// than the in-memory backend implement: one keeping the keys on disk, one tiering them, or a
+// mock for tests. The backend is the engine the server runs with.
+
+use super::{ListDirection, WrongType};
+use crate::{Backend, RespFrame};
+use bytes::Bytes;
+
+/// The key operations the commands run against, values read and written as the frames of the
+/// commands and their replies.
+pub trait Storage {
+    fn get(&self, key: &[u8]) -> Result<Option<RespFrame>, WrongType>;
+
+    /// Sets `key` to the string `value`, replacing whatever value it held.
+    fn set(&self, key: Bytes, value: RespFrame);
+
+    /// Deletes `key`, returning whether it existed.
+    fn del(&self, key: &[u8]) -> bool;
+
+    fn hget(&self, key: &[u8], field: &[u8]) -> Result<Option<RespFrame>, WrongType>;
+
+    /// Sets `field` in the hash at `key`, returning `true` when the field is new.
+    fn hset(&self, key: Bytes, field: Bytes, value: RespFrame) -> Result<bool, WrongType>;
+
+    fn hgetall(&self, key: &[u8]) -> Result<Option<Vec<(Bytes, RespFrame)>>, WrongType>;
+
+    fn hdel(&self, key: &[u8], field: &[u8]) -> Result<bool, WrongType>;
+
+    fn sadd(&self, key: Bytes, member: RespFrame) -> Result<bool, WrongType>;
+
+    fn srem(&self, key: &[u8], member: &RespFrame) -> Result<bool, WrongType>;
+
+    fn sismember(&self, key: &[u8], member: &RespFrame) -> Result<bool, WrongType>;
+
+    fn smembers(&self, key: &[u8]) -> Result<Option<Vec<RespFrame>>, WrongType>;
+
+    /// Pushes `values` in turn at one end of the list at `key`, returning its length after.
+    fn push(
+        &self,
+        key: Bytes,
+        values: Vec<RespFrame>,
+        direction: ListDirection,
+    ) -> Result<usize, WrongType>;
+
+    /// Pops up to `count` values off one end of the list at `key`, `None` when there is none.
+    fn pop(
+        &self,
+        key: &[u8],
+        count: usize,
+        direction: ListDirection,
+    ) -> Result<Option<Vec<RespFrame>>, WrongType>;
+
+    fn llen(&self, key: &[u8]) -> Result<usize, WrongType>;
+
+    fn lrange(&self, key: &[u8], start: i64, stop: i64) -> Result<Vec<RespFrame>, WrongType>;
+
+    /// Sets `key` to expire `millis` milliseconds from now, returning whether it exists.
+    fn expire(&self, key: &[u8], millis: i64) -> bool;
+
+    /// The milliseconds `key` has left to live, `Some(None)` when it doesn't expire and `None`
+    /// when there is no such key.
+    fn ttl(&self, key: &[u8]) -> Option<Option<u64>>;
+
+    /// Removes the timeout of `key`, returning whether it had one.
+    fn persist(&self, key: &[u8]) -> bool;
+}
+
+impl Storage for Backend {
+    fn get(&self, key: &[u8]) -> Result<Option<RespFrame>, WrongType> {
+        Backend::get(self, key)
+    }
+
+    fn set(&self, key: Bytes, value: RespFrame) {
+        Backend::set(self, key, value)
+    }
+
+    fn del(&self, key: &[u8]) -> bool {
+        Backend::del(self, key)
+    }
+
+    fn hget(&self, key: &[u8], field: &[u8]) -> Result<Option<RespFrame>, WrongType> {
+        Backend::hget(self, key, field)
+    }
+
+    fn hset(&self, key: Bytes, field: Bytes, value: RespFrame) -> Result<bool, WrongType> {
+        Backend::hset(self, key, field, value)
+    }
+
+    fn hgetall(&self, key: &[u8]) -> Result<Option<Vec<(Bytes, RespFrame)>>, WrongType> {
+        Backend::hgetall(self, key)
+    }
+
+    fn hdel(&self, key: &[u8], field: &[u8]) -> Result<bool, WrongType> {
+        Backend::hdel(self, key, field)
+    }
+
+    fn sadd(&self, key: Bytes, member: RespFrame) -> Result<bool, WrongType> {
+        Backend::sadd(self, key, member)
+    }
+
+    fn srem(&self, key: &[u8], member: &RespFrame) -> Result<bool, WrongType> {
+        Backend::srem(self, key, member)
+    }
+
+    fn sismember(&self, key: &[u8], member: &RespFrame) -> Result<bool, WrongType> {
+        Backend::sismember(self, key, member)
+    }
+
+    fn smembers(&self, key: &[u8]) -> Result<Option<Vec<RespFrame>>, WrongType> {
+        Backend::smembers(self, key)
+    }
+
+    fn push(
+        &self,
+        key: Bytes,
+        values: Vec<RespFrame>,
+        direction: ListDirection,
+    ) -> Result<usize, WrongType> {
+        Backend::push(self, key, values, direction)
+    }
+
+    fn pop(
+        &self,
+        key: &[u8],
+        count: usize,
+        direction: ListDirection,
+    ) -> Result<Option<Vec<RespFrame>>, WrongType> {
+        Backend::pop(self, key, count, direction)
+    }
+
+    fn llen(&self, key: &[u8]) -> Result<usize, WrongType> {
+        Backend::llen(self, key)
+    }
+
+    fn lrange(&self, key: &[u8], start: i64, stop: i64) -> Result<Vec<RespFrame>, WrongType> {
+        Backend::lrange(self, key, start, stop)
+    }
+
+    fn expire(&self, key: &[u8], millis: i64) -> bool {
+        Backend::expire(self, key, millis)
+    }
+
+    fn ttl(&self, key: &[u8]) -> Option<Option<u64>> {
+        Backend::ttl(self, key)
+    }
+
+    fn persist(&self, key: &[u8]) -> bool {
+        Backend::persist(self, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Command, BulkString, RespArray, RespNull, SimpleString};
+    use std::{collections::HashMap, sync::Mutex};
+
+    // an engine keeping strings only, with no timeouts
+    #[derive(Default)]
+    struct Strings(Mutex<HashMap<Bytes, RespFrame>>);
+
+    impl Storage for Strings {
+        fn get(&self, key: &[u8]) -> Result<Option<RespFrame>, WrongType> {
+            Ok(self.0.lock().unwrap().get(key).cloned())
+        }
+
+        fn set(&self, key: Bytes, value: RespFrame) {
+            self.0.lock().unwrap().insert(key, value);
+        }
+
+        fn del(&self, key: &[u8]) -> bool {
+            self.0.lock().unwrap().remove(key).is_some()
+        }
+
+        fn hget(&self, _: &[u8], _: &[u8]) -> Result<Option<RespFrame>, WrongType> {
+            Err(WrongType)
+        }
+
+        fn hset(&self, _: Bytes, _: Bytes, _: RespFrame) -> Result<bool, WrongType> {
+            Err(WrongType)
+        }
+
+        fn hgetall(&self, _: &[u8]) -> Result<Option<Vec<(Bytes, RespFrame)>>, WrongType> {
+            Err(WrongType)
+        }
+
+        fn hdel(&self, _: &[u8], _: &[u8]) -> Result<bool, WrongType> {
+            Err(WrongType)
+        }
+
+        fn sadd(&self, _: Bytes, _: RespFrame) -> Result<bool, WrongType> {
+            Err(WrongType)
+        }
+
+        fn srem(&self, _: &[u8], _: &RespFrame) -> Result<bool, WrongType> {
+            Err(WrongType)
+        }
+
+        fn sismember(&self, _: &[u8], _: &RespFrame) -> Result<bool, WrongType> {
+            Err(WrongType)
+        }
+
+        fn smembers(&self, _: &[u8]) -> Result<Option<Vec<RespFrame>>, WrongType> {
+            Err(WrongType)
+        }
+
+        fn push(&self, _: Bytes, _: Vec<RespFrame>, _: ListDirection) -> Result<usize, WrongType> {
+            Err(WrongType)
+        }
+
+        fn pop(
+            &self,
+            _: &[u8],
+            _: usize,
+            _: ListDirection,
+        ) -> Result<Option<Vec<RespFrame>>, WrongType> {
+            Err(WrongType)
+        }
+
+        fn llen(&self, _: &[u8]) -> Result<usize, WrongType> {
+            Err(WrongType)
+        }
+
+        fn lrange(&self, _: &[u8], _: i64, _: i64) -> Result<Vec<RespFrame>, WrongType> {
+            Err(WrongType)
+        }
+
+        fn expire(&self, _: &[u8], _: i64) -> bool {
+            false
+        }
+
+        fn ttl(&self, key: &[u8]) -> Option<Option<u64>> {
+            self.0.lock().unwrap().contains_key(key).then_some(None)
+        }
+
+        fn persist(&self, _: &[u8]) -> bool {
+            false
+        }
+    }
+
+    fn command(args: &[&'static str]) -> Command {
+        let args = args.iter().map(|arg| BulkString::from(*arg).into());
+        Command::try_from(RespFrame::from(RespArray::new(args.collect::<Vec<_>>()))).unwrap()
+    }
+
+    #[test]
+    fn test_commands_on_other_storage() {
+        let storage = Strings::default();
+        let run = |args| command(args).execute_on(&storage).unwrap();
+        assert_eq!(
+            run(&["set", "key", "value"]),
+            SimpleString::new("OK").into()
+        );
+        assert_eq!(run(&["get", "key"]), BulkString::from("value").into());
+        assert_eq!(run(&["ttl", "key"]), RespFrame::Integer(-1));
+        assert_eq!(run(&["hget", "key", "field"]), WrongType.into());
+        assert_eq!(run(&["del", "key", "other"]), RespFrame::Integer(1));
+        assert_eq!(run(&["get", "key"]), RespFrame::Null(RespNull));
+
+        // the rest needs the backend
+        assert!(command(&["incr", "key"]).execute_on(&storage).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use bytes::Bytes;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+// How XADD asks for the ID of a new entry: `*`, `<ms>-*` or a full `<ms>-<seq>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamIdSpec {
+    Auto,
+    Partial(u64),
+    Explicit(StreamId),
+}
+
+pub type StreamFields = Vec<(String, Bytes)>;
+
+// Approximate trimming only drops whole nodes of this many entries, like Redis' radix-tree nodes.
+const STREAM_NODE_MAX_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimStrategy {
+    MaxLen(usize),
+    MinId(StreamId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamTrim {
+    pub strategy: TrimStrategy,
+    pub approximate: bool,
+    pub limit: Option<usize>,
+}
+
+// A stream is an append-only log of field/value entries ordered by their IDs.
+#[derive(Debug, Default, Clone)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, StreamFields>,
+    last_id: StreamId,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        Self { ms, seq }
+    }
+
+    pub fn next(self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+
+    pub fn prev(self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_sub(1)?, u64::MAX)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+impl Stream {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&StreamId, &StreamFields)> {
+        self.entries.iter()
+    }
+
+    /// ID of the last entry ever added, which later IDs must be greater than even once that
+    /// entry is deleted.
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    /// Restores the last ID, returning `false` when it is below the newest entry.
+    pub fn set_last_id(&mut self, id: StreamId) -> bool {
+        if self
+            .entries
+            .last_key_value()
+            .is_some_and(|(last, _)| *last > id)
+        {
+            return false;
+        }
+        self.last_id = id;
+        true
+    }
+
+    /// Appends a new entry, returning `None` when the requested ID is not greater than the last one.
+    pub fn add(&mut self, spec: StreamIdSpec, fields: StreamFields) -> Option<StreamId> {
+        let id = match spec {
+            StreamIdSpec::Auto => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default();
+                if now > self.last_id.ms {
+                    StreamId::new(now, 0)
+                } else {
+                    self.last_id.next()?
+                }
+            }
+            StreamIdSpec::Partial(ms) if ms == self.last_id.ms => {
+                StreamId::new(ms, self.last_id.seq.checked_add(1)?)
+            }
+            StreamIdSpec::Partial(ms) => StreamId::new(ms, if ms == 0 { 1 } else { 0 }),
+            StreamIdSpec::Explicit(id) => id,
+        };
+        if id <= self.last_id {
+            return None;
+        }
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        Some(id)
+    }
+
+    /// Evicts the oldest entries according to `trim` and returns how many were removed.
+    pub fn trim(&mut self, trim: StreamTrim) -> usize {
+        let excess = match trim.strategy {
+            TrimStrategy::MaxLen(max_len) => self.len().saturating_sub(max_len),
+            TrimStrategy::MinId(min_id) => self.entries.range(..min_id).count(),
+        };
+        let count = if trim.approximate {
+            let limit = match trim.limit {
+                Some(0) => usize::MAX,
+                Some(limit) => limit,
+                None => STREAM_NODE_MAX_ENTRIES * 100,
+            };
+            let count = excess.min(limit);
+            count - count % STREAM_NODE_MAX_ENTRIES
+        } else {
+            excess
+        };
+        for _ in 0..count {
+            self.entries.pop_first();
+        }
+        count
+    }
+
+    pub fn delete(&mut self, ids: &[StreamId]) -> usize {
+        ids.iter()
+            .filter(|id| self.entries.remove(id).is_some())
+            .count()
+    }
+
+    /// Returns the entries between `start` and `end` inclusive, newest first when `rev` is set.
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+        rev: bool,
+        count: Option<usize>,
+    ) -> Vec<(StreamId, StreamFields)> {
+        if start > end {
+            return vec![];
+        }
+        let range = self.entries.range(start..=end);
+        let count = count.unwrap_or(usize::MAX);
+        let clone = |(id, fields): (&StreamId, &StreamFields)| (*id, fields.clone());
+        if rev {
+            range.rev().take(count).map(clone).collect()
+        } else {
+            range.take(count).map(clone).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_add_ids() {
+        let mut stream = Stream::default();
+        assert_eq!(
+            stream.add(StreamIdSpec::Partial(0), vec![]),
+            Some(StreamId::new(0, 1))
+        );
+        assert_eq!(
+            stream.add(StreamIdSpec::Explicit(StreamId::new(5, 3)), vec![]),
+            Some(StreamId::new(5, 3))
+        );
+        assert_eq!(
+            stream.add(StreamIdSpec::Explicit(StreamId::new(5, 3)), vec![]),
+            None
+        );
+        assert_eq!(stream.add(StreamIdSpec::Partial(4), vec![]), None);
+        assert_eq!(
+            stream.add(StreamIdSpec::Partial(5), vec![]),
+            Some(StreamId::new(5, 4))
+        );
+        let id = stream.add(StreamIdSpec::Auto, vec![]).unwrap();
+        assert!(id > StreamId::new(5, 4));
+        assert_eq!(stream.len(), 4);
+        assert_eq!(stream.last_id, id);
+    }
+
+    #[test]
+    fn test_stream_trim_and_delete() {
+        let mut stream = Stream::default();
+        for ms in 1..=250 {
+            stream.add(StreamIdSpec::Explicit(StreamId::new(ms, 0)), vec![]);
+        }
+        let approximate = StreamTrim {
+            strategy: TrimStrategy::MaxLen(10),
+            approximate: true,
+            limit: None,
+        };
+        assert_eq!(stream.trim(approximate), 200);
+        assert_eq!(stream.len(), 50);
+
+        let exact = StreamTrim {
+            strategy: TrimStrategy::MinId(StreamId::new(211, 0)),
+            approximate: false,
+            limit: None,
+        };
+        assert_eq!(stream.trim(exact), 10);
+        assert_eq!(
+            stream.delete(&[StreamId::new(211, 0), StreamId::new(1, 0)]),
+            1
+        );
+        assert_eq!(stream.len(), 39);
+        // deleting entries never lets the ID go backwards
+        assert_eq!(
+            stream.add(StreamIdSpec::Explicit(StreamId::new(211, 0)), vec![]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_stream_range() {
+        let mut stream = Stream::default();
+        for ms in 1..=5 {
+            stream.add(StreamIdSpec::Explicit(StreamId::new(ms, 0)), vec![]);
+        }
+        let ids = |entries: Vec<(StreamId, StreamFields)>| {
+            entries.into_iter().map(|(id, _)| id.ms).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(stream.range(StreamId::new(2, 0), StreamId::MAX, false, None)),
+            vec![2, 3, 4, 5]
+        );
+        assert_eq!(
+            ids(stream.range(StreamId::MIN, StreamId::new(4, 0), true, Some(2))),
+            vec![4, 3]
+        );
+        assert!(stream
+            .range(StreamId::new(4, 0), StreamId::new(2, 0), false, None)
+            .is_empty());
+    }
+}
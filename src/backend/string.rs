@@ -0,0 +1,278 @@
+// Values of the string type as kept in the keyspace: the bytes written, except that strings
+// holding an integer are stored as the integer itself, inline in the keyspace with no
+// allocation of their own, and, with the `compression` feature, long strings may be
+// compressed. Readers always get the bytes back, as a bulk string.
+//
+// The integers INCR and the like work on are the strings holding one. They stay within 64 bits,
+// unless the increment allows going past them, which is up to the command.
+
+#[cfg(feature = "compression")]
+use super::compression::Compressed;
+use super::{db::Value, encoding::parse_integer, Backend};
+use crate::{BulkString, RespEncoder, RespFrame};
+use bytes::Bytes;
+use dashmap::mapref::entry::Entry;
+use num_bigint::BigInt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// longest string Redis allocates together with its object header
+const EMBSTR_MAX_LEN: usize = 44;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum StringValue {
+    Bytes(Bytes),
+    Int(i64),
+    #[cfg(feature = "compression")]
+    Compressed(Compressed),
+}
+
+impl StringValue {
+    /// Returns the frame a read of the value replies with.
+    pub(super) fn to_frame(&self) -> RespFrame {
+        BulkString::new(self.bytes()).into()
+    }
+
+    pub(super) fn bytes(&self) -> Bytes {
+        match self {
+            StringValue::Bytes(bytes) => bytes.clone(),
+            StringValue::Int(n) => n.to_string().into(),
+            #[cfg(feature = "compression")]
+            StringValue::Compressed(compressed) => compressed.decompress().into(),
+        }
+    }
+
+    // Bit operations work on the raw bytes, so integers and compressed values are expanded
+    // first. The bytes may be shared with other frames, so `update` works on a copy unless
+    // they aren't.
+    pub(super) fn update_bytes<R>(&mut self, update: impl FnOnce(&mut Vec<u8>) -> R) -> R {
+        let mut bytes = match std::mem::replace(self, StringValue::Bytes(Bytes::new())) {
+            StringValue::Bytes(bytes) => Vec::from(bytes),
+            value => value.bytes().into(),
+        };
+        let result = update(&mut bytes);
+        *self = StringValue::Bytes(bytes.into());
+        result
+    }
+
+    pub(super) fn encoding(&self) -> &'static str {
+        match self {
+            StringValue::Int(_) => "int",
+            StringValue::Bytes(bytes) if bytes.len() > EMBSTR_MAX_LEN => "raw",
+            StringValue::Bytes(_) => "embstr",
+            #[cfg(feature = "compression")]
+            StringValue::Compressed(_) => "lz4",
+        }
+    }
+}
+
+/// The bytes a frame written by a client is stored as: the payload of a string, the digits of
+/// an integer and the encoding of anything else.
+pub(super) fn frame_bytes(frame: RespFrame) -> Bytes {
+    match frame {
+        RespFrame::BulkString(s) => s.0,
+        RespFrame::SimpleString(s) => s.0.into(),
+        RespFrame::Integer(n) => n.to_string().into(),
+        frame => frame.encode().into(),
+    }
+}
+
+/// Why an increment left a value as it was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncrError {
+    /// the value isn't a string holding an integer in range
+    NotInteger,
+    /// the key holds a value of another type
+    WrongType,
+    /// the result would go past 64 bits
+    Overflow,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct BignumIncr {
+    // set by incr-promote-bignum, letting increments past 64 bits reply with big numbers
+    enabled: AtomicBool,
+}
+
+impl Backend {
+    /// Turns the bytes of a string into the form it is stored in.
+    pub(super) fn string_value(&self, bytes: Bytes) -> StringValue {
+        if let Some(n) = parse_integer(&bytes) {
+            return StringValue::Int(n);
+        }
+        #[cfg(feature = "compression")]
+        if let Some(compressed) = self.compress(&bytes) {
+            return StringValue::Compressed(compressed);
+        }
+        StringValue::Bytes(bytes)
+    }
+
+    /// Adds `increment` to the integer at `key`, counting from 0 when the key is missing, and
+    /// returns the result. Integers past 64 bits are read and written only when `promote` is
+    /// set, the key otherwise left as it was.
+    pub fn incr_by(&self, key: Bytes, increment: i64, promote: bool) -> Result<BigInt, IncrError> {
+        self.touch(&key);
+        let result = match self.db().keyspace.entry(key.clone()) {
+            Entry::Occupied(mut entry) => {
+                let Value::String(value) = entry.get() else {
+                    return Err(IncrError::WrongType);
+                };
+                let result = incremented(Some(value), increment, promote)?;
+                entry.insert(Value::String(integer_value(&result)));
+                result
+            }
+            Entry::Vacant(entry) => {
+                let result = incremented(None, increment, promote)?;
+                entry.insert(Value::String(integer_value(&result)));
+                result
+            }
+        };
+        self.written(&key);
+        Ok(result)
+    }
+
+    /// Whether increments going past 64 bits reply with big numbers to the clients that
+    /// negotiated RESP3, rather than failing.
+    pub fn bignum_incr(&self) -> bool {
+        self.bignum_incr.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_bignum_incr(&self, enabled: bool) {
+        self.bignum_incr.enabled.store(enabled, Ordering::Relaxed);
+    }
+}
+
+fn integer_value(n: &BigInt) -> StringValue {
+    match i64::try_from(n) {
+        Ok(n) => StringValue::Int(n),
+        Err(_) => StringValue::Bytes(n.to_string().into()),
+    }
+}
+
+fn incremented(
+    value: Option<&StringValue>,
+    increment: i64,
+    promote: bool,
+) -> Result<BigInt, IncrError> {
+    // integers are added to as they are stored, strings parsed first
+    let n = match value {
+        None => 0,
+        Some(StringValue::Int(n)) => *n,
+        Some(value) => {
+            let bytes = value.bytes();
+            match parse_integer(&bytes) {
+                Some(n) => n,
+                None if promote => {
+                    let n = parse_big_integer(&bytes).ok_or(IncrError::NotInteger)?;
+                    return Ok(n + increment);
+                }
+                None => return Err(IncrError::NotInteger),
+            }
+        }
+    };
+    match n.checked_add(increment) {
+        Some(n) => Ok(n.into()),
+        None if promote => Ok(BigInt::from(n) + increment),
+        None => Err(IncrError::Overflow),
+    }
+}
+
+// Like parse_integer, without the 64 bits limit.
+fn parse_big_integer(bytes: &[u8]) -> Option<BigInt> {
+    let n: BigInt = std::str::from_utf8(bytes).ok()?.parse().ok()?;
+    (n.to_string().as_bytes() == bytes).then_some(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Backend;
+
+    #[test]
+    fn test_integer_string_values() {
+        let backend = Backend::new();
+        backend.set("flag".into(), BulkString::from("1").into());
+        backend.set("char".into(), BulkString::from("x").into());
+        assert_eq!(
+            backend.get(b"flag").unwrap(),
+            Some(BulkString::from("1").into())
+        );
+        assert_eq!(backend.object_encoding(b"flag"), Some("int"));
+        assert!(backend.memory_usage(b"flag", 0) < backend.memory_usage(b"char", 0));
+        // non-canonical integers are kept as they were written
+        backend.set("n".into(), BulkString::from("01").into());
+        assert_eq!(backend.object_encoding(b"n"), Some("embstr"));
+
+        // bit operations see the digits of an integer
+        assert!(backend.getbit(b"flag", 2).unwrap());
+        assert_eq!(backend.bitcount(b"flag", None).unwrap(), 3);
+        backend.setbit("flag".into(), 6, true).unwrap();
+        assert_eq!(
+            backend.get(b"flag").unwrap(),
+            Some(BulkString::from("3").into())
+        );
+        assert_eq!(
+            backend.incr_by("flag".into(), 1, false),
+            Ok(BigInt::from(4))
+        );
+        assert_eq!(backend.object_encoding(b"flag"), Some("int"));
+    }
+
+    #[test]
+    fn test_frame_bytes() {
+        assert_eq!(frame_bytes(BulkString::from("a").into()), "a");
+        assert_eq!(frame_bytes(RespFrame::SimpleString("b".into())), "b");
+        assert_eq!(frame_bytes(RespFrame::Integer(-1)), "-1");
+    }
+
+    #[test]
+    fn test_incr_by() {
+        let backend = Backend::new();
+        assert_eq!(backend.incr_by("n".into(), 5, false), Ok(BigInt::from(5)));
+        assert_eq!(backend.incr_by("n".into(), -7, false), Ok(BigInt::from(-2)));
+        assert_eq!(
+            backend.get(b"n").unwrap(),
+            Some(BulkString::from("-2").into())
+        );
+
+        backend.set("s".into(), BulkString::from("nope").into());
+        assert_eq!(
+            backend.incr_by("s".into(), 1, true),
+            Err(IncrError::NotInteger)
+        );
+        backend.set("s".into(), BulkString::from("+1").into());
+        assert_eq!(
+            backend.incr_by("s".into(), 1, false),
+            Err(IncrError::NotInteger)
+        );
+    }
+
+    #[test]
+    fn test_incr_by_past_64_bits() {
+        let backend = Backend::new();
+        backend.set("n".into(), BulkString::from(i64::MAX.to_string()).into());
+        assert_eq!(
+            backend.incr_by("n".into(), 1, false),
+            Err(IncrError::Overflow)
+        );
+        assert_eq!(
+            backend.get(b"n").unwrap(),
+            Some(BulkString::from(i64::MAX.to_string()).into())
+        );
+
+        let past = BigInt::from(i64::MAX) + 1i64;
+        assert_eq!(backend.incr_by("n".into(), 1, true), Ok(past.clone()));
+        assert_eq!(
+            backend.get(b"n").unwrap(),
+            Some(BulkString::from(past.to_string()).into())
+        );
+        // out of range for an increment that can't go past 64 bits
+        assert_eq!(
+            backend.incr_by("n".into(), -1, false),
+            Err(IncrError::NotInteger)
+        );
+        assert_eq!(
+            backend.incr_by("n".into(), -1, true),
+            Ok(BigInt::from(i64::MAX))
+        );
+    }
+}
@@ -0,0 +1,220 @@
+// Client-side caching. A tracking connection is told when keys it may have cached change: in
+// the default mode, the keys it read, each reported once until it reads it again; in
+// broadcasting mode, every key starting with one of its prefixes, whoever read it.
+
+use super::Backend;
+use crate::{BulkString, RespArray, RespFrame, RespPush};
+use bytes::Bytes;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+// the channel RESP2 connections subscribe to when they get the invalidations of another one
+const INVALIDATE_CHANNEL: &str = "__redis__:invalidate";
+
+#[derive(Debug, Default)]
+pub(super) struct Tracking {
+    // number of tracking connections, so writes skip the bookkeeping when there is none
+    count: AtomicUsize,
+    clients: Mutex<HashMap<u64, TrackingOptions>>,
+    // ids of the connections that read each key, in the default mode
+    keys: Mutex<HashMap<Bytes, HashSet<u64>>>,
+}
+
+/// How a connection tracks the keys it caches.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackingOptions {
+    /// the connection the invalidations are sent to instead of the tracking one
+    pub redirect: Option<u64>,
+    /// whether to be told about all the keys matching the prefixes, read or not
+    pub bcast: bool,
+    /// the prefixes of the keys to be told about in broadcasting mode, all keys when empty
+    pub prefixes: Vec<Bytes>,
+}
+
+impl Tracking {
+    fn clients(&self) -> MutexGuard<'_, HashMap<u64, TrackingOptions>> {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn keys(&self) -> MutexGuard<'_, HashMap<Bytes, HashSet<u64>>> {
+        self.keys.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Backend {
+    /// Turns tracking on for the connection `id`, replacing the options it tracked with before.
+    pub fn enable_tracking(&self, id: u64, options: TrackingOptions) -> Result<(), String> {
+        if !options.bcast && !options.prefixes.is_empty() {
+            return Err("PREFIX option requires BCAST mode to be enabled".to_string());
+        }
+        if let Some(redirect) = options.redirect {
+            if !self.client_connected(redirect) {
+                return Err("The client ID you want redirect to does not exist".to_string());
+            }
+        }
+        if self.tracking.clients().insert(id, options).is_none() {
+            self.tracking.count.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// Turns tracking off for the connection `id`. The keys it read are forgotten lazily, when
+    /// they are next invalidated.
+    pub fn disable_tracking(&self, id: u64) {
+        if self.tracking.clients().remove(&id).is_some() {
+            self.tracking.count.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_tracking(&self, id: u64) -> bool {
+        self.tracking.count.load(Ordering::Relaxed) > 0 && self.tracking.clients().contains_key(&id)
+    }
+
+    /// Remembers that the connection `id` read `keys`, so it is told when they change. Nothing
+    /// to remember in broadcasting mode, where the prefixes decide.
+    pub fn track_keys(&self, id: u64, keys: &[&[u8]]) {
+        let bcast = match self.tracking.clients().get(&id) {
+            Some(options) => options.bcast,
+            None => return,
+        };
+        if bcast {
+            return;
+        }
+        let mut tracked = self.tracking.keys();
+        for key in keys {
+            tracked
+                .entry(Bytes::copy_from_slice(key))
+                .or_default()
+                .insert(id);
+        }
+    }
+
+    // Tells the connections tracking `key` that it changed.
+    pub(super) fn invalidate(&self, key: &[u8]) {
+        if self.tracking.count.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let readers = self.tracking.keys().remove(key).unwrap_or_default();
+        let targets: Vec<u64> = self
+            .tracking
+            .clients()
+            .iter()
+            .filter(|(id, options)| match options.bcast {
+                true => {
+                    options.prefixes.is_empty()
+                        || options.prefixes.iter().any(|p| key.starts_with(p))
+                }
+                false => readers.contains(id),
+            })
+            .map(|(id, options)| options.redirect.unwrap_or(*id))
+            .collect();
+        for target in targets {
+            let keys = RespArray::new([BulkString::new(Bytes::copy_from_slice(key)).into()]);
+            // a RESP2 connection can only get them as messages of the invalidation channel
+            let frame: RespFrame = match self.client_subscribed(target, INVALIDATE_CHANNEL) {
+                true => RespArray::new([
+                    BulkString::from("message").into(),
+                    BulkString::from(INVALIDATE_CHANNEL).into(),
+                    keys.into(),
+                ])
+                .into(),
+                false => RespPush::new([BulkString::from("invalidate").into(), keys.into()]).into(),
+            };
+            self.send_to_client(target, frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn invalidation(key: &'static str) -> RespFrame {
+        RespPush::new([
+            BulkString::from("invalidate").into(),
+            RespArray::new([BulkString::from(key).into()]).into(),
+        ])
+        .into()
+    }
+
+    #[tokio::test]
+    async fn test_tracking_default_mode() {
+        let backend = Backend::new();
+        let mut client = backend.subscriber();
+        backend
+            .enable_tracking(client.id(), TrackingOptions::default())
+            .unwrap();
+        assert!(backend.is_tracking(client.id()));
+
+        backend.set("foo".into(), BulkString::from("1").into());
+        backend.track_keys(client.id(), &[b"foo".as_slice()]);
+        backend.set("bar".into(), BulkString::from("1").into());
+        backend.set("foo".into(), BulkString::from("2").into());
+        assert_eq!(
+            client.message().await.map(Arc::unwrap_or_clone),
+            Some(invalidation("foo"))
+        );
+
+        // reported once until read again
+        backend.set("foo".into(), BulkString::from("3").into());
+        backend.track_keys(client.id(), &[b"foo".as_slice()]);
+        backend.del(b"foo");
+        assert_eq!(
+            client.message().await.map(Arc::unwrap_or_clone),
+            Some(invalidation("foo"))
+        );
+
+        backend.disable_tracking(client.id());
+        assert!(!backend.is_tracking(client.id()));
+    }
+
+    #[tokio::test]
+    async fn test_tracking_bcast_and_redirect() {
+        let backend = Backend::new();
+        let client = backend.subscriber();
+        let mut redirect = backend.subscriber();
+        let options = TrackingOptions {
+            redirect: Some(redirect.id()),
+            bcast: true,
+            prefixes: vec!["user:".into()],
+        };
+        backend.enable_tracking(client.id(), options).unwrap();
+        redirect.subscribe(INVALIDATE_CHANNEL);
+
+        backend.set("session:1".into(), BulkString::from("1").into());
+        backend.set("user:1".into(), BulkString::from("1").into());
+        assert_eq!(
+            redirect.message().await.map(Arc::unwrap_or_clone),
+            Some(
+                RespArray::new([
+                    BulkString::from("message").into(),
+                    BulkString::from(INVALIDATE_CHANNEL).into(),
+                    RespArray::new([BulkString::from("user:1").into()]).into(),
+                ])
+                .into()
+            )
+        );
+
+        let options = TrackingOptions {
+            prefixes: vec!["user:".into()],
+            ..Default::default()
+        };
+        assert!(backend.enable_tracking(client.id(), options).is_err());
+        let options = TrackingOptions {
+            redirect: Some(u64::MAX),
+            ..Default::default()
+        };
+        assert!(backend.enable_tracking(client.id(), options).is_err());
+
+        // closing the connection turns tracking off
+        let id = client.id();
+        drop(client);
+        assert!(!backend.is_tracking(id));
+    }
+}
@@ -0,0 +1,152 @@
+// Transactions. Every command runs holding the shared side of a lock that EXEC takes
+// exclusively, so the commands of a transaction run without any other command in between.
+// WATCH makes EXEC fail when a watched key changed since, which the write hook reports here.
+
+use super::Backend;
+use bytes::Bytes;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[derive(Debug, Default)]
+pub(super) struct Transactions {
+    lock: RwLock<()>,
+    // number of connections watching keys, so writes skip the bookkeeping when there is none
+    watching: AtomicUsize,
+    watched: Mutex<Watched>,
+}
+
+#[derive(Debug, Default)]
+struct Watched {
+    // the connections watching each key, by database
+    keys: HashMap<(usize, Bytes), HashSet<u64>>,
+    // the keys each connection watches, and whether one of them changed since
+    clients: HashMap<u64, (HashSet<(usize, Bytes)>, bool)>,
+}
+
+impl Transactions {
+    fn watched(&self) -> MutexGuard<'_, Watched> {
+        self.watched.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Backend {
+    /// Held while running a command, which then can't overlap with a transaction.
+    pub async fn command_lock(&self) -> RwLockReadGuard<'_, ()> {
+        self.transactions.lock.read().await
+    }
+
+    /// Held while running the commands of a transaction or a script, which no other command
+    /// can then overlap with.
+    pub async fn transaction_lock(&self) -> RwLockWriteGuard<'_, ()> {
+        self.transactions.lock.write().await
+    }
+
+    /// Watches `key` in the database for the connection `id`, whose next EXEC fails if the key
+    /// changes.
+    pub fn watch(&self, id: u64, key: &[u8]) {
+        let mut watched = self.transactions.watched();
+        let (keys, _) = watched.clients.entry(id).or_insert_with(|| {
+            self.transactions.watching.fetch_add(1, Ordering::Relaxed);
+            Default::default()
+        });
+        let key = (self.db_index(), Bytes::copy_from_slice(key));
+        if keys.insert(key.clone()) {
+            watched.keys.entry(key).or_default().insert(id);
+        }
+    }
+
+    /// Forgets the keys watched by the connection `id`, returning whether one of them changed
+    /// since it started watching them.
+    pub fn unwatch(&self, id: u64) -> bool {
+        let mut watched = self.transactions.watched();
+        let Some((keys, dirty)) = watched.clients.remove(&id) else {
+            return false;
+        };
+        self.transactions.watching.fetch_sub(1, Ordering::Relaxed);
+        for key in keys {
+            if let Some(clients) = watched.keys.get_mut(&key) {
+                clients.remove(&id);
+                if clients.is_empty() {
+                    watched.keys.remove(&key);
+                }
+            }
+        }
+        dirty
+    }
+
+    // Makes the transactions of the connections watching `key` fail.
+    pub(super) fn touch_watched(&self, key: &[u8]) {
+        if self.transactions.watching.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let mut watched = self.transactions.watched();
+        let Some(ids) = watched
+            .keys
+            .get(&(self.db_index(), Bytes::copy_from_slice(key)))
+            .cloned()
+        else {
+            return;
+        };
+        for id in ids {
+            if let Some((_, dirty)) = watched.clients.get_mut(&id) {
+                *dirty = true;
+            }
+        }
+    }
+
+    // Makes the transactions fail that watch a key of the swapped databases existing in one
+    // of them.
+    pub(super) fn touch_watched_swapped(&self, index1: usize, index2: usize) {
+        if self.transactions.watching.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let (Some(db1), Some(db2)) = (self.select(index1), self.select(index2)) else {
+            return;
+        };
+        let mut watched = self.transactions.watched();
+        let Watched { keys, clients } = &mut *watched;
+        for ((db, key), ids) in keys.iter() {
+            if (*db == index1 || *db == index2) && (db1.exists(key) || db2.exists(key)) {
+                for id in ids {
+                    if let Some((_, dirty)) = clients.get_mut(id) {
+                        *dirty = true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_watch() {
+        let backend = Backend::new();
+        backend.watch(1, b"foo");
+        backend.watch(2, b"bar");
+        backend.set("foo".into(), BulkString::from("1").into());
+        assert!(backend.unwatch(1));
+        assert!(!backend.unwatch(2));
+        // nothing watched anymore
+        assert!(!backend.unwatch(1));
+
+        backend.watch(1, b"foo");
+        backend.expire(b"foo", 0);
+        assert!(backend.unwatch(1));
+
+        // the same key in another database is another key
+        backend.watch(1, b"foo");
+        let other = backend.select(1).unwrap();
+        other.set("foo".into(), BulkString::from("1").into());
+        assert!(!backend.unwatch(1));
+    }
+}
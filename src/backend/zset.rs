@@ -0,0 +1,541 @@
+use bytes::Bytes;
+use ordered_float::OrderedFloat;
+use rand::{seq::IteratorRandom, Rng};
+use std::{
+    collections::{BTreeSet, HashMap},
+    ops::Bound,
+};
+
+// A sorted set keeps a member -> score map for lookups and an ordered index for range queries.
+#[derive(Debug, Default, Clone)]
+pub struct ZSet {
+    scores: HashMap<Bytes, f64>,
+    ordered: BTreeSet<(OrderedFloat<f64>, Bytes)>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ZAddFlags {
+    pub nx: bool,
+    pub xx: bool,
+    pub gt: bool,
+    pub lt: bool,
+    pub incr: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZSetOperation {
+    Union,
+    Inter,
+    Diff,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZAddOutcome {
+    Added(f64),
+    Updated(f64),
+    Unchanged(f64),
+    Ignored,
+    NotANumber,
+}
+
+impl ZSet {
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    pub fn add(&mut self, member: Bytes, score: f64, flags: ZAddFlags) -> ZAddOutcome {
+        match self.scores.get(&member).copied() {
+            Some(current) => {
+                if flags.nx {
+                    return ZAddOutcome::Ignored;
+                }
+                let score = if flags.incr { current + score } else { score };
+                if score.is_nan() {
+                    return ZAddOutcome::NotANumber;
+                }
+                if (flags.gt && score <= current) || (flags.lt && score >= current) {
+                    return ZAddOutcome::Ignored;
+                }
+                if score == current {
+                    return ZAddOutcome::Unchanged(score);
+                }
+                self.ordered
+                    .remove(&(OrderedFloat(current), member.clone()));
+                self.insert(member, score);
+                ZAddOutcome::Updated(score)
+            }
+            None => {
+                if flags.xx {
+                    return ZAddOutcome::Ignored;
+                }
+                self.insert(member, score);
+                ZAddOutcome::Added(score)
+            }
+        }
+    }
+
+    pub fn range_by_rank(&self, start: i64, stop: i64, rev: bool) -> Vec<(Bytes, f64)> {
+        let Some((start, stop)) = normalize_range(start, stop, self.len()) else {
+            return vec![];
+        };
+        let count = stop - start + 1;
+        if rev {
+            collect(self.ordered.iter().rev().skip(start).take(count))
+        } else {
+            collect(self.ordered.iter().skip(start).take(count))
+        }
+    }
+
+    pub fn iter_by_score(
+        &self,
+        min: Bound<f64>,
+        max: Bound<f64>,
+    ) -> impl Iterator<Item = (&Bytes, f64)> + '_ {
+        let start = match min {
+            Bound::Included(v) | Bound::Excluded(v) => v,
+            Bound::Unbounded => f64::NEG_INFINITY,
+        };
+        self.ordered
+            .range((OrderedFloat(start), Bytes::new())..)
+            .skip_while(move |(score, _)| matches!(min, Bound::Excluded(v) if score.0 <= v))
+            .take_while(move |(score, _)| match max {
+                Bound::Included(v) => score.0 <= v,
+                Bound::Excluded(v) => score.0 < v,
+                Bound::Unbounded => true,
+            })
+            .map(|(score, member)| (member, score.0))
+    }
+
+    // Lexicographical ranges assume every member has the same score, like Redis does, so they
+    // seek `min` among the members of the score of the first one.
+    pub fn iter_by_lex<'a>(
+        &'a self,
+        min: Bound<&'a [u8]>,
+        max: Bound<&'a [u8]>,
+    ) -> impl Iterator<Item = (&'a Bytes, f64)> + 'a {
+        let score = self
+            .ordered
+            .first()
+            .map_or(OrderedFloat(0.0), |(score, _)| *score);
+        let start = match min {
+            Bound::Included(v) => Bound::Included((score, Bytes::copy_from_slice(v))),
+            Bound::Excluded(v) => Bound::Excluded((score, Bytes::copy_from_slice(v))),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        self.ordered
+            .range((start, Bound::Unbounded))
+            .take_while(move |(_, member)| match max {
+                Bound::Included(v) => &member[..] <= v,
+                Bound::Excluded(v) => &member[..] < v,
+                Bound::Unbounded => true,
+            })
+            .map(|(score, member)| (member, score.0))
+    }
+
+    pub fn pop(&mut self, count: usize, max: bool) -> Vec<(Bytes, f64)> {
+        let members = if max {
+            collect(self.ordered.iter().rev().take(count))
+        } else {
+            collect(self.ordered.iter().take(count))
+        };
+        for (member, _) in &members {
+            self.remove(member);
+        }
+        members
+    }
+
+    /// Picks `count` distinct members, or `|count|` members with repetitions when negative.
+    pub fn random_members(&self, count: i64) -> Vec<(Bytes, f64)> {
+        let mut rng = rand::thread_rng();
+        if count >= 0 {
+            // sampling reserves room for `count` members, which can't be more than there are
+            let count = (count as usize).min(self.len());
+            let mut members = collect(
+                self.ordered
+                    .iter()
+                    .choose_multiple(&mut rng, count)
+                    .into_iter(),
+            );
+            // choose_multiple doesn't randomize the order of the picked members
+            members.sort_by_cached_key(|_| rng.gen::<u64>());
+            members
+        } else if self.is_empty() {
+            vec![]
+        } else {
+            let members = self.ordered.iter().collect::<Vec<_>>();
+            (0..count.unsigned_abs())
+                .map(|_| {
+                    let (score, member) = members[rng.gen_range(0..members.len())];
+                    (member.clone(), score.0)
+                })
+                .collect()
+        }
+    }
+
+    /// Returns up to `count` members starting at `cursor` and the cursor to resume from, 0 when done.
+    pub fn scan(&self, cursor: usize, count: usize) -> (usize, Vec<(Bytes, f64)>) {
+        let members = collect(self.ordered.iter().skip(cursor).take(count));
+        let next = cursor + members.len();
+        if next >= self.len() {
+            (0, members)
+        } else {
+            (next, members)
+        }
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match self.scores.remove_entry(member) {
+            Some((member, score)) => {
+                self.ordered.remove(&(OrderedFloat(score), member));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove_range_by_rank(&mut self, start: i64, stop: i64) -> usize {
+        let members = self.range_by_rank(start, stop, false);
+        self.remove_all(members.into_iter().map(|(member, _)| member))
+    }
+
+    pub fn remove_range_by_score(&mut self, min: Bound<f64>, max: Bound<f64>) -> usize {
+        let members = self
+            .iter_by_score(min, max)
+            .map(|(member, _)| member.clone())
+            .collect::<Vec<_>>();
+        self.remove_all(members)
+    }
+
+    pub fn remove_range_by_lex(&mut self, min: Bound<&[u8]>, max: Bound<&[u8]>) -> usize {
+        let members = self
+            .iter_by_lex(min, max)
+            .map(|(member, _)| member.clone())
+            .collect::<Vec<_>>();
+        self.remove_all(members)
+    }
+
+    fn remove_all(&mut self, members: impl IntoIterator<Item = Bytes>) -> usize {
+        members
+            .into_iter()
+            .filter(|member| self.remove(member))
+            .count()
+    }
+
+    /// Combines the input sets, a missing key behaves like an empty set.
+    pub fn combine(
+        operation: ZSetOperation,
+        sets: &[Option<ZSet>],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> ZSet {
+        let weight = |i: usize| weights.get(i).copied().unwrap_or(1.0);
+        let mut result = HashMap::<Bytes, f64>::new();
+        match operation {
+            ZSetOperation::Union => {
+                for (i, zset) in sets.iter().enumerate() {
+                    let Some(zset) = zset else { continue };
+                    for (member, score) in &zset.scores {
+                        let score = weighted(*score, weight(i));
+                        result
+                            .entry(member.clone())
+                            .and_modify(|acc| *acc = aggregate.apply(*acc, score))
+                            .or_insert(score);
+                    }
+                }
+            }
+            ZSetOperation::Inter => {
+                let Some(Some(first)) = sets.first() else {
+                    return ZSet::default();
+                };
+                'members: for (member, score) in &first.scores {
+                    let mut acc = weighted(*score, weight(0));
+                    for (i, zset) in sets.iter().enumerate().skip(1) {
+                        match zset.as_ref().and_then(|zset| zset.score(member)) {
+                            Some(score) => acc = aggregate.apply(acc, weighted(score, weight(i))),
+                            None => continue 'members,
+                        }
+                    }
+                    result.insert(member.clone(), acc);
+                }
+            }
+            ZSetOperation::Diff => {
+                let Some(Some(first)) = sets.first() else {
+                    return ZSet::default();
+                };
+                for (member, score) in &first.scores {
+                    let excluded = sets[1..]
+                        .iter()
+                        .flatten()
+                        .any(|zset| zset.scores.contains_key(member));
+                    if !excluded {
+                        result.insert(member.clone(), *score);
+                    }
+                }
+            }
+        }
+        result.into_iter().collect()
+    }
+
+    fn insert(&mut self, member: Bytes, score: f64) {
+        self.ordered.insert((OrderedFloat(score), member.clone()));
+        self.scores.insert(member, score);
+    }
+}
+
+impl FromIterator<(Bytes, f64)> for ZSet {
+    fn from_iter<T: IntoIterator<Item = (Bytes, f64)>>(iter: T) -> Self {
+        let mut zset = ZSet::default();
+        for (member, score) in iter {
+            if let Some(current) = zset.scores.get(&member).copied() {
+                zset.ordered
+                    .remove(&(OrderedFloat(current), member.clone()));
+            }
+            zset.insert(member, score);
+        }
+        zset
+    }
+}
+
+impl Aggregate {
+    fn apply(self, acc: f64, score: f64) -> f64 {
+        let result = match self {
+            Aggregate::Sum => acc + score,
+            Aggregate::Min => acc.min(score),
+            Aggregate::Max => acc.max(score),
+        };
+        // inf + -inf is NaN, which Redis treats as zero
+        if result.is_nan() {
+            0.0
+        } else {
+            result
+        }
+    }
+}
+
+// 0 * inf is NaN, which Redis treats as zero
+fn weighted(score: f64, weight: f64) -> f64 {
+    let score = score * weight;
+    if score.is_nan() {
+        0.0
+    } else {
+        score
+    }
+}
+
+// Resolves negative indexes against `len` and clamps the range, `None` if it is empty.
+pub(super) fn normalize_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop || start >= len {
+        return None;
+    }
+    Some((start as usize, stop as usize))
+}
+
+fn collect<'a>(iter: impl Iterator<Item = &'a (OrderedFloat<f64>, Bytes)>) -> Vec<(Bytes, f64)> {
+    iter.map(|(score, member)| (member.clone(), score.0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zset_add_flags() {
+        let mut zset = ZSet::default();
+        let flags = ZAddFlags::default();
+        assert_eq!(zset.add("a".into(), 1.0, flags), ZAddOutcome::Added(1.0));
+        assert_eq!(
+            zset.add("a".into(), 1.0, flags),
+            ZAddOutcome::Unchanged(1.0)
+        );
+
+        let nx = ZAddFlags {
+            nx: true,
+            ..Default::default()
+        };
+        assert_eq!(zset.add("a".into(), 5.0, nx), ZAddOutcome::Ignored);
+
+        let xx = ZAddFlags {
+            xx: true,
+            ..Default::default()
+        };
+        assert_eq!(zset.add("b".into(), 5.0, xx), ZAddOutcome::Ignored);
+        assert_eq!(zset.len(), 1);
+
+        let gt = ZAddFlags {
+            gt: true,
+            ..Default::default()
+        };
+        assert_eq!(zset.add("a".into(), 0.5, gt), ZAddOutcome::Ignored);
+        assert_eq!(zset.add("a".into(), 2.0, gt), ZAddOutcome::Updated(2.0));
+
+        let incr = ZAddFlags {
+            incr: true,
+            ..Default::default()
+        };
+        assert_eq!(zset.add("a".into(), 3.0, incr), ZAddOutcome::Updated(5.0));
+        assert_eq!(zset.score(b"a"), Some(5.0));
+    }
+
+    #[test]
+    fn test_zset_range_by_rank() {
+        let mut zset = ZSet::default();
+        for (member, score) in [("a", 1.0), ("b", 2.0), ("c", 3.0)] {
+            zset.add(member.into(), score, ZAddFlags::default());
+        }
+        assert_eq!(
+            zset.range_by_rank(0, -1, false),
+            vec![("a".into(), 1.0), ("b".into(), 2.0), ("c".into(), 3.0)]
+        );
+        assert_eq!(
+            zset.range_by_rank(-2, 10, true),
+            vec![("b".into(), 2.0), ("a".into(), 1.0)]
+        );
+        assert!(zset.range_by_rank(2, 1, false).is_empty());
+    }
+
+    #[test]
+    fn test_zset_pop_random_and_scan() {
+        let mut zset: ZSet = ["a", "b", "c", "d"]
+            .iter()
+            .enumerate()
+            .map(|(i, member)| (Bytes::from(*member), i as f64))
+            .collect();
+
+        let (cursor, members) = zset.scan(0, 3);
+        assert_eq!(cursor, 3);
+        assert_eq!(members.len(), 3);
+        let (cursor, members) = zset.scan(cursor, 3);
+        assert_eq!(cursor, 0);
+        assert_eq!(members, vec![("d".into(), 3.0)]);
+
+        assert_eq!(zset.random_members(10).len(), 4);
+        assert_eq!(zset.random_members(-10).len(), 10);
+        assert_eq!(zset.random_members(i64::MAX).len(), 4);
+
+        assert_eq!(zset.pop(1, true), vec![("d".into(), 3.0)]);
+        assert_eq!(
+            zset.pop(2, false),
+            vec![("a".into(), 0.0), ("b".into(), 1.0)]
+        );
+        assert_eq!(zset.len(), 1);
+    }
+
+    #[test]
+    fn test_zset_remove_ranges() {
+        let mut zset: ZSet = ["a", "b", "c", "d", "e"]
+            .iter()
+            .enumerate()
+            .map(|(i, member)| (Bytes::from(*member), i as f64))
+            .collect();
+        assert_eq!(zset.remove_range_by_rank(-2, -1), 2);
+        assert_eq!(
+            zset.remove_range_by_score(Bound::Excluded(0.0), Bound::Included(1.0)),
+            1
+        );
+        assert_eq!(
+            zset.remove_range_by_lex(Bound::Unbounded, Bound::Included(&b"a"[..])),
+            1
+        );
+        assert_eq!(zset.range_by_rank(0, -1, false), vec![("c".into(), 2.0)]);
+    }
+
+    #[test]
+    fn test_zset_combine() {
+        let a: ZSet = [(Bytes::from("x"), 1.0), (Bytes::from("y"), 2.0)]
+            .into_iter()
+            .collect();
+        let b: ZSet = [(Bytes::from("y"), 3.0), (Bytes::from("z"), 4.0)]
+            .into_iter()
+            .collect();
+        let sets = [Some(a), Some(b), None];
+
+        let union = ZSet::combine(ZSetOperation::Union, &sets, &[2.0, 1.0], Aggregate::Sum);
+        assert_eq!(
+            union.range_by_rank(0, -1, false),
+            vec![("x".into(), 2.0), ("z".into(), 4.0), ("y".into(), 7.0)]
+        );
+
+        let inter = ZSet::combine(ZSetOperation::Inter, &sets[..2], &[], Aggregate::Max);
+        assert_eq!(inter.range_by_rank(0, -1, false), vec![("y".into(), 3.0)]);
+        let inter = ZSet::combine(ZSetOperation::Inter, &sets, &[], Aggregate::Max);
+        assert!(inter.is_empty());
+
+        let diff = ZSet::combine(ZSetOperation::Diff, &sets, &[], Aggregate::Sum);
+        assert_eq!(diff.range_by_rank(0, -1, false), vec![("x".into(), 1.0)]);
+    }
+
+    #[test]
+    fn test_zset_iter_by_score_and_lex() {
+        let mut zset = ZSet::default();
+        for (member, score) in [("a", 1.0), ("b", 1.5), ("c", 2.0), ("d", 3.0)] {
+            zset.add(member.into(), score, ZAddFlags::default());
+        }
+        let members = zset
+            .iter_by_score(Bound::Excluded(1.0), Bound::Included(2.0))
+            .map(|(member, _)| member)
+            .collect::<Vec<_>>();
+        assert_eq!(members, vec!["b", "c"]);
+
+        let members = zset
+            .iter_by_score(Bound::Unbounded, Bound::Excluded(1.5))
+            .map(|(member, _)| member)
+            .collect::<Vec<_>>();
+        assert_eq!(members, vec!["a"]);
+
+        let mut zset = ZSet::default();
+        for member in ["a", "b", "c", "d"] {
+            zset.add(member.into(), 0.0, ZAddFlags::default());
+        }
+        let members = zset
+            .iter_by_lex(Bound::Included(&b"b"[..]), Bound::Unbounded)
+            .map(|(member, _)| member)
+            .collect::<Vec<_>>();
+        assert_eq!(members, vec!["b", "c", "d"]);
+
+        let members = zset
+            .iter_by_lex(Bound::Unbounded, Bound::Excluded(&b"c"[..]))
+            .map(|(member, _)| member)
+            .collect::<Vec<_>>();
+        assert_eq!(members, vec!["a", "b"]);
+
+        // seeking past a member, or to where one would be
+        let members = zset
+            .iter_by_lex(Bound::Excluded(&b"b"[..]), Bound::Included(&b"c"[..]))
+            .map(|(member, _)| member)
+            .collect::<Vec<_>>();
+        assert_eq!(members, vec!["c"]);
+        let members = zset
+            .iter_by_lex(Bound::Included(&b"bb"[..]), Bound::Unbounded)
+            .map(|(member, _)| member)
+            .collect::<Vec<_>>();
+        assert_eq!(members, vec!["c", "d"]);
+    }
+}
@@ -0,0 +1,419 @@
+use super::{
+    extract_args, extract_bytes, extract_integer, extract_string, is_keyword, validate_command,
+    CommandError, CommandExecutor,
+};
+use crate::{
+    backend::{BitFieldOp, BitFieldOverflow, BitFieldType, BitRangeUnit},
+    Backend, RespArray, RespFrame,
+};
+use bytes::Bytes;
+
+// strings are capped at 512MB, so bit offsets must fit in 2^32 bits
+const MAX_BIT_OFFSET: i64 = (1 << 32) - 1;
+
+#[derive(Debug)]
+pub struct SetBit {
+    key: Bytes,
+    offset: usize,
+    on: bool,
+}
+
+impl CommandExecutor for SetBit {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend
+            .setbit(self.key, self.offset, self.on)
+            .map_or_else(Into::into, |old| RespFrame::Integer(old as i64))
+    }
+}
+
+// key offset value
+impl TryFrom<RespArray> for SetBit {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["setbit"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = args.0.into_iter();
+        let (Some(key), Some(offset), Some(bit), None) =
+            (args.next(), args.next(), args.next(), args.next())
+        else {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have three arguments".to_string(),
+            ));
+        };
+        let on = match extract_integer(bit) {
+            Ok(0) => false,
+            Ok(1) => true,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "bit is not an integer or out of range".to_string(),
+                ))
+            }
+        };
+        Ok(SetBit {
+            key: extract_bytes(key)?,
+            offset: extract_bit_offset(offset)?,
+            on,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct GetBit {
+    key: Bytes,
+    offset: usize,
+}
+
+impl CommandExecutor for GetBit {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend
+            .getbit(&self.key, self.offset)
+            .map_or_else(Into::into, |bit| RespFrame::Integer(bit as i64))
+    }
+}
+
+// key offset
+impl TryFrom<RespArray> for GetBit {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["getbit"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = args.0.into_iter();
+        let (Some(key), Some(offset), None) = (args.next(), args.next(), args.next()) else {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have two arguments".to_string(),
+            ));
+        };
+        Ok(GetBit {
+            key: extract_bytes(key)?,
+            offset: extract_bit_offset(offset)?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct BitCount {
+    key: Bytes,
+    range: Option<(i64, i64, BitRangeUnit)>,
+}
+
+impl CommandExecutor for BitCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend
+            .bitcount(&self.key, self.range)
+            .map_or_else(Into::into, |count| RespFrame::Integer(count as i64))
+    }
+}
+
+// key [start end [BYTE | BIT]]
+impl TryFrom<RespArray> for BitCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["bitcount"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = args.0.into_iter();
+        let key = match args.next() {
+            Some(key) => extract_bytes(key)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have a key".to_string(),
+                ))
+            }
+        };
+        let range = match (args.next(), args.next(), args.next(), args.next()) {
+            (None, None, None, None) => None,
+            (Some(start), Some(end), unit, None) => {
+                let unit = match unit {
+                    None => BitRangeUnit::Byte,
+                    Some(unit) if is_keyword(&unit, "byte") => BitRangeUnit::Byte,
+                    Some(unit) if is_keyword(&unit, "bit") => BitRangeUnit::Bit,
+                    Some(_) => {
+                        return Err(CommandError::InvalidArgument("syntax error".to_string()))
+                    }
+                };
+                Some((extract_integer(start)?, extract_integer(end)?, unit))
+            }
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(BitCount { key, range })
+    }
+}
+
+#[derive(Debug)]
+pub struct BitField {
+    key: Bytes,
+    ops: Vec<BitFieldOp>,
+}
+
+impl CommandExecutor for BitField {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        // each result an integer, or a null for an increment that overflowed with FAIL
+        backend
+            .bitfield(self.key, &self.ops)
+            .map_or_else(Into::into, RespFrame::from)
+    }
+}
+
+// key [GET type offset] [SET type offset value] [INCRBY type offset increment]
+//     [OVERFLOW <WRAP | SAT | FAIL>] ...
+impl TryFrom<RespArray> for BitField {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["bitfield"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = args.0.into_iter();
+        let key = match args.next() {
+            Some(key) => extract_bytes(key)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have a key".to_string(),
+                ))
+            }
+        };
+        let mut ops = vec![];
+        let mut overflow = BitFieldOverflow::default();
+        while let Some(subcommand) = args.next() {
+            let subcommand = extract_string(subcommand)?.to_ascii_lowercase();
+            if subcommand == "overflow" {
+                overflow = match args.next().map(extract_string).transpose()? {
+                    Some(mode) if mode.eq_ignore_ascii_case("wrap") => BitFieldOverflow::Wrap,
+                    Some(mode) if mode.eq_ignore_ascii_case("sat") => BitFieldOverflow::Sat,
+                    Some(mode) if mode.eq_ignore_ascii_case("fail") => BitFieldOverflow::Fail,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "Invalid OVERFLOW type specified".to_string(),
+                        ))
+                    }
+                };
+                continue;
+            }
+            let (Some(ty), Some(offset)) = (args.next(), args.next()) else {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()));
+            };
+            let ty = extract_bitfield_type(ty)?;
+            let offset = extract_bitfield_offset(offset, ty)?;
+            let op = match subcommand.as_str() {
+                "get" => BitFieldOp::Get { ty, offset },
+                "set" | "incrby" => {
+                    let Some(value) = args.next() else {
+                        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+                    };
+                    let value = extract_integer(value)?;
+                    if subcommand == "set" {
+                        BitFieldOp::Set {
+                            ty,
+                            offset,
+                            value,
+                            overflow,
+                        }
+                    } else {
+                        BitFieldOp::IncrBy {
+                            ty,
+                            offset,
+                            increment: value,
+                            overflow,
+                        }
+                    }
+                }
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            };
+            ops.push(op);
+        }
+        Ok(BitField { key, ops })
+    }
+}
+
+fn extract_bitfield_type(frame: RespFrame) -> Result<BitFieldType, CommandError> {
+    let ty = extract_string(frame)?;
+    let (signed, bits) = match ty.split_at_checked(1) {
+        Some(("i" | "I", bits)) => (true, bits.parse::<u8>().ok()),
+        Some(("u" | "U", bits)) => (false, bits.parse::<u8>().ok()),
+        _ => (false, None),
+    };
+    match bits {
+        Some(bits) if bits >= 1 && (bits <= 63 || (signed && bits == 64)) => {
+            Ok(BitFieldType { signed, bits })
+        }
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is."
+                .to_string(),
+        )),
+    }
+}
+
+// Offsets prefixed with `#` are multiplied by the field width.
+fn extract_bitfield_offset(frame: RespFrame, ty: BitFieldType) -> Result<usize, CommandError> {
+    let offset = extract_string(frame)?;
+    let offset = match offset.strip_prefix('#') {
+        Some(index) => index
+            .parse::<i64>()
+            .ok()
+            .and_then(|index| index.checked_mul(ty.bits as i64)),
+        None => offset.parse::<i64>().ok(),
+    };
+    match offset {
+        Some(offset) if offset >= 0 && offset + ty.bits as i64 - 1 <= MAX_BIT_OFFSET => {
+            Ok(offset as usize)
+        }
+        _ => Err(CommandError::InvalidArgument(
+            "bit offset is not an integer or out of range".to_string(),
+        )),
+    }
+}
+
+fn extract_bit_offset(frame: RespFrame) -> Result<usize, CommandError> {
+    match extract_integer(frame) {
+        Ok(offset) if (0..=MAX_BIT_OFFSET).contains(&offset) => Ok(offset as usize),
+        _ => Err(CommandError::InvalidArgument(
+            "bit offset is not an integer or out of range".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resp::RespDecoder, BulkString, RespNull};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_setbit_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nsetbit\r\n$3\r\nkey\r\n$2\r\n10\r\n$1\r\n1\r\n");
+        let cmd = SetBit::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.offset, 10);
+        assert!(cmd.on);
+
+        buf.extend_from_slice(b"*4\r\n$6\r\nsetbit\r\n$3\r\nkey\r\n$2\r\n-1\r\n$1\r\n1\r\n");
+        assert!(SetBit::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*4\r\n$6\r\nsetbit\r\n$3\r\nkey\r\n$1\r\n1\r\n$1\r\n2\r\n");
+        assert!(SetBit::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitcount_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$8\r\nbitcount\r\n$3\r\nkey\r\n$1\r\n5\r\n$2\r\n30\r\n$3\r\nBIT\r\n",
+        );
+        let cmd = BitCount::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.range, Some((5, 30, BitRangeUnit::Bit)));
+
+        buf.extend_from_slice(b"*3\r\n$8\r\nbitcount\r\n$3\r\nkey\r\n$1\r\n5\r\n");
+        assert!(BitCount::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitfield_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*11\r\n$8\r\nbitfield\r\n$3\r\nkey\r\n$3\r\nGET\r\n$2\r\ni8\r\n$2\r\n#1\r\n$8\r\nOVERFLOW\r\n$3\r\nSAT\r\n$6\r\nINCRBY\r\n$2\r\nu4\r\n$1\r\n0\r\n$2\r\n-3\r\n",
+        );
+        let cmd = BitField::try_from(RespArray::decode(&mut buf)?)?;
+        let i8 = BitFieldType {
+            signed: true,
+            bits: 8,
+        };
+        let u4 = BitFieldType {
+            signed: false,
+            bits: 4,
+        };
+        assert_eq!(
+            cmd.ops,
+            vec![
+                BitFieldOp::Get { ty: i8, offset: 8 },
+                BitFieldOp::IncrBy {
+                    ty: u4,
+                    offset: 0,
+                    increment: -3,
+                    overflow: BitFieldOverflow::Sat,
+                },
+            ]
+        );
+
+        buf.extend_from_slice(
+            b"*5\r\n$8\r\nbitfield\r\n$3\r\nkey\r\n$3\r\nGET\r\n$3\r\nu64\r\n$1\r\n0\r\n",
+        );
+        assert!(BitField::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitmap_cmds_execute() {
+        let backend = Backend::new();
+        let cmd = SetBit {
+            key: "key".into(),
+            offset: 7,
+            on: true,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        assert_eq!(
+            backend.get(b"key").unwrap(),
+            Some(BulkString::new(vec![0b0000_0001]).into())
+        );
+        let cmd = GetBit {
+            key: "key".into(),
+            offset: 7,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        backend.set("key".into(), BulkString::from("foobar").into());
+        let cmd = BitCount {
+            key: "key".into(),
+            range: None,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(26));
+        let cmd = BitCount {
+            key: "key".into(),
+            range: Some((1, 1, BitRangeUnit::Byte)),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(6));
+        let cmd = BitCount {
+            key: "key".into(),
+            range: Some((5, 30, BitRangeUnit::Bit)),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(17));
+
+        let u2 = BitFieldType {
+            signed: false,
+            bits: 2,
+        };
+        let cmd = BitField {
+            key: "counters".into(),
+            ops: vec![
+                BitFieldOp::IncrBy {
+                    ty: u2,
+                    offset: 0,
+                    increment: 3,
+                    overflow: BitFieldOverflow::Fail,
+                },
+                BitFieldOp::IncrBy {
+                    ty: u2,
+                    offset: 0,
+                    increment: 1,
+                    overflow: BitFieldOverflow::Fail,
+                },
+            ],
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(3), RespFrame::Null(RespNull)]).into()
+        );
+        let cmd = BitField {
+            key: "missing".into(),
+            ops: vec![BitFieldOp::Get { ty: u2, offset: 0 }],
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(0)]).into()
+        );
+        assert_eq!(backend.get(b"missing").unwrap(), None);
+    }
+}
@@ -0,0 +1,1090 @@
+use super::{
+    connection_only, extract_args, extract_string, validate_command, CommandError, CommandExecutor,
+    REDACTED, RESP_OK,
+};
+use crate::{
+    backend::{self, Subscriber, TrackingOptions},
+    session::{ReplyMode, Session},
+    Backend, BulkString, RespArray, RespFrame, RespMap, RespNull, SimpleError, SimpleString,
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+pub struct ClientTracking(Option<TrackingOptions>);
+
+#[derive(Debug)]
+pub struct ClientSetName(String);
+
+#[derive(Debug)]
+pub struct ClientGetName;
+
+#[derive(Debug)]
+pub struct ClientId;
+
+#[derive(Debug)]
+pub struct ClientInfo;
+
+#[derive(Debug)]
+pub struct ClientPause {
+    timeout: u64,
+    writes_only: bool,
+}
+
+#[derive(Debug)]
+pub struct ClientUnpause;
+
+#[derive(Debug)]
+pub struct ClientReply(ReplyMode);
+
+#[derive(Debug)]
+pub struct Reset;
+
+#[derive(Debug)]
+pub struct ClientList {
+    kind: Option<ClientType>,
+    // only the connections with these ids when not empty
+    ids: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClientType {
+    Normal,
+    PubSub,
+    Replica,
+}
+
+impl ClientType {
+    fn of(info: &backend::ClientInfo) -> Self {
+        if info.replica {
+            ClientType::Replica
+        } else if info.sub + info.psub + info.ssub > 0 {
+            ClientType::PubSub
+        } else {
+            ClientType::Normal
+        }
+    }
+}
+
+pub struct Auth {
+    username: Option<String>,
+    password: String,
+}
+
+pub struct Hello {
+    protocol: Option<i64>,
+    // username and password
+    auth: Option<(String, String)>,
+    name: Option<String>,
+}
+
+// The passwords are left out, as the commands are logged.
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Auth")
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Hello {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hello")
+            .field("protocol", &self.protocol)
+            .field(
+                "auth",
+                &self.auth.as_ref().map(|(username, _)| (username, REDACTED)),
+            )
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl CommandExecutor for ClientTracking {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("CLIENT TRACKING")
+    }
+}
+
+impl ClientTracking {
+    pub fn execute_client(self, backend: &Backend, id: u64) -> RespFrame {
+        let Some(options) = self.0 else {
+            backend.disable_tracking(id);
+            return RESP_OK.clone();
+        };
+        match backend.enable_tracking(id, options) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => SimpleError::new(format!("ERR {}", e)).into(),
+        }
+    }
+}
+
+impl CommandExecutor for Auth {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("AUTH")
+    }
+}
+
+impl Auth {
+    /// Authenticates the connection, letting it run commands when requirepass is set.
+    pub fn execute_client(self, backend: &Backend, subscriber: &mut Subscriber) -> RespFrame {
+        if self.username.is_none() && !backend.requires_auth() {
+            return SimpleError::new(
+                "ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?",
+            )
+            .into();
+        }
+        let username = self.username.as_deref().unwrap_or("default");
+        match backend.check_password(username, &self.password) {
+            true => {
+                subscriber.set_authenticated(true);
+                RESP_OK.clone()
+            }
+            false => wrong_password(),
+        }
+    }
+}
+
+fn wrong_password() -> RespFrame {
+    SimpleError::new("WRONGPASS invalid username-password pair or user is disabled.").into()
+}
+
+// [username] password
+impl TryFrom<RespArray> for Auth {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["auth"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(password), None, None) => Ok(Auth {
+                username: None,
+                password: extract_string(password)?,
+            }),
+            (Some(username), Some(password), None) => Ok(Auth {
+                username: Some(extract_string(username)?),
+                password: extract_string(password)?,
+            }),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "auth takes a password, optionally after a username".to_string(),
+            )),
+        }
+    }
+}
+
+impl CommandExecutor for Hello {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("HELLO")
+    }
+}
+
+impl Hello {
+    /// Switches the connection to the protocol version, replying with the server metadata in
+    /// that version.
+    pub fn execute_client(self, backend: &Backend, subscriber: &mut Subscriber) -> RespFrame {
+        let protocol = match self.protocol {
+            None => subscriber.protocol(),
+            Some(protocol @ 2..=3) => protocol as u8,
+            Some(_) => return SimpleError::new("NOPROTO unsupported protocol version").into(),
+        };
+        match &self.auth {
+            Some((username, password)) => {
+                if !backend.check_password(username, password) {
+                    return wrong_password();
+                }
+                subscriber.set_authenticated(true);
+            }
+            None if backend.requires_auth() && !subscriber.is_authenticated() => {
+                return SimpleError::new(
+                    "NOAUTH HELLO must be called with the client already authenticated, otherwise the HELLO <proto> AUTH <user> <pass> option can be used to authenticate the client and select the RESP protocol version at the same time",
+                )
+                .into();
+            }
+            None => {}
+        }
+        if let Some(name) = self.name {
+            if !is_valid_name(&name) {
+                return invalid_name();
+            }
+            subscriber.set_name((!name.is_empty()).then_some(name));
+        }
+        subscriber.set_protocol(protocol);
+
+        let mode = match backend.cluster_enabled() {
+            true => "cluster",
+            false => "standalone",
+        };
+        let role = match backend.master() {
+            Some(_) => "replica",
+            None => "master",
+        };
+        let fields: [(&str, RespFrame); 7] = [
+            ("server", BulkString::from("redis").into()),
+            (
+                "version",
+                BulkString::from(env!("CARGO_PKG_VERSION")).into(),
+            ),
+            ("proto", RespFrame::Integer(protocol.into())),
+            ("id", RespFrame::Integer(subscriber.id() as i64)),
+            ("mode", BulkString::from(mode).into()),
+            ("role", BulkString::from(role).into()),
+            ("modules", RespArray::new(vec![]).into()),
+        ];
+        let fields = fields
+            .into_iter()
+            .map(|(name, value)| (RespFrame::from(BulkString::from(name)), value));
+        match protocol {
+            // a flat list of names and values, as RESP2 has no maps
+            2 => RespArray::new(
+                fields
+                    .flat_map(|(name, value)| [name, value])
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            _ => RespMap::new(fields.collect::<HashMap<_, _>>()).into(),
+        }
+    }
+}
+
+// Names are shown space separated by CLIENT INFO, one connection per line.
+fn is_valid_name(name: &str) -> bool {
+    name.bytes().all(|b| (b'!'..=b'~').contains(&b))
+}
+
+fn invalid_name() -> RespFrame {
+    SimpleError::new("ERR Client names cannot contain spaces, newlines or special characters.")
+        .into()
+}
+
+// [protover [AUTH username password] [SETNAME clientname]]
+impl TryFrom<RespArray> for Hello {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["hello"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?
+            .0
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter();
+        let mut hello = Hello {
+            protocol: None,
+            auth: None,
+            name: None,
+        };
+        let Some(protocol) = args.next() else {
+            return Ok(hello);
+        };
+        hello.protocol = Some(protocol.parse().map_err(|_| {
+            CommandError::InvalidArgument(
+                "Protocol version is not an integer or out of range".to_string(),
+            )
+        })?);
+        let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+        while let Some(arg) = args.next() {
+            match arg.to_ascii_lowercase().as_str() {
+                "auth" => {
+                    let username = args.next().ok_or_else(syntax_error)?;
+                    let password = args.next().ok_or_else(syntax_error)?;
+                    hello.auth = Some((username, password));
+                }
+                "setname" => hello.name = Some(args.next().ok_or_else(syntax_error)?),
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(hello)
+    }
+}
+
+impl CommandExecutor for ClientSetName {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("CLIENT SETNAME")
+    }
+}
+
+impl ClientSetName {
+    /// Names the connection, or removes its name when given an empty one.
+    pub fn execute_client(self, subscriber: &Subscriber) -> RespFrame {
+        if !is_valid_name(&self.0) {
+            return invalid_name();
+        }
+        subscriber.set_name((!self.0.is_empty()).then_some(self.0));
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for ClientGetName {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("CLIENT GETNAME")
+    }
+}
+
+impl ClientGetName {
+    pub fn execute_client(self, subscriber: &Subscriber) -> RespFrame {
+        match subscriber.name() {
+            Some(name) => BulkString::from(name).into(),
+            None => RespNull.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ClientId {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("CLIENT ID")
+    }
+}
+
+impl ClientId {
+    pub fn execute_client(self, subscriber: &Subscriber) -> RespFrame {
+        RespFrame::Integer(subscriber.id() as i64)
+    }
+}
+
+impl CommandExecutor for ClientInfo {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("CLIENT INFO")
+    }
+}
+
+impl ClientInfo {
+    pub fn execute_client(self, backend: &Backend, subscriber: &Subscriber) -> RespFrame {
+        match backend.client_info(subscriber.id()) {
+            Some(info) => BulkString::from(client_line(&info, Instant::now())).into(),
+            None => RespNull.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ClientReply {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("CLIENT REPLY")
+    }
+}
+
+impl ClientReply {
+    /// Switches the replies of the connection on or off. Only ON gets its reply.
+    pub fn execute_connection(self, session: &mut Session) -> RespFrame {
+        session.reply = self.0;
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for Reset {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("RESET")
+    }
+}
+
+impl Reset {
+    /// Clears the state of the connection, as `Session::reset` does.
+    pub fn execute_connection(self, session: &mut Session) -> RespFrame {
+        session.reset();
+        SimpleString::new("RESET").into()
+    }
+}
+
+impl CommandExecutor for ClientList {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let now = Instant::now();
+        let lines: String = backend
+            .client_infos()
+            .iter()
+            .filter(|info| self.kind.is_none_or(|kind| ClientType::of(info) == kind))
+            .filter(|info| self.ids.is_empty() || self.ids.contains(&info.id))
+            .map(|info| client_line(info, now))
+            .collect();
+        BulkString::from(lines).into()
+    }
+}
+
+impl CommandExecutor for ClientPause {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend.pause_clients(
+            Instant::now() + Duration::from_millis(self.timeout),
+            self.writes_only,
+        );
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for ClientUnpause {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend.unpause_clients();
+        RESP_OK.clone()
+    }
+}
+
+// The line describing a connection in the replies of CLIENT INFO and CLIENT LIST, in the
+// format of Redis.
+fn client_line(info: &backend::ClientInfo, now: Instant) -> String {
+    let mut flags = String::new();
+    if info.replica {
+        flags.push('S');
+    }
+    if info.sub + info.psub + info.ssub > 0 {
+        flags.push('P');
+    }
+    if info.multi.is_some() {
+        flags.push('x');
+    }
+    if flags.is_empty() {
+        flags.push('N');
+    }
+    format!(
+        "id={} addr={} name={} age={} idle={} flags={} db={} sub={} psub={} ssub={} multi={} resp={} cmd={}\n",
+        info.id,
+        info.addr.map(|addr| addr.to_string()).unwrap_or_default(),
+        info.name.as_deref().unwrap_or_default(),
+        now.duration_since(info.created).as_secs(),
+        now.duration_since(info.last_active).as_secs(),
+        flags,
+        info.db,
+        info.sub,
+        info.psub,
+        info.ssub,
+        info.multi.map_or(-1, |queued| queued as i64),
+        info.protocol,
+        info.cmd.as_deref().unwrap_or("NULL"),
+    )
+}
+
+// name
+impl TryFrom<RespArray> for ClientSetName {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["client", "setname"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        match (args.next(), args.next()) {
+            (Some(name), None) => Ok(ClientSetName(extract_string(name)?)),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "client setname takes a name".to_string(),
+            )),
+        }
+    }
+}
+
+// timeout [WRITE|ALL]
+impl TryFrom<RespArray> for ClientPause {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["client", "pause"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        let timeout = args.next().ok_or_else(|| {
+            CommandError::InvalidCommandArguments("client pause takes a timeout".to_string())
+        })?;
+        let timeout = extract_string(timeout)?.parse::<i64>().map_err(|_| {
+            CommandError::InvalidArgument("timeout is not an integer or out of range".to_string())
+        })?;
+        let timeout = u64::try_from(timeout)
+            .map_err(|_| CommandError::InvalidArgument("timeout is negative".to_string()))?;
+        let writes_only = match (args.next().map(extract_string).transpose()?, args.next()) {
+            (None, None) => false,
+            (Some(mode), None) if mode.eq_ignore_ascii_case("all") => false,
+            (Some(mode), None) if mode.eq_ignore_ascii_case("write") => true,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(ClientPause {
+            timeout,
+            writes_only,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ClientUnpause {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_args(value, ["client", "unpause"]).map(|_| ClientUnpause)
+    }
+}
+
+// ON|OFF|SKIP
+impl TryFrom<RespArray> for ClientReply {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["client", "reply"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        let mode = match (args.next().map(extract_string).transpose()?, args.next()) {
+            (Some(mode), None) => mode.to_ascii_lowercase(),
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        match mode.as_str() {
+            "on" => Ok(ClientReply(ReplyMode::On)),
+            "off" => Ok(ClientReply(ReplyMode::Off)),
+            "skip" => Ok(ClientReply(ReplyMode::SkipNext)),
+            _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Reset {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["reset"];
+        validate_command(&value, &cmd_names)?;
+        if value.len() != cmd_names.len() {
+            return Err(CommandError::InvalidCommandArguments(
+                "reset takes no arguments".to_string(),
+            ));
+        }
+        Ok(Reset)
+    }
+}
+
+impl TryFrom<RespArray> for ClientGetName {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_args(value, ["client", "getname"]).map(|_| ClientGetName)
+    }
+}
+
+impl TryFrom<RespArray> for ClientId {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_args(value, ["client", "id"]).map(|_| ClientId)
+    }
+}
+
+impl TryFrom<RespArray> for ClientInfo {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_args(value, ["client", "info"]).map(|_| ClientInfo)
+    }
+}
+
+// [TYPE normal|pubsub|replica] [ID client-id [client-id ...]]
+impl TryFrom<RespArray> for ClientList {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["client", "list"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?
+            .0
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .peekable();
+        let mut list = ClientList {
+            kind: None,
+            ids: vec![],
+        };
+        let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+        while let Some(arg) = args.next() {
+            match arg.to_ascii_lowercase().as_str() {
+                "type" => {
+                    let kind = args.next().ok_or_else(syntax_error)?;
+                    list.kind = Some(match kind.to_ascii_lowercase().as_str() {
+                        "normal" => ClientType::Normal,
+                        "pubsub" => ClientType::PubSub,
+                        "replica" | "slave" => ClientType::Replica,
+                        _ => {
+                            return Err(CommandError::InvalidArgument(format!(
+                                "Unknown client type '{}'",
+                                kind
+                            )))
+                        }
+                    });
+                }
+                "id" => {
+                    args.peek().ok_or_else(syntax_error)?;
+                    for id in args.by_ref() {
+                        let id = id.parse().map_err(|_| {
+                            CommandError::InvalidArgument("Invalid client ID".to_string())
+                        })?;
+                        list.ids.push(id);
+                    }
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(list)
+    }
+}
+
+fn no_args(value: RespArray, cmd_names: [&'static str; 2]) -> Result<(), CommandError> {
+    validate_command(&value, &cmd_names)?;
+    if value.len() != cmd_names.len() {
+        return Err(CommandError::InvalidCommandArguments(format!(
+            "{} takes no arguments",
+            cmd_names.join(" ")
+        )));
+    }
+    Ok(())
+}
+
+// ON|OFF [REDIRECT client-id] [BCAST] [PREFIX prefix [PREFIX prefix ...]]
+impl TryFrom<RespArray> for ClientTracking {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["client", "tracking"];
+        validate_command(&value, &cmd_names)?;
+        let args = Vec::<String>::try_from(extract_args(value, cmd_names.len())?)?;
+        let mut args = args.into_iter();
+        let on = match args.next().map(|arg| arg.to_ascii_lowercase()).as_deref() {
+            Some("on") => true,
+            Some("off") => false,
+            _ => {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()));
+            }
+        };
+        let mut options = TrackingOptions::default();
+        while let Some(arg) = args.next() {
+            match arg.to_ascii_lowercase().as_str() {
+                "redirect" => {
+                    let redirect = args
+                        .next()
+                        .and_then(|id| id.parse().ok())
+                        .ok_or_else(|| CommandError::InvalidArgument("syntax error".to_string()))?;
+                    options.redirect = Some(redirect);
+                }
+                "bcast" => options.bcast = true,
+                "prefix" => {
+                    let prefix = args
+                        .next()
+                        .ok_or_else(|| CommandError::InvalidArgument("syntax error".to_string()))?;
+                    options.prefixes.push(prefix.into());
+                }
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(ClientTracking(on.then_some(options)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resp::RespDecoder, BulkString, RespPush};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_client_tracking_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$6\r\nclient\r\n$8\r\ntracking\r\n$2\r\non\r\n$5\r\nBCAST\r\n$6\r\nprefix\r\n$5\r\nuser:\r\n$5\r\nbcast\r\n",
+        );
+        let cmd = ClientTracking::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(
+            cmd.0,
+            Some(TrackingOptions {
+                redirect: None,
+                bcast: true,
+                prefixes: vec!["user:".into()],
+            })
+        );
+
+        buf.extend_from_slice(b"*3\r\n$6\r\nclient\r\n$8\r\ntracking\r\n$3\r\noff\r\n");
+        let cmd = ClientTracking::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0, None);
+
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\nclient\r\n$8\r\ntracking\r\n$2\r\non\r\n$8\r\nredirect\r\n$1\r\nx\r\n",
+        );
+        assert!(ClientTracking::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_tracking_execute() {
+        let backend = Backend::new();
+        let mut client = backend.subscriber();
+        let cmd = ClientTracking(Some(TrackingOptions {
+            prefixes: vec!["user:".into()],
+            ..Default::default()
+        }));
+        assert_eq!(
+            cmd.execute_client(&backend, client.id()),
+            SimpleError::new("ERR PREFIX option requires BCAST mode to be enabled").into()
+        );
+
+        let cmd = ClientTracking(Some(TrackingOptions::default()));
+        assert_eq!(cmd.execute_client(&backend, client.id()), RESP_OK.clone());
+        backend.track_keys(client.id(), &[b"foo".as_slice()]);
+        backend.set("foo".into(), BulkString::from("bar").into());
+        assert_eq!(
+            client.message().await.map(Arc::unwrap_or_clone),
+            Some(
+                RespPush::new([
+                    BulkString::from("invalidate").into(),
+                    RespArray::new([BulkString::from("foo").into()]).into(),
+                ])
+                .into()
+            )
+        );
+
+        assert_eq!(
+            ClientTracking(None).execute_client(&backend, client.id()),
+            RESP_OK.clone()
+        );
+        assert!(!backend.is_tracking(client.id()));
+    }
+
+    #[test]
+    fn test_hello_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$5\r\nhello\r\n$1\r\n3\r\n$4\r\nAUTH\r\n$7\r\ndefault\r\n$4\r\npass\r\n$7\r\nsetname\r\n$3\r\napp\r\n",
+        );
+        let cmd = Hello::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.protocol, Some(3));
+        assert_eq!(cmd.auth, Some(("default".to_string(), "pass".to_string())));
+        assert_eq!(cmd.name.as_deref(), Some("app"));
+        assert!(format!("{:?}", cmd).contains(r#"auth: Some(("default", "<redacted>"))"#));
+
+        buf.extend_from_slice(b"*1\r\n$5\r\nhello\r\n");
+        let cmd = Hello::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.protocol, None);
+
+        buf.extend_from_slice(b"*2\r\n$5\r\nhello\r\n$5\r\nthree\r\n");
+        assert!(Hello::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*3\r\n$5\r\nhello\r\n$1\r\n3\r\n$7\r\nsetname\r\n");
+        assert!(Hello::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hello_execute() {
+        let backend = Backend::new();
+        let mut client = backend.subscriber();
+        let hello = |protocol, name: Option<&str>| Hello {
+            protocol,
+            auth: None,
+            name: name.map(str::to_string),
+        };
+        let RespFrame::Map(reply) =
+            hello(Some(3), Some("app")).execute_client(&backend, &mut client)
+        else {
+            panic!("expected a map");
+        };
+        assert_eq!(
+            reply.get(&BulkString::from("proto").into()),
+            Some(&RespFrame::Integer(3))
+        );
+        assert_eq!(
+            reply.get(&BulkString::from("role").into()),
+            Some(&BulkString::from("master").into())
+        );
+        assert_eq!(client.protocol(), 3);
+        assert_eq!(client.name().as_deref(), Some("app"));
+
+        assert_eq!(
+            hello(Some(4), None).execute_client(&backend, &mut client),
+            SimpleError::new("NOPROTO unsupported protocol version").into()
+        );
+        assert_eq!(
+            hello(Some(2), Some("my app")).execute_client(&backend, &mut client),
+            SimpleError::new(
+                "ERR Client names cannot contain spaces, newlines or special characters."
+            )
+            .into()
+        );
+        // nothing changes when the command fails
+        assert_eq!(client.protocol(), 3);
+
+        let RespFrame::Array(reply) = hello(Some(2), None).execute_client(&backend, &mut client)
+        else {
+            panic!("expected an array");
+        };
+        assert_eq!(reply.len(), 14);
+        assert_eq!(reply[0], BulkString::from("server").into());
+        assert_eq!(client.protocol(), 2);
+
+        hello(None, Some("")).execute_client(&backend, &mut client);
+        assert_eq!(client.protocol(), 2);
+        assert_eq!(client.name(), None);
+
+        let cmd = Hello {
+            protocol: Some(2),
+            auth: Some(("admin".to_string(), "secret".to_string())),
+            name: None,
+        };
+        assert_eq!(cmd.execute_client(&backend, &mut client), wrong_password());
+    }
+
+    #[test]
+    fn test_client_name_cmds_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nclient\r\n$7\r\nsetname\r\n$3\r\napp\r\n");
+        let cmd = ClientSetName::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0, "app");
+
+        buf.extend_from_slice(b"*2\r\n$6\r\nclient\r\n$7\r\nsetname\r\n");
+        assert!(ClientSetName::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*2\r\n$6\r\nclient\r\n$7\r\ngetname\r\n");
+        ClientGetName::try_from(RespArray::decode(&mut buf)?)?;
+
+        buf.extend_from_slice(b"*3\r\n$6\r\nclient\r\n$2\r\nid\r\n$1\r\n1\r\n");
+        assert!(ClientId::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_cmds_execute() {
+        let backend = Backend::new();
+        let client = backend.subscriber();
+        assert_eq!(
+            ClientId.execute_client(&client),
+            RespFrame::Integer(client.id() as i64)
+        );
+        assert_eq!(
+            ClientGetName.execute_client(&client),
+            RespFrame::Null(RespNull)
+        );
+        assert_eq!(
+            ClientSetName("app".to_string()).execute_client(&client),
+            RESP_OK.clone()
+        );
+        assert_eq!(
+            ClientSetName("my app".to_string()).execute_client(&client),
+            invalid_name()
+        );
+        assert_eq!(
+            ClientGetName.execute_client(&client),
+            BulkString::from("app").into()
+        );
+
+        backend.client_command(client.id(), "client");
+        client.record_state(2, Some(1));
+        let RespFrame::BulkString(info) = ClientInfo.execute_client(&backend, &client) else {
+            panic!("expected a bulk string");
+        };
+        assert_eq!(
+            String::from_utf8_lossy(&info),
+            format!(
+                "id={} addr= name=app age=0 idle=0 flags=x db=2 sub=0 psub=0 ssub=0 multi=1 resp=2 cmd=client\n",
+                client.id()
+            )
+        );
+
+        ClientSetName(String::new()).execute_client(&client);
+        assert_eq!(
+            ClientGetName.execute_client(&client),
+            RespFrame::Null(RespNull)
+        );
+    }
+
+    #[test]
+    fn test_client_list_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$6\r\nclient\r\n$4\r\nlist\r\n$4\r\nTYPE\r\n$6\r\npubsub\r\n$2\r\nid\r\n$1\r\n7\r\n",
+        );
+        let cmd = ClientList::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.kind, Some(ClientType::PubSub));
+        assert_eq!(cmd.ids, [7]);
+
+        buf.extend_from_slice(
+            b"*4\r\n$6\r\nclient\r\n$4\r\nlist\r\n$4\r\ntype\r\n$6\r\nmaster\r\n",
+        );
+        assert!(ClientList::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*3\r\n$6\r\nclient\r\n$4\r\nlist\r\n$2\r\nid\r\n");
+        assert!(ClientList::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_list_execute() {
+        let backend = Backend::new();
+        let client = backend.subscriber();
+        let mut subscribed = backend.subscriber();
+        subscribed.subscribe("news");
+        subscribed.record_state(0, None);
+        let replica = backend.subscriber();
+        backend.update_client(replica.id(), |info| info.replica = true);
+
+        let listed = |kind, ids: Vec<u64>| -> Vec<String> {
+            let RespFrame::BulkString(lines) = ClientList { kind, ids }.execute(&backend) else {
+                panic!("expected a bulk string");
+            };
+            String::from_utf8_lossy(&lines)
+                .lines()
+                .map(|line| line.split(' ').next().unwrap().to_string())
+                .collect()
+        };
+        let id = |client: &Subscriber| format!("id={}", client.id());
+        assert_eq!(
+            listed(None, vec![]),
+            [id(&client), id(&subscribed), id(&replica)]
+        );
+        assert_eq!(listed(Some(ClientType::Normal), vec![]), [id(&client)]);
+        assert_eq!(listed(Some(ClientType::PubSub), vec![]), [id(&subscribed)]);
+        assert_eq!(listed(Some(ClientType::Replica), vec![]), [id(&replica)]);
+        assert_eq!(
+            listed(None, vec![replica.id(), client.id()]),
+            [id(&client), id(&replica)]
+        );
+        assert!(listed(Some(ClientType::Normal), vec![replica.id()]).is_empty());
+    }
+
+    #[test]
+    fn test_client_pause_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nclient\r\n$5\r\npause\r\n$3\r\n100\r\n$5\r\nWRITE\r\n");
+        let cmd = ClientPause::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!((cmd.timeout, cmd.writes_only), (100, true));
+
+        buf.extend_from_slice(b"*3\r\n$6\r\nclient\r\n$5\r\npause\r\n$1\r\n0\r\n");
+        let cmd = ClientPause::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!((cmd.timeout, cmd.writes_only), (0, false));
+
+        for timeout in ["-1", "soon"] {
+            let frame = RespArray::new(vec![
+                BulkString::from("client").into(),
+                BulkString::from("pause").into(),
+                BulkString::from(timeout).into(),
+            ]);
+            assert!(ClientPause::try_from(frame).is_err());
+        }
+
+        buf.extend_from_slice(b"*4\r\n$6\r\nclient\r\n$5\r\npause\r\n$1\r\n1\r\n$4\r\nread\r\n");
+        assert!(ClientPause::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_auth_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$4\r\nauth\r\n$6\r\nsecret\r\n");
+        let cmd = Auth::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!((cmd.username, cmd.password.as_str()), (None, "secret"));
+
+        buf.extend_from_slice(b"*3\r\n$4\r\nauth\r\n$7\r\ndefault\r\n$6\r\nsecret\r\n");
+        let cmd = Auth::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.username.as_deref(), Some("default"));
+        assert!(!format!("{:?}", cmd).contains("secret"));
+
+        buf.extend_from_slice(b"*1\r\n$4\r\nauth\r\n");
+        assert!(Auth::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_auth_execute() {
+        let backend = Backend::new();
+        let auth = |username: Option<&str>, password: &str| Auth {
+            username: username.map(str::to_string),
+            password: password.to_string(),
+        };
+        let mut client = backend.subscriber();
+        assert!(matches!(
+            auth(None, "secret").execute_client(&backend, &mut client),
+            RespFrame::SimpleError(_)
+        ));
+
+        backend.set_requirepass(Some("secret".to_string()));
+        // connected before the password was required
+        assert!(client.is_authenticated());
+        let mut client = backend.subscriber();
+        assert!(!client.is_authenticated());
+        assert_eq!(
+            auth(None, "wrong").execute_client(&backend, &mut client),
+            wrong_password()
+        );
+        assert_eq!(
+            auth(Some("admin"), "secret").execute_client(&backend, &mut client),
+            wrong_password()
+        );
+        assert!(!client.is_authenticated());
+        assert_eq!(
+            auth(None, "secret").execute_client(&backend, &mut client),
+            RESP_OK.clone()
+        );
+        assert!(client.is_authenticated());
+
+        let mut client = backend.subscriber();
+        let hello = Hello {
+            protocol: Some(3),
+            auth: None,
+            name: None,
+        };
+        assert!(matches!(
+            hello.execute_client(&backend, &mut client),
+            RespFrame::SimpleError(_)
+        ));
+        let hello = Hello {
+            protocol: Some(3),
+            auth: Some(("default".to_string(), "secret".to_string())),
+            name: None,
+        };
+        assert!(matches!(
+            hello.execute_client(&backend, &mut client),
+            RespFrame::Map(_)
+        ));
+        assert!(client.is_authenticated());
+    }
+
+    #[test]
+    fn test_client_reply_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nclient\r\n$5\r\nreply\r\n$4\r\nSKIP\r\n");
+        let cmd = ClientReply::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0, ReplyMode::SkipNext);
+
+        buf.extend_from_slice(b"*3\r\n$6\r\nclient\r\n$5\r\nreply\r\n$5\r\nmaybe\r\n");
+        assert!(ClientReply::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reset_execute() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$5\r\nreset\r\n$1\r\nx\r\n");
+        assert!(Reset::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        let server = Backend::new();
+        let mut session = Session::new(server.select(3).unwrap(), "127.0.0.1:6379".parse()?);
+        let client = &mut session.subscriber;
+        client.subscribe("news");
+        client.psubscribe("n*");
+        client.ssubscribe("shard");
+        client.set_name(Some("app".to_string()));
+        client.set_protocol(3);
+        server
+            .enable_tracking(client.id(), TrackingOptions::default())
+            .unwrap();
+        server.watch(client.id(), b"key");
+        super::super::transaction::Multi.execute_transaction(&mut session.transaction);
+        session.reply = ReplyMode::Off;
+        server.publish("news", &BulkString::from("hi"));
+        server.set_requirepass(Some("secret".to_string()));
+
+        assert_eq!(
+            Reset.execute_connection(&mut session),
+            SimpleString::new("RESET").into()
+        );
+        assert!(!session.transaction.is_active());
+        assert_eq!(session.reply, ReplyMode::On);
+        assert_eq!(session.backend.db_index(), 0);
+        let client = &mut session.subscriber;
+        assert!(!client.is_subscribed());
+        assert_eq!(server.pubsub_numsub("news"), 0);
+        assert_eq!(server.pubsub_numpat(), 0);
+        assert!(!server.is_tracking(client.id()));
+        assert!(!server.unwatch(client.id()));
+        assert_eq!((client.name(), client.protocol()), (None, 2));
+        assert!(!client.is_authenticated());
+        // the message published before is dropped
+        server.publish("news", &BulkString::from("again"));
+        client.psubscribe("x*");
+        server.publish("xs", &BulkString::from("after"));
+        let message = client.message().await.map(Arc::unwrap_or_clone);
+        let Some(RespFrame::Array(message)) = message else {
+            panic!("expected a message");
+        };
+        assert_eq!(message.last(), Some(&BulkString::from("after").into()));
+        Ok(())
+    }
+}
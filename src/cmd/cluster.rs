@@ -0,0 +1,483 @@
+use super::{
+    extract_args, extract_bytes, validate_command, CommandError, CommandExecutor, RESP_OK,
+};
+use crate::{
+    backend::{key_slot, CLUSTER_SLOTS},
+    Backend, BulkString, RespArray, RespFrame, RespMap, SimpleError,
+};
+use bytes::Bytes;
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub struct ClusterKeySlot(Bytes);
+
+impl CommandExecutor for ClusterKeySlot {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if !backend.cluster_enabled() {
+            return cluster_disabled();
+        }
+        RespFrame::Integer(key_slot(&self.0) as i64)
+    }
+}
+
+// key
+impl TryFrom<RespArray> for ClusterKeySlot {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["cluster", "keyslot"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        match <[RespFrame; 1]>::try_from(args.0) {
+            Ok([key]) => Ok(ClusterKeySlot(extract_bytes(key)?)),
+            Err(_) => Err(CommandError::InvalidCommandArguments(
+                "cluster keyslot must have exactly one key".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ClusterAddSlots(Vec<u16>);
+
+impl CommandExecutor for ClusterAddSlots {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if !backend.cluster_enabled() {
+            return cluster_disabled();
+        }
+        reply(backend.add_slots(&self.0))
+    }
+}
+
+// slot [slot ...]
+impl TryFrom<RespArray> for ClusterAddSlots {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["cluster", "addslots"];
+        validate_command(&value, &cmd_names)?;
+        Ok(ClusterAddSlots(parse_slots(value, cmd_names.len())?))
+    }
+}
+
+#[derive(Debug)]
+pub struct ClusterAddSlotsRange(Vec<u16>);
+
+impl CommandExecutor for ClusterAddSlotsRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        ClusterAddSlots(self.0).execute(backend)
+    }
+}
+
+// start end [start end ...]
+impl TryFrom<RespArray> for ClusterAddSlotsRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["cluster", "addslotsrange"];
+        validate_command(&value, &cmd_names)?;
+        let bounds = parse_slots(value, cmd_names.len())?;
+        if !bounds.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidCommandArguments(
+                "cluster addslotsrange must have start end pairs".to_string(),
+            ));
+        }
+        let mut slots = vec![];
+        for range in bounds.chunks(2) {
+            if range[0] > range[1] {
+                return Err(CommandError::InvalidArgument(format!(
+                    "start slot number {} is greater than end slot number {}",
+                    range[0], range[1]
+                )));
+            }
+            slots.extend(range[0]..=range[1]);
+        }
+        Ok(ClusterAddSlotsRange(slots))
+    }
+}
+
+#[derive(Debug)]
+pub struct ClusterDelSlots(Vec<u16>);
+
+impl CommandExecutor for ClusterDelSlots {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if !backend.cluster_enabled() {
+            return cluster_disabled();
+        }
+        reply(backend.del_slots(&self.0))
+    }
+}
+
+// slot [slot ...]
+impl TryFrom<RespArray> for ClusterDelSlots {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["cluster", "delslots"];
+        validate_command(&value, &cmd_names)?;
+        Ok(ClusterDelSlots(parse_slots(value, cmd_names.len())?))
+    }
+}
+
+#[derive(Debug)]
+pub struct ClusterMyId;
+
+impl CommandExecutor for ClusterMyId {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if !backend.cluster_enabled() {
+            return cluster_disabled();
+        }
+        BulkString::from(backend.myid()).into()
+    }
+}
+
+impl TryFrom<RespArray> for ClusterMyId {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_arguments(&value, &["cluster", "myid"])?;
+        Ok(ClusterMyId)
+    }
+}
+
+#[derive(Debug)]
+pub struct ClusterInfo;
+
+impl CommandExecutor for ClusterInfo {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if !backend.cluster_enabled() {
+            return cluster_disabled();
+        }
+        let nodes = backend.cluster_nodes();
+        let assigned: usize = nodes
+            .iter()
+            .flat_map(|(_, slots)| slots)
+            .map(|(start, end)| (end - start) as usize + 1)
+            .sum();
+        let serving = nodes.iter().filter(|(_, slots)| !slots.is_empty()).count();
+        let state = if assigned == CLUSTER_SLOTS as usize {
+            "ok"
+        } else {
+            "fail"
+        };
+        let fields = [
+            ("cluster_state", state.to_string()),
+            ("cluster_slots_assigned", assigned.to_string()),
+            ("cluster_slots_ok", assigned.to_string()),
+            ("cluster_slots_pfail", "0".to_string()),
+            ("cluster_slots_fail", "0".to_string()),
+            ("cluster_known_nodes", nodes.len().to_string()),
+            ("cluster_size", serving.to_string()),
+            ("cluster_current_epoch", "0".to_string()),
+            ("cluster_my_epoch", "0".to_string()),
+        ];
+        let info: String = fields
+            .iter()
+            .map(|(name, value)| format!("{}:{}\r\n", name, value))
+            .collect();
+        BulkString::from(info).into()
+    }
+}
+
+impl TryFrom<RespArray> for ClusterInfo {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_arguments(&value, &["cluster", "info"])?;
+        Ok(ClusterInfo)
+    }
+}
+
+// Ranges of slots with the node serving them, ordered by slot.
+#[derive(Debug)]
+pub struct ClusterSlots;
+
+impl CommandExecutor for ClusterSlots {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if !backend.cluster_enabled() {
+            return cluster_disabled();
+        }
+        let mut ranges: Vec<_> = backend
+            .cluster_nodes()
+            .into_iter()
+            .flat_map(|(node, slots)| slots.into_iter().map(move |range| (range, node.clone())))
+            .collect();
+        ranges.sort_by_key(|((start, _), _)| *start);
+        let ranges: Vec<RespFrame> = ranges
+            .into_iter()
+            .map(|((start, end), node)| {
+                let node = RespArray::new(vec![
+                    BulkString::from(node.host).into(),
+                    RespFrame::Integer(node.port as i64),
+                    BulkString::from(node.id).into(),
+                ]);
+                RespArray::new(vec![
+                    RespFrame::Integer(start as i64),
+                    RespFrame::Integer(end as i64),
+                    node.into(),
+                ])
+                .into()
+            })
+            .collect();
+        RespArray::new(ranges).into()
+    }
+}
+
+impl TryFrom<RespArray> for ClusterSlots {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_arguments(&value, &["cluster", "slots"])?;
+        Ok(ClusterSlots)
+    }
+}
+
+// A shard per node, as nodes have no replicas in the cluster.
+#[derive(Debug)]
+pub struct ClusterShards;
+
+impl CommandExecutor for ClusterShards {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if !backend.cluster_enabled() {
+            return cluster_disabled();
+        }
+        let offset = backend.repl_offset();
+        let shards: Vec<RespFrame> = backend
+            .cluster_nodes()
+            .into_iter()
+            .map(|(node, slots)| {
+                let slots: Vec<RespFrame> = slots
+                    .into_iter()
+                    .flat_map(|(start, end)| [start, end])
+                    .map(|slot| RespFrame::Integer(slot as i64))
+                    .collect();
+                let fields = [
+                    ("id", BulkString::from(node.id).into()),
+                    ("port", RespFrame::Integer(node.port as i64)),
+                    ("ip", BulkString::from(node.host.clone()).into()),
+                    ("endpoint", BulkString::from(node.host).into()),
+                    ("role", BulkString::from("master").into()),
+                    ("replication-offset", RespFrame::integer(offset)),
+                    ("health", BulkString::from("online").into()),
+                ];
+                let shard = [
+                    ("slots", RespArray::new(slots).into()),
+                    ("nodes", RespArray::new(vec![map(fields)]).into()),
+                ];
+                map(shard)
+            })
+            .collect();
+        RespArray::new(shards).into()
+    }
+}
+
+impl TryFrom<RespArray> for ClusterShards {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_arguments(&value, &["cluster", "shards"])?;
+        Ok(ClusterShards)
+    }
+}
+
+#[derive(Debug)]
+pub struct ClusterNodes;
+
+impl CommandExecutor for ClusterNodes {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if !backend.cluster_enabled() {
+            return cluster_disabled();
+        }
+        BulkString::from(backend.cluster_nodes_config()).into()
+    }
+}
+
+impl TryFrom<RespArray> for ClusterNodes {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_arguments(&value, &["cluster", "nodes"])?;
+        Ok(ClusterNodes)
+    }
+}
+
+fn no_arguments(value: &RespArray, cmd_names: &[&'static str]) -> Result<(), CommandError> {
+    validate_command(value, cmd_names)?;
+    if value.len() != cmd_names.len() {
+        return Err(CommandError::InvalidCommandArguments(format!(
+            "{} takes no arguments",
+            cmd_names.join(" ")
+        )));
+    }
+    Ok(())
+}
+
+fn map<const N: usize>(fields: [(&'static str, RespFrame); N]) -> RespFrame {
+    let map: HashMap<_, _> = fields
+        .into_iter()
+        .map(|(name, value)| (BulkString::from(name).into(), value))
+        .collect();
+    RespMap::new(map).into()
+}
+
+fn parse_slots(value: RespArray, start: usize) -> Result<Vec<u16>, CommandError> {
+    let args = Vec::<String>::try_from(extract_args(value, start)?)?;
+    if args.is_empty() {
+        return Err(CommandError::InvalidCommandArguments(
+            "cluster command must have at least one slot".to_string(),
+        ));
+    }
+    args.iter()
+        .map(|slot| {
+            slot.parse::<u16>()
+                .ok()
+                .filter(|&slot| slot < CLUSTER_SLOTS)
+                .ok_or_else(|| CommandError::InvalidArgument("Invalid or out of range slot".into()))
+        })
+        .collect()
+}
+
+fn reply(result: Result<(), String>) -> RespFrame {
+    match result {
+        Ok(()) => RESP_OK.clone(),
+        Err(e) => SimpleError::new(format!("ERR {}", e)).into(),
+    }
+}
+
+fn cluster_disabled() -> RespFrame {
+    SimpleError::new("ERR This instance has cluster support disabled").into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::RespDecoder;
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::{fs, process};
+
+    #[test]
+    fn test_cluster_cmds_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$7\r\ncluster\r\n$7\r\nkeyslot\r\n$3\r\nfoo\r\n");
+        let cmd = ClusterKeySlot::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0, "foo");
+
+        buf.extend_from_slice(
+            b"*6\r\n$7\r\ncluster\r\n$13\r\naddslotsrange\r\n$1\r\n0\r\n$1\r\n2\r\n$1\r\n7\r\n$1\r\n7\r\n",
+        );
+        let cmd = ClusterAddSlotsRange::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0, vec![0, 1, 2, 7]);
+
+        buf.extend_from_slice(b"*3\r\n$7\r\ncluster\r\n$8\r\naddslots\r\n$5\r\n16384\r\n");
+        assert!(ClusterAddSlots::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*2\r\n$7\r\ncluster\r\n$8\r\ndelslots\r\n");
+        assert!(ClusterDelSlots::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cluster_cmds_execute() -> Result<()> {
+        let backend = Backend::new();
+        assert_eq!(
+            ClusterKeySlot("foo".into()).execute(&backend),
+            cluster_disabled()
+        );
+
+        let dir = std::env::temp_dir().join(format!("simple-redis-cluster-cmd-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        backend.set_dir(dir.clone());
+        backend.enable_cluster()?;
+        assert_eq!(
+            ClusterKeySlot("foo".into()).execute(&backend),
+            RespFrame::Integer(12182)
+        );
+        assert_eq!(
+            ClusterAddSlotsRange(vec![12182, 12183]).execute(&backend),
+            RESP_OK.clone()
+        );
+        assert_eq!(backend.check_keys_slot(&[b"foo".as_slice()]), Ok(()));
+        assert_eq!(
+            ClusterAddSlots(vec![12182]).execute(&backend),
+            SimpleError::new("ERR Slot 12182 is already busy").into()
+        );
+        assert_eq!(
+            ClusterDelSlots(vec![12182]).execute(&backend),
+            RESP_OK.clone()
+        );
+        assert!(backend.check_keys_slot(&[b"foo".as_slice()]).is_err());
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_cluster_introspection() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("simple-redis-cluster-info-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        let config = "\
+            b0f0 127.0.0.1:7001@17001 master - 0 0 1 connected 0-8000\n\
+            a1e1 127.0.0.1:7000@17000 myself,master - 0 0 0 connected 8001-16383\n";
+        fs::write(dir.join("nodes.conf"), config)?;
+        let backend = Backend::new();
+        backend.set_dir(dir.clone());
+        backend.set_listening_port(7000);
+        backend.enable_cluster()?;
+
+        assert_eq!(
+            ClusterMyId.execute(&backend),
+            BulkString::from("a1e1").into()
+        );
+        let RespFrame::BulkString(info) = ClusterInfo.execute(&backend) else {
+            panic!("expected a bulk string");
+        };
+        let info = String::from_utf8(info.0.into())?;
+        assert!(info.starts_with("cluster_state:ok\r\ncluster_slots_assigned:16384\r\n"));
+        assert!(info.contains("cluster_known_nodes:2\r\ncluster_size:2\r\n"));
+
+        let node = |host: &'static str, port, id: &'static str| -> RespFrame {
+            RespArray::new(vec![
+                BulkString::from(host).into(),
+                RespFrame::Integer(port),
+                BulkString::from(id).into(),
+            ])
+            .into()
+        };
+        assert_eq!(
+            ClusterSlots.execute(&backend),
+            RespArray::new(vec![
+                RespArray::new(vec![
+                    RespFrame::Integer(0),
+                    RespFrame::Integer(8000),
+                    node("127.0.0.1", 7001, "b0f0"),
+                ])
+                .into(),
+                RespArray::new(vec![
+                    RespFrame::Integer(8001),
+                    RespFrame::Integer(16383),
+                    node("127.0.0.1", 7000, "a1e1"),
+                ])
+                .into(),
+            ])
+            .into()
+        );
+
+        let RespFrame::Array(shards) = ClusterShards.execute(&backend) else {
+            panic!("expected an array");
+        };
+        let RespFrame::Map(shard) = &shards[0] else {
+            panic!("expected a map");
+        };
+        assert_eq!(
+            shard.get(&BulkString::from("slots").into()),
+            Some(&RespArray::new(vec![RespFrame::Integer(8001), RespFrame::Integer(16383)]).into())
+        );
+
+        backend.del_slots(&[0]).map_err(anyhow::Error::msg)?;
+        assert_eq!(
+            ClusterNodes.execute(&backend),
+            BulkString::from(
+                "a1e1 127.0.0.1:7000@17000 myself,master - 0 0 0 connected 8001-16383\n\
+                 b0f0 127.0.0.1:7001@17001 master - 0 0 0 connected 1-8000\n"
+            )
+            .into()
+        );
+        let RespFrame::BulkString(info) = ClusterInfo.execute(&backend) else {
+            panic!("expected a bulk string");
+        };
+        assert!(info.starts_with(b"cluster_state:fail\r\ncluster_slots_assigned:16383\r\n"));
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
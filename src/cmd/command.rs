@@ -0,0 +1,569 @@
+use super::{
+    extract_args, extract_string,
+    plugin::{self, CommandPlugin},
+    validate_command, CommandError, CommandExecutor, KeySpec,
+};
+use crate::{Backend, BulkString, RespArray, RespFrame, RespMap, RespNull, SimpleString};
+use std::{collections::HashMap, sync::Arc};
+
+// What COMMAND reports of a command, as in the Redis command table.
+#[derive(Clone, Copy)]
+struct CommandSpec<'a> {
+    name: &'a str,
+    // the number of arguments including the command name, or minus the least number of them
+    arity: i64,
+    flags: &'static [&'static str],
+    // the first and last key positions and the step between them, zeros when the command takes
+    // no key or finds them from its arguments
+    keys: (i64, i64, i64),
+    group: &'static str,
+    summary: &'static str,
+    subcommands: &'static [CommandSpec<'static>],
+}
+
+const NO_KEYS: (i64, i64, i64) = (0, 0, 0);
+const FIRST_KEY: (i64, i64, i64) = (1, 1, 1);
+const ALL_KEYS: (i64, i64, i64) = (1, -1, 1);
+
+const fn spec(
+    name: &'static str,
+    arity: i64,
+    flags: &'static [&'static str],
+    keys: (i64, i64, i64),
+    group: &'static str,
+    summary: &'static str,
+) -> CommandSpec<'static> {
+    CommandSpec {
+        name,
+        arity,
+        flags,
+        keys,
+        group,
+        summary,
+        subcommands: &[],
+    }
+}
+
+// A command such as CONFIG, which only groups its subcommands.
+const fn container(
+    name: &'static str,
+    group: &'static str,
+    summary: &'static str,
+    subcommands: &'static [CommandSpec<'static>],
+) -> CommandSpec<'static> {
+    CommandSpec {
+        name,
+        arity: -2,
+        flags: &[],
+        keys: NO_KEYS,
+        group,
+        summary,
+        subcommands,
+    }
+}
+
+#[rustfmt::skip]
+const COMMAND_TABLE: &[CommandSpec<'static>] = &[
+    spec("get", 2, &["readonly", "fast"], FIRST_KEY, "string", "Returns the string value of a key."),
+    spec("set", -3, &["write", "denyoom"], FIRST_KEY, "string", "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist."),
+    spec("del", -2, &["write"], ALL_KEYS, "generic", "Deletes one or more keys."),
+    spec("type", 2, &["readonly", "fast"], FIRST_KEY, "generic", "Determines the type of value stored at a key."),
+    spec("incr", 2, &["write", "denyoom", "fast"], FIRST_KEY, "string", "Increments the integer value of a key by one. Uses 0 as initial value if the key doesn't exist."),
+    spec("decr", 2, &["write", "denyoom", "fast"], FIRST_KEY, "string", "Decrements the integer value of a key by one. Uses 0 as initial value if the key doesn't exist."),
+    spec("incrby", 3, &["write", "denyoom", "fast"], FIRST_KEY, "string", "Increments the integer value of a key by a number. Uses 0 as initial value if the key doesn't exist."),
+    spec("decrby", 3, &["write", "denyoom", "fast"], FIRST_KEY, "string", "Decrements a number from the integer value of a key. Uses 0 as initial value if the key doesn't exist."),
+    spec("hset", -4, &["write", "denyoom", "fast"], FIRST_KEY, "hash", "Creates or modifies the value of a field in a hash."),
+    spec("hmset", -4, &["write", "denyoom", "fast"], FIRST_KEY, "hash", "Sets the values of multiple fields."),
+    spec("hget", 3, &["readonly", "fast"], FIRST_KEY, "hash", "Returns the value of a field in a hash."),
+    spec("hmget", -3, &["readonly", "fast"], FIRST_KEY, "hash", "Returns the values of all fields in a hash."),
+    spec("hdel", -3, &["write", "fast"], FIRST_KEY, "hash", "Deletes one or more fields and their values from a hash. Deletes the hash if no fields remain."),
+    spec("hgetall", 2, &["readonly"], FIRST_KEY, "hash", "Returns all fields and values in a hash."),
+    spec("hkeys", 2, &["readonly"], FIRST_KEY, "hash", "Returns all fields in a hash."),
+    spec("echo", 2, &["fast"], NO_KEYS, "connection", "Returns the given string."),
+    spec("ping", -1, &["fast"], NO_KEYS, "connection", "Returns the server's liveliness response."),
+    spec("sadd", -3, &["write", "denyoom", "fast"], FIRST_KEY, "set", "Adds one or more members to a set. Creates the key if it doesn't exist."),
+    spec("sismember", 3, &["readonly", "fast"], FIRST_KEY, "set", "Determines whether a member belongs to a set."),
+    spec("smembers", 2, &["readonly"], FIRST_KEY, "set", "Returns all members of a set."),
+    spec("srem", -3, &["write", "fast"], FIRST_KEY, "set", "Removes one or more members from a set. Deletes the set if the last member was removed."),
+    spec("lpush", -3, &["write", "denyoom", "fast"], FIRST_KEY, "list", "Prepends one or more elements to a list. Creates the key if it doesn't exist."),
+    spec("rpush", -3, &["write", "denyoom", "fast"], FIRST_KEY, "list", "Appends one or more elements to a list. Creates the key if it doesn't exist."),
+    spec("llen", 2, &["readonly", "fast"], FIRST_KEY, "list", "Returns the length of a list."),
+    spec("lrange", 4, &["readonly"], FIRST_KEY, "list", "Returns a range of elements from a list."),
+    spec("lindex", 3, &["readonly"], FIRST_KEY, "list", "Returns an element from a list by its index."),
+    spec("linsert", 5, &["write", "denyoom"], FIRST_KEY, "list", "Inserts an element before or after another element in a list."),
+    spec("lmove", 5, &["write", "denyoom"], (1, 2, 1), "list", "Returns an element after popping it from one list and pushing it to another. Deletes the list if the last element was moved."),
+    spec("blmove", 6, &["write", "denyoom", "blocking"], (1, 2, 1), "list", "Pops an element from a list, pushes it to another list and returns it. Blocks until an element is available otherwise. Deletes the list if the last element was moved."),
+    spec("lmpop", -4, &["write", "movablekeys"], NO_KEYS, "list", "Returns multiple elements from a list after removing them. Deletes the list if the last element was popped."),
+    spec("blmpop", -5, &["write", "blocking", "movablekeys"], NO_KEYS, "list", "Pops the first element from one of multiple lists. Blocks until an element is available otherwise. Deletes the list if the last element was popped."),
+    spec("zadd", -4, &["write", "denyoom", "fast"], FIRST_KEY, "sorted-set", "Adds one or more members to a sorted set, or updates their scores. Creates the key if it doesn't exist."),
+    spec("zscore", 3, &["readonly", "fast"], FIRST_KEY, "sorted-set", "Returns the score of a member in a sorted set."),
+    spec("zcard", 2, &["readonly", "fast"], FIRST_KEY, "sorted-set", "Returns the number of members in a sorted set."),
+    spec("zrange", -4, &["readonly"], FIRST_KEY, "sorted-set", "Returns members in a sorted set within a range of indexes."),
+    spec("zrevrange", -4, &["readonly"], FIRST_KEY, "sorted-set", "Returns members in a sorted set within a range of indexes in reverse order."),
+    spec("zrangebyscore", -4, &["readonly"], FIRST_KEY, "sorted-set", "Returns members in a sorted set within a range of scores."),
+    spec("zrangebylex", -4, &["readonly"], FIRST_KEY, "sorted-set", "Returns members in a sorted set within a lexicographical range."),
+    spec("zincrby", 4, &["write", "denyoom", "fast"], FIRST_KEY, "sorted-set", "Increments the score of a member in a sorted set."),
+    spec("zcount", 4, &["readonly", "fast"], FIRST_KEY, "sorted-set", "Returns the count of members in a sorted set that have scores within a range."),
+    spec("zlexcount", 4, &["readonly", "fast"], FIRST_KEY, "sorted-set", "Returns the number of members in a sorted set within a lexicographical range."),
+    spec("zunionstore", -4, &["write", "denyoom", "movablekeys"], FIRST_KEY, "sorted-set", "Stores the union of multiple sorted sets in a key."),
+    spec("zinterstore", -4, &["write", "denyoom", "movablekeys"], FIRST_KEY, "sorted-set", "Stores the intersect of multiple sorted sets in a key."),
+    spec("zdiffstore", -4, &["write", "denyoom", "movablekeys"], FIRST_KEY, "sorted-set", "Stores the difference of multiple sorted sets in a key."),
+    spec("zunion", -3, &["readonly", "movablekeys"], NO_KEYS, "sorted-set", "Returns the union of multiple sorted sets."),
+    spec("zinter", -3, &["readonly", "movablekeys"], NO_KEYS, "sorted-set", "Returns the intersect of multiple sorted sets."),
+    spec("zdiff", -3, &["readonly", "movablekeys"], NO_KEYS, "sorted-set", "Returns the difference between multiple sorted sets."),
+    spec("zremrangebyrank", 4, &["write"], FIRST_KEY, "sorted-set", "Removes members in a sorted set within a range of indexes. Deletes the sorted set if all members were removed."),
+    spec("zremrangebyscore", 4, &["write"], FIRST_KEY, "sorted-set", "Removes members in a sorted set within a range of scores. Deletes the sorted set if all members were removed."),
+    spec("zremrangebylex", 4, &["write"], FIRST_KEY, "sorted-set", "Removes members in a sorted set within a lexicographical range. Deletes the sorted set if all members were removed."),
+    spec("zrandmember", -2, &["readonly"], FIRST_KEY, "sorted-set", "Returns one or more random members from a sorted set."),
+    spec("zscan", -3, &["readonly"], FIRST_KEY, "sorted-set", "Iterates over members and scores of a sorted set."),
+    spec("zmpop", -4, &["write", "movablekeys"], NO_KEYS, "sorted-set", "Returns the highest- or lowest-scoring members from one or more sorted sets after removing them. Deletes the sorted set if the last member was popped."),
+    spec("xadd", -5, &["write", "denyoom", "fast"], FIRST_KEY, "stream", "Appends a new message to a stream. Creates the key if it doesn't exist."),
+    spec("xlen", 2, &["readonly", "fast"], FIRST_KEY, "stream", "Return the number of messages in a stream."),
+    spec("xrange", -4, &["readonly"], FIRST_KEY, "stream", "Returns the messages from a stream within a range of IDs."),
+    spec("xrevrange", -4, &["readonly"], FIRST_KEY, "stream", "Returns the messages from a stream within a range of IDs in reverse order."),
+    spec("xtrim", -4, &["write"], FIRST_KEY, "stream", "Deletes messages from the beginning of a stream."),
+    spec("xdel", -3, &["write", "fast"], FIRST_KEY, "stream", "Returns the number of messages after removing them from a stream."),
+    spec("setbit", 4, &["write", "denyoom"], FIRST_KEY, "bitmap", "Sets or clears the bit at offset of the string value. Creates the key if it doesn't exist."),
+    spec("getbit", 3, &["readonly", "fast"], FIRST_KEY, "bitmap", "Returns a bit value by offset."),
+    spec("bitcount", -2, &["readonly"], FIRST_KEY, "bitmap", "Counts the number of set bits (population counting) in a string."),
+    spec("bitfield", -2, &["write", "denyoom"], FIRST_KEY, "bitmap", "Performs arbitrary bitfield integer operations on strings."),
+    spec("pfadd", -2, &["write", "denyoom", "fast"], FIRST_KEY, "hyperloglog", "Adds elements to a HyperLogLog key. Creates the key if it doesn't exist."),
+    spec("pfcount", -2, &["readonly"], ALL_KEYS, "hyperloglog", "Returns the approximated cardinality of the set(s) observed by the HyperLogLog key(s)."),
+    spec("pfmerge", -2, &["write", "denyoom"], ALL_KEYS, "hyperloglog", "Merges one or more HyperLogLog values into a single key."),
+    spec("geoadd", -5, &["write", "denyoom"], FIRST_KEY, "geo", "Adds one or more members to a geospatial index. The key is created if it doesn't exist."),
+    spec("geopos", -2, &["readonly"], FIRST_KEY, "geo", "Returns the longitude and latitude of members from a geospatial index."),
+    spec("geodist", -4, &["readonly"], FIRST_KEY, "geo", "Returns the distance between two members of a geospatial index."),
+    spec("geosearch", -7, &["readonly"], FIRST_KEY, "geo", "Queries a geospatial index for members inside an area of a box or a circle."),
+    spec("geosearchstore", -8, &["write", "denyoom"], (1, 2, 1), "geo", "Queries a geospatial index for members inside an area of a box or a circle, optionally stores the result."),
+    container("config", "server", "A container for server configuration commands.", &[
+        spec("config|get", -3, &["admin", "noscript", "loading", "stale"], NO_KEYS, "server", "Returns the effective values of configuration parameters."),
+        spec("config|set", -4, &["admin", "noscript", "loading", "stale"], NO_KEYS, "server", "Sets configuration parameters in-flight."),
+        spec("config|rewrite", 2, &["admin", "noscript", "loading", "stale"], NO_KEYS, "server", "Persists the effective configuration to file."),
+    ]),
+    spec("expire", -3, &["write", "fast"], FIRST_KEY, "generic", "Sets the expiration time of a key in seconds."),
+    spec("pexpire", -3, &["write", "fast"], FIRST_KEY, "generic", "Sets the expiration time of a key in milliseconds."),
+    spec("pexpireat", -3, &["write", "fast"], FIRST_KEY, "generic", "Sets the expiration time of a key to a Unix milliseconds timestamp."),
+    spec("ttl", 2, &["readonly", "fast"], FIRST_KEY, "generic", "Returns the expiration time in seconds of a key."),
+    spec("pttl", 2, &["readonly", "fast"], FIRST_KEY, "generic", "Returns the expiration time in milliseconds of a key."),
+    spec("persist", 2, &["write", "fast"], FIRST_KEY, "generic", "Removes the expiration time of a key."),
+    container("object", "generic", "A container for object introspection commands.", &[
+        spec("object|freq", 3, &["readonly"], (2, 2, 1), "generic", "Returns the logarithmic access frequency counter of a Redis object."),
+        spec("object|encoding", 3, &["readonly"], (2, 2, 1), "generic", "Returns the internal encoding of a Redis object."),
+    ]),
+    container("memory", "server", "A container for memory diagnostics commands.", &[
+        spec("memory|usage", -3, &["readonly"], (2, 2, 1), "server", "Estimates the memory usage of a key."),
+        spec("memory|stats", 2, &[], NO_KEYS, "server", "Returns details about memory usage."),
+        spec("memory|doctor", 2, &[], NO_KEYS, "server", "Outputs a memory problems report."),
+    ]),
+    container("latency", "server", "A container for latency diagnostics commands.", &[
+        spec("latency|latest", 2, &["admin", "noscript", "loading", "stale"], NO_KEYS, "server", "Returns the latest latency samples for all events."),
+        spec("latency|history", 3, &["admin", "noscript", "loading", "stale"], NO_KEYS, "server", "Returns timestamp-latency samples for an event."),
+        spec("latency|reset", -2, &["admin", "noscript", "loading", "stale"], NO_KEYS, "server", "Resets the latency data for one or more events."),
+    ]),
+    spec("debug", -2, &["admin", "noscript", "loading", "stale"], NO_KEYS, "server", "A container for debugging commands."),
+    spec("save", 1, &["admin", "noscript", "no_multi"], NO_KEYS, "server", "Synchronously saves the database(s) to disk."),
+    spec("bgsave", -1, &["admin", "noscript"], NO_KEYS, "server", "Asynchronously saves the database(s) to disk."),
+    spec("shutdown", -1, &["admin", "noscript", "loading", "stale", "no_multi"], NO_KEYS, "server", "Synchronously saves the database(s) to disk and shuts down the Redis server."),
+    spec("lastsave", 1, &["loading", "stale", "fast"], NO_KEYS, "server", "Returns the Unix timestamp of the last successful save to disk."),
+    spec("time", 1, &["loading", "stale", "fast"], NO_KEYS, "server", "Returns the server time."),
+    spec("bgrewriteaof", 1, &["admin", "noscript"], NO_KEYS, "server", "Asynchronously rewrites the append-only file to disk."),
+    spec("info", -1, &["loading", "stale"], NO_KEYS, "server", "Returns information and statistics about the server."),
+    spec("replicaof", 3, &["admin", "noscript", "stale"], NO_KEYS, "server", "Configures a server as replica of another, or promotes it to a master."),
+    spec("replconf", -1, &["admin", "noscript", "loading", "stale"], NO_KEYS, "server", "An internal command for configuring the replication stream."),
+    spec("wait", 3, &["blocking"], NO_KEYS, "generic", "Blocks until the asynchronous replication of all preceding write commands sent by the connection is completed."),
+    spec("failover", -1, &["admin", "noscript", "stale"], NO_KEYS, "server", "Starts a coordinated failover from a server to one of its replicas."),
+    container("cluster", "cluster", "A container for Redis Cluster commands.", &[
+        spec("cluster|keyslot", 3, &["stale"], NO_KEYS, "cluster", "Returns the hash slot for a key."),
+        spec("cluster|addslots", -3, &["admin", "stale"], NO_KEYS, "cluster", "Assigns new hash slots to a node."),
+        spec("cluster|addslotsrange", -4, &["admin", "stale"], NO_KEYS, "cluster", "Assigns new hash slot ranges to a node."),
+        spec("cluster|delslots", -3, &["admin", "stale"], NO_KEYS, "cluster", "Sets hash slots as unbound for a node."),
+        spec("cluster|myid", 2, &["stale"], NO_KEYS, "cluster", "Returns the ID of a node."),
+        spec("cluster|info", 2, &["stale"], NO_KEYS, "cluster", "Returns information about the state of a node."),
+        spec("cluster|slots", 2, &["loading", "stale"], NO_KEYS, "cluster", "Returns the mapping of cluster slots to nodes."),
+        spec("cluster|shards", 2, &["loading", "stale"], NO_KEYS, "cluster", "Returns the mapping of cluster slots to shards."),
+        spec("cluster|nodes", 2, &["loading", "stale"], NO_KEYS, "cluster", "Returns the cluster configuration for a node."),
+    ]),
+    spec("subscribe", -2, &["pubsub", "noscript", "loading", "stale"], NO_KEYS, "pubsub", "Listens for messages published to channels."),
+    spec("unsubscribe", -1, &["pubsub", "noscript", "loading", "stale"], NO_KEYS, "pubsub", "Stops listening to messages posted to channels."),
+    spec("psubscribe", -2, &["pubsub", "noscript", "loading", "stale"], NO_KEYS, "pubsub", "Listens for messages published to channels that match one or more patterns."),
+    spec("punsubscribe", -1, &["pubsub", "noscript", "loading", "stale"], NO_KEYS, "pubsub", "Stops listening to messages published to channels that match one or more patterns."),
+    spec("publish", 3, &["pubsub", "loading", "stale", "fast"], NO_KEYS, "pubsub", "Posts a message to a channel."),
+    spec("ssubscribe", -2, &["pubsub", "noscript", "loading", "stale"], ALL_KEYS, "pubsub", "Listens for messages published to shard channels."),
+    spec("sunsubscribe", -1, &["pubsub", "noscript", "loading", "stale"], ALL_KEYS, "pubsub", "Stops listening to messages posted to shard channels."),
+    spec("spublish", 3, &["pubsub", "loading", "stale", "fast"], FIRST_KEY, "pubsub", "Post a message to a shard channel."),
+    container("pubsub", "pubsub", "A container for Pub/Sub commands.", &[
+        spec("pubsub|channels", -2, &["pubsub", "loading", "stale"], NO_KEYS, "pubsub", "Returns the active channels."),
+        spec("pubsub|numsub", -2, &["pubsub", "loading", "stale"], NO_KEYS, "pubsub", "Returns a count of subscribers to channels."),
+        spec("pubsub|numpat", 2, &["pubsub", "loading", "stale"], NO_KEYS, "pubsub", "Returns a count of unique pattern subscriptions."),
+        spec("pubsub|shardchannels", -2, &["pubsub", "loading", "stale"], NO_KEYS, "pubsub", "Returns the active shard channels."),
+        spec("pubsub|shardnumsub", -2, &["pubsub", "loading", "stale"], NO_KEYS, "pubsub", "Returns the count of subscribers of shard channels."),
+    ]),
+    container("client", "connection", "A container for client connection commands.", &[
+        spec("client|tracking", -3, &["noscript", "loading", "stale"], NO_KEYS, "connection", "Controls server-assisted client-side caching for the connection."),
+        spec("client|setname", 3, &["noscript", "loading", "stale"], NO_KEYS, "connection", "Sets the connection name."),
+        spec("client|getname", 2, &["noscript", "loading", "stale"], NO_KEYS, "connection", "Returns the name of the connection."),
+        spec("client|id", 2, &["noscript", "loading", "stale"], NO_KEYS, "connection", "Returns the unique client ID of the connection."),
+        spec("client|info", 2, &["noscript", "loading", "stale"], NO_KEYS, "connection", "Returns information about the connection."),
+        spec("client|list", -2, &["admin", "noscript", "loading", "stale"], NO_KEYS, "connection", "Lists open connections."),
+        spec("client|pause", -3, &["admin", "noscript", "loading", "stale"], NO_KEYS, "connection", "Suspends commands processing."),
+        spec("client|reply", 3, &["noscript", "loading", "stale"], NO_KEYS, "connection", "Instructs the server whether to reply to commands."),
+        spec("client|unpause", 2, &["admin", "noscript", "loading", "stale"], NO_KEYS, "connection", "Resumes processing commands from paused clients."),
+    ]),
+    spec("hello", -1, &["noscript", "loading", "stale", "fast", "no_auth"], NO_KEYS, "connection", "Handshakes with the Redis server."),
+    spec("auth", -2, &["noscript", "loading", "stale", "fast", "no_auth"], NO_KEYS, "connection", "Authenticates the connection."),
+    spec("reset", 1, &["noscript", "loading", "stale", "fast", "no_auth", "allow_busy"], NO_KEYS, "connection", "Resets the connection."),
+    spec("multi", 1, &["noscript", "loading", "stale", "fast"], NO_KEYS, "transactions", "Starts a transaction."),
+    spec("exec", 1, &["noscript", "loading", "stale"], NO_KEYS, "transactions", "Executes all commands in a transaction."),
+    spec("discard", 1, &["noscript", "loading", "stale", "fast"], NO_KEYS, "transactions", "Discards a transaction."),
+    spec("watch", -2, &["noscript", "loading", "stale", "fast"], ALL_KEYS, "transactions", "Monitors changes to keys to determine the execution of a transaction."),
+    spec("unwatch", 1, &["noscript", "loading", "stale", "fast"], NO_KEYS, "transactions", "Forgets about watched keys of a transaction."),
+    spec("eval", -3, &["noscript", "stale", "movablekeys"], NO_KEYS, "scripting", "Executes a server-side Lua script."),
+    spec("evalsha", -3, &["noscript", "stale", "movablekeys"], NO_KEYS, "scripting", "Executes a server-side Lua script by SHA1 digest."),
+    container("script", "scripting", "A container for Lua scripts management commands.", &[
+        spec("script|load", 3, &["noscript", "stale"], NO_KEYS, "scripting", "Loads a server-side Lua script to the script cache."),
+        spec("script|kill", 2, &["noscript", "allow_busy"], NO_KEYS, "scripting", "Terminates a server-side Lua script during execution."),
+    ]),
+    container("function", "scripting", "A container for function commands.", &[
+        spec("function|load", -3, &["write", "denyoom", "noscript"], NO_KEYS, "scripting", "Creates a library."),
+        spec("function|delete", 3, &["write", "noscript"], NO_KEYS, "scripting", "Deletes a library and its functions."),
+        spec("function|list", -2, &["noscript"], NO_KEYS, "scripting", "Returns information about all libraries."),
+    ]),
+    spec("fcall", -3, &["noscript", "stale", "movablekeys"], NO_KEYS, "scripting", "Invokes a function."),
+    spec("flushdb", -1, &["write"], NO_KEYS, "server", "Removes all keys from the current database."),
+    spec("select", 2, &["loading", "stale", "fast"], NO_KEYS, "connection", "Changes the selected database."),
+    spec("swapdb", 3, &["write", "fast"], NO_KEYS, "server", "Swaps two Redis databases."),
+    container("command", "server", "A container for command introspection commands.", &[
+        spec("command|count", 2, &["loading", "stale"], NO_KEYS, "server", "Returns a count of commands."),
+        spec("command|info", -2, &["loading", "stale"], NO_KEYS, "server", "Returns information about one, multiple or all commands."),
+        spec("command|docs", -2, &["loading", "stale"], NO_KEYS, "server", "Returns documentary information about one, multiple or all commands."),
+    ]),
+];
+
+#[derive(Debug)]
+pub struct Commands;
+
+#[derive(Debug)]
+pub struct CommandCount;
+
+#[derive(Debug)]
+pub struct CommandInfo(Vec<String>);
+
+#[derive(Debug)]
+pub struct CommandDocs(Vec<String>);
+
+impl CommandExecutor for Commands {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        let plugins = plugin::plugins();
+        RespArray::new(
+            all_specs(&plugins)
+                .iter()
+                .map(info_reply)
+                .collect::<Vec<_>>(),
+        )
+        .into()
+    }
+}
+
+impl CommandExecutor for CommandCount {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        RespFrame::Integer(all_specs(&plugin::plugins()).len() as i64)
+    }
+}
+
+impl CommandExecutor for CommandInfo {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if self.0.is_empty() {
+            return Commands.execute(backend);
+        }
+        let plugins = plugin::plugins();
+        let specs = all_specs(&plugins);
+        let infos = self.0.iter().map(|name| match find_spec(&specs, name) {
+            Some(spec) => info_reply(spec),
+            None => RespFrame::Null(RespNull),
+        });
+        RespArray::new(infos.collect::<Vec<_>>()).into()
+    }
+}
+
+impl CommandExecutor for CommandDocs {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        let plugins = plugin::plugins();
+        let specs = all_specs(&plugins);
+        let docs: Vec<&CommandSpec> = match self.0.is_empty() {
+            true => specs.iter().collect(),
+            // the unknown commands are left out
+            false => self
+                .0
+                .iter()
+                .filter_map(|name| find_spec(&specs, name))
+                .collect(),
+        };
+        docs_reply(docs)
+    }
+}
+
+// The built-in commands followed by the `plugins`.
+fn all_specs(plugins: &[Arc<dyn CommandPlugin>]) -> Vec<CommandSpec<'_>> {
+    let plugins = plugins.iter().map(|plugin| {
+        let write = plugin.is_write();
+        let (keys, movable) = match plugin.key_specs().first() {
+            Some(&KeySpec::Range { first, last, step }) => {
+                ((first as i64, last as i64, step as i64), false)
+            }
+            Some(KeySpec::NumKeys { .. }) => (NO_KEYS, true),
+            None => (NO_KEYS, false),
+        };
+        let flags: &'static [&'static str] = match (write, movable) {
+            (true, false) => &["write"],
+            (true, true) => &["write", "movablekeys"],
+            (false, false) => &["readonly"],
+            (false, true) => &["readonly", "movablekeys"],
+        };
+        CommandSpec {
+            name: plugin.name(),
+            arity: plugin.arity(),
+            flags,
+            keys,
+            group: "module",
+            summary: "",
+            subcommands: &[],
+        }
+    });
+    COMMAND_TABLE.iter().copied().chain(plugins).collect()
+}
+
+// The command named `name` regardless of case, a subcommand being named as in `config|get`.
+fn find_spec<'a, 'b>(specs: &'a [CommandSpec<'b>], name: &str) -> Option<&'a CommandSpec<'b>> {
+    let name = name.to_ascii_lowercase();
+    let container = name.split('|').next().unwrap_or_default();
+    let spec = specs
+        .iter()
+        .find(|spec| spec.name.eq_ignore_ascii_case(container))?;
+    match name.contains('|') {
+        true => spec.subcommands.iter().find(|sub| sub.name == name),
+        false => Some(spec),
+    }
+}
+
+// name, arity, flags, first key, last key, step, ACL categories, tips, key specs, subcommands
+fn info_reply(spec: &CommandSpec) -> RespFrame {
+    let flags = spec
+        .flags
+        .iter()
+        .map(|flag| SimpleString::new(*flag).into())
+        .collect::<Vec<RespFrame>>();
+    let (first, last, step) = spec.keys;
+    RespArray::new(vec![
+        BulkString::from(spec.name.to_ascii_lowercase()).into(),
+        RespFrame::Integer(spec.arity),
+        RespArray::new(flags).into(),
+        RespFrame::Integer(first),
+        RespFrame::Integer(last),
+        RespFrame::Integer(step),
+        RespArray::new(vec![]).into(),
+        RespArray::new(vec![]).into(),
+        RespArray::new(vec![]).into(),
+        RespArray::new(spec.subcommands.iter().map(info_reply).collect::<Vec<_>>()).into(),
+    ])
+    .into()
+}
+
+// The map of the command names to their summary, group and subcommands.
+fn docs_reply<'a: 'b, 'b>(specs: impl IntoIterator<Item = &'b CommandSpec<'a>>) -> RespFrame {
+    let docs = specs.into_iter().map(|spec| {
+        let mut doc: HashMap<RespFrame, RespFrame> = HashMap::from([
+            (
+                BulkString::from("summary").into(),
+                BulkString::from(spec.summary).into(),
+            ),
+            (
+                BulkString::from("group").into(),
+                BulkString::from(spec.group).into(),
+            ),
+        ]);
+        if !spec.subcommands.is_empty() {
+            doc.insert(
+                BulkString::from("subcommands").into(),
+                docs_reply(spec.subcommands),
+            );
+        }
+        (
+            BulkString::from(spec.name.to_ascii_lowercase()).into(),
+            RespMap::new(doc).into(),
+        )
+    });
+    RespMap::new(docs.collect::<HashMap<_, _>>()).into()
+}
+
+impl TryFrom<RespArray> for Commands {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["command"];
+        validate_command(&value, &cmd_names)?;
+        match value.len() {
+            1 => Ok(Commands),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "command takes a subcommand".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for CommandCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["command", "count"];
+        validate_command(&value, &cmd_names)?;
+        match value.len() {
+            2 => Ok(CommandCount),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "command count takes no arguments".to_string(),
+            )),
+        }
+    }
+}
+
+// [command-name [command-name ...]]
+impl TryFrom<RespArray> for CommandInfo {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["command", "info"];
+        validate_command(&value, &cmd_names)?;
+        let names = extract_args(value, cmd_names.len())?.0.into_iter();
+        Ok(CommandInfo(
+            names.map(extract_string).collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+// [command-name [command-name ...]]
+impl TryFrom<RespArray> for CommandDocs {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["command", "docs"];
+        validate_command(&value, &cmd_names)?;
+        let names = extract_args(value, cmd_names.len())?.0.into_iter();
+        Ok(CommandDocs(
+            names.map(extract_string).collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::Command;
+    use crate::resp::RespDecoder;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn request(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(arg.to_string()).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[test]
+    fn test_command_table_names_parse() {
+        // every command and subcommand in the table is one a request can name
+        for spec in COMMAND_TABLE {
+            if let Err(CommandError::InvalidCommand(e)) = Command::try_from(request(&[spec.name])) {
+                assert!(!e.starts_with("unknown command"), "{}", spec.name);
+            }
+            for sub in spec.subcommands {
+                let (container, name) = sub.name.split_once('|').unwrap();
+                assert_eq!(container, spec.name);
+                if let Err(CommandError::InvalidArgument(e)) =
+                    Command::try_from(request(&[container, name]))
+                {
+                    assert!(!e.starts_with("unknown subcommand"), "{}", sub.name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_command_info_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$7\r\ncommand\r\n$4\r\ninfo\r\n$3\r\nget\r\n$3\r\nSET\r\n");
+        let cmd = CommandInfo::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0, ["get", "SET"]);
+
+        buf.extend_from_slice(b"*3\r\n$7\r\ncommand\r\n$5\r\ncount\r\n$1\r\nx\r\n");
+        assert!(CommandCount::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_info_execute() {
+        let backend = Backend::new();
+        let reply =
+            CommandInfo(vec!["GET".into(), "nosuch".into(), "config|get".into()]).execute(&backend);
+        let RespFrame::Array(infos) = reply else {
+            panic!("expected an array, got {:?}", reply);
+        };
+        assert_eq!(
+            infos[0],
+            RespArray::new(vec![
+                BulkString::from("get").into(),
+                RespFrame::Integer(2),
+                RespArray::new(vec![
+                    SimpleString::new("readonly").into(),
+                    SimpleString::new("fast").into(),
+                ])
+                .into(),
+                RespFrame::Integer(1),
+                RespFrame::Integer(1),
+                RespFrame::Integer(1),
+                RespArray::new(vec![]).into(),
+                RespArray::new(vec![]).into(),
+                RespArray::new(vec![]).into(),
+                RespArray::new(vec![]).into(),
+            ])
+            .into()
+        );
+        assert_eq!(infos[1], RespFrame::Null(RespNull));
+        let RespFrame::Array(info) = &infos[2] else {
+            panic!("expected an array, got {:?}", infos[2]);
+        };
+        assert_eq!(info[0], BulkString::from("config|get").into());
+
+        let RespFrame::Integer(count) = CommandCount.execute(&backend) else {
+            panic!("expected an integer");
+        };
+        let RespFrame::Array(all) = Commands.execute(&backend) else {
+            panic!("expected an array");
+        };
+        assert_eq!(all.len() as i64, count);
+    }
+
+    #[test]
+    fn test_command_docs_execute() {
+        let backend = Backend::new();
+        let reply = CommandDocs(vec!["client".into(), "nosuch".into()]).execute(&backend);
+        let RespFrame::Map(docs) = reply else {
+            panic!("expected a map, got {:?}", reply);
+        };
+        assert_eq!(docs.0.len(), 1);
+        let RespFrame::Map(doc) = &docs.0[&BulkString::from("client").into()] else {
+            panic!("expected a map");
+        };
+        assert_eq!(
+            doc.0[&BulkString::from("group").into()],
+            BulkString::from("connection").into()
+        );
+        let RespFrame::Map(subcommands) = &doc.0[&BulkString::from("subcommands").into()] else {
+            panic!("expected a map");
+        };
+        assert!(subcommands
+            .0
+            .contains_key(&BulkString::from("client|pause").into()));
+
+        let RespFrame::Map(docs) = CommandDocs(vec![]).execute(&backend) else {
+            panic!("expected a map");
+        };
+        assert_eq!(docs.0.len(), COMMAND_TABLE.len());
+    }
+}
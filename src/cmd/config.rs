@@ -0,0 +1,1073 @@
+use super::{extract_args, validate_command, CommandError, CommandExecutor, REDACTED, RESP_OK};
+use crate::{
+    backend::{
+        glob_match, AppendFsync, ClientClass, EvictionPolicy, OutputBufferLimit, ReplicationTls,
+        SaveRule, SnapshotFormat,
+    },
+    max_aggregate_len, max_bulk_len, max_nesting_depth, set_max_aggregate_len, set_max_bulk_len,
+    set_max_nesting_depth, set_strict_parsing, strict_parsing, Backend, BulkString, RespArray,
+    RespFrame, SimpleError,
+};
+use derive_more::Deref;
+use std::{
+    collections::HashSet,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+// A runtime-tunable server parameter, read and written through CONFIG GET/SET.
+struct ConfigParam {
+    name: &'static str,
+    get: fn(&Backend) -> String,
+    set: fn(&Backend, &str) -> Result<(), String>,
+}
+
+const CONFIG_PARAMS: &[ConfigParam] = &[
+    ConfigParam {
+        name: "dir",
+        get: |backend| backend.dir().display().to_string(),
+        set: |backend, value| {
+            let dir = PathBuf::from(value);
+            if !dir.is_dir() {
+                return Err(format!("No such directory: {}", value));
+            }
+            backend.set_dir(dir);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "dbfilename",
+        get: |backend| backend.dbfilename(),
+        set: |backend, value| {
+            if value.is_empty() || value.contains(['/', '\\']) {
+                return Err("dbfilename can't be a path, just a filename".to_string());
+            }
+            backend.set_dbfilename(value.to_string());
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "dump-format",
+        get: |backend| backend.dump_format().as_str().to_string(),
+        set: |backend, value| {
+            let format = SnapshotFormat::parse(value)
+                .ok_or("argument(s) must be one of the following: native, rdb")?;
+            backend.set_dump_format(format);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "save",
+        get: |backend| {
+            backend
+                .save_rules()
+                .iter()
+                .map(|rule| format!("{} {}", rule.seconds, rule.changes))
+                .collect::<Vec<_>>()
+                .join(" ")
+        },
+        set: |backend, value| {
+            let numbers = value
+                .split_whitespace()
+                .map(|n| n.parse::<u64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| "Invalid save parameters")?;
+            if !numbers.len().is_multiple_of(2) {
+                return Err("Invalid save parameters".to_string());
+            }
+            let rules = numbers
+                .chunks(2)
+                .map(|pair| SaveRule::new(pair[0], pair[1]))
+                .collect();
+            backend.set_save_rules(rules);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "appendonly",
+        get: |backend| yes_no(backend.appendonly()),
+        set: |backend, value| {
+            backend
+                .set_appendonly(parse_yes_no(value)?)
+                .map_err(|e| e.to_string())
+        },
+    },
+    ConfigParam {
+        name: "appendfilename",
+        get: |backend| backend.appendfilename(),
+        set: |backend, value| {
+            if value.is_empty() || value.contains(['/', '\\']) {
+                return Err("appendfilename can't be a path, just a filename".to_string());
+            }
+            if backend.appendonly() {
+                return Err("can't change the file name while the AOF is enabled".to_string());
+            }
+            backend.set_appendfilename(value.to_string());
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "appendfsync",
+        get: |backend| backend.appendfsync().as_str().to_string(),
+        set: |backend, value| {
+            let policy = AppendFsync::parse(value)
+                .ok_or("argument(s) must be one of the following: always, everysec, no")?;
+            backend.set_appendfsync(policy);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "aof-use-rdb-preamble",
+        get: |backend| yes_no(backend.aof_use_rdb_preamble()),
+        set: |backend, value| {
+            backend.set_aof_use_rdb_preamble(parse_yes_no(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "aof-load-truncated",
+        get: |backend| yes_no(backend.aof_load_truncated()),
+        set: |backend, value| {
+            backend.set_aof_load_truncated(parse_yes_no(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "replica-read-only",
+        get: |backend| yes_no(backend.replica_read_only()),
+        set: |backend, value| {
+            backend.set_replica_read_only(parse_yes_no(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "repl-diskless-sync",
+        get: |backend| yes_no(backend.repl_diskless_sync()),
+        set: |backend, value| {
+            backend.set_repl_diskless_sync(parse_yes_no(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "requirepass",
+        get: |backend| backend.requirepass().unwrap_or_default(),
+        set: |backend, value| {
+            backend.set_requirepass((!value.is_empty()).then(|| value.to_string()));
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "masterauth",
+        get: |backend| backend.masterauth().unwrap_or_default(),
+        set: |backend, value| {
+            backend.set_masterauth((!value.is_empty()).then(|| value.to_string()));
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "tls-replication",
+        get: |backend| yes_no(backend.replication_tls().enabled),
+        set: |backend, value| {
+            let enabled = parse_yes_no(value)?;
+            if enabled && !cfg!(feature = "tls") {
+                return Err("TLS support is not available in this build".to_string());
+            }
+            update_replication_tls(backend, |tls| tls.enabled = enabled);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "tls-replication-ca-cert-file",
+        get: |backend| path_or_empty(backend.replication_tls().ca_cert_file),
+        set: |backend, value| {
+            update_replication_tls(backend, |tls| tls.ca_cert_file = optional_path(value));
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "tls-replication-cert-file",
+        get: |backend| path_or_empty(backend.replication_tls().cert_file),
+        set: |backend, value| {
+            update_replication_tls(backend, |tls| tls.cert_file = optional_path(value));
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "tls-replication-key-file",
+        get: |backend| path_or_empty(backend.replication_tls().key_file),
+        set: |backend, value| {
+            update_replication_tls(backend, |tls| tls.key_file = optional_path(value));
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "databases",
+        get: |backend| backend.databases().to_string(),
+        set: |_, _| Err("databases can only be set at startup".to_string()),
+    },
+    ConfigParam {
+        name: "keyspace-shards",
+        get: |backend| backend.keyspace_shards().to_string(),
+        set: |_, _| Err("keyspace-shards can only be set at startup".to_string()),
+    },
+    ConfigParam {
+        name: "cluster-enabled",
+        get: |backend| yes_no(backend.cluster_enabled()),
+        set: |_, _| Err("cluster-enabled can only be set at startup".to_string()),
+    },
+    ConfigParam {
+        name: "cluster-config-file",
+        get: |backend| backend.cluster_config_file(),
+        set: |backend, value| {
+            if value.is_empty() || value.contains(['/', '\\']) {
+                return Err("cluster-config-file can't be a path, just a filename".to_string());
+            }
+            backend.set_cluster_config_file(value.to_string());
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "cluster-announce-ip",
+        get: |backend| backend.cluster_announce_ip(),
+        set: |backend, value| {
+            backend.set_cluster_announce_ip(value.to_string());
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "maxmemory",
+        get: |backend| backend.maxmemory().to_string(),
+        set: |backend, value| {
+            let bytes = parse_memory(value).ok_or("argument must be a memory value")?;
+            backend.set_maxmemory(bytes);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "maxmemory-policy",
+        get: |backend| backend.eviction_policy().as_str().to_string(),
+        set: |backend, value| {
+            let policy = EvictionPolicy::parse(value).ok_or(
+                "argument(s) must be one of the following: noeviction, allkeys-lru, \
+                 allkeys-lfu, allkeys-random, volatile-lru, volatile-lfu, volatile-ttl, \
+                 volatile-random",
+            )?;
+            backend.set_eviction_policy(policy);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "maxmemory-samples",
+        get: |backend| backend.maxmemory_samples().to_string(),
+        set: |backend, value| match parse_integer(value)? {
+            samples @ 1..=64 => {
+                backend.set_maxmemory_samples(samples);
+                Ok(())
+            }
+            _ => Err("argument must be between 1 and 64 inclusive".to_string()),
+        },
+    },
+    ConfigParam {
+        name: "timeout",
+        get: |backend| {
+            backend
+                .client_timeout()
+                .map_or(0, |timeout| timeout.as_secs())
+                .to_string()
+        },
+        set: |backend, value| {
+            backend.set_client_timeout(parse_integer(value)? as u64);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "maxclients-per-ip",
+        get: |backend| backend.max_connections_per_address().to_string(),
+        set: |backend, value| {
+            backend.set_max_connections_per_address(parse_integer(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "max-connections-per-second-per-ip",
+        get: |backend| backend.connection_rate_per_address().to_string(),
+        set: |backend, value| {
+            backend.set_connection_rate_per_address(parse_integer(value)?);
+            Ok(())
+        },
+    },
+    // "<class> <hard> <soft> <soft seconds>", for any of the classes
+    ConfigParam {
+        name: "client-output-buffer-limit",
+        get: |backend| {
+            ClientClass::ALL
+                .iter()
+                .map(|class| {
+                    let limit = backend.output_buffer_limit(*class);
+                    format!(
+                        "{} {} {} {}",
+                        class.as_str(),
+                        limit.hard,
+                        limit.soft,
+                        limit.soft_seconds
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        },
+        set: |backend, value| {
+            let args: Vec<&str> = value.split_whitespace().collect();
+            if args.is_empty() || !args.len().is_multiple_of(4) {
+                return Err("Wrong number of arguments in buffer limit configuration.".to_string());
+            }
+            let invalid =
+                "Error in hard, soft or soft_seconds setting in buffer limit configuration.";
+            let limits = args
+                .chunks(4)
+                .map(|args| {
+                    let class = ClientClass::parse(args[0])
+                        .ok_or("Invalid client class specified in buffer limit configuration.")?;
+                    let limit = OutputBufferLimit {
+                        hard: parse_memory(args[1]).ok_or(invalid)?,
+                        soft: parse_memory(args[2]).ok_or(invalid)?,
+                        soft_seconds: args[3].parse().map_err(|_| invalid)?,
+                    };
+                    Ok((class, limit))
+                })
+                .collect::<Result<Vec<_>, &str>>()?;
+            for (class, limit) in limits {
+                backend.set_output_buffer_limit(class, limit);
+            }
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "proto-max-bulk-len",
+        get: |_| max_bulk_len().to_string(),
+        set: |_, value| {
+            let bytes = parse_memory(value).ok_or("argument must be a memory value")?;
+            if bytes < 1024 * 1024 {
+                return Err("argument must be at least 1mb".to_string());
+            }
+            set_max_bulk_len(bytes);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "proto-max-multibulk-len",
+        get: |_| max_aggregate_len().to_string(),
+        set: |_, value| match parse_integer(value)? {
+            0 => Err("argument must be greater than 0".to_string()),
+            len => {
+                set_max_aggregate_len(len);
+                Ok(())
+            }
+        },
+    },
+    ConfigParam {
+        name: "proto-max-nesting-depth",
+        get: |_| max_nesting_depth().to_string(),
+        set: |_, value| match parse_integer(value)? {
+            0 => Err("argument must be greater than 0".to_string()),
+            depth => {
+                set_max_nesting_depth(depth);
+                Ok(())
+            }
+        },
+    },
+    ConfigParam {
+        name: "proto-strict-parsing",
+        get: |_| yes_no(strict_parsing()),
+        set: |_, value| {
+            set_strict_parsing(parse_yes_no(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "incr-promote-bignum",
+        get: |backend| yes_no(backend.bignum_incr()),
+        set: |backend, value| {
+            backend.set_bignum_incr(parse_yes_no(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "lazyfree-threshold",
+        get: |backend| backend.lazyfree_threshold().to_string(),
+        set: |backend, value| {
+            backend.set_lazyfree_threshold(parse_integer(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "busy-reply-threshold",
+        get: |backend| backend.busy_reply_threshold().to_string(),
+        set: |backend, value| {
+            backend.set_busy_reply_threshold(parse_integer(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "latency-monitor-threshold",
+        get: |backend| backend.latency_monitor_threshold().to_string(),
+        set: |backend, value| {
+            backend.set_latency_monitor_threshold(parse_integer(value)?);
+            Ok(())
+        },
+    },
+    // the former name of busy-reply-threshold
+    ConfigParam {
+        name: "lua-time-limit",
+        get: |backend| backend.busy_reply_threshold().to_string(),
+        set: |backend, value| {
+            backend.set_busy_reply_threshold(parse_integer(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "lfu-log-factor",
+        get: |backend| backend.lfu_log_factor().to_string(),
+        set: |backend, value| {
+            backend.set_lfu_log_factor(parse_integer(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "lfu-decay-time",
+        get: |backend| backend.lfu_decay_time().to_string(),
+        set: |backend, value| {
+            backend.set_lfu_decay_time(parse_integer(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "hash-max-listpack-entries",
+        get: |backend| backend.hash_max_listpack_entries().to_string(),
+        set: |backend, value| {
+            backend.set_hash_max_listpack_entries(parse_integer(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "hash-max-listpack-value",
+        get: |backend| backend.hash_max_listpack_value().to_string(),
+        set: |backend, value| {
+            backend.set_hash_max_listpack_value(parse_integer(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "set-max-intset-entries",
+        get: |backend| backend.set_max_intset_entries().to_string(),
+        set: |backend, value| {
+            backend.set_set_max_intset_entries(parse_integer(value)?);
+            Ok(())
+        },
+    },
+    #[cfg(feature = "compression")]
+    ConfigParam {
+        name: "compression-threshold",
+        get: |backend| backend.compression_threshold().to_string(),
+        set: |backend, value| {
+            backend.set_compression_threshold(parse_integer(value)?);
+            Ok(())
+        },
+    },
+    ConfigParam {
+        name: "list-max-listpack-size",
+        get: |backend| backend.list_max_listpack_size().to_string(),
+        set: |backend, value| match value.parse::<i64>() {
+            Ok(fill @ (-5..=-1 | 1..)) => {
+                backend.set_list_max_listpack_size(fill);
+                Ok(())
+            }
+            _ => Err("argument must be between -5 and -1 or a positive integer".to_string()),
+        },
+    },
+];
+
+/// The names of the parameters CONFIG GET and CONFIG SET take.
+pub fn config_params() -> impl Iterator<Item = &'static str> {
+    CONFIG_PARAMS.iter().map(|param| param.name)
+}
+
+#[derive(Debug, Deref)]
+pub struct ConfigGet(Vec<String>);
+
+impl CommandExecutor for ConfigGet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let frames = CONFIG_PARAMS
+            .iter()
+            .filter(|param| {
+                self.iter().any(|pattern| {
+                    glob_match(
+                        pattern.to_ascii_lowercase().as_bytes(),
+                        param.name.as_bytes(),
+                    )
+                })
+            })
+            .flat_map(|param| {
+                [
+                    BulkString::from(param.name).into(),
+                    BulkString::from((param.get)(backend)).into(),
+                ]
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(frames).into()
+    }
+}
+
+// parameter [parameter ...]
+impl TryFrom<RespArray> for ConfigGet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["config", "get"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+#[derive(Deref)]
+pub struct ConfigSet(Vec<(String, String)>);
+
+// The passwords set are left out, as the commands are logged.
+impl fmt::Debug for ConfigSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params = self.iter().map(|(name, value)| {
+            let secret = ["requirepass", "masterauth"]
+                .iter()
+                .any(|secret| name.eq_ignore_ascii_case(secret));
+            (name, if secret { REDACTED } else { value.as_str() })
+        });
+        f.debug_tuple("ConfigSet")
+            .field(&params.collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl CommandExecutor for ConfigSet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        for (name, value) in self.iter() {
+            let Some(param) = CONFIG_PARAMS
+                .iter()
+                .find(|param| param.name.eq_ignore_ascii_case(name))
+            else {
+                return SimpleError::new(format!(
+                    "ERR Unknown option or number of arguments for CONFIG SET - '{}'",
+                    name
+                ))
+                .into();
+            };
+            if let Err(e) = (param.set)(backend, value) {
+                return SimpleError::new(format!(
+                    "ERR CONFIG SET failed (possibly related to argument '{}') - {}",
+                    param.name, e
+                ))
+                .into();
+            }
+        }
+        RESP_OK.clone()
+    }
+}
+
+// parameter value [parameter value ...]
+impl TryFrom<RespArray> for ConfigSet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["config", "set"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let args = Vec::<String>::try_from(args)?;
+        if !args.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidCommandArguments(
+                "config set must have parameter value pairs".to_string(),
+            ));
+        }
+        let mut args = args.into_iter();
+        let mut pairs = Vec::new();
+        while let (Some(name), Some(value)) = (args.next(), args.next()) {
+            pairs.push((name, value));
+        }
+        Ok(Self(pairs))
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigRewrite;
+
+impl CommandExecutor for ConfigRewrite {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let Some(path) = backend.config_file() else {
+            return SimpleError::new("ERR The server is running without a config file").into();
+        };
+        match rewrite_config_file(backend, &path) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => SimpleError::new(format!("ERR Rewriting config file: {}", e)).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ConfigRewrite {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["config", "rewrite"];
+        validate_command(&value, &cmd_names)?;
+        match value.len() {
+            2 => Ok(ConfigRewrite),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "config rewrite takes no arguments".to_string(),
+            )),
+        }
+    }
+}
+
+/// Reads the directives of a redis.conf-style file as `(name, value)` pairs in order, the
+/// arguments of a directive being joined by spaces. The `save` lines add up, as in Redis.
+pub fn parse_config_file(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut directives: Vec<(String, String)> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let args = split_config_line(line).map_err(|e| format!("line {}: {}", number + 1, e))?;
+        let Some((name, args)) = args.split_first() else {
+            continue;
+        };
+        let name = name.to_ascii_lowercase();
+        let value = args.join(" ");
+        let saved = directives.iter_mut().find(|(saved, _)| *saved == name);
+        match saved {
+            Some((_, rules)) if name == "save" && !rules.is_empty() && !value.is_empty() => {
+                rules.push(' ');
+                rules.push_str(&value);
+            }
+            _ => directives.push((name, value)),
+        }
+    }
+    Ok(directives)
+}
+
+// Writes the current value of the parameters over their lines in the config file at `path`,
+// leaving the comments and the other directives as they are. The parameters the file doesn't
+// set are appended when they differ from their default.
+fn rewrite_config_file(backend: &Backend, path: &Path) -> io::Result<()> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    let mut written = HashSet::new();
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let param = split_config_line(line).ok().and_then(|args| {
+            let name = args.first()?;
+            CONFIG_PARAMS
+                .iter()
+                .find(|param| param.name.eq_ignore_ascii_case(name))
+        });
+        match param {
+            Some(param) if written.insert(param.name) => {
+                lines.push(config_line(param.name, &(param.get)(backend)))
+            }
+            // a further line of a parameter written above, such as a save rule
+            Some(_) => {}
+            None => lines.push(line.to_string()),
+        }
+    }
+    let defaults = Backend::new();
+    let changed = CONFIG_PARAMS.iter().filter(|param| {
+        // the former name of busy-reply-threshold, written under its current name
+        param.name != "lua-time-limit"
+            && !written.contains(param.name)
+            && (param.get)(backend) != (param.get)(&defaults)
+    });
+    for (i, param) in changed.enumerate() {
+        if i == 0 {
+            lines.push("# Generated by CONFIG REWRITE".to_string());
+        }
+        lines.push(config_line(param.name, &(param.get)(backend)));
+    }
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    // replaces the file in one go, so a crash can't leave it half written
+    let tmp = path.with_extension("rewrite.tmp");
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)
+}
+
+// Splits a config file line into its arguments, which may be quoted as in Redis: "..." with
+// backslash escapes, or '...' where only \' is escaped. Comments have no arguments.
+fn split_config_line(line: &str) -> Result<Vec<String>, String> {
+    let line = line.trim();
+    if line.starts_with('#') {
+        return Ok(vec![]);
+    }
+    let unbalanced = || "Unbalanced quotes in configuration line".to_string();
+    let mut args = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            return Ok(args);
+        };
+        let mut arg = String::new();
+        match first {
+            '"' | '\'' => {
+                chars.next();
+                loop {
+                    match (chars.next().ok_or_else(unbalanced)?, first) {
+                        (c, quote) if c == quote => break,
+                        ('\\', '"') => {
+                            let escaped = chars.next().ok_or_else(unbalanced)?;
+                            arg.push(match escaped {
+                                'n' => '\n',
+                                'r' => '\r',
+                                't' => '\t',
+                                'b' => '\u{8}',
+                                'a' => '\u{7}',
+                                'x' => {
+                                    let hex: String = chars.clone().take(2).collect();
+                                    match u8::from_str_radix(&hex, 16) {
+                                        Ok(byte) if hex.len() == 2 => {
+                                            chars.nth(1);
+                                            char::from(byte)
+                                        }
+                                        _ => 'x',
+                                    }
+                                }
+                                c => c,
+                            });
+                        }
+                        ('\\', '\'') if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            arg.push('\'');
+                        }
+                        (c, _) => arg.push(c),
+                    }
+                }
+                // a closing quote must end the argument
+                if chars.peek().is_some_and(|c| !c.is_whitespace()) {
+                    return Err(unbalanced());
+                }
+            }
+            _ => {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    arg.push(c);
+                }
+            }
+        }
+        args.push(arg);
+    }
+}
+
+// The config file line setting `name` to `value`, quoted unless it reads back the same bare.
+fn config_line(name: &str, value: &str) -> String {
+    let bare = !value.is_empty()
+        && value.split(' ').all(|word| {
+            !word.is_empty()
+                && word
+                    .chars()
+                    .all(|c| !c.is_control() && !matches!(c, '"' | '\'' | '\\'))
+        });
+    if bare {
+        return format!("{} {}", name, value);
+    }
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_ascii_control() => quoted.push_str(&format!("\\x{:02x}", c as u8)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    format!("{} {}", name, quoted)
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "yes" } else { "no" }.to_string()
+}
+
+fn parse_yes_no(value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err("argument must be 'yes' or 'no'".to_string()),
+    }
+}
+
+fn update_replication_tls(backend: &Backend, update: impl FnOnce(&mut ReplicationTls)) {
+    let mut tls = backend.replication_tls();
+    update(&mut tls);
+    backend.set_replication_tls(tls);
+}
+
+// an empty value unsets a file
+fn optional_path(value: &str) -> Option<PathBuf> {
+    (!value.is_empty()).then(|| PathBuf::from(value))
+}
+
+fn path_or_empty(path: Option<PathBuf>) -> String {
+    path.map(|path| path.display().to_string())
+        .unwrap_or_default()
+}
+
+fn parse_integer(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| "argument couldn't be parsed into an integer".to_string())
+}
+
+// Parses a byte count with an optional unit suffix, as accepted in redis.conf.
+fn parse_memory(value: &str) -> Option<usize> {
+    let value = value.to_ascii_lowercase();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let multiplier = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    number.parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::RespDecoder;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_config_set_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$6\r\nconfig\r\n$3\r\nset\r\n$9\r\nmaxmemory\r\n$3\r\n1mb\r\n",
+        );
+        let cmd = ConfigSet::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0, vec![("maxmemory".to_string(), "1mb".to_string())]);
+
+        buf.extend_from_slice(b"*3\r\n$6\r\nconfig\r\n$3\r\nset\r\n$9\r\nmaxmemory\r\n");
+        assert!(ConfigSet::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        // the passwords are left out of the logs
+        let cmd = ConfigSet(vec![
+            ("REQUIREPASS".into(), "secret".into()),
+            ("maxmemory".into(), "1mb".into()),
+        ]);
+        let logged = format!("{:?}", cmd);
+        assert!(!logged.contains("secret"));
+        assert!(logged.contains("1mb"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_config_file() {
+        let text = "# a comment\n\
+                    \n\
+                    port 6380\n\
+                    save 900 1\n\
+                    SAVE 60 100\n\
+                    dir \"/tmp/with space\"\n\
+                    requirepass 'it''s'\n";
+        assert!(parse_config_file(text).is_err());
+        let text = text.replace("'it''s'", "'it\\'s'");
+        assert_eq!(
+            parse_config_file(&text).unwrap(),
+            vec![
+                ("port".to_string(), "6380".to_string()),
+                ("save".to_string(), "900 1 60 100".to_string()),
+                ("dir".to_string(), "/tmp/with space".to_string()),
+                ("requirepass".to_string(), "it's".to_string()),
+            ]
+        );
+        assert!(parse_config_file("dir \"/tmp").is_err());
+        assert_eq!(
+            split_config_line(r#"a "\x41\n\"" '' b"#).unwrap(),
+            ["a", "A\n\"", "", "b"]
+        );
+        for value in ["plain", "900 1", "", "two  spaces", "quote\"s", "tab\t"] {
+            let line = config_line("name", value);
+            assert_eq!(split_config_line(&line).unwrap()[1..].join(" "), value);
+        }
+    }
+
+    #[test]
+    fn test_config_rewrite() -> Result<()> {
+        let backend = Backend::new();
+        assert!(matches!(
+            ConfigRewrite.execute(&backend),
+            RespFrame::SimpleError(_)
+        ));
+
+        let path = std::env::temp_dir().join(format!("config-rewrite-{}.conf", std::process::id()));
+        fs::write(
+            &path,
+            "# kept\nport 6380\nmaxmemory 1mb\nsave 900 1\nsave 60 100\n",
+        )?;
+        backend.set_config_file(path.clone());
+        backend.set_maxmemory(2 * 1024 * 1024);
+        backend.set_save_rules(vec![]);
+        backend.set_lfu_decay_time(5);
+        assert_eq!(ConfigRewrite.execute(&backend), RESP_OK.clone());
+        let text = fs::read_to_string(&path)?;
+        fs::remove_file(&path)?;
+        assert_eq!(
+            text,
+            "# kept\nport 6380\nmaxmemory 2097152\nsave \"\"\n\
+             # Generated by CONFIG REWRITE\nlfu-decay-time 5\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_replication_tls() {
+        let backend = Backend::new();
+        let cmd = ConfigSet(vec![
+            ("tls-replication-ca-cert-file".into(), "ca.crt".into()),
+            ("tls-replication-cert-file".into(), "replica.crt".into()),
+        ]);
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let tls = backend.replication_tls();
+        assert_eq!(tls.ca_cert_file, Some(PathBuf::from("ca.crt")));
+        assert_eq!(tls.cert_file, Some(PathBuf::from("replica.crt")));
+        assert_eq!(tls.key_file, None);
+
+        let cmd = ConfigSet(vec![("tls-replication-cert-file".into(), "".into())]);
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd = ConfigGet(vec!["tls-replication-c*".into()]);
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![
+                BulkString::from("tls-replication-ca-cert-file").into(),
+                BulkString::from("ca.crt").into(),
+                BulkString::from("tls-replication-cert-file").into(),
+                BulkString::from("").into(),
+            ])
+            .into()
+        );
+
+        let cmd = ConfigSet(vec![("tls-replication".into(), "yes".into())]);
+        let enabled = cmd.execute(&backend) == RESP_OK.clone();
+        assert_eq!(enabled, cfg!(feature = "tls"));
+        assert_eq!(backend.replication_tls().enabled, enabled);
+    }
+
+    #[test]
+    fn test_config_cmds_execute() {
+        let backend = Backend::new();
+        let cmd = ConfigSet(vec![
+            ("maxmemory".into(), "1mb".into()),
+            ("MAXMEMORY-POLICY".into(), "allkeys-lru".into()),
+        ]);
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(backend.maxmemory(), 1024 * 1024);
+        assert_eq!(backend.eviction_policy(), EvictionPolicy::AllKeysLru);
+
+        let cmd = ConfigGet(vec!["maxmemory*".into()]);
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![
+                BulkString::from("maxmemory").into(),
+                BulkString::from("1048576").into(),
+                BulkString::from("maxmemory-policy").into(),
+                BulkString::from("allkeys-lru").into(),
+                BulkString::from("maxmemory-samples").into(),
+                BulkString::from("5").into(),
+            ])
+            .into()
+        );
+
+        // larger than the default, so decoding elsewhere isn't affected
+        let cmd = ConfigSet(vec![("proto-max-bulk-len".into(), "1gb".into())]);
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(max_bulk_len(), 1024 * 1024 * 1024);
+        let cmd = ConfigSet(vec![("proto-max-bulk-len".into(), "512mb".into())]);
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd = ConfigSet(vec![("proto-max-bulk-len".into(), "1kb".into())]);
+        assert!(matches!(cmd.execute(&backend), RespFrame::SimpleError(_)));
+        let cmd = ConfigSet(vec![("proto-max-multibulk-len".into(), "0".into())]);
+        assert!(matches!(cmd.execute(&backend), RespFrame::SimpleError(_)));
+        let cmd = ConfigSet(vec![("proto-max-nesting-depth".into(), "0".into())]);
+        assert!(matches!(cmd.execute(&backend), RespFrame::SimpleError(_)));
+        // the default, so decoding elsewhere isn't affected
+        let cmd = ConfigSet(vec![("proto-strict-parsing".into(), "no".into())]);
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert!(!strict_parsing());
+        let cmd = ConfigSet(vec![("proto-strict-parsing".into(), "maybe".into())]);
+        assert!(matches!(cmd.execute(&backend), RespFrame::SimpleError(_)));
+
+        let cmd = ConfigSet(vec![("maxmemory".into(), "lots".into())]);
+        assert!(matches!(cmd.execute(&backend), RespFrame::SimpleError(_)));
+        let cmd = ConfigSet(vec![("maxmemory-samples".into(), "0".into())]);
+        assert!(matches!(cmd.execute(&backend), RespFrame::SimpleError(_)));
+        let cmd = ConfigSet(vec![("lfu-log-factor".into(), "-1".into())]);
+        assert!(matches!(cmd.execute(&backend), RespFrame::SimpleError(_)));
+        let cmd = ConfigSet(vec![("lfu-decay-time".into(), "5".into())]);
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(backend.lfu_decay_time(), 5);
+        let cmd = ConfigSet(vec![(
+            "client-output-buffer-limit".into(),
+            "normal 1mb 512kb 10 slave 0 0 0".into(),
+        )]);
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd = ConfigGet(vec!["client-output-buffer-limit".into()]);
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![
+                BulkString::from("client-output-buffer-limit").into(),
+                BulkString::from(
+                    "normal 1048576 524288 10 replica 0 0 0 pubsub 33554432 8388608 60"
+                )
+                .into(),
+            ])
+            .into()
+        );
+        let cmd = ConfigSet(vec![(
+            "client-output-buffer-limit".into(),
+            "pubsub 1mb 1mb".into(),
+        )]);
+        assert!(matches!(cmd.execute(&backend), RespFrame::SimpleError(_)));
+        let cmd = ConfigSet(vec![("save".into(), "900 1 60 100".into())]);
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(
+            backend.save_rules(),
+            vec![SaveRule::new(900, 1), SaveRule::new(60, 100)]
+        );
+        let cmd = ConfigGet(vec!["save".into()]);
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![
+                BulkString::from("save").into(),
+                BulkString::from("900 1 60 100").into(),
+            ])
+            .into()
+        );
+        let cmd = ConfigSet(vec![("save".into(), "900".into())]);
+        assert!(matches!(cmd.execute(&backend), RespFrame::SimpleError(_)));
+        let cmd = ConfigSet(vec![("save".into(), "".into())]);
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert!(backend.save_rules().is_empty());
+        let cmd = ConfigSet(vec![("appendfsync".into(), "ALWAYS".into())]);
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(backend.appendfsync(), AppendFsync::Always);
+        let cmd = ConfigSet(vec![("appendonly".into(), "maybe".into())]);
+        assert!(matches!(cmd.execute(&backend), RespFrame::SimpleError(_)));
+        let cmd = ConfigSet(vec![("no-such-option".into(), "1".into())]);
+        assert!(matches!(cmd.execute(&backend), RespFrame::SimpleError(_)));
+        assert_eq!(parse_memory("100"), Some(100));
+        assert_eq!(parse_memory("2GB"), Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_memory("1tb"), None);
+    }
+}
@@ -0,0 +1,190 @@
+use super::{
+    connection_only, extract_args, extract_integer, unless_busy, validate_command, CommandError,
+    CommandExecutor, Propagated, RESP_OK,
+};
+use crate::{Backend, RespArray, RespFrame, SimpleError};
+use tokio::task;
+
+#[derive(Debug)]
+pub struct Select(i64);
+
+#[derive(Debug)]
+pub struct SwapDb(i64, i64);
+
+impl CommandExecutor for Select {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("SELECT")
+    }
+}
+
+impl Select {
+    /// Switches the connection holding `backend` to the database.
+    pub fn execute_connection(self, backend: &mut Backend) -> RespFrame {
+        match self.select(backend) {
+            Ok(selected) => {
+                *backend = selected;
+                RESP_OK.clone()
+            }
+            Err(e) => SimpleError::new(e).into(),
+        }
+    }
+
+    /// A handle on the database, for replaying commands sent after this one.
+    pub fn select(&self, backend: &Backend) -> Result<Backend, String> {
+        if self.0 != 0 && backend.cluster_enabled() {
+            return Err("ERR SELECT is not allowed in cluster mode".to_string());
+        }
+        usize::try_from(self.0)
+            .ok()
+            .and_then(|index| backend.select(index))
+            .ok_or_else(|| "ERR DB index is out of range".to_string())
+    }
+}
+
+impl CommandExecutor for SwapDb {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.cluster_enabled() {
+            return SimpleError::new("ERR SWAPDB is not allowed in cluster mode").into();
+        }
+        let swapped = match (usize::try_from(self.0), usize::try_from(self.1)) {
+            (Ok(index1), Ok(index2)) => backend.swapdb(index1, index2),
+            _ => false,
+        };
+        match swapped {
+            true => RESP_OK.clone(),
+            false => SimpleError::new("ERR DB index is out of range").into(),
+        }
+    }
+}
+
+impl SwapDb {
+    /// Swaps the databases with no other command running, which could see them half swapped.
+    pub async fn execute_atomic(
+        self,
+        backend: &Backend,
+        propagated: Option<Propagated>,
+    ) -> RespFrame {
+        backend.writes_unpaused().await;
+        match unless_busy(backend, backend.transaction_lock()).await {
+            Ok(_lock) => {
+                let reply = task::block_in_place(|| self.execute(backend));
+                if let Some(propagated) = propagated {
+                    propagated.propagate(backend, &reply);
+                }
+                reply
+            }
+            Err(busy) => busy,
+        }
+    }
+}
+
+// index
+impl TryFrom<RespArray> for Select {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["select"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        match (args.next(), args.next()) {
+            (Some(index), None) => Ok(Select(extract_integer(index)?)),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "select takes a database index".to_string(),
+            )),
+        }
+    }
+}
+
+// index1 index2
+impl TryFrom<RespArray> for SwapDb {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["swapdb"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(index1), Some(index2), None) => {
+                Ok(SwapDb(extract_integer(index1)?, extract_integer(index2)?))
+            }
+            _ => Err(CommandError::InvalidCommandArguments(
+                "swapdb takes two database indexes".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resp::RespDecoder, BulkString};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_select_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$6\r\nselect\r\n$1\r\n3\r\n");
+        let cmd = Select::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0, 3);
+
+        buf.extend_from_slice(b"*2\r\n$6\r\nselect\r\n$3\r\none\r\n");
+        assert!(Select::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*1\r\n$6\r\nselect\r\n");
+        assert!(Select::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_execute() {
+        let mut backend = Backend::with_databases(4);
+        backend.set("key".into(), BulkString::from("0").into());
+        assert_eq!(Select(3).execute_connection(&mut backend), RESP_OK.clone());
+        assert_eq!(backend.db_index(), 3);
+        assert_eq!(backend.get(b"key").unwrap(), None);
+
+        for index in [4, -1] {
+            assert_eq!(
+                Select(index).execute_connection(&mut backend),
+                SimpleError::new("ERR DB index is out of range").into()
+            );
+        }
+        assert_eq!(backend.db_index(), 3);
+        Select(0).execute_connection(&mut backend);
+        assert_eq!(
+            backend.get(b"key").unwrap(),
+            Some(BulkString::from("0").into())
+        );
+    }
+
+    #[test]
+    fn test_swapdb_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nswapdb\r\n$1\r\n0\r\n$1\r\n1\r\n");
+        let cmd = SwapDb::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!((cmd.0, cmd.1), (0, 1));
+
+        buf.extend_from_slice(b"*2\r\n$6\r\nswapdb\r\n$1\r\n0\r\n");
+        assert!(SwapDb::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_swapdb_execute() {
+        let backend = Backend::with_databases(2);
+        backend.set("key".into(), BulkString::from("0").into());
+        assert_eq!(SwapDb(0, 1).execute(&backend), RESP_OK.clone());
+        assert_eq!(backend.get(b"key").unwrap(), None);
+        assert_eq!(
+            backend.select(1).unwrap().get(b"key").unwrap(),
+            Some(BulkString::from("0").into())
+        );
+        assert_eq!(
+            SwapDb(0, 2).execute(&backend),
+            SimpleError::new("ERR DB index is out of range").into()
+        );
+        assert_eq!(
+            SwapDb(-1, 0).execute(&backend),
+            SimpleError::new("ERR DB index is out of range").into()
+        );
+    }
+}
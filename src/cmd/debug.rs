@@ -0,0 +1,214 @@
+use super::{
+    extract_args, extract_float, extract_integer, unless_busy, validate_command, CommandError,
+    CommandExecutor, RESP_OK,
+};
+use crate::{Backend, RespArray, RespFrame, SimpleError, SimpleString};
+use bytes::Bytes;
+use derive_more::Deref;
+use std::{thread, time::Duration};
+use tokio::time;
+use tracing::info;
+
+#[derive(Debug)]
+pub struct DebugSleep(Duration);
+
+#[derive(Debug)]
+pub struct DebugJmap;
+
+#[derive(Debug, Deref)]
+pub struct DebugObject(Bytes);
+
+#[derive(Debug)]
+pub struct DebugSetActiveExpire(bool);
+
+#[derive(Debug)]
+pub struct DebugChangeReplId;
+
+impl CommandExecutor for DebugSleep {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        thread::sleep(self.0);
+        RESP_OK.clone()
+    }
+}
+
+impl DebugSleep {
+    /// Sleeps with no other command running, as the whole server does in Redis.
+    pub async fn execute_atomic(self, backend: &Backend) -> RespFrame {
+        match unless_busy(backend, backend.transaction_lock()).await {
+            Ok(_lock) => {
+                time::sleep(self.0).await;
+                RESP_OK.clone()
+            }
+            Err(busy) => busy,
+        }
+    }
+}
+
+impl CommandExecutor for DebugJmap {
+    // There is no allocator heap to dump, the memory accounting stands for it in the log.
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let stats = backend.memory_stats();
+        info!(
+            "Memory map: used={} peak={} overhead={} keys={} datasets={:?}",
+            stats.used, stats.peak, stats.overhead, stats.keys, stats.datasets
+        );
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for DebugObject {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (Some(encoding), Some(length), Some(idle)) = (
+            backend.object_encoding(&self),
+            backend.serialized_length(&self),
+            backend.object_idletime(&self),
+        ) else {
+            return SimpleError::new("ERR no such key").into();
+        };
+        SimpleString::new(format!(
+            "encoding:{} serializedlength:{} lru_seconds_idle:{}",
+            encoding, length, idle
+        ))
+        .into()
+    }
+}
+
+impl CommandExecutor for DebugSetActiveExpire {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend.set_active_expire(self.0);
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for DebugChangeReplId {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend.change_replid();
+        RESP_OK.clone()
+    }
+}
+
+// seconds
+impl TryFrom<RespArray> for DebugSleep {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["debug", "sleep"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        match (args.next(), args.next()) {
+            (Some(secs), None) => Duration::try_from_secs_f64(extract_float(secs)?)
+                .map(DebugSleep)
+                .map_err(|_| CommandError::InvalidArgument("value is out of range".to_string())),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "debug sleep takes a number of seconds".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for DebugJmap {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_args(value, ["debug", "jmap"]).map(|_| DebugJmap)
+    }
+}
+
+// key
+impl TryFrom<RespArray> for DebugObject {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["debug", "object"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+// 0|1
+impl TryFrom<RespArray> for DebugSetActiveExpire {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["debug", "set-active-expire"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        match (args.next(), args.next()) {
+            (Some(flag), None) => Ok(DebugSetActiveExpire(extract_integer(flag)? != 0)),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "debug set-active-expire takes 0 or 1".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for DebugChangeReplId {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_args(value, ["debug", "change-repl-id"]).map(|_| DebugChangeReplId)
+    }
+}
+
+fn no_args(value: RespArray, cmd_names: [&'static str; 2]) -> Result<(), CommandError> {
+    validate_command(&value, &cmd_names)?;
+    if value.len() != cmd_names.len() {
+        return Err(CommandError::InvalidCommandArguments(format!(
+            "{} takes no arguments",
+            cmd_names.join(" ")
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resp::RespDecoder, BulkString};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_debug_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$5\r\ndebug\r\n$5\r\nsleep\r\n$3\r\n0.5\r\n");
+        let cmd = DebugSleep::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0, Duration::from_millis(500));
+
+        buf.extend_from_slice(b"*3\r\n$5\r\ndebug\r\n$5\r\nsleep\r\n$2\r\n-1\r\n");
+        assert!(DebugSleep::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*3\r\n$5\r\nDEBUG\r\n$17\r\nset-active-expire\r\n$1\r\n0\r\n");
+        let cmd = DebugSetActiveExpire::try_from(RespArray::decode(&mut buf)?)?;
+        assert!(!cmd.0);
+
+        buf.extend_from_slice(b"*3\r\n$5\r\ndebug\r\n$4\r\njmap\r\n$1\r\nx\r\n");
+        assert!(DebugJmap::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_execute() {
+        let backend = Backend::new();
+        assert_eq!(
+            DebugObject("key".into()).execute(&backend),
+            SimpleError::new("ERR no such key").into()
+        );
+        backend.set("key".into(), BulkString::from("12345").into());
+        // the native format writes the value as a bulk string
+        assert_eq!(
+            DebugObject("key".into()).execute(&backend),
+            SimpleString::new("encoding:int serializedlength:11 lru_seconds_idle:0").into()
+        );
+
+        backend.set("short".into(), BulkString::from("0").into());
+        // long enough not to have passed already when set on a busy machine
+        backend.expire(b"short", 50);
+        DebugSetActiveExpire(false).execute(&backend);
+        thread::sleep(Duration::from_millis(60));
+        assert_eq!(backend.active_expire_cycle(), 0);
+        DebugSetActiveExpire(true).execute(&backend);
+        assert_eq!(backend.active_expire_cycle(), 1);
+
+        let replid = backend.replid();
+        assert_eq!(DebugChangeReplId.execute(&backend), RESP_OK.clone());
+        assert_ne!(backend.replid(), replid);
+        assert_eq!(backend.replid2(), ("0".repeat(40), None));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{RespError, RespFrame};
+use crate::{backend::WrongType, RespError, RespFrame};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -8,6 +8,8 @@ pub enum CommandError {
     #[error("Invalid command arguments: {0}")]
     InvalidCommandArguments(String),
     #[error("{0}")]
+    InvalidArgument(String),
+    #[error("{0}")]
     RespError(#[from] RespError),
     #[error("Invalid UTF-8: {0}")]
     Utf8Error(#[from] std::string::FromUtf8Error),
@@ -16,11 +18,53 @@ pub enum CommandError {
 impl From<CommandError> for RespFrame {
     fn from(err: CommandError) -> Self {
         match err {
-            CommandError::InvalidCommand(msg) => RespFrame::SimpleError(msg.into()),
+            CommandError::InvalidCommand(msg) => {
+                RespFrame::SimpleError(format!("ERR {}", msg).into())
+            }
             CommandError::InvalidCommandArguments(_) => {
                 RespFrame::SimpleError("ERR wrong number of arguments for command".into())
             }
-            _ => RespFrame::SimpleError("ERR internal error".into()),
+            CommandError::InvalidArgument(msg) => {
+                RespFrame::SimpleError(format!("ERR {}", msg).into())
+            }
+            CommandError::RespError(e) => {
+                RespFrame::SimpleError(format!("ERR Protocol error: {}", e).into())
+            }
+            CommandError::Utf8Error(_) => {
+                RespFrame::SimpleError("ERR invalid UTF-8 in argument".into())
+            }
         }
     }
 }
+
+impl From<WrongType> for RespFrame {
+    fn from(err: WrongType) -> Self {
+        RespFrame::SimpleError(err.to_string().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimpleError;
+
+    #[test]
+    fn test_command_error_to_frame() {
+        let frame: RespFrame = CommandError::InvalidCommand("unknown command 'foo'".into()).into();
+        assert_eq!(frame, SimpleError::new("ERR unknown command 'foo'").into());
+
+        let frame: RespFrame =
+            CommandError::RespError(RespError::InvalidFrame("unknown prefix".into())).into();
+        assert_eq!(
+            frame,
+            SimpleError::new("ERR Protocol error: Invalid frame: unknown prefix").into()
+        );
+
+        let utf8 = String::from_utf8(vec![0xff]).unwrap_err();
+        let frame: RespFrame = CommandError::Utf8Error(utf8).into();
+        assert_eq!(
+            frame,
+            SimpleError::new("ERR invalid UTF-8 in argument").into()
+        );
+    }
+}
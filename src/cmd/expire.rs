@@ -0,0 +1,283 @@
+use super::{
+    extract_args, extract_integer, validate_command, CommandError, CommandExecutor, StorageExecutor,
+};
+use crate::{Backend, BulkString, RespArray, RespFrame, Storage};
+use bytes::Bytes;
+use derive_more::Deref;
+
+#[derive(Debug)]
+pub struct Expire {
+    key: Bytes,
+    // relative timeout in milliseconds, whatever the unit of the command
+    millis: i64,
+}
+
+impl StorageExecutor for Expire {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        RespFrame::Integer(storage.expire(&self.key, self.millis) as i64)
+    }
+}
+
+impl Expire {
+    // The command as logged in the AOF, with the timeout made absolute so a replay sets the
+    // same one.
+    pub(super) fn to_pexpireat(&self, backend: &Backend) -> RespFrame {
+        let at = backend.now_millis().saturating_add_signed(self.millis);
+        RespArray::new([
+            BulkString::from("PEXPIREAT").into(),
+            BulkString::from(self.key.clone()).into(),
+            BulkString::from(at.to_string()).into(),
+        ])
+        .into()
+    }
+}
+
+// key seconds
+impl TryFrom<RespArray> for Expire {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["expire"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        parse_expire(args, 1000)
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct PExpire(Expire);
+
+impl StorageExecutor for PExpire {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        self.0.execute_on(storage)
+    }
+}
+
+// key milliseconds
+impl TryFrom<RespArray> for PExpire {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["pexpire"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(parse_expire(args, 1)?))
+    }
+}
+
+#[derive(Debug)]
+pub struct PExpireAt {
+    key: Bytes,
+    // unix time in milliseconds
+    at: u64,
+}
+
+impl CommandExecutor for PExpireAt {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.expire_at(&self.key, self.at) as i64)
+    }
+}
+
+// key unix-time-milliseconds
+impl TryFrom<RespArray> for PExpireAt {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["pexpireat"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let Expire { key, millis } = parse_expire(args, 1)?;
+        // times before the epoch are in the past all the same
+        Ok(PExpireAt {
+            key,
+            at: millis.max(0) as u64,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Ttl {
+    key: Bytes,
+    millis: bool,
+}
+
+impl StorageExecutor for Ttl {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        let ttl = match storage.ttl(&self.key) {
+            None => -2,
+            Some(None) => -1,
+            Some(Some(ttl)) if self.millis => ttl as i64,
+            // round to the closest second like Redis does
+            Some(Some(ttl)) => ttl.div_ceil(1000) as i64,
+        };
+        RespFrame::Integer(ttl)
+    }
+}
+
+// key
+impl TryFrom<RespArray> for Ttl {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["ttl"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Ttl {
+            key: args.try_into()?,
+            millis: false,
+        })
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct PTtl(Ttl);
+
+impl StorageExecutor for PTtl {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        self.0.execute_on(storage)
+    }
+}
+
+// key
+impl TryFrom<RespArray> for PTtl {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["pttl"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(Ttl {
+            key: args.try_into()?,
+            millis: true,
+        }))
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct Persist(Bytes);
+
+impl StorageExecutor for Persist {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        RespFrame::Integer(storage.persist(&self) as i64)
+    }
+}
+
+// key
+impl TryFrom<RespArray> for Persist {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["persist"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+fn parse_expire(args: RespArray, unit_millis: i64) -> Result<Expire, CommandError> {
+    if args.len() != 2 {
+        return Err(CommandError::InvalidCommandArguments(
+            "Command must have a key and a timeout".to_string(),
+        ));
+    }
+    let mut args = args.0.into_iter();
+    let (Some(RespFrame::BulkString(key)), Some(timeout)) = (args.next(), args.next()) else {
+        return Err(CommandError::InvalidCommandArguments(
+            "Invalid key or timeout".to_string(),
+        ));
+    };
+    let millis = extract_integer(timeout)?
+        .checked_mul(unit_millis)
+        .ok_or_else(|| {
+            CommandError::InvalidArgument("invalid expire time in 'expire' command".to_string())
+        })?;
+    Ok(Expire { key: key.0, millis })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resp::RespDecoder, BulkString, MockClock};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::{sync::Arc, time::Duration};
+
+    #[test]
+    fn test_expire_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nexpire\r\n$3\r\nkey\r\n$2\r\n10\r\n");
+        let cmd = Expire::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.key, "key");
+        assert_eq!(cmd.millis, 10_000);
+
+        buf.extend_from_slice(b"*3\r\n$7\r\npexpire\r\n$3\r\nkey\r\n$2\r\n10\r\n");
+        let cmd = PExpire::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.millis, 10);
+
+        buf.extend_from_slice(b"*3\r\n$6\r\nexpire\r\n$3\r\nkey\r\n$3\r\nten\r\n");
+        assert!(Expire::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_expire_cmds_execute() {
+        let backend = Backend::new();
+        let ttl = |key: &str| {
+            Ttl {
+                key: Bytes::copy_from_slice(key.as_bytes()),
+                millis: false,
+            }
+            .execute(&backend)
+        };
+        assert_eq!(ttl("key"), RespFrame::Integer(-2));
+        backend.set("key".into(), BulkString::from("value").into());
+        assert_eq!(ttl("key"), RespFrame::Integer(-1));
+
+        let cmd = Expire {
+            key: "key".into(),
+            millis: 100_000,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(ttl("key"), RespFrame::Integer(100));
+        let cmd = PTtl(Ttl {
+            key: "key".into(),
+            millis: true,
+        });
+        assert!(matches!(cmd.execute(&backend), RespFrame::Integer(ms) if ms > 99_000));
+
+        assert_eq!(
+            Persist("key".into()).execute(&backend),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(ttl("key"), RespFrame::Integer(-1));
+
+        let cmd = Expire {
+            key: "key".into(),
+            millis: -1,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(ttl("key"), RespFrame::Integer(-2));
+        let cmd = Expire {
+            key: "key".into(),
+            millis: 1000,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+    }
+
+    #[test]
+    fn test_pexpireat_cmd() -> Result<()> {
+        let backend = Backend::with_clock(Arc::new(MockClock::new(Duration::from_secs(1_000))));
+        backend.set("key".into(), BulkString::from("value").into());
+        let cmd = Expire {
+            key: "key".into(),
+            millis: 100_000,
+        };
+        let RespFrame::Array(propagated) = cmd.to_pexpireat(&backend) else {
+            panic!("expected an array");
+        };
+        let cmd = PExpireAt::try_from(propagated)?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(backend.ttl(b"key"), Some(Some(100_000)));
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$9\r\npexpireat\r\n$3\r\nkey\r\n$1\r\n1\r\n");
+        let cmd = PExpireAt::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(backend.ttl(b"key"), None);
+        Ok(())
+    }
+}
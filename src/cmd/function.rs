@@ -0,0 +1,273 @@
+use super::{
+    extract_args, extract_string, is_keyword,
+    scripting::{run_command, ScriptCall},
+    unless_busy, validate_command, CommandError, CommandExecutor, RESP_OK,
+};
+use crate::{
+    backend::glob_match, Backend, BulkString, FunctionCall, RespArray, RespFrame, RespNull,
+    SimpleError,
+};
+use tokio::task;
+
+#[derive(Debug)]
+pub struct FunctionLoad {
+    name: String,
+    replace: bool,
+}
+
+#[derive(Debug)]
+pub struct FunctionDelete(String);
+
+#[derive(Debug)]
+pub struct FunctionList(Option<String>);
+
+#[derive(Debug)]
+pub struct FCall {
+    function: String,
+    call: ScriptCall,
+}
+
+impl CommandExecutor for FunctionLoad {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.load_library(&self.name, self.replace) {
+            Ok(()) => BulkString::from(self.name).into(),
+            Err(e) => SimpleError::new(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for FunctionDelete {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.delete_library(&self.0) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => SimpleError::new(e).into(),
+        }
+    }
+}
+
+impl CommandExecutor for FunctionList {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let libraries: Vec<RespFrame> = backend
+            .loaded_libraries()
+            .iter()
+            .filter(|library| match &self.0 {
+                Some(pattern) => glob_match(pattern.as_bytes(), library.name().as_bytes()),
+                None => true,
+            })
+            .map(|library| {
+                let functions: Vec<RespFrame> = library
+                    .function_names()
+                    .map(|name| {
+                        RespArray::new([
+                            BulkString::from("name").into(),
+                            BulkString::from(name.to_string()).into(),
+                            BulkString::from("description").into(),
+                            RespFrame::Null(RespNull),
+                            BulkString::from("flags").into(),
+                            RespArray::new(vec![]).into(),
+                        ])
+                        .into()
+                    })
+                    .collect();
+                RespArray::new([
+                    BulkString::from("library_name").into(),
+                    BulkString::from(library.name().to_string()).into(),
+                    BulkString::from("engine").into(),
+                    BulkString::from("RUST").into(),
+                    BulkString::from("functions").into(),
+                    RespArray::new(functions).into(),
+                ])
+                .into()
+            })
+            .collect();
+        RespArray::new(libraries).into()
+    }
+}
+
+impl CommandExecutor for FCall {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let runner = |args| run_command(backend, args);
+        let call = FunctionCall::new(&self.call.keys, &self.call.args, &runner);
+        match backend.call_function(&self.function, &call) {
+            Some(reply) => reply,
+            None => SimpleError::new("ERR Function not found").into(),
+        }
+    }
+}
+
+impl FCall {
+    /// Runs the function with no other command in between, as the commands of a transaction.
+    pub async fn execute_atomic(self, backend: &Backend) -> RespFrame {
+        backend.writes_unpaused().await;
+        match unless_busy(backend, backend.transaction_lock()).await {
+            Ok(_lock) => task::block_in_place(|| self.execute(backend)),
+            Err(busy) => busy,
+        }
+    }
+}
+
+// [REPLACE] library-name
+impl TryFrom<RespArray> for FunctionLoad {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["function", "load"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0;
+        let replace = args.first().is_some_and(|arg| is_keyword(arg, "replace"));
+        if replace {
+            args.remove(0);
+        }
+        let mut args = args.into_iter();
+        match (args.next(), args.next()) {
+            (Some(name), None) => Ok(FunctionLoad {
+                name: extract_string(name)?,
+                replace,
+            }),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "function load takes a library name".to_string(),
+            )),
+        }
+    }
+}
+
+// library-name
+impl TryFrom<RespArray> for FunctionDelete {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["function", "delete"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        match (args.next(), args.next()) {
+            (Some(name), None) => Ok(FunctionDelete(extract_string(name)?)),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "function delete takes a library name".to_string(),
+            )),
+        }
+    }
+}
+
+// [LIBRARYNAME library-name-pattern]
+impl TryFrom<RespArray> for FunctionList {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["function", "list"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (None, _, _) => Ok(FunctionList(None)),
+            (Some(option), Some(pattern), None) if is_keyword(&option, "libraryname") => {
+                Ok(FunctionList(Some(extract_string(pattern)?)))
+            }
+            _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+}
+
+// function numkeys [key [key ...]] [arg [arg ...]]
+impl TryFrom<RespArray> for FCall {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["fcall"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        let function = match args.next() {
+            Some(function) => extract_string(function)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have a function".to_string(),
+                ))
+            }
+        };
+        Ok(FCall {
+            function,
+            call: ScriptCall::try_from(args)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resp::RespDecoder, FunctionLibrary};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_function_cmds_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$8\r\nfunction\r\n$4\r\nload\r\n$7\r\nREPLACE\r\n$3\r\nlib\r\n",
+        );
+        let cmd = FunctionLoad::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.name, "lib");
+        assert!(cmd.replace);
+
+        buf.extend_from_slice(
+            b"*4\r\n$8\r\nfunction\r\n$4\r\nlist\r\n$11\r\nlibraryname\r\n$2\r\nl*\r\n",
+        );
+        let cmd = FunctionList::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0, Some("l*".to_string()));
+
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nfcall\r\n$2\r\nfn\r\n$1\r\n1\r\n$1\r\nk\r\n$1\r\na\r\n",
+        );
+        let cmd = FCall::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.function, "fn");
+        assert_eq!(cmd.call.keys, vec![BulkString::from("k")]);
+        assert_eq!(cmd.call.args, vec![BulkString::from("a")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_function_execute() {
+        let backend = Backend::new();
+        backend.register_library(FunctionLibrary::new("counters").function("incr", |call| {
+            let key = call.keys[0].as_slice();
+            let n = match call.call(&[b"get", key]) {
+                RespFrame::BulkString(n) => String::from_utf8_lossy(&n).parse().unwrap_or(0),
+                _ => 0,
+            } + 1;
+            call.call(&[b"set", key, n.to_string().as_bytes()]);
+            RespFrame::Integer(n)
+        }));
+        let fcall = || FCall {
+            function: "incr".to_string(),
+            call: ScriptCall {
+                keys: vec![BulkString::from("n")],
+                args: vec![],
+            },
+        };
+        assert_eq!(
+            fcall().execute(&backend),
+            SimpleError::new("ERR Function not found").into()
+        );
+
+        let load = FunctionLoad {
+            name: "counters".to_string(),
+            replace: false,
+        };
+        assert_eq!(load.execute(&backend), BulkString::from("counters").into());
+        assert_eq!(fcall().execute(&backend), RespFrame::Integer(1));
+        assert_eq!(fcall().execute(&backend), RespFrame::Integer(2));
+        assert_eq!(
+            backend.get(b"n").unwrap(),
+            Some(BulkString::from("2").into())
+        );
+
+        let RespFrame::Array(libraries) =
+            FunctionList(Some("count*".to_string())).execute(&backend)
+        else {
+            panic!("FUNCTION LIST replies with an array");
+        };
+        assert_eq!(libraries.len(), 1);
+
+        assert_eq!(
+            FunctionDelete("counters".to_string()).execute(&backend),
+            RESP_OK.clone()
+        );
+        assert_eq!(
+            FunctionList(None).execute(&backend),
+            RespArray::new(vec![]).into()
+        );
+    }
+}
@@ -0,0 +1,706 @@
+use super::{
+    extract_args, extract_bytes, extract_float, extract_integer, extract_string, is_keyword,
+    validate_command, CommandError, CommandExecutor,
+};
+use crate::{
+    backend::{geo, GeoShape, GeoUnit, WrongType, ZAddFlags, ZAddOutcome, ZSet},
+    Backend, BulkString, RespArray, RespFrame, RespNull, RespNullArray, SimpleError,
+};
+use bytes::Bytes;
+use derive_more::Deref;
+use std::cmp::Ordering;
+
+#[derive(Debug)]
+pub struct GeoAdd {
+    key: Bytes,
+    flags: ZAddFlags,
+    ch: bool,
+    members: Vec<(Bytes, f64)>,
+}
+
+impl CommandExecutor for GeoAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let outcomes = match backend.zadd(self.key, self.members, self.flags) {
+            Ok(outcomes) => outcomes,
+            Err(e) => return e.into(),
+        };
+        let count = outcomes
+            .iter()
+            .filter(|outcome| match outcome {
+                ZAddOutcome::Added(_) => true,
+                ZAddOutcome::Updated(_) => self.ch,
+                _ => false,
+            })
+            .count();
+        RespFrame::Integer(count as i64)
+    }
+}
+
+// key [NX | XX] [CH] longitude latitude member [longitude latitude member ...]
+impl TryFrom<RespArray> for GeoAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["geoadd"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = args.0.into_iter().peekable();
+        let key = match args.next() {
+            Some(key) => extract_bytes(key)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have a key".to_string(),
+                ))
+            }
+        };
+
+        let mut flags = ZAddFlags::default();
+        let mut ch = false;
+        while let Some(arg) = args.peek() {
+            if is_keyword(arg, "nx") {
+                flags.nx = true;
+            } else if is_keyword(arg, "xx") {
+                flags.xx = true;
+            } else if is_keyword(arg, "ch") {
+                ch = true;
+            } else {
+                break;
+            }
+            args.next();
+        }
+        if flags.nx && flags.xx {
+            return Err(CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+
+        let args = args.collect::<Vec<RespFrame>>();
+        if args.is_empty() || !args.len().is_multiple_of(3) {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let mut members = Vec::with_capacity(args.len() / 3);
+        let mut args = args.into_iter();
+        while let (Some(longitude), Some(latitude), Some(member)) =
+            (args.next(), args.next(), args.next())
+        {
+            let (longitude, latitude) = extract_position(longitude, latitude)?;
+            let score = geo::geohash_encode(longitude, latitude) as f64;
+            members.push((extract_bytes(member)?, score));
+        }
+        Ok(GeoAdd {
+            key,
+            flags,
+            ch,
+            members,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct GeoPos {
+    key: Bytes,
+    members: Vec<Bytes>,
+}
+
+impl CommandExecutor for GeoPos {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let positions = self
+            .members
+            .iter()
+            .map(|member| {
+                Ok(match backend.zscore(&self.key, member)? {
+                    Some(score) => {
+                        let (longitude, latitude) = geo::geohash_decode(score as u64);
+                        RespArray::new([
+                            BulkString::from(longitude.to_string()).into(),
+                            BulkString::from(latitude.to_string()).into(),
+                        ])
+                        .into()
+                    }
+                    None => RespFrame::NullArray(RespNullArray),
+                })
+            })
+            .collect::<Result<Vec<RespFrame>, WrongType>>();
+        positions.map_or_else(Into::into, |positions| RespArray::new(positions).into())
+    }
+}
+
+// key [member ...]
+impl TryFrom<RespArray> for GeoPos {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["geopos"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = Vec::<Bytes>::try_from(args)?.into_iter();
+        let key = args
+            .next()
+            .expect("at least one argument is checked by the conversion");
+        Ok(GeoPos {
+            key,
+            members: args.collect(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct GeoDist {
+    key: Bytes,
+    from: Bytes,
+    to: Bytes,
+    unit: GeoUnit,
+}
+
+impl CommandExecutor for GeoDist {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let scores = backend
+            .zscore(&self.key, &self.from)
+            .and_then(|from| Ok((from, backend.zscore(&self.key, &self.to)?)));
+        let (Some(from), Some(to)) = (match scores {
+            Ok(scores) => scores,
+            Err(e) => return e.into(),
+        }) else {
+            return RespFrame::Null(RespNull);
+        };
+        let distance = geo::geo_distance(
+            geo::geohash_decode(from as u64),
+            geo::geohash_decode(to as u64),
+        );
+        BulkString::from(format_distance(distance, self.unit)).into()
+    }
+}
+
+// key member1 member2 [M | KM | FT | MI]
+impl TryFrom<RespArray> for GeoDist {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["geodist"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = args.0.into_iter();
+        let (Some(key), Some(from), Some(to)) = (args.next(), args.next(), args.next()) else {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have a key and two members".to_string(),
+            ));
+        };
+        let unit = match (args.next(), args.next()) {
+            (None, None) => GeoUnit::default(),
+            (Some(unit), None) => extract_unit(unit)?,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(GeoDist {
+            key: extract_bytes(key)?,
+            from: extract_bytes(from)?,
+            to: extract_bytes(to)?,
+            unit,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum GeoOrigin {
+    Member(Bytes),
+    Position(f64, f64),
+}
+
+#[derive(Debug)]
+pub struct GeoQuery {
+    destination: Option<Bytes>,
+    key: Bytes,
+    origin: GeoOrigin,
+    shape: GeoShape,
+    unit: GeoUnit,
+    order: Option<Ordering>,
+    count: Option<usize>,
+    any: bool,
+    withcoord: bool,
+    withdist: bool,
+    withhash: bool,
+    storedist: bool,
+}
+
+// A member found by a search, with its distance from the center in meters.
+struct GeoMatch {
+    member: Bytes,
+    hash: u64,
+    position: (f64, f64),
+    distance: f64,
+}
+
+impl CommandExecutor for GeoQuery {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let center = match &self.origin {
+            GeoOrigin::Position(longitude, latitude) => (*longitude, *latitude),
+            GeoOrigin::Member(member) => match backend.zscore(&self.key, member) {
+                Ok(Some(score)) => geo::geohash_decode(score as u64),
+                Ok(None) => {
+                    return SimpleError::new("ERR could not decode requested zset member").into()
+                }
+                Err(e) => return e.into(),
+            },
+        };
+
+        // members are checked one by one; sorted sets here are small enough for a full scan
+        let candidates = match backend.zrange(&self.key, 0, -1, false) {
+            Ok(members) => members.into_iter(),
+            Err(e) => return e.into(),
+        };
+        let found = candidates.filter_map(|(member, score)| {
+            let hash = score as u64;
+            let position = geo::geohash_decode(hash);
+            let distance = self.shape.distance_within(center, position)?;
+            Some(GeoMatch {
+                member,
+                hash,
+                position,
+                distance,
+            })
+        });
+        let mut matches = match (self.count, self.any) {
+            (Some(count), true) => found.take(count).collect::<Vec<_>>(),
+            _ => found.collect::<Vec<_>>(),
+        };
+        // COUNT without ANY needs the closest members, so it implies ascending order
+        let order = match (self.order, self.count, self.any) {
+            (None, Some(_), false) => Some(Ordering::Less),
+            (order, _, _) => order,
+        };
+        if let Some(order) = order {
+            matches.sort_by(|a, b| {
+                let ordering = a.distance.total_cmp(&b.distance);
+                if order == Ordering::Less {
+                    ordering
+                } else {
+                    ordering.reverse()
+                }
+            });
+        }
+        if let Some(count) = self.count {
+            matches.truncate(count);
+        }
+
+        match self.destination {
+            Some(destination) => {
+                let zset = matches
+                    .into_iter()
+                    .map(|found| {
+                        let score = if self.storedist {
+                            found.distance / self.unit.to_meters()
+                        } else {
+                            found.hash as f64
+                        };
+                        (found.member, score)
+                    })
+                    .collect::<ZSet>();
+                RespFrame::Integer(backend.zstore(destination, zset) as i64)
+            }
+            None => {
+                let frames = matches
+                    .into_iter()
+                    .map(|found| self.match_to_frame(found))
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(frames).into()
+            }
+        }
+    }
+}
+
+impl GeoQuery {
+    fn match_to_frame(&self, found: GeoMatch) -> RespFrame {
+        let member = BulkString::new(found.member).into();
+        if !self.withdist && !self.withhash && !self.withcoord {
+            return member;
+        }
+        let mut frames = vec![member];
+        if self.withdist {
+            frames.push(BulkString::from(format_distance(found.distance, self.unit)).into());
+        }
+        if self.withhash {
+            frames.push(RespFrame::integer(found.hash));
+        }
+        if self.withcoord {
+            let (longitude, latitude) = found.position;
+            frames.push(
+                RespArray::new([
+                    BulkString::from(longitude.to_string()).into(),
+                    BulkString::from(latitude.to_string()).into(),
+                ])
+                .into(),
+            );
+        }
+        RespArray::new(frames).into()
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct GeoSearch(GeoQuery);
+
+impl CommandExecutor for GeoSearch {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.0.execute(backend)
+    }
+}
+
+impl TryFrom<RespArray> for GeoSearch {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["geosearch"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(parse_geo_query(args, false)?))
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct GeoSearchStore(GeoQuery);
+
+impl CommandExecutor for GeoSearchStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.0.execute(backend)
+    }
+}
+
+impl TryFrom<RespArray> for GeoSearchStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["geosearchstore"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(parse_geo_query(args, true)?))
+    }
+}
+
+// GEOSEARCH key <FROMMEMBER member | FROMLONLAT longitude latitude>
+//   <BYRADIUS radius unit | BYBOX width height unit> [ASC | DESC] [COUNT count [ANY]]
+//   [WITHCOORD] [WITHDIST] [WITHHASH]
+// GEOSEARCHSTORE destination source ... [STOREDIST] takes the same search options.
+fn parse_geo_query(args: RespArray, store: bool) -> Result<GeoQuery, CommandError> {
+    let keys = if store { 2 } else { 1 };
+    if args.len() < keys {
+        return Err(CommandError::InvalidCommandArguments(
+            "Command must have a key".to_string(),
+        ));
+    }
+    let mut args = args.0.into_iter().peekable();
+    let destination = match store {
+        true => args.next().map(extract_bytes).transpose()?,
+        false => None,
+    };
+    let key = args
+        .next()
+        .map(extract_bytes)
+        .transpose()?
+        .unwrap_or_default();
+
+    let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+    let mut origin = None;
+    let mut shape = None;
+    let mut query = GeoQuery {
+        destination,
+        key,
+        origin: GeoOrigin::Position(0.0, 0.0),
+        shape: GeoShape::Radius(0.0),
+        unit: GeoUnit::default(),
+        order: None,
+        count: None,
+        any: false,
+        withcoord: false,
+        withdist: false,
+        withhash: false,
+        storedist: false,
+    };
+    while let Some(arg) = args.next() {
+        let option = extract_string(arg)?.to_ascii_lowercase();
+        match option.as_str() {
+            "frommember" if origin.is_none() => {
+                let member = args.next().ok_or_else(syntax_error)?;
+                origin = Some(GeoOrigin::Member(extract_bytes(member)?));
+            }
+            "fromlonlat" if origin.is_none() => {
+                let (Some(longitude), Some(latitude)) = (args.next(), args.next()) else {
+                    return Err(syntax_error());
+                };
+                let (longitude, latitude) = extract_position(longitude, latitude)?;
+                origin = Some(GeoOrigin::Position(longitude, latitude));
+            }
+            "byradius" if shape.is_none() => {
+                let (Some(radius), Some(unit)) = (args.next(), args.next()) else {
+                    return Err(syntax_error());
+                };
+                let radius = extract_float(radius)?;
+                if radius < 0.0 {
+                    return Err(CommandError::InvalidArgument(
+                        "radius cannot be negative".to_string(),
+                    ));
+                }
+                query.unit = extract_unit(unit)?;
+                shape = Some(GeoShape::Radius(radius * query.unit.to_meters()));
+            }
+            "bybox" if shape.is_none() => {
+                let (Some(width), Some(height), Some(unit)) =
+                    (args.next(), args.next(), args.next())
+                else {
+                    return Err(syntax_error());
+                };
+                let (width, height) = (extract_float(width)?, extract_float(height)?);
+                if width < 0.0 || height < 0.0 {
+                    return Err(CommandError::InvalidArgument(
+                        "height or width cannot be negative".to_string(),
+                    ));
+                }
+                query.unit = extract_unit(unit)?;
+                let meters = query.unit.to_meters();
+                shape = Some(GeoShape::Box {
+                    width: width * meters,
+                    height: height * meters,
+                });
+            }
+            "asc" => query.order = Some(Ordering::Less),
+            "desc" => query.order = Some(Ordering::Greater),
+            "count" => {
+                let count = extract_integer(args.next().ok_or_else(syntax_error)?)?;
+                if count <= 0 {
+                    return Err(CommandError::InvalidArgument(
+                        "COUNT must be > 0".to_string(),
+                    ));
+                }
+                query.count = Some(count as usize);
+                if args.peek().is_some_and(|arg| is_keyword(arg, "any")) {
+                    args.next();
+                    query.any = true;
+                }
+            }
+            "withcoord" if !store => query.withcoord = true,
+            "withdist" if !store => query.withdist = true,
+            "withhash" if !store => query.withhash = true,
+            "storedist" if store => query.storedist = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+    match (origin, shape) {
+        (Some(origin), Some(shape)) => {
+            query.origin = origin;
+            query.shape = shape;
+            Ok(query)
+        }
+        (None, _) => Err(CommandError::InvalidArgument(
+            "exactly one of FROMMEMBER or FROMLONLAT can be specified".to_string(),
+        )),
+        (_, None) => Err(CommandError::InvalidArgument(
+            "exactly one of BYRADIUS and BYBOX can be specified".to_string(),
+        )),
+    }
+}
+
+fn extract_position(longitude: RespFrame, latitude: RespFrame) -> Result<(f64, f64), CommandError> {
+    let (longitude, latitude) = (extract_float(longitude)?, extract_float(latitude)?);
+    if !geo::is_valid_position(longitude, latitude) {
+        return Err(CommandError::InvalidArgument(format!(
+            "invalid longitude,latitude pair {:.6},{:.6}",
+            longitude, latitude
+        )));
+    }
+    Ok((longitude, latitude))
+}
+
+fn extract_unit(frame: RespFrame) -> Result<GeoUnit, CommandError> {
+    GeoUnit::parse(&extract_string(frame)?).ok_or_else(|| {
+        CommandError::InvalidArgument(
+            "unsupported unit provided. please use M, KM, FT, MI".to_string(),
+        )
+    })
+}
+
+fn format_distance(meters: f64, unit: GeoUnit) -> String {
+    format!("{:.4}", meters / unit.to_meters())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::RespDecoder;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_geoadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$6\r\ngeoadd\r\n$6\r\nSicily\r\n$2\r\nCH\r\n$9\r\n13.361389\r\n$9\r\n38.115556\r\n$7\r\nPalermo\r\n",
+        );
+        let cmd = GeoAdd::try_from(RespArray::decode(&mut buf)?)?;
+        assert!(cmd.ch);
+        assert_eq!(
+            cmd.members,
+            vec![(Bytes::from("Palermo"), 3479099956230698.0)]
+        );
+
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\ngeoadd\r\n$6\r\nSicily\r\n$1\r\n0\r\n$2\r\n90\r\n$4\r\nPole\r\n",
+        );
+        assert!(GeoAdd::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_geo_binary_members() -> Result<()> {
+        let backend = Backend::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\ngeoadd\r\n$6\r\nSicily\r\n$9\r\n13.361389\r\n$9\r\n38.115556\r\n$2\r\n\xff\xfe\r\n",
+        );
+        let cmd = GeoAdd::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        buf.extend_from_slice(b"*3\r\n$6\r\ngeopos\r\n$6\r\nSicily\r\n$2\r\n\xff\xfe\r\n");
+        let cmd = GeoPos::try_from(RespArray::decode(&mut buf)?)?;
+        let RespFrame::Array(positions) = cmd.execute(&backend) else {
+            panic!("expected an array");
+        };
+        assert!(matches!(&positions[0], RespFrame::Array(position) if position.len() == 2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_geosearch_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*11\r\n$9\r\ngeosearch\r\n$6\r\nSicily\r\n$10\r\nFROMLONLAT\r\n$2\r\n15\r\n$2\r\n37\r\n$8\r\nBYRADIUS\r\n$3\r\n200\r\n$2\r\nkm\r\n$5\r\nCOUNT\r\n$1\r\n1\r\n$8\r\nWITHDIST\r\n",
+        );
+        let cmd = GeoSearch::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.origin, GeoOrigin::Position(15.0, 37.0));
+        assert_eq!(cmd.shape, GeoShape::Radius(200_000.0));
+        assert_eq!(cmd.unit, GeoUnit::Kilometers);
+        assert_eq!(cmd.count, Some(1));
+        assert!(cmd.withdist);
+
+        buf.extend_from_slice(
+            b"*5\r\n$14\r\ngeosearchstore\r\n$3\r\ndst\r\n$6\r\nSicily\r\n$10\r\nFROMMEMBER\r\n$7\r\nPalermo\r\n",
+        );
+        assert!(GeoSearchStore::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_geosearch_cmds_execute() {
+        let backend = Backend::new();
+        let members = [
+            ("Palermo", 13.361389, 38.115556),
+            ("Catania", 15.087269, 37.502669),
+            ("edge1", 12.758489, 38.788135),
+            ("edge2", 17.241510, 38.788135),
+        ];
+        let members = members
+            .iter()
+            .map(|(member, longitude, latitude)| {
+                (
+                    Bytes::from(*member),
+                    geo::geohash_encode(*longitude, *latitude) as f64,
+                )
+            })
+            .collect();
+        backend
+            .zadd("Sicily".into(), members, ZAddFlags::default())
+            .unwrap();
+
+        let query = |destination: Option<&str>| GeoQuery {
+            destination: destination.map(|d| Bytes::copy_from_slice(d.as_bytes())),
+            key: "Sicily".into(),
+            origin: GeoOrigin::Position(15.0, 37.0),
+            shape: GeoShape::Box {
+                width: 400_000.0,
+                height: 400_000.0,
+            },
+            unit: GeoUnit::Kilometers,
+            order: Some(Ordering::Less),
+            count: None,
+            any: false,
+            withcoord: false,
+            withdist: true,
+            withhash: false,
+            storedist: true,
+        };
+        let RespFrame::Array(found) = GeoSearch(query(None)).execute(&backend) else {
+            panic!("expected an array");
+        };
+        assert_eq!(
+            found.0,
+            vec![
+                RespArray::new([
+                    BulkString::from("Catania").into(),
+                    BulkString::from("56.4413").into(),
+                ])
+                .into(),
+                RespArray::new([
+                    BulkString::from("Palermo").into(),
+                    BulkString::from("190.4424").into(),
+                ])
+                .into(),
+                RespArray::new([
+                    BulkString::from("edge2").into(),
+                    BulkString::from("279.7403").into(),
+                ])
+                .into(),
+                RespArray::new([
+                    BulkString::from("edge1").into(),
+                    BulkString::from("279.7405").into(),
+                ])
+                .into(),
+            ]
+        );
+
+        let cmd = GeoSearchStore(GeoQuery {
+            count: Some(2),
+            ..query(Some("nearby"))
+        });
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        let stored = backend.zrange(b"nearby", 0, -1, false).unwrap();
+        assert_eq!(stored[0].0, "Catania");
+        assert!((stored[0].1 - 56.4413).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_geo_cmds_execute() {
+        let backend = Backend::new();
+        let cmd = GeoAdd {
+            key: "Sicily".into(),
+            flags: ZAddFlags::default(),
+            ch: false,
+            members: vec![
+                (
+                    "Palermo".into(),
+                    geo::geohash_encode(13.361389, 38.115556) as f64,
+                ),
+                (
+                    "Catania".into(),
+                    geo::geohash_encode(15.087269, 37.502669) as f64,
+                ),
+            ],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = GeoDist {
+            key: "Sicily".into(),
+            from: "Palermo".into(),
+            to: "Catania".into(),
+            unit: GeoUnit::Kilometers,
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("166.2742").into());
+        let cmd = GeoDist {
+            key: "Sicily".into(),
+            from: "Palermo".into(),
+            to: "Agrigento".into(),
+            unit: GeoUnit::Meters,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd = GeoPos {
+            key: "Sicily".into(),
+            members: vec!["Palermo".into(), "Agrigento".into()],
+        };
+        let RespFrame::Array(positions) = cmd.execute(&backend) else {
+            panic!("expected an array");
+        };
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[1], RespFrame::NullArray(RespNullArray));
+    }
+}
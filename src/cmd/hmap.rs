@@ -1,21 +1,26 @@
 use derive_more::Deref;
 
 use super::{
-    extract_args, validate_command, CommandError, CommandExecutor, Hmap, KeyField, KeyFields,
+    extract_args, validate_command, CommandError, Hmap, KeyField, KeyFields, StorageExecutor,
     RESP_OK,
 };
-use crate::{Backend, BulkString, RespArray, RespFrame, RespNull};
+use crate::{BulkString, RespArray, RespFrame, RespNull, Storage};
+use bytes::Bytes;
 
 #[derive(Debug, Deref)]
 pub struct HSet(Hmap);
 
-impl CommandExecutor for HSet {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        let len = self.map.len();
+impl StorageExecutor for HSet {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        let mut added = 0;
         for v in self.0.map {
-            backend.hset(self.0.key.clone(), v.0, v.1);
+            match storage.hset(self.0.key.clone(), v.0, v.1) {
+                Ok(true) => added += 1,
+                Ok(false) => {}
+                Err(e) => return e.into(),
+            }
         }
-        RespFrame::Integer(len as i64)
+        RespFrame::Integer(added)
     }
 }
 
@@ -32,10 +37,12 @@ impl TryFrom<RespArray> for HSet {
 #[derive(Debug, Deref)]
 pub struct Hmset(Hmap);
 
-impl CommandExecutor for Hmset {
-    fn execute(self, backend: &Backend) -> RespFrame {
+impl StorageExecutor for Hmset {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
         for v in self.0.map {
-            backend.hset(self.0.key.clone(), v.0, v.1);
+            if let Err(e) = storage.hset(self.0.key.clone(), v.0, v.1) {
+                return e.into();
+            }
         }
         RESP_OK.clone()
     }
@@ -54,11 +61,12 @@ impl TryFrom<RespArray> for Hmset {
 #[derive(Debug, Deref)]
 pub struct HGet(KeyField);
 
-impl CommandExecutor for HGet {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        match backend.hget(&self.key, &self.field) {
-            Some(value) => value,
-            None => RespFrame::Null(RespNull),
+impl StorageExecutor for HGet {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        match storage.hget(&self.key, &self.field) {
+            Ok(Some(value)) => value,
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
         }
     }
 }
@@ -76,13 +84,14 @@ impl TryFrom<RespArray> for HGet {
 #[derive(Debug, Deref)]
 pub struct Hmget(KeyFields);
 
-impl CommandExecutor for Hmget {
-    fn execute(self, backend: &Backend) -> RespFrame {
+impl StorageExecutor for Hmget {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
         let mut data = Vec::with_capacity(self.fields.len());
         for field in self.fields.iter() {
-            match backend.hget(&self.key, field) {
-                Some(value) => data.push(value),
-                None => data.push(RespFrame::Null(RespNull)),
+            match storage.hget(&self.key, field) {
+                Ok(Some(value)) => data.push(value),
+                Ok(None) => data.push(RespFrame::Null(RespNull)),
+                Err(e) => return e.into(),
             }
         }
         RespArray::new(data).into()
@@ -102,12 +111,14 @@ impl TryFrom<RespArray> for Hmget {
 #[derive(Debug, Deref)]
 pub struct HDel(KeyFields);
 
-impl CommandExecutor for HDel {
-    fn execute(self, backend: &Backend) -> RespFrame {
+impl StorageExecutor for HDel {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
         let mut count = 0;
         for field in self.fields.iter() {
-            if backend.hdel(&self.key, field) {
-                count += 1;
+            match storage.hdel(&self.key, field) {
+                Ok(true) => count += 1,
+                Ok(false) => {}
+                Err(e) => return e.into(),
             }
         }
         RespFrame::Integer(count as i64)
@@ -126,20 +137,15 @@ impl TryFrom<RespArray> for HDel {
 
 #[derive(Debug)]
 pub struct HGetAll {
-    key: String,
+    key: Bytes,
     sort: bool,
 }
 
-impl CommandExecutor for HGetAll {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        let hmap = backend.hgetall(&self.key);
+impl StorageExecutor for HGetAll {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        let hmap = storage.hgetall(&self.key);
         match hmap {
-            Some(hmap) => {
-                let mut data = Vec::with_capacity(hmap.len());
-                for v in hmap.iter() {
-                    let key = v.key().to_owned();
-                    data.push((key, v.value().clone()));
-                }
+            Ok(Some(mut data)) => {
                 if self.sort {
                     data.sort_by(|a, b| a.0.cmp(&b.0));
                 }
@@ -150,7 +156,8 @@ impl CommandExecutor for HGetAll {
 
                 RespArray::new(ret).into()
             }
-            None => RespArray::new([]).into(),
+            Ok(None) => RespArray::new([]).into(),
+            Err(e) => e.into(),
         }
     }
 }
@@ -169,24 +176,20 @@ impl TryFrom<RespArray> for HGetAll {
 }
 
 #[derive(Debug, Deref)]
-pub struct HKeys(String);
+pub struct HKeys(Bytes);
 
-impl CommandExecutor for HKeys {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        match backend.hgetall(&self) {
-            Some(hmap) => {
+impl StorageExecutor for HKeys {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        match storage.hgetall(&self) {
+            Ok(Some(hmap)) => {
                 let keys = hmap
-                    .iter()
-                    .map(|v| v.key().to_owned())
-                    .collect::<Vec<String>>();
-                RespArray::new(
-                    keys.into_iter()
-                        .map(|k| BulkString::new(k).into())
-                        .collect::<Vec<RespFrame>>(),
-                )
-                .into()
+                    .into_iter()
+                    .map(|(k, _)| BulkString::new(k).into())
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(keys).into()
             }
-            None => RespArray::new([]).into(),
+            Ok(None) => RespArray::new([]).into(),
+            Err(e) => e.into(),
         }
     }
 }
@@ -204,7 +207,7 @@ impl TryFrom<RespArray> for HKeys {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{resp::RespDecoder, BulkString};
+    use crate::{cmd::CommandExecutor, resp::RespDecoder, Backend, BulkString};
     use anyhow::Result;
     use bytes::BytesMut;
 
@@ -241,6 +244,26 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_binary_field_cmd_execute() -> Result<()> {
+        let backend = Backend::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$4\r\nhset\r\n$1\r\nh\r\n$2\r\n\xff\xfe\r\n$1\r\nv\r\n");
+        let cmd = HSet::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        buf.extend_from_slice(b"*3\r\n$4\r\nhget\r\n$1\r\nh\r\n$2\r\n\xff\xfe\r\n");
+        let cmd = HGet::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.execute(&backend), BulkString::from("v").into());
+
+        let cmd = HKeys("h".into());
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([BulkString::new(Bytes::from_static(b"\xff\xfe")).into()]).into()
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_hgetall_command() -> Result<()> {
         let mut buf = BytesMut::new();
@@ -256,13 +279,13 @@ mod tests {
     fn test_hgetall_cmd_execute() {
         let backend = Backend::new();
         let map = Hmap {
-            key: "family".to_string(),
+            key: "family".into(),
             map: vec![
                 (
-                    "name".to_string(),
+                    Bytes::from("name"),
                     RespFrame::BulkString(BulkString::new("Vic")),
                 ),
-                ("age".to_string(), RespFrame::Integer(10.into())),
+                (Bytes::from("age"), RespFrame::Integer(10.into())),
             ],
         };
         let cmd = HSet(map);
@@ -270,7 +293,7 @@ mod tests {
         assert_eq!(resp, RespFrame::Integer(2));
 
         let cmd = HGetAll {
-            key: "family".to_string(),
+            key: "family".into(),
             sort: true,
         };
         let resp = cmd.execute(&backend);
@@ -278,7 +301,8 @@ mod tests {
             resp,
             RespArray::new([
                 BulkString::from("age").into(),
-                RespFrame::Integer(10),
+                // values read back as bulk strings, whatever frame they were written as
+                BulkString::from("10").into(),
                 BulkString::from("name").into(),
                 RespFrame::BulkString("Vic".into()),
             ])
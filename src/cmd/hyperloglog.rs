@@ -0,0 +1,164 @@
+use super::{extract_args, validate_command, CommandError, CommandExecutor, RESP_OK};
+use crate::{Backend, RespArray, RespFrame, SimpleError};
+use bytes::Bytes;
+use derive_more::Deref;
+
+#[derive(Debug)]
+pub struct PfAdd {
+    key: Bytes,
+    elements: Vec<Bytes>,
+}
+
+impl CommandExecutor for PfAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.pfadd(self.key, &self.elements) {
+            Ok(Some(changed)) => RespFrame::Integer(changed as i64),
+            Ok(None) => invalid_hyperloglog(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// key [element ...]
+impl TryFrom<RespArray> for PfAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["pfadd"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = Vec::<Bytes>::try_from(args)?.into_iter();
+        let key = args
+            .next()
+            .expect("at least one argument is checked by the conversion");
+        Ok(PfAdd {
+            key,
+            elements: args.collect(),
+        })
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct PfCount(Vec<Bytes>);
+
+impl CommandExecutor for PfCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.pfcount(&self) {
+            Ok(Some(count)) => RespFrame::integer(count),
+            Ok(None) => invalid_hyperloglog(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for PfCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["pfcount"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+#[derive(Debug)]
+pub struct PfMerge {
+    destination: Bytes,
+    sources: Vec<Bytes>,
+}
+
+impl CommandExecutor for PfMerge {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.pfmerge(self.destination, &self.sources) {
+            Ok(Some(())) => RESP_OK.clone(),
+            Ok(None) => invalid_hyperloglog(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// destkey [sourcekey ...]
+impl TryFrom<RespArray> for PfMerge {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["pfmerge"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = Vec::<Bytes>::try_from(args)?.into_iter();
+        let destination = args
+            .next()
+            .expect("at least one argument is checked by the conversion");
+        Ok(PfMerge {
+            destination,
+            sources: args.collect(),
+        })
+    }
+}
+
+fn invalid_hyperloglog() -> RespFrame {
+    SimpleError::new("WRONGTYPE Key is not a valid HyperLogLog string value.").into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resp::RespDecoder, BulkString};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_pfadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\npfadd\r\n$3\r\nhll\r\n$1\r\na\r\n$1\r\nb\r\n");
+        let cmd = PfAdd::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.key, "hll");
+        assert_eq!(cmd.elements, vec!["a", "b"]);
+
+        // elements are hashed as they are, whether they are UTF-8 or not
+        buf.extend_from_slice(b"*3\r\n$5\r\npfadd\r\n$3\r\nhll\r\n$2\r\n\xff\xfe\r\n");
+        let cmd = PfAdd::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.elements, vec![Bytes::from_static(b"\xff\xfe")]);
+
+        buf.extend_from_slice(b"*1\r\n$5\r\npfadd\r\n");
+        assert!(PfAdd::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hyperloglog_cmds_execute() {
+        let backend = Backend::new();
+        let cmd = PfAdd {
+            key: "first".into(),
+            elements: vec!["a".into(), "b".into(), "c".into()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd = PfAdd {
+            key: "first".into(),
+            elements: vec!["a".into()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        let cmd = PfAdd {
+            key: "second".into(),
+            elements: vec!["c".into(), "d".into()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = PfCount(vec!["first".into(), "second".into(), "missing".into()]);
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(4));
+
+        let cmd = PfMerge {
+            destination: "merged".into(),
+            sources: vec!["first".into(), "second".into()],
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(
+            PfCount(vec!["merged".into()]).execute(&backend),
+            RespFrame::Integer(4)
+        );
+
+        backend.set("plain".into(), BulkString::from("value").into());
+        assert_eq!(
+            PfCount(vec!["plain".into()]).execute(&backend),
+            invalid_hyperloglog()
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use super::{extract_args, validate_command, CommandError, CommandExecutor};
+use crate::{Backend, BulkString, RespArray, RespFrame};
+
+// A section of the INFO reply, with the function listing its fields.
+struct InfoSection {
+    name: &'static str,
+    fields: fn(&Backend) -> Vec<(String, String)>,
+}
+
+const INFO_SECTIONS: &[InfoSection] = &[
+    InfoSection {
+        name: "Persistence",
+        fields: persistence,
+    },
+    InfoSection {
+        name: "Replication",
+        fields: replication,
+    },
+    InfoSection {
+        name: "Cluster",
+        fields: |backend| named(vec![("cluster_enabled", flag(backend.cluster_enabled()))]),
+    },
+    InfoSection {
+        name: "Shards",
+        fields: shards,
+    },
+];
+
+#[derive(Debug)]
+pub struct Info(Option<String>);
+
+impl CommandExecutor for Info {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let requested = self.0.as_deref().unwrap_or("default");
+        let all = ["all", "default", "everything"].contains(&requested);
+        let sections = INFO_SECTIONS
+            .iter()
+            .filter(|section| all || section.name.eq_ignore_ascii_case(requested));
+        let mut info = String::new();
+        for section in sections {
+            if !info.is_empty() {
+                info.push_str("\r\n");
+            }
+            info.push_str(&format!("# {}\r\n", section.name));
+            for (name, value) in (section.fields)(backend) {
+                info.push_str(&format!("{}:{}\r\n", name, value));
+            }
+        }
+        BulkString::from(info).into()
+    }
+}
+
+// [section]
+impl TryFrom<RespArray> for Info {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["info"];
+        validate_command(&value, &cmd_names)?;
+        match value.len() - cmd_names.len() {
+            0 => Ok(Info(None)),
+            1 => {
+                let section: String = extract_args(value, cmd_names.len())?.try_into()?;
+                Ok(Info(Some(section.to_ascii_lowercase())))
+            }
+            _ => Err(CommandError::InvalidCommandArguments(
+                "info takes at most one section".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Time;
+
+impl CommandExecutor for Time {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let now = backend.now();
+        RespArray::new([
+            BulkString::from(now.as_secs().to_string()).into(),
+            BulkString::from(now.subsec_micros().to_string()).into(),
+        ])
+        .into()
+    }
+}
+
+impl TryFrom<RespArray> for Time {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["time"];
+        validate_command(&value, &cmd_names)?;
+        if value.len() != cmd_names.len() {
+            return Err(CommandError::InvalidCommandArguments(
+                "time takes no arguments".to_string(),
+            ));
+        }
+        Ok(Time)
+    }
+}
+
+// The shards of the selected database, a line each as the keyspace section lists databases.
+fn shards(backend: &Backend) -> Vec<(String, String)> {
+    let mut fields = vec![(
+        "keyspace_shards".to_string(),
+        backend.keyspace_shards().to_string(),
+    )];
+    for (index, stats) in backend.shard_stats().iter().enumerate() {
+        fields.push((
+            format!("shard{}", index),
+            format!(
+                "keys={},accesses={},writes={},contended={}",
+                stats.keys, stats.accesses, stats.writes, stats.contended
+            ),
+        ));
+    }
+    fields
+}
+
+fn persistence(backend: &Backend) -> Vec<(String, String)> {
+    let rdb = backend.save_stats();
+    let aof = backend.aof_stats();
+    let mut fields = vec![
+        (
+            "rdb_changes_since_last_save",
+            rdb.changes_since_last_save.to_string(),
+        ),
+        ("rdb_bgsave_in_progress", flag(rdb.bgsave_in_progress)),
+        ("rdb_last_save_time", rdb.last_save_time.to_string()),
+        ("rdb_last_bgsave_status", status(rdb.last_bgsave_ok)),
+        (
+            "rdb_last_bgsave_time_sec",
+            optional(rdb.last_bgsave_time_sec),
+        ),
+        (
+            "rdb_current_bgsave_time_sec",
+            optional(rdb.current_bgsave_time_sec),
+        ),
+        ("aof_enabled", flag(aof.enabled)),
+        ("aof_rewrite_in_progress", flag(aof.rewrite_in_progress)),
+        (
+            "aof_last_rewrite_time_sec",
+            optional(aof.last_rewrite_time_sec),
+        ),
+        (
+            "aof_current_rewrite_time_sec",
+            optional(aof.current_rewrite_time_sec),
+        ),
+        ("aof_last_bgrewrite_status", status(aof.last_rewrite_ok)),
+        ("aof_last_write_status", status(aof.last_write_ok)),
+    ];
+    if aof.enabled {
+        fields.extend([
+            ("aof_current_size", aof.current_size.to_string()),
+            ("aof_buffer_length", aof.buffer_length.to_string()),
+            ("aof_pending_bio_fsync", flag(aof.fsync_in_progress)),
+            ("aof_delayed_fsync", aof.delayed_fsync.to_string()),
+        ]);
+    }
+    named(fields)
+}
+
+fn replication(backend: &Backend) -> Vec<(String, String)> {
+    let mut fields = vec![];
+    match backend.master() {
+        Some(master) => fields.extend([
+            ("role", "slave".to_string()),
+            ("master_host", master.host),
+            ("master_port", master.port.to_string()),
+            ("master_link_status", link_status(backend.master_link_up())),
+            ("slave_repl_offset", backend.repl_offset().to_string()),
+        ]),
+        None => fields.push(("role", "master".to_string())),
+    }
+    let replicas = backend.replica_infos();
+    fields.push(("connected_slaves", replicas.len().to_string()));
+    let mut fields = named(fields);
+    for (i, replica) in replicas.iter().enumerate() {
+        let state = if replica.online {
+            "online"
+        } else {
+            "wait_bgsave"
+        };
+        fields.push((
+            format!("slave{}", i),
+            format!(
+                "ip={},port={},state={},offset={},lag={}",
+                replica.ip, replica.port, state, replica.ack_offset, replica.lag
+            ),
+        ));
+    }
+    let (replid2, second_offset) = backend.replid2();
+    fields.extend(named(vec![
+        (
+            "master_failover_state",
+            backend.failover_state().as_str().to_string(),
+        ),
+        ("master_replid", backend.replid()),
+        ("master_replid2", replid2),
+        ("master_repl_offset", backend.repl_offset().to_string()),
+        ("second_repl_offset", optional(second_offset)),
+    ]));
+    fields
+}
+
+fn named(fields: Vec<(&'static str, String)>) -> Vec<(String, String)> {
+    fields
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect()
+}
+
+fn link_status(up: bool) -> String {
+    if up { "up" } else { "down" }.to_string()
+}
+
+fn flag(value: bool) -> String {
+    (value as u8).to_string()
+}
+
+fn status(ok: bool) -> String {
+    if ok { "ok" } else { "err" }.to_string()
+}
+
+// -1 stands for an unset value
+fn optional(value: Option<u64>) -> String {
+    value.map_or("-1".to_string(), |value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resp::RespDecoder, MockClock};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::{sync::Arc, time::Duration};
+
+    #[test]
+    fn test_info_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$4\r\ninfo\r\n$11\r\nPersistence\r\n");
+        let cmd = Info::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0.as_deref(), Some("persistence"));
+
+        buf.extend_from_slice(b"*3\r\n$4\r\ninfo\r\n$1\r\na\r\n$1\r\nb\r\n");
+        assert!(Info::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_info_cmd_execute() {
+        let backend = Backend::new();
+        let RespFrame::BulkString(info) = Info(None).execute(&backend) else {
+            panic!("expected a bulk string");
+        };
+        let info = String::from_utf8(info.0.into()).unwrap();
+        assert!(info.starts_with("# Persistence\r\n"));
+        assert!(info.contains("aof_enabled:0\r\n"));
+        assert!(info.contains("rdb_bgsave_in_progress:0\r\n"));
+        assert!(info.contains("rdb_last_bgsave_time_sec:-1\r\n"));
+        let last_save = format!("rdb_last_save_time:{}\r\n", backend.lastsave());
+        assert!(info.contains(&last_save));
+        assert!(!info.contains("aof_current_size"));
+
+        let RespFrame::BulkString(info) = Info(Some("replication".into())).execute(&backend) else {
+            panic!("expected a bulk string");
+        };
+        let info = String::from_utf8(info.0.into()).unwrap();
+        assert!(info.starts_with("# Replication\r\nrole:master\r\nconnected_slaves:0\r\n"));
+
+        let info = Info(Some("server".into())).execute(&backend);
+        assert_eq!(info, BulkString::from("").into());
+    }
+
+    #[test]
+    fn test_info_shards() {
+        let backend = Backend::with_shards(1, 2);
+        backend.set("key".into(), BulkString::from("value").into());
+        let RespFrame::BulkString(info) = Info(Some("shards".into())).execute(&backend) else {
+            panic!("expected a bulk string");
+        };
+        let info = String::from_utf8(info.0.into()).unwrap();
+        assert!(info.starts_with("# Shards\r\nkeyspace_shards:2\r\nshard0:keys="));
+        assert!(info.contains("shard1:keys="));
+        assert!(info.contains("keys=1,accesses=1,writes=1,contended=0\r\n"));
+    }
+
+    #[test]
+    fn test_time_cmd() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$4\r\ntime\r\n$1\r\nx\r\n");
+        assert!(Time::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*1\r\n$4\r\nTIME\r\n");
+        let cmd = Time::try_from(RespArray::decode(&mut buf)?)?;
+        let now = Duration::from_micros(1_700_000_000_123_456);
+        let backend = Backend::with_clock(Arc::new(MockClock::new(now)));
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("1700000000").into(),
+                BulkString::from("123456").into(),
+            ])
+            .into()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use super::{extract_args, extract_string, validate_command, CommandError, CommandExecutor};
+use crate::{backend, Backend, BulkString, RespArray, RespFrame};
+use derive_more::Deref;
+
+#[derive(Debug)]
+pub struct LatencyLatest;
+
+#[derive(Debug, Deref)]
+pub struct LatencyHistory(String);
+
+#[derive(Debug)]
+pub struct LatencyReset(Vec<String>);
+
+impl CommandExecutor for LatencyLatest {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let events = backend
+            .latency_latest()
+            .into_iter()
+            .map(|backend::LatencyLatest { event, latest, max }| {
+                RespArray::new([
+                    BulkString::from(event).into(),
+                    RespFrame::Integer(latest.time as i64),
+                    RespFrame::Integer(latest.latency as i64),
+                    RespFrame::Integer(max as i64),
+                ])
+                .into()
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(events).into()
+    }
+}
+
+impl CommandExecutor for LatencyHistory {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let samples = backend
+            .latency_history(&self)
+            .into_iter()
+            .map(|sample| {
+                RespArray::new([
+                    RespFrame::Integer(sample.time as i64),
+                    RespFrame::Integer(sample.latency as i64),
+                ])
+                .into()
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(samples).into()
+    }
+}
+
+impl CommandExecutor for LatencyReset {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.latency_reset(&self.0) as i64)
+    }
+}
+
+impl TryFrom<RespArray> for LatencyLatest {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["latency", "latest"];
+        validate_command(&value, &cmd_names)?;
+        if value.len() != cmd_names.len() {
+            return Err(CommandError::InvalidCommandArguments(
+                "latency latest takes no arguments".to_string(),
+            ));
+        }
+        Ok(LatencyLatest)
+    }
+}
+
+// event
+impl TryFrom<RespArray> for LatencyHistory {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["latency", "history"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+// [event [event ...]]
+impl TryFrom<RespArray> for LatencyReset {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["latency", "reset"];
+        validate_command(&value, &cmd_names)?;
+        let events = extract_args(value, cmd_names.len())?
+            .0
+            .into_iter()
+            .map(extract_string)
+            .collect::<Result<_, _>>()?;
+        Ok(LatencyReset(events))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resp::RespDecoder, MockClock};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::{sync::Arc, time::Duration};
+
+    #[test]
+    fn test_latency_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$7\r\nlatency\r\n$7\r\nhistory\r\n$4\r\nfork\r\n");
+        let cmd = LatencyHistory::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.as_str(), "fork");
+
+        buf.extend_from_slice(b"*2\r\n$7\r\nlatency\r\n$7\r\nhistory\r\n");
+        assert!(LatencyHistory::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(
+            b"*4\r\n$7\r\nLATENCY\r\n$5\r\nRESET\r\n$4\r\nfork\r\n$7\r\ncommand\r\n",
+        );
+        let cmd = LatencyReset::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0, ["fork", "command"]);
+
+        buf.extend_from_slice(b"*3\r\n$7\r\nlatency\r\n$6\r\nlatest\r\n$1\r\nx\r\n");
+        assert!(LatencyLatest::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_latency_execute() {
+        let backend = Backend::with_clock(Arc::new(MockClock::new(Duration::from_secs(1_000))));
+        backend.set_latency_monitor_threshold(10);
+        backend.record_latency("fork", Duration::from_millis(20));
+        assert_eq!(
+            LatencyLatest.execute(&backend),
+            RespArray::new([RespArray::new([
+                BulkString::from("fork").into(),
+                RespFrame::Integer(1_000),
+                RespFrame::Integer(20),
+                RespFrame::Integer(20),
+            ])
+            .into()])
+            .into()
+        );
+        assert_eq!(
+            LatencyHistory("fork".into()).execute(&backend),
+            RespArray::new([
+                RespArray::new([RespFrame::Integer(1_000), RespFrame::Integer(20)]).into()
+            ])
+            .into()
+        );
+        assert_eq!(
+            LatencyReset(vec![]).execute(&backend),
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            LatencyHistory("fork".into()).execute(&backend),
+            RespArray::new(Vec::<RespFrame>::new()).into()
+        );
+    }
+}
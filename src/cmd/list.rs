@@ -0,0 +1,673 @@
+use super::{
+    extract_args, extract_bytes, extract_float, extract_integer, extract_string, is_keyword,
+    validate_command, CommandError, CommandExecutor, KeyValues, Propagated, StorageExecutor,
+};
+use crate::{
+    backend::ListDirection, Backend, BulkString, RespArray, RespFrame, RespNull, RespNullArray,
+    Storage,
+};
+use bytes::Bytes;
+use derive_more::Deref;
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[derive(Debug, Deref)]
+pub struct LPush(KeyValues);
+
+impl StorageExecutor for LPush {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        storage
+            .push(self.0.key, self.0.values, ListDirection::Left)
+            .map_or_else(Into::into, |len| RespFrame::Integer(len as i64))
+    }
+}
+
+impl TryFrom<RespArray> for LPush {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["lpush"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct RPush(KeyValues);
+
+impl StorageExecutor for RPush {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        storage
+            .push(self.0.key, self.0.values, ListDirection::Right)
+            .map_or_else(Into::into, |len| RespFrame::Integer(len as i64))
+    }
+}
+
+impl TryFrom<RespArray> for RPush {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["rpush"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct LLen(Bytes);
+
+impl StorageExecutor for LLen {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        storage
+            .llen(&self)
+            .map_or_else(Into::into, |len| RespFrame::Integer(len as i64))
+    }
+}
+
+impl TryFrom<RespArray> for LLen {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["llen"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+#[derive(Debug)]
+pub struct LRange {
+    key: Bytes,
+    start: i64,
+    stop: i64,
+}
+
+impl StorageExecutor for LRange {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        storage
+            .lrange(&self.key, self.start, self.stop)
+            .map_or_else(Into::into, |values| RespArray::new(values).into())
+    }
+}
+
+impl TryFrom<RespArray> for LRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["lrange"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        if args.len() != 3 {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have three arguments".to_string(),
+            ));
+        }
+        let mut args = args.0.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(start), Some(stop)) => Ok(LRange {
+                key: extract_bytes(key)?,
+                start: extract_integer(start)?,
+                stop: extract_integer(stop)?,
+            }),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "Invalid key or range".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LIndex {
+    key: Bytes,
+    index: i64,
+}
+
+impl CommandExecutor for LIndex {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lindex(&self.key, self.index) {
+            Ok(Some(value)) => value,
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// key index
+impl TryFrom<RespArray> for LIndex {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["lindex"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(index), None) => Ok(LIndex {
+                key: extract_bytes(key)?,
+                index: extract_integer(index)?,
+            }),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "Command must have two arguments".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LInsert {
+    key: Bytes,
+    after: bool,
+    pivot: RespFrame,
+    value: RespFrame,
+}
+
+impl CommandExecutor for LInsert {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.linsert(&self.key, &self.pivot, self.value, self.after) {
+            Ok(Some(len)) => RespFrame::Integer(len as i64),
+            Ok(None) => RespFrame::Integer(-1),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// key <BEFORE | AFTER> pivot element
+impl TryFrom<RespArray> for LInsert {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["linsert"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        match (
+            args.next(),
+            args.next(),
+            args.next(),
+            args.next(),
+            args.next(),
+        ) {
+            (Some(key), Some(position), Some(pivot), Some(value), None) => {
+                let after = if is_keyword(&position, "after") {
+                    true
+                } else if is_keyword(&position, "before") {
+                    false
+                } else {
+                    return Err(CommandError::InvalidArgument("syntax error".to_string()));
+                };
+                Ok(LInsert {
+                    key: extract_bytes(key)?,
+                    after,
+                    pivot,
+                    value,
+                })
+            }
+            _ => Err(CommandError::InvalidCommandArguments(
+                "Command must have four arguments".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ListMove {
+    source: Bytes,
+    destination: Bytes,
+    from: ListDirection,
+    to: ListDirection,
+}
+
+impl ListMove {
+    // A key of another type is a reply too, ending the wait of the blocking variant.
+    fn try_move(&self, backend: &Backend) -> Option<RespFrame> {
+        backend
+            .lmove(&self.source, self.destination.clone(), self.from, self.to)
+            .unwrap_or_else(|e| Some(e.into()))
+    }
+}
+
+impl TryFrom<RespArray> for ListMove {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() != 4 {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have four arguments".to_string(),
+            ));
+        }
+        let mut args = value.0.into_iter();
+        match (args.next(), args.next(), args.next(), args.next()) {
+            (Some(source), Some(destination), Some(from), Some(to)) => Ok(ListMove {
+                source: extract_bytes(source)?,
+                destination: extract_bytes(destination)?,
+                from: extract_direction(from)?,
+                to: extract_direction(to)?,
+            }),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "Invalid source or destination".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct LMove(ListMove);
+
+impl CommandExecutor for LMove {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.try_move(backend).unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl TryFrom<RespArray> for LMove {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["lmove"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+#[derive(Debug)]
+pub struct BLMove {
+    inner: ListMove,
+    timeout: Option<Duration>,
+}
+
+impl CommandExecutor for BLMove {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        LMove(self.inner).execute(backend)
+    }
+}
+
+impl BLMove {
+    pub async fn execute_blocking(
+        self,
+        backend: &Backend,
+        propagated: Option<Propagated>,
+    ) -> RespFrame {
+        block_on_lists(backend, self.timeout, propagated, || {
+            self.inner.try_move(backend)
+        })
+        .await
+        .unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl TryFrom<RespArray> for BLMove {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["blmove"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0;
+        let timeout = match args.pop() {
+            Some(timeout) => extract_timeout(timeout)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have a timeout".to_string(),
+                ))
+            }
+        };
+        Ok(Self {
+            inner: RespArray::new(args).try_into()?,
+            timeout,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ListPop {
+    keys: Vec<Bytes>,
+    direction: ListDirection,
+    count: usize,
+}
+
+impl ListPop {
+    // As with `ListMove::try_move`, a key of another type is a reply.
+    fn try_pop(&self, backend: &Backend) -> Option<RespFrame> {
+        self.keys
+            .iter()
+            .find_map(|key| match backend.pop(key, self.count, self.direction) {
+                Ok(values) => values.map(|values| {
+                    RespArray::new([
+                        BulkString::from(key.clone()).into(),
+                        RespArray::new(values).into(),
+                    ])
+                    .into()
+                }),
+                Err(e) => Some(e.into()),
+            })
+    }
+}
+
+// numkeys key [key ...] <LEFT | RIGHT> [COUNT count]
+impl TryFrom<RespArray> for ListPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = value.0.into_iter();
+        let numkeys = match args.next() {
+            Some(numkeys) => extract_integer(numkeys)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have numkeys".to_string(),
+                ))
+            }
+        };
+        if numkeys <= 0 {
+            return Err(CommandError::InvalidArgument(
+                "numkeys should be greater than 0".to_string(),
+            ));
+        }
+        let keys = args
+            .by_ref()
+            .take(numkeys as usize)
+            .map(extract_bytes)
+            .collect::<Result<Vec<Bytes>, CommandError>>()?;
+        if keys.len() != numkeys as usize {
+            return Err(CommandError::InvalidCommandArguments(
+                "Number of keys can't be greater than number of args".to_string(),
+            ));
+        }
+        let direction = match args.next() {
+            Some(direction) => extract_direction(direction)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have a direction".to_string(),
+                ))
+            }
+        };
+        let count = match (args.next(), args.next(), args.next()) {
+            (None, _, _) => 1,
+            (Some(option), Some(count), None) if is_keyword(&option, "count") => {
+                let count = extract_integer(count)?;
+                if count <= 0 {
+                    return Err(CommandError::InvalidArgument(
+                        "count should be greater than 0".to_string(),
+                    ));
+                }
+                count as usize
+            }
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(ListPop {
+            keys,
+            direction,
+            count,
+        })
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct LMPop(ListPop);
+
+impl CommandExecutor for LMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.try_pop(backend)
+            .unwrap_or(RespFrame::NullArray(RespNullArray))
+    }
+}
+
+impl TryFrom<RespArray> for LMPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["lmpop"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+#[derive(Debug)]
+pub struct BLMPop {
+    inner: ListPop,
+    timeout: Option<Duration>,
+}
+
+impl CommandExecutor for BLMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        LMPop(self.inner).execute(backend)
+    }
+}
+
+impl BLMPop {
+    pub async fn execute_blocking(
+        self,
+        backend: &Backend,
+        propagated: Option<Propagated>,
+    ) -> RespFrame {
+        block_on_lists(backend, self.timeout, propagated, || {
+            self.inner.try_pop(backend)
+        })
+        .await
+        .unwrap_or(RespFrame::NullArray(RespNullArray))
+    }
+}
+
+impl TryFrom<RespArray> for BLMPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["blmpop"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        let timeout = match args.next() {
+            Some(timeout) => extract_timeout(timeout)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have a timeout".to_string(),
+                ))
+            }
+        };
+        Ok(Self {
+            inner: RespArray::new(args.collect::<Vec<RespFrame>>()).try_into()?,
+            timeout,
+        })
+    }
+}
+
+// Retry `attempt` every time a list is pushed to, until it yields a reply or the timeout
+// elapses, when there is none.
+async fn block_on_lists<F>(
+    backend: &Backend,
+    timeout: Option<Duration>,
+    mut propagated: Option<Propagated>,
+    attempt: F,
+) -> Option<RespFrame>
+where
+    F: Fn() -> Option<RespFrame>,
+{
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        // register interest before trying so a push between the attempt and the wait isn't missed
+        let notified = backend.list_pushed();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        // the lock is released before waiting, so a transaction can run meanwhile
+        let attempted = {
+            let _lock = backend.command_lock().await;
+            let _order = propagated.as_ref().map(|_| backend.propagation_order());
+            let attempted = attempt();
+            // propagated once it pops, in the order it ran among the other writes
+            if let (Some(reply), Some(propagated)) = (&attempted, propagated.take()) {
+                propagated.propagate(backend, reply);
+            }
+            attempted
+        };
+        if attempted.is_some() {
+            return attempted;
+        }
+        match deadline {
+            Some(deadline) => {
+                if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                    return None;
+                }
+            }
+            None => notified.await,
+        }
+    }
+}
+
+fn extract_direction(frame: RespFrame) -> Result<ListDirection, CommandError> {
+    let direction = extract_string(frame)?;
+    if direction.eq_ignore_ascii_case("left") {
+        Ok(ListDirection::Left)
+    } else if direction.eq_ignore_ascii_case("right") {
+        Ok(ListDirection::Right)
+    } else {
+        Err(CommandError::InvalidArgument("syntax error".to_string()))
+    }
+}
+
+// A timeout of zero blocks indefinitely.
+fn extract_timeout(frame: RespFrame) -> Result<Option<Duration>, CommandError> {
+    let timeout = extract_float(frame).map_err(|_| {
+        CommandError::InvalidArgument("timeout is not a float or out of range".into())
+    })?;
+    if timeout < 0.0 {
+        return Err(CommandError::InvalidArgument(
+            "timeout is negative".to_string(),
+        ));
+    }
+    if timeout == 0.0 {
+        return Ok(None);
+    }
+    Duration::try_from_secs_f64(timeout)
+        .map(Some)
+        .map_err(|_| CommandError::InvalidArgument("timeout is out of range".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::RespDecoder;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_lmpop_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$5\r\nlmpop\r\n$1\r\n2\r\n$2\r\nq1\r\n$2\r\nq2\r\n$5\r\nRIGHT\r\n$5\r\nCOUNT\r\n$1\r\n3\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd = LMPop::try_from(frame)?;
+        assert_eq!(cmd.keys, vec!["q1".to_string(), "q2".to_string()]);
+        assert_eq!(cmd.direction, ListDirection::Right);
+        assert_eq!(cmd.count, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmpop_cmd_execute() {
+        let backend = Backend::new();
+        backend
+            .push(
+                "q2".into(),
+                vec![BulkString::from("a").into(), BulkString::from("b").into()],
+                ListDirection::Right,
+            )
+            .unwrap();
+        let cmd = LMPop(ListPop {
+            keys: vec!["q1".into(), "q2".into()],
+            direction: ListDirection::Left,
+            count: 5,
+        });
+        let resp = cmd.execute(&backend);
+        assert_eq!(
+            resp,
+            RespArray::new([
+                BulkString::from("q2").into(),
+                RespArray::new([BulkString::from("a").into(), BulkString::from("b").into()]).into(),
+            ])
+            .into()
+        );
+        assert_eq!(backend.llen(b"q2").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_linsert_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$7\r\nlinsert\r\n$4\r\nlist\r\n$5\r\nAFTER\r\n$1\r\na\r\n$1\r\nb\r\n",
+        );
+        let cmd = LInsert::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.key, "list");
+        assert!(cmd.after);
+        assert_eq!(cmd.pivot, BulkString::from("a").into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lindex_and_linsert_cmd_execute() {
+        let backend = Backend::new();
+        let insert = |pivot: &'static str, after| LInsert {
+            key: "list".into(),
+            after,
+            pivot: BulkString::from(pivot).into(),
+            value: BulkString::from("x").into(),
+        };
+        assert_eq!(insert("a", false).execute(&backend), RespFrame::Integer(0));
+        backend
+            .push(
+                "list".into(),
+                vec![BulkString::from("a").into(), BulkString::from("b").into()],
+                ListDirection::Right,
+            )
+            .unwrap();
+        assert_eq!(insert("b", false).execute(&backend), RespFrame::Integer(3));
+        assert_eq!(insert("c", true).execute(&backend), RespFrame::Integer(-1));
+
+        let index = |index| LIndex {
+            key: "list".into(),
+            index,
+        };
+        assert_eq!(index(1).execute(&backend), BulkString::from("x").into());
+        assert_eq!(index(-1).execute(&backend), BulkString::from("b").into());
+        assert_eq!(index(3).execute(&backend), RespFrame::Null(RespNull));
+        assert_eq!(index(-4).execute(&backend), RespFrame::Null(RespNull));
+    }
+
+    #[tokio::test]
+    async fn test_blmpop_wakes_up_on_push() {
+        let backend = Backend::new();
+        let cmd = BLMPop {
+            inner: ListPop {
+                keys: vec!["queue".into()],
+                direction: ListDirection::Left,
+                count: 1,
+            },
+            timeout: None,
+        };
+        let cloned = backend.clone();
+        let handle = tokio::spawn(async move { cmd.execute_blocking(&cloned, None).await });
+        tokio::task::yield_now().await;
+        backend
+            .push(
+                "queue".into(),
+                vec![BulkString::from("job").into()],
+                ListDirection::Right,
+            )
+            .unwrap();
+        let resp = handle.await.unwrap();
+        assert_eq!(
+            resp,
+            RespArray::new([
+                BulkString::from("queue").into(),
+                RespArray::new([BulkString::from("job").into()]).into(),
+            ])
+            .into()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_blmove_timeout() {
+        let backend = Backend::new();
+        let cmd = BLMove {
+            inner: ListMove {
+                source: "src".into(),
+                destination: "dst".into(),
+                from: ListDirection::Right,
+                to: ListDirection::Left,
+            },
+            timeout: Some(Duration::from_millis(10)),
+        };
+        assert_eq!(
+            cmd.execute_blocking(&backend, None).await,
+            RespFrame::Null(RespNull)
+        );
+    }
+}
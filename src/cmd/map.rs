@@ -1,13 +1,20 @@
-use super::{extract_args, validate_command, CommandError, CommandExecutor, KeyValue, RESP_OK};
-use crate::{Backend, RespArray, RespFrame, RespNull};
+use super::{
+    extract_args, extract_bytes, extract_integer, is_keyword, validate_command, CommandError,
+    CommandExecutor, KeyValue, StorageExecutor, RESP_OK,
+};
+use crate::{
+    backend::{IncrError, WrongType},
+    Backend, BulkString, RespArray, RespFrame, RespNull, SimpleError, SimpleString, Storage,
+};
+use bytes::Bytes;
 use derive_more::Deref;
 
 #[derive(Debug, Deref)]
 pub struct Set(KeyValue);
 
-impl CommandExecutor for Set {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        backend.set(self.0.key, self.0.value);
+impl StorageExecutor for Set {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        storage.set(self.0.key, self.0.value);
         RESP_OK.clone()
     }
 }
@@ -23,13 +30,14 @@ impl TryFrom<RespArray> for Set {
 }
 
 #[derive(Debug, Deref)]
-pub struct Get(String);
+pub struct Get(Bytes);
 
-impl CommandExecutor for Get {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        match backend.get(&self) {
-            Some(value) => value,
-            None => RespFrame::Null(RespNull),
+impl StorageExecutor for Get {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        match storage.get(&self) {
+            Ok(Some(value)) => value,
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
         }
     }
 }
@@ -45,13 +53,13 @@ impl TryFrom<RespArray> for Get {
 }
 
 #[derive(Debug, Deref)]
-pub struct Del(Vec<String>);
+pub struct Del(Vec<Bytes>);
 
-impl CommandExecutor for Del {
-    fn execute(self, backend: &Backend) -> RespFrame {
+impl StorageExecutor for Del {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
         let mut count = 0;
         for key in self.iter() {
-            if backend.del(key) {
+            if storage.del(key) {
                 count += 1;
             }
         }
@@ -69,6 +77,117 @@ impl TryFrom<RespArray> for Del {
     }
 }
 
+#[derive(Debug, Deref)]
+pub struct Type(Bytes);
+
+impl CommandExecutor for Type {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        SimpleString::new(backend.key_type(&self).unwrap_or("none")).into()
+    }
+}
+
+impl TryFrom<RespArray> for Type {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["type"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+/// INCR, DECR, INCRBY and DECRBY, the decrements being negative increments.
+#[derive(Debug)]
+pub struct IncrBy {
+    key: Bytes,
+    increment: i64,
+    // whether the result may go past 64 bits, replied to as a big number
+    promote: bool,
+}
+
+impl CommandExecutor for IncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.incr_by(self.key, self.increment, self.promote) {
+            Ok(n) => RespFrame::integer(n),
+            Err(IncrError::NotInteger) => {
+                SimpleError::new("ERR value is not an integer or out of range").into()
+            }
+            Err(IncrError::Overflow) => {
+                SimpleError::new("ERR increment or decrement would overflow").into()
+            }
+            Err(IncrError::WrongType) => WrongType.into(),
+        }
+    }
+}
+
+impl IncrBy {
+    /// Lets the result go past 64 bits, for a client that can take big numbers or for a write
+    /// replayed as the master or the AOF took it.
+    pub fn promoting(self, promote: bool) -> Self {
+        Self { promote, ..self }
+    }
+
+    // key for INCR and DECR, key increment for INCRBY and DECRBY
+    pub(super) fn parse(value: RespArray, name: &'static str) -> Result<Self, CommandError> {
+        let cmd_names = [name];
+        validate_command(&value, &cmd_names)?;
+        let by = name.ends_with("by");
+        if value.len() != cmd_names.len() + 1 + by as usize {
+            return Err(CommandError::InvalidCommandArguments(format!(
+                "{} takes a key{}",
+                name,
+                if by { " and an increment" } else { "" }
+            )));
+        }
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        let key = extract_bytes(args.next().expect("checked the arguments"))?;
+        let increment = match args.next() {
+            Some(increment) => extract_integer(increment)?,
+            None => 1,
+        };
+        let increment = match name.starts_with("decr") {
+            true => increment.checked_neg().ok_or_else(|| {
+                CommandError::InvalidArgument("decrement would overflow".to_string())
+            })?,
+            false => increment,
+        };
+        Ok(IncrBy {
+            key,
+            increment,
+            promote: false,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct FlushDb {
+    lazy: bool,
+}
+
+impl CommandExecutor for FlushDb {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend.flushdb(self.lazy);
+        RESP_OK.clone()
+    }
+}
+
+// [ASYNC | SYNC]
+impl TryFrom<RespArray> for FlushDb {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["flushdb"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let lazy = match args.first() {
+            None => false,
+            Some(mode) if args.len() == 1 && is_keyword(mode, "async") => true,
+            Some(mode) if args.len() == 1 && is_keyword(mode, "sync") => false,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(FlushDb { lazy })
+    }
+}
+
 #[derive(Debug, Deref)]
 pub struct Echo(String);
 
@@ -89,10 +208,51 @@ impl TryFrom<RespArray> for Echo {
     }
 }
 
+#[derive(Debug)]
+pub struct Ping(Option<String>);
+
+impl CommandExecutor for Ping {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        match self.0 {
+            Some(message) => RespFrame::BulkString(message.into()),
+            None => SimpleString::new("PONG").into(),
+        }
+    }
+}
+
+impl Ping {
+    /// The reply of a connection in subscribe mode, where it must look like a message.
+    pub fn execute_subscribed(self) -> RespFrame {
+        RespArray::new([
+            BulkString::from("pong").into(),
+            BulkString::from(self.0.unwrap_or_default()).into(),
+        ])
+        .into()
+    }
+}
+
+// [message]
+impl TryFrom<RespArray> for Ping {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["ping"];
+        validate_command(&value, &cmd_names)?;
+        match value.len() - cmd_names.len() {
+            0 => Ok(Ping(None)),
+            1 => Ok(Ping(Some(
+                extract_args(value, cmd_names.len())?.try_into()?,
+            ))),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "ping takes at most one message".to_string(),
+            )),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{resp::RespDecoder, BulkString};
+    use crate::resp::RespDecoder;
     use anyhow::Result;
     use bytes::BytesMut;
 
@@ -121,15 +281,139 @@ mod tests {
     fn test_set_and_get_cmd_execute() {
         let backend = Backend::new();
         let key_value = KeyValue {
-            key: "name".to_string(),
+            key: "name".into(),
             value: RespFrame::BulkString("victory".into()),
         };
         let cmd = Set(key_value);
         let resp = cmd.execute(&backend);
         assert_eq!(resp, RESP_OK.clone());
 
-        let cmd = Get("name".to_string());
+        let cmd = Get("name".into());
         let resp = cmd.execute(&backend);
         assert_eq!(resp, RespFrame::BulkString("victory".into()));
     }
+
+    #[test]
+    fn test_binary_key() -> Result<()> {
+        let backend = Backend::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$3\r\nset\r\n$3\r\n\xff\x00k\r\n$1\r\nv\r\n");
+        let set = Set::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(set.execute(&backend), RESP_OK.clone());
+
+        buf.extend_from_slice(b"*2\r\n$3\r\nget\r\n$3\r\n\xff\x00k\r\n");
+        let get = Get::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(get.0, &b"\xff\x00k"[..]);
+        assert_eq!(get.execute(&backend), BulkString::from("v").into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_del_and_flushdb_cmd_execute() -> Result<()> {
+        let backend = Backend::new();
+        backend.set("name".into(), BulkString::from("victory").into());
+        backend
+            .sadd("members".into(), BulkString::from("alice").into())
+            .unwrap();
+        let cmd = Del(vec!["name".into(), "members".into(), "missing".into()]);
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        backend.set("name".into(), BulkString::from("victory").into());
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$7\r\nflushdb\r\n$5\r\nASYNC\r\n");
+        let cmd = FlushDb::try_from(RespArray::decode(&mut buf)?)?;
+        assert!(cmd.lazy);
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(
+            Get("name".into()).execute(&backend),
+            RespFrame::Null(RespNull)
+        );
+
+        buf.extend_from_slice(b"*2\r\n$7\r\nflushdb\r\n$4\r\nlazy\r\n");
+        assert!(FlushDb::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_incr_by_cmd() -> Result<()> {
+        let backend = Backend::new();
+        let incr = |name, args: &[&str]| -> Result<IncrBy> {
+            let mut frames: Vec<RespFrame> = vec![BulkString::from(name).into()];
+            frames.extend(
+                args.iter()
+                    .map(|arg| BulkString::from(arg.to_string()).into()),
+            );
+            Ok(IncrBy::parse(RespArray::new(frames), name)?)
+        };
+        assert_eq!(incr("incr", &["n"])?.execute(&backend), 1.into());
+        assert_eq!(incr("incrby", &["n", "10"])?.execute(&backend), 11.into());
+        assert_eq!(incr("decr", &["n"])?.execute(&backend), 10.into());
+        assert_eq!(incr("decrby", &["n", "-5"])?.execute(&backend), 15.into());
+        assert!(incr("incr", &["n", "1"]).is_err());
+        assert!(incr("decrby", &["n", &i64::MIN.to_string()]).is_err());
+
+        let max = i64::MAX.to_string();
+        backend.set("max".into(), BulkString::from(max.clone()).into());
+        assert_eq!(
+            incr("incr", &["max"])?.execute(&backend),
+            SimpleError::new("ERR increment or decrement would overflow").into()
+        );
+        assert_eq!(
+            incr("incr", &["max"])?.promoting(true).execute(&backend),
+            RespFrame::integer(i64::MAX as u64 + 1)
+        );
+        assert_eq!(
+            incr("incr", &["max"])?.execute(&backend),
+            SimpleError::new("ERR value is not an integer or out of range").into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_type_and_wrong_type_cmd() {
+        let backend = Backend::new();
+        backend
+            .hset("hash".into(), "field".into(), BulkString::from("1").into())
+            .unwrap();
+        assert_eq!(
+            Type("hash".into()).execute(&backend),
+            SimpleString::new("hash").into()
+        );
+        assert_eq!(
+            Type("missing".into()).execute(&backend),
+            SimpleString::new("none").into()
+        );
+        let wrong_type =
+            SimpleError::new("WRONGTYPE Operation against a key holding the wrong kind of value");
+        assert_eq!(
+            Get("hash".into()).execute(&backend),
+            wrong_type.clone().into()
+        );
+        let incr = IncrBy {
+            key: "hash".into(),
+            increment: 1,
+            promote: false,
+        };
+        assert_eq!(incr.execute(&backend), wrong_type.into());
+    }
+
+    #[test]
+    fn test_ping_cmd() -> Result<()> {
+        let backend = Backend::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$4\r\nPING\r\n");
+        let cmd = Ping::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.execute(&backend), SimpleString::new("PONG").into());
+
+        buf.extend_from_slice(b"*2\r\n$4\r\nping\r\n$5\r\nhello\r\n");
+        let cmd = Ping::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.execute(&backend), RespFrame::BulkString("hello".into()));
+
+        let cmd = Ping(None);
+        assert_eq!(
+            cmd.execute_subscribed(),
+            RespArray::new([BulkString::from("pong").into(), BulkString::from("").into()]).into()
+        );
+        Ok(())
+    }
 }
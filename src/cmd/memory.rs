@@ -0,0 +1,275 @@
+use super::{
+    extract_args, extract_bytes, extract_integer, is_keyword, validate_command, CommandError,
+    CommandExecutor,
+};
+use crate::{backend, Backend, BulkString, RespArray, RespDouble, RespFrame, RespMap, RespNull};
+use bytes::Bytes;
+use std::collections::HashMap;
+
+// nested elements looked at by default, as in Redis
+const DEFAULT_SAMPLES: usize = 5;
+// below this much memory there is too little data for MEMORY DOCTOR to say anything useful
+const DOCTOR_MIN_MEMORY: usize = 5 * 1024 * 1024;
+
+#[derive(Debug)]
+pub struct MemoryUsage {
+    key: Bytes,
+    samples: usize,
+}
+
+impl CommandExecutor for MemoryUsage {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.memory_usage(&self.key, self.samples) {
+            Some(size) => RespFrame::integer(size),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+// key [SAMPLES count]
+impl TryFrom<RespArray> for MemoryUsage {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["memory", "usage"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        if args.len() != 1 && args.len() != 3 {
+            return Err(CommandError::InvalidCommandArguments(
+                "memory usage must have a key".to_string(),
+            ));
+        }
+        let mut args = args.0.into_iter();
+        let key = extract_bytes(args.next().expect("argument count checked above"))?;
+        let samples = match (args.next(), args.next()) {
+            (Some(option), Some(count)) if is_keyword(&option, "samples") => {
+                usize::try_from(extract_integer(count)?).map_err(|_| {
+                    CommandError::InvalidArgument(
+                        "value is out of range, must be positive".to_string(),
+                    )
+                })?
+            }
+            (None, None) => DEFAULT_SAMPLES,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(MemoryUsage { key, samples })
+    }
+}
+
+#[derive(Debug)]
+pub struct MemoryStats;
+
+impl CommandExecutor for MemoryStats {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let stats = backend.memory_stats();
+        let dataset = stats.used - stats.overhead;
+        let fields = [
+            ("peak.allocated", RespFrame::integer(stats.peak)),
+            ("total.allocated", RespFrame::integer(stats.used)),
+            ("maxmemory", RespFrame::integer(stats.maxmemory)),
+            ("overhead.total", RespFrame::integer(stats.overhead)),
+            ("keys.count", RespFrame::integer(stats.keys)),
+            (
+                "keys.bytes-per-key",
+                RespFrame::integer(stats.used.checked_div(stats.keys).unwrap_or(0)),
+            ),
+            ("dataset.bytes", RespFrame::integer(dataset)),
+            (
+                "dataset.percentage",
+                RespDouble::new(percentage(dataset, stats.used)).into(),
+            ),
+            (
+                "peak.percentage",
+                RespDouble::new(percentage(stats.used, stats.peak)).into(),
+            ),
+        ];
+        let datasets = stats
+            .datasets
+            .iter()
+            .map(|(name, bytes)| (*name, RespFrame::integer(*bytes)));
+        let mut map = HashMap::new();
+        for (name, value) in fields {
+            map.insert(BulkString::from(name).into(), value);
+        }
+        for (name, value) in datasets {
+            map.insert(BulkString::from(format!("dataset.{}", name)).into(), value);
+        }
+        RespMap::new(map).into()
+    }
+}
+
+impl TryFrom<RespArray> for MemoryStats {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["memory", "stats"];
+        validate_command(&value, &cmd_names)?;
+        if value.len() != cmd_names.len() {
+            return Err(CommandError::InvalidCommandArguments(
+                "memory stats takes no arguments".to_string(),
+            ));
+        }
+        Ok(MemoryStats)
+    }
+}
+
+#[derive(Debug)]
+pub struct MemoryDoctor;
+
+impl CommandExecutor for MemoryDoctor {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        BulkString::from(diagnose(&backend.memory_stats())).into()
+    }
+}
+
+impl TryFrom<RespArray> for MemoryDoctor {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["memory", "doctor"];
+        validate_command(&value, &cmd_names)?;
+        if value.len() != cmd_names.len() {
+            return Err(CommandError::InvalidCommandArguments(
+                "memory doctor takes no arguments".to_string(),
+            ));
+        }
+        Ok(MemoryDoctor)
+    }
+}
+
+fn percentage(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+// Turns the memory statistics into a short report of anything that looks wrong.
+fn diagnose(stats: &backend::MemoryStats) -> String {
+    if stats.used < DOCTOR_MIN_MEMORY {
+        return "This instance is empty or is using very little memory, there is not enough \
+                data to diagnose anything."
+            .to_string();
+    }
+    let mut issues = vec![];
+    if stats.peak * 2 > stats.used * 3 {
+        issues.push(format!(
+            " * Peak memory: in the past this instance used {:.0}% of the memory it uses now. \
+             Memory freed since then may not have been given back to the operating system.",
+            percentage(stats.peak, stats.used)
+        ));
+    }
+    if stats.maxmemory > 0 && stats.used * 10 >= stats.maxmemory * 9 {
+        issues.push(
+            " * Maxmemory: used memory is above 90% of maxmemory, so writes will soon evict keys \
+             or be refused depending on maxmemory-policy."
+                .to_string(),
+        );
+    }
+    if stats.overhead > stats.used - stats.overhead {
+        issues.push(format!(
+            " * High key overhead: keys take {} bytes of bookkeeping against {} bytes of data. \
+             Grouping many small values into hashes would save memory.",
+            stats.overhead,
+            stats.used - stats.overhead
+        ));
+    }
+    if issues.is_empty() {
+        "No memory issues found in this instance.".to_string()
+    } else {
+        format!(
+            "The following memory issues were found:\n\n{}\n",
+            issues.join("\n\n")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resp::RespDecoder, BulkString};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_memory_usage_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nmemory\r\n$5\r\nusage\r\n$3\r\nkey\r\n");
+        let cmd = MemoryUsage::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.key, "key");
+        assert_eq!(cmd.samples, DEFAULT_SAMPLES);
+
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\nmemory\r\n$5\r\nusage\r\n$3\r\nkey\r\n$7\r\nSAMPLES\r\n$1\r\n0\r\n",
+        );
+        let cmd = MemoryUsage::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.samples, 0);
+
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\nmemory\r\n$5\r\nusage\r\n$3\r\nkey\r\n$7\r\nSAMPLES\r\n$2\r\n-1\r\n",
+        );
+        assert!(MemoryUsage::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_usage_execute() {
+        let backend = Backend::new();
+        let cmd = MemoryUsage {
+            key: "key".into(),
+            samples: DEFAULT_SAMPLES,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        backend.set("key".into(), BulkString::new(vec![0; 100]).into());
+        let cmd = MemoryUsage {
+            key: "key".into(),
+            samples: DEFAULT_SAMPLES,
+        };
+        assert!(matches!(cmd.execute(&backend), RespFrame::Integer(n) if n > 100));
+    }
+
+    #[test]
+    fn test_memory_stats_execute() {
+        let backend = Backend::new();
+        backend.set("key".into(), BulkString::new(vec![0; 100]).into());
+        let RespFrame::Map(stats) = MemoryStats.execute(&backend) else {
+            panic!("MEMORY STATS must reply with a map");
+        };
+        assert_eq!(
+            stats.get(&BulkString::from("keys.count").into()),
+            Some(&RespFrame::Integer(1))
+        );
+        assert!(matches!(
+            stats.get(&BulkString::from("dataset.strings").into()),
+            Some(RespFrame::Integer(n)) if *n > 100
+        ));
+        assert_eq!(
+            stats.get(&BulkString::from("dataset.hashes").into()),
+            Some(&RespFrame::Integer(0))
+        );
+    }
+
+    #[test]
+    fn test_memory_doctor() {
+        let mut stats = backend::MemoryStats {
+            used: 1024,
+            peak: 1024,
+            maxmemory: 0,
+            overhead: 100,
+            keys: 1,
+            datasets: vec![("strings", 924)],
+        };
+        assert!(diagnose(&stats).contains("very little memory"));
+
+        stats.used = 10 * DOCTOR_MIN_MEMORY;
+        stats.peak = stats.used;
+        assert_eq!(diagnose(&stats), "No memory issues found in this instance.");
+
+        stats.peak = 2 * stats.used;
+        stats.maxmemory = stats.used;
+        stats.overhead = stats.used - 1;
+        let report = diagnose(&stats);
+        assert!(report.contains("Peak memory"));
+        assert!(report.contains("Maxmemory"));
+        assert!(report.contains("High key overhead"));
+    }
+}
@@ -1,28 +1,101 @@
+mod bitmap;
+mod client;
+mod cluster;
+mod command;
+mod config;
+mod db;
+mod debug;
 mod error;
+mod expire;
+mod function;
+mod geo;
 mod hmap;
+mod hyperloglog;
+mod info;
+mod latency;
+mod list;
 mod map;
+mod memory;
+mod object;
+mod persistence;
+mod plugin;
+mod pubsub;
+mod replication;
+mod scripting;
 mod set;
+mod stream;
+mod transaction;
+mod zset;
 
 use self::{
-    error::CommandError,
+    bitmap::{BitCount, BitField, GetBit, SetBit},
+    client::{
+        Auth, ClientGetName, ClientId, ClientInfo, ClientList, ClientPause, ClientReply,
+        ClientSetName, ClientTracking, ClientUnpause, Hello, Reset,
+    },
+    cluster::{
+        ClusterAddSlots, ClusterAddSlotsRange, ClusterDelSlots, ClusterInfo, ClusterKeySlot,
+        ClusterMyId, ClusterNodes, ClusterShards, ClusterSlots,
+    },
+    command::{CommandCount, CommandDocs, CommandInfo, Commands},
+    config::{ConfigGet, ConfigRewrite, ConfigSet},
+    db::{Select, SwapDb},
+    debug::{DebugChangeReplId, DebugJmap, DebugObject, DebugSetActiveExpire, DebugSleep},
+    expire::{Expire, PExpire, PExpireAt, PTtl, Persist, Ttl},
+    function::{FCall, FunctionDelete, FunctionList, FunctionLoad},
+    geo::{GeoAdd, GeoDist, GeoPos, GeoSearch, GeoSearchStore},
     hmap::{HDel, HGet, HGetAll, HKeys, HSet, Hmget, Hmset},
-    map::{Del, Echo, Get, Set},
+    hyperloglog::{PfAdd, PfCount, PfMerge},
+    info::{Info, Time},
+    latency::{LatencyHistory, LatencyLatest, LatencyReset},
+    list::{BLMPop, BLMove, LIndex, LInsert, LLen, LMPop, LMove, LPush, LRange, RPush},
+    map::{Del, Echo, FlushDb, Get, IncrBy, Ping, Set, Type},
+    memory::{MemoryDoctor, MemoryStats, MemoryUsage},
+    object::{ObjectEncoding, ObjectFreq},
+    persistence::{BgRewriteAof, BgSave, LastSave, Save, Shutdown},
+    plugin::{find_plugin, PluginCommand},
+    pubsub::{
+        PSubscribe, PUnsubscribe, PubSubChannels, PubSubNumPat, PubSubNumSub, PubSubShardChannels,
+        PubSubShardNumSub, Publish, SPublish, SSubscribe, SUnsubscribe, Subscribe, Unsubscribe,
+    },
+    replication::{Failover, ReplConf, ReplicaOf, Wait},
+    scripting::{Eval, EvalSha, ScriptKill, ScriptLoad},
     set::{Sadd, Sismember, Smembers, Srem},
+    stream::{XAdd, XDel, XLen, XRange, XRevRange, XTrim},
+    transaction::{Discard, Exec, Multi, Unwatch, Watch},
+    zset::{
+        ZAdd, ZCard, ZCount, ZDiff, ZDiffStore, ZIncrBy, ZInter, ZInterStore, ZLexCount, ZMPop,
+        ZRandMember, ZRange, ZRangeByLex, ZRangeByScore, ZRemRangeByLex, ZRemRangeByRank,
+        ZRemRangeByScore, ZRevRange, ZScan, ZScore, ZUnion, ZUnionStore,
+    },
 };
-use crate::{Backend, RespArray, RespFrame, SimpleString};
+use crate::{Backend, BulkString, RespArray, RespFrame, SimpleError, SimpleString, Storage};
+use bytes::Bytes;
+
+pub use self::config::{config_params, parse_config_file};
+pub use self::error::CommandError;
+pub use self::persistence::load_aof;
+pub use self::plugin::{register_plugin, CommandPlugin};
+pub use self::transaction::Transaction;
 use enum_dispatch::enum_dispatch;
 use lazy_static::lazy_static;
+use std::future::Future;
 
 lazy_static! {
     static ref RESP_OK: RespFrame = SimpleString::new("OK").into();
 }
 
+// What the passwords in commands are logged as.
+const REDACTED: &str = "<redacted>";
+
 #[enum_dispatch(CommandExecutor)]
 #[derive(Debug)]
 pub enum Command {
     Set(Set),
     Get(Get),
     Del(Del),
+    Type(Type),
+    IncrBy(IncrBy),
     HSet(HSet),
     Hmset(Hmset),
     HGet(HGet),
@@ -31,10 +104,198 @@ pub enum Command {
     HGetAll(HGetAll),
     HKeys(HKeys),
     Echo(Echo),
+    Ping(Ping),
     Sadd(Sadd),
     Sismember(Sismember),
     Smembers(Smembers),
     Srem(Srem),
+    LPush(LPush),
+    RPush(RPush),
+    LLen(LLen),
+    LRange(LRange),
+    LIndex(LIndex),
+    LInsert(LInsert),
+    LMove(LMove),
+    BLMove(BLMove),
+    LMPop(LMPop),
+    BLMPop(BLMPop),
+    ZAdd(ZAdd),
+    ZScore(ZScore),
+    ZCard(ZCard),
+    ZRange(ZRange),
+    ZRevRange(ZRevRange),
+    ZRangeByScore(ZRangeByScore),
+    ZRangeByLex(ZRangeByLex),
+    ZIncrBy(ZIncrBy),
+    ZCount(ZCount),
+    ZLexCount(ZLexCount),
+    ZUnionStore(ZUnionStore),
+    ZInterStore(ZInterStore),
+    ZDiffStore(ZDiffStore),
+    ZUnion(ZUnion),
+    ZInter(ZInter),
+    ZDiff(ZDiff),
+    ZRemRangeByRank(ZRemRangeByRank),
+    ZRemRangeByScore(ZRemRangeByScore),
+    ZRemRangeByLex(ZRemRangeByLex),
+    ZRandMember(ZRandMember),
+    ZScan(ZScan),
+    ZMPop(ZMPop),
+    XAdd(XAdd),
+    XLen(XLen),
+    XRange(XRange),
+    XRevRange(XRevRange),
+    XTrim(XTrim),
+    XDel(XDel),
+    SetBit(SetBit),
+    GetBit(GetBit),
+    BitCount(BitCount),
+    BitField(BitField),
+    PfAdd(PfAdd),
+    PfCount(PfCount),
+    PfMerge(PfMerge),
+    GeoAdd(GeoAdd),
+    GeoPos(GeoPos),
+    GeoDist(GeoDist),
+    GeoSearch(GeoSearch),
+    GeoSearchStore(GeoSearchStore),
+    ConfigGet(ConfigGet),
+    ConfigSet(ConfigSet),
+    ConfigRewrite(ConfigRewrite),
+    Expire(Expire),
+    PExpire(PExpire),
+    PExpireAt(PExpireAt),
+    Ttl(Ttl),
+    PTtl(PTtl),
+    Persist(Persist),
+    ObjectFreq(ObjectFreq),
+    ObjectEncoding(ObjectEncoding),
+    MemoryUsage(MemoryUsage),
+    MemoryStats(MemoryStats),
+    MemoryDoctor(MemoryDoctor),
+    LatencyLatest(LatencyLatest),
+    LatencyHistory(LatencyHistory),
+    LatencyReset(LatencyReset),
+    DebugSleep(DebugSleep),
+    DebugJmap(DebugJmap),
+    DebugObject(DebugObject),
+    DebugSetActiveExpire(DebugSetActiveExpire),
+    DebugChangeReplId(DebugChangeReplId),
+    Save(Save),
+    BgSave(BgSave),
+    LastSave(LastSave),
+    BgRewriteAof(BgRewriteAof),
+    Shutdown(Shutdown),
+    ReplicaOf(ReplicaOf),
+    ReplConf(ReplConf),
+    Wait(Wait),
+    Failover(Failover),
+    ClusterKeySlot(ClusterKeySlot),
+    ClusterAddSlots(ClusterAddSlots),
+    ClusterAddSlotsRange(ClusterAddSlotsRange),
+    ClusterDelSlots(ClusterDelSlots),
+    ClusterMyId(ClusterMyId),
+    ClusterInfo(ClusterInfo),
+    ClusterSlots(ClusterSlots),
+    ClusterShards(ClusterShards),
+    ClusterNodes(ClusterNodes),
+    Subscribe(Subscribe),
+    Unsubscribe(Unsubscribe),
+    PSubscribe(PSubscribe),
+    PUnsubscribe(PUnsubscribe),
+    Publish(Publish),
+    PubSubChannels(PubSubChannels),
+    PubSubNumSub(PubSubNumSub),
+    PubSubNumPat(PubSubNumPat),
+    SSubscribe(SSubscribe),
+    SUnsubscribe(SUnsubscribe),
+    SPublish(SPublish),
+    PubSubShardChannels(PubSubShardChannels),
+    PubSubShardNumSub(PubSubShardNumSub),
+    ClientTracking(ClientTracking),
+    ClientSetName(ClientSetName),
+    ClientGetName(ClientGetName),
+    ClientId(ClientId),
+    ClientInfo(ClientInfo),
+    ClientList(ClientList),
+    ClientPause(ClientPause),
+    ClientUnpause(ClientUnpause),
+    ClientReply(ClientReply),
+    Reset(Reset),
+    Hello(Hello),
+    Auth(Auth),
+    Multi(Multi),
+    Exec(Exec),
+    Discard(Discard),
+    Watch(Watch),
+    Unwatch(Unwatch),
+    Eval(Eval),
+    EvalSha(EvalSha),
+    ScriptLoad(ScriptLoad),
+    ScriptKill(ScriptKill),
+    FunctionLoad(FunctionLoad),
+    FunctionDelete(FunctionDelete),
+    FunctionList(FunctionList),
+    FCall(FCall),
+    Plugin(PluginCommand),
+    FlushDb(FlushDb),
+    Select(Select),
+    SwapDb(SwapDb),
+    Info(Info),
+    Time(Time),
+    Commands(Commands),
+    CommandCount(CommandCount),
+    CommandInfo(CommandInfo),
+    CommandDocs(CommandDocs),
+}
+
+/// Where a command takes keys in its request, the first argument being the command name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySpec {
+    /// every `step` argument from `first` to `last`, a negative `last` counting from the end
+    Range {
+        first: usize,
+        last: isize,
+        step: usize,
+    },
+    /// as many keys as the count at `numkeys`, right after it
+    NumKeys { numkeys: usize },
+}
+
+impl KeySpec {
+    fn keys<'a>(&self, args: &'a [RespFrame]) -> Vec<&'a [u8]> {
+        let (first, last, step) = match *self {
+            KeySpec::Range { first, last, step } => {
+                let last = if last < 0 {
+                    args.len() as isize + last
+                } else {
+                    last
+                };
+                (first, last, step)
+            }
+            KeySpec::NumKeys { numkeys } => {
+                let count = match args.get(numkeys) {
+                    Some(RespFrame::BulkString(count)) => {
+                        String::from_utf8_lossy(count).parse::<usize>().unwrap_or(0)
+                    }
+                    _ => 0,
+                };
+                (numkeys + 1, (numkeys + count) as isize, 1)
+            }
+        };
+        if last < first as isize {
+            return vec![];
+        }
+        args.iter()
+            .take(last as usize + 1)
+            .skip(first)
+            .step_by(step)
+            .filter_map(|arg| match arg {
+                RespFrame::BulkString(key) => Some(key.as_slice()),
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 #[enum_dispatch]
@@ -42,6 +303,370 @@ pub trait CommandExecutor {
     fn execute(self, backend: &Backend) -> RespFrame;
 }
 
+/// Executes a command that only reads and writes keys, against any storage engine.
+pub trait StorageExecutor {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame;
+}
+
+// The commands run against a storage engine, the backend being the one the server executes
+// them with.
+macro_rules! storage_commands {
+    ($($cmd:ident),* $(,)?) => {
+        $(
+            impl CommandExecutor for $cmd {
+                fn execute(self, backend: &Backend) -> RespFrame {
+                    self.execute_on(backend)
+                }
+            }
+        )*
+
+        impl Command {
+            /// Executes the command against `storage` when it only reads and writes keys,
+            /// handing it back otherwise for the backend to execute.
+            pub fn execute_on<S: Storage + ?Sized>(
+                self,
+                storage: &S,
+            ) -> Result<RespFrame, Box<Self>> {
+                match self {
+                    $(Command::$cmd(cmd) => Ok(cmd.execute_on(storage)),)*
+                    cmd => Err(Box::new(cmd)),
+                }
+            }
+
+            /// Whether the command only reads and writes keys, so `execute_on` runs it.
+            pub fn runs_on_storage(&self) -> bool {
+                matches!(self, $(Command::$cmd(_))|*)
+            }
+        }
+    };
+}
+
+storage_commands!(
+    Set, Get, Del, HSet, Hmset, HGet, Hmget, HDel, HGetAll, HKeys, Sadd, Srem, Sismember, Smembers,
+    LPush, RPush, LLen, LRange, Expire, PExpire, Ttl, PTtl, Persist,
+);
+
+impl Command {
+    /// Whether the command may grow memory use and must be refused once `maxmemory` is
+    /// reached and nothing more can be evicted.
+    pub fn denies_oom(&self) -> bool {
+        matches!(
+            self,
+            Command::Set(_)
+                | Command::IncrBy(_)
+                | Command::HSet(_)
+                | Command::Hmset(_)
+                | Command::Sadd(_)
+                | Command::LPush(_)
+                | Command::RPush(_)
+                | Command::LInsert(_)
+                | Command::LMove(_)
+                | Command::BLMove(_)
+                | Command::ZAdd(_)
+                | Command::ZIncrBy(_)
+                | Command::ZUnionStore(_)
+                | Command::ZInterStore(_)
+                | Command::ZDiffStore(_)
+                | Command::XAdd(_)
+                | Command::SetBit(_)
+                | Command::BitField(_)
+                | Command::PfAdd(_)
+                | Command::PfMerge(_)
+                | Command::GeoAdd(_)
+                | Command::GeoSearchStore(_)
+        )
+    }
+
+    /// Whether CLIENT PAUSE WRITE holds the command: the writes and the commands that may
+    /// write or publish messages.
+    pub fn pausable_write(&self) -> bool {
+        self.is_write()
+            || matches!(
+                self,
+                Command::Eval(_)
+                    | Command::EvalSha(_)
+                    | Command::FCall(_)
+                    | Command::Publish(_)
+                    | Command::SPublish(_)
+            )
+    }
+
+    /// Whether the command may modify the dataset, and so must be logged in the AOF.
+    pub fn is_write(&self) -> bool {
+        self.denies_oom()
+            || matches!(
+                self,
+                Command::Del(_)
+                    | Command::HDel(_)
+                    | Command::Srem(_)
+                    | Command::LMPop(_)
+                    | Command::BLMPop(_)
+                    | Command::ZRemRangeByRank(_)
+                    | Command::ZRemRangeByScore(_)
+                    | Command::ZRemRangeByLex(_)
+                    | Command::ZMPop(_)
+                    | Command::XTrim(_)
+                    | Command::XDel(_)
+                    | Command::Expire(_)
+                    | Command::PExpire(_)
+                    | Command::PExpireAt(_)
+                    | Command::Persist(_)
+                    | Command::FlushDb(_)
+                    | Command::SwapDb(_)
+                    | Command::FunctionLoad(_)
+                    | Command::FunctionDelete(_)
+            )
+            || matches!(self, Command::Plugin(cmd) if cmd.is_write())
+    }
+
+    /// Where the keys of the command are in its request, which decide the node serving it in
+    /// cluster mode. Empty for the commands that take no key.
+    pub fn key_specs(&self) -> &'static [KeySpec] {
+        if let Command::Plugin(cmd) = self {
+            return cmd.key_specs();
+        }
+        const FIRST: &[KeySpec] = &[KeySpec::Range {
+            first: 1,
+            last: 1,
+            step: 1,
+        }];
+        match self {
+            Command::Echo(_)
+            | Command::Ping(_)
+            | Command::ConfigGet(_)
+            | Command::ConfigSet(_)
+            | Command::ConfigRewrite(_)
+            | Command::MemoryStats(_)
+            | Command::MemoryDoctor(_)
+            | Command::LatencyLatest(_)
+            | Command::LatencyHistory(_)
+            | Command::LatencyReset(_)
+            | Command::DebugSleep(_)
+            | Command::DebugJmap(_)
+            | Command::DebugSetActiveExpire(_)
+            | Command::DebugChangeReplId(_)
+            | Command::Save(_)
+            | Command::BgSave(_)
+            | Command::LastSave(_)
+            | Command::BgRewriteAof(_)
+            | Command::Shutdown(_)
+            | Command::ReplicaOf(_)
+            | Command::ReplConf(_)
+            | Command::Wait(_)
+            | Command::Failover(_)
+            | Command::ClusterKeySlot(_)
+            | Command::ClusterAddSlots(_)
+            | Command::ClusterAddSlotsRange(_)
+            | Command::ClusterDelSlots(_)
+            | Command::ClusterMyId(_)
+            | Command::ClusterInfo(_)
+            | Command::ClusterSlots(_)
+            | Command::ClusterShards(_)
+            | Command::ClusterNodes(_)
+            | Command::Subscribe(_)
+            | Command::Unsubscribe(_)
+            | Command::PSubscribe(_)
+            | Command::PUnsubscribe(_)
+            | Command::Publish(_)
+            | Command::PubSubChannels(_)
+            | Command::PubSubNumSub(_)
+            | Command::PubSubNumPat(_)
+            | Command::PubSubShardChannels(_)
+            | Command::PubSubShardNumSub(_)
+            | Command::ClientTracking(_)
+            | Command::ClientSetName(_)
+            | Command::ClientGetName(_)
+            | Command::ClientId(_)
+            | Command::ClientInfo(_)
+            | Command::ClientList(_)
+            | Command::ClientPause(_)
+            | Command::ClientUnpause(_)
+            | Command::ClientReply(_)
+            | Command::Reset(_)
+            | Command::Hello(_)
+            | Command::Auth(_)
+            | Command::Multi(_)
+            | Command::Exec(_)
+            | Command::Discard(_)
+            | Command::Unwatch(_)
+            | Command::ScriptLoad(_)
+            | Command::ScriptKill(_)
+            | Command::FunctionLoad(_)
+            | Command::FunctionDelete(_)
+            | Command::FunctionList(_)
+            | Command::FlushDb(_)
+            | Command::Select(_)
+            | Command::SwapDb(_)
+            | Command::Info(_)
+            | Command::Time(_)
+            | Command::Commands(_)
+            | Command::CommandCount(_)
+            | Command::CommandInfo(_)
+            | Command::CommandDocs(_) => &[],
+            // subcommand key
+            Command::ObjectFreq(_)
+            | Command::ObjectEncoding(_)
+            | Command::MemoryUsage(_)
+            | Command::DebugObject(_) => &[KeySpec::Range {
+                first: 2,
+                last: 2,
+                step: 1,
+            }],
+            // key [key ...], shard channels being routed like keys
+            Command::Del(_)
+            | Command::PfCount(_)
+            | Command::PfMerge(_)
+            | Command::SSubscribe(_)
+            | Command::SUnsubscribe(_)
+            | Command::Watch(_) => &[KeySpec::Range {
+                first: 1,
+                last: -1,
+                step: 1,
+            }],
+            // source destination
+            Command::LMove(_) | Command::BLMove(_) | Command::GeoSearchStore(_) => {
+                &[KeySpec::Range {
+                    first: 1,
+                    last: 2,
+                    step: 1,
+                }]
+            }
+            // numkeys key [key ...]
+            Command::LMPop(_)
+            | Command::ZUnion(_)
+            | Command::ZInter(_)
+            | Command::ZDiff(_)
+            | Command::ZMPop(_) => &[KeySpec::NumKeys { numkeys: 1 }],
+            // timeout numkeys key [key ...], script numkeys key [key ...]
+            Command::BLMPop(_) | Command::Eval(_) | Command::EvalSha(_) | Command::FCall(_) => {
+                &[KeySpec::NumKeys { numkeys: 2 }]
+            }
+            // destination numkeys key [key ...]
+            Command::ZUnionStore(_) | Command::ZInterStore(_) | Command::ZDiffStore(_) => &[
+                KeySpec::Range {
+                    first: 1,
+                    last: 1,
+                    step: 1,
+                },
+                KeySpec::NumKeys { numkeys: 2 },
+            ],
+            _ => FIRST,
+        }
+    }
+
+    /// The keys of the command, read from its request `frame` as the key specs tell.
+    pub fn keys<'a>(&self, frame: &'a RespFrame) -> Vec<&'a [u8]> {
+        let RespFrame::Array(args) = frame else {
+            return vec![];
+        };
+        self.key_specs()
+            .iter()
+            .flat_map(|spec| spec.keys(args))
+            .collect()
+    }
+
+    /// Whether a connection subscribed to some channel may still run the command. QUIT, which
+    /// the network layer answers itself, is always allowed.
+    pub fn allowed_while_subscribed(&self) -> bool {
+        matches!(
+            self,
+            Command::Subscribe(_)
+                | Command::Unsubscribe(_)
+                | Command::PSubscribe(_)
+                | Command::PUnsubscribe(_)
+                | Command::SSubscribe(_)
+                | Command::SUnsubscribe(_)
+                | Command::Ping(_)
+                | Command::Reset(_)
+        )
+    }
+
+    /// The command as replayed from the AOF or the replication stream, which hold the writes
+    /// that succeeded: an increment going past 64 bits there did so for a client allowed to.
+    pub fn replayed(self) -> Self {
+        match self {
+            Command::IncrBy(cmd) => cmd.promoting(true).into(),
+            cmd => cmd,
+        }
+    }
+
+    /// The form a write command received as `frame` is logged in, which differs from it when
+    /// replaying it as is would not have the same effect.
+    pub fn propagated(&self, frame: RespFrame, backend: &Backend) -> Propagated {
+        let (frame, generated_id) = match self {
+            Command::Expire(cmd) => (cmd.to_pexpireat(backend), None),
+            Command::PExpire(cmd) => (cmd.to_pexpireat(backend), None),
+            // the ID is only known once the entry is added, and taken from the reply
+            Command::XAdd(cmd) if cmd.generates_id() => {
+                let len = match &frame {
+                    RespFrame::Array(args) => args.len(),
+                    _ => 0,
+                };
+                let index = len.checked_sub(2 * cmd.fields_len() + 1);
+                (frame, index)
+            }
+            // propagated once they popped, when there is no need to block
+            Command::BLMove(_) => (unblocked(frame, "LMOVE", |args| args.len() - 1), None),
+            Command::BLMPop(_) => (unblocked(frame, "LMPOP", |_| 1), None),
+            _ => (frame, None),
+        };
+        Propagated {
+            frame,
+            generated_id,
+        }
+    }
+}
+
+/// A write as it is logged in the AOF and streamed to the replicas.
+#[derive(Debug)]
+pub struct Propagated {
+    frame: RespFrame,
+    // the argument standing for the ID XADD generated
+    generated_id: Option<usize>,
+}
+
+impl Propagated {
+    /// Logs and streams the write, unless `reply` tells it failed. The caller keeps the write from
+    /// overlapping with others from when it runs, so they are propagated in the order they ran.
+    pub fn propagate(self, backend: &Backend, reply: &RespFrame) {
+        if let Some(frame) = self.into_frame(reply) {
+            backend.propagate(&frame);
+        }
+    }
+
+    /// The frame logged for the write given its `reply`, `None` when it failed.
+    pub fn into_frame(self, reply: &RespFrame) -> Option<RespFrame> {
+        if matches!(reply, RespFrame::SimpleError(_)) {
+            return None;
+        }
+        let mut frame = self.frame;
+        if let (Some(index), RespFrame::Array(args)) = (self.generated_id, &mut frame) {
+            if let Some(id) = args.0.get_mut(index) {
+                *id = reply.clone();
+            }
+        }
+        Some(frame)
+    }
+}
+
+// The blocking command in `frame` as the command it blocks on, renamed `name` and without the
+// timeout, at the argument `timeout` picks.
+fn unblocked(
+    frame: RespFrame,
+    name: &'static str,
+    timeout: fn(&[RespFrame]) -> usize,
+) -> RespFrame {
+    let RespFrame::Array(RespArray(mut args)) = frame else {
+        return frame;
+    };
+    if args.len() > 1 {
+        let index = timeout(&args);
+        args.remove(index);
+        args[0] = BulkString::from(name).into();
+    }
+    RespArray::new(args).into()
+}
+
 impl TryFrom<RespFrame> for Command {
     type Error = CommandError;
     fn try_from(v: RespFrame) -> Result<Self, Self::Error> {
@@ -62,6 +687,11 @@ impl TryFrom<RespArray> for Command {
                 b"get" => Ok(Get::try_from(v)?.into()),
                 b"set" => Ok(Set::try_from(v)?.into()),
                 b"del" => Ok(Del::try_from(v)?.into()),
+                b"type" => Ok(Type::try_from(v)?.into()),
+                b"incr" => Ok(IncrBy::parse(v, "incr")?.into()),
+                b"decr" => Ok(IncrBy::parse(v, "decr")?.into()),
+                b"incrby" => Ok(IncrBy::parse(v, "incrby")?.into()),
+                b"decrby" => Ok(IncrBy::parse(v, "decrby")?.into()),
                 b"hget" => Ok(HGet::try_from(v)?.into()),
                 b"hset" => Ok(HSet::try_from(v)?.into()),
                 b"hmget" => Ok(Hmget::try_from(v)?.into()),
@@ -70,14 +700,188 @@ impl TryFrom<RespArray> for Command {
                 b"hgetall" => Ok(HGetAll::try_from(v)?.into()),
                 b"hkeys" => Ok(HKeys::try_from(v)?.into()),
                 b"echo" => Ok(Echo::try_from(v)?.into()),
+                b"ping" => Ok(Ping::try_from(v)?.into()),
                 b"sadd" => Ok(Sadd::try_from(v)?.into()),
                 b"sismember" => Ok(Sismember::try_from(v)?.into()),
                 b"smembers" => Ok(Smembers::try_from(v)?.into()),
                 b"srem" => Ok(Srem::try_from(v)?.into()),
-                _ => Err(CommandError::InvalidCommand(format!(
-                    "unknown command '{}'",
-                    String::from_utf8_lossy(cmd.as_ref())
-                ))),
+                b"lpush" => Ok(LPush::try_from(v)?.into()),
+                b"rpush" => Ok(RPush::try_from(v)?.into()),
+                b"llen" => Ok(LLen::try_from(v)?.into()),
+                b"lrange" => Ok(LRange::try_from(v)?.into()),
+                b"lindex" => Ok(LIndex::try_from(v)?.into()),
+                b"linsert" => Ok(LInsert::try_from(v)?.into()),
+                b"lmove" => Ok(LMove::try_from(v)?.into()),
+                b"blmove" => Ok(BLMove::try_from(v)?.into()),
+                b"lmpop" => Ok(LMPop::try_from(v)?.into()),
+                b"blmpop" => Ok(BLMPop::try_from(v)?.into()),
+                b"zadd" => Ok(ZAdd::try_from(v)?.into()),
+                b"zscore" => Ok(ZScore::try_from(v)?.into()),
+                b"zcard" => Ok(ZCard::try_from(v)?.into()),
+                b"zrange" => Ok(ZRange::try_from(v)?.into()),
+                b"zrevrange" => Ok(ZRevRange::try_from(v)?.into()),
+                b"zrangebyscore" => Ok(ZRangeByScore::try_from(v)?.into()),
+                b"zrangebylex" => Ok(ZRangeByLex::try_from(v)?.into()),
+                b"zincrby" => Ok(ZIncrBy::try_from(v)?.into()),
+                b"zcount" => Ok(ZCount::try_from(v)?.into()),
+                b"zlexcount" => Ok(ZLexCount::try_from(v)?.into()),
+                b"zunionstore" => Ok(ZUnionStore::try_from(v)?.into()),
+                b"zinterstore" => Ok(ZInterStore::try_from(v)?.into()),
+                b"zdiffstore" => Ok(ZDiffStore::try_from(v)?.into()),
+                b"zunion" => Ok(ZUnion::try_from(v)?.into()),
+                b"zinter" => Ok(ZInter::try_from(v)?.into()),
+                b"zdiff" => Ok(ZDiff::try_from(v)?.into()),
+                b"zremrangebyrank" => Ok(ZRemRangeByRank::try_from(v)?.into()),
+                b"zremrangebyscore" => Ok(ZRemRangeByScore::try_from(v)?.into()),
+                b"zremrangebylex" => Ok(ZRemRangeByLex::try_from(v)?.into()),
+                b"zrandmember" => Ok(ZRandMember::try_from(v)?.into()),
+                b"zscan" => Ok(ZScan::try_from(v)?.into()),
+                b"zmpop" => Ok(ZMPop::try_from(v)?.into()),
+                b"xadd" => Ok(XAdd::try_from(v)?.into()),
+                b"xlen" => Ok(XLen::try_from(v)?.into()),
+                b"xrange" => Ok(XRange::try_from(v)?.into()),
+                b"xrevrange" => Ok(XRevRange::try_from(v)?.into()),
+                b"xtrim" => Ok(XTrim::try_from(v)?.into()),
+                b"xdel" => Ok(XDel::try_from(v)?.into()),
+                b"setbit" => Ok(SetBit::try_from(v)?.into()),
+                b"getbit" => Ok(GetBit::try_from(v)?.into()),
+                b"bitcount" => Ok(BitCount::try_from(v)?.into()),
+                b"bitfield" => Ok(BitField::try_from(v)?.into()),
+                b"pfadd" => Ok(PfAdd::try_from(v)?.into()),
+                b"pfcount" => Ok(PfCount::try_from(v)?.into()),
+                b"pfmerge" => Ok(PfMerge::try_from(v)?.into()),
+                b"geoadd" => Ok(GeoAdd::try_from(v)?.into()),
+                b"geopos" => Ok(GeoPos::try_from(v)?.into()),
+                b"geodist" => Ok(GeoDist::try_from(v)?.into()),
+                b"geosearch" => Ok(GeoSearch::try_from(v)?.into()),
+                b"geosearchstore" => Ok(GeoSearchStore::try_from(v)?.into()),
+                b"expire" => Ok(Expire::try_from(v)?.into()),
+                b"pexpire" => Ok(PExpire::try_from(v)?.into()),
+                b"pexpireat" => Ok(PExpireAt::try_from(v)?.into()),
+                b"ttl" => Ok(Ttl::try_from(v)?.into()),
+                b"pttl" => Ok(PTtl::try_from(v)?.into()),
+                b"persist" => Ok(Persist::try_from(v)?.into()),
+                b"flushdb" => Ok(FlushDb::try_from(v)?.into()),
+                b"select" => Ok(Select::try_from(v)?.into()),
+                b"swapdb" => Ok(SwapDb::try_from(v)?.into()),
+                b"object" => match extract_subcommand(&v)?.as_slice() {
+                    b"freq" => Ok(ObjectFreq::try_from(v)?.into()),
+                    b"encoding" => Ok(ObjectEncoding::try_from(v)?.into()),
+                    sub => Err(unknown_subcommand(sub)),
+                },
+                b"latency" => match extract_subcommand(&v)?.as_slice() {
+                    b"latest" => Ok(LatencyLatest::try_from(v)?.into()),
+                    b"history" => Ok(LatencyHistory::try_from(v)?.into()),
+                    b"reset" => Ok(LatencyReset::try_from(v)?.into()),
+                    sub => Err(unknown_subcommand(sub)),
+                },
+                b"debug" => match extract_subcommand(&v)?.as_slice() {
+                    b"sleep" => Ok(DebugSleep::try_from(v)?.into()),
+                    b"jmap" => Ok(DebugJmap::try_from(v)?.into()),
+                    b"object" => Ok(DebugObject::try_from(v)?.into()),
+                    b"set-active-expire" => Ok(DebugSetActiveExpire::try_from(v)?.into()),
+                    b"change-repl-id" => Ok(DebugChangeReplId::try_from(v)?.into()),
+                    sub => Err(unknown_subcommand(sub)),
+                },
+                b"memory" => match extract_subcommand(&v)?.as_slice() {
+                    b"usage" => Ok(MemoryUsage::try_from(v)?.into()),
+                    b"stats" => Ok(MemoryStats::try_from(v)?.into()),
+                    b"doctor" => Ok(MemoryDoctor::try_from(v)?.into()),
+                    sub => Err(unknown_subcommand(sub)),
+                },
+                b"config" => match extract_subcommand(&v)?.as_slice() {
+                    b"get" => Ok(ConfigGet::try_from(v)?.into()),
+                    b"set" => Ok(ConfigSet::try_from(v)?.into()),
+                    b"rewrite" => Ok(ConfigRewrite::try_from(v)?.into()),
+                    sub => Err(unknown_subcommand(sub)),
+                },
+                b"save" => Ok(Save::try_from(v)?.into()),
+                b"bgsave" => Ok(BgSave::try_from(v)?.into()),
+                b"lastsave" => Ok(LastSave::try_from(v)?.into()),
+                b"bgrewriteaof" => Ok(BgRewriteAof::try_from(v)?.into()),
+                b"shutdown" => Ok(Shutdown::try_from(v)?.into()),
+                b"info" => Ok(Info::try_from(v)?.into()),
+                b"time" => Ok(Time::try_from(v)?.into()),
+                b"command" if v.len() == 1 => Ok(Commands::try_from(v)?.into()),
+                b"command" => match extract_subcommand(&v)?.as_slice() {
+                    b"count" => Ok(CommandCount::try_from(v)?.into()),
+                    b"info" => Ok(CommandInfo::try_from(v)?.into()),
+                    b"docs" => Ok(CommandDocs::try_from(v)?.into()),
+                    sub => Err(unknown_subcommand(sub)),
+                },
+                b"replicaof" => Ok(ReplicaOf::try_from(v)?.into()),
+                b"replconf" => Ok(ReplConf::try_from(v)?.into()),
+                b"wait" => Ok(Wait::try_from(v)?.into()),
+                b"failover" => Ok(Failover::try_from(v)?.into()),
+                b"cluster" => match extract_subcommand(&v)?.as_slice() {
+                    b"keyslot" => Ok(ClusterKeySlot::try_from(v)?.into()),
+                    b"addslots" => Ok(ClusterAddSlots::try_from(v)?.into()),
+                    b"addslotsrange" => Ok(ClusterAddSlotsRange::try_from(v)?.into()),
+                    b"delslots" => Ok(ClusterDelSlots::try_from(v)?.into()),
+                    b"myid" => Ok(ClusterMyId::try_from(v)?.into()),
+                    b"info" => Ok(ClusterInfo::try_from(v)?.into()),
+                    b"slots" => Ok(ClusterSlots::try_from(v)?.into()),
+                    b"shards" => Ok(ClusterShards::try_from(v)?.into()),
+                    b"nodes" => Ok(ClusterNodes::try_from(v)?.into()),
+                    sub => Err(unknown_subcommand(sub)),
+                },
+                b"client" => match extract_subcommand(&v)?.as_slice() {
+                    b"tracking" => Ok(ClientTracking::try_from(v)?.into()),
+                    b"setname" => Ok(ClientSetName::try_from(v)?.into()),
+                    b"getname" => Ok(ClientGetName::try_from(v)?.into()),
+                    b"id" => Ok(ClientId::try_from(v)?.into()),
+                    b"info" => Ok(ClientInfo::try_from(v)?.into()),
+                    b"list" => Ok(ClientList::try_from(v)?.into()),
+                    b"pause" => Ok(ClientPause::try_from(v)?.into()),
+                    b"unpause" => Ok(ClientUnpause::try_from(v)?.into()),
+                    b"reply" => Ok(ClientReply::try_from(v)?.into()),
+                    sub => Err(unknown_subcommand(sub)),
+                },
+                b"hello" => Ok(Hello::try_from(v)?.into()),
+                b"auth" => Ok(Auth::try_from(v)?.into()),
+                b"reset" => Ok(Reset::try_from(v)?.into()),
+                b"multi" => Ok(Multi::try_from(v)?.into()),
+                b"exec" => Ok(Exec::try_from(v)?.into()),
+                b"discard" => Ok(Discard::try_from(v)?.into()),
+                b"watch" => Ok(Watch::try_from(v)?.into()),
+                b"unwatch" => Ok(Unwatch::try_from(v)?.into()),
+                b"eval" => Ok(Eval::try_from(v)?.into()),
+                b"evalsha" => Ok(EvalSha::try_from(v)?.into()),
+                b"script" => match extract_subcommand(&v)?.as_slice() {
+                    b"load" => Ok(ScriptLoad::try_from(v)?.into()),
+                    b"kill" => Ok(ScriptKill::try_from(v)?.into()),
+                    sub => Err(unknown_subcommand(sub)),
+                },
+                b"function" => match extract_subcommand(&v)?.as_slice() {
+                    b"load" => Ok(FunctionLoad::try_from(v)?.into()),
+                    b"delete" => Ok(FunctionDelete::try_from(v)?.into()),
+                    b"list" => Ok(FunctionList::try_from(v)?.into()),
+                    sub => Err(unknown_subcommand(sub)),
+                },
+                b"fcall" => Ok(FCall::try_from(v)?.into()),
+                b"subscribe" => Ok(Subscribe::try_from(v)?.into()),
+                b"unsubscribe" => Ok(Unsubscribe::try_from(v)?.into()),
+                b"psubscribe" => Ok(PSubscribe::try_from(v)?.into()),
+                b"punsubscribe" => Ok(PUnsubscribe::try_from(v)?.into()),
+                b"publish" => Ok(Publish::try_from(v)?.into()),
+                b"ssubscribe" => Ok(SSubscribe::try_from(v)?.into()),
+                b"sunsubscribe" => Ok(SUnsubscribe::try_from(v)?.into()),
+                b"spublish" => Ok(SPublish::try_from(v)?.into()),
+                b"pubsub" => match extract_subcommand(&v)?.as_slice() {
+                    b"channels" => Ok(PubSubChannels::try_from(v)?.into()),
+                    b"numsub" => Ok(PubSubNumSub::try_from(v)?.into()),
+                    b"numpat" => Ok(PubSubNumPat::try_from(v)?.into()),
+                    b"shardchannels" => Ok(PubSubShardChannels::try_from(v)?.into()),
+                    b"shardnumsub" => Ok(PubSubShardNumSub::try_from(v)?.into()),
+                    sub => Err(unknown_subcommand(sub)),
+                },
+                name => match find_plugin(name) {
+                    Some(plugin) => Ok(PluginCommand::new(plugin, v)?.into()),
+                    None => Err(CommandError::InvalidCommand(format!(
+                        "unknown command '{}'",
+                        String::from_utf8_lossy(cmd.as_ref())
+                    ))),
+                },
             },
             _ => Err(CommandError::InvalidCommand(
                 "Command must have a BulkString as the first argument".to_string(),
@@ -95,7 +899,7 @@ impl TryFrom<RespArray> for String {
             ));
         }
         match value.first() {
-            Some(RespFrame::BulkString(s)) => Ok(String::from_utf8(s.0.clone())?),
+            Some(RespFrame::BulkString(s)) => Ok(String::from_utf8(s.to_vec())?),
             _ => Err(CommandError::InvalidCommandArguments(
                 "Argument must be of the BulkString type".to_string(),
             )),
@@ -106,7 +910,7 @@ impl TryFrom<RespArray> for String {
 impl TryFrom<RespArray> for Vec<String> {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        if value.len() < 1 {
+        if value.is_empty() {
             return Err(CommandError::InvalidCommandArguments(
                 "Command must have a one argument".to_string(),
             ));
@@ -115,7 +919,7 @@ impl TryFrom<RespArray> for Vec<String> {
             .0
             .into_iter()
             .map(|v| match v {
-                RespFrame::BulkString(s) => Ok(String::from_utf8(s.0)?),
+                RespFrame::BulkString(s) => Ok(String::from_utf8(s.0.into())?),
                 _ => Err(CommandError::InvalidCommandArguments(
                     "Argument must be of the BulkString type".to_string(),
                 )),
@@ -126,10 +930,39 @@ impl TryFrom<RespArray> for Vec<String> {
 
 #[derive(Debug)]
 pub struct KeyValue {
-    key: String,
+    key: Bytes,
     value: RespFrame,
 }
 
+impl TryFrom<RespArray> for Bytes {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() != 1 {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have a one argument".to_string(),
+            ));
+        }
+        match value.first() {
+            Some(RespFrame::BulkString(s)) => Ok(s.0.clone()),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "Argument must be of the BulkString type".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Vec<Bytes> {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.is_empty() {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have a one argument".to_string(),
+            ));
+        }
+        value.0.into_iter().map(extract_bytes).collect()
+    }
+}
+
 impl TryFrom<RespArray> for KeyValue {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
@@ -140,10 +973,7 @@ impl TryFrom<RespArray> for KeyValue {
         }
         let mut args = value.0.into_iter();
         match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(value)) => Ok(KeyValue {
-                key: String::from_utf8(key.0)?,
-                value,
-            }),
+            (Some(RespFrame::BulkString(key)), Some(value)) => Ok(KeyValue { key: key.0, value }),
             _ => Err(CommandError::InvalidCommandArguments(
                 "Invalid key or value".to_string(),
             )),
@@ -153,7 +983,7 @@ impl TryFrom<RespArray> for KeyValue {
 
 #[derive(Debug)]
 pub struct KeyValues {
-    key: String,
+    key: Bytes,
     values: Vec<RespFrame>,
 }
 
@@ -168,7 +998,7 @@ impl TryFrom<RespArray> for KeyValues {
         let mut args = value.0.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(key)) => Ok(KeyValues {
-                key: String::from_utf8(key.0)?,
+                key: key.0,
                 values: args.collect(),
             }),
             _ => Err(CommandError::InvalidCommandArguments(
@@ -180,8 +1010,8 @@ impl TryFrom<RespArray> for KeyValues {
 
 #[derive(Debug)]
 pub struct KeyField {
-    key: String,
-    field: String,
+    key: Bytes,
+    field: Bytes,
 }
 
 impl TryFrom<RespArray> for KeyField {
@@ -196,8 +1026,8 @@ impl TryFrom<RespArray> for KeyField {
         match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field))) => {
                 Ok(KeyField {
-                    key: String::from_utf8(key.0)?,
-                    field: String::from_utf8(field.0)?,
+                    key: key.0,
+                    field: field.0,
                 })
             }
             _ => Err(CommandError::InvalidCommandArguments(
@@ -209,8 +1039,8 @@ impl TryFrom<RespArray> for KeyField {
 
 #[derive(Debug)]
 pub struct KeyFields {
-    key: String,
-    fields: Vec<String>,
+    key: Bytes,
+    fields: Vec<Bytes>,
 }
 
 impl TryFrom<RespArray> for KeyFields {
@@ -224,15 +1054,15 @@ impl TryFrom<RespArray> for KeyFields {
         let mut args = value.0.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(key)) => Ok(KeyFields {
-                key: String::from_utf8(key.0)?,
+                key: key.0,
                 fields: args
                     .map(|v| match v {
-                        RespFrame::BulkString(s) => Ok(String::from_utf8(s.0)?),
+                        RespFrame::BulkString(s) => Ok(s.0),
                         _ => Err(CommandError::InvalidCommandArguments(
                             "Argument must be of the BulkString type".to_string(),
                         )),
                     })
-                    .collect::<Result<Vec<String>, CommandError>>()?,
+                    .collect::<Result<Vec<Bytes>, CommandError>>()?,
             }),
             _ => Err(CommandError::InvalidCommandArguments(
                 "Invalid key or value".to_string(),
@@ -243,8 +1073,8 @@ impl TryFrom<RespArray> for KeyFields {
 
 #[derive(Debug)]
 pub struct Hmap {
-    key: String,
-    map: Vec<(String, RespFrame)>,
+    key: Bytes,
+    map: Vec<(Bytes, RespFrame)>,
 }
 
 impl TryFrom<RespArray> for Hmap {
@@ -256,7 +1086,7 @@ impl TryFrom<RespArray> for Hmap {
             ));
         }
         // Exclude the number of commands and key parameters.
-        if (value.len() - 1) % 2 != 0 {
+        if !(value.len() - 1).is_multiple_of(2) {
             return Err(CommandError::InvalidCommandArguments(
                 "command must have an even number of arguments".to_string(),
             ));
@@ -268,9 +1098,7 @@ impl TryFrom<RespArray> for Hmap {
                 while let Some(field) = args.next() {
                     match args.next() {
                         Some(value) => match field {
-                            RespFrame::BulkString(field) => {
-                                map.push((String::from_utf8(field.0)?, value))
-                            }
+                            RespFrame::BulkString(field) => map.push((field.0, value)),
                             _ => {
                                 return Err(CommandError::InvalidCommandArguments(
                                     "Invalid key or value".to_string(),
@@ -284,10 +1112,7 @@ impl TryFrom<RespArray> for Hmap {
                         }
                     }
                 }
-                Ok(Hmap {
-                    key: String::from_utf8(key.0)?,
-                    map,
-                })
+                Ok(Hmap { key: key.0, map })
             }
             _ => Err(CommandError::InvalidCommandArguments(
                 "Invalid key or value".to_string(),
@@ -325,6 +1150,49 @@ fn validate_command(value: &RespArray, names: &[&'static str]) -> Result<(), Com
     Ok(())
 }
 
+// Returns the lower-cased subcommand of a container command such as CONFIG GET.
+fn extract_subcommand(value: &RespArray) -> Result<Vec<u8>, CommandError> {
+    match value.get(1) {
+        Some(RespFrame::BulkString(sub)) => Ok(sub.to_ascii_lowercase()),
+        _ => Err(CommandError::InvalidCommandArguments(
+            "Command must have a subcommand".to_string(),
+        )),
+    }
+}
+
+/// Waits for `lock`, unless a script keeps running for longer than busy-reply-threshold in the
+/// meantime, which is replied to with a BUSY error.
+pub async fn unless_busy<T>(
+    backend: &Backend,
+    lock: impl Future<Output = T>,
+) -> Result<T, RespFrame> {
+    tokio::select! {
+        biased;
+        guard = lock => Ok(guard),
+        _ = backend.script_busy() => Err(SimpleError::new(
+            "BUSY Redis is busy running a script. You can only call SCRIPT KILL or SHUTDOWN NOSAVE.",
+        )
+        .into()),
+    }
+}
+
+// The reply of the commands acting on the state of the connection running them, which the
+// network layer passes in, when they run without one.
+fn connection_only(name: &str) -> RespFrame {
+    SimpleError::new(format!(
+        "ERR {} is only available on client connections",
+        name
+    ))
+    .into()
+}
+
+fn unknown_subcommand(sub: &[u8]) -> CommandError {
+    CommandError::InvalidArgument(format!(
+        "unknown subcommand '{}'",
+        String::from_utf8_lossy(sub)
+    ))
+}
+
 fn extract_args(value: RespArray, start: usize) -> Result<RespArray, CommandError> {
     Ok(value
         .0
@@ -333,3 +1201,171 @@ fn extract_args(value: RespArray, start: usize) -> Result<RespArray, CommandErro
         .collect::<Vec<RespFrame>>()
         .into())
 }
+
+fn extract_string(frame: RespFrame) -> Result<String, CommandError> {
+    match frame {
+        RespFrame::BulkString(s) => Ok(String::from_utf8(s.0.into())?),
+        _ => Err(CommandError::InvalidCommandArguments(
+            "Argument must be of the BulkString type".to_string(),
+        )),
+    }
+}
+
+fn extract_bytes(frame: RespFrame) -> Result<Bytes, CommandError> {
+    match frame {
+        RespFrame::BulkString(s) => Ok(s.0),
+        _ => Err(CommandError::InvalidCommandArguments(
+            "Argument must be of the BulkString type".to_string(),
+        )),
+    }
+}
+
+fn is_keyword(frame: &RespFrame, keyword: &str) -> bool {
+    match frame {
+        RespFrame::BulkString(s) => s.eq_ignore_ascii_case(keyword.as_bytes()),
+        _ => false,
+    }
+}
+
+fn extract_integer(frame: RespFrame) -> Result<i64, CommandError> {
+    match frame {
+        RespFrame::Integer(n) => Ok(n),
+        RespFrame::BulkString(s) => String::from_utf8_lossy(&s).parse().map_err(|_| {
+            CommandError::InvalidArgument("value is not an integer or out of range".to_string())
+        }),
+        _ => Err(CommandError::InvalidArgument(
+            "value is not an integer or out of range".to_string(),
+        )),
+    }
+}
+
+fn extract_float(frame: RespFrame) -> Result<f64, CommandError> {
+    let err = || CommandError::InvalidArgument("value is not a valid float".to_string());
+    match frame {
+        RespFrame::Integer(n) => Ok(n as f64),
+        RespFrame::Double(d) => Ok(d.0 .0),
+        RespFrame::BulkString(s) => {
+            let f: f64 = String::from_utf8_lossy(&s).parse().map_err(|_| err())?;
+            if f.is_nan() {
+                return Err(err());
+            }
+            Ok(f)
+        }
+        _ => Err(err()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_command_keys() -> anyhow::Result<()> {
+        let request = |args: &[&'static str]| -> RespFrame {
+            RespArray::new(
+                args.iter()
+                    .map(|arg| BulkString::from(*arg).into())
+                    .collect::<Vec<_>>(),
+            )
+            .into()
+        };
+        let frame = request(&["del", "a", "b", "c"]);
+        let cmd = Command::try_from(frame.clone())?;
+        assert_eq!(cmd.keys(&frame), vec![b"a", b"b", b"c"]);
+
+        let frame = request(&["zunionstore", "dest", "2", "a", "b", "WEIGHTS", "1", "2"]);
+        let cmd = Command::try_from(frame.clone())?;
+        let keys: Vec<&[u8]> = vec![b"dest", b"a", b"b"];
+        assert_eq!(cmd.keys(&frame), keys);
+
+        let frame = request(&["blmpop", "0", "2", "a", "b", "LEFT"]);
+        let cmd = Command::try_from(frame.clone())?;
+        assert_eq!(cmd.keys(&frame), vec![b"a", b"b"]);
+
+        let frame = request(&["object", "encoding", "key"]);
+        let cmd = Command::try_from(frame.clone())?;
+        assert_eq!(cmd.keys(&frame), vec![b"key"]);
+
+        let frame = request(&["spublish", "news", "hello"]);
+        let cmd = Command::try_from(frame.clone())?;
+        assert_eq!(cmd.keys(&frame), vec![b"news"]);
+
+        let frame = request(&["info", "replication"]);
+        let cmd = Command::try_from(frame.clone())?;
+        assert!(cmd.keys(&frame).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_allowed_while_subscribed() -> anyhow::Result<()> {
+        let allowed = |args: &[&'static str]| -> anyhow::Result<bool> {
+            let frame: RespFrame = RespArray::new(
+                args.iter()
+                    .map(|arg| BulkString::from(*arg).into())
+                    .collect::<Vec<_>>(),
+            )
+            .into();
+            Ok(Command::try_from(frame)?.allowed_while_subscribed())
+        };
+        assert!(allowed(&["ssubscribe", "news"])?);
+        assert!(allowed(&["punsubscribe"])?);
+        assert!(allowed(&["ping"])?);
+        assert!(!allowed(&["get", "foo"])?);
+        assert!(!allowed(&["publish", "news", "hello"])?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_propagated() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let request = |args: &[&'static str]| -> RespFrame {
+            RespArray::new(
+                args.iter()
+                    .map(|arg| BulkString::from(*arg).into())
+                    .collect::<Vec<_>>(),
+            )
+            .into()
+        };
+        let run = |args: &[&'static str]| -> anyhow::Result<(RespFrame, Option<RespFrame>)> {
+            let cmd = Command::try_from(request(args))?;
+            let propagated = cmd.propagated(request(args), &backend);
+            let reply = cmd.execute(&backend);
+            let frame = propagated.into_frame(&reply);
+            Ok((reply, frame))
+        };
+
+        // the ID XADD generates is logged in place of the one asked for
+        let (id, frame) = run(&["xadd", "events", "MAXLEN", "~", "10", "*", "*", "v"])?;
+        let mut expected = request(&["xadd", "events", "MAXLEN", "~", "10", "*", "*", "v"]);
+        if let RespFrame::Array(args) = &mut expected {
+            args.0[5] = id;
+        }
+        assert_eq!(frame, Some(expected));
+        let (id, frame) = run(&["xadd", "events", "99999999999999-*", "f", "v"])?;
+        assert_eq!(id, BulkString::from("99999999999999-0").into());
+        assert_eq!(
+            frame,
+            Some(request(&["xadd", "events", "99999999999999-0", "f", "v"]))
+        );
+        let (_, frame) = run(&["xadd", "events", "99999999999999-5", "f", "v"])?;
+        assert_eq!(
+            frame,
+            Some(request(&["xadd", "events", "99999999999999-5", "f", "v"]))
+        );
+        // a failed write isn't logged
+        let (_, frame) = run(&["xadd", "events", "1-1", "f", "v"])?;
+        assert_eq!(frame, None);
+
+        // the blocking pops are logged as the pops they ended up doing
+        run(&["rpush", "queue", "a", "b"])?;
+        let (_, frame) = run(&["blmove", "queue", "dest", "LEFT", "RIGHT", "0"])?;
+        assert_eq!(
+            frame,
+            Some(request(&["LMOVE", "queue", "dest", "LEFT", "RIGHT"]))
+        );
+        let (_, frame) = run(&["blmpop", "0.5", "1", "queue", "LEFT"])?;
+        assert_eq!(frame, Some(request(&["LMPOP", "1", "queue", "LEFT"])));
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use super::{extract_args, validate_command, CommandError, CommandExecutor};
+use crate::{Backend, BulkString, RespArray, RespFrame, RespNull, SimpleError};
+use bytes::Bytes;
+use derive_more::Deref;
+
+#[derive(Debug, Deref)]
+pub struct ObjectFreq(Bytes);
+
+impl CommandExecutor for ObjectFreq {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if !backend.eviction_policy().is_lfu() {
+            return SimpleError::new(
+                "ERR An LFU maxmemory policy is not selected, access frequency not tracked.",
+            )
+            .into();
+        }
+        match backend.object_freq(&self) {
+            Some(frequency) => RespFrame::Integer(frequency as i64),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+// key
+impl TryFrom<RespArray> for ObjectFreq {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["object", "freq"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct ObjectEncoding(Bytes);
+
+impl CommandExecutor for ObjectEncoding {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.object_encoding(&self) {
+            Some(encoding) => BulkString::from(encoding).into(),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+// key
+impl TryFrom<RespArray> for ObjectEncoding {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["object", "encoding"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::EvictionPolicy, resp::RespDecoder};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_object_freq_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nobject\r\n$4\r\nfreq\r\n$3\r\nkey\r\n");
+        let cmd = ObjectFreq::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0, "key");
+        Ok(())
+    }
+
+    #[test]
+    fn test_object_freq_execute() {
+        let backend = Backend::new();
+        backend.set("key".into(), BulkString::from("value").into());
+        assert!(matches!(
+            ObjectFreq("key".into()).execute(&backend),
+            RespFrame::SimpleError(_)
+        ));
+
+        backend.set_eviction_policy(EvictionPolicy::AllKeysLfu);
+        assert_eq!(
+            ObjectFreq("key".into()).execute(&backend),
+            RespFrame::Integer(5)
+        );
+        for _ in 0..100 {
+            backend.get(b"key").unwrap();
+        }
+        assert!(matches!(
+            ObjectFreq("key".into()).execute(&backend),
+            RespFrame::Integer(n) if n > 5
+        ));
+        assert_eq!(
+            ObjectFreq("missing".into()).execute(&backend),
+            RespFrame::Null(RespNull)
+        );
+    }
+
+    #[test]
+    fn test_object_encoding_execute() {
+        let backend = Backend::new();
+        backend
+            .hset(
+                "hash".into(),
+                "field".into(),
+                BulkString::from("value").into(),
+            )
+            .unwrap();
+        assert_eq!(
+            ObjectEncoding("hash".into()).execute(&backend),
+            BulkString::from("listpack").into()
+        );
+        assert_eq!(
+            ObjectEncoding("missing".into()).execute(&backend),
+            RespFrame::Null(RespNull)
+        );
+    }
+}
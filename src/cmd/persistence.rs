@@ -0,0 +1,296 @@
+use super::{
+    extract_args, is_keyword, validate_command, Command, CommandError, CommandExecutor, RESP_OK,
+};
+use crate::{Backend, RespArray, RespFrame, SimpleError, SimpleString};
+use anyhow::{anyhow, Result};
+use tracing::{info, warn};
+
+/// Replays the commands logged in the AOF, returning how many were executed, or `None` when
+/// there is no AOF yet.
+pub fn load_aof(backend: &Backend) -> Result<Option<usize>> {
+    let Some(contents) = backend.read_aof()? else {
+        return Ok(None);
+    };
+    if let Some(keys) = contents.preamble_keys {
+        info!(
+            "Loaded the snapshot preamble of the append only file: {} keys",
+            keys
+        );
+    }
+    let count = contents.commands.len();
+    // the commands run against the database the last SELECT before them switched to
+    let mut db = backend.clone();
+    for (i, frame) in contents.commands.into_iter().enumerate() {
+        let cmd = Command::try_from(frame)
+            .map_err(|e| anyhow!("Invalid command #{} in the append only file: {}", i + 1, e))?;
+        if let Command::Select(cmd) = cmd {
+            db = cmd.select(backend).map_err(|e| {
+                anyhow!("Invalid command #{} in the append only file: {}", i + 1, e)
+            })?;
+            continue;
+        }
+        if !cmd.is_write() {
+            return Err(anyhow!(
+                "Unexpected command #{} in the append only file: {:?}",
+                i + 1,
+                cmd
+            ));
+        }
+        cmd.replayed().execute(&db);
+    }
+    Ok(Some(count))
+}
+
+#[derive(Debug)]
+pub struct Save;
+
+impl CommandExecutor for Save {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.save() {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => SimpleError::new(format!("ERR {}", e)).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Save {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["save"];
+        validate_command(&value, &cmd_names)?;
+        if value.len() != cmd_names.len() {
+            return Err(CommandError::InvalidCommandArguments(
+                "save takes no arguments".to_string(),
+            ));
+        }
+        Ok(Save)
+    }
+}
+
+#[derive(Debug)]
+pub struct BgSave;
+
+impl CommandExecutor for BgSave {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.bgsave() {
+            Ok(()) => SimpleString::new("Background saving started").into(),
+            Err(e) => SimpleError::new(format!("ERR {}", e)).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for BgSave {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["bgsave"];
+        validate_command(&value, &cmd_names)?;
+        if value.len() != cmd_names.len() {
+            return Err(CommandError::InvalidCommandArguments(
+                "bgsave takes no arguments".to_string(),
+            ));
+        }
+        Ok(BgSave)
+    }
+}
+
+#[derive(Debug)]
+pub struct LastSave;
+
+impl CommandExecutor for LastSave {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::integer(backend.lastsave())
+    }
+}
+
+impl TryFrom<RespArray> for LastSave {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["lastsave"];
+        validate_command(&value, &cmd_names)?;
+        if value.len() != cmd_names.len() {
+            return Err(CommandError::InvalidCommandArguments(
+                "lastsave takes no arguments".to_string(),
+            ));
+        }
+        Ok(LastSave)
+    }
+}
+
+#[derive(Debug)]
+pub struct BgRewriteAof;
+
+impl CommandExecutor for BgRewriteAof {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.bgrewriteaof() {
+            Ok(()) => SimpleString::new("Background append only file rewriting started").into(),
+            Err(e) => SimpleError::new(format!("ERR {}", e)).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for BgRewriteAof {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["bgrewriteaof"];
+        validate_command(&value, &cmd_names)?;
+        if value.len() != cmd_names.len() {
+            return Err(CommandError::InvalidCommandArguments(
+                "bgrewriteaof takes no arguments".to_string(),
+            ));
+        }
+        Ok(BgRewriteAof)
+    }
+}
+
+#[derive(Debug)]
+pub struct Shutdown {
+    // SAVE or NOSAVE, saving when there are save rules without either
+    save: Option<bool>,
+}
+
+impl Shutdown {
+    /// Whether the command saves the dataset whatever the save rules.
+    pub fn forces_save(&self) -> bool {
+        self.save == Some(true)
+    }
+}
+
+impl CommandExecutor for Shutdown {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.shutdown(self.save) {
+            // the connection is closed without a reply
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => {
+                warn!("Error trying to shut down: {}", e);
+                SimpleError::new("ERR Errors trying to SHUTDOWN. Check logs.").into()
+            }
+        }
+    }
+}
+
+// [NOSAVE|SAVE]
+impl TryFrom<RespArray> for Shutdown {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["shutdown"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        let save = match (args.next(), args.next()) {
+            (None, _) => None,
+            (Some(arg), None) if is_keyword(&arg, "save") => Some(true),
+            (Some(arg), None) if is_keyword(&arg, "nosave") => Some(false),
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(Shutdown { save })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resp::RespDecoder, BulkString};
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_save_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$6\r\nbgsave\r\n");
+        BgSave::try_from(RespArray::decode(&mut buf)?)?;
+
+        buf.extend_from_slice(b"*2\r\n$4\r\nsave\r\n$3\r\nnow\r\n");
+        assert!(Save::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*2\r\n$8\r\nlastsave\r\n$1\r\nx\r\n");
+        assert!(LastSave::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*1\r\n$12\r\nBGREWRITEAOF\r\n");
+        BgRewriteAof::try_from(RespArray::decode(&mut buf)?)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_shutdown_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$8\r\nshutdown\r\n");
+        assert_eq!(Shutdown::try_from(RespArray::decode(&mut buf)?)?.save, None);
+
+        buf.extend_from_slice(b"*2\r\n$8\r\nshutdown\r\n$6\r\nNOSAVE\r\n");
+        assert_eq!(
+            Shutdown::try_from(RespArray::decode(&mut buf)?)?.save,
+            Some(false)
+        );
+
+        buf.extend_from_slice(b"*2\r\n$8\r\nshutdown\r\n$4\r\nsave\r\n");
+        assert_eq!(
+            Shutdown::try_from(RespArray::decode(&mut buf)?)?.save,
+            Some(true)
+        );
+
+        buf.extend_from_slice(b"*3\r\n$8\r\nshutdown\r\n$4\r\nsave\r\n$6\r\nnosave\r\n");
+        assert!(Shutdown::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_aof() -> Result<()> {
+        let backend = Backend::new();
+        let dir =
+            std::env::temp_dir().join(format!("simple-redis-load-aof-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        backend.set_dir(dir.clone());
+        assert_eq!(load_aof(&backend)?, None);
+
+        std::fs::write(
+            backend.aof_path(),
+            b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n\
+              *3\r\n$5\r\nRPUSH\r\n$4\r\nlist\r\n$1\r\na\r\n",
+        )?;
+        assert_eq!(load_aof(&backend)?, Some(2));
+        assert_eq!(
+            backend.get(b"key").unwrap(),
+            Some(BulkString::from("value").into())
+        );
+        assert_eq!(backend.lrange(b"list", 0, -1).unwrap().len(), 1);
+
+        std::fs::write(
+            backend.aof_path(),
+            b"*2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$3\r\none\r\n",
+        )?;
+        assert_eq!(load_aof(&backend)?, Some(2));
+        let db = backend.select(1).unwrap();
+        assert_eq!(
+            db.get(b"key").unwrap(),
+            Some(BulkString::from("one").into())
+        );
+        assert_eq!(
+            backend.get(b"key").unwrap(),
+            Some(BulkString::from("value").into())
+        );
+
+        std::fs::write(backend.aof_path(), b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n")?;
+        assert!(load_aof(&backend).is_err());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_cmd_execute() {
+        let backend = Backend::new();
+        let dir =
+            std::env::temp_dir().join(format!("simple-redis-save-cmd-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        backend.set_dir(dir.clone());
+        backend.set("key".into(), BulkString::from("value").into());
+        assert_eq!(Save.execute(&backend), RESP_OK.clone());
+        assert!(dir.join("dump.rdb").is_file());
+        let RespFrame::Integer(last_save) = LastSave.execute(&backend) else {
+            panic!("expected an integer");
+        };
+        assert_eq!(last_save as u64, backend.lastsave());
+        assert_eq!(backend.save_stats().changes_since_last_save, 0);
+
+        backend.set_dir(dir.join("missing"));
+        assert!(matches!(Save.execute(&backend), RespFrame::SimpleError(_)));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,220 @@
+use super::{extract_args, CommandError, CommandExecutor, KeySpec};
+use crate::{Backend, BulkString, RespArray, RespFrame};
+use lazy_static::lazy_static;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+lazy_static! {
+    static ref PLUGINS: RwLock<HashMap<String, Arc<dyn CommandPlugin>>> = RwLock::default();
+}
+
+/// A command added by another crate, run when a request names no built-in command.
+pub trait CommandPlugin: Send + Sync {
+    /// The name of the command, matched regardless of case.
+    fn name(&self) -> &str;
+
+    /// The number of arguments including the command name, or minus the least number of them
+    /// when it takes a variable number, as in Redis.
+    fn arity(&self) -> i64;
+
+    /// Where the command takes keys in its request, which decide the node serving it in
+    /// cluster mode.
+    fn key_specs(&self) -> &'static [KeySpec] {
+        &[]
+    }
+
+    /// Whether the command may modify the dataset, and so must be logged in the AOF and sent
+    /// to the replicas.
+    fn is_write(&self) -> bool {
+        false
+    }
+
+    /// Runs the command with its arguments, the command name excluded.
+    fn execute(&self, backend: &Backend, args: &[BulkString]) -> RespFrame;
+}
+
+/// Registers a command plugin, replacing the one registered with the same name.
+pub fn register_plugin(plugin: impl CommandPlugin + 'static) {
+    let name = plugin.name().to_ascii_lowercase();
+    PLUGINS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(name, Arc::new(plugin));
+}
+
+// The plugin registered under the lower-cased `name`.
+pub(super) fn find_plugin(name: &[u8]) -> Option<Arc<dyn CommandPlugin>> {
+    let name = std::str::from_utf8(name).ok()?;
+    PLUGINS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .cloned()
+}
+
+// The registered plugins, by name.
+pub(super) fn plugins() -> Vec<Arc<dyn CommandPlugin>> {
+    let plugins = PLUGINS.read().unwrap_or_else(|e| e.into_inner());
+    let mut plugins: Vec<_> = plugins.iter().collect();
+    plugins.sort_by(|a, b| a.0.cmp(b.0));
+    plugins
+        .into_iter()
+        .map(|(_, plugin)| plugin.clone())
+        .collect()
+}
+
+pub struct PluginCommand {
+    plugin: Arc<dyn CommandPlugin>,
+    args: Vec<BulkString>,
+}
+
+impl PluginCommand {
+    pub(super) fn new(
+        plugin: Arc<dyn CommandPlugin>,
+        value: RespArray,
+    ) -> Result<Self, CommandError> {
+        let arity = plugin.arity();
+        let len = value.len() as i64;
+        if (arity >= 0 && len != arity) || (arity < 0 && len < -arity) {
+            return Err(CommandError::InvalidArgument(format!(
+                "wrong number of arguments for '{}' command",
+                plugin.name()
+            )));
+        }
+        let args = extract_args(value, 1)?
+            .0
+            .into_iter()
+            .map(|arg| match arg {
+                RespFrame::BulkString(arg) => Ok(arg),
+                _ => Err(CommandError::InvalidCommandArguments(
+                    "Argument must be of the BulkString type".to_string(),
+                )),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(PluginCommand { plugin, args })
+    }
+
+    pub fn key_specs(&self) -> &'static [KeySpec] {
+        self.plugin.key_specs()
+    }
+
+    pub fn is_write(&self) -> bool {
+        self.plugin.is_write()
+    }
+}
+
+impl fmt::Debug for PluginCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PluginCommand")
+            .field("name", &self.plugin.name())
+            .field("args", &self.args)
+            .finish()
+    }
+}
+
+impl CommandExecutor for PluginCommand {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.plugin.execute(backend, &self.args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::Command;
+    use anyhow::Result;
+
+    struct Append;
+
+    // APPENDTO key value [value ...], appending the values to the string at key
+    impl CommandPlugin for Append {
+        fn name(&self) -> &str {
+            "APPENDTO"
+        }
+
+        fn arity(&self) -> i64 {
+            -3
+        }
+
+        fn key_specs(&self) -> &'static [KeySpec] {
+            &[KeySpec::Range {
+                first: 1,
+                last: 1,
+                step: 1,
+            }]
+        }
+
+        fn is_write(&self) -> bool {
+            true
+        }
+
+        fn execute(&self, backend: &Backend, args: &[BulkString]) -> RespFrame {
+            let key = args[0].0.clone();
+            let mut value = match backend.get(&key).unwrap() {
+                Some(RespFrame::BulkString(value)) => Vec::from(value.0),
+                _ => vec![],
+            };
+            for arg in &args[1..] {
+                value.extend_from_slice(arg);
+            }
+            let len = value.len();
+            backend.set(key, BulkString::new(value).into());
+            RespFrame::Integer(len as i64)
+        }
+    }
+
+    // a plugin taking the name of a built-in command
+    struct Shadow;
+
+    impl CommandPlugin for Shadow {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn arity(&self) -> i64 {
+            2
+        }
+
+        fn execute(&self, _backend: &Backend, _args: &[BulkString]) -> RespFrame {
+            RespFrame::Integer(0)
+        }
+    }
+
+    fn request(args: &[&'static str]) -> RespFrame {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<_>>(),
+        )
+        .into()
+    }
+
+    #[test]
+    fn test_plugin_command() -> Result<()> {
+        let backend = Backend::new();
+        let frame = request(&["appendto", "foo", "a", "b"]);
+        assert!(Command::try_from(frame.clone()).is_err());
+
+        register_plugin(Append);
+        let cmd = Command::try_from(frame.clone())?;
+        assert!(cmd.is_write());
+        assert_eq!(cmd.keys(&frame), vec![b"foo".as_slice()]);
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        let cmd = Command::try_from(request(&["AppendTo", "foo", "c"]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+        assert_eq!(
+            backend.get(b"foo").unwrap(),
+            Some(BulkString::from("abc").into())
+        );
+
+        assert!(Command::try_from(request(&["appendto", "foo"])).is_err());
+        // built-in commands come first
+        register_plugin(Shadow);
+        let cmd = Command::try_from(request(&["echo", "hi"]))?;
+        assert!(matches!(cmd, Command::Echo(_)));
+        Ok(())
+    }
+}
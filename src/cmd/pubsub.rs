@@ -0,0 +1,570 @@
+use super::{
+    connection_only, extract_args, extract_string, validate_command, CommandError, CommandExecutor,
+};
+use crate::{backend::Subscriber, Backend, BulkString, RespArray, RespFrame, RespNull};
+
+// The reply confirming a change to the subscriptions, with how many are left.
+fn confirmation(kind: &'static str, name: Option<String>, count: usize) -> RespFrame {
+    let name = match name {
+        Some(name) => BulkString::from(name).into(),
+        None => RespFrame::Null(RespNull),
+    };
+    RespArray::new([
+        BulkString::from(kind).into(),
+        name,
+        RespFrame::Integer(count as i64),
+    ])
+    .into()
+}
+
+#[derive(Debug)]
+pub struct Subscribe(Vec<String>);
+
+impl CommandExecutor for Subscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("SUBSCRIBE")
+    }
+}
+
+impl Subscribe {
+    pub fn execute_subscriber(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        self.0
+            .into_iter()
+            .map(|channel| {
+                let count = subscriber.subscribe(&channel);
+                confirmation("subscribe", Some(channel), count)
+            })
+            .collect()
+    }
+}
+
+// channel [channel ...]
+impl TryFrom<RespArray> for Subscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["subscribe"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Subscribe(args.try_into()?))
+    }
+}
+
+#[derive(Debug)]
+pub struct Unsubscribe(Vec<String>);
+
+impl CommandExecutor for Unsubscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("UNSUBSCRIBE")
+    }
+}
+
+impl Unsubscribe {
+    /// Unsubscribes from the given channels, or from all of them when none is given.
+    pub fn execute_subscriber(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        let channels = match self.0.is_empty() {
+            true => subscriber.channels(),
+            false => self.0,
+        };
+        if channels.is_empty() {
+            return vec![confirmation("unsubscribe", None, subscriber.count())];
+        }
+        channels
+            .into_iter()
+            .map(|channel| {
+                let count = subscriber.unsubscribe(&channel);
+                confirmation("unsubscribe", Some(channel), count)
+            })
+            .collect()
+    }
+}
+
+// [channel [channel ...]]
+impl TryFrom<RespArray> for Unsubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["unsubscribe"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Unsubscribe(
+            args.0
+                .into_iter()
+                .map(extract_string)
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+#[derive(Debug)]
+pub struct PSubscribe(Vec<String>);
+
+impl CommandExecutor for PSubscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("PSUBSCRIBE")
+    }
+}
+
+impl PSubscribe {
+    pub fn execute_subscriber(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        self.0
+            .into_iter()
+            .map(|pattern| {
+                let count = subscriber.psubscribe(&pattern);
+                confirmation("psubscribe", Some(pattern), count)
+            })
+            .collect()
+    }
+}
+
+// pattern [pattern ...]
+impl TryFrom<RespArray> for PSubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["psubscribe"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(PSubscribe(args.try_into()?))
+    }
+}
+
+#[derive(Debug)]
+pub struct PUnsubscribe(Vec<String>);
+
+impl CommandExecutor for PUnsubscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("PUNSUBSCRIBE")
+    }
+}
+
+impl PUnsubscribe {
+    /// Unsubscribes from the given patterns, or from all of them when none is given.
+    pub fn execute_subscriber(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        let patterns = match self.0.is_empty() {
+            true => subscriber.patterns(),
+            false => self.0,
+        };
+        if patterns.is_empty() {
+            return vec![confirmation("punsubscribe", None, subscriber.count())];
+        }
+        patterns
+            .into_iter()
+            .map(|pattern| {
+                let count = subscriber.punsubscribe(&pattern);
+                confirmation("punsubscribe", Some(pattern), count)
+            })
+            .collect()
+    }
+}
+
+// [pattern [pattern ...]]
+impl TryFrom<RespArray> for PUnsubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["punsubscribe"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(PUnsubscribe(
+            args.0
+                .into_iter()
+                .map(extract_string)
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+#[derive(Debug)]
+pub struct Publish {
+    channel: String,
+    message: BulkString,
+}
+
+impl CommandExecutor for Publish {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.publish(&self.channel, &self.message) as i64)
+    }
+}
+
+// channel message
+impl TryFrom<RespArray> for Publish {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["publish"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        match <[RespFrame; 2]>::try_from(args.0) {
+            Ok([channel, RespFrame::BulkString(message)]) => Ok(Publish {
+                channel: extract_string(channel)?,
+                message,
+            }),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "publish takes a channel and a message".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SSubscribe(Vec<String>);
+
+impl CommandExecutor for SSubscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("SSUBSCRIBE")
+    }
+}
+
+impl SSubscribe {
+    pub fn execute_subscriber(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        self.0
+            .into_iter()
+            .map(|channel| {
+                let count = subscriber.ssubscribe(&channel);
+                confirmation("ssubscribe", Some(channel), count)
+            })
+            .collect()
+    }
+}
+
+// shardchannel [shardchannel ...]
+impl TryFrom<RespArray> for SSubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["ssubscribe"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(SSubscribe(args.try_into()?))
+    }
+}
+
+#[derive(Debug)]
+pub struct SUnsubscribe(Vec<String>);
+
+impl CommandExecutor for SUnsubscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("SUNSUBSCRIBE")
+    }
+}
+
+impl SUnsubscribe {
+    /// Unsubscribes from the given shard channels, or from all of them when none is given.
+    pub fn execute_subscriber(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        let channels = match self.0.is_empty() {
+            true => subscriber.shard_channels(),
+            false => self.0,
+        };
+        if channels.is_empty() {
+            return vec![confirmation("sunsubscribe", None, subscriber.shard_count())];
+        }
+        channels
+            .into_iter()
+            .map(|channel| {
+                let count = subscriber.sunsubscribe(&channel);
+                confirmation("sunsubscribe", Some(channel), count)
+            })
+            .collect()
+    }
+}
+
+// [shardchannel [shardchannel ...]]
+impl TryFrom<RespArray> for SUnsubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["sunsubscribe"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(SUnsubscribe(
+            args.0
+                .into_iter()
+                .map(extract_string)
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+#[derive(Debug)]
+pub struct SPublish(Publish);
+
+impl CommandExecutor for SPublish {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.spublish(&self.0.channel, &self.0.message) as i64)
+    }
+}
+
+// shardchannel message
+impl TryFrom<RespArray> for SPublish {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["spublish"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        match <[RespFrame; 2]>::try_from(args.0) {
+            Ok([channel, RespFrame::BulkString(message)]) => Ok(SPublish(Publish {
+                channel: extract_string(channel)?,
+                message,
+            })),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "spublish takes a shard channel and a message".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PubSubChannels(Option<String>);
+
+impl CommandExecutor for PubSubChannels {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let channels: Vec<RespFrame> = backend
+            .pubsub_channels(self.0.as_deref())
+            .into_iter()
+            .map(|channel| BulkString::from(channel).into())
+            .collect();
+        RespArray::new(channels).into()
+    }
+}
+
+// [pattern]
+impl TryFrom<RespArray> for PubSubChannels {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["pubsub", "channels"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        match <[RespFrame; 1]>::try_from(args.0) {
+            Ok([pattern]) => Ok(PubSubChannels(Some(extract_string(pattern)?))),
+            Err(args) if args.is_empty() => Ok(PubSubChannels(None)),
+            Err(_) => Err(CommandError::InvalidCommandArguments(
+                "pubsub channels takes at most one pattern".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PubSubNumSub(Vec<String>);
+
+impl CommandExecutor for PubSubNumSub {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let counts: Vec<RespFrame> = self
+            .0
+            .into_iter()
+            .flat_map(|channel| {
+                let count = backend.pubsub_numsub(&channel) as i64;
+                [BulkString::from(channel).into(), RespFrame::Integer(count)]
+            })
+            .collect();
+        RespArray::new(counts).into()
+    }
+}
+
+// [channel [channel ...]]
+impl TryFrom<RespArray> for PubSubNumSub {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["pubsub", "numsub"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(PubSubNumSub(
+            args.0
+                .into_iter()
+                .map(extract_string)
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+#[derive(Debug)]
+pub struct PubSubShardChannels(Option<String>);
+
+impl CommandExecutor for PubSubShardChannels {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let channels: Vec<RespFrame> = backend
+            .pubsub_shard_channels(self.0.as_deref())
+            .into_iter()
+            .map(|channel| BulkString::from(channel).into())
+            .collect();
+        RespArray::new(channels).into()
+    }
+}
+
+// [pattern]
+impl TryFrom<RespArray> for PubSubShardChannels {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["pubsub", "shardchannels"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        match <[RespFrame; 1]>::try_from(args.0) {
+            Ok([pattern]) => Ok(PubSubShardChannels(Some(extract_string(pattern)?))),
+            Err(args) if args.is_empty() => Ok(PubSubShardChannels(None)),
+            Err(_) => Err(CommandError::InvalidCommandArguments(
+                "pubsub shardchannels takes at most one pattern".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PubSubShardNumSub(Vec<String>);
+
+impl CommandExecutor for PubSubShardNumSub {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let counts: Vec<RespFrame> = self
+            .0
+            .into_iter()
+            .flat_map(|channel| {
+                let count = backend.pubsub_shard_numsub(&channel) as i64;
+                [BulkString::from(channel).into(), RespFrame::Integer(count)]
+            })
+            .collect();
+        RespArray::new(counts).into()
+    }
+}
+
+// [shardchannel [shardchannel ...]]
+impl TryFrom<RespArray> for PubSubShardNumSub {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["pubsub", "shardnumsub"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(PubSubShardNumSub(
+            args.0
+                .into_iter()
+                .map(extract_string)
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+#[derive(Debug)]
+pub struct PubSubNumPat;
+
+impl CommandExecutor for PubSubNumPat {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.pubsub_numpat() as i64)
+    }
+}
+
+impl TryFrom<RespArray> for PubSubNumPat {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["pubsub", "numpat"];
+        validate_command(&value, &cmd_names)?;
+        if value.len() != cmd_names.len() {
+            return Err(CommandError::InvalidCommandArguments(
+                "pubsub numpat takes no arguments".to_string(),
+            ));
+        }
+        Ok(PubSubNumPat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::RespDecoder;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_pubsub_cmds_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n$5\r\nsport\r\n");
+        let cmd = Subscribe::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0, vec!["news", "sport"]);
+
+        buf.extend_from_slice(b"*1\r\n$9\r\nsubscribe\r\n");
+        assert!(Subscribe::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*1\r\n$11\r\nunsubscribe\r\n");
+        let cmd = Unsubscribe::try_from(RespArray::decode(&mut buf)?)?;
+        assert!(cmd.0.is_empty());
+
+        buf.extend_from_slice(b"*3\r\n$7\r\npublish\r\n$4\r\nnews\r\n$5\r\nhello\r\n");
+        let cmd = Publish::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.channel, "news");
+        assert_eq!(cmd.message, BulkString::from("hello"));
+
+        buf.extend_from_slice(b"*4\r\n$6\r\npubsub\r\n$8\r\nchannels\r\n$1\r\na\r\n$1\r\nb\r\n");
+        assert!(PubSubChannels::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*3\r\n$6\r\npubsub\r\n$6\r\nnumpat\r\n$1\r\na\r\n");
+        assert!(PubSubNumPat::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pubsub_cmds_execute() {
+        let backend = Backend::new();
+        let mut subscriber = backend.subscriber();
+        let frames = Subscribe(vec!["news".to_string(), "sport".to_string()])
+            .execute_subscriber(&mut subscriber);
+        assert_eq!(
+            frames,
+            vec![
+                confirmation("subscribe", Some("news".to_string()), 1),
+                confirmation("subscribe", Some("sport".to_string()), 2),
+            ]
+        );
+        PSubscribe(vec!["n*".to_string()]).execute_subscriber(&mut subscriber);
+
+        assert_eq!(
+            PubSubChannels(Some("s*".to_string())).execute(&backend),
+            RespArray::new([BulkString::from("sport").into()]).into()
+        );
+        assert_eq!(
+            PubSubNumSub(vec!["news".to_string(), "weather".to_string()]).execute(&backend),
+            RespArray::new([
+                BulkString::from("news").into(),
+                RespFrame::Integer(1),
+                BulkString::from("weather").into(),
+                RespFrame::Integer(0),
+            ])
+            .into()
+        );
+        assert_eq!(PubSubNumPat.execute(&backend), RespFrame::Integer(1));
+        let cmd = Publish {
+            channel: "news".to_string(),
+            message: BulkString::from("hello"),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let frames = Unsubscribe(vec![]).execute_subscriber(&mut subscriber);
+        assert_eq!(
+            frames,
+            vec![
+                confirmation("unsubscribe", Some("news".to_string()), 2),
+                confirmation("unsubscribe", Some("sport".to_string()), 1),
+            ]
+        );
+        let frames = Unsubscribe(vec![]).execute_subscriber(&mut subscriber);
+        assert_eq!(frames, vec![confirmation("unsubscribe", None, 1)]);
+
+        let frames = SSubscribe(vec!["news".to_string()]).execute_subscriber(&mut subscriber);
+        assert_eq!(
+            frames,
+            vec![confirmation("ssubscribe", Some("news".to_string()), 1)]
+        );
+        assert_eq!(
+            PubSubShardNumSub(vec!["news".to_string()]).execute(&backend),
+            RespArray::new([BulkString::from("news").into(), RespFrame::Integer(1)]).into()
+        );
+        let cmd = SPublish(Publish {
+            channel: "news".to_string(),
+            message: BulkString::from("hello"),
+        });
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let frames = SUnsubscribe(vec![]).execute_subscriber(&mut subscriber);
+        assert_eq!(
+            frames,
+            vec![confirmation("sunsubscribe", Some("news".to_string()), 0)]
+        );
+        assert_eq!(
+            PubSubShardChannels(None).execute(&backend),
+            RespArray::new(Vec::<RespFrame>::new()).into()
+        );
+        assert_eq!(
+            Subscribe(vec!["news".to_string()]).execute(&backend),
+            connection_only("SUBSCRIBE")
+        );
+    }
+}
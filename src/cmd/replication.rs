@@ -0,0 +1,449 @@
+use super::{extract_args, validate_command, CommandError, CommandExecutor, RESP_OK};
+use crate::{
+    backend::{FailoverState, MasterAddr},
+    Backend, RespArray, RespFrame, SimpleError, SimpleString,
+};
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+
+#[derive(Debug)]
+pub struct ReplicaOf(Option<MasterAddr>);
+
+impl CommandExecutor for ReplicaOf {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if self.0.is_some() && backend.master() == self.0 {
+            return SimpleString::new("OK Already connected to specified master").into();
+        }
+        backend.set_master(self.0);
+        RESP_OK.clone()
+    }
+}
+
+// host port | NO ONE
+impl TryFrom<RespArray> for ReplicaOf {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["replicaof"];
+        validate_command(&value, &cmd_names)?;
+        let args = Vec::<String>::try_from(extract_args(value, cmd_names.len())?)?;
+        let [host, port] = <[String; 2]>::try_from(args).map_err(|_| {
+            CommandError::InvalidCommandArguments("replicaof takes a host and a port".to_string())
+        })?;
+        if host.eq_ignore_ascii_case("no") && port.eq_ignore_ascii_case("one") {
+            return Ok(ReplicaOf(None));
+        }
+        let port = port
+            .parse()
+            .map_err(|_| CommandError::InvalidArgument("Invalid master port".to_string()))?;
+        Ok(ReplicaOf(Some(MasterAddr { host, port })))
+    }
+}
+
+// Sent by replicas during the handshake, which the master only acknowledges.
+#[derive(Debug)]
+pub struct ReplConf(Vec<(String, String)>);
+
+impl CommandExecutor for ReplConf {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        RESP_OK.clone()
+    }
+}
+
+// option value [option value ...]
+impl TryFrom<RespArray> for ReplConf {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["replconf"];
+        validate_command(&value, &cmd_names)?;
+        let args = Vec::<String>::try_from(extract_args(value, cmd_names.len())?)?;
+        if !args.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidCommandArguments(
+                "replconf must have option value pairs".to_string(),
+            ));
+        }
+        let mut args = args.into_iter();
+        let mut options = Vec::new();
+        while let (Some(name), Some(value)) = (args.next(), args.next()) {
+            options.push((name.to_ascii_lowercase(), value));
+        }
+        Ok(ReplConf(options))
+    }
+}
+
+impl ReplConf {
+    /// The port a replica announces with `REPLCONF listening-port`.
+    pub fn listening_port(&self) -> Option<u16> {
+        self.option("listening-port")
+            .and_then(|port| port.parse().ok())
+    }
+
+    /// The offset a replica acknowledges with `REPLCONF ACK`.
+    pub fn ack(&self) -> Option<u64> {
+        self.option("ack").and_then(|offset| offset.parse().ok())
+    }
+
+    /// Whether this is the `REPLCONF GETACK *` of a master asking for an acknowledgement.
+    pub fn is_getack(&self) -> bool {
+        self.option("getack").is_some()
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub struct Wait {
+    replicas: usize,
+    timeout: Option<Duration>,
+}
+
+impl CommandExecutor for Wait {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.acked_replicas(backend.repl_offset()) as i64)
+    }
+}
+
+impl Wait {
+    /// Blocks until `replicas` replicas acknowledged the writes made so far, or the timeout
+    /// elapses, and returns how many did.
+    pub async fn execute_blocking(self, backend: &Backend) -> RespFrame {
+        if backend.master().is_some() {
+            return SimpleError::new("ERR WAIT cannot be used with replica instances.").into();
+        }
+        let offset = backend.repl_offset();
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let mut asked = false;
+        loop {
+            // register interest before counting so an acknowledgement in between isn't missed
+            let notified = backend.replica_acked();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let acked = backend.acked_replicas(offset);
+            if acked >= self.replicas {
+                return RespFrame::Integer(acked as i64);
+            }
+            if !asked {
+                backend.request_acks();
+                asked = true;
+            }
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                        return RespFrame::Integer(backend.acked_replicas(offset) as i64);
+                    }
+                }
+                None => notified.await,
+            }
+        }
+    }
+}
+
+// numreplicas timeout
+impl TryFrom<RespArray> for Wait {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["wait"];
+        validate_command(&value, &cmd_names)?;
+        let args = Vec::<String>::try_from(extract_args(value, cmd_names.len())?)?;
+        let [replicas, timeout] = <[String; 2]>::try_from(args).map_err(|_| {
+            CommandError::InvalidCommandArguments(
+                "wait takes a number of replicas and a timeout".to_string(),
+            )
+        })?;
+        let replicas = replicas.parse().map_err(|_| {
+            CommandError::InvalidArgument("value is out of range, must be positive".to_string())
+        })?;
+        let timeout = timeout.parse::<u64>().map_err(|_| {
+            CommandError::InvalidArgument("timeout is not an integer or out of range".to_string())
+        })?;
+        Ok(Wait {
+            replicas,
+            // a timeout of zero blocks indefinitely
+            timeout: (timeout > 0).then(|| Duration::from_millis(timeout)),
+        })
+    }
+}
+
+// Hands the master role over to a replica: writes are paused until the replica caught up,
+// then this server becomes its replica. The replica takes over when this server connects to it.
+#[derive(Debug, Default)]
+pub struct Failover {
+    target: Option<MasterAddr>,
+    force: bool,
+    abort: bool,
+    timeout: Option<Duration>,
+}
+
+impl CommandExecutor for Failover {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if self.abort {
+            return if backend.end_failover(false) {
+                RESP_OK.clone()
+            } else {
+                SimpleError::new("ERR No failover in progress.").into()
+            };
+        }
+        if backend.master().is_some() {
+            return SimpleError::new("ERR FAILOVER is not valid when server is a replica.").into();
+        }
+        if !backend.has_replicas() {
+            return SimpleError::new("ERR FAILOVER requires connected replicas.").into();
+        }
+        if let Some(target) = &self.target {
+            if !backend.is_replica(target) {
+                return SimpleError::new("ERR FAILOVER target HOST and PORT is not a replica.")
+                    .into();
+            }
+        }
+        if self.force && (self.target.is_none() || self.timeout.is_none()) {
+            return SimpleError::new(
+                "ERR FAILOVER with force option requires both a timeout and target HOST and IP.",
+            )
+            .into();
+        }
+        if let Err(e) = backend.start_failover() {
+            return SimpleError::new(format!("ERR {}", e)).into();
+        }
+        tokio::spawn(self.wait_for_sync(backend.clone()));
+        RESP_OK.clone()
+    }
+}
+
+impl Failover {
+    // Waits for the target, or any replica, to acknowledge the writes made so far, then hands
+    // over to it. Unless forced, the failover is aborted when the timeout elapses first.
+    async fn wait_for_sync(self, backend: Backend) {
+        let offset = backend.repl_offset();
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        backend.request_acks();
+        loop {
+            // register interest before checking so an acknowledgement in between isn't missed
+            let notified = backend.replica_acked();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            if backend.failover_state() != FailoverState::WaitingForSync {
+                // aborted
+                return;
+            }
+            if let Some(replica) = backend.caught_up_replica(offset, self.target.as_ref()) {
+                info!("Failing over to {}:{}", replica.host, replica.port);
+                backend.hand_over(replica);
+                return;
+            }
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                        break;
+                    }
+                }
+                None => notified.await,
+            }
+        }
+        match self.target {
+            Some(target) if self.force => {
+                warn!("Forcing failover to {}:{}", target.host, target.port);
+                backend.hand_over(target);
+            }
+            _ => {
+                warn!("Failover timed out before a replica caught up, aborting");
+                backend.end_failover(false);
+            }
+        }
+    }
+}
+
+// [TO host port [FORCE]] [ABORT] [TIMEOUT milliseconds]
+impl TryFrom<RespArray> for Failover {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["failover"];
+        validate_command(&value, &cmd_names)?;
+        let args = Vec::<String>::try_from(extract_args(value, cmd_names.len())?)?;
+        let mut args = args.into_iter();
+        let mut failover = Failover::default();
+        while let Some(arg) = args.next() {
+            match arg.to_ascii_lowercase().as_str() {
+                "to" if failover.target.is_none() => {
+                    let (Some(host), Some(port)) = (args.next(), args.next()) else {
+                        return Err(CommandError::InvalidCommandArguments(
+                            "failover TO takes a host and a port".to_string(),
+                        ));
+                    };
+                    let port = port
+                        .parse()
+                        .map_err(|_| CommandError::InvalidArgument("Invalid port".to_string()))?;
+                    failover.target = Some(MasterAddr { host, port });
+                }
+                "force" if !failover.force => failover.force = true,
+                "abort" if !failover.abort => failover.abort = true,
+                "timeout" if failover.timeout.is_none() => {
+                    let timeout = args
+                        .next()
+                        .and_then(|timeout| timeout.parse::<u64>().ok())
+                        .filter(|timeout| *timeout > 0)
+                        .ok_or_else(|| {
+                            CommandError::InvalidArgument(
+                                "FAILOVER timeout must be greater than 0".to_string(),
+                            )
+                        })?;
+                    failover.timeout = Some(Duration::from_millis(timeout));
+                }
+                _ => {
+                    return Err(CommandError::InvalidCommandArguments(format!(
+                        "syntax error near '{}'",
+                        arg
+                    )))
+                }
+            }
+        }
+        if failover.abort
+            && (failover.target.is_some() || failover.force || failover.timeout.is_some())
+        {
+            return Err(CommandError::InvalidArgument(
+                "FAILOVER ABORT can't be used with other options".to_string(),
+            ));
+        }
+        Ok(failover)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::RespDecoder;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_replicaof_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$9\r\nreplicaof\r\n$9\r\nlocalhost\r\n$4\r\n6380\r\n");
+        let cmd = ReplicaOf::try_from(RespArray::decode(&mut buf)?)?;
+        let master = MasterAddr {
+            host: "localhost".to_string(),
+            port: 6380,
+        };
+        assert_eq!(cmd.0, Some(master));
+
+        buf.extend_from_slice(b"*3\r\n$9\r\nREPLICAOF\r\n$2\r\nno\r\n$3\r\nONE\r\n");
+        assert_eq!(ReplicaOf::try_from(RespArray::decode(&mut buf)?)?.0, None);
+
+        buf.extend_from_slice(b"*3\r\n$9\r\nreplicaof\r\n$4\r\nhost\r\n$4\r\nport\r\n");
+        assert!(ReplicaOf::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_replicaof_cmd_execute() {
+        let backend = Backend::new();
+        let master = MasterAddr {
+            host: "localhost".to_string(),
+            port: 6380,
+        };
+        assert_eq!(
+            ReplicaOf(Some(master.clone())).execute(&backend),
+            RESP_OK.clone()
+        );
+        assert_eq!(backend.master(), Some(master.clone()));
+        assert_eq!(
+            ReplicaOf(Some(master)).execute(&backend),
+            SimpleString::new("OK Already connected to specified master").into()
+        );
+        ReplicaOf(None).execute(&backend);
+        assert_eq!(backend.master(), None);
+    }
+
+    #[test]
+    fn test_replconf_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$8\r\nREPLCONF\r\n$14\r\nlistening-port\r\n$4\r\n6380\r\n");
+        let cmd = ReplConf::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.listening_port(), Some(6380));
+
+        buf.extend_from_slice(b"*3\r\n$8\r\nreplconf\r\n$3\r\nACK\r\n$2\r\n42\r\n");
+        let cmd = ReplConf::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!((cmd.ack(), cmd.is_getack()), (Some(42), false));
+
+        buf.extend_from_slice(b"*2\r\n$8\r\nreplconf\r\n$4\r\ncapa\r\n");
+        assert!(ReplConf::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wait_cmd() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$4\r\nwait\r\n$1\r\n1\r\n$2\r\n10\r\n");
+        let cmd = Wait::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.timeout, Some(Duration::from_millis(10)));
+
+        // no replica acknowledges in time
+        let backend = Backend::new();
+        assert_eq!(cmd.execute_blocking(&backend).await, RespFrame::Integer(0));
+        let cmd = Wait {
+            replicas: 0,
+            timeout: None,
+        };
+        assert_eq!(cmd.execute_blocking(&backend).await, RespFrame::Integer(0));
+
+        buf.extend_from_slice(b"*3\r\n$4\r\nwait\r\n$1\r\n1\r\n$2\r\n-1\r\n");
+        assert!(Wait::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_failover_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$8\r\nfailover\r\n$2\r\nTO\r\n$9\r\n127.0.0.1\r\n$4\r\n6380\r\n$5\r\nFORCE\r\n$7\r\ntimeout\r\n$3\r\n100\r\n",
+        );
+        let cmd = Failover::try_from(RespArray::decode(&mut buf)?)?;
+        let target = MasterAddr {
+            host: "127.0.0.1".to_string(),
+            port: 6380,
+        };
+        assert_eq!(cmd.target, Some(target));
+        assert!(cmd.force && !cmd.abort);
+        assert_eq!(cmd.timeout, Some(Duration::from_millis(100)));
+
+        buf.extend_from_slice(b"*2\r\n$8\r\nfailover\r\n$5\r\nabort\r\n");
+        assert!(Failover::try_from(RespArray::decode(&mut buf)?)?.abort);
+
+        buf.extend_from_slice(b"*3\r\n$8\r\nfailover\r\n$5\r\nabort\r\n$5\r\nforce\r\n");
+        assert!(Failover::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*3\r\n$8\r\nfailover\r\n$7\r\ntimeout\r\n$1\r\n0\r\n");
+        assert!(Failover::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_failover_cmd_execute() {
+        let backend = Backend::new();
+        assert_eq!(
+            Failover::default().execute(&backend),
+            SimpleError::new("ERR FAILOVER requires connected replicas.").into()
+        );
+        let abort = Failover {
+            abort: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            abort.execute(&backend),
+            SimpleError::new("ERR No failover in progress.").into()
+        );
+        backend.set_master(Some(MasterAddr {
+            host: "127.0.0.1".to_string(),
+            port: 6380,
+        }));
+        assert_eq!(
+            Failover::default().execute(&backend),
+            SimpleError::new("ERR FAILOVER is not valid when server is a replica.").into()
+        );
+    }
+}
@@ -0,0 +1,561 @@
+use super::{
+    extract_args, extract_integer, extract_string, unless_busy, validate_command, Command,
+    CommandError, CommandExecutor, RESP_OK,
+};
+use crate::{Backend, BulkString, RespArray, RespFrame, RespNull, SimpleError, SimpleString};
+use mlua::{HookTriggers, Lua, LuaOptions, StdLib, Value, Variadic};
+use tokio::task;
+
+// redis.call raises the error replies redis.pcall returns
+const PRELUDE: &str = r#"
+redis.call = function(...)
+    local reply = redis.pcall(...)
+    if type(reply) == 'table' and reply.err then
+        error(reply)
+    end
+    return reply
+end
+redis.error_reply = function(err) return {err = err} end
+redis.status_reply = function(ok) return {ok = ok} end
+"#;
+
+// how often a running script checks whether it was killed
+const KILL_CHECK_INSTRUCTIONS: u32 = 10_000;
+
+// runs the script it is passed, replying with the error it raises if any
+const RUNNER: &str = r#"
+local ok, reply = pcall(...)
+if ok or (type(reply) == 'table' and reply.err) then
+    return reply
+end
+return {err = 'ERR Error running script: ' .. tostring(reply)}
+"#;
+
+#[derive(Debug)]
+pub struct Eval {
+    body: String,
+    call: ScriptCall,
+}
+
+#[derive(Debug)]
+pub struct EvalSha {
+    sha: String,
+    call: ScriptCall,
+}
+
+#[derive(Debug)]
+pub struct ScriptLoad(String);
+
+#[derive(Debug)]
+pub struct ScriptKill;
+
+// The keys and arguments a script runs with, bound to its KEYS and ARGV tables.
+#[derive(Debug)]
+pub(super) struct ScriptCall {
+    pub(super) keys: Vec<BulkString>,
+    pub(super) args: Vec<BulkString>,
+}
+
+impl CommandExecutor for Eval {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend.cache_script(&self.body);
+        self.call.run(backend, &self.body)
+    }
+}
+
+impl Eval {
+    /// Runs the script with no other command in between, as the commands of a transaction.
+    pub async fn execute_atomic(self, backend: &Backend) -> RespFrame {
+        // a failover holds writes until the new master caught up with them, and the script
+        // may write
+        backend.writes_unpaused().await;
+        match unless_busy(backend, backend.transaction_lock()).await {
+            // the other connections of this worker thread are served elsewhere meanwhile
+            Ok(_lock) => task::block_in_place(|| self.execute(backend)),
+            Err(busy) => busy,
+        }
+    }
+}
+
+impl CommandExecutor for EvalSha {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.cached_script(&self.sha) {
+            Some(body) => self.call.run(backend, &body),
+            None => SimpleError::new("NOSCRIPT No matching script. Please use EVAL.").into(),
+        }
+    }
+}
+
+impl EvalSha {
+    /// Runs the script with no other command in between, as the commands of a transaction.
+    pub async fn execute_atomic(self, backend: &Backend) -> RespFrame {
+        backend.writes_unpaused().await;
+        match unless_busy(backend, backend.transaction_lock()).await {
+            Ok(_lock) => task::block_in_place(|| self.execute(backend)),
+            Err(busy) => busy,
+        }
+    }
+}
+
+impl CommandExecutor for ScriptLoad {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        BulkString::from(backend.cache_script(&self.0)).into()
+    }
+}
+
+impl CommandExecutor for ScriptKill {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.kill_script() {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => SimpleError::new(e).into(),
+        }
+    }
+}
+
+impl ScriptCall {
+    fn run(self, backend: &Backend, body: &str) -> RespFrame {
+        backend.start_script();
+        let reply = self.try_run(backend, body);
+        if backend.end_script() {
+            return SimpleError::new("ERR Script killed by user with SCRIPT KILL...").into();
+        }
+        match reply {
+            Ok(frame) => frame,
+            Err(e) => SimpleError::new(format!("ERR Error running script: {}", e)).into(),
+        }
+    }
+
+    fn try_run(self, backend: &Backend, body: &str) -> mlua::Result<RespFrame> {
+        // no io, os or module loading for scripts
+        let libs = StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8;
+        let lua = Lua::new_with(libs, LuaOptions::default())?;
+        let globals = lua.globals();
+        let redis = lua.create_table()?;
+        let caller = backend.clone();
+        let pcall = lua
+            .create_function(move |lua, args: Variadic<Value>| to_lua(lua, call(&caller, &args)))?;
+        redis.set("pcall", pcall)?;
+        globals.set("redis", redis)?;
+        lua.load(PRELUDE).exec()?;
+        let killed = backend.clone();
+        let triggers = HookTriggers::new().every_nth_instruction(KILL_CHECK_INSTRUCTIONS);
+        lua.set_hook(triggers, move |_, _| match killed.script_killed() {
+            true => Err(mlua::Error::runtime("killed")),
+            false => Ok(()),
+        });
+        for (name, values) in [("KEYS", &self.keys), ("ARGV", &self.args)] {
+            let values = values
+                .iter()
+                .map(|value| lua.create_string(value.as_slice()))
+                .collect::<mlua::Result<Vec<_>>>()?;
+            globals.set(name, lua.create_sequence_from(values)?)?;
+        }
+
+        let script = match lua.load(body).set_name("@user_script").into_function() {
+            Ok(script) => script,
+            Err(e) => {
+                let e = format!("ERR Error compiling script (new function): {}", e);
+                return Ok(SimpleError::new(e).into());
+            }
+        };
+        let reply: Value = lua.load(RUNNER).call(script)?;
+        Ok(to_frame(reply))
+    }
+}
+
+// Runs a command given as Lua values.
+fn call(backend: &Backend, args: &[Value]) -> RespFrame {
+    if args.is_empty() {
+        return SimpleError::new(
+            "ERR Please specify at least one argument for this redis lib call",
+        )
+        .into();
+    }
+    let mut command = Vec::with_capacity(args.len());
+    for arg in args {
+        let arg = match arg {
+            Value::String(s) => s.as_bytes().to_vec(),
+            Value::Integer(n) => n.to_string().into_bytes(),
+            Value::Number(n) => n.to_string().into_bytes(),
+            _ => {
+                return SimpleError::new(
+                    "ERR Lua redis lib command arguments must be strings or integers",
+                )
+                .into()
+            }
+        };
+        command.push(arg);
+    }
+    run_command(backend, command)
+}
+
+// Runs a command on behalf of a script or a function, the writes being propagated one by one.
+pub(super) fn run_command(backend: &Backend, args: Vec<Vec<u8>>) -> RespFrame {
+    let frames: Vec<RespFrame> = args
+        .into_iter()
+        .map(|arg| BulkString::new(arg).into())
+        .collect();
+    let frame: RespFrame = RespArray::new(frames).into();
+    let cmd = match Command::try_from(frame.clone()) {
+        Ok(cmd) => cmd,
+        Err(e) => return e.into(),
+    };
+    if matches!(
+        cmd,
+        Command::Eval(_)
+            | Command::EvalSha(_)
+            | Command::ScriptLoad(_)
+            | Command::ScriptKill(_)
+            | Command::FCall(_)
+            | Command::FunctionLoad(_)
+            | Command::FunctionDelete(_)
+            | Command::Shutdown(_)
+    ) {
+        return SimpleError::new("ERR This Redis command is not allowed from script").into();
+    }
+    if cmd.is_write() && backend.rejects_writes() {
+        return SimpleError::new("READONLY You can't write against a read only replica.").into();
+    }
+    if cmd.denies_oom() && !backend.evict_to_fit() {
+        return SimpleError::new("OOM command not allowed when used memory > 'maxmemory'.").into();
+    }
+    if cmd.is_write() {
+        backend.script_wrote();
+    }
+    let propagated =
+        (cmd.is_write() && backend.propagating()).then(|| cmd.propagated(frame, backend));
+    let reply = cmd.execute(backend);
+    if let Some(propagated) = propagated {
+        propagated.propagate(backend, &reply);
+    }
+    reply
+}
+
+// A reply as a Lua value, the way a RESP2 client gets it: status and error replies become
+// tables with a single `ok` or `err` field and nil replies become false.
+fn to_lua(lua: &Lua, frame: RespFrame) -> mlua::Result<Value<'_>> {
+    let value = match frame {
+        RespFrame::SimpleString(s) => Value::Table(lua.create_table_from([("ok", s.0)])?),
+        RespFrame::SimpleError(e) => Value::Table(lua.create_table_from([("err", e.0)])?),
+        RespFrame::Integer(n) => Value::Integer(n),
+        RespFrame::Boolean(b) => Value::Integer(b as i64),
+        RespFrame::BulkString(s) => Value::String(lua.create_string(s.0)?),
+        RespFrame::Double(d) => Value::String(lua.create_string(d.0.to_string())?),
+        RespFrame::BigNumber(n) => Value::String(lua.create_string(n.0.to_string())?),
+        RespFrame::Null(_) | RespFrame::NullArray(_) => Value::Boolean(false),
+        RespFrame::Array(frames) => sequence(lua, frames.0)?,
+        RespFrame::Set(frames) => sequence(lua, frames.0)?,
+        RespFrame::Push(frames) => sequence(lua, frames.0)?,
+        RespFrame::Map(map) => sequence(lua, map.0.into_iter().flat_map(|(k, v)| [k, v]))?,
+    };
+    Ok(value)
+}
+
+fn sequence(lua: &Lua, frames: impl IntoIterator<Item = RespFrame>) -> mlua::Result<Value<'_>> {
+    let values = frames
+        .into_iter()
+        .map(|frame| to_lua(lua, frame))
+        .collect::<mlua::Result<Vec<_>>>()?;
+    Ok(Value::Table(lua.create_sequence_from(values)?))
+}
+
+// The reply a Lua value stands for: numbers are truncated to integers, true is 1, false and
+// nil are nil, and a table is an array up to its first nil unless it has an `ok` or `err` field.
+fn to_frame(value: Value) -> RespFrame {
+    match value {
+        Value::Integer(n) => RespFrame::Integer(n),
+        Value::Number(n) => RespFrame::Integer(n as i64),
+        Value::Boolean(true) => RespFrame::Integer(1),
+        Value::String(s) => BulkString::new(s.as_bytes().to_vec()).into(),
+        Value::Table(table) => {
+            if let Ok(Some(e)) = table.raw_get::<_, Option<String>>("err") {
+                return SimpleError::new(e).into();
+            }
+            if let Ok(Some(s)) = table.raw_get::<_, Option<String>>("ok") {
+                return SimpleString::new(s).into();
+            }
+            let frames: Vec<RespFrame> = table
+                .sequence_values::<Value>()
+                .map_while(Result::ok)
+                .map(to_frame)
+                .collect();
+            RespArray::new(frames).into()
+        }
+        _ => RespFrame::Null(RespNull),
+    }
+}
+
+// script numkeys [key [key ...]] [arg [arg ...]]
+impl TryFrom<RespArray> for Eval {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["eval"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        let body = match args.next() {
+            Some(body) => extract_string(body)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have a script".to_string(),
+                ))
+            }
+        };
+        Ok(Eval {
+            body,
+            call: ScriptCall::try_from(args)?,
+        })
+    }
+}
+
+// sha1 numkeys [key [key ...]] [arg [arg ...]]
+impl TryFrom<RespArray> for EvalSha {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["evalsha"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        let sha = match args.next() {
+            Some(sha) => extract_string(sha)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have a sha1".to_string(),
+                ))
+            }
+        };
+        Ok(EvalSha {
+            sha,
+            call: ScriptCall::try_from(args)?,
+        })
+    }
+}
+
+impl TryFrom<std::vec::IntoIter<RespFrame>> for ScriptCall {
+    type Error = CommandError;
+    fn try_from(mut args: std::vec::IntoIter<RespFrame>) -> Result<Self, Self::Error> {
+        let numkeys = match args.next() {
+            Some(numkeys) => extract_integer(numkeys)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have numkeys".to_string(),
+                ))
+            }
+        };
+        if numkeys < 0 {
+            return Err(CommandError::InvalidArgument(
+                "Number of keys can't be negative".to_string(),
+            ));
+        }
+        if numkeys as usize > args.len() {
+            return Err(CommandError::InvalidArgument(
+                "Number of keys can't be greater than number of args".to_string(),
+            ));
+        }
+        let mut values = args.map(|arg| match arg {
+            RespFrame::BulkString(value) => Ok(value),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "Argument must be of the BulkString type".to_string(),
+            )),
+        });
+        let keys = values
+            .by_ref()
+            .take(numkeys as usize)
+            .collect::<Result<_, _>>()?;
+        let args = values.collect::<Result<_, _>>()?;
+        Ok(ScriptCall { keys, args })
+    }
+}
+
+impl TryFrom<RespArray> for ScriptKill {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["script", "kill"];
+        validate_command(&value, &cmd_names)?;
+        if value.len() != cmd_names.len() {
+            return Err(CommandError::InvalidCommandArguments(
+                "script kill takes no arguments".to_string(),
+            ));
+        }
+        Ok(ScriptKill)
+    }
+}
+
+// script
+impl TryFrom<RespArray> for ScriptLoad {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["script", "load"];
+        validate_command(&value, &cmd_names)?;
+        let mut args = extract_args(value, cmd_names.len())?.0.into_iter();
+        match (args.next(), args.next()) {
+            (Some(body), None) => Ok(ScriptLoad(extract_string(body)?)),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "script load takes a script".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::RespDecoder;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn eval(body: &str, keys: &[&str], args: &[&str]) -> Eval {
+        let bulk = |values: &[&str]| {
+            values
+                .iter()
+                .map(|v| BulkString::new(v.as_bytes().to_vec()))
+                .collect()
+        };
+        Eval {
+            body: body.to_string(),
+            call: ScriptCall {
+                keys: bulk(keys),
+                args: bulk(args),
+            },
+        }
+    }
+
+    #[test]
+    fn test_eval_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$4\r\neval\r\n$8\r\nreturn 1\r\n$1\r\n1\r\n$3\r\nfoo\r\n$3\r\nbar\r\n",
+        );
+        let cmd = Eval::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.body, "return 1");
+        assert_eq!(cmd.call.keys, vec![BulkString::from("foo")]);
+        assert_eq!(cmd.call.args, vec![BulkString::from("bar")]);
+
+        buf.extend_from_slice(b"*4\r\n$4\r\neval\r\n$8\r\nreturn 1\r\n$1\r\n2\r\n$3\r\nfoo\r\n");
+        assert!(Eval::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*3\r\n$7\r\nevalsha\r\n$3\r\nabc\r\n$2\r\n-1\r\n");
+        assert!(EvalSha::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_eval_replies() {
+        let backend = Backend::new();
+        assert_eq!(eval("return 1.9", &[], &[]).execute(&backend), 1.into());
+        assert_eq!(
+            eval("return {1, 'a', true, false, nil, 2}", &[], &[]).execute(&backend),
+            RespArray::new([
+                1.into(),
+                BulkString::from("a").into(),
+                1.into(),
+                RespFrame::Null(RespNull),
+            ])
+            .into()
+        );
+        assert_eq!(
+            eval("return redis.status_reply('DONE')", &[], &[]).execute(&backend),
+            SimpleString::new("DONE").into()
+        );
+        assert_eq!(
+            eval("return {err = 'MY failure'}", &[], &[]).execute(&backend),
+            SimpleError::new("MY failure").into()
+        );
+        assert!(matches!(
+            eval("return (", &[], &[]).execute(&backend),
+            RespFrame::SimpleError(_)
+        ));
+        assert!(matches!(
+            eval("error('boom')", &[], &[]).execute(&backend),
+            RespFrame::SimpleError(_)
+        ));
+        // no access to the host
+        assert!(matches!(
+            eval("return os.time()", &[], &[]).execute(&backend),
+            RespFrame::SimpleError(_)
+        ));
+    }
+
+    #[test]
+    fn test_eval_calls() {
+        let backend = Backend::new();
+        let cmd = eval(
+            "redis.call('set', KEYS[1], ARGV[1]); return redis.call('get', KEYS[1])",
+            &["foo"],
+            &["bar"],
+        );
+        assert_eq!(cmd.execute(&backend), BulkString::from("bar").into());
+        assert_eq!(
+            eval("return redis.call('get', 'missing')", &[], &[]).execute(&backend),
+            RespFrame::Null(RespNull)
+        );
+        assert_eq!(
+            eval("return redis.call('set', 'n', 1)", &[], &[]).execute(&backend),
+            SimpleString::new("OK").into()
+        );
+
+        // redis.call raises the error, redis.pcall returns it
+        let cmd = eval("redis.call('nosuchcmd'); return 1", &[], &[]);
+        assert!(matches!(cmd.execute(&backend), RespFrame::SimpleError(_)));
+        let cmd = eval(
+            "local reply = redis.pcall('nosuchcmd'); return type(reply.err)",
+            &[],
+            &[],
+        );
+        assert_eq!(cmd.execute(&backend), BulkString::from("string").into());
+        assert!(matches!(
+            eval("return redis.call('eval', 'return 1', 0)", &[], &[]).execute(&backend),
+            RespFrame::SimpleError(_)
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_busy_script() {
+        let backend = Backend::new();
+        backend.set_busy_reply_threshold(0);
+        let runner = backend.clone();
+        let script = tokio::task::spawn_blocking(move || {
+            let cmd = eval("while true do end", &[], &[]);
+            tokio::runtime::Handle::current().block_on(cmd.execute_atomic(&runner))
+        });
+        backend.script_busy().await;
+        assert!(matches!(
+            unless_busy(&backend, backend.command_lock()).await,
+            Err(RespFrame::SimpleError(_))
+        ));
+        assert_eq!(ScriptKill.execute(&backend), RESP_OK.clone());
+        assert_eq!(
+            script.await.unwrap(),
+            SimpleError::new("ERR Script killed by user with SCRIPT KILL...").into()
+        );
+        assert!(unless_busy(&backend, backend.command_lock()).await.is_ok());
+    }
+
+    #[test]
+    fn test_evalsha() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = EvalSha {
+            sha: "098e0f0d1448c0a81dafe820f66d460eb09263da".to_string(),
+            call: ScriptCall {
+                keys: vec![],
+                args: vec![BulkString::from("x")],
+            },
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("NOSCRIPT No matching script. Please use EVAL.").into()
+        );
+
+        let RespFrame::BulkString(sha) = ScriptLoad("return ARGV[1]".to_string()).execute(&backend)
+        else {
+            panic!("SCRIPT LOAD replies with the sha1");
+        };
+        let cmd = EvalSha {
+            sha: String::from_utf8(sha.0.into())?.to_ascii_uppercase(),
+            call: ScriptCall {
+                keys: vec![],
+                args: vec![BulkString::from("x")],
+            },
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("x").into());
+        Ok(())
+    }
+}
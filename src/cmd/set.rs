@@ -1,16 +1,19 @@
-use super::{extract_args, validate_command, CommandError, CommandExecutor, KeyValue, KeyValues};
-use crate::{Backend, RespArray, RespFrame};
+use super::{extract_args, validate_command, CommandError, KeyValue, KeyValues, StorageExecutor};
+use crate::{RespArray, RespFrame, Storage};
+use bytes::Bytes;
 use derive_more::Deref;
 
 #[derive(Debug, Deref)]
 pub struct Sadd(KeyValues);
 
-impl CommandExecutor for Sadd {
-    fn execute(self, backend: &Backend) -> RespFrame {
+impl StorageExecutor for Sadd {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
         let mut count = 0;
         for v in self.0.values {
-            if backend.sadd(self.0.key.clone(), v) {
-                count += 1;
+            match storage.sadd(self.0.key.clone(), v) {
+                Ok(true) => count += 1,
+                Ok(false) => {}
+                Err(e) => return e.into(),
             }
         }
         RespFrame::Integer(count as i64)
@@ -30,12 +33,14 @@ impl TryFrom<RespArray> for Sadd {
 #[derive(Debug, Deref)]
 pub struct Srem(KeyValues);
 
-impl CommandExecutor for Srem {
-    fn execute(self, backend: &Backend) -> RespFrame {
+impl StorageExecutor for Srem {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
         let mut count = 0;
         for v in self.values.iter() {
-            if backend.srem(&self.key, v) {
-                count += 1;
+            match storage.srem(&self.key, v) {
+                Ok(true) => count += 1,
+                Ok(false) => {}
+                Err(e) => return e.into(),
             }
         }
         RespFrame::Integer(count as i64)
@@ -55,13 +60,12 @@ impl TryFrom<RespArray> for Srem {
 #[derive(Debug, Deref)]
 pub struct Sismember(KeyValue);
 
-impl CommandExecutor for Sismember {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        let result = backend.sismember(&self.key, &self.value);
-        if result {
-            RespFrame::Integer(1)
-        } else {
-            RespFrame::Integer(0)
+impl StorageExecutor for Sismember {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        match storage.sismember(&self.key, &self.value) {
+            Ok(true) => RespFrame::Integer(1),
+            Ok(false) => RespFrame::Integer(0),
+            Err(e) => e.into(),
         }
     }
 }
@@ -77,13 +81,14 @@ impl TryFrom<RespArray> for Sismember {
 }
 
 #[derive(Debug, Deref)]
-pub struct Smembers(String);
-
-impl CommandExecutor for Smembers {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        match backend.smembers(&self) {
-            Some(set) => RespFrame::Array(set.into()),
-            None => RespFrame::Array(vec![].into()),
+pub struct Smembers(Bytes);
+
+impl StorageExecutor for Smembers {
+    fn execute_on<S: Storage + ?Sized>(self, storage: &S) -> RespFrame {
+        match storage.smembers(&self) {
+            Ok(Some(set)) => RespFrame::Array(set.into()),
+            Ok(None) => RespFrame::Array(vec![].into()),
+            Err(e) => e.into(),
         }
     }
 }
@@ -101,6 +106,7 @@ impl TryFrom<RespArray> for Smembers {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{cmd::CommandExecutor, Backend, BulkString};
 
     #[test]
     fn test_sadd() {
@@ -157,7 +163,7 @@ mod tests {
         let resp = smembers.execute(&backend);
         assert_eq!(
             resp,
-            RespFrame::Array(vec![RespFrame::SimpleString("value".into())].into())
+            RespFrame::Array(vec![BulkString::from("value").into()].into())
         );
     }
 }
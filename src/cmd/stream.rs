@@ -0,0 +1,529 @@
+use super::{
+    extract_args, extract_bytes, extract_integer, extract_string, is_keyword, validate_command,
+    CommandError, CommandExecutor,
+};
+use crate::{
+    backend::{StreamFields, StreamId, StreamIdSpec, StreamTrim, TrimStrategy},
+    Backend, BulkString, RespArray, RespFrame, SimpleError,
+};
+use bytes::Bytes;
+use derive_more::Deref;
+use std::{iter::Peekable, vec::IntoIter};
+
+#[derive(Debug)]
+pub struct XAdd {
+    key: Bytes,
+    id: StreamIdSpec,
+    fields: StreamFields,
+    trim: Option<StreamTrim>,
+}
+
+impl XAdd {
+    /// Whether the ID of the entry is generated rather than given in full.
+    pub fn generates_id(&self) -> bool {
+        !matches!(self.id, StreamIdSpec::Explicit(_))
+    }
+
+    pub fn fields_len(&self) -> usize {
+        self.fields.len()
+    }
+}
+
+impl CommandExecutor for XAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xadd(self.key, self.id, self.fields, self.trim) {
+            Ok(Some(id)) => BulkString::from(id.to_string()).into(),
+            Ok(None) => SimpleError::new(
+                "ERR The ID specified in XADD is equal or smaller than the target stream top item",
+            )
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// key [<MAXLEN | MINID> [= | ~] threshold [LIMIT count]] <* | id> field value [field value ...]
+impl TryFrom<RespArray> for XAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["xadd"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = args.0.into_iter().peekable();
+        let key = match args.next() {
+            Some(key) => extract_bytes(key)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have a key".to_string(),
+                ))
+            }
+        };
+        let trim = extract_trim(&mut args)?;
+        let id = match args.next() {
+            Some(id) => extract_string(id)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have an ID".to_string(),
+                ))
+            }
+        };
+        if args.len() == 0 || !args.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have field/value pairs".to_string(),
+            ));
+        }
+        let id = match id.as_str() {
+            "*" => StreamIdSpec::Auto,
+            id => match id.strip_suffix("-*") {
+                Some(ms) => StreamIdSpec::Partial(ms.parse().map_err(|_| invalid_stream_id())?),
+                None => {
+                    StreamIdSpec::Explicit(parse_stream_id(id, 0).ok_or_else(invalid_stream_id)?)
+                }
+            },
+        };
+        if id == StreamIdSpec::Explicit(StreamId::MIN) {
+            return Err(CommandError::InvalidArgument(
+                "The ID specified in XADD must be greater than 0-0".to_string(),
+            ));
+        }
+        let mut fields = Vec::with_capacity(args.len() / 2);
+        while let (Some(field), Some(value)) = (args.next(), args.next()) {
+            fields.push((extract_string(field)?, extract_bytes(value)?));
+        }
+        Ok(XAdd {
+            key,
+            id,
+            fields,
+            trim,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct XTrim {
+    key: Bytes,
+    trim: StreamTrim,
+}
+
+impl CommandExecutor for XTrim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend
+            .xtrim(&self.key, self.trim)
+            .map_or_else(Into::into, |removed| RespFrame::Integer(removed as i64))
+    }
+}
+
+// key <MAXLEN | MINID> [= | ~] threshold [LIMIT count]
+impl TryFrom<RespArray> for XTrim {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["xtrim"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = args.0.into_iter().peekable();
+        let key = match args.next() {
+            Some(key) => extract_bytes(key)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have a key".to_string(),
+                ))
+            }
+        };
+        match (extract_trim(&mut args)?, args.next()) {
+            (Some(trim), None) => Ok(XTrim { key, trim }),
+            _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct XDel {
+    key: Bytes,
+    ids: Vec<StreamId>,
+}
+
+impl CommandExecutor for XDel {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend
+            .xdel(&self.key, &self.ids)
+            .map_or_else(Into::into, |removed| RespFrame::Integer(removed as i64))
+    }
+}
+
+// key id [id ...]
+impl TryFrom<RespArray> for XDel {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["xdel"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        if args.len() < 2 {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have a key and at least one ID".to_string(),
+            ));
+        }
+        let mut args = args.0.into_iter();
+        let Some(key) = args.next() else {
+            unreachable!("argument count checked above");
+        };
+        let key = extract_bytes(key)?;
+        let ids = args
+            .map(|id| parse_stream_id(&extract_string(id)?, 0).ok_or_else(invalid_stream_id))
+            .collect::<Result<Vec<StreamId>, CommandError>>()?;
+        Ok(XDel { key, ids })
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct XLen(Bytes);
+
+impl CommandExecutor for XLen {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend
+            .xlen(&self)
+            .map_or_else(Into::into, |len| RespFrame::Integer(len as i64))
+    }
+}
+
+impl TryFrom<RespArray> for XLen {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["xlen"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+#[derive(Debug)]
+pub struct XRange {
+    key: Bytes,
+    // both ends are inclusive; `None` means the interval is empty
+    range: Option<(StreamId, StreamId)>,
+    rev: bool,
+    count: Option<usize>,
+}
+
+impl CommandExecutor for XRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let entries = match (self.range, self.count) {
+            (None, _) | (_, Some(0)) => Ok(vec![]),
+            (Some((start, end)), count) => backend.xrange(&self.key, start, end, self.rev, count),
+        };
+        entries.map_or_else(Into::into, stream_entries_to_frame)
+    }
+}
+
+// key start end [COUNT count]
+impl TryFrom<RespArray> for XRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["xrange"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        parse_xrange(args, false)
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct XRevRange(XRange);
+
+impl CommandExecutor for XRevRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.0.execute(backend)
+    }
+}
+
+// key end start [COUNT count]
+impl TryFrom<RespArray> for XRevRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["xrevrange"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(parse_xrange(args, true)?))
+    }
+}
+
+fn parse_xrange(args: RespArray, rev: bool) -> Result<XRange, CommandError> {
+    if args.len() != 3 && args.len() != 5 {
+        return Err(CommandError::InvalidCommandArguments(
+            "Command must have a key, a start and an end".to_string(),
+        ));
+    }
+    let mut args = args.0.into_iter();
+    let (Some(key), Some(first), Some(second)) = (args.next(), args.next(), args.next()) else {
+        unreachable!("argument count checked above");
+    };
+    let (start, end) = if rev {
+        (second, first)
+    } else {
+        (first, second)
+    };
+    let start = extract_range_bound(&extract_string(start)?, true)?;
+    let end = extract_range_bound(&extract_string(end)?, false)?;
+    let count = match (args.next(), args.next()) {
+        (None, None) => None,
+        (Some(option), Some(count)) if is_keyword(&option, "count") => {
+            Some(extract_integer(count)?.max(0) as usize)
+        }
+        _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+    };
+    Ok(XRange {
+        key: extract_bytes(key)?,
+        range: start.zip(end),
+        rev,
+        count,
+    })
+}
+
+// Resolves `-`, `+`, `<ms>`, `<ms>-<seq>` and their exclusive `(` forms into an inclusive ID.
+fn extract_range_bound(bound: &str, is_start: bool) -> Result<Option<StreamId>, CommandError> {
+    match bound {
+        "-" => Ok(Some(StreamId::MIN)),
+        "+" => Ok(Some(StreamId::MAX)),
+        _ => {
+            let default_seq = if is_start { 0 } else { u64::MAX };
+            match bound.strip_prefix('(') {
+                Some(id) => {
+                    let id = parse_stream_id(id, default_seq).ok_or_else(invalid_stream_id)?;
+                    Ok(if is_start { id.next() } else { id.prev() })
+                }
+                None => Ok(Some(
+                    parse_stream_id(bound, default_seq).ok_or_else(invalid_stream_id)?,
+                )),
+            }
+        }
+    }
+}
+
+// Consumes an optional `<MAXLEN | MINID> [= | ~] threshold [LIMIT count]` clause.
+fn extract_trim(
+    args: &mut Peekable<IntoIter<RespFrame>>,
+) -> Result<Option<StreamTrim>, CommandError> {
+    let by_len = match args.peek() {
+        Some(arg) if is_keyword(arg, "maxlen") => true,
+        Some(arg) if is_keyword(arg, "minid") => false,
+        _ => return Ok(None),
+    };
+    args.next();
+    let approximate = match args.peek() {
+        Some(arg) if is_keyword(arg, "~") => {
+            args.next();
+            true
+        }
+        Some(arg) if is_keyword(arg, "=") => {
+            args.next();
+            false
+        }
+        _ => false,
+    };
+    let threshold = match args.next() {
+        Some(threshold) => threshold,
+        None => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+    };
+    let strategy = if by_len {
+        let max_len = extract_integer(threshold)?;
+        if max_len < 0 {
+            return Err(CommandError::InvalidArgument(
+                "The MAXLEN argument must be >= 0.".to_string(),
+            ));
+        }
+        TrimStrategy::MaxLen(max_len as usize)
+    } else {
+        let min_id =
+            parse_stream_id(&extract_string(threshold)?, 0).ok_or_else(invalid_stream_id)?;
+        TrimStrategy::MinId(min_id)
+    };
+    let limit = if args.peek().is_some_and(|arg| is_keyword(arg, "limit")) {
+        args.next();
+        if !approximate {
+            return Err(CommandError::InvalidArgument(
+                "syntax error, LIMIT cannot be used without the special ~ option".to_string(),
+            ));
+        }
+        let limit = match args.next() {
+            Some(limit) => extract_integer(limit)?,
+            None => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        if limit < 0 {
+            return Err(CommandError::InvalidArgument(
+                "The LIMIT argument must be >= 0.".to_string(),
+            ));
+        }
+        Some(limit as usize)
+    } else {
+        None
+    };
+    Ok(Some(StreamTrim {
+        strategy,
+        approximate,
+        limit,
+    }))
+}
+
+fn parse_stream_id(id: &str, default_seq: u64) -> Option<StreamId> {
+    match id.split_once('-') {
+        Some((ms, seq)) => Some(StreamId::new(ms.parse().ok()?, seq.parse().ok()?)),
+        None => Some(StreamId::new(id.parse().ok()?, default_seq)),
+    }
+}
+
+fn invalid_stream_id() -> CommandError {
+    CommandError::InvalidArgument(
+        "Invalid stream ID specified as stream command argument".to_string(),
+    )
+}
+
+fn stream_entries_to_frame(entries: Vec<(StreamId, StreamFields)>) -> RespFrame {
+    let entries = entries
+        .into_iter()
+        .map(|(id, fields)| {
+            let fields = fields
+                .into_iter()
+                .flat_map(|(field, value)| {
+                    [
+                        BulkString::from(field).into(),
+                        BulkString::new(value).into(),
+                    ]
+                })
+                .collect::<Vec<RespFrame>>();
+            RespArray::new([
+                BulkString::from(id.to_string()).into(),
+                RespArray::new(fields).into(),
+            ])
+            .into()
+        })
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(entries).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::RespDecoder;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_xadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$4\r\nxadd\r\n$6\r\nevents\r\n$4\r\n12-*\r\n$4\r\nkind\r\n$5\r\nlogin\r\n",
+        );
+        let cmd = XAdd::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.key, "events");
+        assert_eq!(cmd.id, StreamIdSpec::Partial(12));
+        assert_eq!(cmd.fields.len(), 1);
+
+        buf.extend_from_slice(
+            b"*5\r\n$4\r\nxadd\r\n$6\r\nevents\r\n$3\r\n0-0\r\n$4\r\nkind\r\n$5\r\nlogin\r\n",
+        );
+        assert!(XAdd::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xadd_and_xtrim_trim_options() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*10\r\n$4\r\nxadd\r\n$6\r\nevents\r\n$6\r\nMAXLEN\r\n$1\r\n~\r\n$3\r\n100\r\n$5\r\nLIMIT\r\n$1\r\n5\r\n$1\r\n*\r\n$1\r\nk\r\n$1\r\nv\r\n",
+        );
+        let cmd = XAdd::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.id, StreamIdSpec::Auto);
+        assert_eq!(
+            cmd.trim,
+            Some(StreamTrim {
+                strategy: TrimStrategy::MaxLen(100),
+                approximate: true,
+                limit: Some(5),
+            })
+        );
+
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nxtrim\r\n$6\r\nevents\r\n$5\r\nMINID\r\n$1\r\n=\r\n$3\r\n7-1\r\n",
+        );
+        let cmd = XTrim::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.trim.strategy, TrimStrategy::MinId(StreamId::new(7, 1)));
+        assert!(!cmd.trim.approximate);
+
+        buf.extend_from_slice(
+            b"*6\r\n$5\r\nxtrim\r\n$6\r\nevents\r\n$6\r\nMAXLEN\r\n$1\r\n1\r\n$5\r\nLIMIT\r\n$1\r\n5\r\n",
+        );
+        assert!(XTrim::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xrange_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$9\r\nxrevrange\r\n$6\r\nevents\r\n$1\r\n+\r\n$4\r\n(5-1\r\n$5\r\nCOUNT\r\n$1\r\n2\r\n",
+        );
+        let cmd = XRevRange::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.range, Some((StreamId::new(5, 2), StreamId::MAX)));
+        assert!(cmd.rev);
+        assert_eq!(cmd.count, Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_cmds_execute() {
+        let backend = Backend::new();
+        for id in ["1-1", "2-1", "3-1"] {
+            let cmd = XAdd {
+                key: "events".into(),
+                id: StreamIdSpec::Explicit(parse_stream_id(id, 0).unwrap()),
+                fields: vec![("id".into(), id.into())],
+                trim: None,
+            };
+            assert_eq!(cmd.execute(&backend), BulkString::from(id).into());
+        }
+        let cmd = XAdd {
+            key: "events".into(),
+            id: StreamIdSpec::Explicit(StreamId::new(2, 0)),
+            fields: vec![],
+            trim: None,
+        };
+        assert!(matches!(cmd.execute(&backend), RespFrame::SimpleError(_)));
+        assert_eq!(
+            XLen("events".into()).execute(&backend),
+            RespFrame::Integer(3)
+        );
+
+        let cmd = XDel {
+            key: "events".into(),
+            ids: vec![StreamId::new(3, 1), StreamId::new(9, 9)],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd = XTrim {
+            key: "events".into(),
+            trim: StreamTrim {
+                strategy: TrimStrategy::MaxLen(1),
+                approximate: false,
+                limit: None,
+            },
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = XRange {
+            key: "events".into(),
+            range: Some((StreamId::new(2, 0), StreamId::MAX)),
+            rev: false,
+            count: Some(1),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespArray::new([
+                BulkString::from("2-1").into(),
+                RespArray::new([
+                    BulkString::from("id").into(),
+                    BulkString::from("2-1").into()
+                ])
+                .into(),
+            ])
+            .into()])
+            .into()
+        );
+    }
+}
@@ -0,0 +1,428 @@
+use super::{
+    connection_only, extract_args, unless_busy, validate_command, Command, CommandError,
+    CommandExecutor, Propagated, RESP_OK,
+};
+use crate::{Backend, RespArray, RespFrame, RespNullArray, SimpleError, SimpleString};
+use bytes::Bytes;
+use tokio::task;
+
+/// The MULTI state of a connection: the commands queued until EXEC, with the frames the writes
+/// among them are propagated as.
+#[derive(Debug, Default)]
+pub struct Transaction {
+    queued: Option<Vec<(Command, Option<Propagated>)>>,
+    // set when a command could not be queued, which makes EXEC discard the transaction
+    aborted: bool,
+    // the keys of the queued commands, which must all be served by this node in cluster mode
+    keys: Vec<Vec<u8>>,
+}
+
+impl Transaction {
+    pub fn is_active(&self) -> bool {
+        self.queued.is_some()
+    }
+
+    /// Whether one of the commands queued since MULTI may write.
+    pub fn writes(&self) -> bool {
+        self.queued
+            .iter()
+            .flatten()
+            .any(|(cmd, _)| cmd.pausable_write())
+    }
+
+    /// The number of commands queued since MULTI, if the transaction started.
+    pub fn queued(&self) -> Option<usize> {
+        self.queued.as_ref().map(Vec::len)
+    }
+
+    /// Whether `cmd` is queued rather than run once the transaction started.
+    pub fn queues(&self, cmd: &Command) -> bool {
+        self.is_active()
+            && !matches!(
+                cmd,
+                Command::Multi(_)
+                    | Command::Exec(_)
+                    | Command::Discard(_)
+                    | Command::Watch(_)
+                    | Command::Reset(_)
+            )
+    }
+
+    pub fn queue(&mut self, cmd: Command, propagated: Option<Propagated>) -> RespFrame {
+        if let Some(queued) = &mut self.queued {
+            queued.push((cmd, propagated));
+        }
+        SimpleString::new("QUEUED").into()
+    }
+
+    /// Makes EXEC fail after a command was refused while queuing, as Redis does.
+    pub fn abort(&mut self) {
+        if self.is_active() {
+            self.aborted = true;
+        }
+    }
+
+    /// Adds the keys of a command to queue, failing when the transaction would then span
+    /// several slots or not be served by this node in cluster mode.
+    pub fn check_keys_slot(&mut self, backend: &Backend, keys: &[&[u8]]) -> Result<(), String> {
+        let mut all: Vec<&[u8]> = self.keys.iter().map(Vec::as_slice).collect();
+        all.extend_from_slice(keys);
+        backend.check_keys_slot(&all)?;
+        self.keys.extend(keys.iter().map(|key| key.to_vec()));
+        Ok(())
+    }
+
+    /// Leaves MULTI, dropping the queued commands, as RESET does.
+    pub fn clear(&mut self) {
+        self.reset();
+    }
+
+    fn reset(&mut self) -> Option<Vec<(Command, Option<Propagated>)>> {
+        self.aborted = false;
+        self.keys.clear();
+        self.queued.take()
+    }
+}
+
+#[derive(Debug)]
+pub struct Multi;
+
+impl CommandExecutor for Multi {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("MULTI")
+    }
+}
+
+impl Multi {
+    pub fn execute_transaction(self, transaction: &mut Transaction) -> RespFrame {
+        if transaction.is_active() {
+            return SimpleError::new("ERR MULTI calls can not be nested").into();
+        }
+        transaction.queued = Some(vec![]);
+        RESP_OK.clone()
+    }
+}
+
+impl TryFrom<RespArray> for Multi {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_arguments(&value, "multi")?;
+        Ok(Multi)
+    }
+}
+
+#[derive(Debug)]
+pub struct Exec;
+
+impl CommandExecutor for Exec {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("EXEC")
+    }
+}
+
+impl Exec {
+    /// Runs the queued commands with no other command in between, unless a key watched by the
+    /// connection `id` changed. The writes are propagated one by one, in order. A queued SELECT
+    /// switches the database of the connection for the commands after it.
+    pub async fn execute_transaction(
+        self,
+        backend: &mut Backend,
+        transaction: &mut Transaction,
+        id: u64,
+    ) -> RespFrame {
+        let aborted = transaction.aborted;
+        let Some(queued) = transaction.reset() else {
+            return SimpleError::new("ERR EXEC without MULTI").into();
+        };
+        if aborted {
+            backend.unwatch(id);
+            return SimpleError::new("EXECABORT Transaction discarded because of previous errors.")
+                .into();
+        }
+        if queued.iter().any(|(cmd, _)| cmd.is_write()) {
+            // a failover holds writes until the new master caught up with them
+            backend.writes_unpaused().await;
+            if backend.rejects_writes() {
+                backend.unwatch(id);
+                return SimpleError::new(
+                    "EXECABORT Transaction discarded because of: READONLY You can't write against a read only replica.",
+                )
+                .into();
+            }
+        }
+        if queued.iter().any(|(cmd, _)| cmd.denies_oom()) && !backend.evict_to_fit() {
+            backend.unwatch(id);
+            return SimpleError::new(
+                "EXECABORT Transaction discarded because of: OOM command not allowed when used memory > 'maxmemory'.",
+            )
+            .into();
+        }
+
+        let scripted = queued.iter().any(|(cmd, _)| {
+            matches!(
+                cmd,
+                Command::Eval(_) | Command::EvalSha(_) | Command::FCall(_)
+            )
+        });
+        let lock = match unless_busy(backend, backend.transaction_lock()).await {
+            Ok(lock) => lock,
+            Err(busy) => {
+                backend.unwatch(id);
+                return busy;
+            }
+        };
+        // checked holding the lock, so no write can slip in between
+        if backend.unwatch(id) {
+            return RespFrame::NullArray(RespNullArray);
+        }
+        let mut db = backend.clone();
+        let run = || -> Vec<RespFrame> {
+            queued
+                .into_iter()
+                .map(|(cmd, propagated)| {
+                    let reply = match cmd {
+                        Command::Select(cmd) => cmd.execute_connection(&mut db),
+                        cmd => cmd.execute(&db),
+                    };
+                    if let Some(propagated) = propagated {
+                        propagated.propagate(&db, &reply);
+                    }
+                    reply
+                })
+                .collect()
+        };
+        // scripts and functions may run for long, while the other connections of this worker thread are
+        // served elsewhere
+        let replies = match scripted {
+            true => task::block_in_place(run),
+            false => run(),
+        };
+        drop(lock);
+        *backend = db;
+        RespArray::new(replies).into()
+    }
+}
+
+impl TryFrom<RespArray> for Exec {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_arguments(&value, "exec")?;
+        Ok(Exec)
+    }
+}
+
+#[derive(Debug)]
+pub struct Discard;
+
+impl CommandExecutor for Discard {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("DISCARD")
+    }
+}
+
+impl Discard {
+    pub fn execute_transaction(
+        self,
+        backend: &Backend,
+        transaction: &mut Transaction,
+        id: u64,
+    ) -> RespFrame {
+        if transaction.reset().is_none() {
+            return SimpleError::new("ERR DISCARD without MULTI").into();
+        }
+        backend.unwatch(id);
+        RESP_OK.clone()
+    }
+}
+
+impl TryFrom<RespArray> for Discard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_arguments(&value, "discard")?;
+        Ok(Discard)
+    }
+}
+
+#[derive(Debug)]
+pub struct Watch(Vec<Bytes>);
+
+impl CommandExecutor for Watch {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("WATCH")
+    }
+}
+
+impl Watch {
+    pub fn execute_transaction(
+        self,
+        backend: &Backend,
+        transaction: &Transaction,
+        id: u64,
+    ) -> RespFrame {
+        if transaction.is_active() {
+            return SimpleError::new("ERR WATCH inside MULTI is not allowed").into();
+        }
+        for key in &self.0 {
+            backend.watch(id, key);
+        }
+        RESP_OK.clone()
+    }
+}
+
+// key [key ...]
+impl TryFrom<RespArray> for Watch {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["watch"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Watch(args.try_into()?))
+    }
+}
+
+#[derive(Debug)]
+pub struct Unwatch;
+
+impl CommandExecutor for Unwatch {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        connection_only("UNWATCH")
+    }
+}
+
+impl Unwatch {
+    pub fn execute_transaction(self, backend: &Backend, id: u64) -> RespFrame {
+        backend.unwatch(id);
+        RESP_OK.clone()
+    }
+}
+
+impl TryFrom<RespArray> for Unwatch {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        no_arguments(&value, "unwatch")?;
+        Ok(Unwatch)
+    }
+}
+
+fn no_arguments(value: &RespArray, name: &'static str) -> Result<(), CommandError> {
+    validate_command(value, &[name])?;
+    if value.len() != 1 {
+        return Err(CommandError::InvalidCommandArguments(format!(
+            "{} takes no arguments",
+            name
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resp::RespDecoder, BulkString, RespNull};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn command(args: &[&'static str]) -> Result<Command> {
+        let frame: RespFrame = RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<_>>(),
+        )
+        .into();
+        Ok(Command::try_from(frame)?)
+    }
+
+    #[test]
+    fn test_transaction_cmds_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$5\r\nwatch\r\n$3\r\nfoo\r\n$3\r\nbar\r\n");
+        let cmd = Watch::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.0, vec!["foo", "bar"]);
+
+        buf.extend_from_slice(b"*1\r\n$5\r\nwatch\r\n");
+        assert!(Watch::try_from(RespArray::decode(&mut buf)?).is_err());
+
+        buf.extend_from_slice(b"*2\r\n$4\r\nexec\r\n$3\r\nfoo\r\n");
+        assert!(Exec::try_from(RespArray::decode(&mut buf)?).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_execute() -> Result<()> {
+        let mut backend = Backend::new();
+        let mut transaction = Transaction::default();
+        assert_eq!(
+            Exec.execute_transaction(&mut backend, &mut transaction, 1)
+                .await,
+            SimpleError::new("ERR EXEC without MULTI").into()
+        );
+
+        assert_eq!(Multi.execute_transaction(&mut transaction), RESP_OK.clone());
+        assert!(matches!(
+            Multi.execute_transaction(&mut transaction),
+            RespFrame::SimpleError(_)
+        ));
+        for args in [["set", "foo", "1"], ["get", "foo", ""]] {
+            let args: Vec<_> = args.into_iter().filter(|arg| !arg.is_empty()).collect();
+            let cmd = command(&args)?;
+            assert!(transaction.queues(&cmd));
+            transaction.queue(cmd, None);
+        }
+        assert_eq!(backend.get(b"foo").unwrap(), None);
+        assert_eq!(
+            Exec.execute_transaction(&mut backend, &mut transaction, 1)
+                .await,
+            RespArray::new([RESP_OK.clone(), BulkString::from("1").into()]).into()
+        );
+        assert!(!transaction.is_active());
+
+        // a watched key changing makes EXEC fail
+        Watch(vec!["foo".into()]).execute_transaction(&backend, &transaction, 1);
+        Multi.execute_transaction(&mut transaction);
+        transaction.queue(command(&["set", "foo", "2"])?, None);
+        backend.set("foo".into(), BulkString::from("3").into());
+        assert_eq!(
+            Exec.execute_transaction(&mut backend, &mut transaction, 1)
+                .await,
+            RespFrame::NullArray(RespNullArray)
+        );
+        assert_eq!(
+            backend.get(b"foo").unwrap(),
+            Some(BulkString::from("3").into())
+        );
+
+        // a command refused while queuing discards the transaction
+        Multi.execute_transaction(&mut transaction);
+        transaction.queue(command(&["set", "foo", "4"])?, None);
+        transaction.abort();
+        assert!(matches!(
+            Exec.execute_transaction(&mut backend, &mut transaction, 1)
+                .await,
+            RespFrame::SimpleError(_)
+        ));
+        assert_eq!(
+            backend.get(b"foo").unwrap(),
+            Some(BulkString::from("3").into())
+        );
+
+        Multi.execute_transaction(&mut transaction);
+        assert_eq!(
+            Discard.execute_transaction(&backend, &mut transaction, 1),
+            RESP_OK.clone()
+        );
+        assert!(!transaction.is_active());
+
+        // the database selected in the transaction stays selected
+        Multi.execute_transaction(&mut transaction);
+        for args in [["select", "2"], ["get", "foo"]] {
+            transaction.queue(command(&args)?, None);
+        }
+        assert_eq!(
+            Exec.execute_transaction(&mut backend, &mut transaction, 1)
+                .await,
+            RespArray::new([RESP_OK.clone(), RespFrame::Null(RespNull)]).into()
+        );
+        assert_eq!(backend.db_index(), 2);
+        Ok(())
+    }
+}
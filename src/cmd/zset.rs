@@ -0,0 +1,1695 @@
+use super::{
+    extract_args, extract_bytes, extract_float, extract_integer, extract_string, is_keyword,
+    validate_command, CommandError, CommandExecutor,
+};
+use crate::{
+    backend::{glob_match, Aggregate, ZAddFlags, ZAddOutcome, ZSetOperation},
+    Backend, BulkString, RespArray, RespDouble, RespFrame, RespNull, RespNullArray, SimpleError,
+};
+use bytes::Bytes;
+use derive_more::Deref;
+use std::ops::Bound;
+
+#[derive(Debug)]
+pub struct ZAdd {
+    key: Bytes,
+    flags: ZAddFlags,
+    ch: bool,
+    members: Vec<(Bytes, f64)>,
+}
+
+impl CommandExecutor for ZAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let outcomes = match backend.zadd(self.key, self.members, self.flags) {
+            Ok(outcomes) => outcomes,
+            Err(e) => return e.into(),
+        };
+        if self.flags.incr {
+            return match outcomes.first() {
+                Some(ZAddOutcome::Added(score))
+                | Some(ZAddOutcome::Updated(score))
+                | Some(ZAddOutcome::Unchanged(score)) => RespDouble::new(*score).into(),
+                Some(ZAddOutcome::NotANumber) => {
+                    SimpleError::new("ERR resulting score is not a number (NaN)").into()
+                }
+                _ => RespFrame::Null(RespNull),
+            };
+        }
+        let count = outcomes
+            .iter()
+            .filter(|outcome| match outcome {
+                ZAddOutcome::Added(_) => true,
+                ZAddOutcome::Updated(_) => self.ch,
+                _ => false,
+            })
+            .count();
+        RespFrame::Integer(count as i64)
+    }
+}
+
+// key [NX | XX] [GT | LT] [CH] [INCR] score member [score member ...]
+impl TryFrom<RespArray> for ZAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zadd"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = args.0.into_iter().peekable();
+        let key = match args.next() {
+            Some(key) => extract_bytes(key)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have a key".to_string(),
+                ))
+            }
+        };
+
+        let mut flags = ZAddFlags::default();
+        let mut ch = false;
+        while let Some(arg) = args.peek() {
+            if is_keyword(arg, "nx") {
+                flags.nx = true;
+            } else if is_keyword(arg, "xx") {
+                flags.xx = true;
+            } else if is_keyword(arg, "gt") {
+                flags.gt = true;
+            } else if is_keyword(arg, "lt") {
+                flags.lt = true;
+            } else if is_keyword(arg, "ch") {
+                ch = true;
+            } else if is_keyword(arg, "incr") {
+                flags.incr = true;
+            } else {
+                break;
+            }
+            args.next();
+        }
+
+        let args = args.collect::<Vec<RespFrame>>();
+        if args.is_empty() || !args.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        if flags.nx && flags.xx {
+            return Err(CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        if (flags.gt && flags.lt) || (flags.nx && (flags.gt || flags.lt)) {
+            return Err(CommandError::InvalidArgument(
+                "GT, LT, and/or NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        if flags.incr && args.len() > 2 {
+            return Err(CommandError::InvalidArgument(
+                "INCR option supports a single increment-element pair".to_string(),
+            ));
+        }
+
+        let mut members = Vec::with_capacity(args.len() / 2);
+        let mut args = args.into_iter();
+        while let (Some(score), Some(member)) = (args.next(), args.next()) {
+            members.push((extract_bytes(member)?, extract_float(score)?));
+        }
+        Ok(ZAdd {
+            key,
+            flags,
+            ch,
+            members,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ZIncrBy {
+    key: Bytes,
+    increment: f64,
+    member: Bytes,
+}
+
+impl CommandExecutor for ZIncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let flags = ZAddFlags {
+            incr: true,
+            ..Default::default()
+        };
+        let outcomes = match backend.zadd(self.key, vec![(self.member, self.increment)], flags) {
+            Ok(outcomes) => outcomes,
+            Err(e) => return e.into(),
+        };
+        match outcomes.first() {
+            Some(ZAddOutcome::Added(score))
+            | Some(ZAddOutcome::Updated(score))
+            | Some(ZAddOutcome::Unchanged(score)) => RespDouble::new(*score).into(),
+            _ => SimpleError::new("ERR resulting score is not a number (NaN)").into(),
+        }
+    }
+}
+
+// key increment member
+impl TryFrom<RespArray> for ZIncrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zincrby"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        if args.len() != 3 {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have three arguments".to_string(),
+            ));
+        }
+        let mut args = args.0.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(increment), Some(member)) => Ok(ZIncrBy {
+                key: extract_bytes(key)?,
+                increment: extract_float(increment)?,
+                member: extract_bytes(member)?,
+            }),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "Invalid key or member".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ZRange {
+    key: Bytes,
+    start: i64,
+    stop: i64,
+    rev: bool,
+    withscores: bool,
+}
+
+impl CommandExecutor for ZRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend
+            .zrange(&self.key, self.start, self.stop, self.rev)
+            .map_or_else(Into::into, |members| {
+                scored_members_to_frame(members, self.withscores)
+            })
+    }
+}
+
+// key start stop [REV] [WITHSCORES]
+impl TryFrom<RespArray> for ZRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zrange"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        parse_rank_range(args, true)
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct ZRevRange(ZRange);
+
+impl CommandExecutor for ZRevRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.0.execute(backend)
+    }
+}
+
+// key start stop [WITHSCORES]
+impl TryFrom<RespArray> for ZRevRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zrevrange"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut range = parse_rank_range(args, false)?;
+        range.rev = true;
+        Ok(Self(range))
+    }
+}
+
+#[derive(Debug)]
+pub struct ZRangeByScore {
+    key: Bytes,
+    min: Bound<f64>,
+    max: Bound<f64>,
+    withscores: bool,
+    limit: Option<Limit>,
+}
+
+impl CommandExecutor for ZRangeByScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (offset, count) = match self.limit {
+            Some(limit) => match limit.resolve() {
+                Some(limit) => limit,
+                None => return RespArray::new([]).into(),
+            },
+            None => (0, None),
+        };
+        backend
+            .zrange_by_score(&self.key, self.min, self.max, offset, count)
+            .map_or_else(Into::into, |members| {
+                scored_members_to_frame(members, self.withscores)
+            })
+    }
+}
+
+// key min max [WITHSCORES] [LIMIT offset count]
+impl TryFrom<RespArray> for ZRangeByScore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zrangebyscore"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        if args.len() < 3 {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have at least three arguments".to_string(),
+            ));
+        }
+        let mut args = args.0.into_iter();
+        let (Some(key), Some(min), Some(max)) = (args.next(), args.next(), args.next()) else {
+            unreachable!("argument count checked above");
+        };
+        let mut cmd = ZRangeByScore {
+            key: extract_bytes(key)?,
+            min: extract_score_bound(min)?,
+            max: extract_score_bound(max)?,
+            withscores: false,
+            limit: None,
+        };
+        while let Some(arg) = args.next() {
+            if is_keyword(&arg, "withscores") {
+                cmd.withscores = true;
+            } else if is_keyword(&arg, "limit") {
+                cmd.limit = Some(extract_limit(&mut args)?);
+            } else {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()));
+            }
+        }
+        Ok(cmd)
+    }
+}
+
+#[derive(Debug)]
+pub struct ZRangeByLex {
+    key: Bytes,
+    min: LexBound,
+    max: LexBound,
+    limit: Option<Limit>,
+}
+
+impl CommandExecutor for ZRangeByLex {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (offset, count) = match self.limit {
+            Some(limit) => match limit.resolve() {
+                Some(limit) => limit,
+                None => return RespArray::new([]).into(),
+            },
+            None => (0, None),
+        };
+        let Some((min, max)) = lex_range(&self.min, &self.max) else {
+            return RespArray::new([]).into();
+        };
+        let members = match backend.zrange_by_lex(&self.key, min, max, offset, count) {
+            Ok(members) => members,
+            Err(e) => return e.into(),
+        };
+        RespArray::new(
+            members
+                .into_iter()
+                .map(|member| BulkString::from(member).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into()
+    }
+}
+
+// key min max [LIMIT offset count]
+impl TryFrom<RespArray> for ZRangeByLex {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zrangebylex"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        if args.len() < 3 {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have at least three arguments".to_string(),
+            ));
+        }
+        let mut args = args.0.into_iter();
+        let (Some(key), Some(min), Some(max)) = (args.next(), args.next(), args.next()) else {
+            unreachable!("argument count checked above");
+        };
+        let mut cmd = ZRangeByLex {
+            key: extract_bytes(key)?,
+            min: extract_lex_bound(min)?,
+            max: extract_lex_bound(max)?,
+            limit: None,
+        };
+        while let Some(arg) = args.next() {
+            if is_keyword(&arg, "limit") {
+                cmd.limit = Some(extract_limit(&mut args)?);
+            } else {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()));
+            }
+        }
+        Ok(cmd)
+    }
+}
+
+#[derive(Debug)]
+pub struct ZCount {
+    key: Bytes,
+    min: Bound<f64>,
+    max: Bound<f64>,
+}
+
+impl CommandExecutor for ZCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend
+            .zcount(&self.key, self.min, self.max)
+            .map_or_else(Into::into, |count| RespFrame::Integer(count as i64))
+    }
+}
+
+// key min max
+impl TryFrom<RespArray> for ZCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zcount"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        if args.len() != 3 {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have three arguments".to_string(),
+            ));
+        }
+        let mut args = args.0.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(min), Some(max)) => Ok(ZCount {
+                key: extract_bytes(key)?,
+                min: extract_score_bound(min)?,
+                max: extract_score_bound(max)?,
+            }),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "Invalid key or range".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ZLexCount {
+    key: Bytes,
+    min: LexBound,
+    max: LexBound,
+}
+
+impl CommandExecutor for ZLexCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let count = match lex_range(&self.min, &self.max) {
+            Some((min, max)) => backend.zlexcount(&self.key, min, max),
+            None => Ok(0),
+        };
+        count.map_or_else(Into::into, |count| RespFrame::Integer(count as i64))
+    }
+}
+
+// key min max
+impl TryFrom<RespArray> for ZLexCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zlexcount"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        if args.len() != 3 {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have three arguments".to_string(),
+            ));
+        }
+        let mut args = args.0.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(min), Some(max)) => Ok(ZLexCount {
+                key: extract_bytes(key)?,
+                min: extract_lex_bound(min)?,
+                max: extract_lex_bound(max)?,
+            }),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "Invalid key or range".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ZRemRangeByRank {
+    key: Bytes,
+    start: i64,
+    stop: i64,
+}
+
+impl CommandExecutor for ZRemRangeByRank {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend
+            .zremrange_by_rank(&self.key, self.start, self.stop)
+            .map_or_else(Into::into, |removed| RespFrame::Integer(removed as i64))
+    }
+}
+
+// key start stop
+impl TryFrom<RespArray> for ZRemRangeByRank {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zremrangebyrank"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        if args.len() != 3 {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have three arguments".to_string(),
+            ));
+        }
+        let mut args = args.0.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(start), Some(stop)) => Ok(ZRemRangeByRank {
+                key: extract_bytes(key)?,
+                start: extract_integer(start)?,
+                stop: extract_integer(stop)?,
+            }),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "Invalid key or range".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ZRemRangeByScore {
+    key: Bytes,
+    min: Bound<f64>,
+    max: Bound<f64>,
+}
+
+impl CommandExecutor for ZRemRangeByScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend
+            .zremrange_by_score(&self.key, self.min, self.max)
+            .map_or_else(Into::into, |removed| RespFrame::Integer(removed as i64))
+    }
+}
+
+// key min max
+impl TryFrom<RespArray> for ZRemRangeByScore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zremrangebyscore"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        if args.len() != 3 {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have three arguments".to_string(),
+            ));
+        }
+        let mut args = args.0.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(min), Some(max)) => Ok(ZRemRangeByScore {
+                key: extract_bytes(key)?,
+                min: extract_score_bound(min)?,
+                max: extract_score_bound(max)?,
+            }),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "Invalid key or range".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ZRemRangeByLex {
+    key: Bytes,
+    min: LexBound,
+    max: LexBound,
+}
+
+impl CommandExecutor for ZRemRangeByLex {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let removed = match lex_range(&self.min, &self.max) {
+            Some((min, max)) => backend.zremrange_by_lex(&self.key, min, max),
+            None => Ok(0),
+        };
+        removed.map_or_else(Into::into, |removed| RespFrame::Integer(removed as i64))
+    }
+}
+
+// key min max
+impl TryFrom<RespArray> for ZRemRangeByLex {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zremrangebylex"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        if args.len() != 3 {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have three arguments".to_string(),
+            ));
+        }
+        let mut args = args.0.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(min), Some(max)) => Ok(ZRemRangeByLex {
+                key: extract_bytes(key)?,
+                min: extract_lex_bound(min)?,
+                max: extract_lex_bound(max)?,
+            }),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "Invalid key or range".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ZCombine {
+    operation: ZSetOperation,
+    destination: Option<Bytes>,
+    keys: Vec<Bytes>,
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+    withscores: bool,
+}
+
+impl CommandExecutor for ZCombine {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let zset = match backend.zcombine(self.operation, &self.keys, &self.weights, self.aggregate)
+        {
+            Ok(zset) => zset,
+            Err(e) => return e.into(),
+        };
+        match self.destination {
+            Some(destination) => RespFrame::Integer(backend.zstore(destination, zset) as i64),
+            None => scored_members_to_frame(zset.range_by_rank(0, -1, false), self.withscores),
+        }
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct ZUnionStore(ZCombine);
+
+impl CommandExecutor for ZUnionStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.0.execute(backend)
+    }
+}
+
+impl TryFrom<RespArray> for ZUnionStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zunionstore"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(parse_combine(
+            cmd_names[0],
+            ZSetOperation::Union,
+            true,
+            args,
+        )?))
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct ZInterStore(ZCombine);
+
+impl CommandExecutor for ZInterStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.0.execute(backend)
+    }
+}
+
+impl TryFrom<RespArray> for ZInterStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zinterstore"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(parse_combine(
+            cmd_names[0],
+            ZSetOperation::Inter,
+            true,
+            args,
+        )?))
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct ZDiffStore(ZCombine);
+
+impl CommandExecutor for ZDiffStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.0.execute(backend)
+    }
+}
+
+impl TryFrom<RespArray> for ZDiffStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zdiffstore"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(parse_combine(
+            cmd_names[0],
+            ZSetOperation::Diff,
+            true,
+            args,
+        )?))
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct ZUnion(ZCombine);
+
+impl CommandExecutor for ZUnion {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.0.execute(backend)
+    }
+}
+
+impl TryFrom<RespArray> for ZUnion {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zunion"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(parse_combine(
+            cmd_names[0],
+            ZSetOperation::Union,
+            false,
+            args,
+        )?))
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct ZInter(ZCombine);
+
+impl CommandExecutor for ZInter {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.0.execute(backend)
+    }
+}
+
+impl TryFrom<RespArray> for ZInter {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zinter"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(parse_combine(
+            cmd_names[0],
+            ZSetOperation::Inter,
+            false,
+            args,
+        )?))
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct ZDiff(ZCombine);
+
+impl CommandExecutor for ZDiff {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.0.execute(backend)
+    }
+}
+
+impl TryFrom<RespArray> for ZDiff {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zdiff"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(parse_combine(
+            cmd_names[0],
+            ZSetOperation::Diff,
+            false,
+            args,
+        )?))
+    }
+}
+
+#[derive(Debug)]
+pub struct ZRandMember {
+    key: Bytes,
+    count: Option<i64>,
+    withscores: bool,
+}
+
+impl CommandExecutor for ZRandMember {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let members = match backend.zrandmember(&self.key, self.count.unwrap_or(1)) {
+            Ok(members) => members,
+            Err(e) => return e.into(),
+        };
+        match self.count {
+            Some(_) => scored_members_to_frame(members, self.withscores),
+            None => match members.into_iter().next() {
+                Some((member, _)) => BulkString::from(member).into(),
+                None => RespFrame::Null(RespNull),
+            },
+        }
+    }
+}
+
+// key [count [WITHSCORES]]
+impl TryFrom<RespArray> for ZRandMember {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zrandmember"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = args.0.into_iter();
+        let key = match args.next() {
+            Some(key) => extract_bytes(key)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have a key".to_string(),
+                ))
+            }
+        };
+        let count = args.next().map(extract_integer).transpose()?;
+        // as many repeated members as that can't be replied
+        if count.is_some_and(|count| count < -(i64::MAX / 2)) {
+            return Err(CommandError::InvalidArgument(
+                "value is out of range".to_string(),
+            ));
+        }
+        let withscores = match args.next() {
+            None => false,
+            Some(arg) if is_keyword(&arg, "withscores") => true,
+            Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(ZRandMember {
+            key,
+            count,
+            withscores,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ZScan {
+    key: Bytes,
+    cursor: usize,
+    pattern: Option<String>,
+    count: usize,
+}
+
+impl CommandExecutor for ZScan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (cursor, members) = match backend.zscan(&self.key, self.cursor, self.count) {
+            Ok(scanned) => scanned,
+            Err(e) => return e.into(),
+        };
+        let mut frames = Vec::with_capacity(members.len() * 2);
+        for (member, score) in members {
+            if let Some(pattern) = &self.pattern {
+                if !glob_match(pattern.as_bytes(), &member) {
+                    continue;
+                }
+            }
+            frames.push(BulkString::from(member).into());
+            frames.push(BulkString::from(score.to_string()).into());
+        }
+        RespArray::new([
+            BulkString::from(cursor.to_string()).into(),
+            RespArray::new(frames).into(),
+        ])
+        .into()
+    }
+}
+
+// key cursor [MATCH pattern] [COUNT count]
+impl TryFrom<RespArray> for ZScan {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zscan"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        if args.len() < 2 {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have at least two arguments".to_string(),
+            ));
+        }
+        let mut args = args.0.into_iter();
+        let (Some(key), Some(cursor)) = (args.next(), args.next()) else {
+            unreachable!("argument count checked above");
+        };
+        let cursor = extract_integer(cursor)
+            .ok()
+            .and_then(|cursor| usize::try_from(cursor).ok())
+            .ok_or_else(|| CommandError::InvalidArgument("invalid cursor".to_string()))?;
+        let mut cmd = ZScan {
+            key: extract_bytes(key)?,
+            cursor,
+            pattern: None,
+            count: 10,
+        };
+        while let Some(arg) = args.next() {
+            match args.next() {
+                Some(value) if is_keyword(&arg, "match") => {
+                    cmd.pattern = Some(extract_string(value)?);
+                }
+                Some(value) if is_keyword(&arg, "count") => {
+                    let count = extract_integer(value)?;
+                    if count < 1 {
+                        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+                    }
+                    cmd.count = count as usize;
+                }
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(cmd)
+    }
+}
+
+#[derive(Debug)]
+pub struct ZMPop {
+    keys: Vec<Bytes>,
+    max: bool,
+    count: usize,
+}
+
+impl CommandExecutor for ZMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        for key in self.keys {
+            let popped = match backend.zpop(&key, self.count, self.max) {
+                Ok(popped) => popped,
+                Err(e) => return e.into(),
+            };
+            if let Some(members) = popped {
+                let members = members
+                    .into_iter()
+                    .map(|(member, score)| {
+                        RespArray::new([
+                            BulkString::from(member).into(),
+                            RespDouble::new(score).into(),
+                        ])
+                        .into()
+                    })
+                    .collect::<Vec<RespFrame>>();
+                return RespArray::new([
+                    BulkString::from(key).into(),
+                    RespArray::new(members).into(),
+                ])
+                .into();
+            }
+        }
+        RespFrame::NullArray(RespNullArray)
+    }
+}
+
+// numkeys key [key ...] <MIN | MAX> [COUNT count]
+impl TryFrom<RespArray> for ZMPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zmpop"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = args.0.into_iter();
+        let numkeys = match args.next() {
+            Some(numkeys) => extract_integer(numkeys)?,
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have numkeys".to_string(),
+                ))
+            }
+        };
+        if numkeys <= 0 {
+            return Err(CommandError::InvalidArgument(
+                "numkeys should be greater than 0".to_string(),
+            ));
+        }
+        let keys = args
+            .by_ref()
+            .take(numkeys as usize)
+            .map(extract_bytes)
+            .collect::<Result<Vec<Bytes>, CommandError>>()?;
+        if keys.len() != numkeys as usize {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let max = match args.next() {
+            Some(arg) if is_keyword(&arg, "min") => false,
+            Some(arg) if is_keyword(&arg, "max") => true,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        let count = match (args.next(), args.next(), args.next()) {
+            (None, _, _) => 1,
+            (Some(option), Some(count), None) if is_keyword(&option, "count") => {
+                let count = extract_integer(count)?;
+                if count <= 0 {
+                    return Err(CommandError::InvalidArgument(
+                        "count should be greater than 0".to_string(),
+                    ));
+                }
+                count as usize
+            }
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        Ok(ZMPop { keys, max, count })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LexBound {
+    NegativeInfinity,
+    PositiveInfinity,
+    Included(Bytes),
+    Excluded(Bytes),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Limit {
+    offset: i64,
+    count: i64,
+}
+
+impl Limit {
+    // A negative offset selects nothing, a negative count selects everything after the offset.
+    fn resolve(self) -> Option<(usize, Option<usize>)> {
+        if self.offset < 0 {
+            return None;
+        }
+        let count = (self.count >= 0).then_some(self.count as usize);
+        Some((self.offset as usize, count))
+    }
+}
+
+#[derive(Debug)]
+pub struct ZScore {
+    key: Bytes,
+    member: Bytes,
+}
+
+impl CommandExecutor for ZScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zscore(&self.key, &self.member) {
+            Ok(Some(score)) => RespDouble::new(score).into(),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// key member
+impl TryFrom<RespArray> for ZScore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zscore"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        let mut args = Vec::<Bytes>::try_from(args)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(member), None) => Ok(ZScore { key, member }),
+            _ => Err(CommandError::InvalidCommandArguments(
+                "Command must have a two arguments".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Deref)]
+pub struct ZCard(Bytes);
+
+impl CommandExecutor for ZCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend
+            .zcard(&self)
+            .map_or_else(Into::into, |len| RespFrame::Integer(len as i64))
+    }
+}
+
+impl TryFrom<RespArray> for ZCard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let cmd_names = ["zcard"];
+        validate_command(&value, &cmd_names)?;
+        let args = extract_args(value, cmd_names.len())?;
+        Ok(Self(args.try_into()?))
+    }
+}
+
+fn parse_rank_range(args: RespArray, allow_rev: bool) -> Result<ZRange, CommandError> {
+    if args.len() < 3 {
+        return Err(CommandError::InvalidCommandArguments(
+            "Command must have at least three arguments".to_string(),
+        ));
+    }
+    let mut args = args.0.into_iter();
+    let (Some(key), Some(start), Some(stop)) = (args.next(), args.next(), args.next()) else {
+        unreachable!("argument count checked above");
+    };
+    let mut range = ZRange {
+        key: extract_bytes(key)?,
+        start: extract_integer(start)?,
+        stop: extract_integer(stop)?,
+        rev: false,
+        withscores: false,
+    };
+    for arg in args {
+        if allow_rev && is_keyword(&arg, "rev") {
+            range.rev = true;
+        } else if is_keyword(&arg, "withscores") {
+            range.withscores = true;
+        } else {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+    }
+    Ok(range)
+}
+
+// [destination] numkeys key [key ...] [WEIGHTS weight [weight ...]] [AGGREGATE <SUM | MIN | MAX>] [WITHSCORES]
+// WEIGHTS and AGGREGATE are not accepted by the diff variants, WITHSCORES only by the non-store ones.
+fn parse_combine(
+    name: &str,
+    operation: ZSetOperation,
+    store: bool,
+    args: RespArray,
+) -> Result<ZCombine, CommandError> {
+    let mut args = args.0.into_iter();
+    let destination = match store {
+        true => match args.next() {
+            Some(destination) => Some(extract_bytes(destination)?),
+            None => {
+                return Err(CommandError::InvalidCommandArguments(
+                    "Command must have a destination".to_string(),
+                ))
+            }
+        },
+        false => None,
+    };
+    let numkeys = match args.next() {
+        Some(numkeys) => extract_integer(numkeys)?,
+        None => {
+            return Err(CommandError::InvalidCommandArguments(
+                "Command must have numkeys".to_string(),
+            ))
+        }
+    };
+    if numkeys <= 0 {
+        return Err(CommandError::InvalidArgument(format!(
+            "at least 1 input key is needed for '{}' command",
+            name
+        )));
+    }
+    let keys = args
+        .by_ref()
+        .take(numkeys as usize)
+        .map(extract_bytes)
+        .collect::<Result<Vec<Bytes>, CommandError>>()?;
+    if keys.len() != numkeys as usize {
+        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+    }
+
+    let mut cmd = ZCombine {
+        operation,
+        destination,
+        keys,
+        weights: vec![],
+        aggregate: Aggregate::default(),
+        withscores: false,
+    };
+    let weighted = operation != ZSetOperation::Diff;
+    while let Some(arg) = args.next() {
+        if weighted && is_keyword(&arg, "weights") {
+            cmd.weights = args
+                .by_ref()
+                .take(cmd.keys.len())
+                .map(|weight| {
+                    extract_float(weight).map_err(|_| {
+                        CommandError::InvalidArgument("weight value is not a float".to_string())
+                    })
+                })
+                .collect::<Result<Vec<f64>, CommandError>>()?;
+            if cmd.weights.len() != cmd.keys.len() {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()));
+            }
+        } else if weighted && is_keyword(&arg, "aggregate") {
+            cmd.aggregate = match args.next() {
+                Some(v) if is_keyword(&v, "sum") => Aggregate::Sum,
+                Some(v) if is_keyword(&v, "min") => Aggregate::Min,
+                Some(v) if is_keyword(&v, "max") => Aggregate::Max,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            };
+        } else if !store && is_keyword(&arg, "withscores") {
+            cmd.withscores = true;
+        } else {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+    }
+    Ok(cmd)
+}
+
+fn extract_limit(args: &mut impl Iterator<Item = RespFrame>) -> Result<Limit, CommandError> {
+    match (args.next(), args.next()) {
+        (Some(offset), Some(count)) => Ok(Limit {
+            offset: extract_integer(offset)?,
+            count: extract_integer(count)?,
+        }),
+        _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+    }
+}
+
+// Score bounds are inclusive unless prefixed with "(", and accept "-inf" / "+inf".
+fn extract_score_bound(frame: RespFrame) -> Result<Bound<f64>, CommandError> {
+    let err = || CommandError::InvalidArgument("min or max is not a float".to_string());
+    let bound = extract_string(frame)?;
+    let (exclusive, value) = match bound.strip_prefix('(') {
+        Some(value) => (true, value),
+        None => (false, bound.as_str()),
+    };
+    let value: f64 = value.parse().map_err(|_| err())?;
+    if value.is_nan() {
+        return Err(err());
+    }
+    Ok(if exclusive {
+        Bound::Excluded(value)
+    } else {
+        Bound::Included(value)
+    })
+}
+
+// Lex bounds must be "-", "+", or a member prefixed with "[" (inclusive) or "(" (exclusive).
+fn extract_lex_bound(frame: RespFrame) -> Result<LexBound, CommandError> {
+    let bound = extract_bytes(frame)?;
+    match &bound[..] {
+        b"-" => Ok(LexBound::NegativeInfinity),
+        b"+" => Ok(LexBound::PositiveInfinity),
+        [b'[', ..] => Ok(LexBound::Included(bound.slice(1..))),
+        [b'(', ..] => Ok(LexBound::Excluded(bound.slice(1..))),
+        _ => Err(CommandError::InvalidArgument(
+            "min or max not valid string range item".to_string(),
+        )),
+    }
+}
+
+type LexRange<'a> = (Bound<&'a [u8]>, Bound<&'a [u8]>);
+
+// Converts lex bounds into an ordered range, `None` when nothing can possibly match.
+fn lex_range<'a>(min: &'a LexBound, max: &'a LexBound) -> Option<LexRange<'a>> {
+    let min = match min {
+        LexBound::NegativeInfinity => Bound::Unbounded,
+        LexBound::PositiveInfinity => return None,
+        LexBound::Included(v) => Bound::Included(&v[..]),
+        LexBound::Excluded(v) => Bound::Excluded(&v[..]),
+    };
+    let max = match max {
+        LexBound::NegativeInfinity => return None,
+        LexBound::PositiveInfinity => Bound::Unbounded,
+        LexBound::Included(v) => Bound::Included(&v[..]),
+        LexBound::Excluded(v) => Bound::Excluded(&v[..]),
+    };
+    Some((min, max))
+}
+
+// Scores are appended after each member as doubles when requested.
+fn scored_members_to_frame(members: Vec<(Bytes, f64)>, withscores: bool) -> RespFrame {
+    let mut frames = Vec::with_capacity(if withscores {
+        members.len() * 2
+    } else {
+        members.len()
+    });
+    for (member, score) in members {
+        frames.push(BulkString::from(member).into());
+        if withscores {
+            frames.push(RespDouble::new(score).into());
+        }
+    }
+    RespArray::new(frames).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::RespDecoder;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_zadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$4\r\nzadd\r\n$5\r\nboard\r\n$2\r\nXX\r\n$2\r\nCH\r\n$1\r\n1\r\n$5\r\nalice\r\n$2\r\nGT\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        // options must precede the score/member pairs
+        assert!(ZAdd::try_from(frame).is_err());
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*8\r\n$4\r\nzadd\r\n$5\r\nboard\r\n$2\r\nXX\r\n$2\r\nCH\r\n$1\r\n1\r\n$5\r\nalice\r\n$3\r\n2.5\r\n$3\r\nbob\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd = ZAdd::try_from(frame)?;
+        assert_eq!(cmd.key, "board");
+        assert!(cmd.flags.xx && cmd.ch);
+        assert_eq!(
+            cmd.members,
+            vec![(Bytes::from("alice"), 1.0), (Bytes::from("bob"), 2.5)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zadd_cmd_execute() {
+        let backend = Backend::new();
+        let cmd = ZAdd {
+            key: "board".into(),
+            flags: ZAddFlags::default(),
+            ch: false,
+            members: vec![("alice".into(), 1.0), ("bob".into(), 2.0)],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = ZAdd {
+            key: "board".into(),
+            flags: ZAddFlags::default(),
+            ch: true,
+            members: vec![("alice".into(), 3.0), ("carol".into(), 2.0)],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = ZAdd {
+            key: "board".into(),
+            flags: ZAddFlags {
+                incr: true,
+                nx: true,
+                ..Default::default()
+            },
+            ch: false,
+            members: vec![("alice".into(), 1.0)],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd = ZAdd {
+            key: "board".into(),
+            flags: ZAddFlags {
+                incr: true,
+                ..Default::default()
+            },
+            ch: false,
+            members: vec![("alice".into(), 1.5)],
+        };
+        assert_eq!(cmd.execute(&backend), RespDouble::new(4.5).into());
+    }
+
+    #[test]
+    fn test_zincrby_cmd_execute() {
+        let backend = Backend::new();
+        let cmd = ZIncrBy {
+            key: "board".into(),
+            increment: 2.5,
+            member: "alice".into(),
+        };
+        assert_eq!(cmd.execute(&backend), RespDouble::new(2.5).into());
+
+        let cmd = ZIncrBy {
+            key: "board".into(),
+            increment: -1.0,
+            member: "alice".into(),
+        };
+        assert_eq!(cmd.execute(&backend), RespDouble::new(1.5).into());
+        assert_eq!(
+            backend.zrange(b"board", 0, -1, false).unwrap(),
+            vec![(Bytes::from("alice"), 1.5)]
+        );
+    }
+
+    #[test]
+    fn test_zrange_cmd_execute() -> Result<()> {
+        let backend = Backend::new();
+        backend
+            .zadd(
+                "board".into(),
+                vec![("alice".into(), 1.0), ("bob".into(), 2.0)],
+                ZAddFlags::default(),
+            )
+            .unwrap();
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$6\r\nzrange\r\n$5\r\nboard\r\n$1\r\n0\r\n$2\r\n-1\r\n$3\r\nREV\r\n$10\r\nWITHSCORES\r\n",
+        );
+        let cmd = ZRange::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("bob").into(),
+                RespDouble::new(2.0).into(),
+                BulkString::from("alice").into(),
+                RespDouble::new(1.0).into(),
+            ])
+            .into()
+        );
+
+        let cmd = ZRevRange(ZRange {
+            key: "board".into(),
+            start: -1,
+            stop: -1,
+            rev: true,
+            withscores: false,
+        });
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([BulkString::from("alice").into()]).into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrangebyscore_cmd_execute() -> Result<()> {
+        let backend = Backend::new();
+        backend
+            .zadd(
+                "board".into(),
+                vec![("a".into(), 1.0), ("b".into(), 2.0), ("c".into(), 3.0)],
+                ZAddFlags::default(),
+            )
+            .unwrap();
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$13\r\nzrangebyscore\r\n$5\r\nboard\r\n$2\r\n(1\r\n$4\r\n+inf\r\n$5\r\nLIMIT\r\n$1\r\n1\r\n$2\r\n-1\r\n",
+        );
+        let cmd = ZRangeByScore::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.min, Bound::Excluded(1.0));
+        assert_eq!(cmd.max, Bound::Included(f64::INFINITY));
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([BulkString::from("c").into()]).into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zcount_and_zlexcount_cmd_execute() {
+        let backend = Backend::new();
+        backend
+            .zadd(
+                "board".into(),
+                vec![("a".into(), 0.0), ("b".into(), 0.0), ("c".into(), 0.0)],
+                ZAddFlags::default(),
+            )
+            .unwrap();
+
+        let cmd = ZCount {
+            key: "board".into(),
+            min: Bound::Excluded(0.0),
+            max: Bound::Included(f64::INFINITY),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+
+        let cmd = ZCount {
+            key: "board".into(),
+            min: Bound::Included(f64::NEG_INFINITY),
+            max: Bound::Included(0.0),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+
+        let cmd = ZLexCount {
+            key: "board".into(),
+            min: LexBound::Excluded("a".into()),
+            max: LexBound::PositiveInfinity,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+    }
+
+    #[test]
+    fn test_zset_binary_members() -> Result<()> {
+        let backend = Backend::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*8\r\n$4\r\nzadd\r\n$3\r\nbin\r\n$1\r\n0\r\n$2\r\n\xff\x00\r\n$1\r\n0\r\n$1\r\nz\r\n$1\r\n0\r\n$2\r\n\xc3\xa9\r\n",
+        );
+        let cmd = ZAdd::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nzscore\r\n$3\r\nbin\r\n$2\r\n\xff\x00\r\n");
+        let cmd = ZScore::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.execute(&backend), RespDouble::new(0.0).into());
+
+        // members compare as bytes, so "\xff\x00" sorts after "\xc3\xa9", which is after "z"
+        let cmd = ZRangeByLex {
+            key: "bin".into(),
+            min: LexBound::Excluded("z".into()),
+            max: LexBound::Included(Bytes::from_static(b"\xff")),
+            limit: None,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([BulkString::from("\u{e9}").into()]).into()
+        );
+        let cmd = ZRangeByLex {
+            key: "bin".into(),
+            min: LexBound::Included(Bytes::from_static(b"\xff")),
+            max: LexBound::PositiveInfinity,
+            limit: None,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([BulkString::new(Bytes::from_static(b"\xff\x00")).into()]).into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zremrange_cmd_execute() -> Result<()> {
+        let backend = Backend::new();
+        backend
+            .zadd(
+                "board".into(),
+                vec![("a".into(), 1.0), ("b".into(), 2.0), ("c".into(), 3.0)],
+                ZAddFlags::default(),
+            )
+            .unwrap();
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$16\r\nzremrangebyscore\r\n$5\r\nboard\r\n$4\r\n-inf\r\n$2\r\n(2\r\n",
+        );
+        let cmd = ZRemRangeByScore::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = ZRemRangeByRank {
+            key: "board".into(),
+            start: 0,
+            stop: -1,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(backend.zcard(b"board").unwrap(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zmpop_cmd_execute() -> Result<()> {
+        let backend = Backend::new();
+        backend
+            .zadd(
+                "board".into(),
+                vec![("a".into(), 1.0), ("b".into(), 2.0), ("c".into(), 3.0)],
+                ZAddFlags::default(),
+            )
+            .unwrap();
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$5\r\nzmpop\r\n$1\r\n2\r\n$5\r\nempty\r\n$5\r\nboard\r\n$3\r\nMAX\r\n$5\r\nCOUNT\r\n$1\r\n2\r\n",
+        );
+        let cmd = ZMPop::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("board").into(),
+                RespArray::new([
+                    RespArray::new([BulkString::from("c").into(), RespDouble::new(3.0).into()])
+                        .into(),
+                    RespArray::new([BulkString::from("b").into(), RespDouble::new(2.0).into()])
+                        .into(),
+                ])
+                .into(),
+            ])
+            .into()
+        );
+        assert_eq!(backend.zcard(b"board").unwrap(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zscan_and_zrandmember_cmd_execute() {
+        let backend = Backend::new();
+        backend
+            .zadd(
+                "board".into(),
+                vec![("alice".into(), 1.0), ("bob".into(), 2.5)],
+                ZAddFlags::default(),
+            )
+            .unwrap();
+        let cmd = ZScan {
+            key: "board".into(),
+            cursor: 0,
+            pattern: Some("b*".into()),
+            count: 10,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("0").into(),
+                RespArray::new([
+                    BulkString::from("bob").into(),
+                    BulkString::from("2.5").into()
+                ])
+                .into(),
+            ])
+            .into()
+        );
+
+        let cmd = ZRandMember {
+            key: "board".into(),
+            count: Some(-3),
+            withscores: false,
+        };
+        let RespFrame::Array(members) = cmd.execute(&backend) else {
+            panic!("expected an array");
+        };
+        assert_eq!(members.len(), 3);
+
+        // a huge count picks every member once, and a huge negative one is refused
+        let cmd = ZRandMember {
+            key: "board".into(),
+            count: Some(i64::MAX),
+            withscores: false,
+        };
+        let RespFrame::Array(members) = cmd.execute(&backend) else {
+            panic!("expected an array");
+        };
+        assert_eq!(members.len(), 2);
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*3\r\n$11\r\nzrandmember\r\n$5\r\nboard\r\n$20\r\n-9223372036854775807\r\n",
+        );
+        assert!(ZRandMember::try_from(RespArray::decode(&mut buf).unwrap()).is_err());
+        buf.extend_from_slice(
+            b"*3\r\n$11\r\nzrandmember\r\n$5\r\nboard\r\n$19\r\n-4611686018427387903\r\n",
+        );
+        assert!(ZRandMember::try_from(RespArray::decode(&mut buf).unwrap()).is_ok());
+
+        let cmd = ZRandMember {
+            key: "missing".into(),
+            count: None,
+            withscores: false,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+    }
+
+    #[test]
+    fn test_zunionstore_cmd_execute() -> Result<()> {
+        let backend = Backend::new();
+        backend
+            .zadd(
+                "a".into(),
+                vec![("x".into(), 1.0), ("y".into(), 2.0)],
+                ZAddFlags::default(),
+            )
+            .unwrap();
+        backend
+            .zadd("b".into(), vec![("y".into(), 5.0)], ZAddFlags::default())
+            .unwrap();
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*10\r\n$11\r\nzunionstore\r\n$3\r\nout\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$7\r\nWEIGHTS\r\n$1\r\n2\r\n$1\r\n1\r\n$9\r\nAGGREGATE\r\n$3\r\nMAX\r\n",
+        );
+        let cmd = ZUnionStore::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(cmd.weights, vec![2.0, 1.0]);
+        assert_eq!(cmd.aggregate, Aggregate::Max);
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(
+            backend.zrange(b"out", 0, -1, false).unwrap(),
+            vec![(Bytes::from("x"), 2.0), (Bytes::from("y"), 5.0)]
+        );
+
+        let cmd = ZDiffStore(ZCombine {
+            operation: ZSetOperation::Diff,
+            destination: Some("out".into()),
+            keys: vec!["b".into(), "a".into()],
+            weights: vec![],
+            aggregate: Aggregate::Sum,
+            withscores: false,
+        });
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        assert_eq!(backend.zcard(b"out").unwrap(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zinter_cmd_execute() {
+        let backend = Backend::new();
+        backend
+            .zadd(
+                "a".into(),
+                vec![("x".into(), 1.0), ("y".into(), 2.0)],
+                ZAddFlags::default(),
+            )
+            .unwrap();
+        backend
+            .zadd("b".into(), vec![("y".into(), 5.0)], ZAddFlags::default())
+            .unwrap();
+        let cmd = ZInter(ZCombine {
+            operation: ZSetOperation::Inter,
+            destination: None,
+            keys: vec!["a".into(), "b".into()],
+            weights: vec![],
+            aggregate: Aggregate::Sum,
+            withscores: true,
+        });
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([BulkString::from("y").into(), RespDouble::new(7.0).into()]).into()
+        );
+    }
+
+    #[test]
+    fn test_zrangebylex_cmd_execute() -> Result<()> {
+        let backend = Backend::new();
+        backend
+            .zadd(
+                "words".into(),
+                vec![
+                    ("apple".into(), 0.0),
+                    ("banana".into(), 0.0),
+                    ("cherry".into(), 0.0),
+                ],
+                ZAddFlags::default(),
+            )
+            .unwrap();
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$11\r\nzrangebylex\r\n$5\r\nwords\r\n$2\r\n[b\r\n$1\r\n+\r\n",
+        );
+        let cmd = ZRangeByLex::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("banana").into(),
+                BulkString::from("cherry").into()
+            ])
+            .into()
+        );
+
+        let cmd = ZRangeByLex {
+            key: "words".into(),
+            min: LexBound::PositiveInfinity,
+            max: LexBound::PositiveInfinity,
+            limit: None,
+        };
+        assert_eq!(cmd.execute(&backend), RespArray::new([]).into());
+        Ok(())
+    }
+}
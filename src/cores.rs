@@ -0,0 +1,217 @@
+// Thread-per-core execution, the alternative to running every command against the one backend
+// the connections share. The keyspace is split among a thread per core, each owning the keys of
+// some hash slots in a backend of its own that no other thread touches, and the commands on
+// keys are sent over a channel to the thread owning them and run there, so the cores never wait
+// on each other's locks. The keys go by hash slot as in cluster mode, so hash tags keep the keys
+// of a command on the same core.
+//
+// Only the commands that just read and write keys, those `Command::execute_on` runs against a
+// storage, run on the cores: the other commands on keys are refused, as are those whose keys
+// belong to different cores or that a transaction would queue. The commands without keys run
+// against the shared backend, except those that would miss the keys of the cores: flushing,
+// saving, rewriting the AOF, replicating and scripts are refused, and so is turning on the AOF,
+// snapshots or eviction, which the server refuses to start with too. The keys of the cores only
+// expire when next read.
+
+use crate::{backend::key_slot, cmd::Command, Backend, RespFrame, SimpleError};
+use std::{io, sync::Arc, thread};
+use tokio::sync::{mpsc, oneshot};
+
+// A command for a core to run against one of its databases, and where to send the reply.
+struct Job {
+    db: usize,
+    cmd: Command,
+    reply: oneshot::Sender<RespFrame>,
+}
+
+/// The threads owning the keyspace in thread-per-core mode. The threads stop once every clone
+/// is dropped.
+#[derive(Debug, Clone)]
+pub struct Cores {
+    senders: Arc<[mpsc::UnboundedSender<Job>]>,
+}
+
+impl Cores {
+    /// Starts `count` threads, at least one, each owning `databases` databases.
+    pub fn start(count: usize, databases: usize) -> io::Result<Self> {
+        let senders = (0..count.max(1))
+            .map(|core| {
+                let (sender, mut jobs) = mpsc::unbounded_channel::<Job>();
+                // a single thread writes to it, the fewest shards will do
+                let backend = Backend::with_shards(databases, 2);
+                thread::Builder::new()
+                    .name(format!("core-{}", core))
+                    .spawn(move || {
+                        let dbs: Vec<Backend> = (0..backend.databases())
+                            .filter_map(|index| backend.select(index))
+                            .collect();
+                        while let Some(job) = jobs.blocking_recv() {
+                            let reply = match dbs.get(job.db) {
+                                Some(db) => {
+                                    job.cmd.execute_on(db).unwrap_or_else(|_| unsupported())
+                                }
+                                None => SimpleError::new("ERR DB index is out of range").into(),
+                            };
+                            let _ = job.reply.send(reply);
+                        }
+                    })?;
+                Ok(sender)
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Cores { senders })
+    }
+
+    pub fn len(&self) -> usize {
+        self.senders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    /// The core owning the `keys` of `cmd`, `None` when it has no keys and runs against the
+    /// shared backend, or the error refusing it.
+    pub fn route(&self, cmd: &Command, keys: &[&[u8]]) -> Result<Option<usize>, SimpleError> {
+        let Some((first, rest)) = keys.split_first() else {
+            return match misses_cores(cmd) {
+                true => Err(unsupported_error()),
+                false => Ok(None),
+            };
+        };
+        if !cmd.runs_on_storage() {
+            return Err(unsupported_error());
+        }
+        let core = self.owner(first);
+        if rest.iter().any(|key| self.owner(key) != core) {
+            return Err(SimpleError::new(
+                "ERR Keys in request don't belong to the same core",
+            ));
+        }
+        Ok(Some(core))
+    }
+
+    /// Runs `cmd` against the database `db` of the thread `core`.
+    pub async fn execute(&self, core: usize, db: usize, cmd: Command) -> RespFrame {
+        let (reply, replied) = oneshot::channel();
+        if self.senders[core].send(Job { db, cmd, reply }).is_err() {
+            return SimpleError::new("ERR the core owning the keys stopped").into();
+        }
+        replied
+            .await
+            .unwrap_or_else(|_| SimpleError::new("ERR the core owning the keys stopped").into())
+    }
+
+    fn owner(&self, key: &[u8]) -> usize {
+        key_slot(key) as usize % self.senders.len()
+    }
+}
+
+/// Whether setting the config parameter `name` to `value` turns on what would miss the keys of
+/// the cores: the AOF, snapshots or eviction.
+pub fn conflicting_config(name: &str, value: &str) -> bool {
+    match name.to_ascii_lowercase().as_str() {
+        "appendonly" => value.eq_ignore_ascii_case("yes"),
+        "save" => !value.trim().is_empty(),
+        "maxmemory" => value.trim() != "0",
+        _ => false,
+    }
+}
+
+// Whether `cmd`, which has no keys, acts on the whole keyspace, saves it or sends it elsewhere,
+// or may run commands on keys against the shared backend.
+fn misses_cores(cmd: &Command) -> bool {
+    match cmd {
+        Command::ConfigSet(cmd) => cmd
+            .iter()
+            .any(|(name, value)| conflicting_config(name, value)),
+        Command::Shutdown(cmd) => cmd.forces_save(),
+        cmd => matches!(
+            cmd,
+            Command::FlushDb(_)
+                | Command::SwapDb(_)
+                | Command::Save(_)
+                | Command::BgSave(_)
+                | Command::BgRewriteAof(_)
+                | Command::ReplicaOf(_)
+                | Command::Failover(_)
+                | Command::Eval(_)
+                | Command::EvalSha(_)
+                | Command::FCall(_)
+        ),
+    }
+}
+
+fn unsupported_error() -> SimpleError {
+    SimpleError::new("ERR the command isn't supported in thread-per-core mode")
+}
+
+fn unsupported() -> RespFrame {
+    unsupported_error().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespArray, SimpleString};
+
+    fn command(args: &[&'static str]) -> (Command, RespFrame) {
+        let args = args.iter().map(|arg| BulkString::from(*arg).into());
+        let frame = RespFrame::from(RespArray::new(args.collect::<Vec<_>>()));
+        (Command::try_from(frame.clone()).unwrap(), frame)
+    }
+
+    #[tokio::test]
+    async fn test_cores_own_keys() {
+        let cores = Cores::start(4, 2).unwrap();
+        assert_eq!(cores.len(), 4);
+        let run = |args| {
+            let (cmd, frame) = command(args);
+            let core = cores.route(&cmd, &cmd.keys(&frame));
+            (core, cmd)
+        };
+
+        let (core, cmd) = run(&["set", "{user}:name", "ann"]);
+        let core = core.unwrap().unwrap();
+        assert_eq!(
+            cores.execute(core, 0, cmd).await,
+            SimpleString::new("OK").into()
+        );
+        // the keys of a hash tag are on the same core, in each database of their own
+        let (owner, cmd) = run(&["get", "{user}:name"]);
+        assert_eq!(owner.unwrap(), Some(core));
+        assert_eq!(
+            cores.execute(core, 0, cmd).await,
+            BulkString::from("ann").into()
+        );
+        let (_, cmd) = run(&["get", "{user}:name"]);
+        assert_eq!(
+            cores.execute(core, 1, cmd).await,
+            RespFrame::Null(crate::RespNull)
+        );
+
+        let (core, _) = run(&["ping"]);
+        assert_eq!(core.unwrap(), None);
+        // the keyspace-wide commands would miss the keys of the cores
+        for args in [
+            &["flushdb"][..],
+            &["save"],
+            &["bgsave"],
+            &["shutdown", "save"],
+            &["config", "set", "appendonly", "yes"],
+            &["config", "set", "maxmemory", "1mb"],
+        ] {
+            let (core, _) = run(args);
+            assert_eq!(core.unwrap_err(), unsupported_error());
+        }
+        let (core, _) = run(&["config", "set", "appendonly", "no", "save", ""]);
+        assert_eq!(core.unwrap(), None);
+        let (core, _) = run(&["incr", "counter"]);
+        assert!(core.is_err());
+        // slots 0 and 1 are on different cores
+        let (core, _) = run(&["del", "{06S}a", "{Qi}b"]);
+        assert_eq!(
+            core.unwrap_err(),
+            SimpleError::new("ERR Keys in request don't belong to the same core")
+        );
+    }
+}
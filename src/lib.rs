@@ -2,7 +2,17 @@ mod backend;
 mod resp;
 
 pub mod cmd;
+pub mod cores;
 pub mod network;
+pub mod replication;
+pub mod session;
+#[cfg(feature = "tls")]
+mod tls;
 
-pub use backend::Backend;
+pub use backend::{
+    Backend, Clock, FunctionCall, FunctionLibrary, ListDirection, MockClock, PersistenceError,
+    ShardStats, Storage, SystemClock, WrongType, DEFAULT_DATABASES,
+};
+#[cfg(feature = "disk")]
+pub use backend::{DiskStorage, WritePolicy};
 pub use resp::*;
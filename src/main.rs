@@ -1,25 +1,555 @@
-use anyhow::Result;
-use simple_redis::{network, Backend};
-use tokio::net::TcpListener;
-use tracing::{info, warn};
+use anyhow::{anyhow, bail, Result};
+use clap::{Arg, CommandFactory, FromArgMatches, Parser};
+use simple_redis::{
+    cmd::{self, Command, CommandExecutor},
+    cores::Cores,
+    network, replication, Backend, BulkString, RespArray, RespFrame, DEFAULT_DATABASES,
+};
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    path::{Path, PathBuf},
+    process::{self, Stdio},
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+use tokio::{
+    net::TcpListener,
+    signal,
+    task::{self, JoinSet},
+    time,
+};
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+
+// How long the replicas have to acknowledge the last writes when a signal stops the server.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A simple Redis server. The parameters CONFIG SET takes are options too.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    /// A redis.conf-style file, whose directives the options override
+    config_file: Option<PathBuf>,
+    /// The addresses to listen on, `*` standing for any IPv4 address, `::*` for any IPv6 one
+    /// and a `-` prefix making one optional
+    #[arg(long, num_args = 1..)]
+    bind: Vec<String>,
+    /// The port to listen on
+    #[arg(long)]
+    port: Option<u16>,
+    /// The file to log to, the standard output when empty
+    #[arg(long)]
+    logfile: Option<String>,
+    /// Runs the server in the background, detached from the terminal
+    #[arg(long, value_parser = ["yes", "no"])]
+    daemonize: Option<String>,
+    /// The number of databases
+    #[arg(long)]
+    databases: Option<usize>,
+    /// Loads the append only file rather than the dump file, and logs the writes to it
+    #[arg(long, value_parser = ["yes", "no"])]
+    appendonly: Option<String>,
+    /// Runs the server as a cluster node
+    #[arg(long, value_parser = ["yes", "no"])]
+    cluster_enabled: Option<String>,
+    /// The master to replicate, as "<host> <port>"
+    #[arg(long)]
+    replicaof: Option<String>,
+    /// Splits the keys among a thread per core, running the commands on them on their thread
+    #[arg(long, value_parser = ["yes", "no"])]
+    thread_per_core: Option<String>,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt::init();
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--check-aof") {
+        tracing_subscriber::fmt::init();
+        return check_aof(&Backend::new(), &args[1..]);
+    }
+    let (config_file, directives) = directives()?;
+    let databases = databases(&directives)?.unwrap_or(DEFAULT_DATABASES);
+    let backend = Backend::with_shards(databases, keyspace_shards(&directives)?);
+    if let Some(path) = config_file {
+        backend.set_config_file(path.canonicalize()?);
+    }
+    let thread_per_core = thread_per_core(&directives)?;
+    if thread_per_core {
+        // the snapshots would miss the keys of the cores
+        backend.set_save_rules(vec![]);
+    }
+    let options = configure(&backend, directives)?;
+    if thread_per_core {
+        check_thread_per_core(&backend, &options)?;
+    }
+    if options.daemonize {
+        return daemonize();
+    }
+    init_logging(options.logfile.as_deref())?;
+    backend.set_listening_port(options.port);
+    if options.cluster_enabled {
+        backend.enable_cluster()?;
+        info!("Cluster mode enabled, node id {}", backend.myid());
+    }
+    if options.appendonly {
+        match cmd::load_aof(&backend) {
+            Ok(Some(commands)) => info!("DB loaded from append only file: {} commands", commands),
+            Ok(None) => {}
+            Err(e) => {
+                error!("Failed loading {}: {}", backend.aof_path().display(), e);
+                return Err(e);
+            }
+        }
+        backend.open_aof()?;
+    } else if thread_per_core {
+        // the keys loaded would be in the shared backend, out of reach of the cores
+        if backend.dump_path().exists() {
+            bail!(
+                "thread-per-core can't serve the keys of {}",
+                backend.dump_path().display()
+            );
+        }
+    } else {
+        match backend.load() {
+            Ok(Some(keys)) => info!("DB loaded from disk: {} keys", keys),
+            Ok(None) => {}
+            Err(e) => {
+                error!("Failed loading {}: {}", backend.dump_path().display(), e);
+                return Err(e.into());
+            }
+        }
+    }
+
+    // checks the save rules and deletes expired keys ten times a second, like the Redis
+    // server cron
+    let cron_backend = backend.clone();
+    tokio::spawn(async move {
+        let mut interval = time::interval(Duration::from_millis(100));
+        loop {
+            interval.tick().await;
+            cron_backend.run_save_rules();
+            let _lock = cron_backend.command_lock().await;
+            task::block_in_place(|| cron_backend.active_expire_cycle());
+        }
+    });
+
+    // flushes the append only file once per second
+    let aof_backend = backend.clone();
+    tokio::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            let backend = aof_backend.clone();
+            let _ = task::spawn_blocking(move || backend.flush_aof()).await;
+        }
+    });
+
+    // connects to the master once REPLICAOF makes this server a replica
+    tokio::spawn(replication::replicate(backend.clone()));
+
+    let cores = match thread_per_core {
+        true => {
+            let count = thread::available_parallelism().map_or(1, usize::from);
+            info!("Thread-per-core mode enabled, {} cores", count);
+            Some(Cores::start(count, backend.databases())?)
+        }
+        false => None,
+    };
+    let mut listeners = JoinSet::new();
+    for listener in listen(&options.bind, options.port).await? {
+        listeners.spawn(serve(listener, backend.clone(), cores.clone()));
+    }
+    let stop = stop_signal();
+    tokio::pin!(stop);
+    // the accept loops only end on an error, or stop when SHUTDOWN succeeded or on a signal
+    let signal = loop {
+        tokio::select! {
+            Some(served) = listeners.join_next() => served??,
+            _ = backend.shutdown_requested() => break None,
+            signal = &mut stop => break Some(signal?),
+        }
+    };
+    listeners.shutdown().await;
+    if let Some(signal) = signal {
+        warn!("Received {}, shutting down", signal);
+        // the commands running finish first, and none runs after them
+        let _lock = backend.transaction_lock().await;
+        task::block_in_place(|| backend.shutdown(None))?;
+        if !backend.wait_for_replicas(SHUTDOWN_TIMEOUT).await {
+            warn!("Replicas didn't acknowledge the last writes before shutting down");
+        }
+        info!("Simple Redis Server is now ready to exit, bye bye...");
+        return Ok(());
+    }
+    // the writes of the commands still running when SHUTDOWN did
+    backend.sync_aof()?;
+    info!("Simple Redis Server is now ready to exit, bye bye...");
+    Ok(())
+}
 
-    let addr = "0.0.0.0:6379";
-    let listener = TcpListener::bind(addr).await?;
-    info!("Simple Redis Server listening on {}", addr);
-    let backend = Backend::new();
+// Resolves with the name of the signal asking the server to stop, SIGTERM or SIGINT.
+async fn stop_signal() -> Result<&'static str> {
+    #[cfg(unix)]
+    {
+        let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            _ = terminate.recv() => Ok("SIGTERM"),
+            interrupt = signal::ctrl_c() => interrupt.map(|_| "SIGINT").map_err(Into::into),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        signal::ctrl_c().await?;
+        Ok("SIGINT")
+    }
+}
+
+// Listens on each address of `bind`, reporting every one that fails. The server doesn't start
+// when one did, unless all those failing are optional.
+async fn listen(bind: &str, port: u16) -> Result<Vec<TcpListener>> {
+    let mut listeners = vec![];
+    let mut failed = vec![];
+    for addr in bind.split_whitespace() {
+        let (optional, addr) = match addr.strip_prefix('-') {
+            Some(addr) => (true, addr),
+            None => (false, addr),
+        };
+        let host = match addr {
+            "*" => "0.0.0.0",
+            "::*" => "::",
+            addr => addr,
+        };
+        let addr = match host.contains(':') {
+            true => format!("[{}]:{}", host, port),
+            false => format!("{}:{}", host, port),
+        };
+        match TcpListener::bind((host, port)).await {
+            Ok(listener) => {
+                info!(
+                    "Simple Redis Server listening on {}",
+                    listener.local_addr()?
+                );
+                listeners.push(listener);
+            }
+            Err(e) if optional => warn!("Skipping optional address {}: {}", addr, e),
+            Err(e) => {
+                error!("Can't listen on {}: {}", addr, e);
+                failed.push(addr);
+            }
+        }
+    }
+    if !failed.is_empty() {
+        bail!("Failed listening on {}", failed.join(", "));
+    }
+    if listeners.is_empty() {
+        bail!("No address to listen on");
+    }
+    Ok(listeners)
+}
+
+async fn serve(listener: TcpListener, backend: Backend, cores: Option<Cores>) -> Result<()> {
+    let addr = listener.local_addr()?;
     loop {
-        let (stream, s_addr) = listener.accept().await?;
+        let (stream, s_addr) = listener
+            .accept()
+            .await
+            .map_err(|e| anyhow!("Can't accept connections on {}: {}", addr, e))?;
         info!("Accepted connection from: {}", s_addr);
         let cloned_backend = backend.clone();
+        let cores = cores.clone();
         tokio::spawn(async move {
-            match network::stream_handler(stream, cloned_backend).await {
+            let handled = match cores {
+                Some(cores) => {
+                    network::stream_handler_on_cores(stream, cloned_backend, cores).await
+                }
+                None => network::stream_handler(stream, cloned_backend).await,
+            };
+            match handled {
                 Ok(_) => info!("Connection from {} exited", s_addr),
                 Err(e) => warn!("Error handling connection {}: {:?}", s_addr, e),
             }
         });
     }
 }
+
+// `--check-aof [--fix] <file>`: reports whether the AOF can be loaded, and with `--fix`
+// truncates a damaged end. Exits with 1 when the file is left damaged.
+fn check_aof(backend: &Backend, args: &[String]) -> Result<()> {
+    let (fix, path) = match args {
+        [path] => (false, path),
+        [fix, path] if fix == "--fix" => (true, path),
+        _ => bail!("Usage: simple-redis --check-aof [--fix] <file>"),
+    };
+    let check = backend.check_aof(Path::new(path), fix)?;
+    let kind = if check.preamble {
+        "snapshot preamble and commands"
+    } else {
+        "commands"
+    };
+    println!("{}: {} bytes, {}", path, check.size, kind);
+    println!(
+        "{} valid commands in the first {} bytes",
+        check.commands, check.valid_len
+    );
+    let Some(e) = check.error else {
+        println!("AOF is valid");
+        return Ok(());
+    };
+    println!("{} at offset {}", e, check.valid_len);
+    let discarded = check.size - check.valid_len;
+    match (check.tail_only, fix) {
+        (true, true) => {
+            println!("Truncated the AOF, discarding {} bytes", discarded);
+            return Ok(());
+        }
+        (true, false) => println!(
+            "Only the end of the AOF is damaged: --fix would discard its last {} bytes",
+            discarded
+        ),
+        (false, _) => println!("Valid commands follow the damage, the AOF can't be fixed safely"),
+    }
+    process::exit(1);
+}
+
+// `(name, value)` pairs as in the config file, applied in order.
+type Directives = Vec<(String, String)>;
+
+// The directives of the config file the command line names, followed by those of its options
+// so they take precedence, and the path of the file.
+fn directives() -> Result<(Option<PathBuf>, Directives)> {
+    let mut command = Cli::command();
+    let mut params = vec![];
+    for name in cmd::config_params() {
+        if command
+            .get_arguments()
+            .all(|arg| arg.get_long() != Some(name))
+        {
+            command = command.arg(
+                Arg::new(name)
+                    .long(name)
+                    .value_name("VALUE")
+                    .help_heading("Config parameters"),
+            );
+            params.push(name);
+        }
+    }
+    let matches = command.get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
+    let mut directives = match &cli.config_file {
+        Some(path) => config_file_directives(path)?,
+        None => vec![],
+    };
+    let options = [
+        ("bind", (!cli.bind.is_empty()).then(|| cli.bind.join(" "))),
+        ("port", cli.port.map(|port| port.to_string())),
+        ("logfile", cli.logfile),
+        ("daemonize", cli.daemonize),
+        ("databases", cli.databases.map(|count| count.to_string())),
+        ("appendonly", cli.appendonly),
+        ("cluster-enabled", cli.cluster_enabled),
+        ("replicaof", cli.replicaof),
+        ("thread-per-core", cli.thread_per_core),
+    ];
+    directives.extend(
+        options
+            .into_iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value?))),
+    );
+    directives.extend(params.into_iter().filter_map(|name| {
+        let value = matches.get_one::<String>(name)?;
+        Some((name.to_string(), value.clone()))
+    }));
+    Ok((cli.config_file, directives))
+}
+
+fn config_file_directives(path: &Path) -> Result<Directives> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Can't open config file '{}': {}", path.display(), e))?;
+    cmd::parse_config_file(&text)
+        .map_err(|e| anyhow!("Bad config file '{}', {}", path.display(), e))
+}
+
+// The `databases` directive, read before the others as the backend is created with them.
+fn databases(directives: &[(String, String)]) -> Result<Option<usize>> {
+    let mut databases = None;
+    for (_, value) in directives.iter().filter(|(name, _)| name == "databases") {
+        match value.parse() {
+            Ok(count) if count > 0 => databases = Some(count),
+            _ => bail!("Invalid number of databases '{}'", value),
+        }
+    }
+    Ok(databases)
+}
+
+// The `keyspace-shards` directive, zero when unset, read before the others like `databases`.
+fn keyspace_shards(directives: &[(String, String)]) -> Result<usize> {
+    let mut shards = 0;
+    for (_, value) in directives
+        .iter()
+        .filter(|(name, _)| name == "keyspace-shards")
+    {
+        match value.parse() {
+            Ok(count) if count > 0 => shards = count,
+            _ => bail!("Invalid number of keyspace shards '{}'", value),
+        }
+    }
+    Ok(shards)
+}
+
+// The `thread-per-core` directive, read before the others as it changes the defaults.
+fn thread_per_core(directives: &[(String, String)]) -> Result<bool> {
+    let mut enabled = false;
+    for (name, value) in directives
+        .iter()
+        .filter(|(name, _)| name == "thread-per-core")
+    {
+        enabled = yes_no(name, value)?;
+    }
+    Ok(enabled)
+}
+
+// Refuses to start in thread-per-core mode with what would miss the keys of the cores: the AOF,
+// snapshots, replication and eviction.
+fn check_thread_per_core(backend: &Backend, options: &Options) -> Result<()> {
+    let conflict = if options.appendonly {
+        "appendonly yes"
+    } else if !backend.save_rules().is_empty() {
+        "save rules"
+    } else if backend.master().is_some() {
+        "replicaof"
+    } else if backend.maxmemory() > 0 {
+        "maxmemory"
+    } else {
+        return Ok(());
+    };
+    bail!("thread-per-core can't be used with {}", conflict)
+}
+
+// Startup options CONFIG SET doesn't cover.
+struct Options {
+    // whether the AOF is loaded instead of the dump file
+    appendonly: bool,
+    // the addresses to listen on, separated by spaces, `-` prefixing the optional ones
+    bind: String,
+    port: u16,
+    // loads the cluster config file, once the dir is known
+    cluster_enabled: bool,
+    logfile: Option<PathBuf>,
+    daemonize: bool,
+}
+
+// Applies the directives as CONFIG SET would, except the startup options.
+fn configure(backend: &Backend, directives: Directives) -> Result<Options> {
+    let mut options = Options {
+        appendonly: false,
+        bind: "0.0.0.0".to_string(),
+        port: 6379,
+        cluster_enabled: false,
+        logfile: None,
+        daemonize: false,
+    };
+    let mut frames = vec![
+        BulkString::from("config").into(),
+        BulkString::from("set").into(),
+    ];
+    for (name, value) in directives {
+        match name.as_str() {
+            "appendonly" => {
+                options.appendonly = yes_no(&name, &value)?;
+                continue;
+            }
+            "cluster-enabled" => {
+                options.cluster_enabled = yes_no(&name, &value)?;
+                continue;
+            }
+            "daemonize" => {
+                options.daemonize = yes_no(&name, &value)?;
+                continue;
+            }
+            // read by `databases`, `keyspace_shards` and `thread_per_core`
+            "databases" | "keyspace-shards" | "thread-per-core" => continue,
+            "bind" => {
+                options.bind = value;
+                continue;
+            }
+            "logfile" => {
+                options.logfile = (!value.is_empty()).then(|| PathBuf::from(value));
+                continue;
+            }
+            "port" => {
+                options.port = value
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid port '{}'", value))?;
+                continue;
+            }
+            // `--replicaof "<host> <port>"`, applied as REPLICAOF
+            "replicaof" => {
+                let mut args = vec![BulkString::from("replicaof").into()];
+                args.extend(
+                    value
+                        .split_whitespace()
+                        .map(|arg| BulkString::from(arg.to_string()).into()),
+                );
+                execute(backend, args)?;
+                continue;
+            }
+            _ => {}
+        }
+        frames.push(BulkString::from(name.to_string()).into());
+        frames.push(BulkString::from(value).into());
+    }
+    if frames.len() > 2 {
+        execute(backend, frames)?;
+    }
+    Ok(options)
+}
+
+fn yes_no(name: &str, value: &str) -> Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => bail!("{} must be 'yes' or 'no'", name),
+    }
+}
+
+// Starts the server again in the background, detached from the terminal, and exits.
+fn daemonize() -> Result<()> {
+    process::Command::new(env::current_exe()?)
+        .args(env::args_os().skip(1))
+        .args(["--daemonize", "no"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    process::exit(0);
+}
+
+// Logs to `logfile`, or to the standard output when there is none.
+fn init_logging(logfile: Option<&Path>) -> Result<()> {
+    let builder = tracing_subscriber::fmt().with_env_filter(EnvFilter::from_default_env());
+    match logfile {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| anyhow!("Can't open log file '{}': {}", path.display(), e))?;
+            builder
+                .with_ansi(false)
+                .with_writer(Mutex::new(file))
+                .init();
+        }
+        None => builder.init(),
+    }
+    Ok(())
+}
+
+fn execute(backend: &Backend, frames: Vec<RespFrame>) -> Result<()> {
+    let cmd = Command::try_from(RespArray::new(frames))?;
+    if let RespFrame::SimpleError(e) = cmd.execute(backend) {
+        bail!("{}", e.as_str());
+    }
+    Ok(())
+}
@@ -1,67 +1,498 @@
 use anyhow::Result;
 use bytes::BytesMut;
-use futures::SinkExt;
-use tokio::net::TcpStream;
+use futures::{FutureExt, SinkExt};
+use std::time::Instant;
+use tokio::{net::TcpStream, time};
 use tokio_stream::StreamExt;
 use tokio_util::codec::{Decoder, Encoder, Framed};
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::{
-    cmd::{Command, CommandExecutor},
-    Backend, RespDecoder, RespEncoder, RespError, RespFrame,
+    cmd::{unless_busy, Command, CommandError, CommandExecutor},
+    cores::Cores,
+    decode_with, replication,
+    session::Session,
+    strict_parsing, Backend, FrameParser, RespError, RespFrame, SimpleError, SimpleString,
 };
 
-#[derive(Debug)]
-struct RespCodec;
+// the replies to pipelined commands are written out together, unless they buffer more than this
+const PIPELINE_FLUSH_BYTES: usize = 64 * 1024;
 
 #[derive(Debug)]
-struct RedisRequest {
-    frame: RespFrame,
-    backend: Backend,
+pub(crate) struct RespCodec {
+    // the RESP version of the replies, negotiated with HELLO
+    protocol: u8,
+    // what was decoded of a command not all read yet
+    parser: FrameParser,
+}
+
+impl Default for RespCodec {
+    fn default() -> Self {
+        RespCodec {
+            protocol: 2,
+            parser: FrameParser::default(),
+        }
+    }
 }
 
 #[derive(Debug)]
 struct RedisResponse {
-    frame: RespFrame,
+    // a command changing several subscriptions replies once for each
+    frames: Vec<RespFrame>,
+    // the port a replica announced with REPLCONF
+    listening_port: Option<u16>,
 }
 
 pub async fn stream_handler(stream: TcpStream, backend: Backend) -> Result<()> {
+    handle_stream(stream, backend, None).await
+}
+
+/// Serves a connection in thread-per-core mode, the commands on keys running on the thread of
+/// `cores` owning them.
+pub async fn stream_handler_on_cores(
+    stream: TcpStream,
+    backend: Backend,
+    cores: Cores,
+) -> Result<()> {
+    handle_stream(stream, backend, Some(cores)).await
+}
+
+async fn handle_stream(stream: TcpStream, backend: Backend, cores: Option<Cores>) -> Result<()> {
+    let peer = stream.peer_addr()?;
     // how to get a frame from the stream
-    let mut framed = Framed::new(stream, RespCodec);
+    let mut framed = Framed::new(stream, RespCodec::default());
+    // refused before it takes up anything more, the connection counting among those of its
+    // address as long as it is open
+    let _admitted = match backend.admit_connection(peer.ip()) {
+        Ok(admitted) => admitted,
+        Err(e) => {
+            info!("Refusing connection from {}: {}", peer, e);
+            framed.send(SimpleError::new(e).into()).await?;
+            return Ok(());
+        }
+    };
+    let mut session = Session::new(backend, peer);
+    session.cores = cores;
     loop {
-        match framed.next().await {
+        // the commands a client pipelined are run one after the other while they are already
+        // read, their replies only flushed once there is none left
+        let pipelined = match framed.write_buffer().len() {
+            0 => None,
+            n if n >= PIPELINE_FLUSH_BYTES => None,
+            _ => framed.next().now_or_never(),
+        };
+        let next = match pipelined {
+            Some(next) => next,
+            None => {
+                if !flush(&mut framed, &session).await? {
+                    return Ok(());
+                }
+                let idle_check = session.idle_check();
+                tokio::select! {
+                    next = framed.next() => next,
+                    Some(message) = session.subscriber.message() => {
+                        if !queue_frame(&mut framed, &session, &message) {
+                            return Ok(());
+                        }
+                        continue;
+                    }
+                    _ = time::sleep_until(idle_check.into()) => {
+                        if session.is_idle() {
+                            info!("Closing idle connection from {}", session.peer);
+                            return Ok(());
+                        }
+                        continue;
+                    }
+                }
+            }
+        };
+        match next {
             Some(Ok(frame)) => {
-                info!("Received frame: {:?}", frame);
-                let req = RedisRequest {
-                    frame,
-                    backend: backend.clone(),
-                };
-                let res = request_handler(req).await?;
-                framed.send(res.frame).await?;
+                let name = command_name(&frame);
+                if carries_password(&name) {
+                    info!("Received frame: {} (arguments redacted)", name);
+                } else {
+                    info!("Received frame: {:?}", frame);
+                }
+                if is_quit(&frame) {
+                    framed.send(SimpleString::new("OK").into()).await?;
+                    return Ok(());
+                }
+                session.backend.client_command(session.id(), &name);
+                if replication::is_sync_request(&frame) {
+                    // the replicas would miss the keys of the cores in thread-per-core mode
+                    let refused = match session.may_run() {
+                        false => Some(no_auth()),
+                        true if session.cores.is_some() => Some(
+                            SimpleError::new(
+                                "ERR the command isn't supported in thread-per-core mode",
+                            )
+                            .into(),
+                        ),
+                        true => None,
+                    };
+                    if let Some(refused) = refused {
+                        if !queue_frame(&mut framed, &session, &refused) {
+                            return Ok(());
+                        }
+                        continue;
+                    }
+                    let backend = session.backend;
+                    backend.update_client(session.subscriber.id(), |info| info.replica = true);
+                    let port = session.replica_port.unwrap_or(session.peer.port());
+                    let ip = session.peer.ip().to_string();
+                    return replication::serve_replica(framed, backend, frame, ip, port).await;
+                }
+                session.start_command();
+                let res = request_handler(frame, &mut session).await;
+                if session.backend.shutting_down() {
+                    // SHUTDOWN closes the connection instead of replying, after the replies to
+                    // the commands before it
+                    flush(&mut framed, &session).await?;
+                    return Ok(());
+                }
+                session.replica_port = res.listening_port.or(session.replica_port);
+                framed.codec_mut().protocol = session.protocol();
+                if !session.end_command() {
+                    continue;
+                }
+                for frame in res.frames {
+                    if !queue_frame(&mut framed, &session, &frame) {
+                        return Ok(());
+                    }
+                }
+            }
+            // the rest of the stream can't be framed after a malformed frame, so the error is
+            // the last reply
+            Some(Err(e)) => match e.downcast::<RespError>() {
+                Ok(e) => {
+                    info!("Closing connection on protocol error: {}", e);
+                    framed.send(CommandError::from(e).into()).await?;
+                    return Ok(());
+                }
+                Err(e) => return Err(e),
+            },
+            None => {
+                // a client may pipeline its commands and close its side right away
+                flush(&mut framed, &session).await?;
+                return Ok(());
             }
-            Some(Err(e)) => return Err(e),
-            None => return Ok(()),
         }
     }
 }
 
-async fn request_handler(req: RedisRequest) -> Result<RedisResponse> {
-    let (frame, backend) = (req.frame, req.backend);
+// Encodes `frame` into the write buffer of the connection, its bytes counting toward the output
+// buffer until they are flushed. Returns false once the output buffer overflows, so the
+// connection is closed.
+fn queue_frame(
+    framed: &mut Framed<TcpStream, RespCodec>,
+    session: &Session,
+    frame: &RespFrame,
+) -> bool {
+    let before = framed.write_buffer().len();
+    let protocol = framed.codec().protocol;
+    frame.encode_for(protocol, framed.write_buffer_mut());
+    let size = framed.write_buffer().len() - before;
+    if !session
+        .backend
+        .queue_output(session.subscriber.output(), size)
+    {
+        overflowed(session);
+        return false;
+    }
+    true
+}
+
+// Writes out the replies in the write buffer. Returns false, with them left unwritten, once the
+// output buffer overflows while the client doesn't read them.
+async fn flush(framed: &mut Framed<TcpStream, RespCodec>, session: &Session) -> Result<bool> {
+    let size = framed.write_buffer().len();
+    if size == 0 {
+        return Ok(true);
+    }
+    let (backend, output) = (&session.backend, session.subscriber.output());
+    let flushed = tokio::select! {
+        flushed = framed.flush() => {
+            flushed?;
+            true
+        }
+        _ = backend.output_overflow(output) => false,
+    };
+    if !flushed {
+        overflowed(session);
+        return Ok(false);
+    }
+    output.written(size);
+    Ok(true)
+}
+
+fn overflowed(session: &Session) {
+    warn!(
+        "Closing connection from {} for overcoming of output buffer limits",
+        session.peer
+    );
+}
+
+// Runs the command in `frame`, replying to a command that can't run with an error rather than
+// failing, so the connection stays open.
+async fn request_handler(frame: RespFrame, session: &mut Session) -> RedisResponse {
+    let aof_frame = session.backend.propagating().then(|| frame.clone());
+    // the keys decide whether this node serves the command in cluster mode
+    let cluster_frame = session.backend.cluster_enabled().then(|| frame.clone());
+    // the keys a tracking connection reads are those it may cache
+    let tracking_frame = session
+        .backend
+        .is_tracking(session.id())
+        .then(|| frame.clone());
+    // the keys decide which core runs the command in thread-per-core mode
+    let cores_frame = session.cores.is_some().then(|| frame.clone());
+    // quoted by the error refusing a command in subscribe mode, which RESP3 doesn't have
+    let name = (session.subscriber.is_subscribed() && session.protocol() < 3)
+        .then(|| command_name(&frame));
     let cmd = match Command::try_from(frame) {
         Ok(cmd) => cmd,
-        Err(e) => return Ok(RedisResponse { frame: e.into() }),
+        Err(e) => {
+            session.transaction.abort();
+            return RedisResponse {
+                frames: vec![e.into()],
+                listening_port: None,
+            };
+        }
     };
+    if !session.may_run()
+        && !matches!(
+            cmd,
+            Command::Auth(_) | Command::Hello(_) | Command::Reset(_)
+        )
+    {
+        session.transaction.abort();
+        return RedisResponse {
+            frames: vec![no_auth()],
+            listening_port: None,
+        };
+    }
+    if let Some(name) = name {
+        match cmd {
+            Command::Ping(cmd) => return subscribed(vec![cmd.execute_subscribed()]),
+            ref cmd if !cmd.allowed_while_subscribed() => {
+                let e = format!(
+                    "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+                    name
+                );
+                return subscribed(vec![SimpleError::new(e).into()]);
+            }
+            _ => {}
+        }
+    }
+    // the commands acting on the connection as a whole
+    let cmd = match cmd {
+        // increments past 64 bits reply with big numbers, which RESP2 doesn't have
+        Command::IncrBy(cmd) => {
+            let promote = session.protocol() >= 3 && session.backend.bignum_incr();
+            cmd.promoting(promote).into()
+        }
+        Command::Reset(cmd) => {
+            return RedisResponse {
+                frames: vec![cmd.execute_connection(session)],
+                listening_port: None,
+            }
+        }
+        Command::ClientReply(cmd) => {
+            return RedisResponse {
+                frames: vec![cmd.execute_connection(session)],
+                listening_port: None,
+            }
+        }
+        cmd => cmd,
+    };
+    let Session {
+        backend,
+        subscriber,
+        transaction,
+        cores,
+        ..
+    } = session;
     info!("Executing command: {:?}", cmd);
-    let frame = cmd.execute(&backend);
-    Ok(RedisResponse { frame })
+    let listening_port = match &cmd {
+        Command::ReplConf(cmd) => cmd.listening_port(),
+        _ => None,
+    };
+    if let Some(frame) = &cluster_frame {
+        // the keys of a transaction must all be in the same slot
+        let checked = match transaction.queues(&cmd) {
+            true => transaction.check_keys_slot(backend, &cmd.keys(frame)),
+            false => backend.check_keys_slot(&cmd.keys(frame)),
+        };
+        if let Err(e) = checked {
+            transaction.abort();
+            return RedisResponse {
+                frames: vec![SimpleError::new(e).into()],
+                listening_port,
+            };
+        }
+    }
+    if let Some(frame) = &tracking_frame {
+        // remembered before the command runs, so a write right after it isn't missed
+        if !cmd.is_write() {
+            backend.track_keys(subscriber.id(), &cmd.keys(frame));
+        }
+    }
+    let routed = match (cores.as_ref(), &cores_frame) {
+        (Some(cores), Some(frame)) => match cores.route(&cmd, &cmd.keys(frame)) {
+            // the transactions run against the shared backend
+            Ok(Some(_)) if transaction.queues(&cmd) => Err(SimpleError::new(
+                "ERR commands on keys can't be queued in thread-per-core mode",
+            )),
+            Ok(core) => Ok(core.map(|core| (cores, core))),
+            Err(e) => Err(e),
+        },
+        _ => Ok(None),
+    };
+    let routed = match routed {
+        Ok(routed) => routed,
+        Err(e) => {
+            transaction.abort();
+            return RedisResponse {
+                frames: vec![e.into()],
+                listening_port,
+            };
+        }
+    };
+    // keep what to log in the AOF and stream to the replicas, as executing the command
+    // consumes it
+    let propagated = aof_frame
+        .filter(|_| cmd.is_write())
+        .map(|frame| cmd.propagated(frame, backend));
+    if transaction.queues(&cmd) {
+        return RedisResponse {
+            frames: vec![transaction.queue(cmd, propagated)],
+            listening_port,
+        };
+    }
+    // CLIENT PAUSE holds the commands until it ends, except the one ending it
+    if !matches!(cmd, Command::ClientUnpause(_)) {
+        let write =
+            cmd.pausable_write() || (matches!(cmd, Command::Exec(_)) && transaction.writes());
+        backend.clients_unpaused(write).await;
+    }
+    if cmd.is_write() {
+        // a failover holds writes until the new master caught up with them
+        backend.writes_unpaused().await;
+    }
+    if cmd.is_write() && backend.rejects_writes() {
+        return RedisResponse {
+            frames: vec![
+                SimpleError::new("READONLY You can't write against a read only replica.").into(),
+            ],
+            listening_port,
+        };
+    }
+    if cmd.denies_oom() && !backend.evict_to_fit() {
+        return RedisResponse {
+            frames: vec![SimpleError::new(
+                "OOM command not allowed when used memory > 'maxmemory'.",
+            )
+            .into()],
+            listening_port,
+        };
+    }
+    if let Some((cores, core)) = routed {
+        return RedisResponse {
+            frames: vec![cores.execute(core, backend.db_index(), cmd).await],
+            listening_port,
+        };
+    }
+    let frame = match cmd {
+        Command::BLMove(cmd) => cmd.execute_blocking(backend, propagated).await,
+        Command::BLMPop(cmd) => cmd.execute_blocking(backend, propagated).await,
+        Command::Wait(cmd) => cmd.execute_blocking(backend).await,
+        Command::Subscribe(cmd) => return subscribed(cmd.execute_subscriber(subscriber)),
+        Command::Unsubscribe(cmd) => return subscribed(cmd.execute_subscriber(subscriber)),
+        Command::PSubscribe(cmd) => return subscribed(cmd.execute_subscriber(subscriber)),
+        Command::PUnsubscribe(cmd) => return subscribed(cmd.execute_subscriber(subscriber)),
+        Command::SSubscribe(cmd) => return subscribed(cmd.execute_subscriber(subscriber)),
+        Command::SUnsubscribe(cmd) => return subscribed(cmd.execute_subscriber(subscriber)),
+        Command::ClientTracking(cmd) => cmd.execute_client(backend, subscriber.id()),
+        Command::ClientSetName(cmd) => cmd.execute_client(subscriber),
+        Command::ClientGetName(cmd) => cmd.execute_client(subscriber),
+        Command::ClientId(cmd) => cmd.execute_client(subscriber),
+        Command::ClientInfo(cmd) => cmd.execute_client(backend, subscriber),
+        Command::Hello(cmd) => cmd.execute_client(backend, subscriber),
+        Command::Auth(cmd) => cmd.execute_client(backend, subscriber),
+        Command::Select(cmd) => cmd.execute_connection(backend),
+        Command::Multi(cmd) => cmd.execute_transaction(transaction),
+        Command::Exec(cmd) => {
+            cmd.execute_transaction(backend, transaction, subscriber.id())
+                .await
+        }
+        Command::Discard(cmd) => cmd.execute_transaction(backend, transaction, subscriber.id()),
+        Command::Watch(cmd) => cmd.execute_transaction(backend, transaction, subscriber.id()),
+        Command::Unwatch(cmd) => cmd.execute_transaction(backend, subscriber.id()),
+        Command::Eval(cmd) => cmd.execute_atomic(backend).await,
+        Command::EvalSha(cmd) => cmd.execute_atomic(backend).await,
+        Command::FCall(cmd) => cmd.execute_atomic(backend).await,
+        Command::SwapDb(cmd) => cmd.execute_atomic(backend, propagated).await,
+        Command::DebugSleep(cmd) => cmd.execute_atomic(backend).await,
+        // the one command that runs while a script holds the lock
+        Command::ScriptKill(cmd) => cmd.execute(backend),
+        cmd => match unless_busy(backend, backend.command_lock()).await {
+            Ok(_lock) => {
+                // the writes running at once are propagated in the order they ran
+                let _order = propagated.as_ref().map(|_| backend.propagation_order());
+                let started = Instant::now();
+                let reply = cmd.execute(backend);
+                backend.record_latency("command", started.elapsed());
+                if let Some(propagated) = propagated {
+                    propagated.propagate(backend, &reply);
+                }
+                reply
+            }
+            Err(busy) => busy,
+        },
+    };
+    RedisResponse {
+        frames: vec![frame],
+        listening_port,
+    }
+}
+
+// The lower-cased name of the command in `frame`.
+fn command_name(frame: &RespFrame) -> String {
+    match frame {
+        RespFrame::Array(args) => match args.first() {
+            Some(RespFrame::BulkString(name)) => String::from_utf8_lossy(name).to_lowercase(),
+            _ => String::new(),
+        },
+        _ => String::new(),
+    }
+}
+
+// Whether the arguments of the command `name` may hold a password, which mustn't be logged.
+fn carries_password(name: &str) -> bool {
+    matches!(name, "auth" | "hello" | "config")
+}
+
+fn no_auth() -> RespFrame {
+    SimpleError::new("NOAUTH Authentication required.").into()
+}
+
+fn is_quit(frame: &RespFrame) -> bool {
+    command_name(frame) == "quit"
+}
+
+fn subscribed(frames: Vec<RespFrame>) -> RedisResponse {
+    RedisResponse {
+        frames,
+        listening_port: None,
+    }
 }
 
 impl Encoder<RespFrame> for RespCodec {
     type Error = anyhow::Error;
 
     fn encode(&mut self, item: RespFrame, dst: &mut BytesMut) -> Result<()> {
-        let encoded = item.encode();
-        dst.extend_from_slice(&encoded);
+        // the types RESP3 added are sent to RESP2 clients as those standing for them
+        item.encode_for(self.protocol, dst);
         Ok(())
     }
 }
@@ -71,10 +502,151 @@ impl Decoder for RespCodec {
     type Error = anyhow::Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<RespFrame>> {
-        match RespFrame::decode(src) {
+        match decode_with(strict_parsing(), || self.parser.parse(src)) {
             Ok(frame) => Ok(Some(frame)),
             Err(RespError::FrameNotComplete) => Ok(None),
             Err(e) => Err(e.into()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[tokio::test]
+    async fn test_errors_keep_connection() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(stream_handler(stream, Backend::new()));
+            }
+        });
+        let mut client = TcpStream::connect(("127.0.0.1", port)).await?;
+        client
+            .write_all(b"*1\r\n$4\r\nnope\r\n*2\r\n$3\r\nget\r\n*0\r\n:1\r\n*1\r\n$4\r\nping\r\n")
+            .await?;
+        let expected = b"-ERR unknown command 'nope'\r\n-ERR wrong number of arguments for command\r\n-ERR Command must be an Array\r\n+PONG\r\n";
+        let mut reply = vec![0; expected.len()];
+        client.read_exact(&mut reply).await?;
+        assert_eq!(
+            String::from_utf8_lossy(&reply),
+            String::from_utf8_lossy(expected)
+        );
+
+        // a malformed frame is answered, then the connection is closed
+        client.write_all(b"!bad\r\n").await?;
+        let mut reply = String::new();
+        client.read_to_string(&mut reply).await?;
+        assert!(reply.starts_with("-ERR Protocol error: "), "{}", reply);
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_encode_big_number() -> Result<()> {
+        let frame: RespFrame = crate::RespBigNumber::new(1u128 << 64).into();
+        let mut codec = RespCodec::default();
+        let mut buf = BytesMut::new();
+        codec.encode(frame.clone(), &mut buf)?;
+        assert_eq!(&buf[..], b"$20\r\n18446744073709551616\r\n");
+
+        codec.protocol = 3;
+        let mut buf = BytesMut::new();
+        codec.encode(frame, &mut buf)?;
+        assert_eq!(&buf[..], b"(18446744073709551616\r\n");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_connections_per_address() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let backend = Backend::new();
+        backend.set_max_connections_per_address(1);
+        let server = backend.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(stream_handler(stream, server.clone()));
+            }
+        });
+        let mut client = TcpStream::connect(("127.0.0.1", port)).await?;
+        client.write_all(b"*1\r\n$4\r\nping\r\n").await?;
+        let mut reply = [0; 7];
+        client.read_exact(&mut reply).await?;
+        assert_eq!(&reply, b"+PONG\r\n");
+
+        let mut refused = TcpStream::connect(("127.0.0.1", port)).await?;
+        let mut reply = String::new();
+        refused.read_to_string(&mut reply).await?;
+        assert_eq!(
+            reply,
+            "-ERR max number of connections from your address reached\r\n"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_replies() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(stream_handler(stream, Backend::new()));
+            }
+        });
+        let mut client = TcpStream::connect(("127.0.0.1", port)).await?;
+        let commands = b"*3\r\n$5\r\nrpush\r\n$4\r\nlist\r\n$1\r\nx\r\n".repeat(1000);
+        client.write_all(&commands).await?;
+        // the replies are all written out though the client closes its side right away
+        client.shutdown().await?;
+        let mut reply = String::new();
+        client.read_to_string(&mut reply).await?;
+        let expected: String = (1..=1000).map(|n| format!(":{}\r\n", n)).collect();
+        assert_eq!(reply, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_thread_per_core() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let backend = Backend::new();
+        let cores = Cores::start(2, backend.databases())?;
+        let server = backend.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(stream_handler_on_cores(
+                    stream,
+                    server.clone(),
+                    cores.clone(),
+                ));
+            }
+        });
+        let mut client = TcpStream::connect(("127.0.0.1", port)).await?;
+        client
+            .write_all(b"*3\r\n$3\r\nset\r\n$3\r\nkey\r\n$1\r\nx\r\n*2\r\n$3\r\nget\r\n$3\r\nkey\r\n*2\r\n$4\r\nincr\r\n$3\r\nkey\r\n*1\r\n$4\r\nping\r\n")
+            .await?;
+        let expected = "+OK\r\n$1\r\nx\r\n-ERR the command isn't supported in thread-per-core mode\r\n+PONG\r\n";
+        let mut reply = vec![0; expected.len()];
+        client.read_exact(&mut reply).await?;
+        assert_eq!(String::from_utf8_lossy(&reply), expected);
+        // the key is on its core, not in the shared backend
+        assert_eq!(backend.get(b"key").unwrap(), None);
+
+        // nothing flushes, saves or replicates the keys of the cores
+        client
+            .write_all(b"*1\r\n$7\r\nflushdb\r\n*1\r\n$4\r\nsave\r\n*1\r\n$4\r\nsync\r\n*2\r\n$3\r\nget\r\n$3\r\nkey\r\n")
+            .await?;
+        let refused = "-ERR the command isn't supported in thread-per-core mode\r\n";
+        let expected = format!("{}{}{}$1\r\nx\r\n", refused, refused, refused);
+        let mut reply = vec![0; expected.len()];
+        client.read_exact(&mut reply).await?;
+        assert_eq!(String::from_utf8_lossy(&reply), expected);
+        Ok(())
+    }
+}
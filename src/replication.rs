@@ -0,0 +1,639 @@
+// Replication links: streaming to the replicas connected to this server, and, when this server
+// is a replica, the link with its master. The state they share lives in the backend.
+
+use crate::{
+    backend::{FailoverState, FullSync, MasterAddr, ReplicationTls, SyncSnapshot},
+    cmd::{Command, CommandExecutor},
+    network::RespCodec,
+    Backend, BulkString, RespArray, RespDecoder, RespEncoder, RespError, RespFrame, SimpleError,
+    SimpleString,
+};
+use anyhow::{anyhow, bail, Result};
+use bytes::BytesMut;
+use futures::SinkExt;
+use std::time::Duration;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+    task, time,
+};
+use tokio_stream::StreamExt;
+use tokio_util::codec::Framed;
+use tracing::{info, warn};
+
+// how long a replica waits before connecting to its master again
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+// length of the delimiter ending the snapshot of a diskless sync
+const EOF_MARK_LEN: usize = 40;
+// how often a replica acknowledges the stream without being asked
+const ACK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Whether `frame` asks for a full synchronization, which turns its connection into a replica
+/// link.
+pub(crate) fn is_sync_request(frame: &RespFrame) -> bool {
+    let RespFrame::Array(array) = frame else {
+        return false;
+    };
+    matches!(
+        array.first(),
+        Some(RespFrame::BulkString(cmd))
+            if cmd.eq_ignore_ascii_case(b"psync") || cmd.eq_ignore_ascii_case(b"sync")
+    )
+}
+
+// The replication id of a master failing over to this replica, which sends it in a PSYNC
+// followed by its offset and FAILOVER.
+fn failover_replid(frame: &RespFrame) -> Option<&[u8]> {
+    let RespFrame::Array(array) = frame else {
+        return None;
+    };
+    match array.as_slice() {
+        [_, RespFrame::BulkString(replid), _, RespFrame::BulkString(failover)]
+            if failover.eq_ignore_ascii_case(b"failover") =>
+        {
+            Some(replid.as_ref())
+        }
+        _ => None,
+    }
+}
+
+/// Sends a snapshot of the dataset to the replica on the other end of `framed`, then streams
+/// the write commands to it until it disconnects. A master failing over to this server first
+/// has it take over as the master.
+pub(crate) async fn serve_replica(
+    mut framed: Framed<TcpStream, RespCodec>,
+    backend: Backend,
+    request: RespFrame,
+    ip: String,
+    port: u16,
+) -> Result<()> {
+    if let Some(replid) = failover_replid(&request) {
+        let error = if backend.master().is_none() {
+            Some("ERR PSYNC FAILOVER can't be sent to a master.")
+        } else if replid != backend.replid().as_bytes() {
+            Some("ERR PSYNC FAILOVER replid must match my replid.")
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            framed.send(SimpleError::new(error).into()).await?;
+            return Ok(());
+        }
+        info!("Taking over as master at the request of {}:{}", ip, port);
+        backend.set_master(None);
+    }
+    info!("Replica {}:{} asks for synchronization", ip, port);
+    let sync_backend = backend.clone();
+    let mut sync = task::spawn_blocking(move || sync_backend.full_sync(ip, port)).await??;
+    let result = stream_to_replica(&mut framed, &backend, &mut sync).await;
+    backend.remove_replica(sync.id);
+    result
+}
+
+async fn stream_to_replica(
+    framed: &mut Framed<TcpStream, RespCodec>,
+    backend: &Backend,
+    sync: &mut FullSync,
+) -> Result<()> {
+    let reply = format!("FULLRESYNC {} {}", sync.replid, sync.offset);
+    framed.send(SimpleString::new(reply).into()).await?;
+    let socket = framed.get_mut();
+    match &mut sync.snapshot {
+        SyncSnapshot::File(snapshot) => {
+            socket
+                .write_all(format!("${}\r\n", snapshot.len()).as_bytes())
+                .await?;
+            socket.write_all(snapshot).await?;
+        }
+        // the length isn't known up front, so the end is marked with a random delimiter
+        SyncSnapshot::Diskless(chunks) => {
+            let mark = new_eof_mark();
+            socket
+                .write_all(format!("$EOF:{}\r\n", mark).as_bytes())
+                .await?;
+            while let Some(chunk) = chunks.recv().await {
+                socket.write_all(&chunk?).await?;
+            }
+            socket.write_all(mark.as_bytes()).await?;
+        }
+    }
+    backend.replica_online(sync.id);
+    info!("Synchronization with replica succeeded");
+    loop {
+        tokio::select! {
+            bytes = sync.stream.recv() => match bytes {
+                Some(bytes) => {
+                    framed.get_mut().write_all(&bytes).await?;
+                    sync.output.written(bytes.len());
+                }
+                None => return Ok(()),
+            },
+            frame = framed.next() => match frame {
+                Some(Ok(frame)) => {
+                    if let Ok(Command::ReplConf(cmd)) = Command::try_from(frame) {
+                        if let Some(offset) = cmd.ack() {
+                            backend.replica_ack(sync.id, offset);
+                        }
+                    }
+                }
+                Some(Err(e)) => return Err(e),
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+/// Keeps this server in sync with the master set by REPLICAOF, connecting again whenever the
+/// link breaks. Runs for the lifetime of the server.
+pub async fn replicate(backend: Backend) {
+    let mut master = backend.watch_master();
+    loop {
+        let addr = master.borrow_and_update().clone();
+        let Some(addr) = addr else {
+            if master.changed().await.is_err() {
+                return;
+            }
+            continue;
+        };
+        tokio::select! {
+            result = sync_with_master(&backend, &addr) => {
+                backend.set_master_link_down();
+                if backend.failover_state() == FailoverState::InProgress(addr.clone()) {
+                    warn!("Failover to {}:{} failed, staying master", addr.host, addr.port);
+                    backend.end_failover(false);
+                }
+                match result {
+                    Ok(()) => warn!("Connection with master {}:{} lost", addr.host, addr.port),
+                    Err(e) => warn!(
+                        "Replication with master {}:{} failed: {}",
+                        addr.host, addr.port, e
+                    ),
+                }
+                // try again, unless REPLICAOF changes the master meanwhile
+                tokio::select! {
+                    _ = time::sleep(RECONNECT_DELAY) => {}
+                    changed = master.changed() => if changed.is_err() {
+                        return;
+                    },
+                }
+            }
+            changed = master.changed() => if changed.is_err() {
+                return;
+            },
+        }
+    }
+}
+
+async fn sync_with_master(backend: &Backend, master: &MasterAddr) -> Result<()> {
+    info!("Connecting to master {}:{}", master.host, master.port);
+    let stream = connect(backend, master).await?;
+    let mut link = MasterLink {
+        stream,
+        buf: BytesMut::new(),
+    };
+    if let Some(password) = backend.masterauth() {
+        link.expect("OK", &["AUTH", &password]).await?;
+    }
+    link.expect("PONG", &["PING"]).await?;
+    let port = backend.listening_port().to_string();
+    link.expect("OK", &["REPLCONF", "listening-port", &port])
+        .await?;
+    link.expect("OK", &["REPLCONF", "capa", "eof", "capa", "psync2"])
+        .await?;
+
+    // a master failing over asks the target to take over, which it can do as they share the
+    // same history
+    let failover = backend.failover_state() == FailoverState::InProgress(master.clone());
+    let (replid, offset) = if failover {
+        let offset = backend.repl_offset().to_string();
+        let replid = link
+            .psync(&[&backend.replid(), &offset, "FAILOVER"])
+            .await?;
+        backend.end_failover(true);
+        info!("Failover to {}:{} succeeded", master.host, master.port);
+        replid
+    } else {
+        link.psync(&["?", "-1"]).await?
+    };
+    let snapshot = link.read_snapshot().await?;
+    info!(
+        "MASTER <-> REPLICA sync: received {} bytes from master",
+        snapshot.len()
+    );
+    let loader = backend.clone();
+    task::spawn_blocking(move || loader.load_master_snapshot(replid, offset, &snapshot)).await??;
+    if backend.appendonly() {
+        // the AOF describes the dataset that was just replaced
+        if let Err(e) = backend.bgrewriteaof() {
+            warn!("Can't rewrite the append only file after the sync: {}", e);
+        }
+    }
+    info!("MASTER <-> REPLICA sync: Finished with success");
+
+    let mut acks = time::interval(ACK_INTERVAL);
+    // the database the master selected in the stream
+    let mut db = backend.clone();
+    loop {
+        tokio::select! {
+            frame = link.read_frame() => {
+                if apply(&mut db, frame?).await {
+                    link.ack(backend).await?;
+                }
+            }
+            _ = acks.tick() => link.ack(backend).await?,
+        }
+    }
+}
+
+async fn connect(backend: &Backend, master: &MasterAddr) -> Result<Box<dyn MasterStream>> {
+    let stream = TcpStream::connect((master.host.as_str(), master.port)).await?;
+    let tls = backend.replication_tls();
+    if !tls.enabled {
+        return Ok(Box::new(stream));
+    }
+    secure(stream, &master.host, &tls).await
+}
+
+#[cfg(feature = "tls")]
+async fn secure(
+    stream: TcpStream,
+    host: &str,
+    tls: &ReplicationTls,
+) -> Result<Box<dyn MasterStream>> {
+    Ok(Box::new(crate::tls::connect(stream, host, tls).await?))
+}
+
+// tls-replication can't be enabled without the `tls` feature
+#[cfg(not(feature = "tls"))]
+async fn secure(
+    _stream: TcpStream,
+    _host: &str,
+    _tls: &ReplicationTls,
+) -> Result<Box<dyn MasterStream>> {
+    bail!("TLS support is not available in this build")
+}
+
+// Executes a command of the replication stream against the database the master last
+// selected, returning whether the master asks for an acknowledgement. Everything the master
+// sends counts towards the offset, including the commands that don't change the dataset.
+async fn apply(backend: &mut Backend, frame: RespFrame) -> bool {
+    let mut getack = false;
+    match Command::try_from(frame.clone()) {
+        Ok(Command::ReplConf(cmd)) => getack = cmd.is_getack(),
+        Ok(Command::Select(cmd)) => match cmd.select(backend) {
+            Ok(db) => *backend = db,
+            Err(e) => warn!("Ignoring a SELECT from the master: {}", e),
+        },
+        Ok(cmd @ Command::SwapDb(_)) => {
+            // the databases may not be seen half swapped
+            let _lock = backend.transaction_lock().await;
+            cmd.execute(backend);
+            backend.feed_aof(&frame);
+        }
+        Ok(cmd) if cmd.is_write() => {
+            // never interleaved with a transaction run on this replica
+            let _lock = backend.command_lock().await;
+            cmd.replayed().execute(backend);
+            backend.feed_aof(&frame);
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Ignoring an invalid command from the master: {}", e),
+    }
+    backend.feed_replicas(&frame);
+    getack
+}
+
+fn new_eof_mark() -> String {
+    (0..EOF_MARK_LEN)
+        .map(|_| char::from(b"0123456789abcdef"[rand::random::<usize>() % 16]))
+        .collect()
+}
+
+// The connection of a replica to its master, plain or over TLS.
+trait MasterStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> MasterStream for T {}
+
+// The connection of a replica to its master, read without a codec as the snapshot is not a
+// RESP frame.
+struct MasterLink {
+    stream: Box<dyn MasterStream>,
+    buf: BytesMut,
+}
+
+impl MasterLink {
+    async fn send(&mut self, args: &[&str]) -> Result<()> {
+        let frames: Vec<RespFrame> = args
+            .iter()
+            .map(|arg| BulkString::from(arg.to_string()).into())
+            .collect();
+        let command: RespFrame = RespArray::new(frames).into();
+        self.stream.write_all(&command.encode()).await?;
+        Ok(())
+    }
+
+    // Acknowledges the stream applied so far.
+    async fn ack(&mut self, backend: &Backend) -> Result<()> {
+        let offset = backend.repl_offset().to_string();
+        self.send(&["REPLCONF", "ACK", &offset]).await
+    }
+
+    // Sends a command of the handshake, failing unless the master replies `reply`.
+    async fn expect(&mut self, reply: &str, args: &[&str]) -> Result<()> {
+        self.send(args).await?;
+        match self.read_frame().await? {
+            RespFrame::SimpleString(s) if s.as_str() == reply => Ok(()),
+            frame => Err(anyhow!(
+                "Unexpected reply to {}: {:?}",
+                args.join(" "),
+                frame
+            )),
+        }
+    }
+
+    // Asks for a full synchronization, returning the replication id and offset it starts at.
+    async fn psync(&mut self, args: &[&str]) -> Result<(String, u64)> {
+        self.send(&[&["PSYNC"], args].concat()).await?;
+        let reply = self.read_frame().await?;
+        if let RespFrame::SimpleString(s) = &reply {
+            if let ["FULLRESYNC", replid, offset] = s.split(' ').collect::<Vec<_>>()[..] {
+                if let Ok(offset) = offset.parse() {
+                    return Ok((replid.to_string(), offset));
+                }
+            }
+        }
+        bail!("Unexpected reply to PSYNC: {:?}", reply)
+    }
+
+    // Reads the `$<length>\r\n<snapshot>` payload of a full synchronization, or the
+    // `$EOF:<mark>\r\n<snapshot><mark>` one of a diskless synchronization.
+    async fn read_snapshot(&mut self) -> Result<Vec<u8>> {
+        let header = loop {
+            // newlines keep the link alive while the master prepares the snapshot
+            while self.buf.first() == Some(&b'\n') {
+                let _ = self.buf.split_to(1);
+            }
+            if let Some(end) = self.buf.windows(2).position(|w| w == b"\r\n") {
+                let line = self.buf.split_to(end + 2);
+                break String::from_utf8_lossy(&line[..end]).into_owned();
+            }
+            self.fill().await?;
+        };
+        if let Some(mark) = header.strip_prefix("$EOF:") {
+            return self.read_until_mark(mark.as_bytes()).await;
+        }
+        let len = header
+            .strip_prefix('$')
+            .and_then(|len| len.parse::<usize>().ok())
+            .ok_or_else(|| anyhow!("Bad snapshot header from master: {:?}", header))?;
+        while self.buf.len() < len {
+            self.fill().await?;
+        }
+        Ok(self.buf.split_to(len).to_vec())
+    }
+
+    async fn read_until_mark(&mut self, mark: &[u8]) -> Result<Vec<u8>> {
+        if mark.len() != EOF_MARK_LEN {
+            bail!("Bad snapshot end mark from master");
+        }
+        // bytes already searched, which can't hold the start of the mark
+        let mut searched = 0;
+        loop {
+            if let Some(pos) = self.buf[searched..]
+                .windows(mark.len())
+                .position(|w| w == mark)
+            {
+                let snapshot = self.buf.split_to(searched + pos).to_vec();
+                let _ = self.buf.split_to(mark.len());
+                return Ok(snapshot);
+            }
+            searched = self.buf.len().saturating_sub(mark.len() - 1);
+            self.fill().await?;
+        }
+    }
+
+    async fn read_frame(&mut self) -> Result<RespFrame> {
+        loop {
+            match RespFrame::decode(&mut self.buf) {
+                Ok(frame) => return Ok(frame),
+                Err(RespError::FrameNotComplete) => self.fill().await?,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    async fn fill(&mut self) -> Result<()> {
+        if self.stream.read_buf(&mut self.buf).await? == 0 {
+            bail!("connection closed by the master");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network;
+    use std::{fs, process, time::Instant};
+    use tokio::net::TcpListener;
+
+    async fn wait_for(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() && Instant::now() < deadline {
+            time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(condition());
+    }
+
+    async fn serve(backend: Backend) -> Result<u16> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(network::stream_handler(stream, backend.clone()));
+            }
+        });
+        Ok(port)
+    }
+
+    #[tokio::test]
+    async fn test_replicate_from_master() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("simple-redis-replicate-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        let master = Backend::new();
+        master.set_dir(dir.clone());
+        master.set("before".into(), BulkString::from("1").into());
+        let port = serve(master.clone()).await?;
+
+        let replica = Backend::new();
+        replica.set_listening_port(6380);
+        replica.set_master(Some(MasterAddr {
+            host: "127.0.0.1".to_string(),
+            port,
+        }));
+        tokio::spawn(replicate(replica.clone()));
+        wait_for(|| replica.get(b"before").unwrap().is_some()).await;
+        assert!(replica.master_link_up());
+        assert_eq!(master.replica_infos()[0].port, 6380);
+
+        let mut client = TcpStream::connect(("127.0.0.1", port)).await?;
+        client
+            .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nafter\r\n$1\r\n2\r\n")
+            .await?;
+        let mut reply = [0; 5];
+        client.read_exact(&mut reply).await?;
+        assert_eq!(&reply, b"+OK\r\n");
+        wait_for(|| replica.get(b"after").unwrap().is_some()).await;
+        assert_eq!(replica.repl_offset(), master.repl_offset());
+        assert_eq!(replica.replid(), master.replid());
+
+        // the replica acknowledges the write
+        client
+            .write_all(b"*3\r\n$4\r\nWAIT\r\n$1\r\n1\r\n$4\r\n5000\r\n")
+            .await?;
+        let mut reply = [0; 4];
+        client.read_exact(&mut reply).await?;
+        assert_eq!(&reply, b":1\r\n");
+
+        // clients of the replica can only read
+        let replica_port = serve(replica.clone()).await?;
+        let mut client = TcpStream::connect(("127.0.0.1", replica_port)).await?;
+        client
+            .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nafter\r\n$1\r\n3\r\n")
+            .await?;
+        let mut buf = BytesMut::new();
+        client.read_buf(&mut buf).await?;
+        assert!(buf.starts_with(b"-READONLY "));
+        assert_eq!(
+            replica.get(b"after").unwrap(),
+            Some(BulkString::from("2").into())
+        );
+
+        replica.set_master(None);
+        wait_for(|| !master.has_replicas()).await;
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chained_replication() -> Result<()> {
+        let master = Backend::new();
+        let port = serve(master.clone()).await?;
+        let replica = Backend::new();
+        let replica_port = serve(replica.clone()).await?;
+        let sub_replica = Backend::new();
+        for (backend, port) in [(&replica, port), (&sub_replica, replica_port)] {
+            backend.set_master(Some(MasterAddr {
+                host: "127.0.0.1".to_string(),
+                port,
+            }));
+            tokio::spawn(replicate(backend.clone()));
+        }
+        wait_for(|| sub_replica.master_link_up()).await;
+        wait_for(|| master.replica_infos().iter().all(|r| r.online)).await;
+        wait_for(|| replica.replica_infos().iter().all(|r| r.online)).await;
+
+        let mut client = TcpStream::connect(("127.0.0.1", port)).await?;
+        client
+            .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$1\r\n1\r\n")
+            .await?;
+        wait_for(|| sub_replica.get(b"key").unwrap().is_some()).await;
+        assert_eq!(sub_replica.repl_offset(), master.repl_offset());
+        assert_eq!(sub_replica.replid(), master.replid());
+
+        // writes on a writable replica don't reach its replicas
+        replica.set_replica_read_only(false);
+        let mut client = TcpStream::connect(("127.0.0.1", replica_port)).await?;
+        client
+            .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nlocal\r\n$1\r\n1\r\n")
+            .await?;
+        let mut reply = [0; 5];
+        client.read_exact(&mut reply).await?;
+        assert_eq!(
+            replica.get(b"local").unwrap(),
+            Some(BulkString::from("1").into())
+        );
+        assert_eq!(replica.repl_offset(), master.repl_offset());
+        assert_eq!(sub_replica.get(b"local").unwrap(), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replicate_with_password() -> Result<()> {
+        let master = Backend::new();
+        master.set_requirepass(Some("secret".to_string()));
+        let port = serve(master.clone()).await?;
+
+        let mut client = TcpStream::connect(("127.0.0.1", port)).await?;
+        client
+            .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$1\r\n1\r\n")
+            .await?;
+        let mut buf = BytesMut::new();
+        client.read_buf(&mut buf).await?;
+        assert!(buf.starts_with(b"-NOAUTH "));
+        client
+            .write_all(
+                b"*2\r\n$4\r\nAUTH\r\n$6\r\nsecret\r\n*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$1\r\n1\r\n",
+            )
+            .await?;
+        let mut reply = [0; 10];
+        client.read_exact(&mut reply).await?;
+        assert_eq!(&reply, b"+OK\r\n+OK\r\n");
+
+        let replica = Backend::new();
+        replica.set_masterauth(Some("secret".to_string()));
+        replica.set_master(Some(MasterAddr {
+            host: "127.0.0.1".to_string(),
+            port,
+        }));
+        tokio::spawn(replicate(replica.clone()));
+        wait_for(|| replica.get(b"key").unwrap().is_some()).await;
+        assert!(replica.master_link_up());
+        replica.set_master(None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failover() -> Result<()> {
+        let master = Backend::new();
+        let port = serve(master.clone()).await?;
+        let replica = Backend::new();
+        let replica_port = serve(replica.clone()).await?;
+        replica.set_listening_port(replica_port);
+        replica.set_master(Some(MasterAddr {
+            host: "127.0.0.1".to_string(),
+            port,
+        }));
+        tokio::spawn(replicate(replica.clone()));
+        tokio::spawn(replicate(master.clone()));
+        wait_for(|| master.replica_infos().iter().any(|r| r.online)).await;
+
+        let mut client = TcpStream::connect(("127.0.0.1", port)).await?;
+        client
+            .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$1\r\n1\r\n")
+            .await?;
+        let mut reply = [0; 5];
+        client.read_exact(&mut reply).await?;
+        let old_replid = master.replid();
+        let failover = format!(
+            "*4\r\n$8\r\nFAILOVER\r\n$2\r\nTO\r\n$9\r\n127.0.0.1\r\n$5\r\n{}\r\n",
+            replica_port
+        );
+        client.write_all(failover.as_bytes()).await?;
+        client.read_exact(&mut reply).await?;
+        assert_eq!(&reply, b"+OK\r\n");
+
+        // the roles are swapped, and the acknowledged write is kept
+        wait_for(|| master.master_link_up()).await;
+        assert_eq!(master.failover_state(), FailoverState::NoFailover);
+        assert_eq!(replica.master(), None);
+        assert_eq!(
+            replica.get(b"key").unwrap(),
+            Some(BulkString::from("1").into())
+        );
+        assert_eq!(replica.replid2().0, old_replid);
+        assert_eq!(master.replid(), replica.replid());
+        assert_eq!(master.master().map(|m| m.port), Some(replica_port));
+        Ok(())
+    }
+}
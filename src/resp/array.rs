@@ -1,4 +1,4 @@
-use super::{calc_total_length, check_resp2_null, parse_length, CAPACITY, CRLF_LEN, RESP2_NULL};
+use super::{aggregate_layout, check_resp2_null, write_line, Nesting, RESP2_NULL};
 use crate::{RespDecoder, RespEncoder, RespError, RespFrame};
 use bytes::{Buf, BytesMut};
 use derive_more::{Deref, From};
@@ -6,7 +6,8 @@ use derive_more::{Deref, From};
 #[derive(Debug, Clone, Deref, PartialEq, Eq, Hash, From)]
 pub struct RespArray(pub(crate) Vec<RespFrame>);
 
-// Arrays "*<number-of-elements>\r\n<element-1>...<element-n>" decode to RespArray
+// Arrays "*<number-of-elements>\r\n<element-1>...<element-n>", or streamed as
+// "*?\r\n<element-1>...<element-n>.\r\n", decode to RespArray
 impl RespDecoder for RespArray {
     const PREFIX: &'static str = "*";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
@@ -15,43 +16,34 @@ impl RespDecoder for RespArray {
             return Ok(RespArray::new(vec![]));
         }
 
-        let (end, arr_len) = parse_length(buf, Self::PREFIX)?;
-
-        let total_len = calc_total_length(buf, end, arr_len, Self::PREFIX)?;
-        if buf.len() < total_len {
+        let _nesting = Nesting::enter()?;
+        let layout = aggregate_layout(buf, Self::PREFIX)?;
+        if buf.len() < layout.total {
             return Err(RespError::FrameNotComplete);
         }
 
-        buf.advance(end + CRLF_LEN);
-
-        let mut frames = Vec::with_capacity(arr_len);
-
-        if arr_len == 0 {
-            return Ok(RespArray::new(frames));
-        }
-
-        for _ in 0..arr_len {
+        buf.advance(layout.header);
+        let mut frames = Vec::with_capacity(layout.len);
+        for _ in 0..layout.len {
             frames.push(RespFrame::decode(buf)?);
         }
-
+        buf.advance(layout.trailer);
         Ok(RespArray::new(frames))
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        calc_total_length(buf, end, len, Self::PREFIX)
+        let _nesting = Nesting::enter()?;
+        Ok(aggregate_layout(buf, Self::PREFIX)?.total)
     }
 }
 
 // Arrays format "*<number-of-elements>\r\n<element-1>...<element-n>"
 impl RespEncoder for RespArray {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(CAPACITY);
-        buf.extend(format!("*{}\r\n", self.len()).into_bytes());
-        for frame in self.0 {
-            buf.extend(frame.encode());
+    fn encode_to(&self, buf: &mut BytesMut) {
+        write_line(buf, "*", self.len());
+        for frame in &self.0 {
+            frame.encode_to(buf);
         }
-        buf
     }
 }
 
@@ -82,6 +74,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_array_encode_to_appends() {
+        let mut buf = BytesMut::from("+OK\r\n");
+        RespArray::new(vec![
+            RespArray::new(vec![BulkString::new("a").into()]).into()
+        ])
+        .encode_to(&mut buf);
+        assert_eq!(&buf[..], b"+OK\r\n*1\r\n*1\r\n$1\r\na\r\n");
+    }
+
     #[test]
     fn test_array_decode() -> Result<()> {
         let mut buf = BytesMut::from("*2\r\n+simple\r\n:100\r\n");
@@ -114,6 +116,16 @@ mod tests {
                 BulkString::new(b"set".to_vec()).into()
             ])
         );
+
+        // a bulk string cut off inside the array
+        let mut buf = BytesMut::from("*2\r\n$6\r\nSELECT\r\n$2\r\n1");
+        let frame = RespFrame::decode(&mut buf);
+        assert_eq!(frame, Err(RespError::FrameNotComplete));
+
+        // or right after one of its elements
+        let mut buf = BytesMut::from("*2\r\n$6\r\nSELECT\r\n");
+        let frame = RespFrame::decode(&mut buf);
+        assert_eq!(frame, Err(RespError::FrameNotComplete));
         Ok(())
     }
 
@@ -124,4 +136,34 @@ mod tests {
         assert_eq!(arr, RespArray::new(vec![]));
         Ok(())
     }
+    #[test]
+    fn test_array_decode_too_many_elements() {
+        let mut buf = BytesMut::from("*2000000\r\n");
+        let res = RespArray::decode(&mut buf);
+        assert_eq!(res, Err(RespError::InvalidAggregateLength));
+
+        // nested in another frame
+        let mut buf = BytesMut::from("*1\r\n$99999999999\r\n");
+        let res = RespFrame::decode(&mut buf);
+        assert_eq!(res, Err(RespError::InvalidBulkLength));
+    }
+
+    #[test]
+    fn test_array_decode_nesting_too_deep() -> Result<()> {
+        let nested = |depth: usize| format!("{}:1\r\n", "*1\r\n".repeat(depth));
+        let mut buf = BytesMut::from(nested(100).as_str());
+        RespFrame::decode(&mut buf)?;
+        assert!(buf.is_empty());
+
+        let buf = nested(100_000);
+        let res = RespFrame::expect_length(buf.as_bytes());
+        assert_eq!(res, Err(RespError::NestingTooDeep));
+        let res = RespFrame::decode(&mut BytesMut::from(buf.as_str()));
+        assert_eq!(res, Err(RespError::NestingTooDeep));
+
+        // the depth is counted again for the next frame
+        let mut buf = BytesMut::from(nested(100).as_str());
+        RespFrame::decode(&mut buf)?;
+        Ok(())
+    }
 }
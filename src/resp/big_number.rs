@@ -0,0 +1,74 @@
+use super::{extract_simple_resp, write_line, CRLF_LEN};
+use crate::{RespDecoder, RespEncoder, RespError};
+use bytes::BytesMut;
+use derive_more::{Deref, Display, From};
+use num_bigint::BigInt;
+
+#[derive(Debug, Clone, Deref, Display, PartialEq, Eq, Hash, From)]
+pub struct RespBigNumber(pub(crate) BigInt);
+
+// Big number "([+|-]<number>\r\n" decode to RespBigNumber
+impl RespDecoder for RespBigNumber {
+    const PREFIX: &'static str = "(";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let end = extract_simple_resp(buf, Self::PREFIX)?;
+        let data = buf.split_to(end + CRLF_LEN);
+        let s = String::from_utf8_lossy(&data[Self::PREFIX.len()..end]);
+        let num: BigInt = s
+            .parse()
+            .map_err(|_| RespError::InvalidFrame(format!("invalid big number: {}", s)))?;
+        Ok(RespBigNumber::new(num))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let end = extract_simple_resp(buf, Self::PREFIX)?;
+        Ok(end + CRLF_LEN)
+    }
+}
+
+// Big number format "([+|-]<number>\r\n"
+impl RespEncoder for RespBigNumber {
+    fn encode_to(&self, buf: &mut BytesMut) {
+        write_line(buf, "(", self);
+    }
+}
+
+impl RespBigNumber {
+    pub fn new(n: impl Into<BigInt>) -> Self {
+        RespBigNumber(n.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_big_number_encode() {
+        let n = RespBigNumber::new(i64::MAX as i128 + 1);
+        assert_eq!(n.encode(), b"(9223372036854775808\r\n");
+
+        let n = RespBigNumber::new(-12);
+        assert_eq!(n.encode(), b"(-12\r\n");
+    }
+
+    #[test]
+    fn test_big_number_decode() -> Result<()> {
+        let mut buf = BytesMut::from("(3492890328409238509324850943850943825024385\r\n");
+        let frame = RespBigNumber::decode(&mut buf)?;
+        assert_eq!(
+            frame.to_string(),
+            "3492890328409238509324850943850943825024385"
+        );
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from("(-1\r\n");
+        let frame = RespBigNumber::decode(&mut buf)?;
+        assert_eq!(frame, RespBigNumber::new(-1));
+
+        let mut buf = BytesMut::from("(12a\r\n");
+        assert!(RespBigNumber::decode(&mut buf).is_err());
+        Ok(())
+    }
+}
@@ -26,8 +26,8 @@ impl RespDecoder for bool {
 
 // Boolean format "#<t|f>\r\n"
 impl RespEncoder for bool {
-    fn encode(self) -> Vec<u8> {
-        format!("#{}\r\n", if self { "t" } else { "f" }).into_bytes()
+    fn encode_to(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(if *self { b"#t\r\n" } else { b"#f\r\n" });
     }
 }
 
@@ -42,14 +42,14 @@ mod tests {
         let b = bool::decode(&mut buf)?;
         assert!(b);
 
-        let buf = bool::encode(true);
+        let buf = true.encode();
         assert_eq!(buf, b"#t\r\n");
 
         let mut buf = BytesMut::from("#f\r\n");
         let b = bool::decode(&mut buf)?;
         assert!(!b);
 
-        let buf = bool::encode(false);
+        let buf = false.encode();
         assert_eq!(buf, b"#f\r\n");
         Ok(())
     }
@@ -1,13 +1,26 @@
-use super::{check_resp2_null, parse_length, CRLF_LEN, RESP2_NULL};
+use super::{
+    check_resp2_null, is_streamed, is_strict, max_bulk_len, parse_length, write_line, CRLF_LEN,
+    RESP2_NULL, STREAMED,
+};
 use crate::{RespDecoder, RespEncoder, RespError};
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 use derive_more::{AsRef, Deref, From};
+use std::ops::Range;
+
+// the prefix of the chunks of a streamed string
+pub(super) const CHUNK_PREFIX: &str = ";";
+
+// Shorter strings are copied out of the read buffer rather than sliced from it: a slice keeps
+// the whole buffer it came in alive, which for a short value the keyspace holds on to would
+// cost many times the value itself.
+const SLICE_MIN_LEN: usize = 16 * 1024;
 
 #[derive(Debug, Clone, Deref, PartialEq, Eq, Hash, AsRef, From)]
-#[from(String, &'static str, &[u8])]
-pub struct BulkString(pub(crate) Vec<u8>);
+#[from(Bytes, Vec<u8>, String, &'static str, &'static [u8])]
+pub struct BulkString(pub(crate) Bytes);
 
-// Bulk string "$<length>\r\n<data>\r\n" decode to RespBulkString
+// Bulk string "$<length>\r\n<data>\r\n", or streamed as
+// "$?\r\n;<length>\r\n<data>\r\n...;0\r\n", decode to RespBulkString
 impl RespDecoder for BulkString {
     const PREFIX: &'static str = "$";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
@@ -15,6 +28,15 @@ impl RespDecoder for BulkString {
             buf.advance(Self::PREFIX.len() + RESP2_NULL.len());
             return Ok(BulkString::new(vec![]));
         }
+        if is_streamed(buf, Self::PREFIX) {
+            let (chunks, total) = streamed_chunks(buf)?;
+            let mut data = Vec::new();
+            for chunk in chunks {
+                data.extend_from_slice(&buf[chunk]);
+            }
+            buf.advance(total);
+            return Ok(BulkString::new(data));
+        }
 
         let (end, len) = parse_length(buf, Self::PREFIX)?;
         let act_len = buf[end + CRLF_LEN..].len();
@@ -22,42 +44,84 @@ impl RespDecoder for BulkString {
             return Err(RespError::FrameNotComplete);
         }
 
+        if is_strict() && !buf[end + CRLF_LEN + len..].starts_with(b"\r\n") {
+            return Err(RespError::TrailingData);
+        }
         buf.advance(end + CRLF_LEN);
-        let data = buf.split_to(len + CRLF_LEN);
-        Ok(BulkString::new(data[..len].to_vec()))
+        let data = if len < SLICE_MIN_LEN {
+            let data = Bytes::copy_from_slice(&buf[..len]);
+            buf.advance(len);
+            data
+        } else {
+            buf.split_to(len).freeze()
+        };
+        buf.advance(CRLF_LEN);
+        Ok(BulkString::new(data))
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
         if check_resp2_null(buf, Self::PREFIX) {
             return Ok(Self::PREFIX.len() + RESP2_NULL.len());
         }
+        if is_streamed(buf, Self::PREFIX) {
+            return Ok(streamed_chunks(buf)?.1);
+        }
 
         let (end, len) = parse_length(buf, Self::PREFIX)?;
         Ok(end + CRLF_LEN + len + CRLF_LEN)
     }
 }
 
+// Where the data of each chunk of a streamed string is, and the length of the whole frame. The
+// chunks must all be there, an empty one ending the string.
+fn streamed_chunks(buf: &[u8]) -> Result<(Vec<Range<usize>>, usize), RespError> {
+    let mut chunks = vec![];
+    let mut total = BulkString::PREFIX.len() + STREAMED.len();
+    let mut len = 0;
+    loop {
+        let (end, chunk_len) = parse_length(&buf[total..], CHUNK_PREFIX)?;
+        let start = total + end + CRLF_LEN;
+        if chunk_len == 0 {
+            return Ok((chunks, start));
+        }
+        len += chunk_len;
+        if len > max_bulk_len() {
+            return Err(RespError::InvalidBulkLength);
+        }
+        if buf.len() < start + chunk_len + CRLF_LEN {
+            return Err(RespError::FrameNotComplete);
+        }
+        if is_strict() && !buf[start + chunk_len..].starts_with(b"\r\n") {
+            return Err(RespError::TrailingData);
+        }
+        chunks.push(start..start + chunk_len);
+        total = start + chunk_len + CRLF_LEN;
+    }
+}
+
 // Bulk string format "$<length>\r\n<data>\r\n"
 impl RespEncoder for BulkString {
-    fn encode(self) -> Vec<u8> {
-        let length = self.len();
-        let mut buf: Vec<u8> = Vec::with_capacity(length + 10);
-        buf.extend(format!("${}\r\n", length).into_bytes());
-        buf.extend(self.0);
-        buf.extend(b"\r\n");
-        buf
+    fn encode_to(&self, buf: &mut BytesMut) {
+        write_line(buf, "$", self.len());
+        buf.extend_from_slice(&self.0);
+        buf.extend_from_slice(b"\r\n");
     }
 }
 
 impl BulkString {
-    pub fn new(s: impl Into<Vec<u8>>) -> Self {
+    pub fn new(s: impl Into<Bytes>) -> Self {
         BulkString(s.into())
     }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::decode_with;
     use anyhow::Result;
 
     #[test]
@@ -74,6 +138,18 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_bulk_string_decode_long_slices_buffer() -> Result<()> {
+        let data = vec![b'x'; SLICE_MIN_LEN];
+        let mut buf = BytesMut::from(&BulkString::new(data.clone()).encode()[..]);
+        let start = buf.as_ptr() as usize + format!("${}\r\n", data.len()).len();
+        let s = BulkString::decode(&mut buf)?;
+        assert_eq!(s.as_slice(), &data[..]);
+        assert_eq!(s.as_ptr() as usize, start);
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
     #[test]
     fn test_bulk_string_decode_error_not_crlf() {
         let mut buf = BytesMut::from("$5\r\nhello");
@@ -88,6 +164,39 @@ mod tests {
         assert!(res.is_err());
     }
 
+    #[test]
+    fn test_bulk_string_decode_error_too_long() {
+        // rejected before any of its data arrives
+        let mut buf = BytesMut::from("$99999999999\r\n");
+        let res = BulkString::decode(&mut buf);
+        assert_eq!(res, Err(RespError::InvalidBulkLength));
+    }
+
+    #[test]
+    fn test_bulk_string_decode_strict() {
+        let mut buf = BytesMut::from("$3\r\nhello\r\n");
+        let res = decode_with(true, || BulkString::decode(&mut buf));
+        assert_eq!(res, Err(RespError::TrailingData));
+        let res = BulkString::decode(&mut buf);
+        assert_eq!(res, Ok(BulkString::new("hel")));
+
+        let mut buf = BytesMut::from("$-2\r\n");
+        let res = decode_with(true, || BulkString::decode(&mut buf));
+        assert_eq!(res, Err(RespError::NegativeLength(-2)));
+        assert!(matches!(
+            BulkString::decode(&mut buf),
+            Err(RespError::ParseIntError(_))
+        ));
+
+        let mut buf = BytesMut::from("$-1\r\n");
+        let res = decode_with(true, || BulkString::decode(&mut buf));
+        assert_eq!(res, Ok(BulkString::new(vec![])));
+
+        let mut buf = BytesMut::from("$?\r\n;2\r\nhey\r\n;0\r\n");
+        let res = decode_with(true, || BulkString::decode(&mut buf));
+        assert_eq!(res, Err(RespError::TrailingData));
+    }
+
     #[test]
     fn test_bulk_string_expect_length() -> Result<()> {
         let buf = b"$5\r\nhello\r\n";
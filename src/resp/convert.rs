@@ -0,0 +1,227 @@
+// Conversions between frames and the Rust types they stand for, so that frames can be built
+// from plain values and taken apart without matching on each variant. A value is taken from the
+// frames RESP3 sends it as and from those RESP2 stands for it with: a boolean from an integer,
+// a double from a bulk string and a map from an array holding its keys and values in turn.
+
+use crate::{BulkString, RespArray, RespDouble, RespError, RespFrame, RespMap, RespNull};
+use std::collections::HashMap;
+
+impl From<String> for RespFrame {
+    fn from(s: String) -> Self {
+        BulkString::from(s).into()
+    }
+}
+
+impl From<Vec<u8>> for RespFrame {
+    fn from(data: Vec<u8>) -> Self {
+        BulkString::from(data).into()
+    }
+}
+
+impl From<f64> for RespFrame {
+    fn from(f: f64) -> Self {
+        RespDouble::new(f).into()
+    }
+}
+
+impl<T: Into<RespFrame>> From<Option<T>> for RespFrame {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(RespFrame::Null(RespNull), Into::into)
+    }
+}
+
+impl<T: Into<RespFrame>> From<Vec<T>> for RespFrame {
+    fn from(values: Vec<T>) -> Self {
+        RespArray::new(values.into_iter().map(Into::into).collect::<Vec<_>>()).into()
+    }
+}
+
+impl<T: Into<RespFrame>> From<HashMap<String, T>> for RespFrame {
+    fn from(map: HashMap<String, T>) -> Self {
+        let map = map.into_iter().map(|(k, v)| (k.into(), v.into()));
+        RespMap::new(map.collect::<HashMap<_, _>>()).into()
+    }
+}
+
+impl TryFrom<RespFrame> for String {
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        match frame {
+            RespFrame::SimpleString(s) => Ok(s.0),
+            RespFrame::BulkString(s) => String::from_utf8(s.0.into())
+                .map_err(|_| RespError::InvalidFrame("bulk string is not UTF-8".to_string())),
+            _ => Err(RespError::UnexpectedFrame("a string")),
+        }
+    }
+}
+
+impl TryFrom<RespFrame> for Vec<u8> {
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        match frame {
+            RespFrame::SimpleString(s) => Ok(s.0.into_bytes()),
+            RespFrame::BulkString(s) => Ok(s.0.into()),
+            _ => Err(RespError::UnexpectedFrame("a string")),
+        }
+    }
+}
+
+impl TryFrom<RespFrame> for i64 {
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        match frame {
+            RespFrame::Integer(n) => Ok(n),
+            _ => Err(RespError::UnexpectedFrame("an integer")),
+        }
+    }
+}
+
+impl TryFrom<RespFrame> for f64 {
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        match frame {
+            RespFrame::Double(d) => Ok(d.0 .0),
+            RespFrame::Integer(n) => Ok(n as f64),
+            RespFrame::BulkString(s) => Ok(String::from_utf8_lossy(&s).parse()?),
+            _ => Err(RespError::UnexpectedFrame("a double")),
+        }
+    }
+}
+
+impl TryFrom<RespFrame> for bool {
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        match frame {
+            RespFrame::Boolean(b) => Ok(b),
+            RespFrame::Integer(n @ (0 | 1)) => Ok(n == 1),
+            _ => Err(RespError::UnexpectedFrame("a boolean")),
+        }
+    }
+}
+
+impl<T> TryFrom<RespFrame> for Option<T>
+where
+    T: TryFrom<RespFrame, Error = RespError>,
+{
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        match frame {
+            RespFrame::Null(_) | RespFrame::NullArray(_) => Ok(None),
+            frame => T::try_from(frame).map(Some),
+        }
+    }
+}
+
+impl<T> TryFrom<RespFrame> for Vec<T>
+where
+    T: TryFrom<RespFrame, Error = RespError>,
+{
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        let frames: Vec<RespFrame> = match frame {
+            RespFrame::Array(frames) => frames.0,
+            RespFrame::Push(frames) => frames.0,
+            RespFrame::Set(frames) => frames.0.into_iter().collect(),
+            _ => return Err(RespError::UnexpectedFrame("an array")),
+        };
+        frames.into_iter().map(T::try_from).collect()
+    }
+}
+
+impl<T> TryFrom<RespFrame> for HashMap<String, T>
+where
+    T: TryFrom<RespFrame, Error = RespError>,
+{
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        let entries: Vec<(RespFrame, RespFrame)> = match frame {
+            RespFrame::Map(map) => map.0.into_iter().collect(),
+            RespFrame::Array(frames) if frames.len() % 2 == 0 => {
+                let mut frames = frames.0.into_iter();
+                std::iter::from_fn(|| Some((frames.next()?, frames.next()?))).collect()
+            }
+            _ => return Err(RespError::UnexpectedFrame("a map")),
+        };
+        entries
+            .into_iter()
+            .map(|(k, v)| Ok((String::try_from(k)?, T::try_from(v)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RespSet, SimpleString};
+    use anyhow::Result;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_from_values() {
+        assert_eq!(
+            RespFrame::from("foo".to_string()),
+            BulkString::from("foo").into()
+        );
+        assert_eq!(RespFrame::from(1.5), RespDouble::new(1.5).into());
+        assert_eq!(RespFrame::from(None::<i64>), RespFrame::Null(RespNull));
+        assert_eq!(
+            RespFrame::from(vec![Some(1), None]),
+            RespArray::new(vec![1.into(), RespFrame::Null(RespNull)]).into()
+        );
+        assert_eq!(
+            RespFrame::from(HashMap::from([("a".to_string(), true)])),
+            RespMap::new(HashMap::from([(BulkString::from("a").into(), true.into())])).into()
+        );
+    }
+
+    #[test]
+    fn test_try_into_values() -> Result<()> {
+        assert_eq!(
+            String::try_from(RespFrame::from(SimpleString::new("OK")))?,
+            "OK"
+        );
+        assert_eq!(
+            Vec::<u8>::try_from(RespFrame::from(b"\xff".to_vec()))?,
+            b"\xff"
+        );
+        assert_eq!(i64::try_from(RespFrame::Integer(-3))?, -3);
+        assert_eq!(
+            f64::try_from(RespFrame::from(BulkString::from("2.5")))?,
+            2.5
+        );
+        assert!(bool::try_from(RespFrame::Integer(1))?);
+        assert_eq!(Option::<i64>::try_from(RespFrame::Null(RespNull))?, None);
+
+        let set = RespFrame::from(RespSet::new(HashSet::from([RespFrame::Integer(7)])));
+        assert_eq!(Vec::<i64>::try_from(set)?, vec![7]);
+
+        // a map as RESP2 sends it
+        let map = RespFrame::from(vec![RespFrame::from("a".to_string()), 1.into()]);
+        assert_eq!(
+            HashMap::<String, i64>::try_from(map)?,
+            HashMap::from([("a".to_string(), 1)])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_into_wrong_type() {
+        assert_eq!(
+            i64::try_from(RespFrame::from("1".to_string())),
+            Err(RespError::UnexpectedFrame("an integer"))
+        );
+        assert_eq!(
+            Vec::<i64>::try_from(RespFrame::from(vec![RespFrame::Boolean(true)])),
+            Err(RespError::UnexpectedFrame("an integer"))
+        );
+        assert!(String::try_from(RespFrame::from(b"\xff".to_vec())).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use super::{extract_simple_resp, CRLF_LEN};
+use super::{extract_simple_resp, write_line, CRLF_LEN};
 use crate::{RespDecoder, RespEncoder, RespError};
 use bytes::BytesMut;
 use derive_more::{Deref, Display, From};
@@ -13,8 +13,7 @@ impl RespDecoder for RespDouble {
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
         let end = extract_simple_resp(buf, Self::PREFIX)?;
         let data = buf.split_to(end + 2);
-        let s = String::from_utf8_lossy(&data[Self::PREFIX.len()..end]);
-        let num = s.parse()?;
+        let num = parse_double(&data[Self::PREFIX.len()..end])?;
         Ok(RespDouble::new(num))
     }
 
@@ -26,18 +25,28 @@ impl RespDecoder for RespDouble {
 
 // Double format ",[<+|->]<integral>[.<fractional>][<E|e>[sign]<exponent>]\r\n"
 impl RespEncoder for RespDouble {
-    fn encode(self) -> Vec<u8> {
+    fn encode_to(&self, buf: &mut BytesMut) {
         if self.is_nan() {
-            return b",nan\r\n".to_vec();
-        }
-        if self.is_infinite() {
-            return if self.is_sign_negative() {
-                b",-inf\r\n".to_vec()
+            buf.extend_from_slice(b",nan\r\n");
+        } else if self.is_infinite() {
+            buf.extend_from_slice(if self.is_sign_negative() {
+                b",-inf\r\n"
             } else {
-                b",inf\r\n".to_vec()
-            };
+                b",inf\r\n"
+            });
+        } else {
+            write_line(buf, ",", self);
         }
-        format!(",{}\r\n", self).into_bytes()
+    }
+}
+
+// RESP3 spells out infinities and NaN rather than writing them as numbers
+fn parse_double(s: &[u8]) -> Result<f64, RespError> {
+    match s {
+        b"inf" | b"+inf" => Ok(f64::INFINITY),
+        b"-inf" => Ok(f64::NEG_INFINITY),
+        b"nan" | b"-nan" => Ok(f64::NAN),
+        _ => Ok(String::from_utf8_lossy(s).parse()?),
     }
 }
 
@@ -84,4 +93,25 @@ mod tests {
         assert_eq!(frame, RespDouble::new(0.0123));
         Ok(())
     }
+
+    #[test]
+    fn test_double_decode_special_values() -> Result<()> {
+        for (encoded, num) in [
+            (",inf\r\n", f64::INFINITY),
+            (",+inf\r\n", f64::INFINITY),
+            (",-inf\r\n", f64::NEG_INFINITY),
+        ] {
+            let frame = RespDouble::decode(&mut BytesMut::from(encoded))?;
+            assert_eq!(frame, RespDouble::new(num));
+        }
+        let frame = RespDouble::decode(&mut BytesMut::from(",nan\r\n"))?;
+        assert!(frame.is_nan());
+
+        for num in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+            let encoded = RespDouble::new(num).encode();
+            let frame = RespDouble::decode(&mut BytesMut::from(&encoded[..]))?;
+            assert_eq!(frame, RespDouble::new(num));
+        }
+        Ok(())
+    }
 }
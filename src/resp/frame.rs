@@ -1,12 +1,13 @@
+use super::write_line;
 use crate::{
-    BulkString, RespArray, RespDecoder, RespDouble, RespError, RespMap, RespNull, RespSet,
-    SimpleError, SimpleString,
+    BulkString, RespArray, RespBigNumber, RespDecoder, RespDouble, RespEncoder, RespError, RespMap,
+    RespNull, RespNullArray, RespPush, RespSet, SimpleError, SimpleString,
 };
 use bytes::BytesMut;
-use enum_dispatch::enum_dispatch;
+use derive_more::From;
+use num_bigint::BigInt;
 
-#[enum_dispatch(RespEncoder)]
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, From)]
 pub enum RespFrame {
     SimpleString(SimpleString),
     SimpleError(SimpleError),
@@ -14,10 +15,89 @@ pub enum RespFrame {
     BulkString(BulkString),
     Array(RespArray),
     Null(RespNull),
+    NullArray(RespNullArray),
     Boolean(bool),
     Double(RespDouble),
     Map(RespMap),
     Set(RespSet),
+    Push(RespPush),
+    BigNumber(RespBigNumber),
+}
+
+impl RespFrame {
+    /// An integer reply, a big number one when the value doesn't fit in 64 bits rather than
+    /// overflowing.
+    pub fn integer(n: impl Into<BigInt>) -> Self {
+        let n = n.into();
+        match i64::try_from(&n) {
+            Ok(n) => RespFrame::Integer(n),
+            Err(_) => RespBigNumber::new(n).into(),
+        }
+    }
+}
+
+impl RespEncoder for RespFrame {
+    fn encode_to(&self, buf: &mut BytesMut) {
+        match self {
+            RespFrame::SimpleString(s) => s.encode_to(buf),
+            RespFrame::SimpleError(e) => e.encode_to(buf),
+            RespFrame::Integer(n) => n.encode_to(buf),
+            RespFrame::BulkString(s) => s.encode_to(buf),
+            RespFrame::Array(frames) => frames.encode_to(buf),
+            RespFrame::Null(null) => null.encode_to(buf),
+            RespFrame::NullArray(null) => null.encode_to(buf),
+            RespFrame::Boolean(b) => b.encode_to(buf),
+            RespFrame::Double(d) => d.encode_to(buf),
+            RespFrame::Map(map) => map.encode_to(buf),
+            RespFrame::Set(frames) => frames.encode_to(buf),
+            RespFrame::Push(frames) => frames.encode_to(buf),
+            RespFrame::BigNumber(n) => n.encode_to(buf),
+        }
+    }
+}
+
+// RESP2 has none of the types RESP3 added, so the frames of those types are sent as the RESP2
+// frames standing for them: nulls as null bulk strings, or arrays, booleans as integers,
+// doubles and big numbers as bulk strings, and maps, sets and pushes as arrays, those of a map
+// holding its keys and values in turn.
+impl RespFrame {
+    /// Writes the frame at the end of `buf` for a connection speaking the RESP version
+    /// `protocol`.
+    pub fn encode_for(&self, protocol: u8, buf: &mut BytesMut) {
+        if protocol >= 3 {
+            return self.encode_to(buf);
+        }
+        match self {
+            RespFrame::Null(_) => buf.extend_from_slice(b"$-1\r\n"),
+            RespFrame::NullArray(_) => buf.extend_from_slice(b"*-1\r\n"),
+            RespFrame::Boolean(b) => RespFrame::Integer(*b as i64).encode_to(buf),
+            RespFrame::Double(d) => {
+                // the same digits as RESP3, without the prefix and the CRLF
+                let encoded = d.encode();
+                BulkString::new(encoded[1..encoded.len() - 2].to_vec()).encode_to(buf)
+            }
+            RespFrame::BigNumber(n) => BulkString::new(n.to_string()).encode_to(buf),
+            RespFrame::Array(frames) => encode_resp2_array(frames.len(), frames.iter(), buf),
+            RespFrame::Set(frames) => encode_resp2_array(frames.len(), frames.iter(), buf),
+            RespFrame::Push(frames) => encode_resp2_array(frames.len(), frames.iter(), buf),
+            RespFrame::Map(map) => {
+                let len = map.len() * 2;
+                encode_resp2_array(len, map.iter().flat_map(|(k, v)| [k, v]), buf)
+            }
+            frame => frame.encode_to(buf),
+        }
+    }
+}
+
+fn encode_resp2_array<'a>(
+    len: usize,
+    frames: impl IntoIterator<Item = &'a RespFrame>,
+    buf: &mut BytesMut,
+) {
+    write_line(buf, "*", len);
+    for frame in frames {
+        frame.encode_for(2, buf);
+    }
 }
 
 impl RespDecoder for RespFrame {
@@ -65,6 +145,14 @@ impl RespDecoder for RespFrame {
                 let frame = RespSet::decode(buf)?;
                 Ok(frame.into())
             }
+            Some(b'>') => {
+                let frame = RespPush::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'(') => {
+                let frame = RespBigNumber::decode(buf)?;
+                Ok(frame.into())
+            }
             None => Err(RespError::FrameNotComplete),
             _ => Err(RespError::InvalidFrame(format!("data: {:?}", buf))),
         }
@@ -83,6 +171,9 @@ impl RespDecoder for RespFrame {
             Some(b',') => RespDouble::expect_length(buf),
             Some(b'%') => RespMap::expect_length(buf),
             Some(b'~') => RespSet::expect_length(buf),
+            Some(b'>') => RespPush::expect_length(buf),
+            Some(b'(') => RespBigNumber::expect_length(buf),
+            None => Err(RespError::FrameNotComplete),
             _ => Err(RespError::InvalidFrame(format!("data: {:?}", buf))),
         }
     }
@@ -163,6 +254,67 @@ mod tests {
             ])))
         );
 
+        let mut buf = BytesMut::from("*2\r\n(18446744073709551616\r\n:1\r\n");
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            RespFrame::Array(RespArray::from(vec![
+                RespFrame::BigNumber(RespBigNumber::new(1u128 << 64)),
+                RespFrame::Integer(1)
+            ]))
+        );
+
         Ok(())
     }
+
+    #[test]
+    fn test_resp_frame_integer() {
+        assert_eq!(RespFrame::integer(42u64), RespFrame::Integer(42));
+        assert_eq!(RespFrame::integer(i64::MIN), RespFrame::Integer(i64::MIN));
+        assert_eq!(
+            RespFrame::integer(u64::MAX),
+            RespFrame::BigNumber(RespBigNumber::new(u64::MAX))
+        );
+    }
+
+    fn encode_for(frame: &RespFrame, protocol: u8) -> Vec<u8> {
+        let mut buf = BytesMut::new();
+        frame.encode_for(protocol, &mut buf);
+        buf.into()
+    }
+
+    #[test]
+    fn test_resp_frame_encode_for() {
+        assert_eq!(encode_for(&RespFrame::Null(RespNull), 2), b"$-1\r\n");
+        assert_eq!(encode_for(&RespFrame::Null(RespNull), 3), b"_\r\n");
+        assert_eq!(
+            encode_for(&RespFrame::NullArray(RespNullArray), 2),
+            b"*-1\r\n"
+        );
+        assert_eq!(
+            encode_for(&RespFrame::NullArray(RespNullArray), 3),
+            b"_\r\n"
+        );
+        assert_eq!(encode_for(&RespFrame::Boolean(true), 2), b":1\r\n");
+        assert_eq!(encode_for(&RespFrame::Boolean(true), 3), b"#t\r\n");
+        assert_eq!(
+            encode_for(&RespFrame::Double(RespDouble::new(1.5)), 2),
+            b"$3\r\n1.5\r\n"
+        );
+        assert_eq!(
+            encode_for(&RespFrame::BigNumber(RespBigNumber::new(1u128 << 64)), 2),
+            b"$20\r\n18446744073709551616\r\n"
+        );
+
+        let map: RespFrame = RespMap::from(HashMap::from_iter([(
+            SimpleString::from("foo").into(),
+            RespFrame::Boolean(false),
+        )]))
+        .into();
+        assert_eq!(encode_for(&map, 2), b"*2\r\n+foo\r\n:0\r\n");
+        assert_eq!(encode_for(&map, 3), b"%1\r\n+foo\r\n#f\r\n");
+
+        let nested: RespFrame = RespArray::new([RespFrame::Null(RespNull)]).into();
+        assert_eq!(encode_for(&nested, 2), b"*1\r\n$-1\r\n");
+    }
 }
@@ -1,4 +1,4 @@
-use super::{extract_simple_resp, CRLF_LEN};
+use super::{extract_simple_resp, parse_int, write_line, CRLF_LEN};
 use crate::{RespDecoder, RespEncoder, RespError};
 use bytes::BytesMut;
 
@@ -8,9 +8,7 @@ impl RespDecoder for i64 {
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
         let end = extract_simple_resp(buf, Self::PREFIX)?;
         let data = buf.split_to(end + 2);
-        let s = String::from_utf8_lossy(&data[Self::PREFIX.len()..end]);
-        let num = s.parse::<i64>()?;
-        Ok(num)
+        parse_int(&data[Self::PREFIX.len()..end])
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
@@ -21,8 +19,8 @@ impl RespDecoder for i64 {
 
 // integer format ":[<+|->]<value>\r\n"
 impl RespEncoder for i64 {
-    fn encode(self) -> Vec<u8> {
-        format!(":{}\r\n", self).into_bytes()
+    fn encode_to(&self, buf: &mut BytesMut) {
+        write_line(buf, ":", self);
     }
 }
 
@@ -37,15 +35,35 @@ mod tests {
         let num = i64::decode(&mut buf)?;
         assert_eq!(num, 123);
 
-        let buf = i64::encode(123);
+        let buf = 123i64.encode();
         assert_eq!(buf, b":123\r\n");
 
         let mut buf = BytesMut::from(":-123\r\n");
         let num = i64::decode(&mut buf)?;
         assert_eq!(num, -123);
 
-        let buf = i64::encode(-123);
+        let buf = (-123i64).encode();
         assert_eq!(buf, b":-123\r\n");
         Ok(())
     }
+
+    #[test]
+    fn test_integer_decode_edges() -> Result<()> {
+        for num in [0, i64::MAX, i64::MIN, 999_999_999_999_999_999] {
+            let mut buf = BytesMut::from(&num.encode()[..]);
+            assert_eq!(i64::decode(&mut buf)?, num);
+        }
+        let mut buf = BytesMut::from(":+7\r\n");
+        assert_eq!(i64::decode(&mut buf)?, 7);
+
+        for frame in [":\r\n", ":-\r\n", ":12a\r\n", ":9223372036854775808\r\n"] {
+            let res = i64::decode(&mut BytesMut::from(frame));
+            assert!(
+                matches!(res, Err(RespError::ParseIntError(_))),
+                "{:?}",
+                frame
+            );
+        }
+        Ok(())
+    }
 }
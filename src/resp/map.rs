@@ -1,52 +1,51 @@
-use super::{calc_total_length, parse_length, CAPACITY, CRLF_LEN};
+use super::{aggregate_layout, write_line, Nesting};
 use crate::{RespDecoder, RespEncoder, RespError, RespFrame};
 use bytes::{Buf, BytesMut};
 use derive_more::{Deref, From};
-use std::{collections::HashMap, hash::Hash};
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+};
 
 #[derive(Debug, Clone, Deref, PartialEq, Eq, From)]
 pub struct RespMap(pub(crate) HashMap<RespFrame, RespFrame>);
 
-// Map "%<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>" decode to RespMap
+// Map "%<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>", or streamed as
+// "%?\r\n<key-1><value-1>...<key-n><value-n>.\r\n", decode to RespMap
 impl RespDecoder for RespMap {
     const PREFIX: &'static str = "%";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-
-        let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
-        if buf.len() < total_len {
+        let _nesting = Nesting::enter()?;
+        let layout = aggregate_layout(buf, Self::PREFIX)?;
+        if buf.len() < layout.total {
             return Err(RespError::FrameNotComplete);
         }
 
-        buf.advance(end + CRLF_LEN);
-        let mut map = HashMap::with_capacity(len);
-        if len == 0 {
-            return Ok(RespMap::new(map));
-        }
-        for _ in 0..len {
+        buf.advance(layout.header);
+        let mut map = HashMap::with_capacity(layout.len);
+        for _ in 0..layout.len {
             let key = RespFrame::decode(buf)?;
             let value = RespFrame::decode(buf)?;
             map.insert(key, value);
         }
+        buf.advance(layout.trailer);
         Ok(RespMap::new(map))
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        calc_total_length(buf, end, len, Self::PREFIX)
+        let _nesting = Nesting::enter()?;
+        Ok(aggregate_layout(buf, Self::PREFIX)?.total)
     }
 }
 
 // Map format "%<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>"
 impl RespEncoder for RespMap {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(CAPACITY);
-        buf.extend(format!("%{}\r\n", self.len()).into_bytes());
-        for (key, value) in self.0 {
-            buf.extend(key.encode());
-            buf.extend(value.encode());
+    fn encode_to(&self, buf: &mut BytesMut) {
+        write_line(buf, "%", self.len());
+        for (key, value) in &self.0 {
+            key.encode_to(buf);
+            value.encode_to(buf);
         }
-        buf
     }
 }
 
@@ -56,12 +55,16 @@ impl RespMap {
     }
 }
 
+// Equal maps may iterate their entries in different orders, so the hashes of the entries are
+// combined in a way that doesn't depend on it.
 impl Hash for RespMap {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.iter().for_each(|(k, v)| {
-            k.hash(state);
-            v.hash(state);
-        });
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        state.write_u64(self.iter().fold(0, |sum, entry| {
+            let mut hasher = DefaultHasher::new();
+            entry.hash(&mut hasher);
+            sum.wrapping_add(hasher.finish())
+        }));
     }
 }
 
@@ -1,31 +1,87 @@
+#[cfg(test)]
+mod arbitrary;
 mod array;
+mod big_number;
 mod bool;
 mod bulk_string;
+mod convert;
 mod double;
 mod frame;
 mod integer;
 mod map;
 mod null;
+mod parser;
+mod pretty;
+mod push;
 mod set;
 mod simple_error;
 mod simple_string;
+mod streamed;
 
 use bytes::BytesMut;
-use enum_dispatch::enum_dispatch;
+use memchr::{memchr, memmem};
+use std::{
+    cell::Cell,
+    fmt::{Display, Write},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
 use thiserror::Error;
 
 pub use self::{
-    array::RespArray, bulk_string::BulkString, double::RespDouble, frame::RespFrame, map::RespMap,
-    null::RespNull, set::RespSet, simple_error::SimpleError, simple_string::SimpleString,
+    array::RespArray,
+    big_number::RespBigNumber,
+    bulk_string::BulkString,
+    double::RespDouble,
+    frame::RespFrame,
+    map::RespMap,
+    null::{RespNull, RespNullArray},
+    parser::FrameParser,
+    push::RespPush,
+    set::RespSet,
+    simple_error::SimpleError,
+    simple_string::SimpleString,
+    streamed::{StreamedAggregate, StreamedString},
 };
 
-const CAPACITY: usize = 4096;
 const RESP2_NULL: &str = "-1\r\n";
 const CRLF_LEN: usize = b"\r\n".len();
+// what follows the prefix of a streamed string or aggregate instead of its length
+const STREAMED: &str = "?\r\n";
+// what ends a streamed aggregate
+const STREAMED_END: &str = ".\r\n";
+
+// The longest bulk string and the most elements of an aggregate a frame may announce. Decoding a
+// frame announcing more fails right away rather than buffering whatever size it claims.
+static MAX_BULK_LEN: AtomicUsize = AtomicUsize::new(512 * 1024 * 1024);
+static MAX_AGGREGATE_LEN: AtomicUsize = AtomicUsize::new(1024 * 1024);
+// How deep aggregates may nest in a frame, since decoding recurses into them.
+static MAX_NESTING_DEPTH: AtomicUsize = AtomicUsize::new(128);
+// Whether the frames clients send are decoded strictly.
+static STRICT_PARSING: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    // the aggregates the frame decoded on this thread is in
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+    // whether the frame decoded on this thread is held to the strict rules
+    static STRICT: Cell<bool> = const { Cell::new(false) };
+}
 
-#[enum_dispatch]
 pub trait RespEncoder {
-    fn encode(self) -> Vec<u8>;
+    /// Writes the frame at the end of `buf`, the elements of an aggregate straight after its
+    /// header.
+    fn encode_to(&self, buf: &mut BytesMut);
+
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = BytesMut::new();
+        self.encode_to(&mut buf);
+        buf.into()
+    }
+}
+
+// Writes "<prefix><value>\r\n", the first line of most frames.
+fn write_line(buf: &mut BytesMut, prefix: &str, value: impl Display) {
+    // writing to a BytesMut never fails, it grows as needed
+    let _ = write!(buf, "{}{}\r\n", prefix, value);
 }
 
 pub trait RespDecoder: Sized {
@@ -48,6 +104,103 @@ pub enum RespError {
 
     #[error("Invalid float: {0}")]
     ParseFloatError(#[from] std::num::ParseFloatError),
+
+    #[error("invalid bulk length")]
+    InvalidBulkLength,
+
+    #[error("invalid multibulk length")]
+    InvalidAggregateLength,
+
+    #[error("too deeply nested aggregates")]
+    NestingTooDeep,
+
+    #[error("line ended by a bare LF")]
+    BareLineFeed,
+
+    #[error("CR inside a line")]
+    CarriageReturnInLine,
+
+    #[error("invalid negative length: {0}")]
+    NegativeLength(i64),
+
+    #[error("trailing data after the bulk string payload")]
+    TrailingData,
+
+    #[error("unexpected frame, expected {0}")]
+    UnexpectedFrame(&'static str),
+}
+
+/// The longest bulk string a frame may hold, as `proto-max-bulk-len` sets it.
+pub fn max_bulk_len() -> usize {
+    MAX_BULK_LEN.load(Ordering::Relaxed)
+}
+
+pub fn set_max_bulk_len(len: usize) {
+    MAX_BULK_LEN.store(len, Ordering::Relaxed);
+}
+
+/// The most elements an array, set or push frame may hold, or pairs a map frame, as
+/// `proto-max-multibulk-len` sets it.
+pub fn max_aggregate_len() -> usize {
+    MAX_AGGREGATE_LEN.load(Ordering::Relaxed)
+}
+
+pub fn set_max_aggregate_len(len: usize) {
+    MAX_AGGREGATE_LEN.store(len, Ordering::Relaxed);
+}
+
+/// How deep aggregates may nest in a frame, as `proto-max-nesting-depth` sets it.
+pub fn max_nesting_depth() -> usize {
+    MAX_NESTING_DEPTH.load(Ordering::Relaxed)
+}
+
+pub fn set_max_nesting_depth(depth: usize) {
+    MAX_NESTING_DEPTH.store(depth, Ordering::Relaxed);
+}
+
+/// Whether the frames clients send are decoded strictly, as `proto-strict-parsing` sets it.
+pub fn strict_parsing() -> bool {
+    STRICT_PARSING.load(Ordering::Relaxed)
+}
+
+pub fn set_strict_parsing(strict: bool) {
+    STRICT_PARSING.store(strict, Ordering::Relaxed);
+}
+
+/// Runs `decode`, the frames it decodes on this thread held to the strict rules or not. A
+/// strict decoder rejects lines ended by a bare LF, CRs inside lines, negative lengths other
+/// than the -1 of a null and anything but CRLF after the data of a bulk string, which the
+/// lenient one lets through or reports as some other error.
+pub fn decode_with<T>(strict: bool, decode: impl FnOnce() -> T) -> T {
+    let outer = STRICT.replace(strict);
+    let result = decode();
+    STRICT.set(outer);
+    result
+}
+
+fn is_strict() -> bool {
+    STRICT.get()
+}
+
+// Held while decoding an aggregate, or taking its length, counting how deep it is. Entering one
+// past the nesting depth limit fails.
+struct Nesting;
+
+impl Nesting {
+    fn enter() -> Result<Self, RespError> {
+        let depth = DEPTH.get() + 1;
+        if depth > max_nesting_depth() {
+            return Err(RespError::NestingTooDeep);
+        }
+        DEPTH.set(depth);
+        Ok(Nesting)
+    }
+}
+
+impl Drop for Nesting {
+    fn drop(&mut self) {
+        DEPTH.set(DEPTH.get() - 1);
+    }
 }
 
 fn extract_simple_resp(buf: &[u8], prefix: &str) -> Result<usize, RespError> {
@@ -61,29 +214,79 @@ fn extract_simple_resp(buf: &[u8], prefix: &str) -> Result<usize, RespError> {
             prefix, buf
         )));
     }
-    let end = find_crlf(buf, 1).ok_or(RespError::FrameNotComplete)?;
+    if is_strict() {
+        return extract_line_strictly(buf, prefix);
+    }
+    let end = find_crlf(buf).ok_or(RespError::FrameNotComplete)?;
     Ok(end)
 }
 
-// find nth CRLF in the buffer
-fn find_crlf(buf: &[u8], nth: usize) -> Option<usize> {
-    let mut count = 0;
-    for i in 1..buf.len() - 1 {
-        if buf[i] == b'\r' && buf[i + 1] == b'\n' {
-            count += 1;
-            if count == nth {
-                return Some(i);
-            }
-        }
+// The end of the line after `prefix`, which must end with CRLF and hold no other CR or LF.
+fn extract_line_strictly(buf: &[u8], prefix: &str) -> Result<usize, RespError> {
+    let lf = memchr(b'\n', &buf[prefix.len()..]).ok_or(RespError::FrameNotComplete)? + prefix.len();
+    if buf[lf - 1] != b'\r' {
+        return Err(RespError::BareLineFeed);
+    }
+    let end = lf - 1;
+    if memchr(b'\r', &buf[prefix.len()..end]).is_some() {
+        return Err(RespError::CarriageReturnInLine);
     }
+    Ok(end)
+}
+
+// find the first CRLF in the buffer after its first byte
+fn find_crlf(buf: &[u8]) -> Option<usize> {
+    memmem::find(&buf[1..], b"\r\n").map(|i| i + 1)
+}
+
+// The value of `digits` if it holds nothing but decimal digits, and few enough of them that
+// they can't overflow.
+fn parse_digits(digits: &[u8]) -> Option<u64> {
+    if digits.is_empty() || digits.len() > 18 {
+        return None;
+    }
+    digits.iter().try_fold(0u64, |n, &b| {
+        b.is_ascii_digit().then(|| n * 10 + u64::from(b - b'0'))
+    })
+}
 
-    None
+// Parses the integer in `line` straight from its bytes, leaving what that can't handle, signs
+// other than a minus, long numbers or invalid ones, to `str::parse`.
+fn parse_int(line: &[u8]) -> Result<i64, RespError> {
+    let num = match line {
+        [b'-', digits @ ..] => parse_digits(digits).map(|n| -(n as i64)),
+        digits => parse_digits(digits).map(|n| n as i64),
+    };
+    match num {
+        Some(num) => Ok(num),
+        None => Ok(String::from_utf8_lossy(line).parse()?),
+    }
 }
 
 fn parse_length(buf: &[u8], prefix: &str) -> Result<(usize, usize), RespError> {
     let end = extract_simple_resp(buf, prefix)?;
-    let len = String::from_utf8_lossy(&buf[prefix.len()..end]).parse()?;
-    Ok((end, len))
+    let line = &buf[prefix.len()..end];
+    let len = match parse_digits(line).and_then(|len| usize::try_from(len).ok()) {
+        Some(len) => len,
+        None => {
+            let line = String::from_utf8_lossy(line);
+            if is_strict() && line.starts_with('-') {
+                // the null of RESP2 is told apart before the length is parsed
+                let len: i64 = line.parse()?;
+                if len < 0 {
+                    return Err(RespError::NegativeLength(len));
+                }
+            }
+            line.parse()?
+        }
+    };
+    match prefix {
+        "$" if len > max_bulk_len() => Err(RespError::InvalidBulkLength),
+        "*" | "%" | "~" | ">" if len > max_aggregate_len() => {
+            Err(RespError::InvalidAggregateLength)
+        }
+        _ => Ok((end, len)),
+    }
 }
 
 // compatible with RESP2 null
@@ -91,14 +294,65 @@ fn check_resp2_null(buf: &[u8], prefix: &str) -> bool {
     buf.starts_with(format!("{}{}", prefix, RESP2_NULL).as_bytes())
 }
 
+// Whether the frame in `buf` is the streamed form of the type with `prefix`.
+fn is_streamed(buf: &[u8], prefix: &str) -> bool {
+    buf.starts_with(prefix.as_bytes()) && buf[prefix.len()..].starts_with(STREAMED.as_bytes())
+}
+
+// Where the elements of an aggregate frame are: after `header` bytes, `len` of them, or pairs
+// of them for a map, followed by `trailer` bytes, the end marker of a streamed aggregate.
+struct AggregateLayout {
+    header: usize,
+    len: usize,
+    trailer: usize,
+    total: usize,
+}
+
+fn aggregate_layout(buf: &[u8], prefix: &str) -> Result<AggregateLayout, RespError> {
+    if !is_streamed(buf, prefix) {
+        let (end, len) = parse_length(buf, prefix)?;
+        return Ok(AggregateLayout {
+            header: end + CRLF_LEN,
+            len,
+            trailer: 0,
+            total: calc_total_length(buf, end, len, prefix)?,
+        });
+    }
+    let header = prefix.len() + STREAMED.len();
+    let frames = if prefix == "%" { 2 } else { 1 };
+    let mut total = header;
+    let mut len = 0;
+    loop {
+        let data = &buf[total..];
+        if data.starts_with(STREAMED_END.as_bytes()) {
+            return Ok(AggregateLayout {
+                header,
+                len,
+                trailer: STREAMED_END.len(),
+                total: total + STREAMED_END.len(),
+            });
+        }
+        if STREAMED_END.as_bytes().starts_with(data) {
+            return Err(RespError::FrameNotComplete);
+        }
+        if len == max_aggregate_len() {
+            return Err(RespError::InvalidAggregateLength);
+        }
+        for _ in 0..frames {
+            total += RespFrame::expect_length(&buf[total..])?;
+        }
+        len += 1;
+    }
+}
+
 fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result<usize, RespError> {
     let mut total = end + CRLF_LEN;
     let mut data = &buf[total..];
     match prefix {
-        "*" | "~" => {
+        "*" | "~" | ">" => {
             for _ in 0..len {
                 let len = RespFrame::expect_length(data)?;
-                data = &data[len..];
+                data = data.get(len..).ok_or(RespError::FrameNotComplete)?;
                 total += len;
             }
             Ok(total)
@@ -106,10 +360,10 @@ fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result
         "%" => {
             for _ in 0..len {
                 let key_len = RespFrame::expect_length(data)?;
-                data = &data[key_len..];
+                data = data.get(key_len..).ok_or(RespError::FrameNotComplete)?;
 
                 let value_len = RespFrame::expect_length(data)?;
-                data = &data[value_len..];
+                data = data.get(value_len..).ok_or(RespError::FrameNotComplete)?;
 
                 total += key_len + value_len;
             }
@@ -5,6 +5,10 @@ use bytes::{Buf, BytesMut};
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct RespNull;
 
+/// The null standing for a missing array, which only RESP2 tells apart from other nulls.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RespNullArray;
+
 // Null "_\r\n" decode to RespNull
 impl RespDecoder for RespNull {
     const PREFIX: &'static str = "_";
@@ -21,8 +25,14 @@ impl RespDecoder for RespNull {
 
 // Null format "_\r\n"
 impl RespEncoder for RespNull {
-    fn encode(self) -> Vec<u8> {
-        b"_\r\n".to_vec()
+    fn encode_to(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(b"_\r\n");
+    }
+}
+
+impl RespEncoder for RespNullArray {
+    fn encode_to(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(b"_\r\n");
     }
 }
 
@@ -0,0 +1,263 @@
+// Decoding frames from a buffer filled as the bytes of a stream come in, looking at each byte
+// once: the elements of an aggregate are decoded, and taken off the buffer, as soon as each of
+// them is there, rather than the whole aggregate being measured first and decoded again from
+// its start on every read until all of it has arrived.
+
+use super::{
+    check_resp2_null, is_streamed, max_aggregate_len, max_nesting_depth, parse_length, CRLF_LEN,
+    STREAMED, STREAMED_END,
+};
+use crate::{RespArray, RespDecoder, RespError, RespFrame, RespMap, RespPush, RespSet};
+use bytes::{Buf, BytesMut};
+use std::collections::{HashMap, HashSet};
+
+/// Decodes the frames of a stream one after the other, keeping the elements it already decoded
+/// of the aggregates not complete yet.
+#[derive(Debug, Default)]
+pub struct FrameParser {
+    // the aggregates the next element is in, innermost last
+    open: Vec<OpenAggregate>,
+}
+
+#[derive(Debug)]
+struct OpenAggregate {
+    prefix: &'static str,
+    // the elements still to come, the keys and the values of a map counting apart, or none for
+    // a streamed aggregate, which ".\r\n" ends
+    remaining: Option<usize>,
+    frames: Vec<RespFrame>,
+}
+
+impl FrameParser {
+    /// Decodes the next frame from `buf`, taking off it the bytes of what was decoded even when
+    /// the frame isn't complete yet, which is then reported as `FrameNotComplete`. After any
+    /// other error the frame is dropped, the rest of the stream unframed.
+    pub fn parse(&mut self, buf: &mut BytesMut) -> Result<RespFrame, RespError> {
+        let res = self.parse_frame(buf);
+        if matches!(res, Err(ref e) if *e != RespError::FrameNotComplete) {
+            self.open.clear();
+        }
+        res
+    }
+
+    fn parse_frame(&mut self, buf: &mut BytesMut) -> Result<RespFrame, RespError> {
+        loop {
+            let Some(mut frame) = self.parse_element(buf)? else {
+                continue;
+            };
+            // a complete element may complete the aggregates it is the last element of
+            loop {
+                let Some(open) = self.open.last_mut() else {
+                    return Ok(frame);
+                };
+                open.frames.push(frame);
+                match &mut open.remaining {
+                    Some(remaining) => {
+                        *remaining -= 1;
+                        if *remaining > 0 {
+                            break;
+                        }
+                    }
+                    None => {
+                        if open.len() > max_aggregate_len() {
+                            return Err(RespError::InvalidAggregateLength);
+                        }
+                        break;
+                    }
+                }
+                frame = self.open.pop().expect("an open aggregate").finish();
+            }
+        }
+    }
+
+    // Decodes the next element, a whole frame unless it opens an aggregate, which then has no
+    // elements yet.
+    fn parse_element(&mut self, buf: &mut BytesMut) -> Result<Option<RespFrame>, RespError> {
+        if let Some(open) = self.open.last() {
+            if open.remaining.is_none() && buf.starts_with(b".") {
+                return self.end_streamed(buf).map(Some);
+            }
+        }
+        let prefix = match buf.first() {
+            Some(b'*') if !check_resp2_null(buf, RespArray::PREFIX) => RespArray::PREFIX,
+            Some(b'%') => RespMap::PREFIX,
+            Some(b'~') => RespSet::PREFIX,
+            Some(b'>') => RespPush::PREFIX,
+            _ => return RespFrame::decode(buf).map(Some),
+        };
+        if self.open.len() >= max_nesting_depth() {
+            return Err(RespError::NestingTooDeep);
+        }
+        let remaining = if is_streamed(buf, prefix) {
+            buf.advance(prefix.len() + STREAMED.len());
+            None
+        } else {
+            let (end, len) = parse_length(buf, prefix)?;
+            buf.advance(end + CRLF_LEN);
+            Some(if prefix == RespMap::PREFIX {
+                len * 2
+            } else {
+                len
+            })
+        };
+        let open = OpenAggregate {
+            prefix,
+            remaining,
+            frames: vec![],
+        };
+        if remaining == Some(0) {
+            return Ok(Some(open.finish()));
+        }
+        self.open.push(open);
+        Ok(None)
+    }
+
+    fn end_streamed(&mut self, buf: &mut BytesMut) -> Result<RespFrame, RespError> {
+        if !buf.starts_with(STREAMED_END.as_bytes()) {
+            if STREAMED_END.as_bytes().starts_with(buf) {
+                return Err(RespError::FrameNotComplete);
+            }
+            return Err(RespError::InvalidFrame(format!("data: {:?}", buf)));
+        }
+        let open = self.open.pop().expect("an open aggregate");
+        if open.prefix == RespMap::PREFIX && !open.frames.len().is_multiple_of(2) {
+            return Err(RespError::InvalidFrame(
+                "streamed map ended after a key".to_string(),
+            ));
+        }
+        buf.advance(STREAMED_END.len());
+        Ok(open.finish())
+    }
+}
+
+impl OpenAggregate {
+    // the elements decoded so far, or the entries of a map
+    fn len(&self) -> usize {
+        match self.prefix {
+            RespMap::PREFIX => self.frames.len().div_ceil(2),
+            _ => self.frames.len(),
+        }
+    }
+
+    fn finish(self) -> RespFrame {
+        match self.prefix {
+            RespMap::PREFIX => {
+                let mut frames = self.frames.into_iter();
+                let mut map = HashMap::new();
+                while let (Some(key), Some(value)) = (frames.next(), frames.next()) {
+                    map.insert(key, value);
+                }
+                RespMap::new(map).into()
+            }
+            RespSet::PREFIX => RespSet::new(self.frames.into_iter().collect::<HashSet<_>>()).into(),
+            RespPush::PREFIX => RespPush::new(self.frames).into(),
+            _ => RespArray::new(self.frames).into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespEncoder, SimpleString};
+    use anyhow::Result;
+
+    // Feeds `data` to a parser a byte at a time, as if each came in its own read.
+    fn parse_bytewise(data: &[u8]) -> Result<(Vec<RespFrame>, BytesMut), RespError> {
+        let mut parser = FrameParser::default();
+        let mut buf = BytesMut::new();
+        let mut frames = vec![];
+        for &b in data {
+            buf.extend_from_slice(&[b]);
+            match parser.parse(&mut buf) {
+                Ok(frame) => frames.push(frame),
+                Err(RespError::FrameNotComplete) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok((frames, buf))
+    }
+
+    #[test]
+    fn test_parser_bytewise() -> Result<()> {
+        let frames: Vec<RespFrame> = vec![
+            RespArray::new(vec![
+                BulkString::from("set").into(),
+                BulkString::from("key").into(),
+                RespArray::new(vec![]).into(),
+                RespArray::new(vec![1.into(), SimpleString::new("two").into()]).into(),
+            ])
+            .into(),
+            RespMap::new(HashMap::from([(
+                SimpleString::new("key").into(),
+                RespSet::new(HashSet::from([RespFrame::Boolean(true)])).into(),
+            )]))
+            .into(),
+            RespPush::new(vec![BulkString::from("message").into()]).into(),
+            BulkString::from("plain").into(),
+        ];
+        let data: Vec<u8> = frames.iter().flat_map(|frame| frame.encode()).collect();
+        let (parsed, buf) = parse_bytewise(&data)?;
+        assert_eq!(parsed, frames);
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_takes_complete_elements() -> Result<()> {
+        let mut parser = FrameParser::default();
+        let mut buf = BytesMut::from("*2\r\n$3\r\nfoo\r\n$3\r\nba");
+        assert_eq!(parser.parse(&mut buf), Err(RespError::FrameNotComplete));
+        assert_eq!(&buf[..], b"$3\r\nba");
+
+        buf.extend_from_slice(b"r\r\n*-1\r\n");
+        let frame = parser.parse(&mut buf)?;
+        assert_eq!(
+            frame,
+            RespArray::new(vec![
+                BulkString::from("foo").into(),
+                BulkString::from("bar").into()
+            ])
+            .into()
+        );
+        assert_eq!(parser.parse(&mut buf)?, RespArray::new(vec![]).into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_streamed() -> Result<()> {
+        let data = b"*?\r\n:1\r\n%?\r\n+key\r\n$?\r\n;2\r\nhe\r\n;1\r\ny\r\n;0\r\n.\r\n.\r\n";
+        let (parsed, _) = parse_bytewise(data)?;
+        let expected: RespFrame = RespArray::new(vec![
+            1.into(),
+            RespMap::new(HashMap::from([(
+                SimpleString::new("key").into(),
+                BulkString::from("hey").into(),
+            )]))
+            .into(),
+        ])
+        .into();
+        assert_eq!(parsed, vec![expected]);
+
+        let res = parse_bytewise(b"%?\r\n+key\r\n.\r\n");
+        assert!(matches!(res, Err(RespError::InvalidFrame(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_errors() {
+        let nested = format!("{}:1\r\n", "*1\r\n".repeat(max_nesting_depth() + 1));
+        let res = FrameParser::default().parse(&mut BytesMut::from(nested.as_str()));
+        assert_eq!(res, Err(RespError::NestingTooDeep));
+
+        let mut parser = FrameParser::default();
+        let mut buf = BytesMut::from("*2\r\n:1\r\n?\r\n");
+        assert!(matches!(
+            parser.parse(&mut buf),
+            Err(RespError::InvalidFrame(_))
+        ));
+        // the frame it failed in is dropped
+        let mut buf = BytesMut::from(":2\r\n");
+        assert_eq!(parser.parse(&mut buf), Ok(2.into()));
+    }
+}
@@ -0,0 +1,172 @@
+// Frames shown the way redis-cli shows replies: strings quoted with what isn't printable
+// escaped, the types of other values in parentheses and the elements of aggregates numbered,
+// those of nested aggregates indented under the number of the element they are.
+
+use crate::RespFrame;
+use std::fmt::{self, Display, Write};
+
+impl Display for RespFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        write_pretty(self, 0, &mut out);
+        // every value ends its line, the last one needn't
+        f.write_str(out.strip_suffix('\n').unwrap_or(&out))
+    }
+}
+
+// Writes `frame` as the rest of the current line, and the lines after it for the elements of an
+// aggregate, each indented by `indent` spaces.
+fn write_pretty(frame: &RespFrame, indent: usize, out: &mut String) {
+    match frame {
+        RespFrame::SimpleString(s) => out.push_str(s),
+        RespFrame::SimpleError(e) => {
+            let _ = write!(out, "(error) {}", e.0);
+        }
+        RespFrame::Integer(n) => {
+            let _ = write!(out, "(integer) {}", n);
+        }
+        RespFrame::BulkString(s) => write_quoted(s, out),
+        RespFrame::Null(_) | RespFrame::NullArray(_) => out.push_str("(nil)"),
+        RespFrame::Boolean(b) => {
+            let _ = write!(out, "({})", b);
+        }
+        RespFrame::Double(d) => {
+            let _ = write!(out, "(double) {}", d);
+        }
+        RespFrame::BigNumber(n) => {
+            let _ = write!(out, "(big number) {}", n);
+        }
+        RespFrame::Array(frames) => {
+            return write_elements("array", ")", frames.iter(), indent, out)
+        }
+        RespFrame::Push(frames) => return write_elements("push", ")", frames.iter(), indent, out),
+        RespFrame::Set(frames) => return write_elements("set", "~", frames.iter(), indent, out),
+        RespFrame::Map(map) => {
+            if map.is_empty() {
+                out.push_str("(empty map)\n");
+                return;
+            }
+            let width = map.len().to_string().len();
+            for (i, (key, value)) in map.iter().enumerate() {
+                if i > 0 {
+                    out.extend(std::iter::repeat_n(' ', indent));
+                }
+                let _ = write!(out, "{:>width$}# ", i + 1);
+                write_pretty(key, indent + width + 2, out);
+                if out.ends_with('\n') {
+                    out.pop();
+                }
+                out.push_str(" => ");
+                write_pretty(value, indent + width + 2, out);
+            }
+            return;
+        }
+    }
+    out.push('\n');
+}
+
+fn write_elements<'a>(
+    kind: &str,
+    separator: &str,
+    frames: impl ExactSizeIterator<Item = &'a RespFrame>,
+    indent: usize,
+    out: &mut String,
+) {
+    if frames.len() == 0 {
+        let _ = writeln!(out, "(empty {})", kind);
+        return;
+    }
+    let width = frames.len().to_string().len();
+    for (i, frame) in frames.enumerate() {
+        if i > 0 {
+            out.extend(std::iter::repeat_n(' ', indent));
+        }
+        let _ = write!(out, "{:>width$}{} ", i + 1, separator);
+        write_pretty(frame, indent + width + 2, out);
+    }
+}
+
+// Writes `data` in double quotes, escaping quotes, backslashes and bytes that aren't printable.
+fn write_quoted(data: &[u8], out: &mut String) {
+    out.push('"');
+    for &b in data {
+        match b {
+            b'\\' => out.push_str("\\\\"),
+            b'"' => out.push_str("\\\""),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            0x07 => out.push_str("\\a"),
+            0x08 => out.push_str("\\b"),
+            b' '..=b'~' => out.push(b as char),
+            _ => {
+                let _ = write!(out, "\\x{:02x}", b);
+            }
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        BulkString, RespArray, RespBigNumber, RespDouble, RespFrame, RespMap, RespNull, RespSet,
+        SimpleError, SimpleString,
+    };
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_display_values() {
+        assert_eq!(RespFrame::from(SimpleString::new("OK")).to_string(), "OK");
+        assert_eq!(
+            RespFrame::from(SimpleError::new("ERR nope")).to_string(),
+            "(error) ERR nope"
+        );
+        assert_eq!(RespFrame::Integer(-1).to_string(), "(integer) -1");
+        assert_eq!(
+            RespFrame::from(BulkString::from("a \"b\"\n\x00\u{e9}")).to_string(),
+            r#""a \"b\"\n\x00\xc3\xa9""#
+        );
+        assert_eq!(RespFrame::Null(RespNull).to_string(), "(nil)");
+        assert_eq!(RespFrame::Boolean(true).to_string(), "(true)");
+        assert_eq!(
+            RespFrame::from(RespDouble::new(1.5)).to_string(),
+            "(double) 1.5"
+        );
+        assert_eq!(
+            RespFrame::from(RespBigNumber::new(1u128 << 64)).to_string(),
+            "(big number) 18446744073709551616"
+        );
+    }
+
+    #[test]
+    fn test_display_aggregates() {
+        let mut frames: Vec<RespFrame> = (1..=9).map(RespFrame::Integer).collect();
+        frames.push(
+            RespArray::new(vec![
+                BulkString::from("a").into(),
+                RespArray::new(vec![]).into(),
+            ])
+            .into(),
+        );
+        assert_eq!(
+            RespFrame::from(RespArray::new(frames)).to_string(),
+            " 1) (integer) 1\n 2) (integer) 2\n 3) (integer) 3\n 4) (integer) 4\n \
+             5) (integer) 5\n 6) (integer) 6\n 7) (integer) 7\n 8) (integer) 8\n \
+             9) (integer) 9\n10) 1) \"a\"\n    2) (empty array)"
+        );
+
+        let map = RespMap::new(HashMap::from([(
+            BulkString::from("key").into(),
+            RespSet::new(HashSet::from([BulkString::from("member").into()])).into(),
+        )]));
+        assert_eq!(
+            RespFrame::from(map).to_string(),
+            "1# \"key\" => 1~ \"member\""
+        );
+        assert_eq!(
+            RespFrame::from(RespMap::new(HashMap::new())).to_string(),
+            "(empty map)"
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use super::{aggregate_layout, write_line, Nesting};
+use crate::{RespDecoder, RespEncoder, RespError, RespFrame};
+use bytes::{Buf, BytesMut};
+use derive_more::{Deref, From};
+
+/// Out-of-band data the server sends on its own, such as invalidation messages, which RESP3
+/// clients tell apart from replies by its type.
+#[derive(Debug, Clone, Deref, PartialEq, Eq, Hash, From)]
+pub struct RespPush(pub(crate) Vec<RespFrame>);
+
+// Push "><number-of-elements>\r\n<element-1>...<element-n>", or streamed as
+// ">?\r\n<element-1>...<element-n>.\r\n", decode to RespPush
+impl RespDecoder for RespPush {
+    const PREFIX: &'static str = ">";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let _nesting = Nesting::enter()?;
+        let layout = aggregate_layout(buf, Self::PREFIX)?;
+        if buf.len() < layout.total {
+            return Err(RespError::FrameNotComplete);
+        }
+
+        buf.advance(layout.header);
+        let mut frames = Vec::with_capacity(layout.len);
+        for _ in 0..layout.len {
+            frames.push(RespFrame::decode(buf)?);
+        }
+        buf.advance(layout.trailer);
+        Ok(RespPush::new(frames))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let _nesting = Nesting::enter()?;
+        Ok(aggregate_layout(buf, Self::PREFIX)?.total)
+    }
+}
+
+// Push format "><number-of-elements>\r\n<element-1>...<element-n>"
+impl RespEncoder for RespPush {
+    fn encode_to(&self, buf: &mut BytesMut) {
+        write_line(buf, ">", self.len());
+        for frame in &self.0 {
+            frame.encode_to(buf);
+        }
+    }
+}
+
+impl RespPush {
+    pub fn new(frames: impl Into<Vec<RespFrame>>) -> Self {
+        RespPush(frames.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespArray};
+    use anyhow::Result;
+
+    #[test]
+    fn test_push_encode() {
+        let push: RespFrame = RespPush::new(vec![
+            BulkString::new("invalidate").into(),
+            RespArray::new(vec![BulkString::new("foo").into()]).into(),
+        ])
+        .into();
+        assert_eq!(
+            push.encode(),
+            b">2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nfoo\r\n"
+        );
+    }
+
+    #[test]
+    fn test_push_decode() -> Result<()> {
+        let mut buf = BytesMut::from(">2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nfoo\r\n");
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            RespPush::new(vec![
+                BulkString::new("invalidate").into(),
+                RespArray::new(vec![BulkString::new("foo").into()]).into(),
+            ])
+            .into()
+        );
+
+        let buf = BytesMut::from(">1\r\n$10\r\ninvalidate\r\n");
+        assert_eq!(RespFrame::expect_length(&buf)?, buf.len());
+        Ok(())
+    }
+}
@@ -1,50 +1,49 @@
-use super::{calc_total_length, parse_length, CAPACITY, CRLF_LEN};
+use super::{aggregate_layout, write_line, Nesting};
 use crate::{RespDecoder, RespEncoder, RespError, RespFrame};
 use bytes::{Buf, BytesMut};
 use derive_more::{Deref, From};
-use std::{collections::HashSet, hash::Hash};
+use std::{
+    collections::HashSet,
+    hash::{DefaultHasher, Hash, Hasher},
+};
 
 #[derive(Debug, Clone, Deref, PartialEq, Eq, From)]
 pub struct RespSet(pub(crate) HashSet<RespFrame>);
 
-// Set "~<number-of-elements>\r\n<element-1>...<element-n>" decode to RespSet
+// Set "~<number-of-elements>\r\n<element-1>...<element-n>", or streamed as
+// "~?\r\n<element-1>...<element-n>.\r\n", decode to RespSet
 impl RespDecoder for RespSet {
     const PREFIX: &'static str = "~";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-
-        let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
-        if buf.len() < total_len {
+        let _nesting = Nesting::enter()?;
+        let layout = aggregate_layout(buf, Self::PREFIX)?;
+        if buf.len() < layout.total {
             return Err(RespError::FrameNotComplete);
         }
 
-        buf.advance(end + CRLF_LEN);
-        let mut set = HashSet::with_capacity(len);
-        if len == 0 {
-            return Ok(RespSet::new(set));
-        }
-        for _ in 0..len {
+        buf.advance(layout.header);
+        let mut set = HashSet::with_capacity(layout.len);
+        for _ in 0..layout.len {
             let frame = RespFrame::decode(buf)?;
             set.insert(frame);
         }
+        buf.advance(layout.trailer);
         Ok(RespSet::new(set))
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        calc_total_length(buf, end, len, Self::PREFIX)
+        let _nesting = Nesting::enter()?;
+        Ok(aggregate_layout(buf, Self::PREFIX)?.total)
     }
 }
 
 // Set format "~<number-of-elements>\r\n<element-1>...<element-n>"
 impl RespEncoder for RespSet {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(CAPACITY);
-        buf.extend(format!("~{}\r\n", self.len()).into_bytes());
-        for frame in self.0 {
-            buf.extend(frame.encode());
+    fn encode_to(&self, buf: &mut BytesMut) {
+        write_line(buf, "~", self.len());
+        for frame in &self.0 {
+            frame.encode_to(buf);
         }
-        buf
     }
 }
 
@@ -54,9 +53,16 @@ impl RespSet {
     }
 }
 
+// Equal sets may iterate their elements in different orders, so the hashes of the elements are
+// combined in a way that doesn't depend on it.
 impl Hash for RespSet {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.iter().for_each(|frame| frame.hash(state));
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        state.write_u64(self.iter().fold(0, |sum, frame| {
+            let mut hasher = DefaultHasher::new();
+            frame.hash(&mut hasher);
+            sum.wrapping_add(hasher.finish())
+        }));
     }
 }
 
@@ -1,4 +1,4 @@
-use super::{extract_simple_resp, CRLF_LEN};
+use super::{extract_simple_resp, write_line, CRLF_LEN};
 use crate::{RespDecoder, RespEncoder, RespError};
 use bytes::BytesMut;
 use derive_more::{Deref, From};
@@ -25,8 +25,8 @@ impl RespDecoder for SimpleError {
 
 // Simple error format "-<str>\r\n"
 impl RespEncoder for SimpleError {
-    fn encode(self) -> Vec<u8> {
-        format!("-{}\r\n", self.0).into_bytes()
+    fn encode_to(&self, buf: &mut BytesMut) {
+        write_line(buf, "-", &self.0);
     }
 }
 
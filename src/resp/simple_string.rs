@@ -1,4 +1,4 @@
-use super::{extract_simple_resp, CRLF_LEN};
+use super::{extract_simple_resp, write_line, CRLF_LEN};
 use crate::{RespDecoder, RespEncoder, RespError};
 use bytes::BytesMut;
 use derive_more::{Deref, From};
@@ -25,8 +25,8 @@ impl RespDecoder for SimpleString {
 
 // Simple string format "+<str>\r\n"
 impl RespEncoder for SimpleString {
-    fn encode(self) -> Vec<u8> {
-        format!("+{}\r\n", self.0).into_bytes()
+    fn encode_to(&self, buf: &mut BytesMut) {
+        write_line(buf, "+", &self.0);
     }
 }
 
@@ -39,6 +39,7 @@ impl SimpleString {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::decode_with;
     use anyhow::Result;
 
     #[test]
@@ -69,6 +70,26 @@ mod tests {
         assert!(res.is_err());
     }
 
+    #[test]
+    fn test_simple_string_decode_strict() {
+        let mut buf = BytesMut::from("+hel\rlo\r\n");
+        let res = decode_with(true, || SimpleString::decode(&mut buf));
+        assert_eq!(res, Err(RespError::CarriageReturnInLine));
+        let res = SimpleString::decode(&mut buf);
+        assert_eq!(res, Ok(SimpleString::new("hel\rlo")));
+
+        let mut buf = BytesMut::from("+hello\n");
+        let res = decode_with(true, || SimpleString::decode(&mut buf));
+        assert_eq!(res, Err(RespError::BareLineFeed));
+        let mut buf = BytesMut::from("+hel\nlo\r\n");
+        let res = decode_with(true, || SimpleString::decode(&mut buf));
+        assert_eq!(res, Err(RespError::BareLineFeed));
+
+        let mut buf = BytesMut::from("+hello\r");
+        let res = decode_with(true, || SimpleString::decode(&mut buf));
+        assert_eq!(res, Err(RespError::FrameNotComplete));
+    }
+
     #[test]
     fn test_simple_string_expect_length() -> Result<()> {
         let buf = b"+hello\r\n";
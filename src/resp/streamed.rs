@@ -0,0 +1,131 @@
+// Writing a reply whose length isn't known up front, as a RESP3 streamed string or aggregate:
+// its header, then its chunks or elements as they are produced, then what ends it.
+
+use super::{bulk_string::CHUNK_PREFIX, STREAMED, STREAMED_END};
+use crate::{RespEncoder, RespFrame};
+use bytes::{BufMut, BytesMut};
+
+/// A streamed string being written: "$?\r\n", then chunks of data, then an empty chunk.
+#[derive(Debug)]
+pub struct StreamedString(());
+
+impl StreamedString {
+    /// Writes the header of the string to `buf`.
+    pub fn start(buf: &mut BytesMut) -> Self {
+        buf.put_slice(b"$");
+        buf.put_slice(STREAMED.as_bytes());
+        StreamedString(())
+    }
+
+    /// Writes `data` as the next chunk. Nothing is written for empty data, an empty chunk
+    /// ending the string.
+    pub fn chunk(&mut self, data: &[u8], buf: &mut BytesMut) {
+        if data.is_empty() {
+            return;
+        }
+        buf.put_slice(format!("{}{}\r\n", CHUNK_PREFIX, data.len()).as_bytes());
+        buf.put_slice(data);
+        buf.put_slice(b"\r\n");
+    }
+
+    pub fn finish(self, buf: &mut BytesMut) {
+        buf.put_slice(format!("{}0\r\n", CHUNK_PREFIX).as_bytes());
+    }
+}
+
+/// A streamed aggregate being written: "*?\r\n", "~?\r\n", "%?\r\n" or ">?\r\n", then its
+/// elements, the key and the value of each entry in turn for a map, then ".\r\n".
+#[derive(Debug)]
+pub struct StreamedAggregate(());
+
+impl StreamedAggregate {
+    pub fn array(buf: &mut BytesMut) -> Self {
+        Self::start("*", buf)
+    }
+
+    pub fn set(buf: &mut BytesMut) -> Self {
+        Self::start("~", buf)
+    }
+
+    pub fn map(buf: &mut BytesMut) -> Self {
+        Self::start("%", buf)
+    }
+
+    pub fn push(buf: &mut BytesMut) -> Self {
+        Self::start(">", buf)
+    }
+
+    fn start(prefix: &str, buf: &mut BytesMut) -> Self {
+        buf.put_slice(prefix.as_bytes());
+        buf.put_slice(STREAMED.as_bytes());
+        StreamedAggregate(())
+    }
+
+    pub fn element(&mut self, frame: &RespFrame, buf: &mut BytesMut) {
+        frame.encode_to(buf);
+    }
+
+    pub fn finish(self, buf: &mut BytesMut) {
+        buf.put_slice(STREAMED_END.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespArray, RespDecoder, RespError, RespMap, SimpleString};
+    use anyhow::Result;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_streamed_string() -> Result<()> {
+        let mut buf = BytesMut::new();
+        let mut string = StreamedString::start(&mut buf);
+        string.chunk(b"Hell", &mut buf);
+        string.chunk(b"", &mut buf);
+        string.chunk(b"o world", &mut buf);
+        string.finish(&mut buf);
+        assert_eq!(&buf[..], b"$?\r\n;4\r\nHell\r\n;7\r\no world\r\n;0\r\n");
+
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(frame, BulkString::from("Hello world").into());
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_streamed_aggregate() -> Result<()> {
+        let mut buf = BytesMut::new();
+        let mut array = StreamedAggregate::array(&mut buf);
+        array.element(&1.into(), &mut buf);
+        let mut nested = StreamedAggregate::map(&mut buf);
+        nested.element(&SimpleString::new("key").into(), &mut buf);
+        nested.element(&2.into(), &mut buf);
+        nested.finish(&mut buf);
+        array.finish(&mut buf);
+        assert_eq!(&buf[..], b"*?\r\n:1\r\n%?\r\n+key\r\n:2\r\n.\r\n.\r\n");
+
+        let expected: RespFrame = RespArray::new(vec![
+            1.into(),
+            RespMap::new(HashMap::from([(SimpleString::new("key").into(), 2.into())])).into(),
+        ])
+        .into();
+        assert_eq!(RespFrame::expect_length(&buf)?, buf.len());
+        assert_eq!(RespFrame::decode(&mut buf)?, expected);
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_streamed_not_complete() {
+        for frame in [
+            "$?\r\n;4\r\nHell\r\n",
+            "$?\r\n;4\r\nHe",
+            "*?\r\n:1\r\n",
+            "*?\r\n:1\r\n.",
+        ] {
+            let res = RespFrame::decode(&mut BytesMut::from(frame));
+            assert_eq!(res, Err(RespError::FrameNotComplete), "{:?}", frame);
+        }
+    }
+}
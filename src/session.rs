@@ -0,0 +1,174 @@
+// The state of a client connection. The connection handler keeps one for as long as the client
+// stays connected, and the commands that act on the connection rather than on the dataset
+// read and change it.
+
+use crate::{backend::Subscriber, cmd::Transaction, cores::Cores, Backend};
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+/// Whether the server replies to the commands of a connection, as CLIENT REPLY switches it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReplyMode {
+    #[default]
+    On,
+    Off,
+    /// no reply to the command after CLIENT REPLY SKIP
+    SkipNext,
+    /// no reply to the command running
+    Skip,
+}
+
+/// A client connection: the database it selected, its subscriber, which holds its id, name,
+/// protocol version, authentication and subscriptions, the commands it queued since MULTI and
+/// whether it gets replies.
+#[derive(Debug)]
+pub struct Session {
+    // on the database the connection selected, which SELECT switches
+    pub(crate) backend: Backend,
+    // messages published to its subscriptions are written out between replies
+    pub(crate) subscriber: Subscriber,
+    // the commands queued since MULTI
+    pub(crate) transaction: Transaction,
+    pub(crate) reply: ReplyMode,
+    // the port a replica announced with REPLCONF
+    pub(crate) replica_port: Option<u16>,
+    pub(crate) peer: SocketAddr,
+    // when the connection last sent a command, which the idle timeout counts from
+    pub(crate) last_active: Instant,
+    // the threads owning the keys in thread-per-core mode
+    pub(crate) cores: Option<Cores>,
+}
+
+impl Session {
+    /// A session for a client connecting from `peer`, on the database 0 of `backend`.
+    pub fn new(backend: Backend, peer: SocketAddr) -> Self {
+        let subscriber = backend.subscriber();
+        backend.update_client(subscriber.id(), |info| info.addr = Some(peer));
+        Self {
+            backend,
+            subscriber,
+            transaction: Transaction::default(),
+            reply: ReplyMode::default(),
+            replica_port: None,
+            peer,
+            last_active: Instant::now(),
+            cores: None,
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.subscriber.id()
+    }
+
+    /// The RESP version negotiated with HELLO.
+    pub fn protocol(&self) -> u8 {
+        self.subscriber.protocol()
+    }
+
+    /// Whether the connection may run commands, having authenticated if it had to.
+    pub fn may_run(&self) -> bool {
+        self.subscriber.is_authenticated() || !self.backend.requires_auth()
+    }
+
+    /// Brings the connection back to the state it connected in, as RESET does: no transaction,
+    /// the state of its subscriber reset, on the database 0 and replying.
+    pub fn reset(&mut self) {
+        self.transaction.clear();
+        self.subscriber.reset();
+        if let Some(db) = self.backend.select(0) {
+            self.backend = db;
+        }
+        self.reply = ReplyMode::On;
+    }
+
+    /// Called before running a command, so a skip requested by the command before applies to
+    /// this one.
+    pub fn start_command(&mut self) {
+        self.last_active = Instant::now();
+        if self.reply == ReplyMode::SkipNext {
+            self.reply = ReplyMode::Skip;
+        }
+    }
+
+    /// Called after running a command, returning whether to send its reply. Records the state
+    /// CLIENT LIST reports.
+    pub fn end_command(&mut self) -> bool {
+        self.subscriber
+            .record_state(self.backend.db_index(), self.transaction.queued());
+        let replies = self.reply == ReplyMode::On;
+        if self.reply == ReplyMode::Skip {
+            self.reply = ReplyMode::On;
+        }
+        replies
+    }
+
+    /// Whether the connection was idle longer than the timeout. The subscribed connections
+    /// wait for messages rather than commands, so they are never idle.
+    pub fn is_idle(&self) -> bool {
+        match self.backend.client_timeout() {
+            Some(timeout) if !self.subscriber.is_subscribed() => {
+                self.last_active.elapsed() > timeout
+            }
+            _ => false,
+        }
+    }
+
+    /// When to check next whether the connection is idle. At least once a second, so a timeout
+    /// set in the meantime applies.
+    pub fn idle_check(&self) -> Instant {
+        let check = Instant::now() + Duration::from_secs(1);
+        match self.backend.client_timeout() {
+            Some(timeout) => check.min(self.last_active + timeout + Duration::from_millis(1)),
+            None => check,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_reply_mode() {
+        let backend = Backend::new();
+        let mut session = Session::new(backend, "127.0.0.1:6379".parse().unwrap());
+        session.start_command();
+        assert!(session.end_command());
+
+        // CLIENT REPLY SKIP gets no reply, nor the command after it
+        session.start_command();
+        session.reply = ReplyMode::SkipNext;
+        assert!(!session.end_command());
+        session.start_command();
+        assert!(!session.end_command());
+        session.start_command();
+        assert!(session.end_command());
+
+        session.reply = ReplyMode::Off;
+        session.start_command();
+        assert!(!session.end_command());
+        session.reset();
+        assert_eq!(session.reply, ReplyMode::On);
+    }
+
+    #[test]
+    fn test_session_idle() {
+        let backend = Backend::new();
+        let mut session = Session::new(backend.clone(), "127.0.0.1:6379".parse().unwrap());
+        session.last_active = Instant::now() - Duration::from_secs(10);
+        assert!(!session.is_idle());
+        assert!(session.idle_check() > Instant::now());
+
+        backend.set_client_timeout(5);
+        assert!(session.is_idle());
+        assert!(session.idle_check() <= Instant::now());
+        session.subscriber.subscribe("news");
+        assert!(!session.is_idle());
+        session.subscriber.unsubscribe("news");
+
+        session.start_command();
+        assert!(!session.is_idle());
+    }
+}
@@ -0,0 +1,186 @@
+// TLS for the link of a replica with its master, set up from the `tls-replication-*` settings
+// whether or not client connections use TLS.
+
+use crate::backend::ReplicationTls;
+use anyhow::{anyhow, Context, Result};
+use std::{fs::File, io::BufReader, path::Path, sync::Arc};
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    client::TlsStream,
+    rustls::{
+        crypto::ring,
+        pki_types::{CertificateDer, PrivateKeyDer, ServerName},
+        ClientConfig, RootCertStore,
+    },
+    TlsConnector,
+};
+
+/// Runs the TLS handshake over `stream`, checking the master presents a certificate for
+/// `host` signed by the configured CA.
+pub(crate) async fn connect(
+    stream: TcpStream,
+    host: &str,
+    settings: &ReplicationTls,
+) -> Result<TlsStream<TcpStream>> {
+    let config = client_config(settings)?;
+    let server_name = ServerName::try_from(host.to_string())?;
+    let stream = TlsConnector::from(Arc::new(config))
+        .connect(server_name, stream)
+        .await?;
+    Ok(stream)
+}
+
+// Files are read on every connection, so replaced certificates are picked up on reconnect.
+fn client_config(settings: &ReplicationTls) -> Result<ClientConfig> {
+    let ca_cert_file = settings
+        .ca_cert_file
+        .as_deref()
+        .ok_or_else(|| anyhow!("tls-replication-ca-cert-file is not set"))?;
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(ca_cert_file)? {
+        roots.add(cert)?;
+    }
+    let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots);
+    let config = match (&settings.cert_file, &settings.key_file) {
+        (Some(cert_file), Some(key_file)) => {
+            builder.with_client_auth_cert(load_certs(cert_file)?, load_key(key_file)?)?
+        }
+        (None, None) => builder.with_no_client_auth(),
+        _ => {
+            return Err(anyhow!(
+                "tls-replication-cert-file and tls-replication-key-file must be set together"
+            ))
+        }
+    };
+    Ok(config)
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let mut reader = open(path)?;
+    let certs = rustls_pemfile::certs(&mut reader)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Bad certificate file {}", path.display()))?;
+    if certs.is_empty() {
+        return Err(anyhow!("No certificate in {}", path.display()));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    rustls_pemfile::private_key(&mut open(path)?)
+        .with_context(|| format!("Bad key file {}", path.display()))?
+        .ok_or_else(|| anyhow!("No private key in {}", path.display()))
+}
+
+fn open(path: &Path) -> Result<BufReader<File>> {
+    let file = File::open(path).with_context(|| format!("Can't open {}", path.display()))?;
+    Ok(BufReader::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::MasterAddr, network, replication, Backend, BulkString};
+    use std::{path::PathBuf, time::Duration};
+    use tokio::{io, net::TcpListener, time};
+    use tokio_rustls::{
+        rustls::{server::WebPkiClientVerifier, ServerConfig},
+        TlsAcceptor,
+    };
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/tls")
+            .join(name)
+    }
+
+    // Terminates TLS in front of `port`, requiring a client certificate signed by the test CA.
+    async fn tls_proxy(port: u16) -> Result<u16> {
+        let provider = Arc::new(ring::default_provider());
+        let mut roots = RootCertStore::empty();
+        roots.add(load_certs(&fixture("ca.crt"))?.remove(0))?;
+        let verifier =
+            WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                .build()?;
+        let config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(
+                load_certs(&fixture("server.crt"))?,
+                load_key(&fixture("server.key"))?,
+            )?;
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let proxy_port = listener.local_addr()?.port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let mut client = acceptor.accept(stream).await?;
+                    let mut server = TcpStream::connect(("127.0.0.1", port)).await?;
+                    io::copy_bidirectional(&mut client, &mut server).await?;
+                    anyhow::Ok(())
+                });
+            }
+        });
+        Ok(proxy_port)
+    }
+
+    #[tokio::test]
+    async fn test_replicate_over_tls() -> Result<()> {
+        let master = Backend::new();
+        master.set("key".into(), BulkString::from("1").into());
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let server = master.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(network::stream_handler(stream, server.clone()));
+            }
+        });
+        let proxy_port = tls_proxy(port).await?;
+
+        let replica = Backend::new();
+        replica.set_replication_tls(ReplicationTls {
+            enabled: true,
+            ca_cert_file: Some(fixture("ca.crt")),
+            cert_file: Some(fixture("server.crt")),
+            key_file: Some(fixture("server.key")),
+        });
+        replica.set_master(Some(MasterAddr {
+            host: "localhost".to_string(),
+            port: proxy_port,
+        }));
+        tokio::spawn(replication::replicate(replica.clone()));
+        for _ in 0..500 {
+            if replica.master_link_up() {
+                break;
+            }
+            time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(replica.master_link_up());
+        assert_eq!(
+            replica.get(b"key").unwrap(),
+            Some(BulkString::from("1").into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_config() {
+        let mut settings = ReplicationTls {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(client_config(&settings).is_err());
+        settings.ca_cert_file = Some(fixture("ca.crt"));
+        assert!(client_config(&settings).is_ok());
+        // a certificate without its key
+        settings.cert_file = Some(fixture("server.crt"));
+        assert!(client_config(&settings).is_err());
+        settings.key_file = Some(fixture("server.key"));
+        assert!(client_config(&settings).is_ok());
+    }
+}